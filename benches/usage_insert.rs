@@ -0,0 +1,186 @@
+//! 使用记录写入基准：以固定速率（默认 500 rps）模拟请求完成后写入使用记录，
+//! 对比逐条 INSERT 与批量写入器在 SQLite WAL 模式下的写放大。
+//!
+//! 运行：cargo bench --bench usage_insert
+//! 可通过 BENCH_RPS、BENCH_SECONDS、USAGE_BATCH_SIZE、USAGE_FLUSH_INTERVAL_MS 调整参数。
+
+use api_manager::config::{DatabaseConfig, UsageRecorderConfig};
+use api_manager::database::run_migrations;
+use api_manager::models::{ApiCallStatus, ApiUsage};
+use api_manager::services::{DbMetrics, KeyUsageMetrics, UsageRecorder};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tokio::time::{interval, Duration, Instant};
+
+// 每条使用记录的有效数据量（各列的大致字节数），用于计算写放大
+const ROW_BYTES: u64 = 160;
+
+struct BenchResult {
+    mode: &'static str,
+    rows: i64,
+    elapsed: Duration,
+    wal_frames: u64,
+    wal_bytes: u64,
+    p99_wait: Duration,
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn database_config(path: &Path) -> DatabaseConfig {
+    DatabaseConfig {
+        url: format!("sqlite://{}", path.display()),
+        path: path.to_path_buf(),
+        enable_wal: true,
+        enable_foreign_keys: false,
+        max_connections: 10,
+        slow_query_threshold_ms: 1000,
+        busy_timeout_ms: 5000,
+        busy_retry_attempts: 3,
+    }
+}
+
+// 关闭自动检查点，WAL文件大小即为运行期间写入的全部页帧；基准不创建提供商，关闭外键约束
+async fn open_database(path: &Path) -> SqlitePool {
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(false)
+        .pragma("wal_autocheckpoint", "0")
+        .busy_timeout(Duration::from_secs(5));
+    let pool = SqlitePoolOptions::new()
+        .max_connections(10)
+        .connect_with(options)
+        .await
+        .expect("打开基准数据库失败");
+    run_migrations(&pool).await.expect("运行迁移失败");
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(&pool)
+        .await
+        .expect("截断WAL失败");
+    pool
+}
+
+fn usage(i: u64) -> ApiUsage {
+    let mut usage = ApiUsage::new(
+        format!("sk-bench-{}", i % 8),
+        "gpt-4o".to_string(),
+        120,
+        480,
+        ApiCallStatus::Success,
+        Some("127.0.0.1".to_string()),
+        None,
+    );
+    usage.cost = Some(0.0012);
+    usage
+}
+
+async fn run(mode: &'static str, path: &Path, rps: u64, seconds: u64) -> BenchResult {
+    let pool = open_database(path).await;
+    let metrics = Arc::new(DbMetrics::new(&database_config(path)));
+    let recorder = (mode == "batched").then(|| {
+        Arc::new(UsageRecorder::new(pool.clone(), metrics.clone(), Arc::new(KeyUsageMetrics::new(0)), UsageRecorderConfig {
+            batch_size: env_or("USAGE_BATCH_SIZE", 64),
+            flush_interval_ms: env_or("USAGE_FLUSH_INTERVAL_MS", 100),
+            queue_capacity: env_or("USAGE_QUEUE_CAPACITY", 10_000),
+            overflow: "block".to_string(),
+        }))
+    });
+
+    let total = rps * seconds;
+    let mut ticker = interval(Duration::from_micros(1_000_000 / rps));
+    let mut tasks = JoinSet::new();
+    let started = Instant::now();
+    for i in 0..total {
+        ticker.tick().await;
+        let pool = pool.clone();
+        let metrics = metrics.clone();
+        let recorder = recorder.clone();
+        tasks.spawn(async move {
+            let usage = usage(i);
+            let start = Instant::now();
+            match recorder {
+                Some(recorder) => recorder.record(usage).await,
+                None => metrics
+                    .run("api_usage.insert", || usage.insert(&pool))
+                    .await
+                    .expect("写入使用记录失败"),
+            }
+            start.elapsed()
+        });
+    }
+
+    let mut waits = Vec::with_capacity(total as usize);
+    while let Some(wait) = tasks.join_next().await {
+        waits.push(wait.expect("写入任务异常"));
+    }
+    if let Some(recorder) = &recorder {
+        recorder.flush().await;
+    }
+    let elapsed = started.elapsed();
+    waits.sort();
+
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM api_usage")
+        .fetch_one(&pool)
+        .await
+        .expect("统计使用记录失败");
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+        .fetch_one(&pool)
+        .await
+        .expect("读取页大小失败");
+    let wal_bytes = std::fs::metadata(format!("{}-wal", path.display()))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    pool.close().await;
+
+    BenchResult {
+        mode,
+        rows,
+        elapsed,
+        // WAL文件头32字节，每帧24字节帧头加一页
+        wal_frames: wal_bytes.saturating_sub(32) / (page_size as u64 + 24),
+        wal_bytes,
+        p99_wait: waits[(waits.len() * 99 / 100).min(waits.len() - 1)],
+    }
+}
+
+fn main() {
+    let rps: u64 = env_or("BENCH_RPS", 500);
+    let seconds: u64 = env_or("BENCH_SECONDS", 5);
+    let dir: PathBuf = env::temp_dir().join(format!("api-manager-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("创建临时目录失败");
+
+    let runtime = tokio::runtime::Runtime::new().expect("创建运行时失败");
+    let results: Vec<BenchResult> = ["per_request", "batched"]
+        .into_iter()
+        .map(|mode| runtime.block_on(run(mode, &dir.join(format!("{}.sqlite", mode)), rps, seconds)))
+        .collect();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    println!("使用记录写入基准: {} rps x {}s", rps, seconds);
+    println!(
+        "{:<12} {:>8} {:>10} {:>10} {:>12} {:>10} {:>12}",
+        "mode", "rows", "elapsed", "wal_frames", "wal_bytes", "write_amp", "p99_wait"
+    );
+    for r in &results {
+        println!(
+            "{:<12} {:>8} {:>9.2}s {:>10} {:>12} {:>9.1}x {:>10.2}ms",
+            r.mode,
+            r.rows,
+            r.elapsed.as_secs_f64(),
+            r.wal_frames,
+            r.wal_bytes,
+            r.wal_bytes as f64 / (r.rows.max(1) as u64 * ROW_BYTES) as f64,
+            r.p99_wait.as_secs_f64() * 1000.0,
+        );
+    }
+}
@@ -0,0 +1,8 @@
+-- 为API提供商添加余额查询URL字段
+-- 设置后 BalanceChecker 将直接使用该URL，不再根据 base_url 推导
+ALTER TABLE api_providers ADD COLUMN balance_check_url TEXT;
+
+-- 为已有的 SiliconFlow 提供商回填余额查询URL，保持原有行为
+UPDATE api_providers
+SET balance_check_url = 'https://api.siliconflow.cn/v1/user/info'
+WHERE base_url LIKE '%siliconflow%' AND balance_check_url IS NULL;
@@ -0,0 +1,2 @@
+-- 为API提供商添加流式输出节流配置（目标 tokens/秒，为空表示不节流）
+ALTER TABLE api_providers ADD COLUMN stream_pacing_tps REAL;
@@ -0,0 +1,12 @@
+-- 为API提供商添加隔离（Quarantined）状态相关字段
+-- 连续鉴权失败次数：余额检查返回401时递增，成功时清零
+ALTER TABLE api_providers ADD COLUMN consecutive_auth_failures INTEGER NOT NULL DEFAULT 0;
+-- 进入隔离状态的时间
+ALTER TABLE api_providers ADD COLUMN quarantined_at TEXT;
+
+-- 旧版本将401的提供商余额置为NULL，这里将其转为隔离状态
+UPDATE api_providers
+SET status = 'Quarantined',
+    consecutive_auth_failures = 1,
+    quarantined_at = datetime('now')
+WHERE balance IS NULL AND support_balance_check = 1;
@@ -0,0 +1,2 @@
+-- 为API提供商添加是否接受gzip压缩请求体的标记
+ALTER TABLE api_providers ADD COLUMN supports_gzip_request INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- 记录搜索增强模型每次请求使用的引用来源数量（用于按来源计费）
+ALTER TABLE api_usage ADD COLUMN num_sources INTEGER NOT NULL DEFAULT 0;
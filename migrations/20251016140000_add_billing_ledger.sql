@@ -0,0 +1,27 @@
+-- 创建只追加的计费账本表
+-- 借记（请求成本）金额为负，贷记（充值）和调整金额按实际正负记录，账户余额即金额之和
+CREATE TABLE IF NOT EXISTS billing_ledger (
+    id TEXT PRIMARY KEY,
+    account TEXT NOT NULL,                -- 计费账户（客户端虚拟密钥或组织标识）
+    entry_type TEXT NOT NULL CHECK (entry_type IN ('Debit', 'Credit', 'Adjustment')),
+    amount REAL NOT NULL,                 -- 带符号的金额
+    currency TEXT NOT NULL DEFAULT 'USD', -- 货币单位
+    description TEXT,                     -- 说明
+    usage_id TEXT,                        -- 借记关联的 api_usage 记录
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_billing_ledger_account ON billing_ledger (account, created_at);
+
+-- 账本不可修改：禁止更新和删除
+CREATE TRIGGER IF NOT EXISTS billing_ledger_no_update
+BEFORE UPDATE ON billing_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'billing_ledger is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS billing_ledger_no_delete
+BEFORE DELETE ON billing_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'billing_ledger is append-only');
+END;
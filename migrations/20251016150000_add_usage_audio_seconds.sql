@@ -0,0 +1,2 @@
+-- 记录音频转写请求的音频时长（秒），用于按音频时长统计用量
+ALTER TABLE api_usage ADD COLUMN audio_seconds REAL NOT NULL DEFAULT 0;
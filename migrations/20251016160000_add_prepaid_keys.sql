@@ -0,0 +1,5 @@
+-- 预付费虚拟密钥：登记后的密钥按账本余额限制请求，余额用完即拒绝
+CREATE TABLE IF NOT EXISTS prepaid_keys (
+    key TEXT PRIMARY KEY,       -- 客户端虚拟密钥（与 billing_ledger.account 对应）
+    created_at TEXT NOT NULL
+);
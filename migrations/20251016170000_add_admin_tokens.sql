@@ -0,0 +1,9 @@
+-- 管理API的作用域令牌（只保存令牌的SHA-256哈希）
+CREATE TABLE IF NOT EXISTS admin_tokens (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,            -- 令牌用途说明，如 grafana-dashboard
+    token_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,          -- 逗号分隔的作用域，如 providers:read,usage:read
+    created_at TEXT NOT NULL,
+    revoked_at TEXT                -- 吊销时间，非空表示已失效
+);
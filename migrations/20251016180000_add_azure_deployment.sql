@@ -0,0 +1,3 @@
+-- 为Azure OpenAI提供商添加部署名称和API版本
+ALTER TABLE api_providers ADD COLUMN deployment TEXT;
+ALTER TABLE api_providers ADD COLUMN api_version TEXT;
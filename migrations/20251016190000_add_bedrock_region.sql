@@ -0,0 +1,2 @@
+-- 为AWS Bedrock提供商添加区域（用于SigV4签名）
+ALTER TABLE api_providers ADD COLUMN aws_region TEXT;
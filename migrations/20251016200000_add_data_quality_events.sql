@@ -0,0 +1,13 @@
+-- 提供商数据质量事件：上游返回无法严格解析的JSON时记录
+CREATE TABLE IF NOT EXISTS provider_data_quality_events (
+    id TEXT PRIMARY KEY,
+    provider_api_key TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    recovered BOOLEAN NOT NULL,
+    repairs TEXT,
+    detail TEXT,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_data_quality_events_provider
+    ON provider_data_quality_events (provider_api_key, created_at);
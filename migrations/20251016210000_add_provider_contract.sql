@@ -0,0 +1,4 @@
+-- 提供商合同信息：购买额度、到期时间和供应商账号邮箱（部分代理商密钥到期即失效，与余额无关）
+ALTER TABLE api_providers ADD COLUMN purchased_quota REAL;
+ALTER TABLE api_providers ADD COLUMN expires_at DATETIME;
+ALTER TABLE api_providers ADD COLUMN vendor_account_email TEXT;
@@ -0,0 +1,5 @@
+-- 自带上游密钥（BYOK）请求的密钥标识（密钥摘要，不保存明文）
+-- provider_api_key 仍记录所用的提供商，但此类请求不消耗池中密钥的余额
+ALTER TABLE api_usage ADD COLUMN own_key_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_api_usage_own_key ON api_usage (own_key_id);
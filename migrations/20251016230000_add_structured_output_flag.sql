@@ -0,0 +1,2 @@
+-- 标记提供商是否支持结构化输出（response_format: json_object / json_schema），默认支持
+ALTER TABLE api_providers ADD COLUMN supports_structured_output BOOLEAN NOT NULL DEFAULT 1;
@@ -0,0 +1,4 @@
+-- 记录每次请求按定价计算的成本，未配置定价时为空（可由对账任务按历史定价回填）
+ALTER TABLE api_usage ADD COLUMN cost REAL;
+
+CREATE INDEX IF NOT EXISTS idx_api_usage_request_id ON api_usage (request_id);
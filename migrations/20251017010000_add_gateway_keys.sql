@@ -0,0 +1,11 @@
+-- 推理接口的网关密钥（只保存密钥的SHA-256哈希）
+CREATE TABLE IF NOT EXISTS gateway_keys (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,            -- 密钥用途说明，如 chat-frontend
+    key_hash TEXT NOT NULL UNIQUE,
+    key_prefix TEXT NOT NULL,      -- 密钥明文前缀，便于识别
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    revoked_at TEXT                -- 吊销时间，非空表示已失效
+);
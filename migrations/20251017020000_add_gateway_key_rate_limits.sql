@@ -0,0 +1,3 @@
+-- 网关密钥的限流配置（为空时使用全局默认值，0表示不限制）
+ALTER TABLE gateway_keys ADD COLUMN requests_per_minute INTEGER;
+ALTER TABLE gateway_keys ADD COLUMN tokens_per_minute INTEGER;
@@ -0,0 +1,12 @@
+-- 网关密钥的月度预算（为空时使用全局默认值，0表示不限制）
+ALTER TABLE gateway_keys ADD COLUMN monthly_budget REAL;
+
+-- 网关密钥按月累计的花费（按模型定价计算）
+CREATE TABLE IF NOT EXISTS gateway_key_spend (
+    key_id TEXT NOT NULL,
+    period TEXT NOT NULL,          -- 计费月份（UTC），如 2025-10
+    spent REAL NOT NULL DEFAULT 0,
+    reset_at TEXT,                 -- 最近一次手动重置时间
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (key_id, period)
+);
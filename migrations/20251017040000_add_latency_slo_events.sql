@@ -0,0 +1,12 @@
+-- 延迟SLO事件：网关整体p99延迟超出上限开始降载、恢复后解除降载时记录
+CREATE TABLE IF NOT EXISTS latency_slo_events (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    p99_ms INTEGER,
+    threshold_ms INTEGER NOT NULL,
+    sample_count INTEGER NOT NULL,
+    created_at DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_latency_slo_events_created_at
+    ON latency_slo_events (created_at);
@@ -0,0 +1,17 @@
+-- 管理员用户：每个运维人员使用自己的账号登录，登录后获得归属该用户的管理令牌
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    email TEXT,
+    password_hash TEXT NOT NULL,   -- argon2 哈希（PHC 字符串）
+    scopes TEXT NOT NULL,          -- 逗号分隔的作用域，登录签发的令牌继承这些作用域
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    last_login_at TEXT
+);
+
+-- 管理令牌的所属用户（为空表示通过 /v1/admin/tokens 直接创建）
+ALTER TABLE admin_tokens ADD COLUMN user_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_admin_tokens_user ON admin_tokens (user_id);
@@ -0,0 +1,2 @@
+-- 网关密钥是否在响应的 usage 中附带网关计算的成本、上游耗时和当月累计花费
+ALTER TABLE gateway_keys ADD COLUMN usage_echo INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- 用户角色（Admin/User/ReadOnly/ApiConsumer），决定用户可以持有的作用域上限
+-- 已有用户的作用域是单独指定的，迁移为 Admin 以保持其权限不变
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'Admin';
@@ -0,0 +1,5 @@
+-- 网关密钥过期时间（为空表示永不过期），过期的密钥被拒绝
+ALTER TABLE gateway_keys ADD COLUMN expires_at TEXT;
+
+-- 轮换后替代该密钥的新密钥ID，旧密钥在宽限期结束后过期
+ALTER TABLE gateway_keys ADD COLUMN replaced_by TEXT;
@@ -0,0 +1,9 @@
+-- 全部提供商失败时返回的兜底回复（按模型配置，model 为 * 时作为默认规则）
+CREATE TABLE IF NOT EXISTS fallback_responses (
+    id TEXT PRIMARY KEY,
+    model TEXT NOT NULL UNIQUE,
+    template TEXT NOT NULL,        -- 回复模板，支持 {model} 占位符
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- 使用记录关联的网关密钥ID（未使用网关密钥的请求为空），用于按密钥统计用量
+ALTER TABLE api_usage ADD COLUMN gateway_key_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_api_usage_gateway_key ON api_usage (gateway_key_id, request_time);
@@ -0,0 +1,9 @@
+-- 客户端IP访问规则（与 IP_ALLOWLIST/IP_DENYLIST 环境变量中的规则合并生效）
+CREATE TABLE IF NOT EXISTS ip_access_rules (
+    id TEXT PRIMARY KEY,
+    cidr TEXT NOT NULL,            -- CIDR 网段或单个IP，保存为规范形式
+    action TEXT NOT NULL,          -- Allow 或 Deny
+    description TEXT,
+    created_at TEXT NOT NULL,
+    UNIQUE (cidr, action)
+);
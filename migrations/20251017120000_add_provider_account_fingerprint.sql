@@ -0,0 +1,4 @@
+-- 上游账户指纹：余额接口返回的用户ID（连同余额接口的主机）的哈希，
+-- 用于发现看似不同、实际属于同一上游账户（共享同一额度）的密钥
+ALTER TABLE api_providers ADD COLUMN account_fingerprint TEXT;
+CREATE INDEX IF NOT EXISTS idx_api_providers_account_fingerprint ON api_providers (account_fingerprint);
@@ -0,0 +1,4 @@
+-- 提供商最近一次失败（调用或余额检查），便于排查密钥不健康的原因
+ALTER TABLE api_providers ADD COLUMN last_error_at DATETIME;
+ALTER TABLE api_providers ADD COLUMN last_error_category TEXT;
+ALTER TABLE api_providers ADD COLUMN last_error_message TEXT;
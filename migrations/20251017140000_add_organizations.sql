@@ -0,0 +1,8 @@
+-- 组织专属提供商池：绑定到组织的提供商（组织自购的密钥）只服务该组织的网关密钥，
+-- 组织的请求也不会使用共享池中的提供商
+ALTER TABLE api_providers ADD COLUMN organization TEXT;
+ALTER TABLE gateway_keys ADD COLUMN organization TEXT;
+
+-- 使用记录归属的组织（由实际服务请求的提供商决定），共享池的请求为空
+ALTER TABLE api_usage ADD COLUMN organization TEXT;
+CREATE INDEX IF NOT EXISTS idx_api_usage_organization ON api_usage (organization, request_time);
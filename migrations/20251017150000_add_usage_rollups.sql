@@ -0,0 +1,25 @@
+-- 使用记录汇总：后台任务按小时/天、网关密钥、提供商和模型聚合 api_usage，
+-- 统计接口对已汇总的时间段直接读取汇总表
+CREATE TABLE IF NOT EXISTS usage_rollups (
+    granularity TEXT NOT NULL,               -- hour 或 day
+    bucket_start DATETIME NOT NULL,          -- 时间段起点（UTC，含）
+    gateway_key_id TEXT NOT NULL DEFAULT '', -- 未使用网关密钥的请求为空字符串
+    provider_api_key TEXT NOT NULL,
+    model TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    successful_requests INTEGER NOT NULL DEFAULT 0,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    completion_tokens INTEGER NOT NULL DEFAULT 0,
+    total_tokens INTEGER NOT NULL DEFAULT 0,
+    cost REAL NOT NULL DEFAULT 0.0,
+    PRIMARY KEY (granularity, bucket_start, gateway_key_id, provider_api_key, model)
+);
+
+CREATE INDEX IF NOT EXISTS idx_usage_rollups_key ON usage_rollups (granularity, gateway_key_id, bucket_start);
+
+-- 各粒度已汇总到的时间点（不含），之前的时间段都已完整汇总
+CREATE TABLE IF NOT EXISTS usage_rollup_state (
+    granularity TEXT PRIMARY KEY,
+    rolled_up_to DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL
+);
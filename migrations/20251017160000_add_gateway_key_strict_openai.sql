@@ -0,0 +1,2 @@
+-- 网关密钥是否开启严格 OpenAI 兼容模式：按 OpenAI 格式校验、修复或拒绝返回给客户端的聊天响应
+ALTER TABLE gateway_keys ADD COLUMN strict_openai INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,10 @@
+-- 提供商上游耗时直方图：内存中累计，定时任务定期写入，启动时加载
+CREATE TABLE IF NOT EXISTS provider_latency (
+    api_key TEXT NOT NULL,
+    metric TEXT NOT NULL,          -- request：上游请求耗时
+    bucket_counts TEXT NOT NULL,   -- 各桶（不含 +Inf）的非累计计数，JSON数组
+    count INTEGER NOT NULL DEFAULT 0,
+    sum_ms REAL NOT NULL DEFAULT 0.0,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (api_key, metric)
+);
@@ -0,0 +1,4 @@
+-- 为API提供商添加重试策略：同一提供商的最大尝试次数（含首次）、重试间隔，以及需要重试的上游状态码（逗号分隔，为空表示所有失败状态码都重试）
+ALTER TABLE api_providers ADD COLUMN retry_attempts INTEGER NOT NULL DEFAULT 3;
+ALTER TABLE api_providers ADD COLUMN retry_delay_ms INTEGER NOT NULL DEFAULT 1000;
+ALTER TABLE api_providers ADD COLUMN retry_on_statuses TEXT;
@@ -0,0 +1,3 @@
+-- 为API提供商添加单独的上游超时：连接超时和请求超时（毫秒），为空时使用 UPSTREAM_CONNECT_TIMEOUT_MS / UPSTREAM_REQUEST_TIMEOUT_MS
+ALTER TABLE api_providers ADD COLUMN connect_timeout_ms INTEGER;
+ALTER TABLE api_providers ADD COLUMN request_timeout_ms INTEGER;
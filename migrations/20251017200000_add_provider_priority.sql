@@ -0,0 +1,2 @@
+-- 为API提供商添加优先级：同一模型优先使用优先级最高的一层提供商，该层饱和或全部失败时才使用较低的一层（数值越大越优先）
+ALTER TABLE api_providers ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- 网关密钥的默认负载均衡策略：请求未携带 X-LB-Strategy 请求头时使用，为空时使用 LOAD_BALANCE_STRATEGY
+ALTER TABLE gateway_keys ADD COLUMN load_balance_strategy TEXT;
@@ -0,0 +1,7 @@
+-- 为API提供商添加连接复用设置（为空时使用默认值）
+-- pool_max_idle_per_host：每个上游域名最多保留的空闲连接数
+-- keep_alive_secs：TCP keepalive 和 HTTP/2 PING 的间隔(秒)
+-- http_version：auto（TLS协商）、http1（只用HTTP/1.1）、http2（直接使用HTTP/2）
+ALTER TABLE api_providers ADD COLUMN pool_max_idle_per_host INTEGER;
+ALTER TABLE api_providers ADD COLUMN keep_alive_secs INTEGER;
+ALTER TABLE api_providers ADD COLUMN http_version TEXT;
@@ -0,0 +1,2 @@
+-- 为API提供商添加附加请求头（JSON对象，如 {"X-Tenant-Id": "t1"}），随该提供商的每个上游请求发送
+ALTER TABLE api_providers ADD COLUMN extra_headers TEXT;
@@ -0,0 +1,5 @@
+-- 为API提供商添加TLS设置（自托管服务常使用私有CA）
+-- tls_ca_path：额外信任的CA证书文件路径（PEM，可包含多个证书）
+-- tls_insecure_skip_verify：跳过证书校验，需显式开启，仅用于测试环境
+ALTER TABLE api_providers ADD COLUMN tls_ca_path TEXT;
+ALTER TABLE api_providers ADD COLUMN tls_insecure_skip_verify BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,18 @@
+-- 模型目录：从提供商的模型列表接口同步，每个提供商的每个模型一行
+CREATE TABLE IF NOT EXISTS ai_models (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    provider_id TEXT NOT NULL,
+    model_type TEXT NOT NULL,
+    version TEXT NOT NULL,
+    context_window INTEGER,
+    input_price_per_million_tokens REAL,
+    output_price_per_million_tokens REAL,
+    capabilities TEXT NOT NULL DEFAULT '{}',  -- 模型能力，JSON对象（如 vision、tools）
+    is_enabled INTEGER NOT NULL DEFAULT 1,    -- 提供商的模型列表中已不再出现的模型会被停用
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE (provider_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_models_name ON ai_models(name);
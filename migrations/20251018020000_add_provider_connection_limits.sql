@@ -0,0 +1,6 @@
+-- 为API提供商添加独立的并发连接上限和连接许可等待时间（之前并发上限复用每分钟请求数 rate_limit，等待时间固定为3000毫秒）
+-- max_connections：同时进行的上游请求数上限（启用并发自适应时作为初始上限），已有提供商沿用原来的 rate_limit
+-- acquire_timeout_ms：没有空闲连接许可时排队等待的最长时间(毫秒)，超时后尝试下一个提供商
+ALTER TABLE api_providers ADD COLUMN max_connections INTEGER NOT NULL DEFAULT 10;
+ALTER TABLE api_providers ADD COLUMN acquire_timeout_ms INTEGER NOT NULL DEFAULT 3000;
+UPDATE api_providers SET max_connections = rate_limit WHERE rate_limit > 0;
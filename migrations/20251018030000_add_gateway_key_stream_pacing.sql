@@ -0,0 +1,2 @@
+-- 网关密钥的流式输出节流速率（tokens/秒）：设置后该密钥的流式响应按此速率平滑下发，为空时使用提供商的 stream_pacing_tps
+ALTER TABLE gateway_keys ADD COLUMN stream_pacing_tps REAL;
@@ -0,0 +1,2 @@
+-- 管理令牌过期时间（为空表示永不过期），登录签发的令牌与登录JWT同时过期
+ALTER TABLE admin_tokens ADD COLUMN expires_at TEXT;
@@ -0,0 +1,25 @@
+-- 预付额度改为挂在网关密钥上：只保存网关密钥ID（密钥本身只存哈希），
+-- 并单独维护可用余额，请求开始时原子地预扣额度，结束后按实际成本结算
+
+-- 旧版按客户端密钥明文记账，账本中的这些账户改为脱敏显示（只保留最后4个字符）
+DROP TRIGGER IF EXISTS billing_ledger_no_update;
+
+UPDATE billing_ledger
+SET account = CASE WHEN length(account) <= 8 THEN '****' ELSE '****' || substr(account, -4) END
+WHERE account IN (SELECT key FROM prepaid_keys)
+   OR (entry_type = 'Debit' AND account NOT IN (SELECT id FROM gateway_keys));
+
+CREATE TRIGGER IF NOT EXISTS billing_ledger_no_update
+BEFORE UPDATE ON billing_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'billing_ledger is append-only');
+END;
+
+-- 旧的明文密钥无法对应到网关密钥，需要为网关密钥重新加载额度
+DROP TABLE IF EXISTS prepaid_keys;
+
+CREATE TABLE IF NOT EXISTS prepaid_keys (
+    gateway_key_id TEXT PRIMARY KEY,   -- 网关密钥ID（与 billing_ledger.account 对应）
+    balance REAL NOT NULL DEFAULT 0,   -- 可用余额：充值减去已结算的成本和在途请求的预扣额度
+    created_at TEXT NOT NULL
+);
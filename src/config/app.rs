@@ -20,8 +20,74 @@ pub struct AppConfig {
     pub connection_pool: ConnectionPoolConfig,
     /// 健康检查配置
     pub health_check: HealthCheckConfig,
+    /// 提供商冷却配置
+    pub provider_cooldown: ProviderCooldownConfig,
     /// 代理配置
     pub proxy: ProxyConfig,
+    /// 请求体压缩配置
+    pub request_compression: RequestCompressionConfig,
+    /// 响应压缩配置
+    pub response_compression: ResponseCompressionConfig,
+    /// 上游响应大小限制
+    pub response_limits: ResponseLimitsConfig,
+    /// 并发自适应配置
+    pub concurrency: ConcurrencyConfig,
+    /// 请求追踪采样配置
+    pub tracing: TracingConfig,
+    /// 请求/响应内容日志配置
+    pub payload_logging: PayloadLoggingConfig,
+    /// 错误上报配置
+    pub error_reporting: ErrorReportingConfig,
+    /// OpenRouter 应用归属配置
+    pub openrouter: OpenRouterConfig,
+    /// 提供商合同到期提醒配置
+    pub provider_expiry: ProviderExpiryConfig,
+    /// 内容被拦截时的模板回复配置
+    pub blocked_response: BlockedResponseConfig,
+    /// 客户端自带上游密钥（BYOK）配置
+    pub byok: ByokConfig,
+    /// 提供商实时统计快照配置
+    pub provider_stats: ProviderStatsConfig,
+    /// 管理列表接口缓存配置
+    pub list_cache: ListCacheConfig,
+    /// 降级提示配置
+    pub degradation: DegradationConfig,
+    /// 流式响应心跳配置
+    pub stream_heartbeat: StreamHeartbeatConfig,
+    /// 流式响应发送合并配置
+    pub stream_flush: StreamFlushConfig,
+    /// 上游预热配置
+    pub upstream_warmup: UpstreamWarmupConfig,
+    /// 上游超时配置
+    pub upstream_timeouts: UpstreamTimeoutConfig,
+    /// 负载均衡配置
+    pub load_balancing: LoadBalancingConfig,
+    /// 会话亲和配置
+    pub conversation_affinity: ConversationAffinityConfig,
+    /// 模型回退链配置
+    pub model_fallback: ModelFallbackConfig,
+    /// 延迟SLO配置
+    pub latency_slo: LatencySloConfig,
+    /// 慢请求日志配置
+    pub slow_requests: SlowRequestConfig,
+    /// 全局在途请求上限配置
+    pub in_flight: InFlightLimitConfig,
+    /// 使用记录批量写入配置
+    pub usage_recorder: UsageRecorderConfig,
+    /// 客户端IP访问控制配置
+    pub ip_access: IpAccessConfig,
+    /// Prometheus 指标导出配置
+    pub metrics: MetricsConfig,
+    /// 使用记录汇总配置
+    pub usage_rollup: UsageRollupConfig,
+    /// 使用记录保留配置
+    pub usage_retention: UsageRetentionConfig,
+    /// 自检诊断配置
+    pub diagnostics: DiagnosticsConfig,
+    /// 用量报表分享链接配置
+    pub report_links: ReportLinksConfig,
+    /// 定时任务配置
+    pub scheduler: SchedulerConfig,
     /// API提供商配置
     pub api_providers: HashMap<String, ApiProviderConfig>,
 }
@@ -58,6 +124,10 @@ pub struct ServerConfig {
     pub log_level: String,
     /// CORS允许的域名
     pub cors_allowed_origins: Vec<String>,
+    /// 是否监听TCP地址（host:port）
+    pub tcp_enabled: bool,
+    /// UNIX域套接字路径，设置后额外在该路径监听
+    pub unix_socket: Option<PathBuf>,
 }
 
 /// 数据库配置 - SQLite版本
@@ -73,17 +143,37 @@ pub struct DatabaseConfig {
     pub enable_foreign_keys: bool,
     /// 最大连接数
     pub max_connections: u32,
+    /// 慢查询阈值(毫秒)，超过时记录警告日志
+    pub slow_query_threshold_ms: u64,
+    /// 数据库被锁定时SQLite内部等待的超时时间(毫秒)
+    pub busy_timeout_ms: u64,
+    /// 热路径查询遇到SQLITE_BUSY/SQLITE_LOCKED时的重试次数
+    pub busy_retry_attempts: u32,
 }
 
 /// 认证配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthConfig {
-    /// JWT密钥
-    pub jwt_secret: String,
-    /// JWT过期时间(秒)
+    /// 登录JWT的签名密钥，未配置时登录不签发JWT，管理接口也不接受JWT
+    pub jwt_secret: Option<String>,
+    /// JWT和登录签发的管理令牌的过期时间(秒)
     pub jwt_expiration: u64,
     /// 默认管理员信息
     pub admin: AdminConfig,
+    /// 管理API根令牌（拥有全部作用域），未配置时不启用管理API鉴权
+    pub admin_api_token: Option<String>,
+    /// 推理接口是否要求网关密钥（Bearer），关闭时仅识别有效密钥而不拒绝请求
+    pub require_gateway_key: bool,
+    /// 网关密钥默认每分钟请求数上限（密钥未单独设置时使用，0表示不限制）
+    pub gateway_key_default_rpm: u32,
+    /// 网关密钥默认每分钟token数上限（密钥未单独设置时使用，0表示不限制）
+    pub gateway_key_default_tpm: u64,
+    /// 网关密钥默认月度预算（密钥未单独设置时使用，0表示不限制）
+    pub gateway_key_default_monthly_budget: f64,
+    /// 预付费网关密钥每个请求开始时预扣的额度，请求结束后按实际成本结算
+    pub prepaid_credit_hold: f64,
+    /// 网关密钥轮换后旧密钥的默认宽限期(秒)
+    pub gateway_key_rotation_grace_secs: u64,
 }
 
 /// 管理员配置
@@ -113,6 +203,19 @@ pub struct HealthCheckConfig {
     pub interval: u64,
     /// 超时时间(毫秒)
     pub timeout: u64,
+    /// 连续鉴权失败多少次后删除被隔离的提供商
+    pub quarantine_failure_threshold: u32,
+}
+
+/// 提供商冷却配置：连续返回限流(429)或服务端错误(5xx)的提供商，以及429响应带 Retry-After 的提供商暂时移出轮换
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderCooldownConfig {
+    /// 连续失败多少次后进入冷却，0表示不冷却
+    pub failure_threshold: u32,
+    /// 冷却时长(秒)
+    pub cooldown_secs: u64,
+    /// 按上游 429 响应的 Retry-After 冷却的最长时长(秒)，0表示忽略 Retry-After
+    pub retry_after_max_secs: u64,
 }
 
 /// 代理配置
@@ -124,6 +227,346 @@ pub struct ProxyConfig {
     pub url: String,
 }
 
+/// 请求体压缩配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestCompressionConfig {
+    /// 请求体超过该大小(字节)时对支持的提供商启用gzip压缩
+    pub gzip_threshold_bytes: usize,
+}
+
+/// 响应压缩配置（gzip/br，按客户端的 Accept-Encoding 协商；流式响应不压缩）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseCompressionConfig {
+    /// 是否压缩响应
+    pub enabled: bool,
+    /// 响应体不小于该大小(字节)时才压缩
+    pub min_size_bytes: u16,
+}
+
+/// 上游响应大小限制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseLimitsConfig {
+    /// 非流式响应体的最大字节数，超过时返回截断错误
+    pub max_body_bytes: usize,
+    /// 流式响应累计的最大字节数，超过时发送错误事件并结束流
+    pub max_stream_bytes: usize,
+}
+
+/// 并发自适应配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcurrencyConfig {
+    /// 是否根据429和延迟自动调整提供商并发上限
+    pub auto_tuning: bool,
+    /// 并发上限的最小值
+    pub min_limit: u32,
+    /// 并发上限的最大值
+    pub max_limit: u32,
+    /// 目标延迟(毫秒)，超过时减小并发上限
+    pub latency_target_ms: u64,
+}
+
+/// 请求追踪采样配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracingConfig {
+    /// 默认采样率(0.0-1.0)
+    pub default_sample_rate: f64,
+    /// 按路由设置的采样率，键为路由模板（如 /v1/chat/completions）
+    pub route_sample_rates: HashMap<String, f64>,
+    /// 请求失败(状态码>=400)时是否总是记录追踪
+    pub always_sample_errors: bool,
+    /// 强制采样的客户端密钥（Authorization Bearer）
+    pub force_sample_keys: Vec<String>,
+}
+
+/// 请求/响应内容日志配置：请求体、上游响应和流式数据块可能包含用户隐私，默认不记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadLoggingConfig {
+    /// 记录方式：off（不记录）、truncated（截断到 max_chars 个字符）、full（完整记录）
+    pub mode: String,
+    /// truncated 模式下每条内容最多记录的字符数
+    pub max_chars: usize,
+    /// 是否记录流式响应每个数据块的内容（trace 级别，且 mode 不为 off 时才记录）
+    pub stream_chunks: bool,
+}
+
+/// 错误上报配置：panic 和上游失败事件发送到兼容 Sentry 的 DSN
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorReportingConfig {
+    /// 上报地址（Sentry DSN），为空时不上报
+    pub dsn: Option<String>,
+    /// 事件的环境标签
+    pub environment: String,
+}
+
+/// OpenRouter 应用归属配置（随请求发送 HTTP-Referer 和 X-Title 请求头）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRouterConfig {
+    /// 应用网址（HTTP-Referer）
+    pub referer: String,
+    /// 应用名称（X-Title）
+    pub title: String,
+}
+
+/// 提供商合同到期提醒配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderExpiryConfig {
+    /// 提前多少天开始提醒
+    pub alert_days: i64,
+    /// 到期检查间隔(秒)
+    pub check_interval_secs: u64,
+}
+
+/// 内容被上游审核拦截时的模板回复配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedResponseConfig {
+    /// 是否以模板补全代替HTTP错误返回
+    pub enabled: bool,
+    /// 回复模板，支持 {model} 和 {reason} 占位符
+    pub template: String,
+}
+
+/// 客户端自带上游密钥（BYOK）配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ByokConfig {
+    /// 是否允许客户端通过请求头提供自己的上游密钥
+    pub enabled: bool,
+}
+
+/// 提供商实时统计快照配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderStatsConfig {
+    /// 快照刷新间隔(毫秒)
+    pub refresh_interval_ms: u64,
+}
+
+/// 管理列表接口（提供商、定价）响应缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListCacheConfig {
+    /// 是否启用缓存
+    pub enabled: bool,
+    /// 缓存新鲜期(毫秒)，期内直接返回缓存
+    pub ttl_ms: u64,
+    /// 过期后仍可返回旧数据的时长(毫秒)，期间在后台刷新
+    pub stale_ms: u64,
+}
+
+/// 降级提示配置：网关处于降级状态时在推理响应中附带提示
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegradationConfig {
+    /// 是否启用降级提示
+    pub enabled: bool,
+    /// 可用提供商占比低于该值时视为降级
+    pub min_healthy_ratio: f64,
+    /// 数据库只读错误后保持降级状态的时长(秒)
+    pub read_only_window_secs: u64,
+}
+
+/// 流式响应心跳配置：等待上游数据时定期向客户端发送SSE注释帧，避免中间代理断开空闲连接
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamHeartbeatConfig {
+    /// 心跳间隔(毫秒)，0表示不发送
+    pub interval_ms: u64,
+}
+
+/// 流式响应发送合并配置：immediate 模式每块数据立即发送；batched 模式缓冲一段时间或一定字节后合并发送，
+/// 用少量延迟换取更少的小TCP包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamFlushConfig {
+    /// 发送模式（immediate、batched）
+    pub mode: String,
+    /// batched 模式下最多缓冲多久(毫秒)
+    pub batch_ms: u64,
+    /// batched 模式下缓冲达到多少字节立即发送
+    pub batch_bytes: usize,
+    /// batched 模式下内容在句子结尾处时立即发送
+    pub flush_on_sentence: bool,
+}
+
+/// 上游预热配置：提供商池重新加载后在后台预先解析提供商域名并可选地建立连接，
+/// 避免变更后的第一个请求承担DNS解析和TLS握手的延迟
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamWarmupConfig {
+    /// 是否在提供商池重新加载后预解析域名
+    pub enabled: bool,
+    /// 是否同时为每个上游地址建立一条预热连接
+    pub warm_connections: bool,
+    /// DNS解析结果缓存时间(秒)
+    pub dns_ttl_secs: u64,
+}
+
+/// 上游超时配置：提供商未单独配置 connect_timeout_ms / request_timeout_ms 时使用的默认值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamTimeoutConfig {
+    /// 连接超时(毫秒)
+    pub connect_timeout_ms: u64,
+    /// 请求超时(毫秒)，流式请求包含接收完整个响应的时间
+    pub request_timeout_ms: u64,
+}
+
+/// 支持的负载均衡策略
+pub const LOAD_BALANCE_STRATEGIES: [&str; 5] = ["RoundRobin", "LeastConnections", "LeastTokens", "LeastCost", "LowestLatency"];
+
+/// 负载均衡配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadBalancingConfig {
+    /// 选择提供商的首选策略（RoundRobin、LeastConnections、LeastTokens、LeastCost、LowestLatency）
+    pub strategy: String,
+}
+
+/// 会话亲和配置：请求携带会话标识时，后续轮次优先路由到之前服务该会话的提供商
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationAffinityConfig {
+    /// 是否启用会话亲和
+    pub enabled: bool,
+    /// 会话最后一次请求后保持亲和的时长(秒)
+    pub ttl_secs: u64,
+    /// 最多记录的会话数，超出时淘汰最久未使用的会话
+    pub max_entries: usize,
+    /// 请求未携带会话请求头时，是否使用请求体中的 user 字段作为会话标识
+    pub user_field: bool,
+}
+
+/// 模型回退链配置：请求的模型没有可用的提供商时，依次改用链上第一个有可用提供商的模型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelFallbackConfig {
+    /// 按请求的模型名称配置的回退模型，按顺序尝试
+    pub chains: HashMap<String, Vec<String>>,
+}
+
+impl ModelFallbackConfig {
+    /// 解析 MODEL_FALLBACKS：分号分隔的 `模型=回退模型1,回退模型2`，
+    /// 忽略格式错误和回退链为空的条目，同一模型配置多次时以最后一次为准
+    pub fn parse(value: &str) -> Self {
+        let chains = value
+            .split(';')
+            .filter_map(|entry| {
+                let (model, chain) = entry.split_once('=')?;
+                let chain: Vec<String> = chain
+                    .split(',')
+                    .map(|m| m.trim().to_string())
+                    .filter(|m| !m.is_empty())
+                    .collect();
+                Some((model.trim().to_string(), chain))
+            })
+            .filter(|(model, chain)| !model.is_empty() && !chain.is_empty())
+            .collect();
+        Self { chains }
+    }
+}
+
+/// 慢请求日志配置：端到端耗时超过阈值的请求单独记录警告日志并计数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowRequestConfig {
+    /// 慢请求阈值(毫秒)，0表示不记录
+    pub threshold_ms: u64,
+}
+
+/// 全局在途请求上限配置：网关同时处理的请求数超过上限时返回429，保护数据库连接池和内存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InFlightLimitConfig {
+    /// 最大在途请求数，0表示不限制
+    pub max_in_flight: usize,
+    /// 被拒绝时返回的 Retry-After(秒)
+    pub retry_after_secs: u64,
+}
+
+/// 延迟SLO配置：网关整体p99延迟持续超出上限时自动拒绝低优先级请求，延迟恢复后解除
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySloConfig {
+    /// 是否启用延迟SLO保护
+    pub enabled: bool,
+    /// p99延迟上限(毫秒)
+    pub p99_ms: u64,
+    /// 统计p99的滑动窗口(秒)
+    pub window_secs: u64,
+    /// p99持续超出上限多少分钟后开始降载
+    pub breach_minutes: u64,
+    /// p99持续恢复多少分钟后解除降载
+    pub recovery_minutes: u64,
+    /// 降载时拒绝的最高优先级（low、normal），不高于该优先级的请求被拒绝
+    pub shed_priority: String,
+}
+
+/// 使用记录批量写入配置：请求的使用记录先进入队列，合并为多行INSERT在一个事务中写入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecorderConfig {
+    /// 单次写入的最大记录数，1表示每条记录单独写入
+    pub batch_size: usize,
+    /// 第一条记录进入队列后最多等待多久写入(毫秒)
+    pub flush_interval_ms: u64,
+    /// 队列容量
+    pub queue_capacity: usize,
+    /// 队列已满时的处理方式：block（等待入队，形成背压）、drop（丢弃并计数）、inline（绕过队列直接写入）
+    pub overflow: String,
+}
+
+/// 客户端IP访问控制配置（CIDR 网段，逗号分隔；运行时还可通过管理接口增删规则）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpAccessConfig {
+    /// 允许的网段，非空时只有命中的客户端可以访问
+    pub allowlist: Vec<String>,
+    /// 拒绝的网段，优先于允许规则
+    pub denylist: Vec<String>,
+}
+
+/// Prometheus 指标导出配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// 单独导出的网关密钥数量上限，其余密钥合并为 other，控制标签基数
+    pub key_top_n: usize,
+}
+
+/// 使用记录汇总配置（后台任务把 api_usage 汇总为按小时、按天的 usage_rollups，用量统计接口优先读取汇总表）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRollupConfig {
+    /// 是否启用汇总任务
+    pub enabled: bool,
+    /// 汇总任务的执行间隔(秒)
+    pub interval_secs: u64,
+    /// 时间段结束后等待多久再汇总(秒)，给批量写入和迟到的记录留出时间
+    pub settle_secs: u64,
+}
+
+/// 使用记录保留配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRetentionConfig {
+    /// 使用记录保留天数，0表示永久保留
+    pub retention_days: u64,
+    /// 删除前把过期记录归档为CSV文件的目录，为空时直接删除
+    pub archive_dir: Option<String>,
+    /// 每批删除的记录数，避免长时间占用数据库写锁
+    pub batch_size: u64,
+}
+
+/// 自检诊断配置（GET /v1/admin/diagnostics）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsConfig {
+    /// 数据库所在磁盘的最小剩余空间(MB)，低于该值时检查失败
+    pub min_free_disk_mb: u64,
+    /// 允许的最大时钟偏差(秒)
+    pub max_clock_skew_secs: u64,
+    /// 用于比对时钟的参考地址（读取响应的 Date 头），未配置时跳过时钟检查
+    pub clock_reference_url: Option<String>,
+}
+
+/// 用量报表分享链接配置（签名的限时链接，无需网关凭证即可查看）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportLinksConfig {
+    /// 签名密钥，未配置时不能生成分享链接，已有链接也不再可用
+    pub signing_secret: Option<String>,
+    /// 生成链接使用的外部访问地址（如 https://gateway.example.com），未配置时返回相对路径
+    pub public_base_url: Option<String>,
+    /// 链接的最长有效期(秒)
+    pub max_ttl_secs: u64,
+}
+
+/// 定时任务配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// 按任务名称覆盖执行计划（@every 间隔、五段式 cron 表达式或 off），未配置的任务使用默认计划
+    pub schedules: HashMap<String, String>,
+}
+
 /// API提供商配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiProviderConfig {
@@ -157,6 +600,14 @@ impl AppConfig {
             .split(',')
             .map(|s| s.trim().to_string())
             .collect();
+        let tcp_enabled = env::var("APP_TCP_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let unix_socket = env::var("APP_UNIX_SOCKET")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(PathBuf::from);
 
         // SQLite数据库配置
         let db_path = env::var("SQLITE_PATH").unwrap_or_else(|_| "database.sqlite3".to_string());
@@ -175,9 +626,21 @@ impl AppConfig {
             .unwrap_or_else(|_| "5".to_string())
             .parse::<u32>()
             .unwrap_or(5);
+        let slow_query_threshold_ms = env::var("SQLITE_SLOW_QUERY_MS")
+            .unwrap_or_else(|_| "200".to_string())
+            .parse::<u64>()
+            .unwrap_or(200);
+        let busy_timeout_ms = env::var("SQLITE_BUSY_TIMEOUT_MS")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse::<u64>()
+            .unwrap_or(5000);
+        let busy_retry_attempts = env::var("SQLITE_BUSY_RETRIES")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .unwrap_or(3);
 
         // 认证配置
-        let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "default_secret_key".to_string());
+        let jwt_secret = env::var("JWT_SECRET").ok().filter(|s| !s.is_empty());
         let jwt_expiration = env::var("JWT_EXPIRATION")
             .unwrap_or_else(|_| "86400".to_string())
             .parse::<u64>()
@@ -187,6 +650,36 @@ impl AppConfig {
         let admin_username = env::var("ADMIN_USERNAME").unwrap_or_else(|_| "admin".to_string());
         let admin_email = env::var("ADMIN_EMAIL").unwrap_or_else(|_| "admin@example.com".to_string());
         let admin_password = env::var("ADMIN_PASSWORD").unwrap_or_else(|_| "changeme".to_string());
+        let admin_api_token = env::var("ADMIN_API_TOKEN")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let require_gateway_key = env::var("REQUIRE_GATEWAY_KEY")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+        let gateway_key_default_rpm = env::var("GATEWAY_KEY_DEFAULT_RPM")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u32>()
+            .unwrap_or(0);
+        let gateway_key_default_tpm = env::var("GATEWAY_KEY_DEFAULT_TPM")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .unwrap_or(0);
+        let gateway_key_default_monthly_budget = env::var("GATEWAY_KEY_DEFAULT_MONTHLY_BUDGET")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<f64>()
+            .unwrap_or(0.0);
+        let prepaid_credit_hold = env::var("PREPAID_CREDIT_HOLD")
+            .unwrap_or_else(|_| "0.01".to_string())
+            .parse::<f64>()
+            .ok()
+            .filter(|hold| *hold > 0.0)
+            .unwrap_or(0.01);
+        let gateway_key_rotation_grace_secs = env::var("GATEWAY_KEY_ROTATION_GRACE_SECS")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse::<u64>()
+            .unwrap_or(86400);
 
         // 连接池配置
         let pool_max_size = env::var("POOL_MAX_SIZE")
@@ -207,6 +700,24 @@ impl AppConfig {
             .unwrap_or_else(|_| "5000".to_string())
             .parse::<u64>()
             .unwrap_or(5000);
+        let quarantine_failure_threshold = env::var("QUARANTINE_FAILURE_THRESHOLD")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .unwrap_or(3);
+
+        // 提供商冷却配置
+        let provider_cooldown_failures = env::var("PROVIDER_COOLDOWN_FAILURES")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .unwrap_or(3);
+        let provider_cooldown_secs = env::var("PROVIDER_COOLDOWN_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .unwrap_or(30);
+        let provider_retry_after_max_secs = env::var("PROVIDER_RETRY_AFTER_MAX_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u64>()
+            .unwrap_or(300);
 
         // 代理配置
         let enable_proxy = env::var("ENABLE_PROXY")
@@ -216,6 +727,395 @@ impl AppConfig {
         let proxy_url = env::var("PROXY_URL")
             .unwrap_or_else(|_| "socks5://127.0.0.1:1080".to_string());
 
+        // 请求体压缩配置
+        let gzip_threshold_bytes = env::var("REQUEST_GZIP_THRESHOLD_BYTES")
+            .unwrap_or_else(|_| "65536".to_string())
+            .parse::<usize>()
+            .unwrap_or(65536);
+
+        // 响应压缩配置
+        let response_compression_enabled = env::var("RESPONSE_COMPRESSION_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let response_compression_min_bytes = env::var("RESPONSE_COMPRESSION_MIN_BYTES")
+            .unwrap_or_else(|_| "1024".to_string())
+            .parse::<u16>()
+            .unwrap_or(1024);
+
+        // 上游响应大小限制配置
+        let max_response_body_bytes = env::var("UPSTREAM_MAX_RESPONSE_BYTES")
+            .unwrap_or_else(|_| "16777216".to_string())
+            .parse::<usize>()
+            .unwrap_or(16 * 1024 * 1024);
+        let max_response_stream_bytes = env::var("UPSTREAM_MAX_STREAM_BYTES")
+            .unwrap_or_else(|_| "67108864".to_string())
+            .parse::<usize>()
+            .unwrap_or(64 * 1024 * 1024);
+
+        // OpenRouter 应用归属配置
+        let openrouter_referer = env::var("OPENROUTER_REFERER")
+            .unwrap_or_else(|_| "https://github.com/Dolores18/api-manager".to_string());
+        let openrouter_title = env::var("OPENROUTER_TITLE")
+            .unwrap_or_else(|_| "api-manager".to_string());
+
+        // 提供商合同到期提醒配置
+        let provider_expiry_alert_days = env::var("PROVIDER_EXPIRY_ALERT_DAYS")
+            .unwrap_or_else(|_| "7".to_string())
+            .parse::<i64>()
+            .unwrap_or(7)
+            .max(0);
+        let provider_expiry_check_interval = env::var("PROVIDER_EXPIRY_CHECK_INTERVAL")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .unwrap_or(3600)
+            .max(1);
+
+        // 内容拦截模板回复配置
+        let blocked_response_enabled = env::var("BLOCKED_RESPONSE_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let blocked_response_template = env::var("BLOCKED_RESPONSE_TEMPLATE")
+            .unwrap_or_else(|_| "抱歉，该请求触发了内容安全策略，无法提供回答。".to_string());
+
+        // 客户端自带上游密钥配置
+        let byok_enabled = env::var("BYOK_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+
+        // 提供商实时统计快照配置
+        let provider_stats_refresh_interval = env::var("PROVIDER_STATS_REFRESH_MS")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<u64>()
+            .unwrap_or(1000)
+            .max(100);
+
+        // 管理列表接口缓存配置
+        let list_cache_enabled = env::var("LIST_CACHE_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let list_cache_ttl = env::var("LIST_CACHE_TTL_MS")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse::<u64>()
+            .unwrap_or(5000);
+        let list_cache_stale = env::var("LIST_CACHE_STALE_MS")
+            .unwrap_or_else(|_| "30000".to_string())
+            .parse::<u64>()
+            .unwrap_or(30000);
+
+        // 降级提示配置
+        let degradation_enabled = env::var("DEGRADATION_ADVISORY_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let degradation_min_healthy_ratio = env::var("DEGRADED_MIN_HEALTHY_RATIO")
+            .unwrap_or_else(|_| "0.5".to_string())
+            .parse::<f64>()
+            .unwrap_or(0.5);
+        let degradation_read_only_window = env::var("DEGRADED_READ_ONLY_WINDOW_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .unwrap_or(60);
+
+        // 流式响应心跳配置
+        let stream_heartbeat_interval = env::var("STREAM_HEARTBEAT_INTERVAL_MS")
+            .unwrap_or_else(|_| "15000".to_string())
+            .parse::<u64>()
+            .unwrap_or(15000);
+
+        // 流式响应发送合并配置（未知模式回退到 immediate）
+        let stream_flush_mode = env::var("STREAM_FLUSH_MODE")
+            .ok()
+            .filter(|s| ["immediate", "batched"].contains(&s.as_str()))
+            .unwrap_or_else(|| "immediate".to_string());
+        let stream_batch_ms = env::var("STREAM_BATCH_MS")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse::<u64>()
+            .unwrap_or(20);
+        let stream_batch_bytes = env::var("STREAM_BATCH_BYTES")
+            .unwrap_or_else(|_| "4096".to_string())
+            .parse::<usize>()
+            .unwrap_or(4096);
+        let stream_flush_on_sentence = env::var("STREAM_FLUSH_ON_SENTENCE")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+
+        // 上游预热配置
+        let upstream_warmup_enabled = env::var("UPSTREAM_PREWARM_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let upstream_warm_connections = env::var("UPSTREAM_PREWARM_CONNECTIONS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+        let upstream_dns_ttl = env::var("UPSTREAM_DNS_TTL_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u64>()
+            .unwrap_or(300);
+
+        // 上游超时配置
+        let upstream_connect_timeout = env::var("UPSTREAM_CONNECT_TIMEOUT_MS")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<u64>()
+            .unwrap_or(10000);
+        let upstream_request_timeout = env::var("UPSTREAM_REQUEST_TIMEOUT_MS")
+            .unwrap_or_else(|_| "300000".to_string())
+            .parse::<u64>()
+            .unwrap_or(300000);
+
+        // 负载均衡配置（未知策略回退到 RoundRobin）
+        let load_balance_strategy = env::var("LOAD_BALANCE_STRATEGY")
+            .ok()
+            .filter(|s| LOAD_BALANCE_STRATEGIES.contains(&s.as_str()))
+            .unwrap_or_else(|| "RoundRobin".to_string());
+
+        // 会话亲和配置
+        let conversation_affinity_enabled = env::var("CONVERSATION_AFFINITY_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let conversation_affinity_ttl = env::var("CONVERSATION_AFFINITY_TTL_SECS")
+            .unwrap_or_else(|_| "1800".to_string())
+            .parse::<u64>()
+            .unwrap_or(1800);
+        let conversation_affinity_max_entries = env::var("CONVERSATION_AFFINITY_MAX_ENTRIES")
+            .unwrap_or_else(|_| "100000".to_string())
+            .parse::<usize>()
+            .unwrap_or(100_000)
+            .max(1);
+        let conversation_affinity_user_field = env::var("CONVERSATION_AFFINITY_USER_FIELD")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+
+        // 模型回退链配置，例如 DeepSeek-V3=Qwen2.5-72B,Llama-3.3-70B;gpt-4o=gpt-4o-mini
+        let model_fallbacks = ModelFallbackConfig::parse(&env::var("MODEL_FALLBACKS").unwrap_or_default());
+
+        // 延迟SLO配置（high 优先级的请求总是放行）
+        let latency_slo_enabled = env::var("LATENCY_SLO_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+        let latency_slo_p99 = env::var("LATENCY_SLO_P99_MS")
+            .unwrap_or_else(|_| "30000".to_string())
+            .parse::<u64>()
+            .unwrap_or(30000);
+        let latency_slo_window = env::var("LATENCY_SLO_WINDOW_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .unwrap_or(60)
+            .max(1);
+        let latency_slo_breach = env::var("LATENCY_SLO_BREACH_MINUTES")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u64>()
+            .unwrap_or(5);
+        let latency_slo_recovery = env::var("LATENCY_SLO_RECOVERY_MINUTES")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse::<u64>()
+            .unwrap_or(2);
+        let latency_slo_shed_priority = env::var("LATENCY_SLO_SHED_PRIORITY")
+            .ok()
+            .filter(|s| ["low", "normal"].contains(&s.as_str()))
+            .unwrap_or_else(|| "low".to_string());
+
+        // 使用记录批量写入配置
+        let usage_batch_size = env::var("USAGE_BATCH_SIZE")
+            .unwrap_or_else(|_| "64".to_string())
+            .parse::<usize>()
+            .unwrap_or(64)
+            .max(1);
+        let usage_flush_interval = env::var("USAGE_FLUSH_INTERVAL_MS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<u64>()
+            .unwrap_or(100);
+        let usage_queue_capacity = env::var("USAGE_QUEUE_CAPACITY")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse::<usize>()
+            .unwrap_or(10_000)
+            .max(1);
+        let usage_queue_overflow = env::var("USAGE_QUEUE_OVERFLOW")
+            .map(|s| s.trim().to_lowercase())
+            .ok()
+            .filter(|s| ["block", "drop", "inline"].contains(&s.as_str()))
+            .unwrap_or_else(|| "block".to_string());
+
+        // Prometheus 指标导出配置
+        let metrics_key_top_n = env::var("METRICS_KEY_TOP_N")
+            .unwrap_or_else(|_| "50".to_string())
+            .parse::<usize>()
+            .unwrap_or(50);
+
+        // 慢请求日志配置
+        let slow_request_threshold = env::var("SLOW_REQUEST_THRESHOLD_MS")
+            .unwrap_or_else(|_| "30000".to_string())
+            .parse::<u64>()
+            .unwrap_or(30000);
+
+        // 全局在途请求上限配置
+        let max_in_flight = env::var("MAX_IN_FLIGHT_REQUESTS")
+            .unwrap_or_else(|_| "512".to_string())
+            .parse::<usize>()
+            .unwrap_or(512);
+        let in_flight_retry_after = env::var("IN_FLIGHT_RETRY_AFTER_SECS")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<u64>()
+            .unwrap_or(1);
+
+        // 请求/响应内容日志配置
+        let log_payloads = env::var("LOG_PAYLOADS")
+            .map(|s| s.trim().to_lowercase())
+            .ok()
+            .filter(|s| ["off", "truncated", "full"].contains(&s.as_str()))
+            .unwrap_or_else(|| "off".to_string());
+        let log_payloads_max_chars = env::var("LOG_PAYLOADS_MAX_CHARS")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<usize>()
+            .unwrap_or(1000);
+        let log_stream_chunks = env::var("LOG_STREAM_CHUNKS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .unwrap_or(false);
+
+        // 错误上报配置
+        let sentry_dsn = env::var("SENTRY_DSN").ok().filter(|s| !s.trim().is_empty());
+        let sentry_environment = env::var("SENTRY_ENVIRONMENT")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| format!("{:?}", environment).to_lowercase());
+
+        // 使用记录汇总配置
+        let usage_rollup_enabled = env::var("USAGE_ROLLUP_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let usage_rollup_interval = env::var("USAGE_ROLLUP_INTERVAL_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u64>()
+            .unwrap_or(300)
+            .max(1);
+        let usage_rollup_settle = env::var("USAGE_ROLLUP_SETTLE_SECS")
+            .unwrap_or_else(|_| "120".to_string())
+            .parse::<u64>()
+            .unwrap_or(120);
+
+        // 使用记录保留配置
+        let usage_retention_days = env::var("USAGE_RETENTION_DAYS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .unwrap_or(0);
+        let usage_archive_dir = env::var("USAGE_ARCHIVE_DIR")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let usage_retention_batch = env::var("USAGE_RETENTION_BATCH_SIZE")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse::<u64>()
+            .unwrap_or(5000)
+            .max(1);
+
+        // 自检诊断配置
+        let diagnostics_min_free_disk = env::var("DIAGNOSTICS_MIN_FREE_DISK_MB")
+            .unwrap_or_else(|_| "512".to_string())
+            .parse::<u64>()
+            .unwrap_or(512);
+        let diagnostics_max_clock_skew = env::var("DIAGNOSTICS_MAX_CLOCK_SKEW_SECS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u64>()
+            .unwrap_or(5);
+        let diagnostics_clock_url = env::var("DIAGNOSTICS_CLOCK_URL")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+
+        // 用量报表分享链接配置
+        let report_link_secret = env::var("REPORT_LINK_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let public_base_url = env::var("PUBLIC_BASE_URL")
+            .ok()
+            .map(|s| s.trim().trim_end_matches('/').to_string())
+            .filter(|s| !s.is_empty());
+        let report_link_max_ttl = env::var("REPORT_LINK_MAX_TTL_SECS")
+            .unwrap_or_else(|_| "2592000".to_string())
+            .parse::<u64>()
+            .unwrap_or(2592000);
+
+        // 定时任务配置（分号分隔，cron 表达式本身可能包含逗号）
+        let job_schedules = env::var("JOB_SCHEDULES")
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|entry| {
+                let (name, expr) = entry.split_once('=')?;
+                Some((name.trim().to_string(), expr.trim().to_string()))
+            })
+            .filter(|(name, expr)| !name.is_empty() && !expr.is_empty())
+            .collect::<HashMap<_, _>>();
+
+        // 客户端IP访问控制配置
+        let ip_allowlist = env::var("IP_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        let ip_denylist = env::var("IP_DENYLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
+        // 并发自适应配置
+        let concurrency_auto_tuning = env::var("CONCURRENCY_AUTO_TUNING")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let concurrency_min_limit = env::var("CONCURRENCY_MIN_LIMIT")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<u32>()
+            .unwrap_or(1)
+            .max(1);
+        let concurrency_max_limit = env::var("CONCURRENCY_MAX_LIMIT")
+            .unwrap_or_else(|_| "64".to_string())
+            .parse::<u32>()
+            .unwrap_or(64)
+            .max(concurrency_min_limit);
+        let concurrency_latency_target_ms = env::var("CONCURRENCY_LATENCY_TARGET_MS")
+            .unwrap_or_else(|_| "30000".to_string())
+            .parse::<u64>()
+            .unwrap_or(30000);
+
+        // 请求追踪采样配置
+        let trace_sample_rate = env::var("TRACE_SAMPLE_RATE")
+            .unwrap_or_else(|_| "1.0".to_string())
+            .parse::<f64>()
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        let trace_route_sample_rates = env::var("TRACE_ROUTE_SAMPLE_RATES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (route, rate) = entry.split_once('=')?;
+                let rate = rate.trim().parse::<f64>().ok()?.clamp(0.0, 1.0);
+                Some((route.trim().to_string(), rate))
+            })
+            .collect::<HashMap<_, _>>();
+        let trace_always_sample_errors = env::var("TRACE_ALWAYS_SAMPLE_ERRORS")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .unwrap_or(true);
+        let trace_force_sample_keys = env::var("TRACE_FORCE_SAMPLE_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
         // API提供商配置
         let mut api_providers = HashMap::new();
         
@@ -259,6 +1159,8 @@ impl AppConfig {
                 port,
                 log_level,
                 cors_allowed_origins,
+                tcp_enabled,
+                unix_socket,
             },
             database: DatabaseConfig {
                 url: db_url,
@@ -266,6 +1168,9 @@ impl AppConfig {
                 enable_wal,
                 enable_foreign_keys,
                 max_connections,
+                slow_query_threshold_ms,
+                busy_timeout_ms,
+                busy_retry_attempts,
             },
             auth: AuthConfig {
                 jwt_secret,
@@ -275,6 +1180,13 @@ impl AppConfig {
                     email: admin_email,
                     password: admin_password,
                 },
+                admin_api_token,
+                require_gateway_key,
+                gateway_key_default_rpm,
+                gateway_key_default_tpm,
+                gateway_key_default_monthly_budget,
+                prepaid_credit_hold,
+                gateway_key_rotation_grace_secs,
             },
             connection_pool: ConnectionPoolConfig {
                 max_size: pool_max_size,
@@ -283,11 +1195,156 @@ impl AppConfig {
             health_check: HealthCheckConfig {
                 interval: health_check_interval,
                 timeout: health_check_timeout,
+                quarantine_failure_threshold,
+            },
+            provider_cooldown: ProviderCooldownConfig {
+                failure_threshold: provider_cooldown_failures,
+                cooldown_secs: provider_cooldown_secs,
+                retry_after_max_secs: provider_retry_after_max_secs,
             },
             proxy: ProxyConfig {
                 enable: enable_proxy,
                 url: proxy_url,
             },
+            request_compression: RequestCompressionConfig {
+                gzip_threshold_bytes,
+            },
+            response_compression: ResponseCompressionConfig {
+                enabled: response_compression_enabled,
+                min_size_bytes: response_compression_min_bytes,
+            },
+            response_limits: ResponseLimitsConfig {
+                max_body_bytes: max_response_body_bytes,
+                max_stream_bytes: max_response_stream_bytes,
+            },
+            concurrency: ConcurrencyConfig {
+                auto_tuning: concurrency_auto_tuning,
+                min_limit: concurrency_min_limit,
+                max_limit: concurrency_max_limit,
+                latency_target_ms: concurrency_latency_target_ms,
+            },
+            tracing: TracingConfig {
+                default_sample_rate: trace_sample_rate,
+                route_sample_rates: trace_route_sample_rates,
+                always_sample_errors: trace_always_sample_errors,
+                force_sample_keys: trace_force_sample_keys,
+            },
+            openrouter: OpenRouterConfig {
+                referer: openrouter_referer,
+                title: openrouter_title,
+            },
+            provider_expiry: ProviderExpiryConfig {
+                alert_days: provider_expiry_alert_days,
+                check_interval_secs: provider_expiry_check_interval,
+            },
+            blocked_response: BlockedResponseConfig {
+                enabled: blocked_response_enabled,
+                template: blocked_response_template,
+            },
+            byok: ByokConfig {
+                enabled: byok_enabled,
+            },
+            provider_stats: ProviderStatsConfig {
+                refresh_interval_ms: provider_stats_refresh_interval,
+            },
+            list_cache: ListCacheConfig {
+                enabled: list_cache_enabled,
+                ttl_ms: list_cache_ttl,
+                stale_ms: list_cache_stale,
+            },
+            degradation: DegradationConfig {
+                enabled: degradation_enabled,
+                min_healthy_ratio: degradation_min_healthy_ratio,
+                read_only_window_secs: degradation_read_only_window,
+            },
+            stream_heartbeat: StreamHeartbeatConfig {
+                interval_ms: stream_heartbeat_interval,
+            },
+            stream_flush: StreamFlushConfig {
+                mode: stream_flush_mode,
+                batch_ms: stream_batch_ms,
+                batch_bytes: stream_batch_bytes,
+                flush_on_sentence: stream_flush_on_sentence,
+            },
+            upstream_warmup: UpstreamWarmupConfig {
+                enabled: upstream_warmup_enabled,
+                warm_connections: upstream_warm_connections,
+                dns_ttl_secs: upstream_dns_ttl,
+            },
+            upstream_timeouts: UpstreamTimeoutConfig {
+                connect_timeout_ms: upstream_connect_timeout,
+                request_timeout_ms: upstream_request_timeout,
+            },
+            load_balancing: LoadBalancingConfig {
+                strategy: load_balance_strategy,
+            },
+            conversation_affinity: ConversationAffinityConfig {
+                enabled: conversation_affinity_enabled,
+                ttl_secs: conversation_affinity_ttl,
+                max_entries: conversation_affinity_max_entries,
+                user_field: conversation_affinity_user_field,
+            },
+            model_fallback: model_fallbacks,
+            slow_requests: SlowRequestConfig {
+                threshold_ms: slow_request_threshold,
+            },
+            in_flight: InFlightLimitConfig {
+                max_in_flight,
+                retry_after_secs: in_flight_retry_after,
+            },
+            latency_slo: LatencySloConfig {
+                enabled: latency_slo_enabled,
+                p99_ms: latency_slo_p99,
+                window_secs: latency_slo_window,
+                breach_minutes: latency_slo_breach,
+                recovery_minutes: latency_slo_recovery,
+                shed_priority: latency_slo_shed_priority,
+            },
+            usage_recorder: UsageRecorderConfig {
+                batch_size: usage_batch_size,
+                flush_interval_ms: usage_flush_interval,
+                queue_capacity: usage_queue_capacity,
+                overflow: usage_queue_overflow,
+            },
+            ip_access: IpAccessConfig {
+                allowlist: ip_allowlist,
+                denylist: ip_denylist,
+            },
+            metrics: MetricsConfig {
+                key_top_n: metrics_key_top_n,
+            },
+            usage_rollup: UsageRollupConfig {
+                enabled: usage_rollup_enabled,
+                interval_secs: usage_rollup_interval,
+                settle_secs: usage_rollup_settle,
+            },
+            payload_logging: PayloadLoggingConfig {
+                mode: log_payloads,
+                max_chars: log_payloads_max_chars,
+                stream_chunks: log_stream_chunks,
+            },
+            error_reporting: ErrorReportingConfig {
+                dsn: sentry_dsn,
+                environment: sentry_environment,
+            },
+            usage_retention: UsageRetentionConfig {
+                retention_days: usage_retention_days,
+                archive_dir: usage_archive_dir,
+                batch_size: usage_retention_batch,
+            },
+            diagnostics: DiagnosticsConfig {
+                min_free_disk_mb: diagnostics_min_free_disk,
+                max_clock_skew_secs: diagnostics_max_clock_skew,
+                clock_reference_url: diagnostics_clock_url,
+            },
+            report_links: ReportLinksConfig {
+                signing_secret: report_link_secret,
+                public_base_url,
+                max_ttl_secs: report_link_max_ttl,
+            },
+            scheduler: SchedulerConfig {
+                schedules: job_schedules,
+            },
             api_providers,
         })
     }
@@ -8,3 +8,38 @@ pub use app::AuthConfig;
 pub use app::HealthCheckConfig;
 pub use app::ConnectionPoolConfig;
 pub use app::ApiProviderConfig;
+pub use app::RequestCompressionConfig;
+pub use app::ResponseCompressionConfig;
+pub use app::ResponseLimitsConfig;
+pub use app::ConcurrencyConfig;
+pub use app::TracingConfig;
+pub use app::PayloadLoggingConfig;
+pub use app::ErrorReportingConfig;
+pub use app::SlowRequestConfig;
+pub use app::InFlightLimitConfig;
+pub use app::ProviderCooldownConfig;
+pub use app::OpenRouterConfig;
+pub use app::ProviderExpiryConfig;
+pub use app::BlockedResponseConfig;
+pub use app::ByokConfig;
+pub use app::ProviderStatsConfig;
+pub use app::ListCacheConfig;
+pub use app::DegradationConfig;
+pub use app::StreamHeartbeatConfig;
+pub use app::UpstreamWarmupConfig;
+pub use app::UpstreamTimeoutConfig;
+pub use app::ProxyConfig;
+pub use app::LoadBalancingConfig;
+pub use app::LOAD_BALANCE_STRATEGIES;
+pub use app::ConversationAffinityConfig;
+pub use app::ModelFallbackConfig;
+pub use app::LatencySloConfig;
+pub use app::UsageRecorderConfig;
+pub use app::IpAccessConfig;
+pub use app::StreamFlushConfig;
+pub use app::MetricsConfig;
+pub use app::UsageRollupConfig;
+pub use app::UsageRetentionConfig;
+pub use app::DiagnosticsConfig;
+pub use app::ReportLinksConfig;
+pub use app::SchedulerConfig;
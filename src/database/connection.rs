@@ -1,8 +1,12 @@
-use sqlx::SqlitePool;
+use sqlx::migrate::Migrator;
+use sqlx::{ConnectOptions, SqlitePool};
+use std::time::Duration;
 use crate::config::DatabaseConfig;
 
 use anyhow::Result;
 
+/// 编译时嵌入的数据库迁移
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 /// 创建SQLite数据库连接池
 pub async fn create_sqlite_pool(config: &DatabaseConfig) -> Result<SqlitePool> {
@@ -20,7 +24,10 @@ pub async fn create_sqlite_pool(config: &DatabaseConfig) -> Result<SqlitePool> {
     // 构建连接选项
     let mut options = sqlx::sqlite::SqliteConnectOptions::new()
         .filename(&config.path)
-        .create_if_missing(true);
+        .create_if_missing(true)
+        .busy_timeout(Duration::from_millis(config.busy_timeout_ms))
+        // 所有语句超过慢查询阈值时记录警告日志
+        .log_slow_statements(log::LevelFilter::Warn, Duration::from_millis(config.slow_query_threshold_ms));
 
     // 配置WAL模式
     if config.enable_wal {
@@ -44,9 +51,7 @@ pub async fn create_sqlite_pool(config: &DatabaseConfig) -> Result<SqlitePool> {
 
 /// 运行数据库迁移
 pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
-    sqlx::migrate!("./migrations")
-        .run(pool)
-        .await?;
+    MIGRATOR.run(pool).await?;
     
     Ok(())
 }
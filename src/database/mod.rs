@@ -1,3 +1,4 @@
 pub mod connection;
+pub mod schema;
 
-pub use connection::{create_sqlite_pool, run_migrations, initialize_database};
+pub use connection::{create_sqlite_pool, run_migrations, initialize_database, MIGRATOR};
@@ -0,0 +1,180 @@
+use serde::Serialize;
+use sqlx::{FromRow, SqlitePool};
+use utoipa::ToSchema;
+
+/// 当前数据库结构（表、列、索引、外键和已执行的迁移）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DatabaseSchema {
+    /// 最新已执行的迁移版本
+    pub migration_version: Option<i64>,
+    /// 已执行的迁移
+    pub migrations: Vec<AppliedMigration>,
+    /// 表和视图
+    pub tables: Vec<TableSchema>,
+}
+
+/// 已执行的迁移
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AppliedMigration {
+    /// 迁移版本（文件名中的时间戳）
+    pub version: i64,
+    /// 迁移说明
+    pub description: String,
+    /// 执行时间
+    pub installed_on: String,
+}
+
+/// 表或视图的结构
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableSchema {
+    /// 名称
+    pub name: String,
+    /// 类型（table/view）
+    pub kind: String,
+    /// 建表语句（包含后续 ALTER TABLE 的结果）
+    pub sql: Option<String>,
+    /// 列
+    pub columns: Vec<ColumnSchema>,
+    /// 索引
+    pub indexes: Vec<IndexSchema>,
+    /// 外键
+    pub foreign_keys: Vec<ForeignKeySchema>,
+}
+
+/// 列定义
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct ColumnSchema {
+    /// 列序号
+    pub position: i64,
+    /// 列名
+    pub name: String,
+    /// 声明的类型
+    pub data_type: String,
+    /// 是否非空
+    pub not_null: bool,
+    /// 默认值表达式
+    pub default_value: Option<String>,
+    /// 在主键中的位置（0表示不属于主键）
+    pub primary_key: i64,
+}
+
+/// 索引定义
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IndexSchema {
+    /// 索引名
+    pub name: String,
+    /// 是否唯一索引
+    pub unique: bool,
+    /// 来源：c 为 CREATE INDEX 创建，u 为 UNIQUE 约束，pk 为主键
+    pub origin: String,
+    /// 是否为部分索引
+    pub partial: bool,
+    /// 索引列（按索引顺序）
+    pub columns: Vec<String>,
+}
+
+/// 外键定义
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct ForeignKeySchema {
+    /// 本表的列
+    pub column: String,
+    /// 引用的表
+    pub references_table: String,
+    /// 引用的列（为空表示引用主键）
+    pub references_column: Option<String>,
+    /// 更新时的动作
+    pub on_update: String,
+    /// 删除时的动作
+    pub on_delete: String,
+}
+
+#[derive(FromRow)]
+struct TableRow {
+    name: String,
+    kind: String,
+    sql: Option<String>,
+}
+
+#[derive(FromRow)]
+struct IndexRow {
+    name: String,
+    unique: bool,
+    origin: String,
+    partial: bool,
+}
+
+/// 读取当前数据库结构，不包含 SQLite 内部表和迁移记录表
+pub async fn describe(db: &SqlitePool) -> Result<DatabaseSchema, sqlx::Error> {
+    let migrations = sqlx::query_as::<_, AppliedMigration>(
+        "SELECT version, description, CAST(installed_on AS TEXT) AS installed_on \
+         FROM _sqlx_migrations WHERE success = 1 ORDER BY version",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let table_rows = sqlx::query_as::<_, TableRow>(
+        "SELECT name, type AS kind, sql FROM sqlite_master \
+         WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' AND name != '_sqlx_migrations' \
+         ORDER BY name",
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut tables = Vec::with_capacity(table_rows.len());
+    for table in table_rows {
+        let columns = sqlx::query_as::<_, ColumnSchema>(
+            "SELECT cid AS position, name, type AS data_type, \"notnull\" AS not_null, \
+             dflt_value AS default_value, pk AS primary_key \
+             FROM pragma_table_info(?) ORDER BY cid",
+        )
+        .bind(&table.name)
+        .fetch_all(db)
+        .await?;
+
+        let index_rows = sqlx::query_as::<_, IndexRow>(
+            "SELECT name, \"unique\", origin, partial FROM pragma_index_list(?) ORDER BY name",
+        )
+        .bind(&table.name)
+        .fetch_all(db)
+        .await?;
+        let mut indexes = Vec::with_capacity(index_rows.len());
+        for index in index_rows {
+            let columns = sqlx::query_scalar::<_, String>(
+                "SELECT COALESCE(name, '<expr>') FROM pragma_index_info(?) ORDER BY seqno",
+            )
+            .bind(&index.name)
+            .fetch_all(db)
+            .await?;
+            indexes.push(IndexSchema {
+                name: index.name,
+                unique: index.unique,
+                origin: index.origin,
+                partial: index.partial,
+                columns,
+            });
+        }
+
+        let foreign_keys = sqlx::query_as::<_, ForeignKeySchema>(
+            "SELECT \"from\" AS \"column\", \"table\" AS references_table, \"to\" AS references_column, \
+             on_update, on_delete FROM pragma_foreign_key_list(?) ORDER BY id, seq",
+        )
+        .bind(&table.name)
+        .fetch_all(db)
+        .await?;
+
+        tables.push(TableSchema {
+            name: table.name,
+            kind: table.kind,
+            sql: table.sql,
+            columns,
+            indexes,
+            foreign_keys,
+        });
+    }
+
+    Ok(DatabaseSchema {
+        migration_version: migrations.last().map(|m| m.version),
+        migrations,
+        tables,
+    })
+}
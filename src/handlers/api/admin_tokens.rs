@@ -0,0 +1,139 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::admin_token::{is_valid_scope, AdminToken, ADMIN_SCOPES};
+use crate::routes::api::AppState;
+
+/// 创建管理令牌请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateAdminTokenRequest {
+    /// 令牌用途说明
+    pub name: String,
+    /// 作用域列表，如 ["providers:read", "billing:read"]，支持 "providers:*" 和 "*"
+    pub scopes: Vec<String>,
+}
+
+/// 创建管理令牌响应（令牌明文仅返回这一次）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateAdminTokenResponse {
+    /// 令牌明文
+    pub token: String,
+    /// 令牌信息
+    pub info: AdminToken,
+}
+
+/// 管理令牌列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminTokenList {
+    /// 令牌列表
+    pub tokens: Vec<AdminToken>,
+    /// 支持的作用域
+    pub available_scopes: Vec<String>,
+}
+
+/// 创建作用域管理令牌
+#[utoipa::path(
+    post,
+    path = "/v1/admin/tokens",
+    request_body = CreateAdminTokenRequest,
+    responses(
+        (status = 201, description = "成功创建管理令牌", body = CreateAdminTokenResponse),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn create_admin_token(
+    State(state): State<AppState>,
+    Json(request): Json<CreateAdminTokenRequest>,
+) -> Response {
+    if request.scopes.is_empty() {
+        return bad_request("作用域不能为空".to_string());
+    }
+    if let Some(scope) = request.scopes.iter().find(|s| !is_valid_scope(s)) {
+        return bad_request(format!("未知的作用域: {}", scope));
+    }
+
+    match AdminToken::create(&state.db, &request.name, &request.scopes, None, None).await {
+        Ok((info, token)) => {
+            info!("管理令牌已创建: name={}, scopes={}", info.name, info.scopes);
+            (StatusCode::CREATED, Json(CreateAdminTokenResponse { token, info })).into_response()
+        }
+        Err(e) => internal_error("创建管理令牌失败", e),
+    }
+}
+
+/// 列出管理令牌
+#[utoipa::path(
+    get,
+    path = "/v1/admin/tokens",
+    responses(
+        (status = 200, description = "成功获取管理令牌列表", body = AdminTokenList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn list_admin_tokens(
+    State(state): State<AppState>,
+) -> Response {
+    match AdminToken::list(&state.db).await {
+        Ok(tokens) => (
+            StatusCode::OK,
+            Json(AdminTokenList {
+                tokens,
+                available_scopes: ADMIN_SCOPES.iter().map(|s| s.to_string()).collect(),
+            }),
+        ).into_response(),
+        Err(e) => internal_error("获取管理令牌列表失败", e),
+    }
+}
+
+/// 吊销管理令牌
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/tokens/{id}",
+    params(
+        ("id" = String, Path, description = "令牌ID")
+    ),
+    responses(
+        (status = 204, description = "成功吊销管理令牌"),
+        (status = 404, description = "令牌不存在或已吊销", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "admin"
+)]
+pub async fn revoke_admin_token(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    match AdminToken::revoke(&state.db, &id).await {
+        Ok(true) => {
+            info!("管理令牌已吊销: id={}", id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: format!("令牌不存在或已吊销: {}", id) }),
+        ).into_response(),
+        Err(e) => internal_error("吊销管理令牌失败", e),
+    }
+}
+
+fn bad_request(error: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+}
+
+fn internal_error(message: &str, e: sqlx::Error) -> Response {
+    error!("{}: {}", message, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: format!("{}: {}", message, e) }),
+    ).into_response()
+}
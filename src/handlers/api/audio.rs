@@ -0,0 +1,258 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::Response,
+};
+use bytes::{Bytes, BytesMut};
+use futures::{channel::mpsc, SinkExt};
+use futures_util::StreamExt;
+use std::net::SocketAddr;
+use tracing::{error, info};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::services::upstream_client::{create_http_client, record_request};
+use crate::models::api_usage::{ApiCallStatus, ApiUsage};
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::routes::api::AppState;
+use crate::services::TokenManager;
+use crate::utils::lb_strategy::request_strategy;
+use crate::utils::client_key::upstream_key;
+use crate::utils::response_limit::read_limited_body;
+
+// 音频转写请求的提供商模型类型
+const AUDIO_TRANSCRIPTION_MODEL_TYPE: &str = "AudioTranscription";
+// 音频转写请求的超时时间（秒）
+const AUDIO_TIMEOUT_SECS: u64 = 600;
+// 查找 model 字段时最多缓冲的字节数（与Whisper的25MB上传限制一致）
+const MAX_PREFIX_BYTES: usize = 25 * 1024 * 1024;
+// multipart中 model 字段的字段头标记
+const MODEL_FIELD_MARKER: &[u8] = b"name=\"model\"";
+
+/// 处理音频转写请求（multipart/form-data）
+/// 只缓冲到读出 model 字段为止，其余请求体直接流式转发给提供商
+#[utoipa::path(
+    post,
+    path = "/v1/audio/transcriptions",
+    request_body(content = String, content_type = "multipart/form-data", description = "Whisper兼容的表单：file、model 及其他可选参数"),
+    responses(
+        (status = 200, description = "成功转写音频（上游原始响应）"),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 503, description = "服务不可用", body = ErrorResponse),
+    ),
+    tag = "audio"
+)]
+pub async fn handle_audio_transcription(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+) -> Response {
+    let client_ip = addr.ip().to_string();
+    let gateway_key = request.extensions().get::<GatewayKeyIdentity>();
+    let gateway_key_id = gateway_key.map(|key| key.id.clone());
+    let organization = gateway_key.and_then(|key| key.organization.clone());
+    let upstream_key = match upstream_key(request.headers(), state.config.byok.enabled) {
+        Ok(upstream_key) => upstream_key,
+        Err(e) => return error_response(StatusCode::FORBIDDEN, e),
+    };
+    let key_strategy = gateway_key.and_then(|key| key.load_balance_strategy.as_deref());
+    let strategy = match request_strategy(request.headers(), key_strategy, &state.config.load_balancing.strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let content_type = match request.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+        Some(ct) if ct.starts_with("multipart/form-data") => ct.to_string(),
+        _ => return error_response(StatusCode::BAD_REQUEST, "请求必须为 multipart/form-data".to_string()),
+    };
+
+    // 读取请求体前缀直到找到 model 字段
+    let mut body = request.into_body().into_data_stream();
+    let mut prefix = BytesMut::new();
+    // 已确认不含 model 字段的前缀位置，避免每次收到数据都从头扫描
+    let mut search_from = 0;
+    let model = loop {
+        match find_bytes(&prefix[search_from..], MODEL_FIELD_MARKER) {
+            Some(pos) => {
+                search_from += pos;
+                if let Some(model) = read_field_value(&prefix[search_from..]) {
+                    break model;
+                }
+            }
+            None => search_from = prefix.len().saturating_sub(MODEL_FIELD_MARKER.len()),
+        }
+        if prefix.len() > MAX_PREFIX_BYTES {
+            return error_response(StatusCode::BAD_REQUEST, "请求体过大或缺少 model 字段".to_string());
+        }
+        match body.next().await {
+            Some(Ok(chunk)) => prefix.extend_from_slice(&chunk),
+            Some(Err(e)) => return error_response(StatusCode::BAD_REQUEST, format!("读取请求体失败: {}", e)),
+            None => return error_response(StatusCode::BAD_REQUEST, "缺少 model 字段".to_string()),
+        }
+    };
+
+    info!("收到音频转写请求, 模型: {}, 客户端IP: {}", model, client_ip);
+
+    let token_manager = match TokenManager::acquire(
+        state.provider_pool.clone(),
+        state.concurrency.clone(),
+        &model,
+        Some(AUDIO_TRANSCRIPTION_MODEL_TYPE),
+        &strategy,
+        organization.as_deref(),
+        upstream_key.as_ref(),
+    ).await {
+        Some(manager) => manager,
+        None => {
+            error!("音频转写请求：无法获取可用的提供商");
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("未找到支持模型 {} 的提供商", model),
+            );
+        }
+    };
+
+    let client = match create_http_client(state.config.proxy.enable, &state.config.proxy.url, AUDIO_TIMEOUT_SECS) {
+        Ok(client) => client,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    // 先发送已缓冲的前缀，再把剩余的请求体原样转发
+    let (mut sender, receiver) = mpsc::channel::<Result<Bytes, std::io::Error>>(8);
+    tokio::spawn(async move {
+        if sender.send(Ok(prefix.freeze())).await.is_err() {
+            return;
+        }
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(std::io::Error::other);
+            let failed = chunk.is_err();
+            if sender.send(chunk).await.is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    let mut request_builder = client
+        .post(&token_manager.provider.base_url)
+        .header("Content-Type", content_type);
+    if let Some((auth_name, auth_value)) = token_manager.provider.auth_header() {
+        request_builder = request_builder.header(auth_name, auth_value);
+    }
+    for (name, value) in token_manager.provider.static_headers(&state.config.openrouter) {
+        request_builder = request_builder.header(name, value);
+    }
+    record_request(&token_manager.provider.base_url);
+    let request_start = std::time::Instant::now();
+    let response = request_builder
+        .body(reqwest::Body::wrap_stream(receiver))
+        .send()
+        .await;
+
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            error!("音频转写请求发送失败: {}", e);
+            record_usage(&state, &token_manager, &model, 0.0, ApiCallStatus::Error, &client_ip, gateway_key_id.as_deref()).await;
+            return error_response(StatusCode::BAD_GATEWAY, format!("请求失败: {}", e));
+        }
+    };
+
+    let status = response.status();
+    token_manager.record_upstream_response(&response);
+    let upstream_content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+
+    let bytes = match read_limited_body(response, state.config.response_limits.max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("音频转写请求：{}", e);
+            record_usage(&state, &token_manager, &model, 0.0, ApiCallStatus::Error, &client_ip, gateway_key_id.as_deref()).await;
+            return error_response(StatusCode::BAD_GATEWAY, e);
+        }
+    };
+
+    if !status.is_success() {
+        error!("音频转写请求：API调用失败, 状态码: {}, 提供商: {}", status, token_manager.provider.base_url);
+        record_usage(&state, &token_manager, &model, 0.0, ApiCallStatus::Error, &client_ip, gateway_key_id.as_deref()).await;
+        return error_response(StatusCode::BAD_GATEWAY, format!("API调用失败，状态码: {}", status));
+    }
+
+    token_manager.record_success(request_start.elapsed());
+
+    let audio_seconds = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .map(|json| extract_audio_seconds(&json))
+        .unwrap_or(0.0);
+    record_usage(&state, &token_manager, &model, audio_seconds, ApiCallStatus::Success, &client_ip, gateway_key_id.as_deref()).await;
+
+    info!(
+        "音频转写请求完成, 提供商: {}, 音频时长: {:.1}s",
+        token_manager.provider.base_url, audio_seconds
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", upstream_content_type)
+        .body(Body::from(bytes))
+        .unwrap()
+}
+
+// 从字段头开始读取multipart文本字段的值（字段需完整出现在缓冲区中）
+fn read_field_value(part: &[u8]) -> Option<String> {
+    let headers_end = find_bytes(part, b"\r\n\r\n")? + 4;
+    let value_end = headers_end + find_bytes(&part[headers_end..], b"\r\n--")?;
+    let value = String::from_utf8_lossy(&part[headers_end..value_end]).trim().to_string();
+    Some(value)
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// 从响应中读取音频时长：优先使用 usage.seconds，其次 verbose_json 的 duration
+fn extract_audio_seconds(json: &serde_json::Value) -> f64 {
+    json.get("usage")
+        .and_then(|usage| usage.get("seconds"))
+        .or_else(|| json.get("duration"))
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0)
+}
+
+// 记录音频转写请求的使用情况
+async fn record_usage(
+    state: &AppState,
+    token_manager: &TokenManager,
+    model: &str,
+    audio_seconds: f64,
+    status: ApiCallStatus,
+    client_ip: &str,
+    gateway_key_id: Option<&str>,
+) {
+    let mut usage = ApiUsage::new(
+        token_manager.provider.api_key.clone(),
+        model.to_string(),
+        0,
+        0,
+        status,
+        Some(client_ip.to_string()),
+        None,
+    );
+    usage.audio_seconds = audio_seconds;
+    usage.own_key_id = token_manager.provider.own_key_id();
+    usage.gateway_key_id = gateway_key_id.map(str::to_string);
+    usage.organization = token_manager.provider.organization.clone();
+
+    state.usage_recorder.record(usage).await;
+}
+
+// 构建错误响应
+fn error_response(status: StatusCode, error: String) -> Response {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(&ErrorResponse { error }).unwrap()))
+        .unwrap()
+}
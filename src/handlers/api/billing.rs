@@ -0,0 +1,347 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::billing_ledger::{LedgerEntry, LedgerEntryType};
+use crate::models::gateway_key::GatewayKey;
+use crate::models::gateway_key_spend::GatewayKeySpend;
+use crate::models::model_pricing::ModelPricing;
+use crate::models::prepaid_key::PrepaidKey;
+use crate::routes::api::AppState;
+use crate::utils::redact::redact;
+
+/// 添加账本贷记/调整请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AddLedgerEntryRequest {
+    /// 计费账户（网关密钥ID或组织标识），预付费网关密钥的可用余额同时调整
+    pub account: String,
+    /// 金额：充值必须为正数，调整可正可负
+    pub amount: f64,
+    /// 条目类型（Credit/Adjustment，可选，默认Credit）
+    #[serde(default = "default_entry_type")]
+    pub entry_type: LedgerEntryType,
+    /// 货币单位（可选，默认USD）
+    pub currency: Option<String>,
+    /// 说明（可选）
+    pub description: Option<String>,
+}
+
+fn default_entry_type() -> LedgerEntryType { LedgerEntryType::Credit }
+
+/// 账本查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LedgerQuery {
+    /// 起始时间（包含）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含）
+    pub to: Option<DateTime<Utc>>,
+    /// 输出格式（json/csv，默认json）
+    pub format: Option<String>,
+}
+
+/// 账本条目列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LedgerEntryList {
+    /// 账本条目
+    pub entries: Vec<LedgerEntry>,
+    /// 条目数量
+    pub count: usize,
+}
+
+/// 为账户添加充值或调整
+#[utoipa::path(
+    post,
+    path = "/v1/billing/ledger",
+    request_body = AddLedgerEntryRequest,
+    responses(
+        (status = 201, description = "成功添加账本条目", body = LedgerEntry),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn add_ledger_entry(
+    State(state): State<AppState>,
+    Json(request): Json<AddLedgerEntryRequest>,
+) -> Response {
+    let invalid = match request.entry_type {
+        LedgerEntryType::Debit => Some("借记条目只能由请求计费自动生成".to_string()),
+        LedgerEntryType::Credit if request.amount <= 0.0 => Some("充值金额必须大于0".to_string()),
+        LedgerEntryType::Adjustment if request.amount == 0.0 => Some("调整金额不能为0".to_string()),
+        _ if request.account.trim().is_empty() => Some("计费账户不能为空".to_string()),
+        _ => None,
+    };
+    if let Some(error) = invalid {
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response();
+    }
+
+    let entry = LedgerEntry::new(
+        request.account.trim(),
+        request.entry_type,
+        request.amount,
+        request.currency.as_deref().unwrap_or("USD"),
+        request.description,
+        None,
+    );
+
+    let result = async {
+        let mut tx = state.db.begin().await?;
+        entry.insert(&mut *tx).await?;
+        PrepaidKey::apply(&mut *tx, &entry.account, entry.amount).await?;
+        tx.commit().await
+    }.await;
+
+    match result {
+        Ok(_) => {
+            info!("账本条目已添加: account={}, type={}, amount={}", redact(&entry.account), entry.entry_type, entry.amount);
+            (StatusCode::CREATED, Json(entry)).into_response()
+        }
+        Err(e) => {
+            error!("添加账本条目失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("添加账本条目失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 查询账户余额
+#[utoipa::path(
+    get,
+    path = "/v1/billing/accounts/{account}/balance",
+    params(
+        ("account" = String, Path, description = "计费账户")
+    ),
+    responses(
+        (status = 200, description = "成功获取账户余额", body = LedgerBalance),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn get_account_balance(
+    State(state): State<AppState>,
+    Path(account): Path<String>,
+) -> Response {
+    match LedgerEntry::balance(&state.db, &account).await {
+        Ok(balance) => (StatusCode::OK, Json(balance)).into_response(),
+        Err(e) => {
+            error!("查询账户余额失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("查询账户余额失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 查询账户账本，format=csv 时导出CSV
+#[utoipa::path(
+    get,
+    path = "/v1/billing/accounts/{account}/ledger",
+    params(
+        ("account" = String, Path, description = "计费账户"),
+        LedgerQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取账本条目", body = LedgerEntryList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn get_account_ledger(
+    State(state): State<AppState>,
+    Path(account): Path<String>,
+    Query(query): Query<LedgerQuery>,
+) -> Response {
+    let entries = match LedgerEntry::list(&state.db, &account, query.from, query.to).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("查询账本失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("查询账本失败: {}", e) }),
+            ).into_response();
+        }
+    };
+
+    if query.format.as_deref() == Some("csv") {
+        return (
+            StatusCode::OK,
+            [
+                ("Content-Type", "text/csv; charset=utf-8".to_string()),
+                ("Content-Disposition", format!("attachment; filename=\"ledger-{}.csv\"", account)),
+            ],
+            entries_to_csv(&entries),
+        ).into_response();
+    }
+
+    let count = entries.len();
+    (StatusCode::OK, Json(LedgerEntryList { entries, count })).into_response()
+}
+
+/// 预付额度充值请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LoadCreditRequest {
+    /// 充值金额（必须为正数）
+    pub amount: f64,
+    /// 货币单位（可选，默认USD）
+    pub currency: Option<String>,
+    /// 说明（可选）
+    pub description: Option<String>,
+}
+
+/// 为网关密钥加载预付额度，首次加载后该密钥的请求受可用余额限制
+#[utoipa::path(
+    post,
+    path = "/v1/billing/keys/{id}/credits",
+    params(
+        ("id" = String, Path, description = "网关密钥ID")
+    ),
+    request_body = LoadCreditRequest,
+    responses(
+        (status = 201, description = "成功加载预付额度", body = PrepaidKey),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 404, description = "网关密钥不存在或已吊销", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn load_prepaid_credit(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<LoadCreditRequest>,
+) -> Response {
+    if request.amount <= 0.0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: "充值金额必须大于0".to_string() }),
+        ).into_response();
+    }
+    match GatewayKey::find(&state.db, &id).await {
+        Ok(Some(key)) if key.revoked_at.is_none() => {}
+        Ok(_) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse { error: format!("网关密钥不存在或已吊销: {}", id) }),
+            ).into_response();
+        }
+        Err(e) => {
+            error!("查询网关密钥失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("查询网关密钥失败: {}", e) }),
+            ).into_response();
+        }
+    }
+
+    let entry = LedgerEntry::new(
+        &id,
+        LedgerEntryType::Credit,
+        request.amount,
+        request.currency.as_deref().unwrap_or("USD"),
+        Some(request.description.unwrap_or_else(|| "预付额度充值".to_string())),
+        None,
+    );
+
+    match PrepaidKey::load(&state.db, &id, &entry).await {
+        Ok(prepaid) => {
+            info!("预付额度已加载: gateway_key_id={}, amount={}, balance={}", id, request.amount, prepaid.balance);
+            (StatusCode::CREATED, Json(prepaid)).into_response()
+        }
+        Err(e) => {
+            error!("加载预付额度失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("加载预付额度失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 请求成功后按定价计算成本并写入使用记录，网关密钥的请求同时按密钥ID记录借记
+/// （预付费密钥同时扣除可用余额），账本中不保存任何密钥明文；未配置定价时跳过
+/// 返回计算出的成本
+pub async fn charge_usage(
+    state: &AppState,
+    gateway_key_id: Option<&str>,
+    provider_api_key: &str,
+    model: &str,
+    tokens: (u32, u32),
+    usage_id: &str,
+) -> Option<f64> {
+    let lookup = state.db_metrics.run("model_pricing.for_provider_key", || {
+        ModelPricing::get_price_for_provider_key(&state.db, provider_api_key, model)
+    });
+    let pricing = match lookup.await {
+        Ok(Some(pricing)) => pricing,
+        Ok(None) => {
+            info!("模型 {} 未配置定价，跳过计费", model);
+            return None;
+        }
+        Err(e) => {
+            error!("查询模型定价失败: model={}, 错误={}", model, e);
+            return None;
+        }
+    };
+
+    let cost = pricing.calculate_cost(tokens.0, tokens.1);
+    state.usage_recorder.set_cost(usage_id, cost).await;
+
+    // 未识别出网关密钥的请求没有计费账户
+    let Some(account) = gateway_key_id else {
+        return Some(cost);
+    };
+
+    // 网关密钥的请求计入密钥当月花费（月度预算）
+    let spend = state.db_metrics.run("gateway_key_spend.add", || GatewayKeySpend::add(&state.db, account, cost));
+    if let Err(e) = spend.await {
+        error!("记录网关密钥花费失败: usage_id={}, 错误={}", usage_id, e);
+    }
+
+    let debit = state.db_metrics.run("billing_ledger.debit", || {
+        LedgerEntry::record_usage_debit(&state.db, account, &pricing, model, tokens, usage_id)
+    });
+    match debit.await {
+        Ok(entry) => info!("已记录请求借记: account={}, model={}, amount={}", redact(account), model, entry.amount),
+        Err(e) => error!("记录请求借记失败: account={}, 错误={}", redact(account), e),
+    }
+    Some(cost)
+}
+
+// 将账本条目导出为CSV
+fn entries_to_csv(entries: &[LedgerEntry]) -> String {
+    let mut csv = String::from("id,account,entry_type,amount,currency,description,usage_id,created_at\n");
+    for entry in entries {
+        let fields = [
+            entry.id.clone(),
+            entry.account.clone(),
+            entry.entry_type.clone(),
+            entry.amount.to_string(),
+            entry.currency.clone(),
+            entry.description.clone().unwrap_or_default(),
+            entry.usage_id.clone().unwrap_or_default(),
+            entry.created_at.to_rfc3339(),
+        ];
+        let line = fields.iter().map(|f| escape_csv_field(f)).collect::<Vec<_>>().join(",");
+        csv.push_str(&line);
+        csv.push('\n');
+    }
+    csv
+}
+
+// CSV字段转义：包含逗号、引号或换行时加引号
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
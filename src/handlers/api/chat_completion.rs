@@ -1,42 +1,118 @@
 use axum::{
     extract::{Json, State, ConnectInfo},
-    http::StatusCode,
+    Extension,
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
 };
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::{time::Duration, net::SocketAddr};
-use tokio::sync::Mutex;
-use tracing::{error, info};
-use sqlx::SqlitePool;
+use tracing::{error, info, trace, warn};
 use anyhow::Result;
 use crate::routes::api::AppState;
 use bytes::Bytes;
 use futures_util::{Stream, StreamExt};
 use axum::body::Body;
 use std::pin::Pin;
-use crate::services::{ProviderInfo, TokenManager};
-use crate::services::provider_pool::ProviderPoolState;
+use crate::services::{sse_error_event, ProviderInfo, SseDecoder, SseEvent, StreamPacer, TokenManager};
+use crate::services::{bedrock, model_fallback, provider_cooldown};
+use crate::services::error_reporter::{self, ErrorEvent, Level};
+use crate::services::provider_errors::{self, ProviderErrorCategory};
+use crate::services::slow_requests::CompletedRequest;
+use crate::services::upstream_client;
+use crate::services::stream_heartbeat::{heartbeat_interval, with_heartbeat};
+use crate::services::stream_flush::{with_flush_policy, FlushPolicy};
+use crate::services::blocked_content::{self, CONTENT_BLOCKED_ERROR, CONTENT_FILTER_FINISH_REASON};
+use crate::config::{AppConfig, BlockedResponseConfig};
+use crate::utils::lb_strategy::request_strategy;
+use crate::utils::client_key::{upstream_key, UpstreamKey};
+use crate::utils::compression::encode_json_body;
+use crate::utils::response_limit::{read_limited_body, StreamSizeLimit};
+use crate::utils::sigv4::{sign_request, AwsCredentials};
+use crate::utils::lenient_json::parse_lenient;
+use crate::utils::payload_log;
+use crate::utils::redact::redact;
+use crate::models::data_quality_event::DataQualityEvent;
+use crate::models::{ApiCallStatus, ApiUsage, FallbackResponse};
+use crate::handlers::api::billing::charge_usage;
+use crate::handlers::api::usage_echo::{self, UsageEcho};
+use crate::handlers::api::strict_openai::StrictConformance;
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
 use utoipa::ToSchema;
-use crate::models::api_usage::{ApiUsage, ApiCallStatus};
 use uuid;
 use chrono;
 
-// 配置常量
-const RETRY_DELAY: Duration = Duration::from_secs(1);        // 重试延迟
+// 流式响应体类型
+type SseStream = Pin<Box<dyn Stream<Item = Result<Bytes, Box<dyn std::error::Error + Send + Sync>>> + Send>>;
+
+// 兜底回复的标记：非流式响应的响应头，以及响应体（流式为数据块）中的字段
+const FALLBACK_HEADER: &str = "X-Gateway-Fallback";
+const FALLBACK_FIELD: &str = "x_gateway_fallback";
 
 // OpenAI格式的消息
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Message {
     /// 消息角色（system/user/assistant）
     pub role: String,
-    /// 消息内容
-    pub content: String,
+    /// 消息内容（字符串或多模态内容片段数组）
+    pub content: MessageContent,
     /// 拒绝原因（Grok API 特有，可选）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refusal: Option<String>,
 }
 
+/// 消息内容：纯文本，或包含文本和图片的内容片段数组（视觉模型）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum MessageContent {
+    /// 纯文本内容
+    Text(String),
+    /// 内容片段数组
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// 是否没有任何内容
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(text) => text.is_empty(),
+            Self::Parts(parts) => parts.is_empty(),
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// 多模态内容片段
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// 文本片段
+    Text {
+        /// 文本内容
+        text: String,
+    },
+    /// 图片片段
+    ImageUrl {
+        /// 图片地址
+        image_url: ImageUrl,
+    },
+}
+
+/// 图片地址（http(s) URL 或 data:image/...;base64 形式）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImageUrl {
+    /// 图片URL
+    pub url: String,
+    /// 图片解析精度（auto/low/high），可选
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
 // 请求格式
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ChatCompletionRequest {
@@ -44,12 +120,34 @@ pub struct ChatCompletionRequest {
     pub model: Option<String>,
     /// 对话消息列表
     pub messages: Vec<Message>,
-    /// 最大生成token数，可选，默认1024
+    /// 最大生成token数，可选，未设置时使用提供商的默认值
     pub max_tokens: Option<u32>,
-    /// 温度参数，可选，默认0.7
+    /// 温度参数，可选，未设置时使用提供商的默认值
     pub temperature: Option<f32>,
     /// 是否使用流式响应，可选，默认false
     pub stream: Option<bool>,
+    /// top_p采样，可选
+    pub top_p: Option<f32>,
+    /// 停止序列（字符串或字符串数组），可选
+    #[schema(value_type = Option<Object>)]
+    pub stop: Option<serde_json::Value>,
+    /// 频率惩罚，可选
+    pub frequency_penalty: Option<f32>,
+    /// 存在惩罚，可选
+    pub presence_penalty: Option<f32>,
+    /// 生成数量，可选
+    pub n: Option<u32>,
+    /// 是否返回对数概率，可选
+    pub logprobs: Option<bool>,
+    /// 每个位置返回的候选token数量（需开启 logprobs），可选
+    pub top_logprobs: Option<u32>,
+    /// 随机种子，可选
+    pub seed: Option<i64>,
+    /// 终端用户标识，可选
+    pub user: Option<String>,
+    /// 输出格式（如 {"type": "json_object"} 或 {"type": "json_schema", ...}），可选
+    #[schema(value_type = Option<Object>)]
+    pub response_format: Option<serde_json::Value>,
 }
 
 // 通用 API 请求格式（支持 DeepSeek、Grok 等）
@@ -59,8 +157,40 @@ struct ApiRequest {
     messages: Vec<Message>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
-    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_logprobs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+}
+
+impl ApiRequest {
+    // 是否要求结构化输出（json_object / json_schema），type 为 text 时不要求
+    fn requires_structured_output(&self) -> bool {
+        self.response_format
+            .as_ref()
+            .and_then(|format| format.get("type"))
+            .and_then(|t| t.as_str())
+            .is_some_and(|t| t != "text")
+    }
 }
 
 // 通用 API 响应格式（支持 DeepSeek、Grok 等）
@@ -75,6 +205,186 @@ struct ApiResponse {
     // Grok API 特有字段（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
     system_fingerprint: Option<String>,
+    // 引用来源（Grok 实时搜索、Perplexity 等，统一为 {url, title} 格式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    citations: Option<Vec<Citation>>,
+    // Perplexity 风格的搜索结果，合并到 citations 后不再单独返回
+    #[serde(default, skip_serializing)]
+    search_results: Option<Vec<Citation>>,
+}
+
+/// 引用来源
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(from = "RawCitation")]
+pub struct Citation {
+    /// 来源URL
+    pub url: String,
+    /// 来源标题（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+// 上游返回的引用来源：纯URL字符串或包含url/title的对象
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum RawCitation {
+    Url(String),
+    Object {
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+    },
+}
+
+impl From<RawCitation> for Citation {
+    fn from(raw: RawCitation) -> Self {
+        match raw {
+            RawCitation::Url(url) => Self { url, title: None },
+            RawCitation::Object { url, title } => Self { url, title },
+        }
+    }
+}
+
+impl ApiResponse {
+    // 合并 citations 与 search_results，并在上游未返回时补全 num_sources_used
+    fn normalize_citations(&mut self) {
+        if let Some(results) = self.search_results.take() {
+            let citations = self.citations.get_or_insert_with(Vec::new);
+            for result in results {
+                match citations.iter_mut().find(|c| c.url == result.url) {
+                    Some(existing) => {
+                        if existing.title.is_none() {
+                            existing.title = result.title;
+                        }
+                    }
+                    None => citations.push(result),
+                }
+            }
+        }
+
+        if self.usage.num_sources_used.is_none() {
+            if let Some(citations) = self.citations.as_ref().filter(|c| !c.is_empty()) {
+                self.usage.num_sources_used = Some(citations.len() as u32);
+            }
+        }
+    }
+
+    // 上游以 content_filter 结束且没有返回内容时，填充拦截提示
+    fn fill_blocked_content(&mut self, config: &BlockedResponseConfig, model: &str) {
+        for choice in &mut self.choices {
+            if choice.finish_reason == CONTENT_FILTER_FINISH_REASON && choice.message.content.is_empty() {
+                choice.message.content = blocked_content::render(config, model, CONTENT_FILTER_FINISH_REASON).into();
+            }
+        }
+    }
+
+    // 内容被拦截或全部提供商失败时返回的模板补全
+    fn canned(model: &str, content: String, finish_reason: &str) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: model.to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: content.into(),
+                    refusal: None,
+                },
+                finish_reason: finish_reason.to_string(),
+                logprobs: None,
+            }],
+            usage: Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                prompt_tokens_details: None,
+                completion_tokens_details: None,
+                num_sources_used: None,
+            },
+            system_fingerprint: None,
+            citations: None,
+            search_results: None,
+        }
+    }
+}
+
+// 流式模板补全：单个数据块加结束标记；兜底回复带 x_gateway_fallback 标记
+fn canned_stream_events(model: &str, content: &str, finish_reason: &str, fallback: bool) -> String {
+    let mut chunk = serde_json::json!({
+        "id": format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+        "object": "chat.completion.chunk",
+        "created": chrono::Utc::now().timestamp(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "delta": {"role": "assistant", "content": content},
+            "finish_reason": finish_reason,
+        }],
+    });
+    if fallback {
+        chunk[FALLBACK_FIELD] = true.into();
+    }
+    format!("data: {}\n\ndata: [DONE]\n\n", chunk)
+}
+
+// 流式事件是否携带内容（文本、推理内容或工具调用），用于统计首字延迟
+fn event_has_content(event: &SseEvent) -> bool {
+    let Some(data) = event.data.as_deref().filter(|data| *data != "[DONE]") else {
+        return false;
+    };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(data) else {
+        return false;
+    };
+    json.get("choices")
+        .and_then(|choices| choices.as_array())
+        .is_some_and(|choices| {
+            choices.iter().filter_map(|choice| choice.get("delta")).any(|delta| {
+                ["content", "reasoning_content"]
+                    .iter()
+                    .any(|field| delta.get(*field).and_then(|v| v.as_str()).is_some_and(|text| !text.is_empty()))
+                    || delta.get("tool_calls").and_then(|v| v.as_array()).is_some_and(|calls| !calls.is_empty())
+            })
+        })
+}
+
+// 从流式事件中提取usage信息
+fn event_usage(event: &SseEvent) -> Option<Usage> {
+    let data = event.data.as_deref()?;
+    if !data.contains("\"usage\"") {
+        return None;
+    }
+
+    let json = match serde_json::from_str::<serde_json::Value>(data) {
+        Ok(json) => json,
+        Err(e) => {
+            info!("流式请求：解析JSON失败: {}", e);
+            return None;
+        }
+    };
+    let usage = json.get("usage")?;
+    let (prompt, completion, total) = (
+        usage.get("prompt_tokens").and_then(|v| v.as_u64())?,
+        usage.get("completion_tokens").and_then(|v| v.as_u64())?,
+        usage.get("total_tokens").and_then(|v| v.as_u64())?,
+    );
+    // 引用来源数量：优先使用上游统计，否则按 citations 计数
+    let num_sources_used = usage.get("num_sources_used")
+        .and_then(|v| v.as_u64())
+        .or_else(|| json.get("citations")
+            .and_then(|v| v.as_array())
+            .map(|c| c.len() as u64))
+        .map(|n| n as u32);
+
+    Some(Usage {
+        prompt_tokens: prompt as u32,
+        completion_tokens: completion as u32,
+        total_tokens: total as u32,
+        prompt_tokens_details: None,
+        completion_tokens_details: None,
+        num_sources_used,
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -82,20 +392,24 @@ struct Choice {
     index: u32,
     message: Message,
     finish_reason: String,
+    // 请求开启 logprobs 时上游返回的对数概率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    logprobs: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
-struct Usage {
-    prompt_tokens: u32,
-    completion_tokens: u32,
-    total_tokens: u32,
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
     // Grok API 扩展字段（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
-    prompt_tokens_details: Option<serde_json::Value>,
+    pub prompt_tokens_details: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    completion_tokens_details: Option<serde_json::Value>,
+    pub completion_tokens_details: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    num_sources_used: Option<u32>,
+    pub num_sources_used: Option<u32>,
 }
 
 // 我们的API响应格式
@@ -123,6 +437,8 @@ pub struct ErrorResponse {
     request_body = ChatCompletionRequest,
     responses(
         (status = 200, description = "成功处理聊天请求", body = ChatCompletionResponse),
+        (status = 400, description = "所选提供商不支持 response_format", body = ErrorResponse),
+        (status = 403, description = "未启用自带上游密钥模式", body = ErrorResponse),
         (status = 503, description = "服务不可用", body = ErrorResponse),
     ),
     tag = "chat"
@@ -130,256 +446,464 @@ pub struct ErrorResponse {
 pub async fn handle_chat_completion(
     State(state): State<AppState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    Json(request): Json<ChatCompletionRequest>,
+    gateway_key: Option<Extension<GatewayKeyIdentity>>,
+    headers: HeaderMap,
+    Json(mut request): Json<ChatCompletionRequest>,
 ) -> Response {
-    let model_name = request.model.clone().unwrap_or_else(|| "DeepSeek-V3".to_string());
+    let mut model_name = request.model.clone().unwrap_or_else(|| "DeepSeek-V3".to_string());
     let client_ip = addr.ip().to_string();
+    // 客户端自带的上游密钥（BYOK）
+    let upstream_key = match upstream_key(&headers, state.config.byok.enabled) {
+        Ok(upstream_key) => upstream_key,
+        Err(e) => return error_response(StatusCode::FORBIDDEN, e),
+    };
 
     info!(
-        "收到聊天完成请求, 模型: {}, 消息数: {}, 流式请求: {}, 客户端IP: {}", 
+        "收到聊天完成请求, 模型: {}, 消息数: {}, 流式请求: {}, 客户端IP: {}, 网关密钥: {}", 
         model_name,
         request.messages.len(),
         request.stream.unwrap_or(false),
-        client_ip
+        client_ip,
+        gateway_key.as_ref().map(|key| key.name.as_str()).unwrap_or("-")
     );
 
+    // 负载均衡策略：X-LB-Strategy 请求头优先，其次网关密钥的默认策略
+    let key_strategy = gateway_key.as_ref().and_then(|key| key.load_balance_strategy.as_deref());
+    let strategy = match request_strategy(&headers, key_strategy, &state.config.load_balancing.strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    // 按请求头或网关密钥设置在 usage 中回显成本和累计花费
+    let echo = UsageEcho::from_request(&headers, gateway_key.as_deref());
+    let gateway_key = gateway_key.map(|Extension(key)| key);
+
+    // 请求的模型没有可用提供商时改用回退链上的模型；自带上游密钥的请求不回退
+    let organization = gateway_key.as_ref().and_then(|key| key.organization.as_deref());
+    let fallback_model = match upstream_key {
+        Some(_) => None,
+        None => model_fallback::resolve(&state.config.model_fallback, &state.provider_pool, &model_name, None, organization).await,
+    };
+    if let Some(fallback) = &fallback_model {
+        model_name = fallback.clone();
+        request.model = Some(fallback.clone());
+    }
+    // 携带会话标识时优先使用该会话之前的提供商；自带上游密钥的请求不参与
+    let caller = gateway_key.as_ref().map_or(client_ip.as_str(), |key| key.id.as_str());
+    let affinity = upstream_key
+        .is_none()
+        .then(|| state.conversation_affinity.key(&headers, request.user.as_deref(), caller, &model_name))
+        .flatten();
+
     // 根据请求中的 stream 参数决定使用哪种响应模式
-    if request.stream.unwrap_or(false) {
-        handle_stream_response(state, request, client_ip).await
+    let mut response = if request.stream.unwrap_or(false) {
+        handle_stream_response(state, request, client_ip, gateway_key, upstream_key, echo, affinity, strategy).await
     } else {
-        handle_normal_response(state, request, client_ip).await.into_response()
+        handle_normal_response(state, request, client_ip, gateway_key, upstream_key, echo, affinity, strategy).await.into_response()
+    };
+    // 使用了回退模型时在响应头中标明实际使用的模型
+    if let Some(value) = fallback_model.and_then(|model| HeaderValue::from_str(&model).ok()) {
+        response.headers_mut().insert(model_fallback::MODEL_USED_HEADER, value);
     }
+    response
 }
 
+// 流式请求最多尝试的提供商数量
+const STREAM_FAILOVER_ATTEMPTS: usize = 3;
+
 // 处理流式响应
-async fn handle_stream_response(state: AppState, request: ChatCompletionRequest, client_ip: String) -> Response {
+#[allow(clippy::too_many_arguments)]
+async fn handle_stream_response(
+    state: AppState,
+    request: ChatCompletionRequest,
+    client_ip: String,
+    gateway_key: Option<GatewayKeyIdentity>,
+    upstream_key: Option<UpstreamKey>,
+    echo: Option<UsageEcho>,
+    affinity: Option<String>,
+    strategy: String,
+) -> Response {
     use std::error::Error as StdError;
     
-    let stream: Pin<Box<dyn Stream<Item = Result<Bytes, Box<dyn StdError + Send + Sync>>> + Send>> = Box::pin(async_stream::try_stream! {
+    let received = std::time::Instant::now();
+    let gateway_key_id = gateway_key.as_ref().map(|key| key.id.clone());
+    // 网关密钥开启严格模式时按 OpenAI 格式修复流式数据块
+    let strict = gateway_key.as_ref().is_some_and(|key| key.strict_openai);
+    // 流式输出节流按调用方的网关密钥设置，未设置时使用提供商的设置
+    let pacing_tps = gateway_key.as_ref().and_then(|key| key.stream_pacing_tps);
+    // 组织的网关密钥只使用绑定到该组织的提供商
+    let organization = gateway_key.and_then(|key| key.organization);
+    let heartbeat = heartbeat_interval(state.config.stream_heartbeat.interval_ms);
+    let flush_policy = FlushPolicy::from_config(&state.config.stream_flush);
+    let stream: SseStream = Box::pin(async_stream::try_stream! {
         let model_name = request.model.clone().unwrap_or_else(|| "DeepSeek-V3".to_string());
-        let token_manager = match TokenManager::new(state.provider_pool.clone(), &model_name, "RoundRobin").await {
-            Some(manager) => {
-                info!("流式请求：选择提供商成功\nURL: {}\nAPI Key: {}", 
-                    manager.provider.base_url,
-                    manager.provider.api_key
-                );
-                manager
-            },
-            None => {
-                error!("流式请求：无法获取可用的提供商");
-                yield Bytes::from("data: {\"error\":\"无法获取可用的提供商\"}\n\n");
-                return;
-            }
-        };
-
         // 构建 API 请求
         let api_request = build_api_request(&request, &model_name, true);
-        
-        // 消息已经在 api_request 中处理，无需额外转换
 
-        info!("流式请求：准备发送请求\nURL: {}\n请求体: {}", 
-            token_manager.provider.base_url,
-            serde_json::to_string_pretty(&api_request).unwrap_or_default()
-        );
+        // 尚未向客户端输出内容时，上游连接失败、限流或5xx会换下一个提供商重试
+        let mut tried: Vec<String> = Vec::new();
+        let mut last_error = "无法获取可用的提供商".to_string();
+        // 不支持 response_format 的提供商直接跳过；没有提供商收到请求时返回该错误
+        let mut structured_unsupported: Option<String> = None;
+        let mut dispatched = false;
+        'providers: while tried.len() < STREAM_FAILOVER_ATTEMPTS {
+            // 第一次尝试优先使用会话之前的提供商
+            let preferred = match affinity.as_deref().filter(|_| tried.is_empty()) {
+                Some(key) => preferred_provider(&state, key, &model_name, organization.as_deref()).await,
+                None => None,
+            };
+            let acquired = match preferred {
+                Some(manager) => Some(manager),
+                None => TokenManager::acquire_excluding(
+                    state.provider_pool.clone(),
+                    state.concurrency.clone(),
+                    &model_name,
+                    None,
+                    &strategy,
+                    organization.as_deref(),
+                    upstream_key.as_ref(),
+                    &tried,
+                ).await,
+            };
+            let token_manager = match acquired {
+                Some(manager) => {
+                    info!("流式请求：选择提供商成功\nURL: {}\nAPI Key: {}", 
+                        manager.provider.base_url,
+                        redact(&manager.provider.api_key)
+                    );
+                    manager
+                },
+                None => {
+                    error!("流式请求：无法获取可用的提供商，已尝试 {} 个", tried.len());
+                    break;
+                }
+            };
+            tried.push(token_manager.provider.limit_key());
 
-        info!("流式请求：准备创建HTTP客户端");
-        info!("代理配置：启用={}, URL={}", state.config.proxy.enable, state.config.proxy.url);
-        
-        let client = create_http_client(
-            state.config.proxy.enable, 
-            &state.config.proxy.url, 
-            300  // 流式请求需要更长的超时时间
-        ).map_err(|e| {
-            error!("流式请求：创建HTTP客户端失败: {}", e);
-            Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)) as Box<dyn StdError + Send + Sync>
-        })?;
-        
-        info!("流式请求：HTTP客户端创建成功");
+            if api_request.requires_structured_output() && !token_manager.provider.accepts_response_format() {
+                let error = structured_output_unsupported(&token_manager.provider);
+                warn!("流式请求：{}，换下一个提供商", error);
+                structured_unsupported.get_or_insert(error);
+                continue 'providers;
+            }
 
-        info!("流式请求：开始发送HTTP请求到 {}", token_manager.provider.base_url);
+            info!("代理配置：启用={}, URL={}", state.config.proxy.enable, state.config.proxy.url);
+            let client = upstream_client::provider_client(&state.config, &token_manager.provider).map_err(|e| {
+                error!("流式请求：创建HTTP客户端失败: {}", e);
+                Box::new(std::io::Error::other(e)) as Box<dyn StdError + Send + Sync>
+            })?;
         
-        let response = match client
-            .post(&token_manager.provider.base_url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", token_manager.provider.api_key))
-            .json(&api_request)
-            .send()
-            .await {
-                Ok(res) => {
-                    info!("流式请求：收到HTTP响应，状态码: {}", res.status());
-                    if !res.status().is_success() {
-                        error!("流式请求：API调用失败\n状态码: {}\nURL: {}", 
-                            res.status(), token_manager.provider.base_url
-                        );
-                        yield Bytes::from(format!("data: {{\"error\":\"API调用失败，状态码: {}\"}}\n\n", res.status()));
-                        return;
-                    }
-                    info!("流式请求：连接建立成功，开始接收流式数据");
-                    res
-                },
-                Err(e) => {
-                    error!("流式请求：发送HTTP请求失败");
-                    error!("错误详情: {}", e);
-                    error!("目标URL: {}", token_manager.provider.base_url);
-                    error!("代理配置: 启用={}, URL={}", state.config.proxy.enable, state.config.proxy.url);
+            // 消息已经在 api_request 中处理，无需额外转换
+
+            info!("流式请求：准备发送请求\nURL: {}", token_manager.provider.base_url);
+            if let Some(body) = payload_log::payload(&state.config.payload_logging, || {
+                serde_json::to_string_pretty(&api_request).unwrap_or_default()
+            }) {
+                info!("流式请求：请求体: {}", body);
+            }
+
+            let request_builder = build_upstream_request(
+                &client,
+                &token_manager.provider,
+                &api_request,
+                &state.config,
+            ).map_err(|e| {
+                error!("流式请求：构建请求失败: {}", e);
+                Box::new(std::io::Error::other(e)) as Box<dyn StdError + Send + Sync>
+            })?;
+
+            info!("流式请求：开始发送HTTP请求到 {}", token_manager.provider.base_url);
+
+            let request_start = std::time::Instant::now();
+            dispatched = true;
+            let response = match request_builder
+                .send()
+                .await {
+                    Ok(res) => {
+                        info!("流式请求：收到HTTP响应，状态码: {}", res.status());
+                        token_manager.record_upstream_response(&res);
+                        if !res.status().is_success() {
+                            let status = res.status();
+                            error!("流式请求：API调用失败\n状态码: {}\nURL: {}", 
+                                status, token_manager.provider.base_url
+                            );
+                            // 内容被拦截时返回模板补全，避免聊天界面因错误中断
+                            if state.config.blocked_response.enabled {
+                                let body = read_limited_body(res, state.config.response_limits.max_body_bytes)
+                                    .await
+                                    .map(|body| String::from_utf8_lossy(&body).into_owned())
+                                    .unwrap_or_default();
+                                if let Some(reason) = blocked_content::blocked_reason(status.as_u16(), &body) {
+                                    info!("流式请求：内容被上游拦截，返回模板回复: model={}, 原因={}", model_name, reason);
+                                    let content = blocked_content::render(&state.config.blocked_response, &model_name, &reason);
+                                    yield Bytes::from(canned_stream_events(&model_name, &content, CONTENT_FILTER_FINISH_REASON, false));
+                                    return;
+                                }
+                            }
+                            last_error = format!("API调用失败，状态码: {}", status);
+                            provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::from_status(status), &last_error).await;
+                            // 限流或上游故障时尚未向客户端输出任何内容，换下一个提供商重试
+                            if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                                continue 'providers;
+                            }
+                            yield sse_error_event(&last_error);
+                            return;
+                        }
+                        info!("流式请求：连接建立成功，开始接收流式数据");
+                        // 流式请求以收到响应头的耗时作为延迟
+                        token_manager.record_success(request_start.elapsed());
+                        res
+                    },
+                    Err(e) => {
+                        error!("流式请求：发送HTTP请求失败");
+                        error!("错误详情: {}", e);
+                        error!("目标URL: {}", token_manager.provider.base_url);
+                        error!("代理配置: 启用={}, URL={}", state.config.proxy.enable, state.config.proxy.url);
                     
-                    // 检查是否是代理相关错误
-                    let error_msg = e.to_string();
-                    if error_msg.contains("proxy") || error_msg.contains("socks") {
-                        error!("❌ 这可能是代理连接问题！");
+                        // 检查是否是代理相关错误
+                        let error_msg = e.to_string();
+                        if error_msg.contains("proxy") || error_msg.contains("socks") {
+                            error!("❌ 这可能是代理连接问题！");
+                        }
+                        last_error = format!("请求失败: {}", e);
+                        provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::from_request_error(&e), &last_error).await;
+                        continue 'providers;
                     }
-                    
-                    yield Bytes::from(format!("data: {{\"error\":\"请求失败: {}\"}}\n\n", e));
-                    return;
-                }
-            };
+                };
 
-        info!("流式请求：开始接收数据流");
-        let mut stream = response.bytes_stream();
-        let mut chunk_count = 0;
-        let mut latest_usage: Option<Usage> = None;  // 跟踪最新的usage信息
+            info!("流式请求：开始接收数据流");
+            // Bedrock 的事件流先转换为OpenAI格式的SSE数据块
+            let mut stream: SseStream = if token_manager.provider.is_bedrock() {
+                Box::pin(bedrock::openai_sse_stream(
+                    response,
+                    model_name.clone(),
+                    bedrock::model_id_from_url(&token_manager.provider.base_url),
+                ).map(|chunk| chunk.map_err(|e| Box::new(std::io::Error::other(e)) as Box<dyn StdError + Send + Sync>)))
+            } else {
+                Box::pin(response.bytes_stream().map(|chunk| chunk.map_err(|e| Box::new(e) as Box<dyn StdError + Send + Sync>)))
+            };
+            let mut chunk_count = 0;
+            let mut stream_bytes = 0;
+            let mut event_count = 0;
+            let mut pacer = pacing_tps.or(token_manager.provider.stream_pacing_tps).and_then(StreamPacer::new);
+            let mut latest_usage: Option<Usage> = None;  // 跟踪最新的usage信息
+            let mut size_limit = StreamSizeLimit::new(state.config.response_limits.max_stream_bytes);
+            let mut decoder = SseDecoder::new();
+            let mut yielded = false;
+            // 开启用量回显时暂缓转发的 usage 事件
+            let mut held: Vec<SseEvent> = Vec::new();
+            let mut conformance = strict.then(StrictConformance::default);
+            let mut first_token_recorded = false;
         
-        while let Some(chunk) = stream.next().await {
-            match chunk {
-                Ok(data) => {
-                    chunk_count += 1;
-                    let text = String::from_utf8_lossy(&data);
-                    
-                    // 检查是否包含usage信息
-                    if text.contains("\"usage\"") {
-                        // 处理带有data:前缀的流式响应格式
-                        let json_text = if text.starts_with("data: ") {
-                            text.trim_start_matches("data: ")
-                                .trim_end_matches("\n\n")
-                        } else {
-                            &text
-                        };
-                        
-                        // 尝试解析JSON获取usage信息
-                        match serde_json::from_str::<serde_json::Value>(json_text) {
-                            Ok(json) => {
-                                if let Some(usage) = json.get("usage") {
-                                    if let (Some(prompt), Some(completion), Some(total)) = (
-                                        usage.get("prompt_tokens").and_then(|v| v.as_u64()),
-                                        usage.get("completion_tokens").and_then(|v| v.as_u64()),
-                                        usage.get("total_tokens").and_then(|v| v.as_u64())
-                                    ) {
-                                        latest_usage = Some(Usage {
-                                            prompt_tokens: prompt as u32,
-                                            completion_tokens: completion as u32,
-                                            total_tokens: total as u32,
-                                            prompt_tokens_details: None,
-                                            completion_tokens_details: None,
-                                            num_sources_used: None,
-                                        });
-                                        
-                                        info!("流式请求：获取到usage信息：prompt={}, completion={}, total={}", 
-                                            prompt, completion, total);
-                                    }
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(data) => {
+                        chunk_count += 1;
+                        stream_bytes += data.len();
+                        if !size_limit.accept(data.len()) {
+                            error!("流式请求：上游响应超过大小限制，已截断\nURL: {}\n已接收块数: {}",
+                                token_manager.provider.base_url, chunk_count);
+                            for event in held.drain(..) {
+                                yield event.raw;
+                            }
+                            yield size_limit.truncation_event();
+                            return;
+                        }
+                        if let Some(content) = payload_log::stream_chunk(&state.config.payload_logging, || {
+                            String::from_utf8_lossy(&data).into_owned()
+                        }) {
+                            trace!("流式请求：接收到第 {} 个数据块\n内容: {}", chunk_count, content);
+                        }
+                        // 数据块可能截断事件，只转发解码出的完整事件
+                        for event in decoder.push(&data) {
+                            let Some(event) = conform_event(&mut conformance, event, &model_name) else {
+                                continue;
+                            };
+                            event_count += 1;
+                            let usage = event_usage(&event);
+                            let has_usage = usage.is_some();
+                            if let Some(usage) = usage {
+                                latest_usage = Some(usage);
+                            }
+                            if !first_token_recorded && event_has_content(&event) {
+                                token_manager.record_first_token(request_start.elapsed());
+                                first_token_recorded = true;
+                            }
+                            yielded = true;
+                            let ready = match echo {
+                                Some(_) => usage_echo::hold_usage_event(&mut held, event, has_usage),
+                                None => vec![event],
+                            };
+                            for event in ready {
+                                // 配置了节流时，按SSE事件逐个控制下发速率
+                                if let Some(pacer) = pacer.as_mut() {
+                                    pacer.pace().await;
                                 }
-                            },
-                            Err(e) => {
-                                info!("流式请求：解析JSON失败: {}, 原始文本: {}", e, json_text);
+                                yield event.raw;
                             }
                         }
+                    },
+                    Err(err) => {
+                        error!("流式请求：接收数据流错误\n错误: {}\n已接收块数: {}", err, chunk_count);
+                        let message = format!("接收数据流错误: {}", err);
+                        provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::Stream, &message).await;
+                        // 尚未向客户端输出任何事件时，换下一个提供商重试
+                        if !yielded {
+                            last_error = message;
+                            continue 'providers;
+                        }
+                        for event in held.drain(..) {
+                            yield event.raw;
+                        }
+                        yield sse_error_event(&message);
+                        return;
                     }
-                    
-                    info!("流式请求：接收到第 {} 个数据块\n内容: {}", 
-                        chunk_count,
-                        text
-                    );
-                    yield data;
-                },
-                Err(e) => {
-                    let err: Box<dyn StdError + Send + Sync> = Box::new(e);
-                    error!("流式请求：接收数据流错误\n错误: {}\n已接收块数: {}", err, chunk_count);
-                    yield Bytes::from(format!("data: {{\"error\":\"接收数据流错误: {}\"}}\n\n", err));
-                    return;
                 }
             }
-        }
         
-        info!("流式请求：数据流接收完成，共接收 {} 个数据块", chunk_count);
+            // 上游结束时缺少结尾空行的事件也要转发
+            if let Some(event) = decoder.finish().and_then(|event| conform_event(&mut conformance, event, &model_name)) {
+                event_count += 1;
+                let usage = event_usage(&event);
+                let has_usage = usage.is_some();
+                if let Some(usage) = usage {
+                    latest_usage = Some(usage);
+                }
+                if !first_token_recorded && event_has_content(&event) {
+                    token_manager.record_first_token(request_start.elapsed());
+                }
+                yielded = true;
+                let ready = match echo {
+                    Some(_) => usage_echo::hold_usage_event(&mut held, event, has_usage),
+                    None => vec![event],
+                };
+                for event in ready {
+                    yield event.raw;
+                }
+            }
+            // 上游未返回任何事件即结束时，换下一个提供商重试
+            if !yielded {
+                error!("流式请求：上游未返回任何数据\nURL: {}", token_manager.provider.base_url);
+                last_error = "上游未返回任何数据".to_string();
+                provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::Stream, &last_error).await;
+                continue 'providers;
+            }
+
+            info!(
+                "流式请求：数据流接收完成, 提供商: {}, 数据块: {}, 事件: {}, 字节: {}, 耗时: {:?}",
+                token_manager.provider.base_url, chunk_count, event_count, stream_bytes, request_start.elapsed()
+            );
         
-        // 请求结束后，记录usage信息
-        if let Some(usage) = latest_usage {
-            // 更新token使用情况
-            token_manager.update_usage(usage.total_tokens).await;
+            // 请求结束后，记录usage信息
+            let (prompt_tokens, completion_tokens) = latest_usage.as_ref().map_or((0, 0), |u| (u.prompt_tokens, u.completion_tokens));
+            let mut charged_cost = None;
+            if let Some(usage) = latest_usage {
+                // 更新token使用情况
+                token_manager.update_usage(usage.total_tokens).await;
             
-            // 记录到数据库
-            let _ = sqlx::query(
-                r#"
-                INSERT INTO api_usage (
-                    id, provider_api_key, request_time, model, 
-                    prompt_tokens, completion_tokens, total_tokens, 
-                    status, client_ip, request_id
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                "#
-            )
-            .bind(uuid::Uuid::new_v4().to_string())
-            .bind(&token_manager.provider.api_key)
-            .bind(chrono::Utc::now())
-            .bind(&model_name)
-            .bind(usage.prompt_tokens)
-            .bind(usage.completion_tokens)
-            .bind(usage.total_tokens)
-            .bind("Success")
-            .bind(&client_ip)
-            .bind(None::<String>) // request_id
-            .execute(&state.db)
-            .await
-            .map_err(|e| {
-                error!("记录流式API使用情况失败: {}", e);
-            });
+                // 记录到数据库
+                let record = usage_record(
+                    &token_manager.provider,
+                    &model_name,
+                    (usage.prompt_tokens, usage.completion_tokens, usage.total_tokens),
+                    usage.num_sources_used.unwrap_or(0),
+                    "Success",
+                    &client_ip,
+                    gateway_key_id.as_deref(),
+                );
+                let usage_id = record.id.clone();
+                state.usage_recorder.record(record).await;
+
+                let cost = charge_usage(
+                    &state,
+                    gateway_key_id.as_deref(),
+                    &token_manager.provider.api_key,
+                    &model_name,
+                    (usage.prompt_tokens, usage.completion_tokens),
+                    &usage_id,
+                ).await;
+                token_manager.record_cost(cost).await;
+                charged_cost = cost;
             
-            info!("流式请求：已记录usage信息：prompt={}, completion={}, total={}", 
-                usage.prompt_tokens, usage.completion_tokens, usage.total_tokens);
-        } else {
-            // 没有usage信息，记录部分成功的请求
-            let _ = sqlx::query(
-                r#"
-                INSERT INTO api_usage (
-                    id, provider_api_key, request_time, model, 
-                    prompt_tokens, completion_tokens, total_tokens, 
-                    status, client_ip, request_id
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                "#
-            )
-            .bind(uuid::Uuid::new_v4().to_string())
-            .bind(&token_manager.provider.api_key)
-            .bind(chrono::Utc::now())
-            .bind(&model_name)
-            .bind(0) // 没有usage信息时默认为0
-            .bind(0)
-            .bind(0)
-            .bind(if chunk_count > 0 { "PartialSuccess" } else { "Error" })
-            .bind(&client_ip)
-            .bind(None::<String>)
-            .execute(&state.db)
-            .await
-            .map_err(|e| {
-                error!("记录流式API使用失败情况失败: {}", e);
-            });
+                info!("流式请求：已记录usage信息：prompt={}, completion={}, total={}", 
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens);
+            } else {
+                // 没有usage信息，记录部分成功的请求
+                let status = if chunk_count > 0 { "PartialSuccess" } else { "Error" };
+                let record = usage_record(&token_manager.provider, &model_name, (0, 0, 0), 0, status, &client_ip, gateway_key_id.as_deref());
+                state.usage_recorder.record(record).await;
             
-            info!("流式请求：未获取到usage信息，记录为{}状态", 
-                if chunk_count > 0 { "PartialSuccess" } else { "Error" });
+                info!("流式请求：未获取到usage信息，记录为{}状态", 
+                    if chunk_count > 0 { "PartialSuccess" } else { "Error" });
+            }
+            if let Some(conformance) = &conformance {
+                conformance.record(&state.db, &token_manager.provider.api_key, None).await;
+            }
+            if let Some(key) = &affinity {
+                state.conversation_affinity.remember(key, &token_manager.provider.api_key);
+            }
+            state.slow_requests.observe(CompletedRequest {
+                provider: &token_manager.provider,
+                model: &model_name,
+                stream: true,
+                prompt_tokens,
+                completion_tokens,
+                elapsed: received.elapsed(),
+            });
+
+            // 计费完成后补上回显字段，转发暂缓的 usage 事件
+            if let Some(echo) = &echo {
+                let fields = echo.fields(&state, charged_cost, request_start.elapsed()).await;
+                for event in held.drain(..) {
+                    yield usage_echo::echo_event(&event, &fields);
+                }
+            }
+            return;
+        }
+
+        if let Some(error) = structured_unsupported.filter(|_| !dispatched) {
+            error!("流式请求：{}", error);
+            yield sse_error_event(&error);
+            return;
         }
+        error!("流式请求：所有提供商均失败: {}", last_error);
+        error_reporter::capture(
+            ErrorEvent::new(Level::Error, format!("所有提供商均失败: {}", last_error)).tag("model", model_name.clone()).tag("stream", "true"),
+        );
+        if let Some(content) = fallback_content(&state, &model_name).await {
+            warn!("流式请求：所有提供商均失败，返回兜底回复: model={}", model_name);
+            yield Bytes::from(canned_stream_events(&model_name, &content, "stop", true));
+            return;
+        }
+        yield sse_error_event(&last_error);
     });
 
     Response::builder()
         .header("Content-Type", "text/event-stream")
         .header("Cache-Control", "no-cache")
         .header("Connection", "keep-alive")
-        .body(Body::from_stream(stream))
+        .body(Body::from_stream(with_heartbeat(Box::pin(with_flush_policy(stream, flush_policy)), heartbeat)))
         .unwrap()
 }
 
 // 处理普通响应
+#[allow(clippy::too_many_arguments)]
 async fn handle_normal_response(
     state: AppState,
     request: ChatCompletionRequest,
     client_ip: String,
+    gateway_key: Option<GatewayKeyIdentity>,
+    upstream_key: Option<UpstreamKey>,
+    echo: Option<UsageEcho>,
+    affinity: Option<String>,
+    strategy: String,
 ) -> Response {
+    let received = std::time::Instant::now();
+    let gateway_key_id = gateway_key.as_ref().map(|key| key.id.clone());
+    // 网关密钥开启严格模式时按 OpenAI 格式修复响应，无法修复时换下一个提供商
+    let strict = gateway_key.as_ref().is_some_and(|key| key.strict_openai);
+    // 组织的网关密钥只使用绑定到该组织的提供商
+    let organization = gateway_key.and_then(|key| key.organization);
     // 获取模型名称，直接使用前端传入的值
     let model_name = request.model.clone().unwrap_or_else(|| "DeepSeek-V3".to_string());
     
@@ -388,13 +912,30 @@ async fn handle_normal_response(
 
     // 尝试不同的token
     let mut last_error = None;
-    let strategies = ["RoundRobin", "LeastConnections", "LeastTokens"];
+    let strategies = [strategy.as_str(), "LeastConnections", "LeastTokens"];
     
-    for strategy in strategies.iter() {
+    for (attempt, strategy) in strategies.iter().enumerate() {
         info!("尝试使用 {} 策略选择提供商", strategy);
         
+        // 第一次尝试优先使用会话之前的提供商
+        let preferred = match affinity.as_deref().filter(|_| attempt == 0) {
+            Some(key) => preferred_provider(&state, key, &model_name, organization.as_deref()).await,
+            None => None,
+        };
         // 获取token管理器
-        let token_manager = match TokenManager::new(state.provider_pool.clone(), &model_name, strategy).await {
+        let acquired = match preferred {
+            Some(manager) => Some(manager),
+            None => TokenManager::acquire(
+                state.provider_pool.clone(),
+                state.concurrency.clone(),
+                &model_name,
+                None,
+                strategy,
+                organization.as_deref(),
+                upstream_key.as_ref(),
+            ).await,
+        };
+        let token_manager = match acquired {
             Some(manager) => {
                 info!(
                     "选择提供商成功, URL: {}, 策略: {}", 
@@ -403,47 +944,84 @@ async fn handle_normal_response(
                 manager
             },
             None => {
-                info!("使用 {} 策略无法获取可用提供商，尝试下一个策略", strategy);
-                continue
+                // 选择过程已在共同的等待期限内尝试过全部候选提供商，换策略也不会有空闲的提供商
+                info!("使用 {} 策略无法获取可用提供商", strategy);
+                last_error.get_or_insert_with(|| "无法获取可用的提供商".to_string());
+                break
             },
         };
 
+        if api_request.requires_structured_output() && !token_manager.provider.accepts_response_format() {
+            let error = structured_output_unsupported(&token_manager.provider);
+            error!("{}", error);
+            return error_response(StatusCode::BAD_REQUEST, error);
+        }
+
         // 调用 API
+        let request_start = std::time::Instant::now();
         match call_api(
+            &state.db,
             api_request.clone(), 
             &token_manager.provider, 
-            state.config.proxy.enable, 
-            &state.config.proxy.url
+            &state.config,
         ).await {
-            Ok(response) => {
+            Ok(mut response) => {
+                let latency = request_start.elapsed();
+                if state.config.blocked_response.enabled {
+                    response.fill_blocked_content(&state.config.blocked_response, &model_name);
+                }
+                let mut body = serde_json::to_value(&response).unwrap();
+                let mut conformance_error = None;
+                if strict {
+                    let mut conformance = StrictConformance::default();
+                    let conformed = conformance.response(&mut body, &model_name);
+                    conformance.record(&state.db, &token_manager.provider.api_key, conformed.as_ref().err().map(String::as_str)).await;
+                    conformance_error = conformed.err();
+                }
                 let total_tokens = response.usage.total_tokens;
-                // 更新使用情况
+                // 更新使用情况（严格模式丢弃的响应同样消耗了上游的tokens）
                 token_manager.update_usage(total_tokens).await;
-                
+
                 // 记录API使用情况
-                let _ = sqlx::query(
-                    r#"
-                    INSERT INTO api_usage (
-                        id, provider_api_key, request_time, model, 
-                        prompt_tokens, completion_tokens, total_tokens, 
-                        status, client_ip, request_id
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                    "#
-                )
-                .bind(uuid::Uuid::new_v4().to_string())
-                .bind(&token_manager.provider.api_key)
-                .bind(chrono::Utc::now())
-                .bind(&response.model)
-                .bind(response.usage.prompt_tokens)
-                .bind(response.usage.completion_tokens)
-                .bind(total_tokens)
-                .bind("Success")
-                .bind(&client_ip)
-                .bind(None::<String>) // request_id
-                .execute(&state.db)
-                .await
-                .map_err(|e| {
-                    error!("记录API使用情况失败: {}", e);
+                let record = usage_record(
+                    &token_manager.provider,
+                    &response.model,
+                    (response.usage.prompt_tokens, response.usage.completion_tokens, total_tokens),
+                    response.usage.num_sources_used.unwrap_or(0),
+                    if conformance_error.is_some() { "Error" } else { "Success" },
+                    &client_ip,
+                    gateway_key_id.as_deref(),
+                );
+                let usage_id = record.id.clone();
+                state.usage_recorder.record(record).await;
+
+                // 丢弃的响应只记录成本，不向调用方计费（调用方没有收到这次响应）
+                let billed = conformance_error.is_none();
+                let cost = charge_usage(
+                    &state,
+                    gateway_key_id.as_deref().filter(|_| billed),
+                    &token_manager.provider.api_key,
+                    &model_name,
+                    (response.usage.prompt_tokens, response.usage.completion_tokens),
+                    &usage_id,
+                ).await;
+                token_manager.record_cost(cost).await;
+                if let Some(e) = conformance_error {
+                    error!("严格模式：提供商响应不符合 OpenAI 格式, 提供商: {}, 原因: {}", token_manager.provider.base_url, e);
+                    last_error = Some(format!("响应不符合 OpenAI 格式: {}", e));
+                    continue;
+                }
+                token_manager.record_success(latency);
+                if let Some(key) = &affinity {
+                    state.conversation_affinity.remember(key, &token_manager.provider.api_key);
+                }
+                state.slow_requests.observe(CompletedRequest {
+                    provider: &token_manager.provider,
+                    model: &model_name,
+                    stream: false,
+                    prompt_tokens: response.usage.prompt_tokens,
+                    completion_tokens: response.usage.completion_tokens,
+                    elapsed: received.elapsed(),
                 });
                 
                 info!(
@@ -452,44 +1030,48 @@ async fn handle_normal_response(
                     total_tokens
                 );
 
-                // 直接转发原始响应，保持与 OpenAI 格式一致
+                // 直接转发原始响应，保持与 OpenAI 格式一致；开启用量回显时在 usage 中附带扩展字段
+                if let Some(echo) = &echo {
+                    let fields = echo.fields(&state, cost, latency).await;
+                    usage_echo::append_fields(&mut body, &fields);
+                }
                 return Response::builder()
                     .status(StatusCode::OK)
                     .header("Content-Type", "application/json")
-                    .body(Body::from(serde_json::to_string(&response).unwrap()))
+                    .body(Body::from(body.to_string()))
                     .unwrap();
             }
             Err(err) => {
                 error!(
                     "使用token {} 调用API失败: {}, 策略: {}", 
-                    token_manager.provider.api_key, err, strategy
+                    redact(&token_manager.provider.api_key), err, strategy
                 );
+                if let Some(status) = upstream_status(&err) {
+                    token_manager.record_upstream_status(status);
+                }
+                let blocked_reason = err
+                    .strip_prefix(CONTENT_BLOCKED_ERROR)
+                    .map(|reason| reason.trim_start_matches(": ").to_string());
                 
                 // 记录失败的请求
-                let _ = sqlx::query(
-                    r#"
-                    INSERT INTO api_usage (
-                        id, provider_api_key, request_time, model, 
-                        prompt_tokens, completion_tokens, total_tokens, 
-                        status, client_ip, request_id
-                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                    "#
-                )
-                .bind(uuid::Uuid::new_v4().to_string())
-                .bind(&token_manager.provider.api_key)
-                .bind(chrono::Utc::now())
-                .bind(&model_name)
-                .bind(0)
-                .bind(0)
-                .bind(0)
-                .bind("Error")
-                .bind(&client_ip)
-                .bind(None::<String>) // request_id
-                .execute(&state.db)
-                .await
-                .map_err(|e| {
-                    error!("记录API失败使用情况失败: {}", e);
-                });
+                let status = if blocked_reason.is_some() { "Blocked" } else { "Error" };
+                // 内容被拦截与提供商无关，不计为提供商错误
+                if blocked_reason.is_none() {
+                    provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::classify(&err), &err).await;
+                }
+                let record = usage_record(&token_manager.provider, &model_name, (0, 0, 0), 0, status, &client_ip, gateway_key_id.as_deref());
+                state.usage_recorder.record(record).await;
+
+                // 内容被拦截时返回模板补全，避免聊天界面因HTTP错误中断
+                if let Some(reason) = blocked_reason.filter(|_| state.config.blocked_response.enabled) {
+                    info!("请求内容被上游拦截，返回模板回复: model={}, 原因={}", model_name, reason);
+                    let content = blocked_content::render(&state.config.blocked_response, &model_name, &reason);
+                    return Response::builder()
+                        .status(StatusCode::OK)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(serde_json::to_string(&ApiResponse::canned(&model_name, content, CONTENT_FILTER_FINISH_REASON)).unwrap()))
+                        .unwrap();
+                }
                 
                 last_error = Some(err);
                 // 继续尝试下一个策略
@@ -501,6 +1083,21 @@ async fn handle_normal_response(
     let error_message = format!("所有可用的API提供商都失败了。最后的错误: {}", 
         last_error.unwrap_or_else(|| "未知错误".to_string()));
     error!("{}", error_message);
+    error_reporter::capture(
+        ErrorEvent::new(Level::Error, error_message.clone()).tag("model", model_name.clone()).tag("stream", "false"),
+    );
+
+    if let Some(content) = fallback_content(&state, &model_name).await {
+        warn!("所有提供商均失败，返回兜底回复: model={}", model_name);
+        let mut body = serde_json::to_value(ApiResponse::canned(&model_name, content, "stop")).unwrap();
+        body[FALLBACK_FIELD] = true.into();
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header(FALLBACK_HEADER, "outage")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+    }
     
     Response::builder()
         .status(StatusCode::SERVICE_UNAVAILABLE)
@@ -509,52 +1106,77 @@ async fn handle_normal_response(
         .unwrap()
 }
 
-// 创建 HTTP 客户端（支持代理）
-pub fn create_http_client(enable_proxy: bool, proxy_url: &str, timeout_secs: u64) -> Result<Client, String> {
-    info!("创建HTTP客户端：enable_proxy={}, proxy_url={}, timeout={}s", enable_proxy, proxy_url, timeout_secs);
-    
-    let mut client_builder = Client::builder()
-        .timeout(Duration::from_secs(timeout_secs));
+// 会话之前使用的提供商，已不可用或没有空闲连接时返回空
+async fn preferred_provider(state: &AppState, key: &str, model: &str, organization: Option<&str>) -> Option<TokenManager> {
+    let api_key = state.conversation_affinity.provider(key)?;
+    TokenManager::acquire_preferred(state.provider_pool.clone(), state.concurrency.clone(), &api_key, model, organization).await
+}
 
-    // 如果启用代理，添加代理配置
-    if enable_proxy {
-        info!("正在配置代理: {}", proxy_url);
-        
-        // 检查是否是 SOCKS 代理，如果是，确保使用远程 DNS 解析
-        let proxy_url_fixed = if proxy_url.starts_with("socks5://") {
-            let fixed_url = proxy_url.replace("socks5://", "socks5h://");
-            info!("将 SOCKS 代理URL修改为远程DNS解析: {} -> {}", proxy_url, fixed_url);
-            fixed_url
-        } else {
-            proxy_url.to_string()
-        };
-        
-        match reqwest::Proxy::all(&proxy_url_fixed) {
-            Ok(proxy) => {
-                client_builder = client_builder.proxy(proxy);
-                info!("✅ 代理配置成功: {}", proxy_url_fixed);
-            }
-            Err(e) => {
-                error!("❌ 代理配置失败: {} - 错误: {}", proxy_url_fixed, e);
-                return Err(format!("无效的代理URL: {} - {}", proxy_url_fixed, e));
-            }
-        }
-    } else {
-        info!("未启用代理，使用直连");
+// 严格模式下按 OpenAI 格式修复流式事件，无法修复时丢弃
+fn conform_event(conformance: &mut Option<StrictConformance>, event: SseEvent, model: &str) -> Option<SseEvent> {
+    match conformance {
+        Some(conformance) => conformance.chunk(event, model),
+        None => Some(event),
     }
+}
 
-    match client_builder.build() {
-        Ok(client) => {
-            info!("✅ HTTP客户端创建成功");
-            Ok(client)
-        }
+// 全部提供商失败时查找模型的兜底回复
+async fn fallback_content(state: &AppState, model: &str) -> Option<String> {
+    match FallbackResponse::for_model(&state.db, model).await {
+        Ok(fallback) => fallback.map(|fallback| fallback.render(model)),
         Err(e) => {
-            error!("❌ HTTP客户端创建失败: {}", e);
-            Err(format!("创建HTTP客户端失败: {}", e))
+            error!("查询兜底回复失败: model={}, 错误={}", model, e);
+            None
         }
     }
 }
 
+// 所选提供商不支持结构化输出时的错误信息
+fn structured_output_unsupported(provider: &ProviderInfo) -> String {
+    format!(
+        "提供商不支持 response_format 结构化输出: provider_type={}, model={}",
+        provider.provider_type, provider.model_name
+    )
+}
+
+// 构建使用记录（状态可能是 PartialSuccess、Blocked 等 ApiCallStatus 之外的值）
+fn usage_record(
+    provider: &ProviderInfo,
+    model: &str,
+    (prompt_tokens, completion_tokens, total_tokens): (u32, u32, u32),
+    num_sources: u32,
+    status: &str,
+    client_ip: &str,
+    gateway_key_id: Option<&str>,
+) -> ApiUsage {
+    let mut usage = ApiUsage::new(
+        provider.api_key.clone(),
+        model.to_string(),
+        prompt_tokens as i32,
+        completion_tokens as i32,
+        ApiCallStatus::Success,
+        Some(client_ip.to_string()),
+        None,
+    );
+    usage.total_tokens = total_tokens as i32;
+    usage.status = status.to_string();
+    usage.num_sources = num_sources as i32;
+    usage.own_key_id = provider.own_key_id();
+    usage.gateway_key_id = gateway_key_id.map(str::to_string);
+    usage.organization = provider.organization.clone();
+    usage
+}
+
+// 构建错误响应
+fn error_response(status: StatusCode, error: String) -> Response {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(&ErrorResponse { error }).unwrap()))
+        .unwrap()
+}
+
+
 // 构建 API 请求
 fn build_api_request(request: &ChatCompletionRequest, model_name: &str, stream: bool) -> ApiRequest {
     ApiRequest {
@@ -564,63 +1186,191 @@ fn build_api_request(request: &ChatCompletionRequest, model_name: &str, stream:
             content: m.content.clone(),
             refusal: None, // 请求中不包含 refusal
         }).collect(),
-        max_tokens: request.max_tokens.or(Some(1000)), // 总是包含 max_tokens，API 会忽略不需要的参数
-        temperature: request.temperature.unwrap_or(0.7),
+        // 只转发客户端设置的生成参数，未设置的使用提供商的默认值
+        max_tokens: request.max_tokens,
+        temperature: request.temperature,
         stream,
+        top_p: request.top_p,
+        stop: request.stop.clone(),
+        frequency_penalty: request.frequency_penalty,
+        presence_penalty: request.presence_penalty,
+        n: request.n,
+        logprobs: request.logprobs,
+        top_logprobs: request.top_logprobs,
+        seed: request.seed,
+        user: request.user.clone(),
+        response_format: request.response_format.clone(),
     }
 }
 
-// 调用通用 API
-async fn call_api(request: ApiRequest, provider: &ProviderInfo, enable_proxy: bool, proxy_url: &str) -> Result<ApiResponse, String> {
-    info!(
-        "准备调用 API\nURL: {}\nAPI Key: {}\n请求体: {}", 
-        provider.base_url,
-        provider.api_key,
-        serde_json::to_string_pretty(&request).unwrap_or_default()
-    );
-
-    let mut client_builder = Client::builder()
-        .timeout(Duration::from_secs(300))
-        .pool_max_idle_per_host(provider.max_connections as usize)
-        .pool_idle_timeout(Duration::from_millis(provider.idle_timeout_ms as u64));
-
-    // 如果启用代理，添加代理配置
-    if enable_proxy {
-        if let Ok(proxy) = reqwest::Proxy::all(proxy_url) {
-            client_builder = client_builder.proxy(proxy);
-            info!("已启用代理: {}", proxy_url);
+// 构建发往提供商的请求：Bedrock 转换请求格式并使用 SigV4 签名，其余提供商使用 OpenAI 格式
+fn build_upstream_request(
+    client: &Client,
+    provider: &ProviderInfo,
+    request: &ApiRequest,
+    config: &AppConfig,
+) -> Result<reqwest::RequestBuilder, String> {
+    upstream_client::record_request(&provider.base_url);
+    if provider.is_bedrock() {
+        let model_id = bedrock::model_id_from_url(&provider.base_url);
+        let url = if request.stream {
+            bedrock::stream_url(&provider.base_url)
         } else {
-            return Err(format!("无效的代理URL: {}", proxy_url));
+            provider.base_url.clone()
+        };
+        let url = reqwest::Url::parse(&url).map_err(|e| format!("无效的Bedrock端点: {}", e))?;
+        let body = bedrock::build_request_body(
+            &model_id,
+            request.messages.iter().map(|m| (m.role.as_str(), serde_json::to_value(&m.content).unwrap_or_default())),
+            request.max_tokens,
+            request.temperature,
+        )?;
+        let body = serde_json::to_vec(&body).map_err(|e| format!("序列化请求失败: {}", e))?;
+        let credentials = AwsCredentials::parse(provider.credential())?;
+        let region = provider.aws_region.as_deref().unwrap_or_default();
+
+        let mut builder = client
+            .post(url.clone())
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json");
+        for (name, value) in sign_request(&credentials, region, "bedrock", "POST", &url, &body, chrono::Utc::now()) {
+            builder = builder.header(name, value);
         }
+        for (name, value) in provider.static_headers(&config.openrouter) {
+            builder = builder.header(name, value);
+        }
+        return Ok(builder.timeout(provider.request_timeout(&config.upstream_timeouts)).body(body));
+    }
+
+    // OpenRouter 需要带厂商前缀的完整模型名称
+    let openrouter_request;
+    let request = if provider.is_openrouter() {
+        openrouter_request = ApiRequest { model: provider.model_name.clone(), ..request.clone() };
+        &openrouter_request
+    } else {
+        request
+    };
+
+    // 序列化请求体，超过阈值且提供商支持时使用gzip压缩
+    let body = encode_json_body(
+        request,
+        provider.supports_gzip_request,
+        config.request_compression.gzip_threshold_bytes,
+    )?;
+    let mut builder = client
+        .post(&provider.base_url)
+        .header("Content-Type", "application/json");
+    if let Some((auth_name, auth_value)) = provider.auth_header() {
+        builder = builder.header(auth_name, auth_value);
+    }
+    for (name, value) in provider.static_headers(&config.openrouter) {
+        builder = builder.header(name, value);
+    }
+    if let Some(encoding) = body.content_encoding {
+        builder = builder.header("Content-Encoding", encoding);
+    }
+    Ok(builder.timeout(provider.request_timeout(&config.upstream_timeouts)).body(body.bytes))
+}
+
+// 解析提供商响应，Bedrock 响应转换为 OpenAI 格式
+fn parse_api_response(provider: &ProviderInfo, model: &str, response_text: &str) -> Result<ApiResponse, String> {
+    if !provider.is_bedrock() {
+        return serde_json::from_str::<ApiResponse>(response_text).map_err(|e| e.to_string());
+    }
+
+    let json = serde_json::from_str::<serde_json::Value>(response_text).map_err(|e| e.to_string())?;
+    let completion = bedrock::parse_response(&bedrock::model_id_from_url(&provider.base_url), &json)?;
+    Ok(ApiResponse {
+        id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+        object: "chat.completion".to_string(),
+        created: chrono::Utc::now().timestamp() as u64,
+        model: model.to_string(),
+        choices: vec![Choice {
+            index: 0,
+            message: Message {
+                role: "assistant".to_string(),
+                content: completion.content.into(),
+                refusal: None,
+            },
+            finish_reason: completion.finish_reason,
+            logprobs: None,
+        }],
+        usage: Usage {
+            prompt_tokens: completion.prompt_tokens,
+            completion_tokens: completion.completion_tokens,
+            total_tokens: completion.prompt_tokens + completion.completion_tokens,
+            prompt_tokens_details: None,
+            completion_tokens_details: None,
+            num_sources_used: None,
+        },
+        system_fingerprint: None,
+        citations: None,
+        search_results: None,
+    })
+}
+
+// 严格解析失败后宽松解析一次（BOM、首尾无关内容、尾随逗号），并记录提供商的数据质量事件
+async fn reparse_leniently(
+    db: &sqlx::SqlitePool,
+    provider: &ProviderInfo,
+    model: &str,
+    response_text: &str,
+    strict_error: &str,
+) -> Result<ApiResponse, String> {
+    let repaired = parse_lenient::<serde_json::Value>(response_text)
+        .map_err(|e| e.to_string())
+        .and_then(|parsed| {
+            let api_response = parse_api_response(provider, model, &parsed.value.to_string())?;
+            Ok((api_response, parsed.repairs))
+        });
+
+    let (recovered, repairs) = match &repaired {
+        Ok((_, repairs)) => (true, repairs.as_slice()),
+        Err(_) => (false, &[][..]),
+    };
+    if recovered {
+        info!("宽松解析响应成功, 提供商: {}, 修复项: {:?}", provider.base_url, repairs);
+    }
+    if let Err(e) = DataQualityEvent::record_malformed_json(db, &provider.api_key, recovered, repairs, strict_error).await {
+        error!("记录数据质量事件失败: {}", e);
+    }
+
+    repaired.map(|(api_response, _)| api_response).map_err(|_| strict_error.to_string())
+}
+
+// 从 call_api 的错误信息中取出上游状态码
+fn upstream_status(err: &str) -> Option<reqwest::StatusCode> {
+    let (_, rest) = err.split_once("状态码: ")?;
+    rest.get(..3)?.parse::<u16>().ok().and_then(|code| reqwest::StatusCode::from_u16(code).ok())
+}
+
+// 调用通用 API
+async fn call_api(
+    db: &sqlx::SqlitePool,
+    request: ApiRequest,
+    provider: &ProviderInfo,
+    config: &AppConfig,
+) -> Result<ApiResponse, String> {
+    info!("准备调用 API\nURL: {}\nAPI Key: {}", provider.base_url, redact(&provider.api_key));
+    if let Some(body) = payload_log::payload(&config.payload_logging, || {
+        serde_json::to_string_pretty(&request).unwrap_or_default()
+    }) {
+        info!("请求体: {}", body);
     }
 
-    let client = client_builder
-        .build()
-        .map_err(|e| format!("创建HTTP客户端失败: {}", e))?;
-
-    let headers = reqwest::header::HeaderMap::from_iter([
-        (
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        ),
-        (
-            reqwest::header::AUTHORIZATION,
-            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", provider.api_key))
-                .map_err(|e| format!("无效的API密钥: {}", e))?,
-        ),
-    ]);
+    // 使用按提供商设置共享的客户端，请求之间复用连接
+    let client = upstream_client::provider_client(config, provider)?;
 
     // 使用提供商的重试配置
-    for attempt in 0..provider.retry_attempts {
+    let retry_delay = Duration::from_millis(provider.retry_delay_ms.max(0) as u64);
+    for attempt in 0..provider.retry_attempts.max(1) {
         info!(
             "发送请求到 {}, 尝试次数: {}/{}", 
             provider.base_url, attempt + 1, provider.retry_attempts
         );
 
-        match client
-            .post(&provider.base_url)
-            .headers(headers.clone())
-            .json(&request)
+        // 每次重试重新构建请求（Bedrock 签名包含请求时间）
+        match build_upstream_request(&client, provider, &request, config)?
             .send()
             .await
         {
@@ -628,36 +1378,65 @@ async fn call_api(request: ApiRequest, provider: &ProviderInfo, enable_proxy: bo
                 let status = response.status();
                 if status.is_success() {
                     // 先获取原始响应文本
-                    let response_text = response.text().await.map_err(|e| format!("读取响应失败: {}", e))?;
-                    info!("收到原始响应: {}", response_text);
+                    let response_body = read_limited_body(response, config.response_limits.max_body_bytes).await?;
+                    let response_text = String::from_utf8_lossy(&response_body);
+                    if let Some(text) = payload_log::payload(&config.payload_logging, || response_text.to_string()) {
+                        info!("收到原始响应: {}", text);
+                    }
                     
-                    // 解析响应
-                    match serde_json::from_str::<ApiResponse>(&response_text) {
-                        Ok(api_response) => {
+                    // 解析响应，严格解析失败时使用宽松解析重试一次
+                    let parsed = match parse_api_response(provider, &request.model, &response_text) {
+                        Ok(api_response) => Ok(api_response),
+                        Err(e) => {
+                            match payload_log::payload(&config.payload_logging, || response_text.to_string()) {
+                                Some(text) => error!("解析响应失败: {}\n原始响应: {}", e, text),
+                                None => error!("解析响应失败: {}", e),
+                            }
+                            reparse_leniently(db, provider, &request.model, &response_text, &e).await
+                        }
+                    };
+                    match parsed {
+                        Ok(mut api_response) => {
+                            api_response.normalize_citations();
                             info!(
-                                "请求成功\n模型: {}\n总tokens: {}\nprompt_tokens: {}\ncompletion_tokens: {}\n响应内容: {}", 
+                                "请求成功\n模型: {}\n总tokens: {}\nprompt_tokens: {}\ncompletion_tokens: {}", 
                                 api_response.model,
                                 api_response.usage.total_tokens,
                                 api_response.usage.prompt_tokens,
-                                api_response.usage.completion_tokens,
-                                serde_json::to_string_pretty(&api_response.choices).unwrap_or_default()
+                                api_response.usage.completion_tokens
                             );
+                            if let Some(content) = payload_log::payload(&config.payload_logging, || {
+                                serde_json::to_string_pretty(&api_response.choices).unwrap_or_default()
+                            }) {
+                                info!("响应内容: {}", content);
+                            }
                             return Ok(api_response)
                         },
-                        Err(e) => {
-                            error!("解析响应失败: {}\n原始响应: {}", e, response_text);
-                            return Err(format!("解析响应失败: {}", e))
-                        },
+                        Err(e) => return Err(format!("解析响应失败: {}", e)),
                     }
                 } else {
-                    let error_text = response.text().await.unwrap_or_default();
-                    error!(
-                        "API调用失败\n状态码: {}\nURL: {}\n错误响应: {}", 
-                        status, provider.base_url, error_text
-                    );
-                    if attempt < provider.retry_attempts - 1 {
+                    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        provider_cooldown::respect_retry_after(provider, response.headers());
+                    }
+                    let error_text = read_limited_body(response, config.response_limits.max_body_bytes)
+                        .await
+                        .map(|body| String::from_utf8_lossy(&body).into_owned())
+                        .unwrap_or_else(|e| e);
+                    match payload_log::payload(&config.payload_logging, || error_text.clone()) {
+                        Some(text) => error!("API调用失败\n状态码: {}\nURL: {}\n错误响应: {}", status, provider.base_url, text),
+                        None => error!("API调用失败\n状态码: {}\nURL: {}", status, provider.base_url),
+                    }
+                    // 内容审核拦截与提供商无关，不再重试
+                    if let Some(reason) = blocked_content::blocked_reason(status.as_u16(), &error_text) {
+                        return Err(format!("{}: {}", CONTENT_BLOCKED_ERROR, reason));
+                    }
+                    // 限流时直接换下一个提供商，不在同一密钥上消耗重试次数
+                    if status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                        && attempt < provider.retry_attempts - 1
+                        && provider.retries_status(status.as_u16())
+                    {
                         info!("请求失败，正在重试({}/{})", attempt + 1, provider.retry_attempts);
-                        tokio::time::sleep(RETRY_DELAY).await;
+                        tokio::time::sleep(retry_delay).await;
                         continue;
                     }
                     return Err(format!("API调用失败，状态码: {}，错误: {}", status, error_text));
@@ -666,7 +1445,7 @@ async fn call_api(request: ApiRequest, provider: &ProviderInfo, enable_proxy: bo
             Err(e) => {
                 if e.is_timeout() && attempt < provider.retry_attempts - 1 {
                     info!("请求超时，正在重试({}/{})", attempt + 1, provider.retry_attempts);
-                    tokio::time::sleep(RETRY_DELAY).await;
+                    tokio::time::sleep(retry_delay).await;
                     continue;
                 }
                 error!("请求发送失败: {}", e);
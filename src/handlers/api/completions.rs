@@ -0,0 +1,107 @@
+use axum::{
+    extract::{ConnectInfo, Json, State},
+    Extension,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::handlers::api::passthrough::{error_response, proxy_json_request, proxy_stream_request, PassthroughTarget};
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::routes::api::AppState;
+use crate::utils::lb_strategy::request_strategy;
+use crate::utils::client_key::upstream_key;
+
+// 文本补全请求的提供商模型类型
+const TEXT_COMPLETION_MODEL_TYPE: &str = "TextCompletion";
+
+/// OpenAI格式的文本补全请求（旧版 /v1/completions）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CompletionRequest {
+    /// 模型名称
+    pub model: String,
+    /// 提示文本（字符串或字符串数组）
+    #[schema(value_type = Object)]
+    pub prompt: serde_json::Value,
+    /// 最大生成token数（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// 温度参数（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// top_p采样（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// 生成数量（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// 停止序列（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub stop: Option<serde_json::Value>,
+    /// 后缀（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    /// 是否回显提示（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<bool>,
+    /// 是否使用流式响应（可选，默认false）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// 终端用户标识（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// 处理文本补全请求
+#[utoipa::path(
+    post,
+    path = "/v1/completions",
+    request_body = CompletionRequest,
+    responses(
+        (status = 200, description = "成功生成文本补全（上游原始响应）"),
+        (status = 503, description = "服务不可用", body = ErrorResponse),
+    ),
+    tag = "completions"
+)]
+pub async fn handle_completion(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    gateway_key: Option<Extension<GatewayKeyIdentity>>,
+    headers: HeaderMap,
+    Json(request): Json<CompletionRequest>,
+) -> Response {
+    let client_ip = addr.ip().to_string();
+    let upstream_key = match upstream_key(&headers, state.config.byok.enabled) {
+        Ok(upstream_key) => upstream_key,
+        Err(e) => return error_response(StatusCode::FORBIDDEN, e),
+    };
+    let strategy = match request_strategy(&headers, gateway_key.as_ref().and_then(|key| key.load_balance_strategy.as_deref()), &state.config.load_balancing.strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    let stream = request.stream.unwrap_or(false);
+    info!(
+        "收到文本补全请求, 模型: {}, 流式请求: {}, 客户端IP: {}",
+        request.model, stream, client_ip
+    );
+
+    let target = PassthroughTarget {
+        model: &request.model,
+        model_type: TEXT_COMPLETION_MODEL_TYPE,
+        label: "文本补全请求",
+        upstream_key: upstream_key.as_ref(),
+        gateway_key_id: gateway_key.as_ref().map(|key| key.id.as_str()),
+        organization: gateway_key.as_ref().and_then(|key| key.organization.as_deref()),
+        strategy: &strategy,
+    };
+
+    if stream {
+        proxy_stream_request(state, target, &request, client_ip).await
+    } else {
+        proxy_json_request(&state, target, &request, &client_ip).await
+    }
+}
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::data_quality_event::{DataQualityEvent, ProviderDataQuality};
+use crate::routes::api::AppState;
+
+/// 数据质量统计查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DataQualityQuery {
+    /// 统计最近多少小时的事件（默认24）
+    pub since_hours: Option<i64>,
+}
+
+/// 提供商数据质量报告
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DataQualityReport {
+    /// 统计窗口（小时）
+    pub since_hours: i64,
+    /// 各提供商的统计，问题最多的排在前面
+    pub providers: Vec<ProviderDataQuality>,
+}
+
+/// 获取各提供商返回格式异常JSON的统计
+#[utoipa::path(
+    get,
+    path = "/v1/providers/data-quality",
+    params(DataQualityQuery),
+    responses(
+        (status = 200, description = "成功获取数据质量统计", body = DataQualityReport),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn get_data_quality(
+    State(state): State<AppState>,
+    Query(query): Query<DataQualityQuery>,
+) -> Response {
+    let since_hours = query.since_hours.unwrap_or(24).max(1);
+    match DataQualityEvent::summary(&state.db, Utc::now() - Duration::hours(since_hours)).await {
+        Ok(providers) => (StatusCode::OK, Json(DataQualityReport { since_hours, providers })).into_response(),
+        Err(e) => {
+            error!("获取数据质量统计失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("获取数据质量统计失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+use crate::services::burn_rate::{project_depletion, DepletionProjection};
+
+/// 余额耗尽预测查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DepletionQuery {
+    /// 计算消耗速度的统计窗口（天，默认7）
+    pub window_days: Option<i64>,
+    /// 日历提醒提前的天数（默认1）
+    pub remind_days_before: Option<i64>,
+}
+
+/// 余额耗尽预测列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DepletionFeed {
+    /// 统计窗口（天）
+    pub window_days: i64,
+    /// 各提供商的预测，按预计耗尽时间排序
+    pub projections: Vec<DepletionProjection>,
+}
+
+/// 获取各提供商余额预计耗尽时间（JSON）
+#[utoipa::path(
+    get,
+    path = "/v1/providers/depletion",
+    params(DepletionQuery),
+    responses(
+        (status = 200, description = "成功获取余额耗尽预测", body = DepletionFeed),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn get_depletion_feed(
+    State(state): State<AppState>,
+    Query(query): Query<DepletionQuery>,
+) -> Response {
+    let window_days = query.window_days.unwrap_or(7).max(1);
+    match project_depletion(&state.db, window_days).await {
+        Ok(projections) => (StatusCode::OK, Json(DepletionFeed { window_days, projections })).into_response(),
+        Err(e) => projection_error(e),
+    }
+}
+
+/// 获取各提供商充值提醒日历（iCal），可直接订阅到日历应用；无法设置请求头时可用 access_token 查询参数传入只读作用域令牌
+#[utoipa::path(
+    get,
+    path = "/v1/providers/depletion.ics",
+    params(DepletionQuery),
+    responses(
+        (status = 200, description = "iCal格式的充值提醒日历", content_type = "text/calendar"),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn get_depletion_calendar(
+    State(state): State<AppState>,
+    Query(query): Query<DepletionQuery>,
+) -> Response {
+    let window_days = query.window_days.unwrap_or(7).max(1);
+    let remind_days_before = query.remind_days_before.unwrap_or(1).max(0);
+    match project_depletion(&state.db, window_days).await {
+        Ok(projections) => (
+            StatusCode::OK,
+            [
+                ("Content-Type", "text/calendar; charset=utf-8"),
+                ("Content-Disposition", "inline; filename=\"provider-topups.ics\""),
+            ],
+            build_calendar(&projections, remind_days_before),
+        ).into_response(),
+        Err(e) => projection_error(e),
+    }
+}
+
+// 生成iCal日历：每个有消耗的提供商一个全天事件
+fn build_calendar(projections: &[DepletionProjection], remind_days_before: i64) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//api-manager//provider top-ups//ZH".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:API提供商充值提醒".to_string(),
+    ];
+
+    for projection in projections {
+        let depletion_date = match projection.depletion_date {
+            Some(date) => date,
+            None => continue,
+        };
+        let day = depletion_date.date_naive();
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:depletion-{}@api-manager", projection.provider_id),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", (day + Duration::days(1)).format("%Y%m%d")),
+            format!("SUMMARY:{}", escape_text(&format!("充值提醒: {} 预计余额耗尽", projection.name))),
+            format!(
+                "DESCRIPTION:{}",
+                escape_text(&format!(
+                    "当前余额: {:.4}\n最小阈值: {:.4}\n日均消耗: {:.4}\n预计可用天数: {:.1}",
+                    projection.balance,
+                    projection.min_balance_threshold,
+                    projection.daily_burn,
+                    projection.days_remaining.unwrap_or(0.0),
+                ))
+            ),
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", escape_text(&format!("{} 即将余额耗尽", projection.name))),
+            format!("TRIGGER:-P{}D", remind_days_before),
+            "END:VALARM".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    // iCal要求使用CRLF换行
+    lines.join("\r\n") + "\r\n"
+}
+
+// iCal文本转义
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn projection_error(e: sqlx::Error) -> Response {
+    error!("计算余额耗尽预测失败: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: format!("计算余额耗尽预测失败: {}", e) }),
+    ).into_response()
+}
@@ -0,0 +1,79 @@
+use axum::{
+    extract::{ConnectInfo, Json, State},
+    Extension,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::handlers::api::passthrough::{error_response, proxy_json_request, PassthroughTarget};
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::routes::api::AppState;
+use crate::utils::lb_strategy::request_strategy;
+use crate::utils::client_key::upstream_key;
+
+// 嵌入请求的提供商模型类型
+const EMBEDDING_MODEL_TYPE: &str = "Embedding";
+
+/// OpenAI格式的嵌入请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbeddingRequest {
+    /// 模型名称
+    pub model: String,
+    /// 输入文本（字符串、字符串数组或token数组）
+    #[schema(value_type = Object)]
+    pub input: serde_json::Value,
+    /// 返回格式（float/base64，可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding_format: Option<String>,
+    /// 输出向量维度（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+    /// 终端用户标识（可选）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// 处理嵌入请求
+#[utoipa::path(
+    post,
+    path = "/v1/embeddings",
+    request_body = EmbeddingRequest,
+    responses(
+        (status = 200, description = "成功生成嵌入向量（上游原始响应）"),
+        (status = 503, description = "服务不可用", body = ErrorResponse),
+    ),
+    tag = "embeddings"
+)]
+pub async fn handle_embeddings(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    gateway_key: Option<Extension<GatewayKeyIdentity>>,
+    headers: HeaderMap,
+    Json(request): Json<EmbeddingRequest>,
+) -> Response {
+    let client_ip = addr.ip().to_string();
+    let upstream_key = match upstream_key(&headers, state.config.byok.enabled) {
+        Ok(upstream_key) => upstream_key,
+        Err(e) => return error_response(StatusCode::FORBIDDEN, e),
+    };
+    let strategy = match request_strategy(&headers, gateway_key.as_ref().and_then(|key| key.load_balance_strategy.as_deref()), &state.config.load_balancing.strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    info!("收到嵌入请求, 模型: {}, 客户端IP: {}", request.model, client_ip);
+
+    let target = PassthroughTarget {
+        model: &request.model,
+        model_type: EMBEDDING_MODEL_TYPE,
+        label: "嵌入请求",
+        upstream_key: upstream_key.as_ref(),
+        gateway_key_id: gateway_key.as_ref().map(|key| key.id.as_str()),
+        organization: gateway_key.as_ref().and_then(|key| key.organization.as_deref()),
+        strategy: &strategy,
+    };
+    proxy_json_request(&state, target, &request, &client_ip).await
+}
@@ -0,0 +1,56 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+use crate::services::contract_expiry::{find_expiring, ExpiringProvider};
+
+/// 即将到期提供商查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExpiringQuery {
+    /// 列出多少天内到期的提供商（默认使用 PROVIDER_EXPIRY_ALERT_DAYS）
+    pub within_days: Option<i64>,
+}
+
+/// 即将到期的提供商列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpiringProviderList {
+    /// 查询窗口（天）
+    pub within_days: i64,
+    /// 即将到期或已到期的提供商，按到期时间排序
+    pub providers: Vec<ExpiringProvider>,
+}
+
+/// 获取合同即将到期（含已到期）的提供商
+#[utoipa::path(
+    get,
+    path = "/v1/providers/expiring",
+    params(ExpiringQuery),
+    responses(
+        (status = 200, description = "成功获取即将到期的提供商", body = ExpiringProviderList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn get_expiring_providers(
+    State(state): State<AppState>,
+    Query(query): Query<ExpiringQuery>,
+) -> Response {
+    let within_days = query.within_days.unwrap_or(state.config.provider_expiry.alert_days).max(0);
+    match find_expiring(&state.db, within_days).await {
+        Ok(providers) => (StatusCode::OK, Json(ExpiringProviderList { within_days, providers })).into_response(),
+        Err(e) => {
+            error!("获取即将到期的提供商失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("获取即将到期的提供商失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::fallback_response::FallbackResponse;
+use crate::routes::api::AppState;
+
+/// 设置兜底回复请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetFallbackResponseRequest {
+    /// 回复模板，支持 {model} 占位符
+    pub template: String,
+    /// 是否启用（默认启用）
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool { true }
+
+/// 兜底回复列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FallbackResponseList {
+    /// 兜底回复
+    pub fallbacks: Vec<FallbackResponse>,
+}
+
+/// 列出兜底回复
+#[utoipa::path(
+    get,
+    path = "/v1/fallbacks",
+    responses(
+        (status = 200, description = "成功获取兜底回复列表", body = FallbackResponseList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "fallbacks"
+)]
+pub async fn list_fallback_responses(
+    State(state): State<AppState>,
+) -> Response {
+    match FallbackResponse::list(&state.db).await {
+        Ok(fallbacks) => (StatusCode::OK, Json(FallbackResponseList { fallbacks })).into_response(),
+        Err(e) => internal_error("获取兜底回复列表失败", e),
+    }
+}
+
+/// 设置模型的兜底回复
+/// 聊天补全请求的所有提供商都失败时返回该回复（带 X-Gateway-Fallback 响应头和 x_gateway_fallback 字段），
+/// 模型为 * 时作为未单独配置的模型的默认回复
+#[utoipa::path(
+    put,
+    path = "/v1/fallbacks/{model}",
+    params(
+        ("model" = String, Path, description = "模型名称（* 表示默认规则）")
+    ),
+    request_body = SetFallbackResponseRequest,
+    responses(
+        (status = 200, description = "成功设置兜底回复", body = FallbackResponse),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "fallbacks"
+)]
+pub async fn set_fallback_response(
+    State(state): State<AppState>,
+    Path(model): Path<String>,
+    Json(request): Json<SetFallbackResponseRequest>,
+) -> Response {
+    if request.template.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "回复模板不能为空".to_string() })).into_response();
+    }
+
+    match FallbackResponse::upsert(&state.db, &model, &request.template, request.enabled).await {
+        Ok(fallback) => {
+            info!("兜底回复已设置: model={}, enabled={}", fallback.model, fallback.enabled);
+            (StatusCode::OK, Json(fallback)).into_response()
+        }
+        Err(e) => internal_error("设置兜底回复失败", e),
+    }
+}
+
+/// 删除模型的兜底回复
+#[utoipa::path(
+    delete,
+    path = "/v1/fallbacks/{model}",
+    params(
+        ("model" = String, Path, description = "模型名称（* 表示默认规则）")
+    ),
+    responses(
+        (status = 204, description = "成功删除兜底回复"),
+        (status = 404, description = "兜底回复不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "fallbacks"
+)]
+pub async fn delete_fallback_response(
+    State(state): State<AppState>,
+    Path(model): Path<String>,
+) -> Response {
+    match FallbackResponse::delete(&state.db, &model).await {
+        Ok(true) => {
+            info!("兜底回复已删除: model={}", model);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: format!("兜底回复不存在: {}", model) }),
+        ).into_response(),
+        Err(e) => internal_error("删除兜底回复失败", e),
+    }
+}
+
+fn internal_error(message: &str, e: sqlx::Error) -> Response {
+    error!("{}: {}", message, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: format!("{}: {}", message, e) }),
+    ).into_response()
+}
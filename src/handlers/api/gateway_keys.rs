@@ -0,0 +1,607 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::config::LOAD_BALANCE_STRATEGIES;
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::api_usage::KeyUsageDay;
+use crate::models::gateway_key::{GatewayKey, KeyRateLimits};
+use crate::models::gateway_key_spend::{effective_budget, GatewayKeySpend};
+use crate::routes::api::AppState;
+use crate::services::usage_rollup;
+
+/// 创建网关密钥请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateGatewayKeyRequest {
+    /// 密钥用途说明
+    pub name: String,
+    /// 每分钟请求数上限（缺省使用全局默认值，0表示不限制）
+    #[serde(default)]
+    pub requests_per_minute: Option<i64>,
+    /// 每分钟token数上限（缺省使用全局默认值，0表示不限制）
+    #[serde(default)]
+    pub tokens_per_minute: Option<i64>,
+    /// 月度预算（缺省使用全局默认值，0表示不限制）
+    #[serde(default)]
+    pub monthly_budget: Option<f64>,
+    /// 是否在聊天响应的 usage 中附带成本、上游耗时和当月累计花费
+    #[serde(default)]
+    pub usage_echo: bool,
+    /// 过期时间（缺省永不过期）
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 所属组织（设置后该密钥的请求只使用绑定到该组织的提供商）
+    #[serde(default)]
+    pub organization: Option<String>,
+    /// 是否开启严格 OpenAI 兼容模式：按 OpenAI 格式修复或拒绝不规范的聊天响应
+    #[serde(default)]
+    pub strict_openai: bool,
+    /// 默认负载均衡策略（RoundRobin、LeastConnections、LeastTokens、LeastCost、LowestLatency；缺省使用 LOAD_BALANCE_STRATEGY）
+    #[serde(default)]
+    pub load_balance_strategy: Option<String>,
+    /// 流式输出节流速率（tokens/秒），设置后该密钥的流式响应按此速率平滑下发；缺省使用提供商的设置
+    #[serde(default)]
+    pub stream_pacing_tps: Option<f64>,
+}
+
+/// 创建网关密钥响应（密钥明文仅返回这一次）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateGatewayKeyResponse {
+    /// 密钥明文
+    pub key: String,
+    /// 密钥信息
+    pub info: GatewayKey,
+}
+
+/// 更新网关密钥请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateGatewayKeyRequest {
+    /// 密钥用途说明
+    pub name: Option<String>,
+    /// 是否启用
+    pub enabled: Option<bool>,
+    /// 每分钟请求数上限（0表示不限制）
+    pub requests_per_minute: Option<i64>,
+    /// 每分钟token数上限（0表示不限制）
+    pub tokens_per_minute: Option<i64>,
+    /// 月度预算（0表示不限制）
+    pub monthly_budget: Option<f64>,
+    /// 是否在聊天响应的 usage 中附带成本、上游耗时和当月累计花费
+    pub usage_echo: Option<bool>,
+    /// 过期时间
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 所属组织
+    pub organization: Option<String>,
+    /// 是否开启严格 OpenAI 兼容模式
+    pub strict_openai: Option<bool>,
+    /// 默认负载均衡策略
+    pub load_balance_strategy: Option<String>,
+    /// 流式输出节流速率（tokens/秒）
+    pub stream_pacing_tps: Option<f64>,
+}
+
+/// 轮换网关密钥请求
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RotateGatewayKeyRequest {
+    /// 旧密钥继续有效的宽限期（秒，缺省使用 GATEWAY_KEY_ROTATION_GRACE_SECS，0表示立即失效）
+    #[serde(default)]
+    pub grace_period_secs: Option<u64>,
+    /// 新密钥的过期时间（缺省永不过期）
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 轮换网关密钥响应（新密钥明文仅返回这一次）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RotateGatewayKeyResponse {
+    /// 新密钥明文
+    pub key: String,
+    /// 新密钥信息
+    pub info: GatewayKey,
+    /// 被替代的旧密钥（expires_at 为宽限期结束时间）
+    pub previous: GatewayKey,
+}
+
+/// 网关密钥列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GatewayKeyList {
+    /// 密钥列表
+    pub keys: Vec<GatewayKey>,
+}
+
+/// 网关密钥当月预算状态
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GatewayKeyQuota {
+    /// 密钥ID
+    pub key_id: String,
+    /// 计费月份（UTC，YYYY-MM）
+    pub period: String,
+    /// 生效的月度预算（为空表示不限制）
+    pub monthly_budget: Option<f64>,
+    /// 当月累计花费
+    pub spent: f64,
+    /// 剩余预算（不限制时为空）
+    pub remaining: Option<f64>,
+    /// 预算是否已用完
+    pub exhausted: bool,
+    /// 最近一次手动重置时间
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+// 用量查询默认覆盖的天数和最大天数
+const DEFAULT_USAGE_DAYS: i64 = 30;
+const MAX_USAGE_DAYS: i64 = 366;
+
+/// 网关密钥用量查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct GatewayKeyUsageQuery {
+    /// 起始日期（UTC，YYYY-MM-DD，包含；默认为结束日期前29天）
+    #[param(value_type = Option<String>)]
+    pub from: Option<NaiveDate>,
+    /// 结束日期（UTC，YYYY-MM-DD，包含；默认为今天）
+    #[param(value_type = Option<String>)]
+    pub to: Option<NaiveDate>,
+}
+
+/// 网关密钥按天的用量
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GatewayKeyUsage {
+    /// 密钥ID
+    pub key_id: String,
+    /// 起始日期（UTC，包含）
+    pub from: NaiveDate,
+    /// 结束日期（UTC，包含）
+    pub to: NaiveDate,
+    /// 区间内的请求次数
+    pub requests: i64,
+    /// 区间内的失败请求数
+    pub errors: i64,
+    /// 区间内的失败率（0-1，无请求时为0）
+    pub error_rate: f64,
+    /// 区间内的总token
+    pub total_tokens: i64,
+    /// 区间内按定价估算的成本
+    pub cost: f64,
+    /// 每天的用量（没有请求的日期不列出）
+    pub days: Vec<KeyUsageDay>,
+}
+
+impl GatewayKeyUsage {
+    fn new(key_id: String, from: NaiveDate, to: NaiveDate, days: Vec<KeyUsageDay>) -> Self {
+        let requests = days.iter().map(|d| d.requests).sum::<i64>();
+        let errors = days.iter().map(|d| d.errors).sum::<i64>();
+        Self {
+            key_id,
+            from,
+            to,
+            requests,
+            errors,
+            error_rate: if requests > 0 { errors as f64 / requests as f64 } else { 0.0 },
+            total_tokens: days.iter().map(|d| d.total_tokens).sum(),
+            cost: days.iter().map(|d| d.cost).sum(),
+            days,
+        }
+    }
+}
+
+impl GatewayKeyQuota {
+    fn new(key: &GatewayKey, default_budget: f64, period: String, spend: Option<GatewayKeySpend>) -> Self {
+        let budget = effective_budget(key.monthly_budget, default_budget);
+        let spent = spend.as_ref().map(|s| s.spent).unwrap_or(0.0);
+        let monthly_budget = (budget > 0.0).then_some(budget);
+        Self {
+            key_id: key.id.clone(),
+            period,
+            monthly_budget,
+            spent,
+            remaining: monthly_budget.map(|budget| (budget - spent).max(0.0)),
+            exhausted: monthly_budget.is_some_and(|budget| spent >= budget),
+            reset_at: spend.and_then(|s| s.reset_at),
+        }
+    }
+}
+
+/// 创建网关密钥
+#[utoipa::path(
+    post,
+    path = "/v1/keys",
+    request_body = CreateGatewayKeyRequest,
+    responses(
+        (status = 201, description = "成功创建网关密钥", body = CreateGatewayKeyResponse),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn create_gateway_key(
+    State(state): State<AppState>,
+    Json(request): Json<CreateGatewayKeyRequest>,
+) -> Response {
+    if request.name.trim().is_empty() {
+        return bad_request("密钥名称不能为空".to_string());
+    }
+    let limits = KeyRateLimits {
+        requests_per_minute: request.requests_per_minute,
+        tokens_per_minute: request.tokens_per_minute,
+    };
+    if let Err(e) = validate_limits(&limits, request.monthly_budget)
+        .and_then(|_| validate_expiry(request.expires_at))
+        .and_then(|_| validate_strategy(request.load_balance_strategy.as_deref()))
+        .and_then(|_| validate_pacing(request.stream_pacing_tps))
+    {
+        return bad_request(e);
+    }
+
+    let created = GatewayKey::create(
+        &state.db,
+        request.name.trim(),
+        limits,
+        request.monthly_budget,
+        request.usage_echo,
+        request.expires_at,
+        organization(&request.organization),
+        request.strict_openai,
+        request.load_balance_strategy.as_deref(),
+        request.stream_pacing_tps,
+    );
+    match created.await {
+        Ok((info, key)) => {
+            info!("网关密钥已创建: name={}, prefix={}", info.name, info.key_prefix);
+            (StatusCode::CREATED, Json(CreateGatewayKeyResponse { key, info })).into_response()
+        }
+        Err(e) => internal_error("创建网关密钥失败", e),
+    }
+}
+
+/// 列出网关密钥
+#[utoipa::path(
+    get,
+    path = "/v1/keys",
+    responses(
+        (status = 200, description = "成功获取网关密钥列表", body = GatewayKeyList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn list_gateway_keys(
+    State(state): State<AppState>,
+) -> Response {
+    match GatewayKey::list(&state.db).await {
+        Ok(keys) => (StatusCode::OK, Json(GatewayKeyList { keys })).into_response(),
+        Err(e) => internal_error("获取网关密钥列表失败", e),
+    }
+}
+
+/// 获取网关密钥
+#[utoipa::path(
+    get,
+    path = "/v1/keys/{id}",
+    params(
+        ("id" = String, Path, description = "密钥ID")
+    ),
+    responses(
+        (status = 200, description = "成功获取网关密钥", body = GatewayKey),
+        (status = 404, description = "密钥不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn get_gateway_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    match GatewayKey::find(&state.db, &id).await {
+        Ok(Some(key)) => (StatusCode::OK, Json(key)).into_response(),
+        Ok(None) => not_found(format!("密钥不存在: {}", id)),
+        Err(e) => internal_error("获取网关密钥失败", e),
+    }
+}
+
+/// 更新网关密钥的名称、启用状态、限流设置或月度预算
+#[utoipa::path(
+    put,
+    path = "/v1/keys/{id}",
+    params(
+        ("id" = String, Path, description = "密钥ID")
+    ),
+    request_body = UpdateGatewayKeyRequest,
+    responses(
+        (status = 200, description = "成功更新网关密钥", body = GatewayKey),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 404, description = "密钥不存在或已吊销", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn update_gateway_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateGatewayKeyRequest>,
+) -> Response {
+    let name = request.name.as_deref().map(str::trim);
+    if name.is_some_and(str::is_empty) {
+        return bad_request("密钥名称不能为空".to_string());
+    }
+    let limits = KeyRateLimits {
+        requests_per_minute: request.requests_per_minute,
+        tokens_per_minute: request.tokens_per_minute,
+    };
+    if let Err(e) = validate_limits(&limits, request.monthly_budget)
+        .and_then(|_| validate_expiry(request.expires_at))
+        .and_then(|_| validate_strategy(request.load_balance_strategy.as_deref()))
+        .and_then(|_| validate_pacing(request.stream_pacing_tps))
+    {
+        return bad_request(e);
+    }
+
+    let updated = GatewayKey::update(
+        &state.db,
+        &id,
+        name,
+        request.enabled,
+        limits,
+        request.monthly_budget,
+        request.usage_echo,
+        request.expires_at,
+        organization(&request.organization),
+        request.strict_openai,
+        request.load_balance_strategy.as_deref(),
+        request.stream_pacing_tps,
+    );
+    match updated.await {
+        Ok(Some(key)) => {
+            info!("网关密钥已更新: id={}, name={}, enabled={}", key.id, key.name, key.enabled);
+            (StatusCode::OK, Json(key)).into_response()
+        }
+        Ok(None) => not_found(format!("密钥不存在或已吊销: {}", id)),
+        Err(e) => internal_error("更新网关密钥失败", e),
+    }
+}
+
+/// 吊销网关密钥
+#[utoipa::path(
+    delete,
+    path = "/v1/keys/{id}",
+    params(
+        ("id" = String, Path, description = "密钥ID")
+    ),
+    responses(
+        (status = 204, description = "成功吊销网关密钥"),
+        (status = 404, description = "密钥不存在或已吊销", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn revoke_gateway_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    match GatewayKey::revoke(&state.db, &id).await {
+        Ok(true) => {
+            info!("网关密钥已吊销: id={}", id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => not_found(format!("密钥不存在或已吊销: {}", id)),
+        Err(e) => internal_error("吊销网关密钥失败", e),
+    }
+}
+
+/// 轮换网关密钥
+/// 签发沿用原密钥名称、限流、预算等设置的新密钥，原密钥在宽限期内继续有效，之后自动过期
+#[utoipa::path(
+    post,
+    path = "/v1/keys/{id}/rotate",
+    params(
+        ("id" = String, Path, description = "密钥ID")
+    ),
+    request_body = RotateGatewayKeyRequest,
+    responses(
+        (status = 201, description = "成功签发替代密钥", body = RotateGatewayKeyResponse),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 404, description = "密钥不存在、已吊销或已过期", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn rotate_gateway_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<RotateGatewayKeyRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    if let Err(e) = validate_expiry(request.expires_at) {
+        return bad_request(e);
+    }
+    let grace_secs = request.grace_period_secs.unwrap_or(state.config.auth.gateway_key_rotation_grace_secs);
+    let grace_until = Utc::now() + Duration::seconds(grace_secs.min(i64::MAX as u64 / 1000) as i64);
+
+    match GatewayKey::rotate(&state.db, &id, grace_until, request.expires_at).await {
+        Ok(Some((info, key, previous))) => {
+            info!(
+                "网关密钥已轮换: name={}, 旧密钥={}, 新密钥={}, 旧密钥过期时间={:?}",
+                info.name, previous.key_prefix, info.key_prefix, previous.expires_at
+            );
+            (StatusCode::CREATED, Json(RotateGatewayKeyResponse { key, info, previous })).into_response()
+        }
+        Ok(None) => not_found(format!("密钥不存在、已吊销或已过期: {}", id)),
+        Err(e) => internal_error("轮换网关密钥失败", e),
+    }
+}
+
+/// 查看网关密钥当月的预算和花费
+#[utoipa::path(
+    get,
+    path = "/v1/keys/{id}/quota",
+    params(
+        ("id" = String, Path, description = "密钥ID")
+    ),
+    responses(
+        (status = 200, description = "成功获取预算状态", body = GatewayKeyQuota),
+        (status = 404, description = "密钥不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn get_gateway_key_quota(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let key = match GatewayKey::find(&state.db, &id).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return not_found(format!("密钥不存在: {}", id)),
+        Err(e) => return internal_error("获取网关密钥失败", e),
+    };
+
+    let period = GatewayKeySpend::current_period();
+    match GatewayKeySpend::find(&state.db, &id, &period).await {
+        Ok(spend) => {
+            let quota = GatewayKeyQuota::new(&key, state.config.auth.gateway_key_default_monthly_budget, period, spend);
+            (StatusCode::OK, Json(quota)).into_response()
+        }
+        Err(e) => internal_error("获取网关密钥花费失败", e),
+    }
+}
+
+/// 将网关密钥当月花费清零
+#[utoipa::path(
+    post,
+    path = "/v1/keys/{id}/quota/reset",
+    params(
+        ("id" = String, Path, description = "密钥ID")
+    ),
+    responses(
+        (status = 200, description = "成功重置预算", body = GatewayKeyQuota),
+        (status = 404, description = "密钥不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn reset_gateway_key_quota(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let key = match GatewayKey::find(&state.db, &id).await {
+        Ok(Some(key)) => key,
+        Ok(None) => return not_found(format!("密钥不存在: {}", id)),
+        Err(e) => return internal_error("获取网关密钥失败", e),
+    };
+
+    match GatewayKeySpend::reset(&state.db, &id).await {
+        Ok(spend) => {
+            info!("网关密钥当月花费已重置: id={}, period={}", id, spend.period);
+            let period = spend.period.clone();
+            let quota = GatewayKeyQuota::new(&key, state.config.auth.gateway_key_default_monthly_budget, period, Some(spend));
+            (StatusCode::OK, Json(quota)).into_response()
+        }
+        Err(e) => internal_error("重置网关密钥花费失败", e),
+    }
+}
+
+/// 查看网关密钥按天的用量（请求数、失败率、token数和估算成本）
+#[utoipa::path(
+    get,
+    path = "/v1/keys/{id}/usage",
+    params(
+        ("id" = String, Path, description = "密钥ID"),
+        GatewayKeyUsageQuery
+    ),
+    responses(
+        (status = 200, description = "成功获取密钥用量", body = GatewayKeyUsage),
+        (status = 400, description = "日期范围无效", body = ErrorResponse),
+        (status = 404, description = "密钥不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "keys"
+)]
+pub async fn get_gateway_key_usage(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<GatewayKeyUsageQuery>,
+) -> Response {
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query.from.unwrap_or(to - Duration::days(DEFAULT_USAGE_DAYS - 1));
+    if from > to {
+        return bad_request("from 不能晚于 to".to_string());
+    }
+    if (to - from).num_days() >= MAX_USAGE_DAYS {
+        return bad_request(format!("查询范围不能超过 {} 天", MAX_USAGE_DAYS));
+    }
+
+    match GatewayKey::find(&state.db, &id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(format!("密钥不存在: {}", id)),
+        Err(e) => return internal_error("获取网关密钥失败", e),
+    }
+
+    let start = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    match usage_rollup::daily_for_gateway_key(&state.db, &id, start, end).await {
+        Ok(days) => (StatusCode::OK, Json(GatewayKeyUsage::new(id, from, to, days))).into_response(),
+        Err(e) => internal_error("获取网关密钥用量失败", e),
+    }
+}
+
+fn validate_limits(limits: &KeyRateLimits, monthly_budget: Option<f64>) -> Result<(), String> {
+    if limits.requests_per_minute.is_some_and(|limit| limit < 0) {
+        return Err("requests_per_minute 不能为负数".to_string());
+    }
+    if limits.tokens_per_minute.is_some_and(|limit| limit < 0) {
+        return Err("tokens_per_minute 不能为负数".to_string());
+    }
+    if monthly_budget.is_some_and(|budget| !budget.is_finite() || budget < 0.0) {
+        return Err("monthly_budget 必须为非负数".to_string());
+    }
+    Ok(())
+}
+
+fn validate_expiry(expires_at: Option<DateTime<Utc>>) -> Result<(), String> {
+    if expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err("expires_at 必须晚于当前时间".to_string());
+    }
+    Ok(())
+}
+
+fn validate_strategy(strategy: Option<&str>) -> Result<(), String> {
+    match strategy {
+        Some(strategy) if !LOAD_BALANCE_STRATEGIES.contains(&strategy) => Err(format!(
+            "不支持的负载均衡策略: {}，可选: {}",
+            strategy,
+            LOAD_BALANCE_STRATEGIES.join("、")
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_pacing(stream_pacing_tps: Option<f64>) -> Result<(), String> {
+    match stream_pacing_tps {
+        Some(tps) if !tps.is_finite() || tps <= 0.0 => Err("stream_pacing_tps 必须大于0".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// 去掉组织名称两端空白，空字符串视为未设置
+fn organization(organization: &Option<String>) -> Option<&str> {
+    organization.as_deref().map(str::trim).filter(|o| !o.is_empty())
+}
+
+fn bad_request(error: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+}
+
+fn not_found(error: String) -> Response {
+    (StatusCode::NOT_FOUND, Json(ErrorResponse { error })).into_response()
+}
+
+fn internal_error(message: &str, e: sqlx::Error) -> Response {
+    error!("{}: {}", message, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: format!("{}: {}", message, e) }),
+    ).into_response()
+}
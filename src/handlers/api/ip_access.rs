@@ -0,0 +1,182 @@
+use axum::{
+    extract::{ConnectInfo, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::ip_access_rule::{IpAccessAction, IpAccessRule};
+use crate::routes::api::AppState;
+use crate::services::ip_access::IpNetwork;
+
+/// 添加IP访问规则请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateIpAccessRuleRequest {
+    /// CIDR 网段或单个IP，例如 10.0.0.0/8、2001:db8::/32、192.168.1.10
+    pub cidr: String,
+    /// 动作（Allow 或 Deny）
+    pub action: IpAccessAction,
+    /// 规则说明
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// IP访问规则列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IpAccessRuleList {
+    /// 环境变量 IP_ALLOWLIST 中的允许网段（只能通过修改配置并重启变更）
+    pub config_allow: Vec<String>,
+    /// 环境变量 IP_DENYLIST 中的拒绝网段（只能通过修改配置并重启变更）
+    pub config_deny: Vec<String>,
+    /// 运行时添加的规则
+    pub rules: Vec<IpAccessRule>,
+}
+
+/// 列出客户端IP访问规则
+#[utoipa::path(
+    get,
+    path = "/v1/admin/ip-rules",
+    responses(
+        (status = 200, description = "成功获取IP访问规则", body = IpAccessRuleList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "system"
+)]
+pub async fn list_ip_access_rules(
+    State(state): State<AppState>,
+) -> Response {
+    match IpAccessRule::list(&state.db).await {
+        Ok(rules) => {
+            let list = IpAccessRuleList {
+                config_allow: state.ip_access.config_allow(),
+                config_deny: state.ip_access.config_deny(),
+                rules,
+            };
+            (StatusCode::OK, Json(list)).into_response()
+        }
+        Err(e) => internal_error("获取IP访问规则失败", e),
+    }
+}
+
+/// 添加客户端IP访问规则，立即生效
+/// 会导致当前调用方被拒绝的规则不会被添加
+#[utoipa::path(
+    post,
+    path = "/v1/admin/ip-rules",
+    request_body = CreateIpAccessRuleRequest,
+    responses(
+        (status = 201, description = "成功添加IP访问规则", body = IpAccessRule),
+        (status = 400, description = "网段无效或会阻止当前调用方", body = ErrorResponse),
+        (status = 409, description = "相同的规则已存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "system"
+)]
+pub async fn create_ip_access_rule(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Json(request): Json<CreateIpAccessRuleRequest>,
+) -> Response {
+    // 保存规范形式（清零主机位），相同网段的不同写法视为同一条规则
+    let cidr = match IpNetwork::parse(&request.cidr) {
+        Ok(net) => net.to_string(),
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+
+    let mut rules = match IpAccessRule::list(&state.db).await {
+        Ok(rules) => rules,
+        Err(e) => return internal_error("获取IP访问规则失败", e),
+    };
+    rules.push(IpAccessRule {
+        id: String::new(),
+        cidr: cidr.clone(),
+        action: request.action.as_str().to_string(),
+        description: None,
+        created_at: Utc::now(),
+    });
+    if !state.ip_access.compose(&rules).permits(addr.ip()) {
+        return error_response(StatusCode::BAD_REQUEST, format!("该规则会阻止当前调用方 {} 访问", addr.ip()));
+    }
+
+    let rule = match IpAccessRule::create(&state.db, &cidr, request.action, request.description.as_deref()).await {
+        Ok(rule) => rule,
+        Err(e) if is_unique_violation(&e) => {
+            return error_response(StatusCode::CONFLICT, format!("规则已存在: {} {}", request.action.as_str(), cidr));
+        }
+        Err(e) => return internal_error("添加IP访问规则失败", e),
+    };
+    if let Err(e) = state.ip_access.reload(&state.db).await {
+        return internal_error("重新加载IP访问规则失败", e);
+    }
+
+    info!("IP访问规则已添加: id={}, action={}, cidr={}", rule.id, rule.action, rule.cidr);
+    (StatusCode::CREATED, Json(rule)).into_response()
+}
+
+/// 删除客户端IP访问规则，立即生效
+/// 会导致当前调用方被拒绝的删除（如删除唯一放行调用方的允许规则）不会执行
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/ip-rules/{id}",
+    params(
+        ("id" = String, Path, description = "规则ID")
+    ),
+    responses(
+        (status = 204, description = "成功删除IP访问规则"),
+        (status = 400, description = "删除后会阻止当前调用方", body = ErrorResponse),
+        (status = 404, description = "规则不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "system"
+)]
+pub async fn delete_ip_access_rule(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(id): Path<String>,
+) -> Response {
+    let mut rules = match IpAccessRule::list(&state.db).await {
+        Ok(rules) => rules,
+        Err(e) => return internal_error("获取IP访问规则失败", e),
+    };
+    let count = rules.len();
+    rules.retain(|rule| rule.id != id);
+    if rules.len() == count {
+        return error_response(StatusCode::NOT_FOUND, format!("规则不存在: {}", id));
+    }
+    if !state.ip_access.compose(&rules).permits(addr.ip()) {
+        return error_response(StatusCode::BAD_REQUEST, format!("删除该规则后会阻止当前调用方 {} 访问", addr.ip()));
+    }
+
+    match IpAccessRule::delete(&state.db, &id).await {
+        Ok(true) => {}
+        Ok(false) => return error_response(StatusCode::NOT_FOUND, format!("规则不存在: {}", id)),
+        Err(e) => return internal_error("删除IP访问规则失败", e),
+    }
+    if let Err(e) = state.ip_access.reload(&state.db).await {
+        return internal_error("重新加载IP访问规则失败", e);
+    }
+
+    info!("IP访问规则已删除: id={}", id);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().is_some_and(|e| e.is_unique_violation())
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
+
+fn internal_error(message: &str, e: sqlx::Error) -> Response {
+    error!("{}: {}", message, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: format!("{}: {}", message, e) }),
+    ).into_response()
+}
@@ -1,6 +1,27 @@
 pub mod chat_completion;
 pub mod provider;
 pub mod pricing;
+pub mod embeddings;
+pub mod completions;
+pub mod passthrough;
+pub mod billing;
+pub mod audio;
+pub mod moderations;
+pub mod depletion;
+pub mod admin_tokens;
+pub mod gateway_keys;
+pub mod data_quality;
+pub mod expiry;
+pub mod provider_stats;
+pub mod reconcile;
+pub mod system;
+pub mod users;
+pub mod usage_echo;
+pub mod strict_openai;
+pub mod fallbacks;
+pub mod ip_access;
+pub mod usage;
+pub mod model_catalog;
 
 pub use chat_completion::{
     handle_chat_completion,
@@ -9,6 +30,21 @@ pub use chat_completion::{
     Message,
 };
 
+pub use embeddings::{
+    handle_embeddings,
+    EmbeddingRequest,
+};
+
+pub use completions::{
+    handle_completion,
+    CompletionRequest,
+};
+
+pub use moderations::{
+    handle_moderation,
+    ModerationRequest,
+};
+
 pub use provider::{
     add_provider,
     batch_add_providers,
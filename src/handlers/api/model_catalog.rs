@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Extension, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use tracing::error;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::routes::api::AppState;
+use crate::services::model_catalog;
+use crate::services::provider_pool::load_provider;
+
+/// 立即从提供商的模型列表接口同步模型目录（后台任务 model_catalog_sync 也会定期同步）
+#[utoipa::path(
+    post,
+    path = "/v1/providers/{id}/sync-models",
+    params(
+        ("id" = String, Path, description = "提供商ID")
+    ),
+    responses(
+        (status = 200, description = "同步完成", body = ModelSyncResult),
+        (status = 400, description = "提供商不支持模型列表接口", body = ErrorResponse),
+        (status = 404, description = "提供商不存在", body = ErrorResponse),
+        (status = 502, description = "请求提供商的模型列表失败", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn sync_provider_models(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let provider = match load_provider(&state.db, &id).await {
+        Ok(Some(provider)) => provider,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse { error: format!("提供商不存在: {}", id) }),
+            ).into_response();
+        }
+        Err(e) => {
+            error!("读取提供商失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("读取提供商失败: {}", e) }),
+            ).into_response();
+        }
+    };
+
+    if !model_catalog::supports_sync(&provider) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: format!("提供商类型 {} 不支持模型列表接口", provider.provider_type) }),
+        ).into_response();
+    }
+
+    match model_catalog::sync_provider(&state.db, &id, &provider, &state.config.openrouter).await {
+        Ok(result) => (StatusCode::OK, Json(result)).into_response(),
+        Err(e) => {
+            error!("提供商 {} 模型目录同步失败: {}", id, e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse { error: format!("模型目录同步失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 查询网关模型目录中模型的能力信息（上下文窗口、是否支持图片输入和工具调用、价格），
+/// 数据来自提供商模型列表的同步结果；绑定组织的网关密钥同时能看到该组织的提供商
+#[utoipa::path(
+    get,
+    path = "/v1/models/{name}/metadata",
+    params(
+        ("name" = String, Path, description = "模型名称（含 / 的名称需要URL编码）")
+    ),
+    responses(
+        (status = 200, description = "成功获取模型能力信息", body = ModelMetadata),
+        (status = 404, description = "模型目录中没有该模型", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "models"
+)]
+pub async fn get_model_metadata(
+    State(state): State<AppState>,
+    gateway_key: Option<Extension<GatewayKeyIdentity>>,
+    Path(name): Path<String>,
+) -> Response {
+    let organization = gateway_key.and_then(|Extension(key)| key.organization);
+    match model_catalog::metadata(&state.db, &name, organization.as_deref()).await {
+        Ok(Some(metadata)) => (StatusCode::OK, Json(metadata)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: format!("模型目录中没有该模型: {}", name) }),
+        ).into_response(),
+        Err(e) => {
+            error!("查询模型能力信息失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("查询模型能力信息失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
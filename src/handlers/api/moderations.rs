@@ -0,0 +1,75 @@
+use axum::{
+    extract::{ConnectInfo, Json, State},
+    Extension,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::handlers::api::passthrough::{error_response, proxy_json_request, PassthroughTarget};
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::routes::api::AppState;
+use crate::utils::lb_strategy::request_strategy;
+use crate::utils::client_key::upstream_key;
+
+// 内容审核请求的提供商模型类型
+const MODERATION_MODEL_TYPE: &str = "Moderation";
+// 未指定模型时使用的默认审核模型
+const DEFAULT_MODERATION_MODEL: &str = "omni-moderation-latest";
+
+/// OpenAI格式的内容审核请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ModerationRequest {
+    /// 待审核的输入（字符串、字符串数组或多模态输入数组）
+    #[schema(value_type = Object)]
+    pub input: serde_json::Value,
+    /// 模型名称（可选，默认omni-moderation-latest）
+    #[serde(default = "default_moderation_model")]
+    pub model: String,
+}
+
+fn default_moderation_model() -> String { DEFAULT_MODERATION_MODEL.to_string() }
+
+/// 处理内容审核请求
+#[utoipa::path(
+    post,
+    path = "/v1/moderations",
+    request_body = ModerationRequest,
+    responses(
+        (status = 200, description = "成功完成内容审核（上游原始响应）"),
+        (status = 503, description = "服务不可用", body = ErrorResponse),
+    ),
+    tag = "moderations"
+)]
+pub async fn handle_moderation(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    gateway_key: Option<Extension<GatewayKeyIdentity>>,
+    headers: HeaderMap,
+    Json(request): Json<ModerationRequest>,
+) -> Response {
+    let client_ip = addr.ip().to_string();
+    let upstream_key = match upstream_key(&headers, state.config.byok.enabled) {
+        Ok(upstream_key) => upstream_key,
+        Err(e) => return error_response(StatusCode::FORBIDDEN, e),
+    };
+    let strategy = match request_strategy(&headers, gateway_key.as_ref().and_then(|key| key.load_balance_strategy.as_deref()), &state.config.load_balancing.strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    info!("收到内容审核请求, 模型: {}, 客户端IP: {}", request.model, client_ip);
+
+    let target = PassthroughTarget {
+        model: &request.model,
+        model_type: MODERATION_MODEL_TYPE,
+        label: "内容审核请求",
+        upstream_key: upstream_key.as_ref(),
+        gateway_key_id: gateway_key.as_ref().map(|key| key.id.as_str()),
+        organization: gateway_key.as_ref().and_then(|key| key.organization.as_deref()),
+        strategy: &strategy,
+    };
+    proxy_json_request(&state, target, &request, &client_ip).await
+}
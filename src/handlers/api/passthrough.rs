@@ -0,0 +1,381 @@
+use axum::{
+    body::Body,
+    http::StatusCode,
+    response::Response,
+};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::handlers::api::billing::charge_usage;
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::services::provider_errors::{self, ProviderErrorCategory};
+use crate::services::upstream_client;
+use crate::models::api_usage::{ApiCallStatus, ApiUsage};
+use crate::routes::api::AppState;
+use crate::services::{sse_error_event, SseDecoder, SseEvent, TokenManager};
+use crate::services::stream_heartbeat::{heartbeat_interval, with_heartbeat};
+use crate::services::stream_flush::{with_flush_policy, FlushPolicy};
+use crate::utils::client_key::UpstreamKey;
+use crate::utils::compression::encode_json_body;
+use crate::utils::lenient_json::parse_lenient;
+use crate::models::data_quality_event::DataQualityEvent;
+use crate::utils::response_limit::{read_limited_body, StreamSizeLimit};
+
+/// 透传请求的目标：模型名称和提供商模型类型
+pub struct PassthroughTarget<'a> {
+    /// 模型名称
+    pub model: &'a str,
+    /// 提供商模型类型（Embedding/TextCompletion等）
+    pub model_type: &'a str,
+    /// 日志中使用的请求名称
+    pub label: &'a str,
+    /// 客户端自带的上游密钥（BYOK）
+    pub upstream_key: Option<&'a UpstreamKey>,
+    /// 调用方的网关密钥ID
+    pub gateway_key_id: Option<&'a str>,
+    /// 调用方所属组织（只使用绑定到该组织的提供商）
+    pub organization: Option<&'a str>,
+    /// 首选的负载均衡策略
+    pub strategy: &'a str,
+}
+
+/// 将JSON请求原样转发给指定类型的提供商，按策略依次重试，返回上游原始响应
+pub async fn proxy_json_request<T: Serialize>(
+    state: &AppState,
+    target: PassthroughTarget<'_>,
+    request: &T,
+    client_ip: &str,
+) -> Response {
+    let mut last_error = None;
+    let strategies = [target.strategy, "LeastConnections", "LeastTokens"];
+
+    for strategy in strategies.iter() {
+        let token_manager = match TokenManager::acquire(
+            state.provider_pool.clone(),
+            state.concurrency.clone(),
+            target.model,
+            Some(target.model_type),
+            strategy,
+            target.organization,
+            target.upstream_key,
+        ).await {
+            Some(manager) => manager,
+            None => {
+                info!("{}：使用 {} 策略无法获取可用提供商，尝试下一个策略", target.label, strategy);
+                continue;
+            }
+        };
+
+        let request_start = std::time::Instant::now();
+        let response = match send_request(state, &token_manager, request).await {
+            Ok(response) => response,
+            Err(err) => {
+                error!("{}：请求发送失败: {}, 策略: {}", target.label, err, strategy);
+                provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::classify(&err), &err).await;
+                record_usage(state, &token_manager, target.model, (0, 0), ApiCallStatus::Error, client_ip, target.gateway_key_id).await;
+                last_error = Some(err);
+                continue;
+            }
+        };
+
+        let status = response.status();
+        token_manager.record_upstream_response(&response);
+
+        let body = match read_limited_body(response, state.config.response_limits.max_body_bytes).await {
+            Ok(body) => body,
+            Err(e) => {
+                error!("{}：{}", target.label, e);
+                provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::InvalidResponse, &e).await;
+                record_usage(state, &token_manager, target.model, (0, 0), ApiCallStatus::Error, client_ip, target.gateway_key_id).await;
+                last_error = Some(e);
+                continue;
+            }
+        };
+
+        if !status.is_success() {
+            error!("{}：API调用失败, 状态码: {}, 提供商: {}", target.label, status, token_manager.provider.base_url);
+            record_usage(state, &token_manager, target.model, (0, 0), ApiCallStatus::Error, client_ip, target.gateway_key_id).await;
+            let message = format!("API调用失败，状态码: {}，错误: {}", status, String::from_utf8_lossy(&body));
+            provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::from_status(status), &message).await;
+            last_error = Some(format!("API调用失败，状态码: {}", status));
+            continue;
+        }
+
+        // 严格解析失败时宽松解析重试一次，仍无法解析则视为提供商故障
+        let (body, json) = match serde_json::from_slice::<serde_json::Value>(&body) {
+            Ok(json) => (body, json),
+            Err(e) => {
+                error!("{}：解析响应失败: {}, 提供商: {}", target.label, e, token_manager.provider.base_url);
+                let parsed = parse_lenient::<serde_json::Value>(&String::from_utf8_lossy(&body));
+                let repairs = parsed.as_ref().map(|p| p.repairs.as_slice()).unwrap_or_default();
+                if let Err(db_err) = DataQualityEvent::record_malformed_json(
+                    &state.db,
+                    &token_manager.provider.api_key,
+                    parsed.is_ok(),
+                    repairs,
+                    &e.to_string(),
+                ).await {
+                    error!("{}：记录数据质量事件失败: {}", target.label, db_err);
+                }
+
+                match parsed {
+                    Ok(parsed) => {
+                        info!("{}：宽松解析响应成功, 修复项: {:?}", target.label, parsed.repairs);
+                        (Bytes::from(parsed.value.to_string()), parsed.value)
+                    }
+                    Err(_) => {
+                        record_usage(state, &token_manager, target.model, (0, 0), ApiCallStatus::Error, client_ip, target.gateway_key_id).await;
+                        last_error = Some(format!("解析响应失败: {}", e));
+                        provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::InvalidResponse, &format!("解析响应失败: {}", e)).await;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        token_manager.record_success(request_start.elapsed());
+
+        let tokens = extract_usage(&json);
+
+        token_manager.update_usage(tokens.0 + tokens.1).await;
+        record_usage(state, &token_manager, target.model, tokens, ApiCallStatus::Success, client_ip, target.gateway_key_id).await;
+
+        info!(
+            "{}：请求完成, 提供商: {}, 总tokens: {}",
+            target.label, token_manager.provider.base_url, tokens.0 + tokens.1
+        );
+
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+    }
+
+    let error_message = format!(
+        "所有可用的API提供商都失败了。最后的错误: {}",
+        last_error.unwrap_or_else(|| format!("未找到支持模型 {} 的提供商", target.model))
+    );
+    error!("{}：{}", target.label, error_message);
+
+    error_response(StatusCode::SERVICE_UNAVAILABLE, error_message)
+}
+
+/// 将流式JSON请求转发给指定类型的提供商，原样透传SSE响应并在结束后记录使用情况
+pub async fn proxy_stream_request<T: Serialize>(
+    state: AppState,
+    target: PassthroughTarget<'_>,
+    request: &T,
+    client_ip: String,
+) -> Response {
+    let token_manager = match TokenManager::acquire(
+        state.provider_pool.clone(),
+        state.concurrency.clone(),
+        target.model,
+        Some(target.model_type),
+        target.strategy,
+        target.organization,
+        target.upstream_key,
+    ).await {
+        Some(manager) => manager,
+        None => {
+            error!("{}：无法获取可用的提供商", target.label);
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("未找到支持模型 {} 的提供商", target.model),
+            );
+        }
+    };
+
+    let request_start = std::time::Instant::now();
+    let response = match send_request(&state, &token_manager, request).await {
+        Ok(response) => response,
+        Err(err) => {
+            error!("{}：流式请求发送失败: {}", target.label, err);
+            provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::classify(&err), &err).await;
+            record_usage(&state, &token_manager, target.model, (0, 0), ApiCallStatus::Error, &client_ip, target.gateway_key_id).await;
+            return error_response(StatusCode::BAD_GATEWAY, err);
+        }
+    };
+
+    let status = response.status();
+    token_manager.record_upstream_response(&response);
+    if !status.is_success() {
+        error!("{}：流式API调用失败, 状态码: {}", target.label, status);
+        provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::from_status(status), &format!("API调用失败，状态码: {}", status)).await;
+        record_usage(&state, &token_manager, target.model, (0, 0), ApiCallStatus::Error, &client_ip, target.gateway_key_id).await;
+        return error_response(StatusCode::BAD_GATEWAY, format!("API调用失败，状态码: {}", status));
+    }
+    token_manager.record_success(request_start.elapsed());
+
+    let model = target.model.to_string();
+    let label = target.label.to_string();
+    let gateway_key_id = target.gateway_key_id.map(str::to_string);
+    let heartbeat = heartbeat_interval(state.config.stream_heartbeat.interval_ms);
+    let flush_policy = FlushPolicy::from_config(&state.config.stream_flush);
+    let stream = async_stream::stream! {
+        let mut upstream = response.bytes_stream();
+        let mut tokens = None;
+        let mut size_limit = StreamSizeLimit::new(state.config.response_limits.max_stream_bytes);
+        let mut status = ApiCallStatus::Success;
+        let mut decoder = SseDecoder::new();
+
+        while let Some(chunk) = upstream.next().await {
+            match chunk {
+                Ok(data) => {
+                    if !size_limit.accept(data.len()) {
+                        error!("{}：上游流式响应超过大小限制，已截断", label);
+                        yield Ok(size_limit.truncation_event());
+                        status = ApiCallStatus::Error;
+                        break;
+                    }
+                    // 只转发完整事件，并记录最新出现的usage信息
+                    for event in decoder.push(&data) {
+                        if let Some(usage) = event_tokens(&event) {
+                            tokens = Some(usage);
+                        }
+                        yield Ok::<Bytes, std::io::Error>(event.raw);
+                    }
+                }
+                Err(e) => {
+                    error!("{}：接收数据流错误: {}", label, e);
+                    provider_errors::record(&state.db, &token_manager.provider, ProviderErrorCategory::Stream, &format!("接收数据流错误: {}", e)).await;
+                    yield Ok(sse_error_event(&format!("接收数据流错误: {}", e)));
+                    break;
+                }
+            }
+        }
+
+        if status == ApiCallStatus::Success {
+            if let Some(event) = decoder.finish() {
+                if let Some(usage) = event_tokens(&event) {
+                    tokens = Some(usage);
+                }
+                yield Ok(event.raw);
+            }
+        }
+
+        let tokens = tokens.unwrap_or((0, 0));
+        token_manager.update_usage(tokens.0 + tokens.1).await;
+        record_usage(&state, &token_manager, &model, tokens, status, &client_ip, gateway_key_id.as_deref()).await;
+    };
+
+    Response::builder()
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .header("Connection", "keep-alive")
+        .body(Body::from_stream(with_heartbeat(Box::pin(with_flush_policy(Box::pin(stream), flush_policy)), heartbeat)))
+        .unwrap()
+}
+
+// 发送请求到选中的提供商
+async fn send_request<T: Serialize>(
+    state: &AppState,
+    token_manager: &TokenManager,
+    request: &T,
+) -> Result<reqwest::Response, String> {
+    let client = upstream_client::provider_client(&state.config, &token_manager.provider)?;
+    upstream_client::record_request(&token_manager.provider.base_url);
+
+    // OpenRouter 需要带厂商前缀的完整模型名称
+    let body = if token_manager.provider.is_openrouter() {
+        let mut request = serde_json::to_value(request).map_err(|e| format!("序列化请求失败: {}", e))?;
+        request["model"] = token_manager.provider.model_name.clone().into();
+        encode_json_body(
+            &request,
+            token_manager.provider.supports_gzip_request,
+            state.config.request_compression.gzip_threshold_bytes,
+        )?
+    } else {
+        encode_json_body(
+            request,
+            token_manager.provider.supports_gzip_request,
+            state.config.request_compression.gzip_threshold_bytes,
+        )?
+    };
+
+    let mut request_builder = client
+        .post(&token_manager.provider.base_url)
+        .header("Content-Type", "application/json");
+    if let Some((auth_name, auth_value)) = token_manager.provider.auth_header() {
+        request_builder = request_builder.header(auth_name, auth_value);
+    }
+    for (name, value) in token_manager.provider.static_headers(&state.config.openrouter) {
+        request_builder = request_builder.header(name, value);
+    }
+    if let Some(encoding) = body.content_encoding {
+        request_builder = request_builder.header("Content-Encoding", encoding);
+    }
+
+    request_builder
+        .timeout(token_manager.provider.request_timeout(&state.config.upstream_timeouts))
+        .body(body.bytes)
+        .send()
+        .await
+        .map_err(|e| format!("请求失败: {}", e))
+}
+
+// 从响应JSON中读取 (prompt_tokens, completion_tokens)
+fn extract_usage(json: &serde_json::Value) -> (u32, u32) {
+    let usage = match json.get("usage") {
+        Some(usage) => usage,
+        None => return (0, 0),
+    };
+    let prompt = usage.get("prompt_tokens").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let completion = usage.get("completion_tokens").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    (prompt, completion)
+}
+
+// 从流式事件中读取usage信息，事件不含usage时返回None
+fn event_tokens(event: &SseEvent) -> Option<(u32, u32)> {
+    let data = event.data.as_deref()?;
+    if !data.contains("\"usage\"") {
+        return None;
+    }
+    let json = serde_json::from_str::<serde_json::Value>(data).ok()?;
+    json.get("usage").is_some_and(|u| !u.is_null()).then(|| extract_usage(&json))
+}
+
+// 记录透传请求的使用情况
+async fn record_usage(
+    state: &AppState,
+    token_manager: &TokenManager,
+    model: &str,
+    (prompt_tokens, completion_tokens): (u32, u32),
+    status: ApiCallStatus,
+    client_ip: &str,
+    gateway_key_id: Option<&str>,
+) {
+    let charge = status == ApiCallStatus::Success;
+    let mut usage = ApiUsage::new(
+        token_manager.provider.api_key.clone(),
+        model.to_string(),
+        prompt_tokens as i32,
+        completion_tokens as i32,
+        status,
+        Some(client_ip.to_string()),
+        None,
+    );
+    usage.own_key_id = token_manager.provider.own_key_id();
+    usage.gateway_key_id = gateway_key_id.map(str::to_string);
+    usage.organization = token_manager.provider.organization.clone();
+
+    let usage_id = usage.id.clone();
+    state.usage_recorder.record(usage).await;
+
+    if charge {
+        let cost = charge_usage(state, gateway_key_id, &token_manager.provider.api_key, model, (prompt_tokens, completion_tokens), &usage_id).await;
+        token_manager.record_cost(cost).await;
+    }
+}
+
+// 构建错误响应
+pub fn error_response(status: StatusCode, error: String) -> Response {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(&ErrorResponse { error }).unwrap()))
+        .unwrap()
+}
@@ -1,8 +1,9 @@
 use axum::{
     extract::{Json, Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 // use tracing::{error, info}; // 未使用，已注释
@@ -11,6 +12,9 @@ use utoipa::ToSchema;
 
 use crate::models::model_pricing::{ModelPricing, ModelPricingSummary};
 use crate::routes::api::AppState;
+use crate::services::list_cache::PRICING_KEY;
+use crate::utils::etag::cached_json_response;
+use sqlx::SqlitePool;
 
 /// 添加模型定价请求
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -104,15 +108,18 @@ pub async fn add_pricing(
         Some(effective_date),
     )
     .await {
-        Ok(pricing) => (
-            StatusCode::CREATED,
-            Json(PricingResponse {
-                success: true,
-                message: "成功添加模型定价".to_string(),
-                data: Some(pricing),
-            }),
-        )
-            .into_response(),
+        Ok(pricing) => {
+            state.list_cache.invalidate(PRICING_KEY);
+            (
+                StatusCode::CREATED,
+                Json(PricingResponse {
+                    success: true,
+                    message: "成功添加模型定价".to_string(),
+                    data: Some(pricing),
+                }),
+            )
+                .into_response()
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(PricingResponse {
@@ -131,45 +138,30 @@ pub async fn add_pricing(
     path = "/v1/pricing",
     responses(
         (status = 200, description = "成功获取所有模型定价", body = ModelPricingSummary),
+        (status = 304, description = "列表未变化（If-None-Match 与 ETag 匹配）"),
         (status = 500, description = "服务器错误", body = PricingResponse),
     ),
     tag = "pricing"
 )]
 pub async fn get_all_pricing(
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Response {
-    match sqlx::query_as::<_, ModelPricing>(
-        r#"
-        SELECT * FROM model_pricing
-        ORDER BY name, model, effective_date DESC
-        "#
-    )
-    .fetch_all(&state.db)
-    .await {
-        Ok(pricing_list) => {
-            let count = pricing_list.len();
-            let currencies: Vec<String> = pricing_list
-                .iter()
-                .map(|p| p.currency.clone())
-                .collect::<std::collections::HashSet<_>>()
-                .into_iter()
-                .collect();
-                
-            (
-                StatusCode::OK,
-                Json(ModelPricingSummary {
-                    pricing_list,
-                    count,
-                    currencies,
-                }),
-            )
-                .into_response()
-        }
-        Err(e) => (
+    let db = state.db.clone();
+    match state.list_cache.get(PRICING_KEY, || async move {
+        let summary = load_pricing(&db)
+            .await
+            .map_err(|e| format!("获取模型定价失败: {}", e))?;
+        serde_json::to_vec(&summary)
+            .map(Bytes::from)
+            .map_err(|e| format!("序列化模型定价失败: {}", e))
+    }).await {
+        Ok(list) => cached_json_response(&list, &headers),
+        Err(message) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(PricingResponse {
                 success: false,
-                message: format!("获取模型定价失败: {}", e),
+                message,
                 data: None,
             }),
         )
@@ -177,6 +169,31 @@ pub async fn get_all_pricing(
     }
 }
 
+// 查询所有定价记录并汇总币种
+async fn load_pricing(db: &SqlitePool) -> Result<ModelPricingSummary, sqlx::Error> {
+    let pricing_list = sqlx::query_as::<_, ModelPricing>(
+        r#"
+        SELECT * FROM model_pricing
+        ORDER BY name, model, effective_date DESC
+        "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let count = pricing_list.len();
+    let currencies: Vec<String> = pricing_list
+        .iter()
+        .map(|p| p.currency.clone())
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter()
+        .collect();
+    Ok(ModelPricingSummary {
+        pricing_list,
+        count,
+        currencies,
+    })
+}
+
 /// 获取特定提供商和模型的定价
 #[utoipa::path(
     get,
@@ -292,15 +309,18 @@ pub async fn update_pricing(
                 Some(effective_date),
             )
             .await {
-                Ok(pricing) => (
-                    StatusCode::OK,
-                    Json(PricingResponse {
-                        success: true,
-                        message: "成功更新模型定价".to_string(),
-                        data: Some(pricing),
-                    }),
-                )
-                    .into_response(),
+                Ok(pricing) => {
+                    state.list_cache.invalidate(PRICING_KEY);
+                    (
+                        StatusCode::OK,
+                        Json(PricingResponse {
+                            success: true,
+                            message: "成功更新模型定价".to_string(),
+                            data: Some(pricing),
+                        }),
+                    )
+                        .into_response()
+                }
                 Err(e) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
                     Json(PricingResponse {
@@ -1,70 +1,177 @@
 use axum::{
-    extract::{Json, State},
-    http::StatusCode,
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
-// use std::collections::HashMap; // 未使用，已注释
+use std::collections::HashMap;
 use tracing::{error, info};
 use crate::routes::api::AppState;
 use crate::models::api_provider::ProviderType;
 use crate::services::balance_checker::BalanceChecker;
-use crate::services::{ProviderInfo, provider_pool::initialize_provider_pool};
+use crate::services::{ProviderInfo, provider_pool::{refresh_providers, LOCAL_API_KEY_PREFIX}};
+use crate::services::import_jobs::ImportKeyResult;
+use crate::services::bedrock;
+use crate::services::upstream_client::HTTP_VERSIONS;
+use crate::services::list_cache::PROVIDERS_KEY;
+use crate::utils::etag::cached_json_response;
+use crate::utils::sigv4::AwsCredentials;
+use crate::utils::redact::redact;
+use futures_util::{stream, StreamExt};
 // use std::sync::Arc; // 未使用，已注释
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteQueryResult;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AddProviderRequest {
-    /// API密钥
+    /// API密钥（Bedrock 为 ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]；SelfHosted 可留空，不发送鉴权请求头）
+    #[serde(default)]
     pub api_key: String,
-    /// 提供商类型（OpenAI/Anthropic/DeepSeek/MistralAI/Custom）
+    /// 提供商类型（OpenAI/Anthropic/DeepSeek/MistralAI/Azure/Bedrock/SelfHosted/OpenRouter/Custom）
     pub provider_type: String,
     /// 模型名称
     pub model_name: String,
     /// 提供商名称（可选，默认使用provider_type-uuid后8位）
     #[serde(default)]
     pub name: Option<String>,
-    /// 基础URL（可选，根据provider_type自动设置）
+    /// 基础URL（可选，根据provider_type自动设置；Azure必填，为资源端点如 https://xxx.openai.azure.com；
+    /// SelfHosted必填，为服务地址如 http://localhost:11434）
     #[serde(default)]
     pub base_url: Option<String>,
     /// 是否为官方API（可选，默认false）
     #[serde(default)]
     pub is_official: bool,
-    /// 费率限制（可选，默认10）
+    /// 每分钟请求数上限（可选，默认10，0表示不限制）
     #[serde(default = "default_rate_limit")]
     pub rate_limit: u32,
+    /// 最大并发请求数（可选，默认10；启用并发自适应时作为初始上限）
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    /// 没有空闲连接许可时排队等待的最长时间（可选，毫秒，默认3000），超时后尝试下一个提供商
+    #[serde(default = "default_acquire_timeout_ms")]
+    pub acquire_timeout_ms: u32,
     /// 最小余额阈值（可选，默认0.0）
     #[serde(default = "default_min_balance_threshold")]
     pub min_balance_threshold: f64,
-    /// 是否支持余额检查（可选，默认true）
-    #[serde(default = "default_support_balance_check")]
-    pub support_balance_check: bool,
+    /// 是否支持余额检查（可选，默认true；SelfHosted默认false，改为探测模型列表接口）
+    #[serde(default)]
+    pub support_balance_check: Option<bool>,
     /// 模型类型（可选，默认ChatCompletion）
     #[serde(default = "default_model_type")]
     pub model_type: String,
     /// 模型版本（可选，默认v3）
     #[serde(default = "default_model_version")]
     pub model_version: String,
+    /// 余额查询URL（可选，设置后余额检查直接使用该URL）
+    #[serde(default)]
+    pub balance_check_url: Option<String>,
+    /// 流式输出节流速率（可选，tokens/秒，用于平滑打字机效果；网关密钥设置了 stream_pacing_tps 时以密钥为准）
+    #[serde(default)]
+    pub stream_pacing_tps: Option<f64>,
+    /// 提供商是否接受gzip压缩的请求体（可选，默认false）
+    #[serde(default)]
+    pub supports_gzip_request: bool,
+    /// 提供商是否支持结构化输出 response_format（可选，默认true）
+    #[serde(default = "default_supports_structured_output")]
+    pub supports_structured_output: bool,
+    /// Azure OpenAI 部署名称或 Bedrock 模型ID（provider_type为Azure/Bedrock时必填）
+    #[serde(default)]
+    pub deployment: Option<String>,
+    /// Azure OpenAI API版本（可选，默认2024-06-01）
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// AWS Bedrock 区域（provider_type为Bedrock时必填，如 us-east-1）
+    #[serde(default)]
+    pub aws_region: Option<String>,
+    /// 购买的额度（可选，合同信息）
+    #[serde(default)]
+    pub purchased_quota: Option<f64>,
+    /// 到期时间（可选，到期后停用，与余额无关）
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 供应商账号邮箱（可选，用于续费时查找账号）
+    #[serde(default)]
+    pub vendor_account_email: Option<String>,
+    /// 绑定的组织（可选，设置后该提供商只服务该组织的网关密钥，不进入共享池）
+    #[serde(default)]
+    pub organization: Option<String>,
+    /// 同一提供商的最大尝试次数（可选，含首次，默认3）
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    /// 重试间隔（可选，毫秒，默认1000）
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// 需要重试的上游状态码（可选，逗号分隔如 429,502,503；为空表示所有失败状态码都重试）
+    #[serde(default)]
+    pub retry_on_statuses: Option<String>,
+    /// 连接超时（可选，毫秒，为空时使用 UPSTREAM_CONNECT_TIMEOUT_MS）
+    #[serde(default)]
+    pub connect_timeout_ms: Option<u64>,
+    /// 请求超时（可选，毫秒，流式请求包含接收完整个响应的时间，为空时使用 UPSTREAM_REQUEST_TIMEOUT_MS）
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+    /// 优先级（可选，默认0，数值越大越优先；同一模型只在较高一层饱和或全部失败时才使用较低一层）
+    #[serde(default)]
+    pub priority: i32,
+    /// 每个上游域名最多保留的空闲连接数（可选，为空时不限制）
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<u64>,
+    /// TCP keepalive 和 HTTP/2 PING 的间隔（可选，秒，为空时不发送）
+    #[serde(default)]
+    pub keep_alive_secs: Option<u64>,
+    /// HTTP 协议版本（可选）：auto（TLS协商，默认）、http1（只用HTTP/1.1）、http2（直接使用HTTP/2，适用于h2c上游）
+    #[serde(default)]
+    pub http_version: Option<String>,
+    /// 附加请求头（可选，如 {"X-Tenant-Id": "t1"}），随该提供商的每个上游请求发送；不能覆盖鉴权和请求体相关的请求头
+    #[serde(default)]
+    pub extra_headers: Option<HashMap<String, String>>,
+    /// 额外信任的CA证书文件路径（可选，PEM，可包含多个证书），用于使用私有CA的自托管服务
+    #[serde(default)]
+    pub tls_ca_path: Option<String>,
+    /// 跳过TLS证书校验（可选，默认false，需显式开启，仅用于测试环境）
+    #[serde(default)]
+    pub tls_insecure_skip_verify: bool,
 }
 
 // 默认值函数
 fn default_rate_limit() -> u32 { 10 }
+fn default_max_connections() -> u32 { 10 }
+fn default_acquire_timeout_ms() -> u32 { 3000 }
 fn default_min_balance_threshold() -> f64 { 1.0 }
-fn default_support_balance_check() -> bool { true }
 fn default_model_type() -> String { "ChatCompletion".to_string() }
 fn default_model_version() -> String { "v3".to_string() }
+fn default_supports_structured_output() -> bool { true }
+fn default_retry_attempts() -> u32 { 3 }
+fn default_retry_delay_ms() -> u64 { 1000 }
+
+// Azure OpenAI 默认API版本
+const DEFAULT_AZURE_API_VERSION: &str = "2024-06-01";
+
+// 附加请求头不能覆盖的请求头（由网关根据提供商和请求体设置）
+const RESERVED_HEADERS: [&str; 6] = ["authorization", "api-key", "content-type", "content-length", "content-encoding", "host"];
 
 impl AddProviderRequest {
     fn get_default_base_url(&self) -> String {
-        match self.provider_type.as_str() {
+        let url = match self.provider_type.as_str() {
             "DeepSeek" => "https://api.siliconflow.cn/v1/chat/completions".to_string(),
             "OpenAI" => "https://api.openai.com/v1/chat/completions".to_string(),
             "Anthropic" => "https://api.anthropic.com/v1/messages".to_string(),
             "MistralAI" => "https://api.mistral.ai/v1/chat/completions".to_string(),
+            "OpenRouter" => "https://openrouter.ai/api/v1/chat/completions".to_string(),
             _ => "".to_string(),
+        };
+
+        // 嵌入、文本补全、音频转写和内容审核模型使用各自的端点
+        match self.model_type.as_str() {
+            "Embedding" => url.replace("/chat/completions", "/embeddings"),
+            "TextCompletion" => url.replace("/chat/completions", "/completions"),
+            "AudioTranscription" => url.replace("/chat/completions", "/audio/transcriptions"),
+            "Moderation" => url.replace("/chat/completions", "/moderations"),
+            _ => url,
         }
     }
 
@@ -80,8 +187,335 @@ impl AddProviderRequest {
     }
 
     fn get_base_url(&self) -> String {
+        if self.is_azure() {
+            return self.get_azure_url();
+        }
+        if self.is_self_hosted() {
+            return self.get_self_hosted_url();
+        }
+        if self.is_bedrock() {
+            let endpoint = self.base_url.clone().unwrap_or_else(|| {
+                bedrock::default_endpoint(self.aws_region.as_deref().unwrap_or_default())
+            });
+            return bedrock::invoke_url(&endpoint, self.deployment.as_deref().unwrap_or_default());
+        }
         self.base_url.clone().unwrap_or_else(|| self.get_default_base_url())
     }
+
+    fn is_azure(&self) -> bool {
+        self.provider_type == "Azure"
+    }
+
+    fn is_bedrock(&self) -> bool {
+        self.provider_type == "Bedrock"
+    }
+
+    fn is_self_hosted(&self) -> bool {
+        self.provider_type == "SelfHosted"
+    }
+
+    // 模型类型对应的 OpenAI 兼容接口路径
+    fn operation_path(&self) -> &'static str {
+        match self.model_type.as_str() {
+            "Embedding" => "embeddings",
+            "TextCompletion" => "completions",
+            "AudioTranscription" => "audio/transcriptions",
+            "Moderation" => "moderations",
+            _ => "chat/completions",
+        }
+    }
+
+    // Azure 按部署路由：{endpoint}/openai/deployments/{deployment}/{operation}?api-version=...
+    fn get_azure_url(&self) -> String {
+        let endpoint = self.base_url.as_deref().unwrap_or_default().trim_end_matches('/');
+        format!(
+            "{}/openai/deployments/{}/{}?api-version={}",
+            endpoint,
+            self.deployment.as_deref().unwrap_or_default(),
+            self.operation_path(),
+            self.get_api_version().unwrap_or_default(),
+        )
+    }
+
+    // 自托管服务只填写服务地址时补全 OpenAI 兼容接口路径，已包含 /v1/ 的完整地址原样使用
+    fn get_self_hosted_url(&self) -> String {
+        let base_url = self.base_url.as_deref().unwrap_or_default();
+        if base_url.contains("/v1/") {
+            return base_url.to_string();
+        }
+        format!("{}/v1/{}", base_url.trim_end_matches('/').trim_end_matches("/v1"), self.operation_path())
+    }
+
+    // 未填写密钥的自托管提供商以服务地址和模型生成唯一标识，重复添加时更新同一条记录
+    fn with_local_api_key(mut self) -> Self {
+        if self.is_self_hosted() && self.api_key.is_empty() {
+            self.api_key = format!("{}{}#{}", LOCAL_API_KEY_PREFIX, self.get_base_url(), self.model_name);
+        }
+        self
+    }
+
+    // 仅 Azure 和 Bedrock 提供商保存部署信息，Azure 的部署名称同时决定上游使用 api-key 鉴权
+    fn get_deployment(&self) -> Option<String> {
+        self.deployment.clone().filter(|_| self.is_azure() || self.is_bedrock())
+    }
+
+    fn get_aws_region(&self) -> Option<String> {
+        self.aws_region.clone().filter(|_| self.is_bedrock())
+    }
+
+    // 组织名称去掉两端空白，空字符串视为属于共享池
+    fn get_organization(&self) -> Option<String> {
+        self.organization.as_deref().map(str::trim).filter(|o| !o.is_empty()).map(str::to_string)
+    }
+
+    fn get_api_version(&self) -> Option<String> {
+        self.is_azure().then(|| {
+            self.api_version.clone().unwrap_or_else(|| DEFAULT_AZURE_API_VERSION.to_string())
+        })
+    }
+
+    // Azure 和 Bedrock 没有余额查询接口，始终跳过余额检查；自托管服务默认不检查余额
+    fn balance_check_enabled(&self) -> bool {
+        self.support_balance_check.unwrap_or(!self.is_self_hosted()) && !self.is_azure() && !self.is_bedrock()
+    }
+
+    // 校验提供商特有的必填字段
+    fn validate(&self) -> Result<(), String> {
+        if self.api_key.is_empty() && !self.is_self_hosted() {
+            return Err("必须提供 api_key".to_string());
+        }
+        if self.is_self_hosted() && self.base_url.as_deref().is_none_or(str::is_empty) {
+            return Err("SelfHosted 提供商必须提供 base_url（服务地址）".to_string());
+        }
+        if self.is_azure() {
+            if self.base_url.as_deref().is_none_or(str::is_empty) {
+                return Err("Azure 提供商必须提供 base_url（资源端点）".to_string());
+            }
+            if self.deployment.as_deref().is_none_or(str::is_empty) {
+                return Err("Azure 提供商必须提供 deployment（部署名称）".to_string());
+            }
+        }
+        if self.is_bedrock() {
+            if self.aws_region.as_deref().is_none_or(str::is_empty) {
+                return Err("Bedrock 提供商必须提供 aws_region".to_string());
+            }
+            if self.deployment.as_deref().is_none_or(str::is_empty) {
+                return Err("Bedrock 提供商必须提供 deployment（模型ID）".to_string());
+            }
+            if self.model_type != "ChatCompletion" {
+                return Err("Bedrock 提供商目前仅支持 ChatCompletion 模型".to_string());
+            }
+            AwsCredentials::parse(&self.api_key)?;
+        }
+        if self.retry_attempts == 0 {
+            return Err("retry_attempts 至少为1（含首次请求）".to_string());
+        }
+        if let Some(statuses) = self.retry_on_statuses.as_deref() {
+            for code in statuses.split(',').map(str::trim).filter(|code| !code.is_empty()) {
+                if !code.parse::<u16>().is_ok_and(|code| (400..600).contains(&code)) {
+                    return Err(format!("retry_on_statuses 包含无效的状态码: {}", code));
+                }
+            }
+        }
+        if self.max_connections == 0 {
+            return Err("max_connections 必须大于0".to_string());
+        }
+        if self.connect_timeout_ms == Some(0) || self.request_timeout_ms == Some(0) {
+            return Err("connect_timeout_ms 和 request_timeout_ms 必须大于0".to_string());
+        }
+        if self.keep_alive_secs == Some(0) {
+            return Err("keep_alive_secs 必须大于0".to_string());
+        }
+        if let Some(version) = self.http_version.as_deref() {
+            if !HTTP_VERSIONS.contains(&version.to_ascii_lowercase().as_str()) {
+                return Err(format!("http_version 必须是 {} 之一", HTTP_VERSIONS.join("、")));
+            }
+        }
+        for (name, value) in self.extra_headers.iter().flatten() {
+            let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("extra_headers 包含无效的请求头名称: {}", name))?;
+            if RESERVED_HEADERS.contains(&header_name.as_str()) {
+                return Err(format!("extra_headers 不能设置 {} 请求头", name));
+            }
+            if reqwest::header::HeaderValue::from_str(value).is_err() {
+                return Err(format!("extra_headers 中 {} 的值无效", name));
+            }
+        }
+        if let Some(ca_path) = self.tls_ca_path.as_deref() {
+            let pem = std::fs::read(ca_path).map_err(|e| format!("无法读取 tls_ca_path: {} - {}", ca_path, e))?;
+            if !reqwest::Certificate::from_pem_bundle(&pem).is_ok_and(|certificates| !certificates.is_empty()) {
+                return Err(format!("tls_ca_path 不是有效的PEM证书: {}", ca_path));
+            }
+        }
+        Ok(())
+    }
+
+    // 附加请求头保存为JSON对象，未设置或为空时保存为 NULL
+    fn extra_headers_json(&self) -> Option<String> {
+        self.extra_headers
+            .as_ref()
+            .filter(|headers| !headers.is_empty())
+            .and_then(|headers| serde_json::to_string(headers).ok())
+    }
+
+    // 创建临时的 ProviderInfo 用于检查余额
+    fn to_provider_info(&self) -> ProviderInfo {
+        ProviderInfo {
+            base_url: self.get_base_url(),
+            api_key: self.api_key.clone(),
+            own_api_key: None,
+            provider_type: self.provider_type.clone(),
+            max_connections: self.max_connections as i32,
+            min_connections: 1,
+            acquire_timeout_ms: self.acquire_timeout_ms as i32,
+            idle_timeout_ms: 600000,
+            load_balance_strategy: "RoundRobin".to_string(),
+            requests_per_minute: self.rate_limit as i64,
+            retry_attempts: self.retry_attempts as i32,
+            retry_delay_ms: self.retry_delay_ms as i64,
+            retry_on_statuses: self.retry_on_statuses.clone(),
+            connect_timeout_ms: self.connect_timeout_ms.map(|ms| ms as i64),
+            request_timeout_ms: self.request_timeout_ms.map(|ms| ms as i64),
+            priority: self.priority,
+            pool_max_idle_per_host: self.pool_max_idle_per_host.map(|max| max as i64),
+            keep_alive_secs: self.keep_alive_secs.map(|secs| secs as i64),
+            http_version: self.http_version.clone(),
+            extra_headers: self.extra_headers_json(),
+            tls_ca_path: self.tls_ca_path.clone(),
+            tls_insecure_skip_verify: self.tls_insecure_skip_verify,
+            balance: 0.0,
+            last_balance_check: None,
+            min_balance_threshold: self.min_balance_threshold,
+            support_balance_check: self.balance_check_enabled(),
+            balance_check_url: self.balance_check_url.clone(),
+            stream_pacing_tps: self.stream_pacing_tps,
+            supports_gzip_request: self.supports_gzip_request,
+            supports_structured_output: self.supports_structured_output,
+            deployment: self.get_deployment(),
+            api_version: self.get_api_version(),
+            aws_region: self.get_aws_region(),
+            expires_at: self.expires_at,
+            organization: self.get_organization(),
+            model_name: self.model_name.clone(),
+            model_type: self.model_type.clone(),
+            model_version: self.model_version.clone(),
+        }
+    }
+
+    // 验证API密钥有效性并检查余额是否满足最小阈值，失败时返回 (余额, 原因)
+    async fn verify(&self, balance_checker: &BalanceChecker) -> Result<f64, (Option<f64>, String)> {
+        if !self.balance_check_enabled() {
+            // 自托管服务没有计费接口，改为确认服务在线
+            if self.is_self_hosted() {
+                balance_checker
+                    .probe_health(&self.to_provider_info())
+                    .await
+                    .map_err(|e| (None, format!("自托管服务不可用: {}", e)))?;
+            }
+            return Ok(0.0);
+        }
+
+        match balance_checker.verify_api_key(&self.to_provider_info()).await {
+            Ok(balance) => {
+                info!("API密钥验证成功: api_key={}, balance={}", redact(&self.api_key), balance);
+
+                // 检查余额是否满足最小阈值
+                if balance < self.min_balance_threshold {
+                    error!("API密钥余额不足: api_key={}, balance={}, 最小阈值={}",
+                           redact(&self.api_key), balance, self.min_balance_threshold);
+                    return Err((
+                        Some(balance),
+                        format!("余额不足: {:.4} < {:.4}", balance, self.min_balance_threshold),
+                    ));
+                }
+
+                Ok(balance)
+            }
+            Err(e) => {
+                error!("API密钥验证失败: api_key={}, 错误={}", redact(&self.api_key), e);
+                Err((None, format!("API密钥验证失败: {}", e)))
+            }
+        }
+    }
+
+    // 保存到数据库 - 使用 INSERT OR REPLACE 来处理重复的 API key
+    async fn upsert(
+        &self,
+        db: &SqlitePool,
+        id: &str,
+        name: &str,
+        status: &str,
+        balance: f64,
+        now: DateTime<Utc>,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO api_providers (
+                id, name, provider_type, is_official, base_url, api_key,
+                status, rate_limit, balance, last_balance_check, min_balance_threshold,
+                support_balance_check, model_name, model_type, model_version,
+                balance_check_url, stream_pacing_tps, supports_gzip_request,
+                supports_structured_output, deployment, api_version, aws_region, purchased_quota, expires_at,
+                vendor_account_email, organization, retry_attempts, retry_delay_ms, retry_on_statuses,
+                connect_timeout_ms, request_timeout_ms, priority, pool_max_idle_per_host, keep_alive_secs, http_version,
+                max_connections, acquire_timeout_ms, extra_headers, tls_ca_path, tls_insecure_skip_verify, account_fingerprint, created_at, updated_at
+            ) VALUES (
+                COALESCE((SELECT id FROM api_providers WHERE api_key = ?), ?),
+                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                (SELECT account_fingerprint FROM api_providers WHERE api_key = ?),
+                COALESCE((SELECT created_at FROM api_providers WHERE api_key = ?), ?),
+                ?
+            )
+            "#,
+        )
+        .bind(&self.api_key)  // 用于查找现有记录的 api_key
+        .bind(id)             // 新的 id（如果是新记录）
+        .bind(name)
+        .bind(&self.provider_type)
+        .bind(self.is_official)
+        .bind(self.get_base_url())
+        .bind(&self.api_key)
+        .bind(status)
+        .bind(self.rate_limit)  // 使用请求中的 rate_limit（已有默认值10）
+        .bind(balance)
+        .bind(now)
+        .bind(self.min_balance_threshold)
+        .bind(self.balance_check_enabled())
+        .bind(&self.model_name)
+        .bind(&self.model_type)
+        .bind(&self.model_version)
+        .bind(&self.balance_check_url)
+        .bind(self.stream_pacing_tps)
+        .bind(self.supports_gzip_request)
+        .bind(self.supports_structured_output)
+        .bind(self.get_deployment())
+        .bind(self.get_api_version())
+        .bind(self.get_aws_region())
+        .bind(self.purchased_quota)
+        .bind(self.expires_at)
+        .bind(&self.vendor_account_email)
+        .bind(self.get_organization())
+        .bind(self.retry_attempts)
+        .bind(self.retry_delay_ms as i64)
+        .bind(&self.retry_on_statuses)
+        .bind(self.connect_timeout_ms.map(|ms| ms as i64))
+        .bind(self.request_timeout_ms.map(|ms| ms as i64))
+        .bind(self.priority)
+        .bind(self.pool_max_idle_per_host.map(|max| max as i64))
+        .bind(self.keep_alive_secs.map(|secs| secs as i64))
+        .bind(self.http_version.as_deref().map(str::to_ascii_lowercase))
+        .bind(self.max_connections)
+        .bind(self.acquire_timeout_ms)
+        .bind(self.extra_headers_json())
+        .bind(&self.tls_ca_path)
+        .bind(self.tls_insecure_skip_verify)
+        .bind(&self.api_key)  // 保留已记录的上游账户指纹
+        .bind(&self.api_key)  // 用于查找现有记录的 created_at
+        .bind(now)            // 新的 created_at（如果是新记录）
+        .bind(now)            // updated_at 总是更新为当前时间
+        .execute(db)
+        .await
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -112,6 +546,17 @@ pub struct ProviderAddResult {
 pub struct BatchAddProviderRequest {
     /// API提供商列表
     pub providers: Vec<AddProviderRequest>,
+    /// 是否异步验证（可选，默认false）。为true时密钥立即以Verifying状态入库，由后台任务并发验证
+    #[serde(default)]
+    pub async_verify: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportJobAccepted {
+    /// 导入任务ID，用于查询验证进度
+    pub job_id: String,
+    /// 已接收的API密钥数量
+    pub total: usize,
 }
 
 /// 生成UUID作为提供商ID
@@ -135,7 +580,12 @@ pub async fn add_provider(
     State(state): State<AppState>,
     Json(request): Json<AddProviderRequest>,
 ) -> Response {
-    info!("收到添加API提供商请求: {:?}", request);
+    info!("收到添加API提供商请求: provider_type={}, model={}, api_key={}", request.provider_type, request.model_name, redact(&request.api_key));
+
+    if let Err(error) = request.validate() {
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response();
+    }
+    let request = request.with_local_api_key();
 
     let mut success = Vec::new();
     let mut failed = Vec::new();
@@ -149,30 +599,23 @@ pub async fn add_provider(
         "Anthropic" => ProviderType::Anthropic,
         "DeepSeek" => ProviderType::DeepSeek,
         "MistralAI" => ProviderType::MistralAI,
+        "Azure" => ProviderType::AzureOpenAI,
+        "Bedrock" => ProviderType::Bedrock,
+        "SelfHosted" => ProviderType::SelfHosted,
+        "OpenRouter" => ProviderType::OpenRouter,
         custom => ProviderType::Custom(custom.to_string()),
     };
 
     // 创建临时的 ProviderInfo 用于检查余额
-    let mut provider_info = ProviderInfo {
-        base_url: request.get_base_url(),
-        api_key: request.api_key.clone(),
-        max_connections: 10,
-        min_connections: 1,
-        acquire_timeout_ms: 3000,
-        idle_timeout_ms: 600000,
-        load_balance_strategy: "RoundRobin".to_string(),
-        retry_attempts: 3,
-        balance: 0.0,
-        last_balance_check: None,
-        min_balance_threshold: request.min_balance_threshold,
-        support_balance_check: request.support_balance_check,
-        model_name: request.model_name.clone(),
-        model_type: request.model_type.clone(),
-        model_version: request.model_version.clone(),
-    };
+    let mut provider_info = request.to_provider_info();
 
     // 初始化 BalanceChecker，传入 db 和 provider_pool
-    let balance_checker = BalanceChecker::new(state.db.clone().into(), state.provider_pool.clone());
+    let balance_checker = BalanceChecker::new(
+        state.db.clone().into(),
+        state.provider_pool.clone(),
+        state.config.health_check.quarantine_failure_threshold,
+        state.config.health_check.timeout,
+    );
 
     // 检查余额
     if provider_info.support_balance_check {
@@ -202,48 +645,34 @@ pub async fn add_provider(
                 return (StatusCode::OK, Json(AddProviderResponse { success, failed })).into_response();
             }
         }
+    } else if provider_info.is_self_hosted() {
+        // 自托管服务没有计费接口，确认服务在线后再添加
+        if let Err(e) = balance_checker.probe_health(&provider_info).await {
+            failed.push(ProviderAddResult {
+                id: None,
+                name: request.get_name(),
+                api_key: request.api_key.clone(),
+                balance: None,
+                error: Some(format!("自托管服务不可用: {}", e)),
+                created_at: None,
+            });
+            return (StatusCode::OK, Json(AddProviderResponse { success, failed })).into_response();
+        }
     }
 
     // 保存到数据库 - 使用 INSERT OR REPLACE 来处理重复的 API key
     let now = Utc::now();
-    match sqlx::query(
-        r#"
-        INSERT OR REPLACE INTO api_providers (
-            id, name, provider_type, is_official, base_url, api_key,
-            status, rate_limit, balance, last_balance_check, min_balance_threshold,
-            support_balance_check, model_name, model_type, model_version,
-            created_at, updated_at
-        ) VALUES (
-            COALESCE((SELECT id FROM api_providers WHERE api_key = ?), ?),
-            ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
-            COALESCE((SELECT created_at FROM api_providers WHERE api_key = ?), ?),
-            ?
-        )
-        "#,
-    )
-    .bind(&request.api_key)  // 用于查找现有记录的 api_key
-    .bind(&id)               // 新的 id（如果是新记录）
-    .bind(&request.get_name())
-    .bind(&request.provider_type)
-    .bind(request.is_official)
-    .bind(&request.get_base_url())
-    .bind(&request.api_key)
-    .bind("Active")
-    .bind(request.rate_limit)  // 使用请求中的 rate_limit（已有默认值10）
-    .bind(provider_info.balance)
-    .bind(now)
-    .bind(request.min_balance_threshold)
-    .bind(request.support_balance_check)
-    .bind(&request.model_name)
-    .bind(&request.model_type)
-    .bind(&request.model_version)
-    .bind(&request.api_key)  // 用于查找现有记录的 created_at
-    .bind(now)               // 新的 created_at（如果是新记录）
-    .bind(now)               // updated_at 总是更新为当前时间
-    .execute(&state.db)
-    .await
+    match request
+        .upsert(&state.db, &id, &request.get_name(), "Active", provider_info.balance, now)
+        .await
     {
         Ok(_) => {
+            state.list_cache.invalidate(PROVIDERS_KEY);
+            // 更新provider pool（只更新该提供商，保留其他提供商的运行状态）
+            if let Err(e) = refresh_providers(&state.db, &state.provider_pool, std::slice::from_ref(&request.api_key)).await {
+                error!("更新提供商池失败: {}", e);
+            }
+
             success.push(ProviderAddResult {
                 id: Some(id),
                 name: request.get_name(),
@@ -253,12 +682,6 @@ pub async fn add_provider(
                 created_at: Some(now),
             });
 
-            // 更新provider pool
-            if let Ok(new_pool) = initialize_provider_pool(&state.db).await {
-                let mut pool = state.provider_pool.lock().await;
-                *pool = new_pool;
-            }
-
             (StatusCode::CREATED, Json(AddProviderResponse { success, failed })).into_response()
         }
         Err(e) => {
@@ -283,6 +706,7 @@ pub async fn add_provider(
     request_body = BatchAddProviderRequest,
     responses(
         (status = 201, description = "成功添加API提供商", body = AddProviderResponse),
+        (status = 202, description = "已接收，后台异步验证（async_verify=true）", body = ImportJobAccepted),
         (status = 400, description = "请求参数错误", body = ErrorResponse),
         (status = 500, description = "服务器内部错误", body = ErrorResponse),
     ),
@@ -292,7 +716,18 @@ pub async fn batch_add_providers(
     State(state): State<AppState>,
     Json(request): Json<BatchAddProviderRequest>,
 ) -> Response {
-    info!("收到批量添加API提供商请求: {:?}", request);
+    info!("收到批量添加API提供商请求: 数量={}, 异步验证={}", request.providers.len(), request.async_verify);
+
+    if request.async_verify {
+        return start_import_job(state, request.providers).await;
+    }
+
+    let balance_checker = BalanceChecker::new(
+        state.db.clone().into(),
+        state.provider_pool.clone(),
+        state.config.health_check.quarantine_failure_threshold,
+        state.config.health_check.timeout,
+    );
 
     let mut success = Vec::new();
     let mut failed = Vec::new();
@@ -307,117 +742,55 @@ pub async fn batch_add_providers(
             "Anthropic" => ProviderType::Anthropic,
             "DeepSeek" => ProviderType::DeepSeek,
             "MistralAI" => ProviderType::MistralAI,
+            "Azure" => ProviderType::AzureOpenAI,
+            "Bedrock" => ProviderType::Bedrock,
+            "SelfHosted" => ProviderType::SelfHosted,
+            "OpenRouter" => ProviderType::OpenRouter,
             custom => ProviderType::Custom(custom.to_string()),
         };
 
-        // 创建临时的 ProviderInfo 用于检查余额
-        let provider_info = ProviderInfo {
-            base_url: provider_request.get_base_url(),
-            api_key: provider_request.api_key.clone(),
-            max_connections: 10,
-            min_connections: 1,
-            acquire_timeout_ms: 3000,
-            idle_timeout_ms: 600000,
-            load_balance_strategy: "RoundRobin".to_string(),
-            retry_attempts: 3,
-            balance: 0.0,
-            last_balance_check: None,
-            min_balance_threshold: provider_request.min_balance_threshold,
-            support_balance_check: provider_request.support_balance_check,
-            model_name: provider_request.model_name.clone(),
-            model_type: provider_request.model_type.clone(),
-            model_version: provider_request.model_version.clone(),
-        };
+        if let Err(error) = provider_request.validate() {
+            failed.push(ProviderAddResult {
+                id: None,
+                name: provider_request.get_name(),
+                api_key: provider_request.api_key.clone(),
+                balance: None,
+                error: Some(error),
+                created_at: None,
+            });
+            continue;
+        }
+        let provider_request = provider_request.with_local_api_key();
 
         // 先验证API密钥有效性
-        let balance_checker = BalanceChecker::new(state.db.clone().into(), state.provider_pool.clone());
-        let verified_balance = if provider_info.support_balance_check {
-            match balance_checker.verify_api_key(&provider_info).await {
-                Ok(balance) => {
-                    info!("API密钥验证成功: api_key={}, balance={}", 
-                          provider_request.api_key, balance);
-                    
-                    // 检查余额是否满足最小阈值
-                    if balance < provider_request.min_balance_threshold {
-                        error!("API密钥余额不足: api_key={}, balance={}, 最小阈值={}", 
-                               provider_request.api_key, balance, provider_request.min_balance_threshold);
-                        failed.push(ProviderAddResult {
-                            id: None,
-                            name: provider_request.get_name(),
-                            api_key: provider_request.api_key.clone(),
-                            balance: Some(balance),
-                            error: Some(format!("余额不足: {:.4} < {:.4}", balance, provider_request.min_balance_threshold)),
-                            created_at: None,
-                        });
-                        continue;
-                    }
-                    
-                    balance
-                }
-                Err(e) => {
-                    error!("API密钥验证失败: api_key={}, 错误={}", 
-                           provider_request.api_key, e);
-                    failed.push(ProviderAddResult {
-                        id: None,
-                        name: provider_request.get_name(),
-                        api_key: provider_request.api_key.clone(),
-                        balance: None,
-                        error: Some(format!("API密钥验证失败: {}", e)),
-                        created_at: None,
-                    });
-                    continue;
-                }
+        let verified_balance = match provider_request.verify(&balance_checker).await {
+            Ok(balance) => balance,
+            Err((balance, error)) => {
+                failed.push(ProviderAddResult {
+                    id: None,
+                    name: provider_request.get_name(),
+                    api_key: provider_request.api_key.clone(),
+                    balance,
+                    error: Some(error),
+                    created_at: None,
+                });
+                continue;
             }
-        } else {
-            provider_info.balance
         };
 
         // 验证通过后，保存到数据库
         let now = Utc::now();
         info!("开始保存已验证的提供商到数据库: api_key={}, name={}, balance={}", 
-              provider_request.api_key, provider_request.get_name(), verified_balance);
+              redact(&provider_request.api_key), provider_request.get_name(), verified_balance);
         
-        let result = sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO api_providers (
-                id, name, provider_type, is_official, base_url, api_key,
-                status, rate_limit, balance, last_balance_check, min_balance_threshold,
-                support_balance_check, model_name, model_type, model_version,
-                created_at, updated_at
-            ) VALUES (
-                COALESCE((SELECT id FROM api_providers WHERE api_key = ?), ?),
-                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
-                COALESCE((SELECT created_at FROM api_providers WHERE api_key = ?), ?),
-                ?
-            )
-            "#,
-        )
-        .bind(&provider_request.api_key)  // 用于查找现有记录的 api_key
-        .bind(&id)                        // 新的 id（如果是新记录）
-        .bind(&provider_request.get_name())
-        .bind(&provider_request.provider_type)
-        .bind(provider_request.is_official)
-        .bind(&provider_request.get_base_url())
-        .bind(&provider_request.api_key)
-        .bind("Active")
-        .bind(provider_request.rate_limit)  // 使用请求中的 rate_limit（已有默认值10）
-        .bind(verified_balance)
-        .bind(now)
-        .bind(provider_request.min_balance_threshold)
-        .bind(provider_request.support_balance_check)
-        .bind(&provider_request.model_name)
-        .bind(&provider_request.model_type)
-        .bind(&provider_request.model_version)
-        .bind(&provider_request.api_key)  // 用于查找现有记录的 created_at
-        .bind(now)                        // 新的 created_at（如果是新记录）
-        .bind(now)                        // updated_at 总是更新为当前时间
-        .execute(&state.db)
-        .await;
+        let result = provider_request
+            .upsert(&state.db, &id, &provider_request.get_name(), "Active", verified_balance, now)
+            .await;
 
         match result {
             Ok(exec_result) => {
                 info!("提供商保存成功: api_key={}, 影响行数={}", 
-                      provider_request.api_key, exec_result.rows_affected());
+                      redact(&provider_request.api_key), exec_result.rows_affected());
                 
                 // 验证数据是否真的保存到数据库
                 let verify_count = sqlx::query_scalar::<_, i64>(
@@ -430,11 +803,11 @@ pub async fn batch_add_providers(
                 match verify_count {
                     Ok(count) => {
                         info!("验证保存结果: api_key={}, 数据库中的记录数={}", 
-                              provider_request.api_key, count);
+                              redact(&provider_request.api_key), count);
                     }
                     Err(e) => {
                         error!("验证保存结果失败: api_key={}, 错误={}", 
-                               provider_request.api_key, e);
+                               redact(&provider_request.api_key), e);
                     }
                 }
                 
@@ -450,12 +823,12 @@ pub async fn batch_add_providers(
                 });
             }
             Err(e) => {
-                error!("保存提供商失败: api_key={}, 错误={}", provider_request.api_key, e);
+                error!("保存提供商失败: api_key={}, 错误={}", redact(&provider_request.api_key), e);
                 failed.push(ProviderAddResult {
                     id: None,
                     name: provider_request.get_name(),
                     api_key: provider_request.api_key,
-                    balance: Some(provider_info.balance),
+                    balance: Some(verified_balance),
                     error: Some(format!("保存提供商失败: {}", e)),
                     created_at: None,
                 });
@@ -463,13 +836,13 @@ pub async fn batch_add_providers(
         }
     }
 
-    // 更新provider pool
+    // 更新provider pool（只更新新增的提供商，保留其他提供商的运行状态）
     if !success.is_empty() {
-        info!("开始重新加载提供商池，成功添加了 {} 个提供商", success.len());
-        if let Ok(new_pool) = initialize_provider_pool(&state.db).await {
-            let mut pool = state.provider_pool.lock().await;
-            *pool = new_pool;
-            info!("提供商池重新加载完成，当前有 {} 个提供商", pool.get_providers().len());
+        info!("开始更新提供商池，成功添加了 {} 个提供商", success.len());
+        state.list_cache.invalidate(PROVIDERS_KEY);
+        let api_keys: Vec<String> = success.iter().map(|result| result.api_key.clone()).collect();
+        if let Err(e) = refresh_providers(&state.db, &state.provider_pool, &api_keys).await {
+            error!("更新提供商池失败: {}", e);
         }
     }
 
@@ -478,21 +851,212 @@ pub async fn batch_add_providers(
     (StatusCode::CREATED, Json(response)).into_response()
 }
 
+// 异步导入后台验证的最大并发数
+const IMPORT_VERIFY_CONCURRENCY: usize = 8;
+
+// 异步导入：密钥先以 Verifying 状态入库，再由后台任务并发验证
+async fn start_import_job(state: AppState, providers: Vec<AddProviderRequest>) -> Response {
+    if let Some(error) = providers.iter().find_map(|p| p.validate().err()) {
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response();
+    }
+    let providers: Vec<_> = providers.into_iter().map(AddProviderRequest::with_local_api_key).collect();
+
+    let job_id = generate_uuid();
+    let now = Utc::now();
+    let mut accepted = Vec::new();
+
+    for provider_request in providers {
+        let id = generate_uuid();
+        let name = provider_request.get_name();
+        match provider_request.upsert(&state.db, &id, &name, "Verifying", 0.0, now).await {
+            Ok(_) => accepted.push((provider_request, name)),
+            Err(e) => {
+                error!("保存待验证提供商失败: api_key={}, 错误={}", redact(&provider_request.api_key), e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse { error: format!("保存提供商失败: {}", e) }),
+                ).into_response();
+            }
+        }
+    }
+
+    let total = accepted.len();
+    state.import_jobs.create(job_id.clone(), total).await;
+    info!("异步导入任务已创建: job_id={}, 密钥数={}", job_id, total);
+
+    tokio::spawn(run_import_job(state, job_id.clone(), accepted));
+
+    (StatusCode::ACCEPTED, Json(ImportJobAccepted { job_id, total })).into_response()
+}
+
+// 后台并发验证导入的密钥，验证通过的转为 Active，失败的从数据库删除
+async fn run_import_job(state: AppState, job_id: String, providers: Vec<(AddProviderRequest, String)>) {
+    let balance_checker = BalanceChecker::new(
+        state.db.clone().into(),
+        state.provider_pool.clone(),
+        state.config.health_check.quarantine_failure_threshold,
+        state.config.health_check.timeout,
+    );
+
+    let mut results = stream::iter(providers)
+        .map(|(provider_request, name)| {
+            let state = &state;
+            let balance_checker = &balance_checker;
+            async move {
+                let id = sqlx::query_scalar::<_, String>("SELECT id FROM api_providers WHERE api_key = ?")
+                    .bind(&provider_request.api_key)
+                    .fetch_one(&state.db)
+                    .await
+                    .unwrap_or_default();
+
+                let (balance, error) = match provider_request.verify(balance_checker).await {
+                    Ok(balance) => {
+                        let now = Utc::now();
+                        let result = sqlx::query(
+                            "UPDATE api_providers SET status = 'Active', balance = ?, last_balance_check = ?, updated_at = ? WHERE id = ? AND status = 'Verifying'"
+                        )
+                        .bind(balance)
+                        .bind(now)
+                        .bind(now)
+                        .bind(&id)
+                        .execute(&state.db)
+                        .await;
+                        match result {
+                            Ok(_) => (Some(balance), None),
+                            Err(e) => (Some(balance), Some(format!("保存提供商失败: {}", e))),
+                        }
+                    }
+                    Err((balance, error)) => {
+                        if let Err(e) = sqlx::query("DELETE FROM api_providers WHERE id = ? AND status = 'Verifying'")
+                            .bind(&id)
+                            .execute(&state.db)
+                            .await
+                        {
+                            error!("删除验证失败的提供商失败: api_key={}, 错误={}", redact(&provider_request.api_key), e);
+                        }
+                        (balance, Some(error))
+                    }
+                };
+
+                ImportKeyResult {
+                    id,
+                    name,
+                    api_key: provider_request.api_key,
+                    balance,
+                    error,
+                }
+            }
+        })
+        .buffer_unordered(IMPORT_VERIFY_CONCURRENCY);
+
+    let mut verified = Vec::new();
+    while let Some(result) = results.next().await {
+        if result.error.is_none() {
+            verified.push(result.api_key.clone());
+        }
+        state.import_jobs.record(&job_id, result).await;
+    }
+
+    // 更新provider pool（只更新验证通过的提供商，保留其他提供商的运行状态）
+    if !verified.is_empty() {
+        info!("开始更新提供商池，异步导入验证通过 {} 个提供商", verified.len());
+        state.list_cache.invalidate(PROVIDERS_KEY);
+        if let Err(e) = refresh_providers(&state.db, &state.provider_pool, &verified).await {
+            error!("更新提供商池失败: {}", e);
+        }
+    }
+
+    state.import_jobs.finish(&job_id).await;
+    info!("异步导入任务完成: job_id={}, 验证通过={}", job_id, verified.len());
+}
+
+/// 查询异步导入任务进度
+#[utoipa::path(
+    get,
+    path = "/v1/providers/import/{job_id}",
+    params(
+        ("job_id" = String, Path, description = "导入任务ID")
+    ),
+    responses(
+        (status = 200, description = "成功获取导入任务进度", body = ImportJob),
+        (status = 404, description = "导入任务不存在", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn get_import_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> Response {
+    match state.import_jobs.get(&job_id).await {
+        Some(job) => (StatusCode::OK, Json(job)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: format!("导入任务不存在: {}", job_id) }),
+        ).into_response(),
+    }
+}
+
 // 定义数据库查询结果DTO
 #[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct ProviderInfoDTO {
     pub base_url: String,
     pub api_key: String,
+    pub provider_type: String,
+    /// 最大并发请求数
     pub max_connections: i32,
     pub min_connections: i32,
+    /// 没有空闲连接许可时排队等待的最长时间(毫秒)
     pub acquire_timeout_ms: i32,
     pub idle_timeout_ms: i32,
     pub load_balance_strategy: String,
+    /// 每分钟请求数上限（rate_limit），0表示不限制
+    pub requests_per_minute: i64,
     pub retry_attempts: i32,
+    /// 重试间隔(毫秒)
+    pub retry_delay_ms: i64,
+    /// 需要重试的上游状态码（逗号分隔），为空表示所有失败状态码都重试
+    pub retry_on_statuses: Option<String>,
+    /// 连接超时(毫秒)，为空时使用 UPSTREAM_CONNECT_TIMEOUT_MS
+    pub connect_timeout_ms: Option<i64>,
+    /// 请求超时(毫秒)，为空时使用 UPSTREAM_REQUEST_TIMEOUT_MS
+    pub request_timeout_ms: Option<i64>,
+    /// 优先级，数值越大越优先
+    pub priority: i32,
+    /// 每个上游域名最多保留的空闲连接数，为空时不限制
+    pub pool_max_idle_per_host: Option<i64>,
+    /// TCP keepalive 和 HTTP/2 PING 的间隔(秒)，为空时不发送
+    pub keep_alive_secs: Option<i64>,
+    /// HTTP 协议版本：auto、http1 或 http2，为空时按 auto 处理
+    pub http_version: Option<String>,
+    /// 附加请求头（JSON对象）
+    pub extra_headers: Option<String>,
+    /// 额外信任的CA证书文件路径（PEM）
+    pub tls_ca_path: Option<String>,
+    /// 跳过TLS证书校验
+    pub tls_insecure_skip_verify: bool,
     pub balance: f64,
     pub last_balance_check: Option<chrono::DateTime<chrono::Utc>>,
     pub min_balance_threshold: f64,
     pub support_balance_check: bool,
+    pub balance_check_url: Option<String>,
+    pub stream_pacing_tps: Option<f64>,
+    pub supports_gzip_request: bool,
+    pub supports_structured_output: bool,
+    pub deployment: Option<String>,
+    pub api_version: Option<String>,
+    pub aws_region: Option<String>,
+    pub purchased_quota: Option<f64>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub vendor_account_email: Option<String>,
+    pub account_fingerprint: Option<String>,
+    /// 绑定的组织（为空表示属于共享池）
+    pub organization: Option<String>,
+    /// 最近一次失败的时间
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// 最近一次失败的类别（auth、rate_limited、timeout、balance_check 等）
+    pub last_error_category: Option<String>,
+    /// 最近一次失败的错误信息（截断）
+    pub last_error_message: Option<String>,
     pub model_name: String,
     pub model_type: String,
     pub model_version: String,
@@ -504,16 +1068,39 @@ impl From<ProviderInfoDTO> for ProviderInfo {
         Self {
             base_url: dto.base_url,
             api_key: dto.api_key,
+            own_api_key: None,
+            provider_type: dto.provider_type,
             max_connections: dto.max_connections,
             min_connections: dto.min_connections,
             acquire_timeout_ms: dto.acquire_timeout_ms,
             idle_timeout_ms: dto.idle_timeout_ms,
             load_balance_strategy: dto.load_balance_strategy,
+            requests_per_minute: dto.requests_per_minute,
             retry_attempts: dto.retry_attempts,
+            retry_delay_ms: dto.retry_delay_ms,
+            retry_on_statuses: dto.retry_on_statuses,
+            connect_timeout_ms: dto.connect_timeout_ms,
+            request_timeout_ms: dto.request_timeout_ms,
+            priority: dto.priority,
+            pool_max_idle_per_host: dto.pool_max_idle_per_host,
+            keep_alive_secs: dto.keep_alive_secs,
+            http_version: dto.http_version,
+            extra_headers: dto.extra_headers,
+            tls_ca_path: dto.tls_ca_path,
+            tls_insecure_skip_verify: dto.tls_insecure_skip_verify,
             balance: dto.balance,
             last_balance_check: dto.last_balance_check,
             min_balance_threshold: dto.min_balance_threshold,
             support_balance_check: dto.support_balance_check,
+            balance_check_url: dto.balance_check_url,
+            stream_pacing_tps: dto.stream_pacing_tps,
+            supports_gzip_request: dto.supports_gzip_request,
+            supports_structured_output: dto.supports_structured_output,
+            deployment: dto.deployment,
+            api_version: dto.api_version,
+            aws_region: dto.aws_region,
+            expires_at: dto.expires_at,
+            organization: dto.organization,
             model_name: dto.model_name,
             model_type: dto.model_type,
             model_version: dto.model_version,
@@ -533,63 +1120,92 @@ pub struct ProviderListResponse {
     path = "/v1/providers",
     responses(
         (status = 200, description = "成功获取所有API提供商", body = ProviderListResponse),
+        (status = 304, description = "列表未变化（If-None-Match 与 ETag 匹配）"),
         (status = 500, description = "服务器内部错误", body = ErrorResponse),
     ),
     tag = "providers"
 )]
 pub async fn get_all_providers(
     State(state): State<AppState>,
+    headers: HeaderMap,
 ) -> Response {
     info!("收到获取所有API提供商请求");
 
-    match sqlx::query_as::<_, ProviderInfoDTO>(
-        r#"
-        SELECT 
-            base_url,
-            api_key,
-            rate_limit as max_connections,
-            1 as min_connections,
-            3000 as acquire_timeout_ms,
-            60000 as idle_timeout_ms,
-            'RoundRobin' as load_balance_strategy,
-            3 as retry_attempts,
-            balance,
-            last_balance_check,
-            min_balance_threshold,
-            support_balance_check,
-            model_name,
-            model_type,
-            model_version
-        FROM api_providers
-        WHERE status = 'Active'
-        "#
-    )
-    .fetch_all(&state.db)
-    .await {
-        Ok(providers) => {
-            let count = providers.len();
-            info!("成功获取API提供商列表，共 {} 条记录", count);
-            
-            let response = ProviderListResponse {
-                providers,
-                count,
-            };
-            
-            (StatusCode::OK, Json(response)).into_response()
-        }
-        Err(e) => {
+    let db = state.db.clone();
+    match state.list_cache.get(PROVIDERS_KEY, || async move {
+        let response = load_providers(&db).await.map_err(|e| {
             error!("获取API提供商列表失败: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("获取API提供商列表失败: {}", e),
-                }),
-            )
-                .into_response()
-        }
+            format!("获取API提供商列表失败: {}", e)
+        })?;
+        serde_json::to_vec(&response)
+            .map(Bytes::from)
+            .map_err(|e| format!("序列化API提供商列表失败: {}", e))
+    }).await {
+        Ok(list) => cached_json_response(&list, &headers),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error })).into_response(),
     }
 }
 
+// 查询所有启用的提供商
+async fn load_providers(db: &SqlitePool) -> Result<ProviderListResponse, sqlx::Error> {
+    let providers = sqlx::query_as::<_, ProviderInfoDTO>(
+    r#"
+    SELECT 
+        base_url,
+        api_key,
+        provider_type,
+        max_connections,
+        1 as min_connections,
+        acquire_timeout_ms,
+        60000 as idle_timeout_ms,
+        'RoundRobin' as load_balance_strategy,
+        rate_limit as requests_per_minute,
+        retry_attempts,
+        retry_delay_ms,
+        retry_on_statuses,
+        connect_timeout_ms,
+        request_timeout_ms,
+        priority,
+        pool_max_idle_per_host,
+        keep_alive_secs,
+        http_version,
+        extra_headers,
+        tls_ca_path,
+        tls_insecure_skip_verify,
+        balance,
+        last_balance_check,
+        min_balance_threshold,
+        support_balance_check,
+        balance_check_url,
+        stream_pacing_tps,
+        supports_gzip_request,
+        supports_structured_output,
+        deployment,
+        api_version,
+        aws_region,
+        purchased_quota,
+        expires_at,
+        vendor_account_email,
+        account_fingerprint,
+        organization,
+        last_error_at,
+        last_error_category,
+        last_error_message,
+        model_name,
+        model_type,
+        model_version
+    FROM api_providers
+    WHERE status = 'Active'
+    "#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let count = providers.len();
+    info!("成功获取API提供商列表，共 {} 条记录", count);
+    Ok(ProviderListResponse { providers, count })
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     /// 错误信息
@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use tracing::error;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+use crate::services::provider_limits::{self, ProviderLimits};
+use crate::services::provider_stats::ProviderStatsSnapshot;
+
+/// 获取提供商实时统计（读取后台刷新的快照，不获取提供商池的锁）
+#[utoipa::path(
+    get,
+    path = "/v1/providers/stats",
+    responses(
+        (status = 200, description = "成功获取提供商实时统计", body = ProviderStatsSnapshot),
+    ),
+    tag = "providers"
+)]
+pub async fn get_provider_stats(State(state): State<AppState>) -> Response {
+    let snapshot: ProviderStatsSnapshot = state.provider_stats.load().as_ref().clone();
+    (StatusCode::OK, Json(snapshot)).into_response()
+}
+
+/// 汇总提供商当前受到的所有限制（连接许可、自适应并发、最近用量、隔离状态、余额与额度），
+/// 用于排查提供商为何未被选中
+#[utoipa::path(
+    get,
+    path = "/v1/providers/{id}/limits",
+    params(
+        ("id" = String, Path, description = "提供商ID")
+    ),
+    responses(
+        (status = 200, description = "成功获取提供商限制状态", body = ProviderLimits),
+        (status = 404, description = "提供商不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "providers"
+)]
+pub async fn get_provider_limits(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let result: Result<Option<ProviderLimits>, _> = provider_limits::inspect(
+        &state.db,
+        &state.provider_pool,
+        &state.concurrency,
+        state.config.health_check.quarantine_failure_threshold,
+        &id,
+    )
+    .await;
+
+    match result {
+        Ok(Some(limits)) => (StatusCode::OK, Json(limits)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: format!("提供商不存在: {}", id) }),
+        ).into_response(),
+        Err(e) => {
+            error!("获取提供商限制状态失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("获取提供商限制状态失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use serde::Deserialize;
+use tracing::{error, info};
+use utoipa::IntoParams;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+use crate::services::invoice_reconcile::{parse_csv, reconcile_invoice, InvoiceLine, InvoiceReconcileReport};
+use crate::services::reconcile::{reconcile, ReconcileReport};
+
+/// 对账查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ReconcileQuery {
+    /// 是否按请求时生效的历史定价回填缺少的成本（默认false）
+    #[serde(default)]
+    pub backfill: bool,
+}
+
+/// 检查使用记录与账本、成本的一致性
+#[utoipa::path(
+    get,
+    path = "/v1/admin/reconcile",
+    params(ReconcileQuery),
+    responses(
+        (status = 200, description = "成功生成对账报告", body = ReconcileReport),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn get_reconcile_report(
+    State(state): State<AppState>,
+    Query(query): Query<ReconcileQuery>,
+) -> Response {
+    match reconcile(&state.db, query.backfill).await {
+        Ok(report) => {
+            let report: ReconcileReport = report;
+            if let Some(backfilled) = report.backfilled_costs {
+                info!("已按历史定价回填 {} 条使用记录的成本", backfilled);
+            }
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Err(e) => {
+            error!("生成对账报告失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("生成对账报告失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 账单对账查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct InvoiceReconcileQuery {
+    /// 相对容差（默认0.01，即1%）
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+}
+
+fn default_tolerance() -> f64 {
+    0.01
+}
+
+/// 导入供应商用量账单并与网关记录按密钥、按天比对
+#[utoipa::path(
+    post,
+    path = "/v1/admin/reconcile/invoice",
+    params(InvoiceReconcileQuery),
+    request_body(
+        content = Vec<InvoiceLine>,
+        description = "供应商用量导出：JSON数组，或 Content-Type 为 text/csv 的CSV（列：api_key,date,prompt_tokens,completion_tokens,total_tokens,cost）"
+    ),
+    responses(
+        (status = 200, description = "成功生成账单对账报告", body = InvoiceReconcileReport),
+        (status = 400, description = "账单格式无效", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn post_invoice_reconcile(
+    State(state): State<AppState>,
+    Query(query): Query<InvoiceReconcileQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/csv"));
+    let lines: Result<Vec<InvoiceLine>, String> = if is_csv {
+        parse_csv(&String::from_utf8_lossy(&body))
+    } else {
+        serde_json::from_slice(&body).map_err(|e| format!("账单JSON无效: {}", e))
+    };
+    let lines = match lines {
+        Ok(lines) => lines,
+        Err(error) => return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response(),
+    };
+    if !query.tolerance.is_finite() || query.tolerance < 0.0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: "tolerance 必须为非负数".to_string() }),
+        ).into_response();
+    }
+
+    match reconcile_invoice(&state.db, &lines, query.tolerance).await {
+        Ok(report) => {
+            let report: InvoiceReconcileReport = report;
+            info!("账单对账完成: 账单行数={}, 不一致={}", report.invoice_lines, report.discrepancy_count);
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Err(e) => {
+            error!("账单对账失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("账单对账失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use bytes::Bytes;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use tracing::{error, info};
+
+use crate::models::data_quality_event::DataQualityEvent;
+use crate::services::SseEvent;
+
+// OpenAI 定义的结束原因
+const FINISH_REASONS: [&str; 5] = ["stop", "length", "tool_calls", "content_filter", "function_call"];
+// 各层级允许的字段，其余厂商字段在严格模式下移除；网关扩展字段（x_gateway_ 前缀）保留
+const RESPONSE_FIELDS: [&str; 8] = ["id", "object", "created", "model", "choices", "usage", "system_fingerprint", "service_tier"];
+const CHOICE_FIELDS: [&str; 4] = ["index", "message", "finish_reason", "logprobs"];
+const CHUNK_CHOICE_FIELDS: [&str; 4] = ["index", "delta", "finish_reason", "logprobs"];
+const MESSAGE_FIELDS: [&str; 7] = ["role", "content", "refusal", "tool_calls", "function_call", "audio", "annotations"];
+const DELTA_FIELDS: [&str; 5] = ["role", "content", "refusal", "tool_calls", "function_call"];
+const USAGE_FIELDS: [&str; 5] = ["prompt_tokens", "completion_tokens", "total_tokens", "prompt_tokens_details", "completion_tokens_details"];
+const EXTENSION_PREFIX: &str = "x_gateway_";
+
+/// 严格 OpenAI 兼容模式：按 OpenAI chat completion（及流式数据块）的格式校验返回给客户端的响应，
+/// 能修复的修复（补全缺失字段、统一结束原因、移除厂商字段），无法修复的拒绝，
+/// 使按响应模型严格解析的 SDK（LangChain、openai-python 等）不受各家提供商差异影响
+#[derive(Debug, Default)]
+pub struct StrictConformance {
+    // 本次响应做过的修复项
+    repairs: BTreeSet<&'static str>,
+    // 流式响应中丢弃的事件数
+    dropped_events: usize,
+}
+
+impl StrictConformance {
+    /// 校验并修复非流式响应，无法修复时返回原因
+    pub fn response(&mut self, json: &mut Value, model: &str) -> Result<(), String> {
+        let object = json.as_object_mut().ok_or("响应不是JSON对象")?;
+        self.envelope(object, "chat.completion", model);
+
+        let choices = match object.get_mut("choices").and_then(Value::as_array_mut) {
+            Some(choices) if !choices.is_empty() => choices,
+            _ => return Err("响应缺少 choices".to_string()),
+        };
+        for (index, choice) in choices.iter_mut().enumerate() {
+            let choice = choice.as_object_mut().ok_or_else(|| format!("choices[{}] 不是对象", index))?;
+            self.choice_index(choice, index);
+            let message = choice
+                .get_mut("message")
+                .and_then(Value::as_object_mut)
+                .ok_or_else(|| format!("choices[{}] 缺少 message", index))?;
+            self.message(message, &MESSAGE_FIELDS)
+                .map_err(|e| format!("choices[{}].message {}", index, e))?;
+            let finish_reason = self.finish_reason(choice.get("finish_reason")).unwrap_or("stop");
+            choice.insert("finish_reason".to_string(), finish_reason.into());
+            self.retain(choice, &CHOICE_FIELDS);
+        }
+        self.usage(object)
+    }
+
+    /// 校验并修复流式事件，无法修复的事件返回空（丢弃）；注释、结束标记和错误事件原样返回
+    pub fn chunk(&mut self, event: SseEvent, model: &str) -> Option<SseEvent> {
+        let data = match event.data.as_deref() {
+            Some(data) if data != "[DONE]" => data,
+            _ => return Some(event),
+        };
+        let mut json = match serde_json::from_str::<Value>(data) {
+            Ok(json) if json.is_object() => json,
+            _ => {
+                self.dropped_events += 1;
+                return None;
+            }
+        };
+        if json.get("error").is_some() {
+            return Some(event);
+        }
+        match self.chunk_json(&mut json, model) {
+            Ok(()) => {
+                let data = json.to_string();
+                Some(SseEvent { raw: Bytes::from(format!("data: {}\n\n", data)), data: Some(data) })
+            }
+            Err(e) => {
+                info!("严格模式：丢弃不符合格式的流式数据块: {}", e);
+                self.dropped_events += 1;
+                None
+            }
+        }
+    }
+
+    /// 把修复或拒绝记录为提供商的数据质量事件，没有问题时不记录
+    pub async fn record(&self, db: &sqlx::SqlitePool, provider_api_key: &str, rejection: Option<&str>) {
+        if self.repairs.is_empty() && self.dropped_events == 0 && rejection.is_none() {
+            return;
+        }
+        let mut repairs: Vec<&str> = self.repairs.iter().copied().collect();
+        if self.dropped_events > 0 {
+            repairs.push("dropped_events");
+        }
+        let detail = match rejection {
+            Some(reason) => reason.to_string(),
+            None if self.dropped_events > 0 => format!("丢弃 {} 个流式事件", self.dropped_events),
+            None => "已按 OpenAI 格式修复".to_string(),
+        };
+        if let Err(e) = DataQualityEvent::record_nonconforming(db, provider_api_key, rejection.is_none(), &repairs, &detail).await {
+            error!("记录数据质量事件失败: {}", e);
+        }
+    }
+
+    fn chunk_json(&mut self, json: &mut Value, model: &str) -> Result<(), String> {
+        let object = json.as_object_mut().ok_or("数据块不是JSON对象")?;
+        self.envelope(object, "chat.completion.chunk", model);
+
+        // 只携带 usage 的最后一个数据块的 choices 为空数组
+        if !object.get("choices").is_some_and(Value::is_array) {
+            self.repair("choices", object, "choices", Value::Array(Vec::new()));
+        }
+        let choices = object.get_mut("choices").and_then(Value::as_array_mut).ok_or("数据块缺少 choices")?;
+        for (index, choice) in choices.iter_mut().enumerate() {
+            let choice = choice.as_object_mut().ok_or_else(|| format!("choices[{}] 不是对象", index))?;
+            self.choice_index(choice, index);
+            // 部分提供商在数据块中使用 message 代替 delta
+            if !choice.get("delta").is_some_and(Value::is_object) {
+                let delta = choice.remove("message").filter(Value::is_object).unwrap_or_else(|| Value::Object(Map::new()));
+                self.repair("delta", choice, "delta", delta);
+            }
+            if let Some(delta) = choice.get_mut("delta").and_then(Value::as_object_mut) {
+                self.message(delta, &DELTA_FIELDS)
+                    .map_err(|e| format!("choices[{}].delta {}", index, e))?;
+            }
+            let finish_reason = self.finish_reason(choice.get("finish_reason")).map_or(Value::Null, Value::from);
+            choice.insert("finish_reason".to_string(), finish_reason);
+            self.retain(choice, &CHUNK_CHOICE_FIELDS);
+        }
+        if object.get("usage").is_some_and(Value::is_null) {
+            return Ok(());
+        }
+        self.usage(object)
+    }
+
+    // 顶层字段：id、object、created、model
+    fn envelope(&mut self, object: &mut Map<String, Value>, kind: &str, model: &str) {
+        if !object.get("id").is_some_and(Value::is_string) {
+            self.repair("id", object, "id", format!("chatcmpl-{}", uuid::Uuid::new_v4()).into());
+        }
+        if object.get("object").and_then(Value::as_str) != Some(kind) {
+            self.repair("object", object, "object", kind.into());
+        }
+        if !object.get("created").is_some_and(Value::is_u64) {
+            self.repair("created", object, "created", chrono::Utc::now().timestamp().into());
+        }
+        if !object.get("model").is_some_and(Value::is_string) {
+            self.repair("model", object, "model", model.into());
+        }
+        self.retain(object, &RESPONSE_FIELDS);
+    }
+
+    fn choice_index(&mut self, choice: &mut Map<String, Value>, index: usize) {
+        if !choice.get("index").is_some_and(Value::is_u64) {
+            self.repair("index", choice, "index", index.into());
+        }
+    }
+
+    // 消息（或增量）：角色统一为 assistant，内容片段数组拼接为字符串
+    fn message(&mut self, message: &mut Map<String, Value>, fields: &[&str]) -> Result<(), String> {
+        // 增量中的 role 只出现在第一个数据块
+        let role_required = fields == MESSAGE_FIELDS;
+        match message.get("role").and_then(Value::as_str) {
+            Some("assistant") => {}
+            None if !role_required && !message.contains_key("role") => {}
+            _ => self.repair("role", message, "role", "assistant".into()),
+        }
+        match message.get("content") {
+            None if role_required => self.repair("content", message, "content", Value::Null),
+            None | Some(Value::Null) | Some(Value::String(_)) => {}
+            Some(Value::Array(parts)) => {
+                let text: String = parts
+                    .iter()
+                    .filter_map(|part| part.as_str().or_else(|| part.get("text").and_then(Value::as_str)))
+                    .collect();
+                self.repair("content", message, "content", text.into());
+            }
+            Some(other) => return Err(format!("content 类型不正确: {}", other)),
+        }
+        if message.get("refusal").is_some_and(|refusal| !refusal.is_string() && !refusal.is_null()) {
+            message.remove("refusal");
+            self.repairs.insert("refusal");
+        }
+        if message.get("tool_calls").is_some_and(|calls| !calls.is_array()) {
+            message.remove("tool_calls");
+            self.repairs.insert("tool_calls");
+        }
+        self.retain(message, fields);
+        Ok(())
+    }
+
+    // 结束原因映射为 OpenAI 定义的取值，未结束（null）时返回空
+    fn finish_reason(&mut self, value: Option<&Value>) -> Option<&'static str> {
+        let reason = match value {
+            None | Some(Value::Null) => return None,
+            Some(value) => value.as_str().unwrap_or_default(),
+        };
+        if let Some(reason) = FINISH_REASONS.iter().find(|r| **r == reason) {
+            return Some(reason);
+        }
+        self.repairs.insert("finish_reason");
+        Some(match reason.to_ascii_lowercase().as_str() {
+            "max_tokens" | "length" | "max_output_tokens" => "length",
+            "tool_use" | "tool_calls" | "function_call" => "tool_calls",
+            "content_filter" | "content_filtered" | "safety" | "blocked" => "content_filter",
+            _ => "stop",
+        })
+    }
+
+    // usage：token数必须为非负整数，total_tokens 缺失或不一致时按两者之和补全；没有 usage 时保持缺省
+    fn usage(&mut self, object: &mut Map<String, Value>) -> Result<(), String> {
+        let usage = match object.get_mut("usage") {
+            None => return Ok(()),
+            Some(Value::Object(usage)) => usage,
+            Some(_) => return Err("usage 不是对象".to_string()),
+        };
+        let prompt = usage.get("prompt_tokens").and_then(Value::as_u64).ok_or("usage 缺少 prompt_tokens")?;
+        let completion = usage.get("completion_tokens").and_then(Value::as_u64).ok_or("usage 缺少 completion_tokens")?;
+        if usage.get("total_tokens").and_then(Value::as_u64).is_none_or(|total| total < prompt + completion) {
+            self.repair("total_tokens", usage, "total_tokens", (prompt + completion).into());
+        }
+        self.retain(usage, &USAGE_FIELDS);
+        Ok(())
+    }
+
+    fn repair(&mut self, name: &'static str, object: &mut Map<String, Value>, field: &str, value: Value) {
+        object.insert(field.to_string(), value);
+        self.repairs.insert(name);
+    }
+
+    // 移除不在允许列表中的厂商字段
+    fn retain(&mut self, object: &mut Map<String, Value>, fields: &[&str]) {
+        let before = object.len();
+        object.retain(|key, _| fields.contains(&key.as_str()) || key.starts_with(EXTENSION_PREFIX));
+        if object.len() != before {
+            self.repairs.insert("vendor_fields");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn repairs(conformance: &StrictConformance) -> Vec<&'static str> {
+        conformance.repairs.iter().copied().collect()
+    }
+
+    fn response(message: Value, finish_reason: Value, usage: Value) -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o",
+            "choices": [{ "index": 0, "message": message, "finish_reason": finish_reason }],
+            "usage": usage,
+        })
+    }
+
+    fn usage() -> Value {
+        json!({ "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5 })
+    }
+
+    #[test]
+    fn repairs_responses() {
+        let assistant = json!({ "role": "assistant", "content": "Hi" });
+        let cases: Vec<(Value, Value, &[&str])> = vec![
+            (
+                response(assistant.clone(), json!("stop"), usage()),
+                response(assistant.clone(), json!("stop"), usage()),
+                &[],
+            ),
+            (
+                response(assistant.clone(), json!("max_tokens"), usage()),
+                response(assistant.clone(), json!("length"), usage()),
+                &["finish_reason"],
+            ),
+            (
+                response(assistant.clone(), json!("SAFETY"), usage()),
+                response(assistant.clone(), json!("content_filter"), usage()),
+                &["finish_reason"],
+            ),
+            (
+                response(assistant.clone(), json!("tool_use"), usage()),
+                response(assistant.clone(), json!("tool_calls"), usage()),
+                &["finish_reason"],
+            ),
+            (
+                response(assistant.clone(), json!("eos"), usage()),
+                response(assistant.clone(), json!("stop"), usage()),
+                &["finish_reason"],
+            ),
+            (
+                response(assistant.clone(), Value::Null, usage()),
+                response(assistant.clone(), json!("stop"), usage()),
+                &[],
+            ),
+            (
+                response(json!({ "role": "model", "content": [{ "type": "text", "text": "H" }, "i"] }), json!("stop"), usage()),
+                response(assistant.clone(), json!("stop"), usage()),
+                &["content", "role"],
+            ),
+            (
+                response(json!({ "role": "assistant" }), json!("stop"), usage()),
+                response(json!({ "role": "assistant", "content": null }), json!("stop"), usage()),
+                &["content"],
+            ),
+            (
+                response(json!({ "role": "assistant", "content": "Hi", "reasoning": "...", "refusal": 1, "tool_calls": {} }), json!("stop"), usage()),
+                response(assistant.clone(), json!("stop"), usage()),
+                &["refusal", "tool_calls", "vendor_fields"],
+            ),
+            (
+                response(assistant.clone(), json!("stop"), json!({ "prompt_tokens": 3, "completion_tokens": 2, "cached": 1 })),
+                response(assistant.clone(), json!("stop"), usage()),
+                &["total_tokens", "vendor_fields"],
+            ),
+            (
+                response(assistant.clone(), json!("stop"), json!({ "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 1 })),
+                response(assistant.clone(), json!("stop"), usage()),
+                &["total_tokens"],
+            ),
+        ];
+
+        for (mut input, expected, expected_repairs) in cases {
+            let original = input.clone();
+            let mut conformance = StrictConformance::default();
+            conformance.response(&mut input, "gpt-4o").unwrap_or_else(|e| panic!("{}: {}", original, e));
+            assert_eq!(input, expected, "{}", original);
+            assert_eq!(repairs(&conformance), expected_repairs, "{}", original);
+        }
+    }
+
+    #[test]
+    fn fills_missing_envelope_fields() {
+        let mut input = json!({
+            "object": "chat.completions",
+            "created": "now",
+            "provider": "x",
+            "x_gateway_provider": "y",
+            "choices": [{ "message": { "role": "assistant", "content": "Hi" } }],
+        });
+        let mut conformance = StrictConformance::default();
+        conformance.response(&mut input, "gpt-4o").unwrap();
+
+        assert!(input["id"].as_str().unwrap().starts_with("chatcmpl-"));
+        assert_eq!(input["object"], "chat.completion");
+        assert!(input["created"].is_u64());
+        assert_eq!(input["model"], "gpt-4o");
+        assert_eq!(input["choices"][0]["index"], 0);
+        assert!(input.get("provider").is_none());
+        assert_eq!(input["x_gateway_provider"], "y");
+        assert_eq!(repairs(&conformance), ["created", "id", "index", "model", "object", "vendor_fields"]);
+    }
+
+    #[test]
+    fn rejects_unrepairable_responses() {
+        let assistant = json!({ "role": "assistant", "content": "Hi" });
+        let cases = [
+            (json!([]), "响应不是JSON对象"),
+            (json!({ "id": "x" }), "响应缺少 choices"),
+            (json!({ "choices": [] }), "响应缺少 choices"),
+            (json!({ "choices": ["text"] }), "choices[0] 不是对象"),
+            (json!({ "choices": [{ "text": "Hi" }] }), "choices[0] 缺少 message"),
+            (response(json!({ "content": 42 }), json!("stop"), usage()), "choices[0].message content 类型不正确: 42"),
+            (response(assistant.clone(), json!("stop"), json!(5)), "usage 不是对象"),
+            (response(assistant.clone(), json!("stop"), json!({ "completion_tokens": 2 })), "usage 缺少 prompt_tokens"),
+            (response(assistant.clone(), json!("stop"), json!({ "prompt_tokens": 3, "completion_tokens": -1 })), "usage 缺少 completion_tokens"),
+        ];
+
+        for (mut input, expected) in cases {
+            let original = input.clone();
+            let error = StrictConformance::default().response(&mut input, "gpt-4o").unwrap_err();
+            assert_eq!(error, expected, "{}", original);
+        }
+    }
+
+    #[test]
+    fn repairs_or_drops_stream_chunks() {
+        let event = |data: &str| SseEvent { raw: Bytes::from(format!("data: {}\n\n", data)), data: Some(data.to_string()) };
+        let chunk = |choices: Value| {
+            json!({ "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000, "model": "gpt-4o", "choices": choices })
+        };
+
+        // (输入, 修复后的数据块，空表示丢弃)
+        let cases: Vec<(String, Option<Value>)> = vec![
+            (
+                chunk(json!([{ "index": 0, "delta": { "content": "Hi" }, "finish_reason": null }])).to_string(),
+                Some(chunk(json!([{ "index": 0, "delta": { "content": "Hi" }, "finish_reason": null }]))),
+            ),
+            (
+                chunk(json!([{ "index": 0, "message": { "role": "assistant", "content": "Hi" } }])).to_string(),
+                Some(chunk(json!([{ "index": 0, "delta": { "role": "assistant", "content": "Hi" }, "finish_reason": null }]))),
+            ),
+            (
+                chunk(json!([{ "index": 0, "delta": {}, "finish_reason": "end_turn" }])).to_string(),
+                Some(chunk(json!([{ "index": 0, "delta": {}, "finish_reason": "stop" }]))),
+            ),
+            (
+                json!({ "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000, "model": "gpt-4o", "usage": usage() }).to_string(),
+                Some(json!({ "id": "chatcmpl-1", "object": "chat.completion.chunk", "created": 1700000000, "model": "gpt-4o", "choices": [], "usage": usage() })),
+            ),
+            (chunk(json!([{ "index": 0, "delta": { "content": 42 } }])).to_string(), None),
+            (chunk(json!(["text"])).to_string(), None),
+            ("not json".to_string(), None),
+            ("[1, 2]".to_string(), None),
+        ];
+
+        let mut conformance = StrictConformance::default();
+        for (input, expected) in cases {
+            let output = conformance.chunk(event(&input), "gpt-4o");
+            let output = output.map(|event| serde_json::from_str::<Value>(event.data.as_deref().unwrap()).unwrap());
+            assert_eq!(output, expected, "{}", input);
+        }
+        assert_eq!(conformance.dropped_events, 4);
+
+        // 结束标记、错误事件和注释原样转发
+        for passthrough in [event("[DONE]"), event(r#"{"error":{"message":"boom"}}"#), SseEvent { raw: Bytes::from(": ping\n\n"), data: None }] {
+            let raw = passthrough.raw.clone();
+            assert_eq!(conformance.chunk(passthrough, "gpt-4o").unwrap().raw, raw);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Json, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+
+use tracing::error;
+
+use crate::database::schema::{self, DatabaseSchema};
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::gateway_key::GatewayKey;
+use crate::models::latency_slo_event::LatencySloEvent;
+use crate::routes::api::AppState;
+use crate::services::db_metrics::DbMetricsSnapshot;
+use crate::services::diagnostics::{self, DiagnosticsReport};
+use crate::services::{provider_latency, upstream_client};
+use crate::services::latency_slo::LatencySloStatus;
+use crate::services::scheduler::{self, JobStatus};
+
+// 状态中返回的最近事件数
+const RECENT_SLO_EVENTS: i64 = 20;
+
+/// 获取热路径数据库查询的耗时与锁竞争统计
+#[utoipa::path(
+    get,
+    path = "/v1/system/db-metrics",
+    responses(
+        (status = 200, description = "成功获取数据库查询统计", body = DbMetricsSnapshot),
+    ),
+    tag = "system"
+)]
+pub async fn get_db_metrics(
+    State(state): State<AppState>,
+) -> Response {
+    let snapshot: DbMetricsSnapshot = state.db_metrics.snapshot();
+    (StatusCode::OK, Json(snapshot)).into_response()
+}
+
+/// 以 Prometheus 文本格式导出按网关密钥统计的请求数、token数、成本和失败数，以及使用记录队列的长度和溢出计数
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Prometheus 文本格式的指标", content_type = "text/plain"),
+    ),
+    tag = "system"
+)]
+pub async fn get_prometheus_metrics(
+    State(state): State<AppState>,
+) -> Response {
+    // 密钥名称仅用于标签，查询失败时标签留空
+    let names: HashMap<String, String> = match GatewayKey::list(&state.db).await {
+        Ok(keys) => keys.into_iter().map(|key| (key.id, key.name)).collect(),
+        Err(e) => {
+            error!("获取网关密钥名称失败: {}", e);
+            HashMap::new()
+        }
+    };
+    let providers = match provider_latency::provider_labels(&state.db).await {
+        Ok(providers) => providers,
+        Err(e) => {
+            error!("获取提供商名称失败: {}", e);
+            HashMap::new()
+        }
+    };
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        state.key_metrics.render(&names)
+            + &state.usage_recorder.render()
+            + &provider_latency::tracker().render(&providers)
+            + &state.slow_requests.render()
+            + &state.in_flight.render()
+            + &upstream_client::render(),
+    ).into_response()
+}
+
+/// 获取延迟SLO保护状态（当前p99、是否降载）和最近的降载事件
+#[utoipa::path(
+    get,
+    path = "/v1/system/slo",
+    responses(
+        (status = 200, description = "成功获取延迟SLO状态", body = LatencySloStatus),
+    ),
+    tag = "system"
+)]
+pub async fn get_latency_slo(
+    State(state): State<AppState>,
+) -> Response {
+    let mut status: LatencySloStatus = state.latency_slo.status();
+    match LatencySloEvent::recent(&state.db, RECENT_SLO_EVENTS).await {
+        Ok(events) => status.recent_events = events,
+        Err(e) => error!("获取延迟SLO事件失败: {}", e),
+    }
+    (StatusCode::OK, Json(status)).into_response()
+}
+
+/// 获取当前数据库结构（表、列、索引、外键和已执行的迁移），供备份、BI和迁移审计等外部工具适配结构变化
+#[utoipa::path(
+    get,
+    path = "/v1/admin/schema",
+    responses(
+        (status = 200, description = "成功获取数据库结构", body = DatabaseSchema),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "system"
+)]
+pub async fn get_database_schema(
+    State(state): State<AppState>,
+) -> Response {
+    let result: Result<DatabaseSchema, _> = schema::describe(&state.db).await;
+    match result {
+        Ok(schema) => (StatusCode::OK, Json(schema)).into_response(),
+        Err(e) => {
+            error!("读取数据库结构失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("读取数据库结构失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 执行深度自检（数据库可写、迁移已执行、每个模型有可用提供商、磁盘空间、时钟偏差），
+/// 任一检查失败时返回503，可直接用作容器编排的健康检查或附在支持请求中
+#[utoipa::path(
+    get,
+    path = "/v1/admin/diagnostics",
+    responses(
+        (status = 200, description = "全部检查通过（可能有警告）", body = DiagnosticsReport),
+        (status = 503, description = "有检查失败", body = DiagnosticsReport),
+    ),
+    tag = "system"
+)]
+pub async fn get_diagnostics(
+    State(state): State<AppState>,
+) -> Response {
+    let stats = state.provider_stats.load();
+    let report: DiagnosticsReport = diagnostics::run(&state.db, &stats, &state.config).await;
+    for check in report.checks.iter().filter(|c| c.status == diagnostics::CheckStatus::Fail) {
+        error!("自检未通过 {}: {}", check.name, check.message);
+    }
+    let status = if report.failed() { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+    (status, Json(report)).into_response()
+}
+
+/// 存活探针：进程能处理请求即返回200，不检查数据库等外部依赖
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "服务存活", body = DiagnosticsReport),
+    ),
+    tag = "system"
+)]
+pub async fn get_liveness() -> Response {
+    (StatusCode::OK, Json(diagnostics::liveness())).into_response()
+}
+
+/// 就绪探针：数据库可连接且至少有一个 Active 状态的提供商时返回200，否则返回503
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "服务就绪", body = DiagnosticsReport),
+        (status = 503, description = "服务未就绪", body = DiagnosticsReport),
+    ),
+    tag = "system"
+)]
+pub async fn get_readiness(
+    State(state): State<AppState>,
+) -> Response {
+    let report = diagnostics::readiness(&state.db).await;
+    if report.failed() {
+        for check in report.checks.iter().filter(|c| c.status == diagnostics::CheckStatus::Fail) {
+            error!("就绪检查未通过 {}: {}", check.name, check.message);
+        }
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(report)).into_response();
+    }
+    (StatusCode::OK, Json(report)).into_response()
+}
+
+/// 获取已注册定时任务的执行计划和最近一次运行情况
+#[utoipa::path(
+    get,
+    path = "/v1/system/jobs",
+    responses(
+        (status = 200, description = "按名称排序的定时任务状态", body = Vec<JobStatus>),
+    ),
+    tag = "system"
+)]
+pub async fn get_scheduled_jobs() -> Response {
+    let jobs: Vec<JobStatus> = scheduler::scheduler().statuses();
+    (StatusCode::OK, Json(jobs)).into_response()
+}
@@ -0,0 +1,447 @@
+use axum::{
+    body::Body,
+    extract::{Json, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use chrono::{DateTime, Duration, NaiveDate, SubsecRound, Utc};
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::api_usage::{ApiUsage, UsageFilter};
+use crate::models::gateway_key::GatewayKey;
+use crate::models::usage_rollup::RollupGranularity;
+use crate::routes::api::AppState;
+use crate::services::usage_cost;
+use crate::services::usage_export;
+use crate::services::usage_report::{self, ReportSubject};
+use crate::services::usage_rollup;
+use crate::utils::redact::redact;
+
+// 每页默认条数和最大条数
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+// 报表分享链接的默认有效期(秒)和最大日期范围(天)
+const DEFAULT_REPORT_LINK_TTL_SECS: u64 = 7 * 86400;
+const MAX_REPORT_DAYS: i64 = 366;
+
+/// 使用记录查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UsageQuery {
+    /// 起始时间（包含）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含）
+    pub to: Option<DateTime<Utc>>,
+    /// 模型名称
+    pub model: Option<String>,
+    /// 提供商API密钥
+    pub provider_api_key: Option<String>,
+    /// 调用状态（如 Success、Error、RateLimited）
+    pub status: Option<String>,
+    /// 客户端IP
+    pub client_ip: Option<String>,
+    /// 归属的组织
+    pub organization: Option<String>,
+    /// 返回条数（默认50，最多500）
+    pub limit: Option<i64>,
+    /// 跳过的条数（默认0）
+    pub offset: Option<i64>,
+}
+
+/// 使用记录分页结果
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageList {
+    /// 使用记录，按请求时间倒序（提供商API密钥脱敏显示）
+    pub usages: Vec<ApiUsage>,
+    /// 符合条件的记录总数
+    pub total: i64,
+    /// 本次返回条数上限
+    pub limit: i64,
+    /// 本次跳过的条数
+    pub offset: i64,
+}
+
+/// 查询使用记录
+#[utoipa::path(
+    get,
+    path = "/v1/usage",
+    params(UsageQuery),
+    responses(
+        (status = 200, description = "成功获取使用记录", body = UsageList),
+        (status = 400, description = "查询参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn list_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return bad_request(format!("limit 必须在 1 到 {} 之间", MAX_LIMIT));
+    }
+    if offset < 0 {
+        return bad_request("offset 不能为负数".to_string());
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return bad_request("from 必须早于 to".to_string());
+        }
+    }
+
+    let filter = UsageFilter {
+        from: query.from,
+        to: query.to,
+        model: query.model,
+        provider_api_key: query.provider_api_key,
+        status: query.status,
+        client_ip: query.client_ip,
+        organization: query.organization,
+        gateway_key_id: None,
+    };
+    match ApiUsage::search(&state.db, &filter, limit, offset).await {
+        Ok((mut usages, total)) => {
+            // 与CSV导出一致，不返回提供商API密钥明文
+            for usage in &mut usages {
+                usage.provider_api_key = redact(&usage.provider_api_key);
+            }
+            (StatusCode::OK, Json(UsageList { usages, total, limit, offset })).into_response()
+        }
+        Err(e) => {
+            error!("查询使用记录失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("查询使用记录失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 使用统计查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UsageSummaryQuery {
+    /// 起始时间（包含）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含）
+    pub to: Option<DateTime<Utc>>,
+    /// 分组维度，逗号分隔（provider、model）
+    pub group_by: Option<String>,
+}
+
+/// 汇总使用统计：总请求数、token合计、成功/失败次数，可按提供商和模型分组
+#[utoipa::path(
+    get,
+    path = "/v1/usage/summary",
+    params(UsageSummaryQuery),
+    responses(
+        (status = 200, description = "成功获取使用统计", body = ApiUsageSummary),
+        (status = 400, description = "查询参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn get_usage_summary(
+    State(state): State<AppState>,
+    Query(query): Query<UsageSummaryQuery>,
+) -> Response {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return bad_request("from 必须早于 to".to_string());
+        }
+    }
+    let (mut by_provider, mut by_model) = (false, false);
+    for dimension in query.group_by.iter().flat_map(|s| s.split(',')).map(str::trim).filter(|s| !s.is_empty()) {
+        match dimension {
+            "provider" => by_provider = true,
+            "model" => by_model = true,
+            other => return bad_request(format!("不支持的分组维度: {}（可选 provider、model）", other)),
+        }
+    }
+
+    match usage_rollup::summarize(&state.db, query.from, query.to, by_provider, by_model).await {
+        Ok(summary) => (StatusCode::OK, Json(summary)).into_response(),
+        Err(e) => {
+            error!("汇总使用统计失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("汇总使用统计失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 成本查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UsageCostQuery {
+    /// 起始时间（包含）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含）
+    pub to: Option<DateTime<Utc>>,
+    /// 归属的组织
+    pub organization: Option<String>,
+    /// 分组维度，逗号分隔（provider、model、day）
+    pub group_by: Option<String>,
+}
+
+/// 按模型定价统计实际成本：请求时未配置定价的记录按当前定价补算，可按提供商、模型和天（UTC）分组
+#[utoipa::path(
+    get,
+    path = "/v1/usage/cost",
+    params(UsageCostQuery),
+    responses(
+        (status = 200, description = "成功获取成本统计", body = UsageCostReport),
+        (status = 400, description = "查询参数错误", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn get_usage_cost(
+    State(state): State<AppState>,
+    Query(query): Query<UsageCostQuery>,
+) -> Response {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return bad_request("from 必须早于 to".to_string());
+        }
+    }
+    let (mut by_provider, mut by_model, mut by_day) = (false, false, false);
+    for dimension in query.group_by.iter().flat_map(|s| s.split(',')).map(str::trim).filter(|s| !s.is_empty()) {
+        match dimension {
+            "provider" => by_provider = true,
+            "model" => by_model = true,
+            "day" => by_day = true,
+            other => return bad_request(format!("不支持的分组维度: {}（可选 provider、model、day）", other)),
+        }
+    }
+
+    let filter = UsageFilter {
+        from: query.from,
+        to: query.to,
+        organization: query.organization,
+        ..Default::default()
+    };
+    match usage_cost::attribute(&state.db, &filter, by_provider, by_model, by_day).await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(e) => {
+            error!("统计使用成本失败: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("统计使用成本失败: {}", e) }),
+            ).into_response()
+        }
+    }
+}
+
+/// 导出查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UsageExportQuery {
+    /// 导出格式（目前仅支持 csv）
+    pub format: Option<String>,
+    /// 数据来源：raw（原始记录，默认）、hour 或 day（汇总表）
+    pub source: Option<String>,
+    /// 起始时间（包含；汇总表按时间段起点过滤）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含；汇总表按时间段起点过滤）
+    pub to: Option<DateTime<Utc>>,
+    /// 模型名称
+    pub model: Option<String>,
+    /// 提供商API密钥
+    pub provider_api_key: Option<String>,
+    /// 网关密钥ID
+    pub gateway_key_id: Option<String>,
+    /// 调用状态（仅原始记录）
+    pub status: Option<String>,
+    /// 客户端IP（仅原始记录）
+    pub client_ip: Option<String>,
+    /// 归属的组织（仅原始记录）
+    pub organization: Option<String>,
+}
+
+/// 流式导出使用记录或汇总记录，供财务和报表流程使用；响应体边查询边发送，导出大量记录不会占用大量内存。
+/// 导出文件中的提供商密钥已脱敏（只保留末四位），按 provider_api_key 过滤时仍使用完整密钥
+#[utoipa::path(
+    get,
+    path = "/v1/usage/export",
+    params(UsageExportQuery),
+    responses(
+        (status = 200, description = "CSV文件", content_type = "text/csv"),
+        (status = 400, description = "查询参数错误", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn export_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageExportQuery>,
+) -> Response {
+    match query.format.as_deref() {
+        None | Some("csv") => {}
+        Some(other) => return bad_request(format!("不支持的导出格式: {}（可选 csv）", other)),
+    }
+    let granularity = match query.source.as_deref() {
+        None | Some("raw") => None,
+        Some("hour") => Some(RollupGranularity::Hour),
+        Some("day") => Some(RollupGranularity::Day),
+        Some(other) => return bad_request(format!("不支持的数据来源: {}（可选 raw、hour、day）", other)),
+    };
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return bad_request("from 必须早于 to".to_string());
+        }
+    }
+    if granularity.is_some() && (query.status.is_some() || query.client_ip.is_some() || query.organization.is_some()) {
+        return bad_request("汇总表不支持按 status、client_ip、organization 过滤".to_string());
+    }
+
+    let filter = UsageFilter {
+        from: query.from,
+        to: query.to,
+        model: query.model,
+        provider_api_key: query.provider_api_key,
+        status: query.status,
+        client_ip: query.client_ip,
+        organization: query.organization,
+        gateway_key_id: query.gateway_key_id,
+    };
+    let (name, body) = match granularity {
+        None => ("usage".to_string(), Body::from_stream(log_export_error(usage_export::usage_csv(state.db.clone(), filter)))),
+        Some(granularity) => (
+            format!("usage-{}", granularity.as_str()),
+            Body::from_stream(log_export_error(usage_export::rollup_csv(state.db.clone(), granularity, filter))),
+        ),
+    };
+    let disposition = format!("attachment; filename=\"{}-{}.csv\"", name, Utc::now().format("%Y%m%d%H%M%S"));
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ).into_response()
+}
+
+// 响应头发送后查询出错只能中断响应，记录错误日志
+fn log_export_error<S>(stream: S) -> impl Stream<Item = Result<Bytes, sqlx::Error>>
+where
+    S: Stream<Item = Result<Bytes, sqlx::Error>>,
+{
+    stream.inspect(|chunk| {
+        if let Err(e) = chunk {
+            error!("导出使用记录失败: {}", e);
+        }
+    })
+}
+
+/// 生成用量报表分享链接请求，gateway_key_id 和 organization 必须且只能设置一个
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateReportLinkRequest {
+    /// 网关密钥ID
+    #[serde(default)]
+    pub gateway_key_id: Option<String>,
+    /// 组织
+    #[serde(default)]
+    pub organization: Option<String>,
+    /// 起始日期（UTC，包含）
+    pub from: NaiveDate,
+    /// 结束日期（UTC，包含）
+    pub to: NaiveDate,
+    /// 有效期（秒，默认7天，不超过 REPORT_LINK_MAX_TTL_SECS）
+    #[serde(default)]
+    pub expires_in_secs: Option<u64>,
+}
+
+/// 用量报表分享链接
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReportLink {
+    /// HTML表格形式的报表链接
+    pub url: String,
+    /// JSON形式的报表链接
+    pub json_url: String,
+    /// 过期时间
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 生成限时的签名链接，持有链接即可查看某个网关密钥或组织在固定日期范围内的用量报表，无需网关凭证
+#[utoipa::path(
+    post,
+    path = "/v1/usage/reports/links",
+    request_body = CreateReportLinkRequest,
+    responses(
+        (status = 201, description = "成功生成分享链接", body = ReportLink),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 404, description = "网关密钥不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+        (status = 503, description = "未配置 REPORT_LINK_SECRET，分享链接已停用", body = ErrorResponse),
+    ),
+    tag = "billing"
+)]
+pub async fn create_usage_report_link(
+    State(state): State<AppState>,
+    Json(request): Json<CreateReportLinkRequest>,
+) -> Response {
+    let subject = match (request.gateway_key_id, request.organization) {
+        (Some(key), None) => ReportSubject::GatewayKey(key),
+        (None, Some(organization)) => ReportSubject::Organization(organization),
+        _ => return bad_request("gateway_key_id 和 organization 必须且只能设置一个".to_string()),
+    };
+    if request.from > request.to {
+        return bad_request("from 不能晚于 to".to_string());
+    }
+    if (request.to - request.from).num_days() >= MAX_REPORT_DAYS {
+        return bad_request(format!("日期范围不能超过 {} 天", MAX_REPORT_DAYS));
+    }
+    let config = &state.config.report_links;
+    let secret = match &config.signing_secret {
+        Some(secret) => secret,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse { error: "未配置 REPORT_LINK_SECRET，分享链接已停用".to_string() }),
+            ).into_response();
+        }
+    };
+    let ttl = request.expires_in_secs.unwrap_or(DEFAULT_REPORT_LINK_TTL_SECS);
+    if ttl == 0 || ttl > config.max_ttl_secs {
+        return bad_request(format!("expires_in_secs 必须在 1 到 {} 之间", config.max_ttl_secs));
+    }
+
+    if let ReportSubject::GatewayKey(id) = &subject {
+        match GatewayKey::find(&state.db, id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(ErrorResponse { error: format!("密钥不存在: {}", id) }),
+                ).into_response();
+            }
+            Err(e) => {
+                error!("获取网关密钥失败: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse { error: format!("获取网关密钥失败: {}", e) }),
+                ).into_response();
+            }
+        }
+    }
+
+    // 链接中的过期时间精确到秒
+    let expires_at = (Utc::now() + Duration::seconds(ttl as i64)).trunc_subsecs(0);
+    let params = usage_report::sign(secret, &subject, request.from, request.to, expires_at);
+    let base_url = config.public_base_url.as_deref();
+    let link = ReportLink {
+        url: usage_report::link(base_url, &params, "html"),
+        json_url: usage_report::link(base_url, &params, "json"),
+        expires_at,
+    };
+    (StatusCode::CREATED, Json(link)).into_response()
+}
+
+fn bad_request(error: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+}
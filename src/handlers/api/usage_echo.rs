@@ -0,0 +1,98 @@
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use serde_json::{Map, Value};
+use std::time::Duration;
+use tracing::error;
+
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::models::gateway_key_spend::GatewayKeySpend;
+use crate::routes::api::AppState;
+use crate::services::SseEvent;
+
+/// 按请求开启或关闭用量回显的请求头（true/false），未提供时使用网关密钥的设置
+pub const USAGE_ECHO_HEADER: &str = "x-gateway-usage-echo";
+
+/// 用量回显：在响应的 usage 对象中附带网关计算的成本、上游耗时和密钥当月累计花费，
+/// 客户端无需再调用一次接口即可展示实时花费。扩展字段统一使用 x_gateway_ 前缀
+#[derive(Debug, Clone)]
+pub struct UsageEcho {
+    // 网关密钥ID，未使用网关密钥时不回显累计花费
+    key_id: Option<String>,
+}
+
+impl UsageEcho {
+    /// 根据请求头和网关密钥的设置决定是否开启，请求头优先
+    pub fn from_request(headers: &HeaderMap, gateway_key: Option<&GatewayKeyIdentity>) -> Option<Self> {
+        let requested = headers
+            .get(USAGE_ECHO_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| match value.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" => Some(true),
+                "false" | "0" => Some(false),
+                _ => None,
+            });
+        let enabled = requested.unwrap_or_else(|| gateway_key.is_some_and(|key| key.usage_echo));
+        enabled.then(|| Self { key_id: gateway_key.map(|key| key.id.clone()) })
+    }
+
+    /// 计算回显字段；成本在计费之后才能确定，须在 charge_usage 之后调用
+    pub async fn fields(&self, state: &AppState, cost: Option<f64>, latency: Duration) -> Map<String, Value> {
+        let mut fields = Map::new();
+        fields.insert("x_gateway_cost".to_string(), cost.into());
+        fields.insert("x_gateway_latency_ms".to_string(), (latency.as_millis() as u64).into());
+
+        if let Some(key_id) = &self.key_id {
+            let period = GatewayKeySpend::current_period();
+            let spent = state.db_metrics.run("gateway_key_spend.spent", || GatewayKeySpend::spent(&state.db, key_id, &period));
+            match spent.await {
+                Ok(spent) => {
+                    fields.insert("x_gateway_key_spend".to_string(), spent.into());
+                }
+                Err(e) => error!("查询网关密钥累计花费失败: key_id={}, 错误={}", key_id, e),
+            }
+        }
+        fields
+    }
+}
+
+/// 把回显字段合并进响应JSON的 usage 对象，没有 usage 对象时不做修改
+pub fn append_fields(json: &mut Value, fields: &Map<String, Value>) -> bool {
+    match json.get_mut("usage").and_then(Value::as_object_mut) {
+        Some(usage) => {
+            usage.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+            true
+        }
+        None => false,
+    }
+}
+
+/// 开启用量回显时暂缓转发携带 usage 的流式事件（及紧随其后的结束标记），
+/// 等计费完成后补上回显字段再转发；返回可以立即转发的事件
+pub fn hold_usage_event(held: &mut Vec<SseEvent>, event: SseEvent, has_usage: bool) -> Vec<SseEvent> {
+    if has_usage {
+        // 新的 usage 事件到达，之前暂缓的事件已不是最终用量，原样转发
+        let ready = std::mem::take(held);
+        held.push(event);
+        ready
+    } else if !held.is_empty() && event.data.as_deref() == Some("[DONE]") {
+        held.push(event);
+        Vec::new()
+    } else {
+        let mut ready = std::mem::take(held);
+        ready.push(event);
+        ready
+    }
+}
+
+/// 为暂缓的流式事件补上回显字段，不是 usage 事件时原样返回
+pub fn echo_event(event: &SseEvent, fields: &Map<String, Value>) -> Bytes {
+    let mut json = match event.data.as_deref().and_then(|data| serde_json::from_str::<Value>(data).ok()) {
+        Some(json) => json,
+        None => return event.raw.clone(),
+    };
+    if append_fields(&mut json, fields) {
+        Bytes::from(format!("data: {}\n\n", json))
+    } else {
+        event.raw.clone()
+    }
+}
@@ -0,0 +1,405 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::middlewares::admin_auth::{issue_jwt, CurrentUser};
+use crate::models::admin_token::{is_valid_scope, AdminToken};
+use crate::models::user::{hash_password, verify_password, User, UserRole};
+use crate::routes::api::AppState;
+
+// 密码最小长度
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// 创建用户请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateUserRequest {
+    /// 登录用户名
+    pub username: String,
+    /// 邮箱
+    #[serde(default)]
+    pub email: Option<String>,
+    /// 登录密码（至少8个字符）
+    pub password: String,
+    /// 角色（缺省为 ReadOnly），决定可以持有的作用域上限
+    #[serde(default)]
+    pub role: UserRole,
+    /// 作用域列表（缺省使用角色的默认作用域），登录签发的令牌继承这些作用域，支持 "providers:*" 和 "*"
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
+}
+
+/// 更新用户请求（未提供的字段保持不变）
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateUserRequest {
+    /// 邮箱
+    #[serde(default)]
+    pub email: Option<String>,
+    /// 新密码
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 角色，修改角色且未提供作用域时重置为新角色的默认作用域
+    #[serde(default)]
+    pub role: Option<UserRole>,
+    /// 作用域列表
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+/// 用户列表
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserList {
+    /// 用户列表
+    pub users: Vec<User>,
+}
+
+/// 登录请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    /// 用户名
+    pub username: String,
+    /// 密码
+    pub password: String,
+}
+
+/// 登录响应（令牌明文仅返回这一次）
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LoginResponse {
+    /// 管理令牌明文，作为 Bearer 令牌调用管理接口；JWT_EXPIRATION 秒后过期，再次登录时之前的令牌被吊销
+    pub token: String,
+    /// 令牌信息
+    pub token_info: AdminToken,
+    /// 携带用户角色的登录JWT（配置了 JWT_SECRET 时签发），作为 Bearer 令牌调用管理接口时按角色检查权限
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    /// 用户信息
+    pub user: User,
+}
+
+/// 创建用户
+#[utoipa::path(
+    post,
+    path = "/v1/users",
+    request_body = CreateUserRequest,
+    responses(
+        (status = 201, description = "成功创建用户", body = User),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 409, description = "用户名已存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn create_user(
+    State(state): State<AppState>,
+    Json(request): Json<CreateUserRequest>,
+) -> Response {
+    let username = request.username.trim();
+    if username.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "用户名不能为空".to_string());
+    }
+    let scopes = request.scopes.unwrap_or_else(|| role_scopes(request.role));
+    if let Err(e) = validate_password(&request.password).and_then(|_| validate_scopes(&scopes, request.role)) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+
+    let password_hash = match hash_in_background(request.password).await {
+        Ok(hash) => hash,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    match User::create(&state.db, username, request.email.as_deref(), &password_hash, request.role, &scopes).await {
+        Ok(user) => {
+            info!("用户已创建: username={}, role={}, scopes={}", user.username, user.role, user.scopes);
+            (StatusCode::CREATED, Json(user)).into_response()
+        }
+        Err(e) if is_unique_violation(&e) => {
+            error_response(StatusCode::CONFLICT, format!("用户名已存在: {}", username))
+        }
+        Err(e) => internal_error("创建用户失败", e),
+    }
+}
+
+/// 列出用户
+#[utoipa::path(
+    get,
+    path = "/v1/users",
+    responses(
+        (status = 200, description = "成功获取用户列表", body = UserList),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn list_users(
+    State(state): State<AppState>,
+) -> Response {
+    match User::list(&state.db).await {
+        Ok(users) => (StatusCode::OK, Json(UserList { users })).into_response(),
+        Err(e) => internal_error("获取用户列表失败", e),
+    }
+}
+
+/// 查看用户
+#[utoipa::path(
+    get,
+    path = "/v1/users/{id}",
+    params(
+        ("id" = String, Path, description = "用户ID")
+    ),
+    responses(
+        (status = 200, description = "成功获取用户", body = User),
+        (status = 404, description = "用户不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    match User::find(&state.db, &id).await {
+        Ok(Some(user)) => (StatusCode::OK, Json(user)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("用户不存在: {}", id)),
+        Err(e) => internal_error("获取用户失败", e),
+    }
+}
+
+/// 更新用户
+/// 修改密码、角色、作用域或停用用户时吊销该用户已签发的令牌，需要重新登录
+#[utoipa::path(
+    put,
+    path = "/v1/users/{id}",
+    params(
+        ("id" = String, Path, description = "用户ID")
+    ),
+    request_body = UpdateUserRequest,
+    responses(
+        (status = 200, description = "成功更新用户", body = User),
+        (status = 400, description = "请求参数错误", body = ErrorResponse),
+        (status = 404, description = "用户不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn update_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<UpdateUserRequest>,
+) -> Response {
+    if let Err(e) = request.password.as_deref().map_or(Ok(()), validate_password) {
+        return error_response(StatusCode::BAD_REQUEST, e);
+    }
+
+    // 修改角色时作用域缺省重置为新角色的默认值；作用域须在角色（新角色或现有角色）允许的范围内
+    let scopes = request.scopes.or_else(|| request.role.map(role_scopes));
+    if let Some(scopes) = &scopes {
+        let role = match request.role {
+            Some(role) => role,
+            None => match User::find(&state.db, &id).await {
+                Ok(Some(user)) => user.role(),
+                Ok(None) => return error_response(StatusCode::NOT_FOUND, format!("用户不存在: {}", id)),
+                Err(e) => return internal_error("获取用户失败", e),
+            },
+        };
+        if let Err(e) = validate_scopes(scopes, role) {
+            return error_response(StatusCode::BAD_REQUEST, e);
+        }
+    }
+
+    let revoke_tokens = request.password.is_some() || scopes.is_some() || request.enabled == Some(false);
+    let password_hash = match request.password {
+        Some(password) => match hash_in_background(password).await {
+            Ok(hash) => Some(hash),
+            Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+        },
+        None => None,
+    };
+
+    let updated = User::update(
+        &state.db,
+        &id,
+        request.email.as_deref(),
+        password_hash.as_deref(),
+        request.role,
+        scopes.as_deref(),
+        request.enabled,
+    ).await;
+    let user = match updated {
+        Ok(Some(user)) => user,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, format!("用户不存在: {}", id)),
+        Err(e) => return internal_error("更新用户失败", e),
+    };
+
+    if revoke_tokens {
+        match AdminToken::revoke_for_user(&state.db, &user.id).await {
+            Ok(count) if count > 0 => info!("用户凭据或权限已变更，吊销其令牌: username={}, 数量={}", user.username, count),
+            Ok(_) => {}
+            Err(e) => return internal_error("吊销用户令牌失败", e),
+        }
+    }
+
+    info!("用户已更新: username={}", user.username);
+    (StatusCode::OK, Json(user)).into_response()
+}
+
+/// 删除用户（同时吊销该用户的令牌）
+#[utoipa::path(
+    delete,
+    path = "/v1/users/{id}",
+    params(
+        ("id" = String, Path, description = "用户ID")
+    ),
+    responses(
+        (status = 204, description = "成功删除用户"),
+        (status = 404, description = "用户不存在", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    if let Err(e) = AdminToken::revoke_for_user(&state.db, &id).await {
+        return internal_error("吊销用户令牌失败", e);
+    }
+
+    match User::delete(&state.db, &id).await {
+        Ok(true) => {
+            info!("用户已删除: id={}", id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => error_response(StatusCode::NOT_FOUND, format!("用户不存在: {}", id)),
+        Err(e) => internal_error("删除用户失败", e),
+    }
+}
+
+/// 用户登录，签发继承用户作用域的管理令牌，配置了 JWT_SECRET 时同时签发携带角色的JWT
+#[utoipa::path(
+    post,
+    path = "/v1/users/login",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "登录成功", body = LoginResponse),
+        (status = 401, description = "用户名或密码错误", body = ErrorResponse),
+        (status = 403, description = "接口调用方用户不能登录管理接口", body = ErrorResponse),
+        (status = 500, description = "服务器内部错误", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn login(
+    State(state): State<AppState>,
+    Json(request): Json<LoginRequest>,
+) -> Response {
+    let found = match User::find_with_password(&state.db, request.username.trim()).await {
+        Ok(found) => found,
+        Err(e) => return internal_error("查询用户失败", e),
+    };
+
+    // 用户不存在、已停用或密码错误时返回相同的错误，避免暴露用户名是否存在
+    let (user, password_hash) = match found {
+        Some((user, password_hash)) if user.enabled => (user, password_hash),
+        _ => return error_response(StatusCode::UNAUTHORIZED, "用户名或密码错误".to_string()),
+    };
+    let password = request.password;
+    let verified = tokio::task::spawn_blocking(move || verify_password(&password, &password_hash))
+        .await
+        .unwrap_or(false);
+    if !verified {
+        info!("用户登录失败: username={}", user.username);
+        return error_response(StatusCode::UNAUTHORIZED, "用户名或密码错误".to_string());
+    }
+    // 接口调用方没有任何管理作用域，只通过网关密钥调用推理接口
+    if user.role() == UserRole::ApiConsumer {
+        info!("接口调用方用户尝试登录管理接口: username={}", user.username);
+        return error_response(StatusCode::FORBIDDEN, "接口调用方用户不能登录管理接口".to_string());
+    }
+
+    // 登录令牌与登录JWT同时过期，重新登录时吊销之前签发的令牌，避免累积长期有效的凭证
+    if let Err(e) = AdminToken::revoke_for_user(&state.db, &user.id).await {
+        return internal_error("吊销之前的登录令牌失败", e);
+    }
+    let name = format!("login:{}", user.username);
+    let expires_at = Utc::now() + Duration::seconds(state.config.auth.jwt_expiration as i64);
+    let (token_info, token) = match AdminToken::create(&state.db, &name, &user.scope_list(), Some(&user.id), Some(expires_at)).await {
+        Ok(created) => created,
+        Err(e) => return internal_error("签发登录令牌失败", e),
+    };
+    if let Err(e) = User::record_login(&state.db, &user.id).await {
+        error!("记录登录时间失败: username={}, 错误={}", user.username, e);
+    }
+
+    let access_token = issue_jwt(&state.config.auth, &user);
+
+    info!("用户登录成功: username={}", user.username);
+    (StatusCode::OK, Json(LoginResponse { token, token_info, access_token, user })).into_response()
+}
+
+/// 查看登录JWT对应的当前用户及其角色
+#[utoipa::path(
+    get,
+    path = "/v1/users/me",
+    responses(
+        (status = 200, description = "成功获取当前用户", body = User),
+        (status = 401, description = "缺少JWT、JWT无效或用户已停用", body = ErrorResponse),
+    ),
+    tag = "users"
+)]
+pub async fn get_current_user(current: CurrentUser) -> Response {
+    (StatusCode::OK, Json(current.user)).into_response()
+}
+
+fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!("密码至少需要 {} 个字符", MIN_PASSWORD_LEN));
+    }
+    Ok(())
+}
+
+fn validate_scopes(scopes: &[String], role: UserRole) -> Result<(), String> {
+    if scopes.is_empty() && role != UserRole::ApiConsumer {
+        return Err("作用域不能为空".to_string());
+    }
+    if let Some(scope) = scopes.iter().find(|s| !is_valid_scope(s)) {
+        return Err(format!("未知的作用域: {}", scope));
+    }
+    match scopes.iter().find(|s| !role.permits(s)) {
+        Some(scope) => Err(format!("角色 {:?} 不允许作用域: {}", role, scope)),
+        None => Ok(()),
+    }
+}
+
+fn role_scopes(role: UserRole) -> Vec<String> {
+    role.scopes().iter().map(|s| s.to_string()).collect()
+}
+
+// argon2 计算较慢，放到阻塞线程中执行
+async fn hash_in_background(password: String) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| format!("计算密码哈希失败: {}", e))?
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().is_some_and(|e| e.is_unique_violation())
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
+
+fn internal_error(message: &str, e: sqlx::Error) -> Response {
+    error!("{}: {}", message, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse { error: format!("{}: {}", message, e) }),
+    ).into_response()
+}
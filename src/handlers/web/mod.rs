@@ -1 +1,3 @@
-// Web处理器模块，暂时留空 
\ No newline at end of file
+// Web处理器模块：无需网关凭证即可访问的页面
+
+pub mod usage_report;
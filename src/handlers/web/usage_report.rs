@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Json, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fmt::Write;
+use tracing::error;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+use crate::services::usage_cost::UsageCostGroup;
+use crate::services::usage_report::{self, SignedReportParams, UsageReport};
+
+/// 报表输出格式
+#[derive(Debug, Deserialize)]
+pub struct ReportFormat {
+    /// html（默认）或 json
+    pub format: Option<String>,
+}
+
+/// 通过签名链接查看用量报表，签名和有效期校验通过后按天列出用量和成本
+pub async fn get_shared_usage_report(
+    State(state): State<AppState>,
+    Query(params): Query<SignedReportParams>,
+    Query(format): Query<ReportFormat>,
+) -> Response {
+    let secret = match &state.config.report_links.signing_secret {
+        Some(secret) => secret,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse { error: "分享链接已停用".to_string() }),
+            ).into_response();
+        }
+    };
+    let subject = match usage_report::verify(secret, &params, Utc::now()) {
+        Ok(subject) => subject,
+        Err(reason) => {
+            return (StatusCode::FORBIDDEN, Json(ErrorResponse { error: reason.to_string() })).into_response();
+        }
+    };
+    let expires_at = DateTime::from_timestamp(params.expires, 0).unwrap_or_default();
+    let report = match usage_report::build(&state.db, &subject, params.from, params.to, expires_at).await {
+        Ok(report) => report,
+        Err(e) => {
+            error!("生成用量报表失败: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: format!("生成用量报表失败: {}", e) }),
+            ).into_response();
+        }
+    };
+
+    match format.format.as_deref() {
+        Some("json") => (StatusCode::OK, Json(report)).into_response(),
+        Some("html") | None => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            render_html(&report),
+        ).into_response(),
+        Some(other) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: format!("不支持的格式: {}（可选 html、json）", other) }),
+        ).into_response(),
+    }
+}
+
+// 渲染为简单的HTML表格
+fn render_html(report: &UsageReport) -> String {
+    let subject_label = if report.subject_type == "key" { "网关密钥" } else { "组织" };
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>用量报表</title>\n\
+         <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}\
+         th,td{{border:1px solid #ccc;padding:4px 12px;text-align:right}}th:first-child,td:first-child{{text-align:left}}</style>\n\
+         </head>\n<body>\n<h1>用量报表</h1>\n<p>{}：{}</p>\n<p>日期范围（UTC）：{} 至 {}</p>\n\
+         <p>生成时间：{}，链接有效期至：{}</p>\n<table>\n\
+         <tr><th>日期</th><th>请求数</th><th>输入token</th><th>输出token</th><th>成本</th><th>未计价请求</th></tr>\n",
+        subject_label,
+        escape_html(&report.subject),
+        report.from,
+        report.to,
+        report.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        report.expires_at.format("%Y-%m-%d %H:%M:%S UTC"),
+    );
+    for day in &report.usage.groups {
+        push_row(&mut html, day.date.as_deref().unwrap_or(""), day);
+    }
+    push_row(&mut html, "合计", &report.usage.total);
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+fn push_row(html: &mut String, label: &str, group: &UsageCostGroup) {
+    let _ = writeln!(
+        html,
+        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.6}</td><td>{}</td></tr>",
+        escape_html(label),
+        group.requests,
+        group.prompt_tokens,
+        group.completion_tokens,
+        group.cost,
+        group.unpriced_requests,
+    );
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
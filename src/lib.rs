@@ -7,6 +7,8 @@ pub mod services;
 pub mod errors;
 pub mod utils;
 pub mod middlewares;
+pub mod server;
+
 
 #[cfg(test)]
 pub mod tests;
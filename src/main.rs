@@ -1,14 +1,13 @@
 use std::sync::Arc;
-use tokio::time::{interval, Duration};
 use api_manager::{
     config::AppConfig,
     database::initialize_database,
     routes::api::app_routes,
-    services::{balance_checker::BalanceChecker, provider_pool::initialize_provider_pool},
+    server,
+    services::{balance_checker::BalanceChecker, contract_expiry::check_expiry, error_reporter, provider_cooldown, model_catalog, provider_latency, provider_pool::initialize_provider_pool, reconcile, scheduler, upstream_client, usage_retention, usage_rollup},
 };
 use tracing::{info, error};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use std::net::SocketAddr;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -25,21 +24,42 @@ async fn main() -> anyhow::Result<()> {
     // 加载配置
     let config = AppConfig::from_env()?;
     info!("环境: {:?}", config.environment);
-    info!("监听地址: {}", config.socket_addr());
+    if config.server.tcp_enabled {
+        info!("监听地址: {}", config.socket_addr());
+    }
+    if let Some(path) = &config.server.unix_socket {
+        info!("UNIX域套接字: {}", path.display());
+    }
+
+    // 提供商池加载后按配置在后台预热上游
+    upstream_client::configure(&config);
+
+    // 配置了 SENTRY_DSN 时上报 panic 和上游失败
+    error_reporter::configure(&config);
+    provider_cooldown::configure(&config.provider_cooldown);
 
     // 初始化数据库
     let db_pool = initialize_database(&config.database).await?;
     let db_pool = Arc::new(db_pool);
 
+    // 启动时检查使用记录与账本、成本的一致性
+    reconcile::check_on_startup(&db_pool).await;
+
+    // 提供请求服务的代理池，余额检查、隔离和合同到期停用都直接修改这个池
     info!("初始化API代理池...");
-    let provider_pool = Arc::new(tokio::sync::Mutex::new(
+    let provider_pool = Arc::new(tokio::sync::RwLock::new(
         initialize_provider_pool(&db_pool)
             .await
             .expect("Failed to initialize provider pool")
     ));
 
     // 创建余额检查器
-    let balance_checker = Arc::new(BalanceChecker::new(db_pool.clone(), provider_pool.clone()));
+    let balance_checker = Arc::new(BalanceChecker::new(
+        db_pool.clone(),
+        provider_pool.clone(),
+        config.health_check.quarantine_failure_threshold,
+        config.health_check.timeout,
+    ));
 
     // 启动时立即执行一次余额检查（从数据库加载）
     info!("开始启动时余额检查...");
@@ -47,32 +67,62 @@ async fn main() -> anyhow::Result<()> {
         error!("启动时余额检查失败: {}", e);
     }
 
-    // 启动定期余额检查任务（从数据库加载）
+    // 注册定时任务：定期余额检查、提供商合同到期检查、使用记录汇总和清理、耗时统计写入、模型目录同步
+    let jobs = scheduler::scheduler();
     let checker_clone = balance_checker.clone();
-    tokio::spawn(async move {
-        let mut interval = interval(Duration::from_secs(300)); // 每5分钟检查一次
-        loop {
-            interval.tick().await;
-            info!("开始定期余额检查...");
-            if let Err(e) = checker_clone.check_all_providers_from_db().await {
-                error!("定期余额检查失败: {}", e);
+    jobs.register(
+        "balance_check",
+        "从数据库加载提供商检查余额",
+        scheduler::resolve(&config.scheduler, "balance_check", "@every 5m"),
+        move || {
+            let checker = checker_clone.clone();
+            async move {
+                info!("开始定期余额检查...");
+                checker.check_all_providers_from_db().await.map_err(|e| format!("定期余额检查失败: {}", e))
             }
-        }
-    });
+        },
+    );
+
+    // 停用已到期的提供商（从提供请求服务的代理池中移除），提前提醒即将到期的提供商
+    let expiry_db = db_pool.clone();
+    let expiry_pool = provider_pool.clone();
+    let alert_days = config.provider_expiry.alert_days;
+    jobs.register(
+        "contract_expiry",
+        "停用合同已到期的提供商并提醒即将到期的提供商",
+        scheduler::resolve(
+            &config.scheduler,
+            "contract_expiry",
+            &format!("@every {}s", config.provider_expiry.check_interval_secs),
+        ),
+        move || {
+            let (db, pool) = (expiry_db.clone(), expiry_pool.clone());
+            async move {
+                check_expiry(&db, &pool, alert_days).await;
+                Ok(())
+            }
+        },
+    );
+
+    usage_rollup::register(&jobs, &config.scheduler, (*db_pool).clone(), config.usage_rollup.clone());
+    usage_retention::register(&jobs, &config.scheduler, (*db_pool).clone(), config.usage_retention.clone());
+
+    // 加载之前保存的提供商耗时统计，并定期写回数据库
+    if let Err(e) = provider_latency::tracker().load(&db_pool).await {
+        error!("加载提供商耗时统计失败: {}", e);
+    }
+    provider_latency::register(&jobs, &config.scheduler, (*db_pool).clone());
+
+    // 定期从提供商的模型列表接口同步模型目录
+    model_catalog::register(&jobs, &config.scheduler, (*db_pool).clone(), config.openrouter.clone());
 
     info!("API代理池初始化成功");
 
     // 创建路由
-    let app = app_routes((*db_pool).clone(), config.clone()).await;
+    let app = app_routes((*db_pool).clone(), config.clone(), provider_pool).await;
 
     // 启动服务器
-    let addr = config.socket_addr();
-    info!("Starting server on {}", addr);
-    axum::serve(
-        tokio::net::TcpListener::bind(&addr).await?,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await?;
+    server::serve(app, &config).await?;
 
     Ok(())
 }
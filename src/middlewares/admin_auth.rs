@@ -0,0 +1,206 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::config::AuthConfig;
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::admin_token::AdminToken;
+use crate::models::user::{User, UserRole};
+use crate::routes::api::AppState;
+use crate::utils::client_key::client_key;
+
+/// 管理接口所需的作用域
+#[derive(Clone)]
+pub struct ScopeGuard {
+    state: AppState,
+    scope: &'static str,
+    query_token: bool,
+}
+
+impl ScopeGuard {
+    pub fn new(state: AppState, scope: &'static str) -> Self {
+        Self { state, scope, query_token: false }
+    }
+
+    /// 允许通过 access_token 查询参数传入只读作用域令牌（用于日历订阅等无法设置请求头的场景）
+    pub fn with_query_token(mut self) -> Self {
+        self.query_token = true;
+        self
+    }
+}
+
+/// 登录JWT携带的声明
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleClaims {
+    /// 用户ID
+    pub sub: String,
+    /// 签发时的用户角色
+    pub role: UserRole,
+    /// 过期时间（Unix秒）
+    pub exp: i64,
+}
+
+/// 通过登录JWT识别的当前用户；角色以数据库中的当前值为准，JWT签发后的角色变更和停用立即生效
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub user: User,
+    pub role: UserRole,
+}
+
+/// 为用户签发登录JWT，未配置 JWT_SECRET 时返回 None
+pub fn issue_jwt(auth: &AuthConfig, user: &User) -> Option<String> {
+    let secret = auth.jwt_secret.as_ref()?;
+    let claims = RoleClaims {
+        sub: user.id.clone(),
+        role: user.role(),
+        exp: Utc::now().timestamp() + auth.jwt_expiration as i64,
+    };
+    match jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            error!("签发登录JWT失败: username={}, 错误={}", user.username, e);
+            None
+        }
+    }
+}
+
+// 管理令牌以 amk_ 开头，JWT 由三段 base64 组成
+fn is_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+/// 校验登录JWT并加载对应的用户
+async fn resolve_user(state: &AppState, token: &str) -> Result<CurrentUser, Response> {
+    let secret = match &state.config.auth.jwt_secret {
+        Some(secret) => secret,
+        None => return Err(error_response(StatusCode::UNAUTHORIZED, "未配置 JWT_SECRET，不接受JWT".to_string())),
+    };
+    let claims = match jsonwebtoken::decode::<RoleClaims>(token, &DecodingKey::from_secret(secret.as_bytes()), &Validation::default()) {
+        Ok(data) => data.claims,
+        Err(e) => {
+            info!("登录JWT校验失败: {}", e);
+            return Err(error_response(StatusCode::UNAUTHORIZED, "JWT无效或已过期".to_string()));
+        }
+    };
+    match User::find(&state.db, &claims.sub).await {
+        Ok(Some(user)) if user.enabled => {
+            let role = user.role();
+            Ok(CurrentUser { user, role })
+        }
+        Ok(_) => Err(error_response(StatusCode::UNAUTHORIZED, "JWT对应的用户不存在或已停用".to_string())),
+        Err(e) => {
+            error!("查询用户失败: {}", e);
+            Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查询用户失败: {}", e)))
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for CurrentUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match client_key(&parts.headers) {
+            Some(token) if is_jwt(&token) => resolve_user(state, &token).await,
+            _ => Err(error_response(StatusCode::UNAUTHORIZED, "缺少登录JWT".to_string())),
+        }
+    }
+}
+
+/// 管理接口鉴权中间件
+/// 根令牌拥有全部作用域，其余令牌按作用域检查，登录JWT按用户角色检查
+/// （每组路由的作用域前缀对应角色的权限范围，例如 ReadOnly 只能访问 *:read）；
+/// 未配置 ADMIN_API_TOKEN 时放行未携带凭证的请求，但携带的JWT和作用域令牌仍然按角色和作用域检查
+pub async fn require_scope(
+    State(guard): State<ScopeGuard>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let root_token = guard.state.config.auth.admin_api_token.as_ref();
+
+    // 日历等无法设置请求头的订阅方可在允许的路由上使用 access_token 查询参数，
+    // 查询参数会出现在URL和代理日志中，只接受只读作用域令牌
+    let (token, from_query) = match client_key(request.headers()) {
+        Some(token) => (token, false),
+        None => match query_token(&request).filter(|_| guard.query_token) {
+            Some(token) => (token, true),
+            None if root_token.is_none() => return next.run(request).await,
+            None => return error_response(StatusCode::UNAUTHORIZED, "缺少管理令牌".to_string()),
+        },
+    };
+
+    if from_query {
+        return match find_token(&guard, &token).await {
+            Ok(admin_token) if admin_token.is_read_only() => next.run(request).await,
+            Ok(_) => error_response(StatusCode::FORBIDDEN, "access_token 查询参数只接受只读作用域令牌".to_string()),
+            Err(response) => response,
+        };
+    }
+
+    if root_token.is_some_and(|root_token| constant_time_eq(&token, root_token)) {
+        return next.run(request).await;
+    }
+
+    if is_jwt(&token) {
+        return match resolve_user(&guard.state, &token).await {
+            Ok(current) if current.role.permits(guard.scope) => next.run(request).await,
+            Ok(current) => {
+                info!("用户角色缺少权限: username={}, 角色={:?}, 需要={}", current.user.username, current.role, guard.scope);
+                error_response(StatusCode::FORBIDDEN, format!("角色 {:?} 没有权限: {}", current.role, guard.scope))
+            }
+            Err(response) => response,
+        };
+    }
+
+    match find_token(&guard, &token).await {
+        Ok(_) => next.run(request).await,
+        Err(response) => response,
+    }
+}
+
+/// 查找未吊销且拥有所需作用域的作用域令牌
+async fn find_token(guard: &ScopeGuard, token: &str) -> Result<AdminToken, Response> {
+    let lookup = guard.state.db_metrics.run("admin_tokens.find_active", || {
+        AdminToken::find_active(&guard.state.db, token)
+    });
+    match lookup.await {
+        Ok(Some(admin_token)) if admin_token.allows(guard.scope) => Ok(admin_token),
+        Ok(Some(admin_token)) => {
+            info!("管理令牌缺少作用域: token={}, 需要={}", admin_token.name, guard.scope);
+            Err(error_response(StatusCode::FORBIDDEN, format!("令牌缺少作用域: {}", guard.scope)))
+        }
+        Ok(None) => Err(error_response(StatusCode::UNAUTHORIZED, "管理令牌无效或已吊销".to_string())),
+        Err(e) => {
+            error!("查询管理令牌失败: {}", e);
+            Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查询管理令牌失败: {}", e)))
+        }
+    }
+}
+
+// 查询参数中的令牌按 URL 编码解码
+fn query_token(request: &Request) -> Option<String> {
+    serde_urlencoded::from_str::<Vec<(String, String)>>(request.uri().query()?)
+        .ok()?
+        .into_iter()
+        .find_map(|(name, value)| (name == "access_token").then_some(value))
+}
+
+// 先取摘要再逐字节比较，比较耗时与令牌内容和长度无关
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (Sha256::digest(a.as_bytes()), Sha256::digest(b.as_bytes()));
+    a.iter().zip(b.iter()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
@@ -0,0 +1,69 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::error;
+
+use crate::routes::api::AppState;
+use crate::services::degradation::{assess, DegradationAdvisory};
+
+/// 降级原因响应头（逗号分隔的原因代码）
+pub const DEGRADED_HEADER: &str = "X-Gateway-Degraded";
+/// JSON响应中附带的降级提示字段
+pub const ADVISORY_FIELD: &str = "gateway_advisory";
+
+/// 降级提示中间件
+/// 网关处于降级状态时在推理响应头中标明原因；非流式JSON响应额外附带提示字段，供下游展示给用户
+pub async fn degradation_advisory(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+
+    let stats = state.provider_stats.load();
+    let advisory = match assess(&state.config.degradation, &stats, &state.db_metrics) {
+        Some(advisory) => advisory,
+        None => return response,
+    };
+
+    let mut response = with_advisory_field(response, &advisory).await;
+    if let Ok(value) = HeaderValue::from_str(&advisory.reasons.join(",")) {
+        response.headers_mut().insert(DEGRADED_HEADER, value);
+    }
+    response
+}
+
+// 在JSON对象响应中加入提示字段，其他响应（如SSE流）原样返回
+async fn with_advisory_field(response: Response, advisory: &DegradationAdvisory) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json || response.headers().contains_key(header::CONTENT_ENCODING) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("读取响应体失败，无法附带降级提示: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert(ADVISORY_FIELD.to_string(), serde_json::json!(advisory));
+            serde_json::to_vec(&object).map(Body::from).unwrap_or_else(|_| Body::from(bytes))
+        }
+        _ => Body::from(bytes),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, body)
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, info};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::models::gateway_key::{GatewayKey, KeyRateLimits, KEY_PREFIX};
+use crate::routes::api::AppState;
+use crate::utils::client_key::client_key;
+
+/// 通过网关密钥鉴权的调用方身份，由中间件写入请求扩展
+#[derive(Debug, Clone)]
+pub struct GatewayKeyIdentity {
+    /// 密钥ID
+    pub id: String,
+    /// 密钥用途说明
+    pub name: String,
+    /// 密钥的限流设置
+    pub limits: KeyRateLimits,
+    /// 密钥的月度预算
+    pub monthly_budget: Option<f64>,
+    /// 是否在响应的 usage 中附带成本、上游耗时和当月累计花费
+    pub usage_echo: bool,
+    /// 所属组织（设置后只使用绑定到该组织的提供商）
+    pub organization: Option<String>,
+    /// 是否开启严格 OpenAI 兼容模式
+    pub strict_openai: bool,
+    /// 默认负载均衡策略
+    pub load_balance_strategy: Option<String>,
+    /// 流式输出节流速率（tokens/秒）
+    pub stream_pacing_tps: Option<f64>,
+}
+
+/// 网关密钥鉴权中间件
+/// 校验 Bearer 网关密钥并把调用方身份写入请求扩展；未开启 REQUIRE_GATEWAY_KEY 时只识别身份，不拒绝请求
+pub async fn authenticate_gateway_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let required = state.config.auth.require_gateway_key;
+    // 不是网关密钥格式的凭证不查询网关密钥表
+    let key = client_key(request.headers()).filter(|key| key.starts_with(KEY_PREFIX));
+    let key = match key {
+        Some(key) => key,
+        None if required => return error_response(StatusCode::UNAUTHORIZED, "缺少网关密钥".to_string()),
+        None => return next.run(request).await,
+    };
+
+    let lookup = state.db_metrics.run("gateway_keys.find_active", || GatewayKey::find_active(&state.db, &key));
+    match lookup.await {
+        Ok(Some(gateway_key)) if gateway_key.is_expired() => {
+            info!("网关密钥已过期: name={}, prefix={}", gateway_key.name, gateway_key.key_prefix);
+            error_response(StatusCode::UNAUTHORIZED, "网关密钥已过期".to_string())
+        }
+        Ok(Some(gateway_key)) => {
+            request.extensions_mut().insert(GatewayKeyIdentity {
+                id: gateway_key.id,
+                name: gateway_key.name,
+                limits: KeyRateLimits {
+                    requests_per_minute: gateway_key.requests_per_minute,
+                    tokens_per_minute: gateway_key.tokens_per_minute,
+                },
+                monthly_budget: gateway_key.monthly_budget,
+                usage_echo: gateway_key.usage_echo,
+                organization: gateway_key.organization,
+                strict_openai: gateway_key.strict_openai,
+                load_balance_strategy: gateway_key.load_balance_strategy,
+                stream_pacing_tps: gateway_key.stream_pacing_tps,
+            });
+            next.run(request).await
+        }
+        Ok(None) if required => {
+            info!("网关密钥无效或已吊销: prefix={}", key.get(..12).unwrap_or(&key));
+            error_response(StatusCode::UNAUTHORIZED, "网关密钥无效、已停用或已吊销".to_string())
+        }
+        Ok(None) => next.run(request).await,
+        Err(e) => {
+            error!("查询网关密钥失败: {}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查询网关密钥失败: {}", e))
+        }
+    }
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
@@ -0,0 +1,37 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::warn;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+
+/// 全局在途请求上限中间件
+/// 同时处理的请求数达到 MAX_IN_FLIGHT_REQUESTS 时返回429并附带 Retry-After，名额在响应返回后归还
+pub async fn limit_in_flight(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = state.in_flight.clone();
+    if !limiter.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let Some(_permit) = limiter.try_enter() else {
+        warn!("网关在途请求已达上限，拒绝请求: path={}", request.uri().path());
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse { error: "网关繁忙，请稍后重试".to_string() }),
+        ).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(limiter.retry_after_secs()));
+        return response;
+    };
+    next.run(request).await
+}
@@ -0,0 +1,35 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::net::SocketAddr;
+use tracing::debug;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+
+/// 客户端IP访问控制中间件
+/// 在路由和鉴权之前执行，不允许的客户端IP直接返回 403
+pub async fn enforce_ip_access(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(ip) = ip {
+        if !state.ip_access.permits(ip) {
+            debug!("客户端IP不允许访问: ip={}, path={}", ip, request.uri().path());
+            return (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse { error: "客户端IP不允许访问".to_string() }),
+            ).into_response();
+        }
+    }
+    next.run(request).await
+}
@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, info};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::models::gateway_key_spend::{effective_budget, GatewayKeySpend};
+use crate::routes::api::AppState;
+
+/// 网关密钥当月剩余预算响应头
+pub const BUDGET_REMAINING_HEADER: &str = "X-Key-Budget-Remaining";
+
+/// 网关密钥月度预算中间件
+/// 密钥当月花费达到预算时拒绝请求（402），并在响应头中返回剩余预算；未设置预算的密钥不受影响
+pub async fn enforce_gateway_key_quota(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let identity = match request.extensions().get::<GatewayKeyIdentity>() {
+        Some(identity) => identity.clone(),
+        None => return next.run(request).await,
+    };
+
+    let budget = effective_budget(identity.monthly_budget, state.config.auth.gateway_key_default_monthly_budget);
+    if budget <= 0.0 {
+        return next.run(request).await;
+    }
+
+    let period = GatewayKeySpend::current_period();
+    let lookup = state.db_metrics.run("gateway_key_spend.spent", || GatewayKeySpend::spent(&state.db, &identity.id, &period));
+    let spent = match lookup.await {
+        Ok(spent) => spent,
+        Err(e) => {
+            error!("查询网关密钥花费失败: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查询网关密钥预算失败: {}", e));
+        }
+    };
+
+    if spent >= budget {
+        info!("网关密钥本月预算已用完，拒绝请求: name={}, spent={}, budget={}", identity.name, spent, budget);
+        let mut response = error_response(StatusCode::PAYMENT_REQUIRED, "网关密钥本月预算已用完".to_string());
+        set_budget_header(&mut response, 0.0);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+
+    // 非流式请求在返回前已记录花费，重新查询得到扣费后的剩余预算；流式请求为请求开始时的剩余预算
+    let spent = state.db_metrics.run("gateway_key_spend.spent", || GatewayKeySpend::spent(&state.db, &identity.id, &period))
+        .await
+        .unwrap_or(spent);
+    set_budget_header(&mut response, (budget - spent).max(0.0));
+    response
+}
+
+fn set_budget_header(response: &mut Response, remaining: f64) {
+    if let Ok(value) = HeaderValue::from_str(&format!("{:.6}", remaining)) {
+        response.headers_mut().insert(BUDGET_REMAINING_HEADER, value);
+    }
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
@@ -0,0 +1,148 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::routes::api::AppState;
+use crate::services::key_rate_limiter::{KeyRateLimiter, RateLimitDecision};
+use crate::services::SseDecoder;
+
+/// 网关密钥限流中间件
+/// 按密钥的每分钟请求数和每分钟token数限流，超出时返回429；
+/// 放行的响应附带 x-ratelimit-* 响应头，并按响应中的 usage 扣减token额度
+pub async fn enforce_gateway_key_rate_limit(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let identity = match request.extensions().get::<GatewayKeyIdentity>() {
+        Some(identity) => identity.clone(),
+        None => return next.run(request).await,
+    };
+
+    // 密钥未单独设置时使用全局默认值，0表示不限制
+    let auth = &state.config.auth;
+    let requests_per_minute = limit_or(identity.limits.requests_per_minute, auth.gateway_key_default_rpm as u64);
+    let tokens_per_minute = limit_or(identity.limits.tokens_per_minute, auth.gateway_key_default_tpm);
+    if requests_per_minute == 0 && tokens_per_minute == 0 {
+        return next.run(request).await;
+    }
+
+    let decision = state.key_rate_limiter.check(&identity.id, requests_per_minute, tokens_per_minute);
+    if !decision.allowed {
+        info!(
+            "网关密钥超出限流: name={}, retry_after={}s",
+            identity.name, decision.retry_after_secs
+        );
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ErrorResponse {
+                error: format!("网关密钥请求过于频繁，请在 {} 秒后重试", decision.retry_after_secs),
+            }),
+        )
+            .into_response();
+        insert_rate_limit_headers(response.headers_mut(), &decision);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(decision.retry_after_secs));
+        return response;
+    }
+
+    let response = next.run(request).await;
+    let mut response = if tokens_per_minute > 0 {
+        meter_tokens(response, state.key_rate_limiter.clone(), identity.id).await
+    } else {
+        response
+    };
+    insert_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+fn limit_or(limit: Option<i64>, default: u64) -> u64 {
+    limit.map(|limit| limit.max(0) as u64).unwrap_or(default)
+}
+
+// 按 OpenAI 的约定输出 x-ratelimit-{limit,remaining,reset}-{requests,tokens}，reset 为恢复满额所需秒数
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    let buckets = [("requests", decision.requests), ("tokens", decision.tokens)];
+    for (kind, state) in buckets {
+        let Some(state) = state else { continue };
+        for (field, value) in [("limit", state.limit), ("remaining", state.remaining), ("reset", state.reset_secs)] {
+            if let Ok(name) = HeaderName::from_bytes(format!("x-ratelimit-{}-{}", field, kind).as_bytes()) {
+                headers.insert(name, HeaderValue::from(value));
+            }
+        }
+    }
+}
+
+// 从响应的 usage 中读取token用量并扣减额度：JSON响应读取完整响应体，SSE流在事件经过时解析
+async fn meter_tokens(response: Response, limiter: Arc<KeyRateLimiter>, key_id: String) -> Response {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if response.headers().contains_key(header::CONTENT_ENCODING) {
+        return response;
+    }
+
+    if content_type.starts_with("text/event-stream") {
+        let (parts, body) = response.into_parts();
+        let mut decoder = SseDecoder::new();
+        let stream = body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                for event in decoder.push(bytes) {
+                    let usage = event
+                        .data
+                        .as_deref()
+                        .and_then(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+                        .and_then(|value| total_tokens(&value));
+                    if let Some(tokens) = usage {
+                        limiter.consume_tokens(&key_id, tokens);
+                    }
+                }
+            }
+            chunk
+        });
+        return Response::from_parts(parts, Body::from_stream(stream));
+    }
+
+    if !content_type.starts_with("application/json") {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("读取响应体失败，无法统计token用量: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    if let Some(tokens) = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|value| total_tokens(&value))
+    {
+        limiter.consume_tokens(&key_id, tokens);
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn total_tokens(value: &serde_json::Value) -> Option<u64> {
+    let usage = value.get("usage")?;
+    usage.get("total_tokens").and_then(|v| v.as_u64()).or_else(|| {
+        let prompt = usage.get("prompt_tokens").and_then(|v| v.as_u64());
+        let completion = usage.get("completion_tokens").and_then(|v| v.as_u64());
+        (prompt.is_some() || completion.is_some()).then(|| prompt.unwrap_or(0) + completion.unwrap_or(0))
+    })
+}
@@ -0,0 +1,57 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::routes::api::AppState;
+use crate::services::latency_slo::RequestPriority;
+
+/// 请求优先级请求头（low / normal / high）
+pub const PRIORITY_HEADER: &str = "X-Request-Priority";
+/// 因降载被拒绝时的响应头
+pub const SHED_HEADER: &str = "X-Gateway-Shed";
+
+/// 延迟SLO中间件
+/// 降载期间拒绝低优先级请求（503）；其余请求记录从进入网关到返回响应头的耗时（流式请求即首字节时间），
+/// 客户端错误（鉴权失败、限流、预算不足等）不计入延迟统计
+pub async fn latency_slo_guard(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let guard = state.latency_slo.clone();
+    if !guard.is_enabled() {
+        return next.run(request).await;
+    }
+
+    let priority = request
+        .headers()
+        .get(PRIORITY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(RequestPriority::parse)
+        .unwrap_or(RequestPriority::Normal);
+    if guard.should_shed(priority) {
+        debug!("网关降载中，拒绝低优先级请求: path={}, priority={}", request.uri().path(), priority.as_str());
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse { error: "网关延迟过高，暂时拒绝低优先级请求，请稍后重试".to_string() }),
+        ).into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from(guard.retry_after_secs()));
+        headers.insert(SHED_HEADER, HeaderValue::from_static(priority.as_str()));
+        return response;
+    }
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    if !response.status().is_client_error() {
+        guard.record(started.elapsed());
+    }
+    response
+}
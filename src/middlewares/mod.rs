@@ -0,0 +1,10 @@
+pub mod request_tracing;
+pub mod prepaid_credit;
+pub mod admin_auth;
+pub mod degradation;
+pub mod gateway_auth;
+pub mod key_rate_limit;
+pub mod key_quota;
+pub mod latency_slo;
+pub mod ip_access;
+pub mod in_flight_limit;
@@ -0,0 +1,112 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::StreamExt;
+use tracing::{error, info};
+
+use crate::handlers::api::chat_completion::ErrorResponse;
+use crate::middlewares::gateway_auth::GatewayKeyIdentity;
+use crate::models::prepaid_key::PrepaidKey;
+use crate::routes::api::AppState;
+
+/// 剩余预付额度响应头
+pub const CREDIT_REMAINING_HEADER: &str = "X-Credit-Remaining";
+
+/// 预付额度检查中间件（在网关密钥鉴权之后执行）
+/// 预付费网关密钥的请求开始时原子地预扣 PREPAID_CREDIT_HOLD，余额不足时拒绝，并在响应头中返回剩余额度；
+/// 预扣额度在响应体发送完毕后归还，实际成本在记录借记时扣除。登记了预付费密钥后，
+/// 未携带有效网关密钥的请求一律拒绝，避免绕过额度检查；其余网关密钥不受影响
+pub async fn enforce_prepaid_credit(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key_id = match request.extensions().get::<GatewayKeyIdentity>() {
+        Some(identity) => identity.id.clone(),
+        None => {
+            return match state.db_metrics.run("prepaid_keys.any", || PrepaidKey::any(&state.db)).await {
+                Ok(false) => next.run(request).await,
+                Ok(true) => error_response(StatusCode::UNAUTHORIZED, "已启用预付额度，请求必须携带有效的网关密钥".to_string()),
+                Err(e) => lookup_failed(e),
+            };
+        }
+    };
+
+    match state.db_metrics.run("prepaid_keys.find", || PrepaidKey::find(&state.db, &key_id)).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return next.run(request).await,
+        Err(e) => return lookup_failed(e),
+    }
+
+    let amount = state.config.auth.prepaid_credit_hold;
+    let reserved = state.db_metrics.run("prepaid_keys.reserve", || PrepaidKey::reserve(&state.db, &key_id, amount));
+    match reserved.await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let balance = PrepaidKey::find(&state.db, &key_id).await.ok().flatten().map_or(0.0, |key| key.balance);
+            info!("预付额度不足，拒绝请求: gateway_key_id={}, balance={}", key_id, balance);
+            let mut response = error_response(StatusCode::PAYMENT_REQUIRED, "预付额度已用完，请充值后重试".to_string());
+            set_credit_header(&mut response, balance);
+            return response;
+        }
+        Err(e) => return lookup_failed(e),
+    }
+    let hold = CreditHold { db: state.db.clone(), key_id: key_id.clone(), amount };
+
+    let response = next.run(request).await;
+
+    // 非流式请求在返回前已记录借记，剩余额度为扣费后的余额；流式请求为请求开始时的余额
+    let remaining = state.db_metrics.run("prepaid_keys.find", || PrepaidKey::find(&state.db, &key_id))
+        .await
+        .ok()
+        .flatten()
+        .map_or(0.0, |key| key.balance + amount);
+
+    // 响应体发送完毕或客户端断开时归还预扣额度
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _ = &hold;
+        chunk
+    });
+    let mut response = Response::from_parts(parts, Body::from_stream(body));
+    set_credit_header(&mut response, remaining);
+    response
+}
+
+// 预扣的额度，drop 时归还
+struct CreditHold {
+    db: sqlx::SqlitePool,
+    key_id: String,
+    amount: f64,
+}
+
+impl Drop for CreditHold {
+    fn drop(&mut self) {
+        let (db, key_id, amount) = (self.db.clone(), std::mem::take(&mut self.key_id), self.amount);
+        tokio::spawn(async move {
+            if let Err(e) = PrepaidKey::release(&db, &key_id, amount).await {
+                error!("归还预扣额度失败: gateway_key_id={}, amount={}, 错误={}", key_id, amount, e);
+            }
+        });
+    }
+}
+
+fn set_credit_header(response: &mut Response, balance: f64) {
+    if let Ok(value) = HeaderValue::from_str(&format!("{:.6}", balance)) {
+        response.headers_mut().insert(CREDIT_REMAINING_HEADER, value);
+    }
+}
+
+fn lookup_failed(e: sqlx::Error) -> Response {
+    error!("查询预付额度失败: {}", e);
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("查询预付额度失败: {}", e))
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
@@ -0,0 +1,74 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::config::TracingConfig;
+use crate::routes::api::AppState;
+use crate::utils::client_key::client_key;
+
+/// 请求追踪中间件
+/// 按路由采样率决定是否为请求创建追踪span；失败请求和强制采样的密钥总是记录
+pub async fn trace_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = &state.config.tracing;
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let forced = is_force_sampled(config, &request);
+    let sampled = forced || rand::random::<f64>() < sample_rate(config, &route);
+
+    let start = std::time::Instant::now();
+    let response = if sampled {
+        let span = info_span!("request", %method, %route, forced);
+        next.run(request).instrument(span).await
+    } else {
+        next.run(request).await
+    };
+    let elapsed_ms = start.elapsed().as_millis();
+    let status = response.status();
+
+    if sampled {
+        info!(
+            target: "request_trace",
+            "请求追踪: {} {} 状态码: {}, 耗时: {}ms, 强制采样: {}",
+            method, route, status.as_u16(), elapsed_ms, forced
+        );
+    } else if config.always_sample_errors && (status.is_client_error() || status.is_server_error()) {
+        warn!(
+            target: "request_trace",
+            "请求追踪(失败请求): {} {} 状态码: {}, 耗时: {}ms",
+            method, route, status.as_u16(), elapsed_ms
+        );
+    }
+
+    response
+}
+
+// 获取路由的采样率，未单独配置时使用默认采样率
+fn sample_rate(config: &TracingConfig, route: &str) -> f64 {
+    config
+        .route_sample_rates
+        .get(route)
+        .copied()
+        .unwrap_or(config.default_sample_rate)
+}
+
+// 请求携带的客户端密钥是否在强制采样列表中
+fn is_force_sampled(config: &TracingConfig, request: &Request) -> bool {
+    if config.force_sample_keys.is_empty() {
+        return false;
+    }
+
+    client_key(request.headers())
+        .is_some_and(|key| config.force_sample_keys.contains(&key))
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// 管理API支持的作用域
+pub const ADMIN_SCOPES: &[&str] = &[
+    "providers:read",
+    "providers:write",
+    "pricing:read",
+    "pricing:write",
+    "billing:read",
+    "billing:write",
+    "tokens:write",
+    "keys:read",
+    "keys:write",
+    "system:read",
+    "system:write",
+    "users:read",
+    "users:write",
+];
+
+/// 管理API作用域令牌（不包含令牌明文）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct AdminToken {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 令牌用途说明
+    pub name: String,
+
+    /// 逗号分隔的作用域
+    pub scopes: String,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 吊销时间（非空表示已失效）
+    pub revoked_at: Option<DateTime<Utc>>,
+
+    /// 所属用户ID（用户登录时签发），为空表示直接创建的令牌
+    pub user_id: Option<String>,
+
+    /// 过期时间（为空表示永不过期）
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+const COLUMNS: &str = "id, name, scopes, created_at, revoked_at, user_id, expires_at";
+
+impl AdminToken {
+    /// 创建新令牌，返回令牌记录和仅此一次可见的令牌明文
+    pub async fn create(
+        db: &sqlx::SqlitePool,
+        name: &str,
+        scopes: &[String],
+        user_id: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(Self, String), sqlx::Error> {
+        let mut bytes = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let plaintext = format!("amk_{}", to_hex(&bytes));
+
+        let token = Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            scopes: scopes.join(","),
+            created_at: Utc::now(),
+            revoked_at: None,
+            user_id: user_id.map(str::to_string),
+            expires_at,
+        };
+
+        sqlx::query(
+            "INSERT INTO admin_tokens (id, name, token_hash, scopes, created_at, user_id, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&token.id)
+        .bind(&token.name)
+        .bind(hash_token(&plaintext))
+        .bind(&token.scopes)
+        .bind(token.created_at)
+        .bind(&token.user_id)
+        .bind(token.expires_at)
+        .execute(db)
+        .await?;
+
+        Ok((token, plaintext))
+    }
+
+    /// 根据令牌明文查找未吊销且未过期的令牌（属于用户的令牌要求用户仍然存在且已启用）
+    pub async fn find_active(db: &sqlx::SqlitePool, plaintext: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!(
+            r#"
+            SELECT {} FROM admin_tokens
+            WHERE token_hash = ? AND revoked_at IS NULL
+              AND (user_id IS NULL OR user_id IN (SELECT id FROM users WHERE enabled = 1))
+            "#,
+            COLUMNS
+        ))
+        .bind(hash_token(plaintext))
+        .fetch_optional(db)
+        .await
+        .map(|token| token.filter(|token| !token.is_expired()))
+    }
+
+    /// 列出所有令牌
+    pub async fn list(db: &sqlx::SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM admin_tokens ORDER BY created_at DESC", COLUMNS))
+            .fetch_all(db)
+            .await
+    }
+
+    /// 吊销令牌，返回是否找到未吊销的令牌
+    pub async fn revoke(db: &sqlx::SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE admin_tokens SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 吊销用户的所有令牌，返回吊销的数量
+    pub async fn revoke_for_user(db: &sqlx::SqlitePool, user_id: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("UPDATE admin_tokens SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL")
+            .bind(Utc::now())
+            .bind(user_id)
+            .execute(db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 令牌是否拥有指定作用域（支持 `*` 和 `providers:*` 形式的通配）
+    pub fn allows(&self, required: &str) -> bool {
+        scopes_allow(self.scopes.split(','), required)
+    }
+
+    /// 令牌是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    /// 令牌是否只拥有只读作用域
+    pub fn is_read_only(&self) -> bool {
+        self.scopes.split(',').map(str::trim).all(|scope| scope.ends_with(":read"))
+    }
+}
+
+/// 作用域列表是否包含所需作用域
+pub fn scopes_allow<'a>(granted: impl IntoIterator<Item = &'a str>, required: &str) -> bool {
+    let resource = required.split(':').next().unwrap_or(required);
+    granted.into_iter().map(str::trim).any(|scope| {
+        scope == "*"
+            || scope == required
+            || scope.strip_suffix(":*").is_some_and(|r| r == resource)
+    })
+}
+
+/// 作用域是否有效（已知作用域或通配）
+pub fn is_valid_scope(scope: &str) -> bool {
+    scope == "*"
+        || ADMIN_SCOPES.contains(&scope)
+        || scope
+            .strip_suffix(":*")
+            .is_some_and(|resource| ADMIN_SCOPES.iter().any(|s| s.starts_with(&format!("{}:", resource))))
+}
+
+pub(crate) fn hash_token(plaintext: &str) -> String {
+    to_hex(&Sha256::digest(plaintext.as_bytes()))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
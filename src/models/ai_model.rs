@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqliteRow, Row};
 use std::collections::HashMap;
 
 /// AI模型的类型
@@ -9,9 +10,41 @@ pub enum ModelType {
     Embedding,
     ImageGeneration,
     AudioTranscription,
+    Moderation,
     Other(String),
 }
 
+impl ModelType {
+    /// 类型名称，与 api_providers.model_type 中使用的名称一致
+    pub fn as_str(&self) -> &str {
+        match self {
+            ModelType::ChatCompletion => "ChatCompletion",
+            ModelType::TextCompletion => "TextCompletion",
+            ModelType::Embedding => "Embedding",
+            ModelType::ImageGeneration => "ImageGeneration",
+            ModelType::AudioTranscription => "AudioTranscription",
+            ModelType::Moderation => "Moderation",
+            ModelType::Other(name) => name,
+        }
+    }
+
+    /// 从类型名称解析，未知的名称保存为 Other
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "ChatCompletion" => ModelType::ChatCompletion,
+            "TextCompletion" => ModelType::TextCompletion,
+            "Embedding" => ModelType::Embedding,
+            "ImageGeneration" => ModelType::ImageGeneration,
+            "AudioTranscription" => ModelType::AudioTranscription,
+            "Moderation" => ModelType::Moderation,
+            other => ModelType::Other(other.to_string()),
+        }
+    }
+}
+
+const COLUMNS: &str = "id, name, provider_id, model_type, version, context_window, \
+    input_price_per_million_tokens, output_price_per_million_tokens, capabilities, is_enabled, created_at, updated_at";
+
 /// AI模型信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiModel {
@@ -43,6 +76,7 @@ pub struct AiModel {
 
 impl AiModel {
     /// 创建新的AI模型
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: String,
         name: String,
@@ -93,4 +127,104 @@ impl AiModel {
         self.output_price_per_million_tokens = output_price_per_million_tokens;
         self.updated_at = chrono::Utc::now();
     }
-} 
\ No newline at end of file
+
+    /// 写入模型目录：同一提供商的同名模型已存在时更新，并重新启用；
+    /// 本次未提供的上下文窗口和价格保留原值，模型类型和创建时间不变
+    pub async fn upsert(&self, db: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO ai_models ({})
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(provider_id, name) DO UPDATE SET
+                context_window = COALESCE(excluded.context_window, ai_models.context_window),
+                input_price_per_million_tokens = COALESCE(excluded.input_price_per_million_tokens, ai_models.input_price_per_million_tokens),
+                output_price_per_million_tokens = COALESCE(excluded.output_price_per_million_tokens, ai_models.output_price_per_million_tokens),
+                capabilities = excluded.capabilities,
+                is_enabled = excluded.is_enabled,
+                updated_at = excluded.updated_at
+            "#,
+            COLUMNS
+        ))
+        .bind(&self.id)
+        .bind(&self.name)
+        .bind(&self.provider_id)
+        .bind(self.model_type.as_str())
+        .bind(&self.version)
+        .bind(self.context_window.map(i64::from))
+        .bind(self.input_price_per_million_tokens)
+        .bind(self.output_price_per_million_tokens)
+        .bind(serde_json::to_string(&self.capabilities).unwrap_or_else(|_| "{}".to_string()))
+        .bind(self.is_enabled)
+        .bind(self.created_at)
+        .bind(self.updated_at)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// 列出提供商的所有模型
+    pub async fn list_for_provider(db: &sqlx::SqlitePool, provider_id: &str) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query(&format!("SELECT {} FROM ai_models WHERE provider_id = ? ORDER BY name", COLUMNS))
+            .bind(provider_id)
+            .fetch_all(db)
+            .await?;
+        Ok(rows.iter().map(Self::from_row).collect())
+    }
+
+    /// 列出模型名称对应的已启用模型，只包含 Active 提供商的模型；
+    /// 指定组织时包含共享池和该组织的提供商，否则只包含共享池的提供商
+    pub async fn list_enabled_by_name(
+        db: &sqlx::SqlitePool,
+        name: &str,
+        organization: Option<&str>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let columns = COLUMNS.split(", ").map(|column| format!("m.{}", column.trim())).collect::<Vec<_>>().join(", ");
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM ai_models m
+            JOIN api_providers p ON p.id = m.provider_id
+            WHERE m.name = ? AND m.is_enabled = 1 AND p.status = 'Active'
+              AND (p.organization IS NULL OR p.organization = ?)
+            ORDER BY m.updated_at DESC
+            "#,
+            columns
+        ))
+        .bind(name)
+        .bind(organization)
+        .fetch_all(db)
+        .await?;
+        Ok(rows.iter().map(Self::from_row).collect())
+    }
+
+    /// 启用或停用模型
+    pub async fn set_enabled(db: &sqlx::SqlitePool, id: &str, enabled: bool) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE ai_models SET is_enabled = ?, updated_at = ? WHERE id = ?")
+            .bind(enabled)
+            .bind(chrono::Utc::now())
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    // 从查询结果构建模型，能力字段无法解析时视为空
+    fn from_row(row: &SqliteRow) -> Self {
+        let model_type: String = row.get("model_type");
+        let context_window: Option<i64> = row.get("context_window");
+        let capabilities: String = row.get("capabilities");
+        Self {
+            id: row.get("id"),
+            name: row.get("name"),
+            provider_id: row.get("provider_id"),
+            model_type: ModelType::parse(&model_type),
+            version: row.get("version"),
+            context_window: context_window.and_then(|window| u32::try_from(window).ok()),
+            input_price_per_million_tokens: row.get("input_price_per_million_tokens"),
+            output_price_per_million_tokens: row.get("output_price_per_million_tokens"),
+            capabilities: serde_json::from_str(&capabilities).unwrap_or_default(),
+            is_enabled: row.get("is_enabled"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
@@ -8,6 +8,14 @@ pub enum ProviderType {
     Anthropic,
     DeepSeek,
     MistralAI,
+    /// Azure OpenAI（按部署路由，使用 api-key 请求头）
+    AzureOpenAI,
+    /// AWS Bedrock（SigV4签名，请求和响应转换为OpenAI格式）
+    Bedrock,
+    /// 自托管的 OpenAI 兼容服务（Ollama、LM Studio 等，无计费接口，可不配置密钥）
+    SelfHosted,
+    /// OpenRouter（模型名称带厂商前缀，通过 /auth/key 查询剩余额度）
+    OpenRouter,
     Custom(String),
 }
 
@@ -18,6 +26,10 @@ pub enum ProviderStatus {
     Inactive,
     Limited,
     Maintenance,
+    /// 鉴权失败被隔离，等待重新验证
+    Quarantined,
+    /// 已异步导入，等待后台验证
+    Verifying,
 }
 
 /// API提供商模型
@@ -46,6 +58,10 @@ pub struct ApiProvider {
     pub last_health_check: Option<chrono::DateTime<chrono::Utc>>,
     /// 费率限制（请求/分钟）
     pub rate_limit: Option<u32>,
+    /// 最大并发请求数
+    pub max_connections: u32,
+    /// 没有空闲连接许可时排队等待的最长时间(毫秒)
+    pub acquire_timeout_ms: u64,
     /// 当前余额
     pub balance: f64,
     /// 最后一次余额检查时间
@@ -54,10 +70,69 @@ pub struct ApiProvider {
     pub min_balance_threshold: f64,
     /// 是否支持余额检查
     pub support_balance_check: bool,
+    /// 余额查询URL（可选，设置后直接使用，不再根据base_url推导）
+    pub balance_check_url: Option<String>,
+    /// 流式输出节流速率（tokens/秒，可选，为空表示不节流）
+    pub stream_pacing_tps: Option<f64>,
+    /// 是否接受gzip压缩的请求体
+    pub supports_gzip_request: bool,
+    /// 是否支持结构化输出（response_format）
+    pub supports_structured_output: bool,
+    /// Azure OpenAI 部署名称（Bedrock 为模型ID）
+    pub deployment: Option<String>,
+    /// Azure OpenAI API版本
+    pub api_version: Option<String>,
+    /// AWS Bedrock 区域
+    pub aws_region: Option<String>,
+    /// 购买的额度
+    pub purchased_quota: Option<f64>,
+    /// 合同到期时间
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 供应商账号邮箱
+    pub vendor_account_email: Option<String>,
+    /// 上游账户指纹（根据余额接口返回的用户ID计算，相同表示共享同一额度）
+    pub account_fingerprint: Option<String>,
+    /// 绑定的组织（为空表示属于共享池）
+    pub organization: Option<String>,
+    /// 最近一次失败的时间
+    pub last_error_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 最近一次失败的类别
+    pub last_error_category: Option<String>,
+    /// 最近一次失败的错误信息（截断）
+    pub last_error_message: Option<String>,
+    /// 连续鉴权失败次数
+    pub consecutive_auth_failures: u32,
+    /// 进入隔离状态的时间
+    pub quarantined_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 同一提供商的最大尝试次数（含首次）
+    pub retry_attempts: u32,
+    /// 重试间隔(毫秒)
+    pub retry_delay_ms: u64,
+    /// 需要重试的上游状态码（逗号分隔），为空表示所有失败状态码都重试
+    pub retry_on_statuses: Option<String>,
+    /// 连接超时(毫秒)，为空时使用 UPSTREAM_CONNECT_TIMEOUT_MS
+    pub connect_timeout_ms: Option<u64>,
+    /// 请求超时(毫秒)，为空时使用 UPSTREAM_REQUEST_TIMEOUT_MS
+    pub request_timeout_ms: Option<u64>,
+    /// 优先级，数值越大越优先
+    pub priority: i32,
+    /// 每个上游域名最多保留的空闲连接数，为空时不限制
+    pub pool_max_idle_per_host: Option<u64>,
+    /// TCP keepalive 和 HTTP/2 PING 的间隔(秒)，为空时不发送
+    pub keep_alive_secs: Option<u64>,
+    /// HTTP 协议版本：auto、http1 或 http2，为空时按 auto 处理
+    pub http_version: Option<String>,
+    /// 附加请求头（JSON对象），随每个上游请求发送
+    pub extra_headers: Option<String>,
+    /// 额外信任的CA证书文件路径（PEM）
+    pub tls_ca_path: Option<String>,
+    /// 跳过TLS证书校验
+    pub tls_insecure_skip_verify: bool,
 }
 
 impl ApiProvider {
     /// 创建新的API提供商
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: String,
         name: String,
@@ -77,6 +152,10 @@ impl ApiProvider {
                 ProviderType::Anthropic => "Anthropic".to_string(),
                 ProviderType::DeepSeek => "DeepSeek".to_string(),
                 ProviderType::MistralAI => "MistralAI".to_string(),
+                ProviderType::AzureOpenAI => "Azure".to_string(),
+                ProviderType::Bedrock => "Bedrock".to_string(),
+                ProviderType::SelfHosted => "SelfHosted".to_string(),
+                ProviderType::OpenRouter => "OpenRouter".to_string(),
                 ProviderType::Custom(ref s) => s.clone(),
             }
         });
@@ -94,10 +173,41 @@ impl ApiProvider {
             updated_at: now,
             last_health_check: None,
             rate_limit,
+            max_connections: 10,
+            acquire_timeout_ms: 3000,
             balance: 0.0,
             last_balance_check: None,
             min_balance_threshold: 3.0,
             support_balance_check: false,
+            balance_check_url: None,
+            stream_pacing_tps: None,
+            supports_gzip_request: false,
+            supports_structured_output: true,
+            deployment: None,
+            api_version: None,
+            aws_region: None,
+            purchased_quota: None,
+            expires_at: None,
+            vendor_account_email: None,
+            account_fingerprint: None,
+            organization: None,
+            last_error_at: None,
+            last_error_category: None,
+            last_error_message: None,
+            consecutive_auth_failures: 0,
+            quarantined_at: None,
+            retry_attempts: 3,
+            retry_delay_ms: 1000,
+            retry_on_statuses: None,
+            connect_timeout_ms: None,
+            request_timeout_ms: None,
+            priority: 0,
+            pool_max_idle_per_host: None,
+            keep_alive_secs: None,
+            http_version: None,
+            extra_headers: None,
+            tls_ca_path: None,
+            tls_insecure_skip_verify: false,
         }
     }
 
@@ -108,6 +218,10 @@ impl ApiProvider {
             ProviderType::Anthropic => "Anthropic".to_string(),
             ProviderType::DeepSeek => "DeepSeek".to_string(),
             ProviderType::MistralAI => "MistralAI".to_string(),
+            ProviderType::AzureOpenAI => "Azure".to_string(),
+            ProviderType::Bedrock => "Bedrock".to_string(),
+            ProviderType::SelfHosted => "SelfHosted".to_string(),
+            ProviderType::OpenRouter => "OpenRouter".to_string(),
             ProviderType::Custom(ref s) => s.clone(),
         }
     }
@@ -128,6 +242,11 @@ impl ApiProvider {
         self.status == ProviderStatus::Active
     }
 
+    /// 检查是否处于隔离状态
+    pub fn is_quarantined(&self) -> bool {
+        self.status == ProviderStatus::Quarantined
+    }
+
     /// 更新余额
     pub fn update_balance(&mut self, balance: f64) {
         self.balance = balance;
@@ -1,10 +1,26 @@
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, TryStreamExt};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+// 多行INSERT每条语句的最大行数（16列，远低于SQLite的参数数量上限）
+const ROWS_PER_STATEMENT: usize = 64;
+
+/// 汇总和报表中代替提供商API密钥的自带上游密钥（BYOK）标记：
+/// BYOK请求记录的是池中模板提供商的密钥，但花费属于客户自己的密钥，不计入池中的密钥
+pub const BYOK_PROVIDER_KEY: &str = "byok";
+
+/// 汇总时归属的提供商：BYOK请求归到 BYOK_PROVIDER_KEY
+pub(crate) fn attributed_provider_key_sql() -> String {
+    format!("CASE WHEN own_key_id IS NULL THEN provider_api_key ELSE '{}' END", BYOK_PROVIDER_KEY)
+}
+
 /// API调用状态
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ApiCallStatus {
+    #[default]
     Success,          // 成功
     Error,            // 错误
     RateLimited,      // 速率限制
@@ -12,14 +28,8 @@ pub enum ApiCallStatus {
     InvalidRequest,   // 无效请求
 }
 
-impl Default for ApiCallStatus {
-    fn default() -> Self {
-        Self::Success
-    }
-}
-
 /// API使用量记录
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ApiUsage {
     /// 唯一标识符
     pub id: String,
@@ -50,6 +60,24 @@ pub struct ApiUsage {
     
     /// 请求ID
     pub request_id: Option<String>,
+
+    /// 引用来源数量（搜索增强模型）
+    pub num_sources: i32,
+
+    /// 音频时长（秒，音频转写请求）
+    pub audio_seconds: f64,
+
+    /// 自带上游密钥（BYOK）请求的密钥标识，使用池中密钥时为空
+    pub own_key_id: Option<String>,
+
+    /// 按定价计算的请求成本，未配置定价时为空
+    pub cost: Option<f64>,
+
+    /// 通过网关密钥调用时的密钥ID
+    pub gateway_key_id: Option<String>,
+
+    /// 归属的组织（组织专属提供商服务的请求），共享池的请求为空
+    pub organization: Option<String>,
 }
 
 impl ApiUsage {
@@ -75,9 +103,204 @@ impl ApiUsage {
             status: format!("{:?}", status),
             client_ip,
             request_id,
+            num_sources: 0,
+            audio_seconds: 0.0,
+            own_key_id: None,
+            cost: None,
+            gateway_key_id: None,
+            organization: None,
         }
     }
     
+    /// 写入数据库
+    pub async fn insert(&self, db: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO api_usage (
+                id, provider_api_key, request_time, model,
+                prompt_tokens, completion_tokens, total_tokens,
+                status, client_ip, request_id, num_sources, audio_seconds, own_key_id, gateway_key_id, organization
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&self.id)
+        .bind(&self.provider_api_key)
+        .bind(self.request_time)
+        .bind(&self.model)
+        .bind(self.prompt_tokens)
+        .bind(self.completion_tokens)
+        .bind(self.total_tokens)
+        .bind(&self.status)
+        .bind(&self.client_ip)
+        .bind(&self.request_id)
+        .bind(self.num_sources)
+        .bind(self.audio_seconds)
+        .bind(&self.own_key_id)
+        .bind(&self.gateway_key_id)
+        .bind(&self.organization)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+    
+    /// 在一个事务中批量写入，按固定行数拆分为多行INSERT，相同行数的语句复用预编译语句
+    pub async fn insert_batch(db: &sqlx::SqlitePool, usages: &[ApiUsage]) -> Result<(), sqlx::Error> {
+        let mut tx = db.begin().await?;
+        for chunk in usages.chunks(ROWS_PER_STATEMENT) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO api_usage (\
+                    id, provider_api_key, request_time, model, \
+                    prompt_tokens, completion_tokens, total_tokens, \
+                    status, client_ip, request_id, num_sources, audio_seconds, own_key_id, cost, gateway_key_id, organization\
+                ) "
+            );
+            query.push_values(chunk, |mut row, usage| {
+                row.push_bind(&usage.id)
+                    .push_bind(&usage.provider_api_key)
+                    .push_bind(usage.request_time)
+                    .push_bind(&usage.model)
+                    .push_bind(usage.prompt_tokens)
+                    .push_bind(usage.completion_tokens)
+                    .push_bind(usage.total_tokens)
+                    .push_bind(&usage.status)
+                    .push_bind(&usage.client_ip)
+                    .push_bind(&usage.request_id)
+                    .push_bind(usage.num_sources)
+                    .push_bind(usage.audio_seconds)
+                    .push_bind(&usage.own_key_id)
+                    .push_bind(usage.cost)
+                    .push_bind(&usage.gateway_key_id)
+                    .push_bind(&usage.organization);
+            });
+            query.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await
+    }
+
+    /// 按条件分页查询使用记录（按请求时间倒序），同时返回符合条件的总数
+    pub async fn search(
+        db: &sqlx::SqlitePool,
+        filter: &UsageFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<ApiUsage>, i64), sqlx::Error> {
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM api_usage");
+        filter.push_conditions(&mut count);
+        let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT id, provider_api_key, request_time, model, \
+                prompt_tokens, completion_tokens, total_tokens, \
+                status, client_ip, request_id, num_sources, audio_seconds, own_key_id, cost, gateway_key_id, organization \
+             FROM api_usage"
+        );
+        filter.push_conditions(&mut query);
+        query
+            .push(" ORDER BY request_time DESC, id LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+        let usages = query.build_query_as::<ApiUsage>().fetch_all(db).await?;
+
+        Ok((usages, total))
+    }
+
+    /// 按请求时间升序逐条读取符合条件的使用记录，用于导出大量记录而不一次性载入内存
+    pub fn stream(
+        db: sqlx::SqlitePool,
+        filter: UsageFilter,
+    ) -> impl Stream<Item = Result<ApiUsage, sqlx::Error>> + Send + 'static {
+        async_stream::try_stream! {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "SELECT id, provider_api_key, request_time, model, \
+                    prompt_tokens, completion_tokens, total_tokens, \
+                    status, client_ip, request_id, num_sources, audio_seconds, own_key_id, cost, gateway_key_id, organization \
+                 FROM api_usage"
+            );
+            filter.push_conditions(&mut query);
+            query.push(" ORDER BY request_time, id");
+            let mut rows = query.build_query_as::<ApiUsage>().fetch(&db);
+            while let Some(usage) = rows.try_next().await? {
+                yield usage;
+            }
+        }
+    }
+
+    /// 删除请求时间早于 before 的使用记录，每次最多删除 limit 条，返回删除的条数
+    pub async fn delete_before(db: &sqlx::SqlitePool, before: DateTime<Utc>, limit: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM api_usage WHERE rowid IN (SELECT rowid FROM api_usage WHERE request_time < ? LIMIT ?)"
+        )
+        .bind(before)
+        .bind(limit)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 写入请求成本，返回记录关联的网关密钥ID；记录不存在时返回空
+    pub async fn set_cost(db: &sqlx::SqlitePool, id: &str, cost: f64) -> Result<Option<Option<String>>, sqlx::Error> {
+        sqlx::query_scalar::<_, Option<String>>("UPDATE api_usage SET cost = ? WHERE id = ? RETURNING gateway_key_id")
+            .bind(cost)
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// 按天（UTC）统计网关密钥在 [from, to) 内的用量，按日期升序
+    pub async fn daily_for_gateway_key(
+        db: &sqlx::SqlitePool,
+        key_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<KeyUsageDay>, sqlx::Error> {
+        sqlx::query_as::<_, KeyUsageDay>(
+            r#"
+            SELECT substr(request_time, 1, 10) AS date,
+                   COUNT(*) AS requests,
+                   SUM(CASE WHEN status IN ('Success', 'PartialSuccess') THEN 0 ELSE 1 END) AS errors,
+                   AVG(CASE WHEN status IN ('Success', 'PartialSuccess') THEN 0.0 ELSE 1.0 END) AS error_rate,
+                   COALESCE(SUM(prompt_tokens), 0) AS prompt_tokens,
+                   COALESCE(SUM(completion_tokens), 0) AS completion_tokens,
+                   COALESCE(SUM(total_tokens), 0) AS total_tokens,
+                   COALESCE(SUM(cost), 0.0) AS cost
+            FROM api_usage
+            WHERE gateway_key_id = ? AND request_time >= ? AND request_time < ?
+            GROUP BY date
+            ORDER BY date
+            "#
+        )
+        .bind(key_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(db)
+        .await
+    }
+
+    /// 按日期（UTC）、提供商和模型汇总符合条件的用量和已记录的成本，
+    /// 同时统计尚未记录成本的请求和token，供查询时按定价补算
+    pub async fn cost_breakdown(
+        db: &sqlx::SqlitePool,
+        filter: &UsageFilter,
+    ) -> Result<Vec<UsageCostRow>, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT substr(request_time, 1, 10) AS date, provider_api_key, model, \
+                own_key_id IS NOT NULL AS byok, \
+                COUNT(*) AS requests, \
+                COALESCE(SUM(prompt_tokens), 0) AS prompt_tokens, \
+                COALESCE(SUM(completion_tokens), 0) AS completion_tokens, \
+                COALESCE(SUM(cost), 0.0) AS recorded_cost, \
+                SUM(CASE WHEN cost IS NULL THEN 1 ELSE 0 END) AS unpriced_requests, \
+                COALESCE(SUM(CASE WHEN cost IS NULL THEN prompt_tokens END), 0) AS unpriced_prompt_tokens, \
+                COALESCE(SUM(CASE WHEN cost IS NULL THEN completion_tokens END), 0) AS unpriced_completion_tokens \
+             FROM api_usage"
+        );
+        filter.push_conditions(&mut query);
+        query.push(" GROUP BY date, provider_api_key, model, byok ORDER BY date, provider_api_key, model");
+        query.build_query_as::<UsageCostRow>().fetch_all(db).await
+    }
+
     /// 计算估计成本（如果知道token价格）
     pub fn estimate_cost(&self, prompt_token_price: f64, completion_token_price: f64) -> f64 {
         (self.prompt_tokens as f64 * prompt_token_price) + 
@@ -86,7 +309,7 @@ impl ApiUsage {
 }
 
 /// API使用量统计摘要
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ApiUsageSummary {
     /// 总请求次数
     pub total_requests: i64,
@@ -114,7 +337,7 @@ pub struct ApiUsageSummary {
 }
 
 /// 按提供商的使用统计
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ProviderStats {
     /// 提供商API密钥
     pub provider_api_key: String,
@@ -127,7 +350,7 @@ pub struct ProviderStats {
 }
 
 /// 按模型的使用统计
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ModelStats {
     /// 模型名称
     pub model: String,
@@ -143,4 +366,228 @@ pub struct ModelStats {
     
     /// 总token
     pub total_tokens: i64,
-} 
\ No newline at end of file
+}
+
+impl ApiUsageSummary {
+    /// 汇总符合条件的使用记录，可选按提供商、按模型分组（按总token倒序）
+    pub async fn summarize(
+        db: &sqlx::SqlitePool,
+        filter: &UsageFilter,
+        by_provider: bool,
+        by_model: bool,
+    ) -> Result<Self, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT COUNT(*), \
+                COALESCE(SUM(prompt_tokens), 0), \
+                COALESCE(SUM(completion_tokens), 0), \
+                COALESCE(SUM(total_tokens), 0), \
+                COALESCE(SUM(CASE WHEN status IN ('Success', 'PartialSuccess') THEN 1 ELSE 0 END), 0) \
+             FROM api_usage"
+        );
+        filter.push_conditions(&mut query);
+        let (total_requests, total_prompt_tokens, total_completion_tokens, total_tokens, successful_requests) = query
+            .build_query_as::<(i64, i64, i64, i64, i64)>()
+            .fetch_one(db)
+            .await?;
+
+        let provider_stats = if by_provider {
+            let mut query = QueryBuilder::<Sqlite>::new(format!(
+                "SELECT {} AS provider_api_key, COUNT(*) AS request_count, \
+                    COALESCE(SUM(total_tokens), 0) AS total_tokens \
+                 FROM api_usage",
+                attributed_provider_key_sql()
+            ));
+            filter.push_conditions(&mut query);
+            query.push(" GROUP BY 1 ORDER BY total_tokens DESC, provider_api_key");
+            Some(query.build_query_as::<ProviderStats>().fetch_all(db).await?)
+        } else {
+            None
+        };
+
+        let model_stats = if by_model {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "SELECT model, COUNT(*) AS request_count, \
+                    COALESCE(SUM(prompt_tokens), 0) AS total_prompt_tokens, \
+                    COALESCE(SUM(completion_tokens), 0) AS total_completion_tokens, \
+                    COALESCE(SUM(total_tokens), 0) AS total_tokens \
+                 FROM api_usage"
+            );
+            filter.push_conditions(&mut query);
+            query.push(" GROUP BY model ORDER BY total_tokens DESC, model");
+            Some(query.build_query_as::<ModelStats>().fetch_all(db).await?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            total_requests,
+            total_prompt_tokens,
+            total_completion_tokens,
+            total_tokens,
+            successful_requests,
+            failed_requests: total_requests - successful_requests,
+            provider_stats,
+            model_stats,
+        })
+    }
+}
+
+impl ApiUsageSummary {
+    /// 合并两个不重叠时间段的统计，分组统计按键相加后重新按总token倒序排列
+    pub fn merge(self, other: Self) -> Self {
+        let provider_stats = merge_stats(self.provider_stats, other.provider_stats, |stats| {
+            (stats.provider_api_key.clone(), stats.total_tokens)
+        }, |acc, stats| {
+            acc.request_count += stats.request_count;
+            acc.total_tokens += stats.total_tokens;
+        });
+        let model_stats = merge_stats(self.model_stats, other.model_stats, |stats| {
+            (stats.model.clone(), stats.total_tokens)
+        }, |acc, stats| {
+            acc.request_count += stats.request_count;
+            acc.total_prompt_tokens += stats.total_prompt_tokens;
+            acc.total_completion_tokens += stats.total_completion_tokens;
+            acc.total_tokens += stats.total_tokens;
+        });
+        Self {
+            total_requests: self.total_requests + other.total_requests,
+            total_prompt_tokens: self.total_prompt_tokens + other.total_prompt_tokens,
+            total_completion_tokens: self.total_completion_tokens + other.total_completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+            successful_requests: self.successful_requests + other.successful_requests,
+            failed_requests: self.failed_requests + other.failed_requests,
+            provider_stats,
+            model_stats,
+        }
+    }
+}
+
+// 按分组键合并两组统计，key 返回分组键和排序用的总token
+fn merge_stats<T>(
+    a: Option<Vec<T>>,
+    b: Option<Vec<T>>,
+    key: impl Fn(&T) -> (String, i64),
+    add: impl Fn(&mut T, &T),
+) -> Option<Vec<T>> {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (a, b) => return a.or(b),
+    };
+    let mut merged: Vec<T> = Vec::with_capacity(a.len() + b.len());
+    let mut index = std::collections::HashMap::new();
+    for stats in a.into_iter().chain(b) {
+        match index.get(&key(&stats).0) {
+            Some(&i) => add(&mut merged[i], &stats),
+            None => {
+                index.insert(key(&stats).0, merged.len());
+                merged.push(stats);
+            }
+        }
+    }
+    merged.sort_by(|x, y| {
+        let (x_name, x_tokens) = key(x);
+        let (y_name, y_tokens) = key(y);
+        y_tokens.cmp(&x_tokens).then(x_name.cmp(&y_name))
+    });
+    Some(merged)
+}
+
+/// 使用记录查询条件，未设置的条件不参与过滤
+#[derive(Debug, Clone, Default)]
+pub struct UsageFilter {
+    /// 起始时间（包含）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含）
+    pub to: Option<DateTime<Utc>>,
+    /// 模型名称
+    pub model: Option<String>,
+    /// 提供商API密钥
+    pub provider_api_key: Option<String>,
+    /// 调用状态
+    pub status: Option<String>,
+    /// 客户端IP
+    pub client_ip: Option<String>,
+    /// 归属的组织
+    pub organization: Option<String>,
+    /// 网关密钥ID
+    pub gateway_key_id: Option<String>,
+}
+
+impl UsageFilter {
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+        query.push(" WHERE 1 = 1");
+        if let Some(from) = self.from {
+            query.push(" AND request_time >= ").push_bind(from);
+        }
+        if let Some(to) = self.to {
+            query.push(" AND request_time < ").push_bind(to);
+        }
+        for (column, value) in [
+            ("model", &self.model),
+            ("provider_api_key", &self.provider_api_key),
+            ("status", &self.status),
+            ("client_ip", &self.client_ip),
+            ("organization", &self.organization),
+            ("gateway_key_id", &self.gateway_key_id),
+        ] {
+            if let Some(value) = value {
+                query.push(format!(" AND {} = ", column)).push_bind(value.clone());
+            }
+        }
+    }
+}
+
+/// 某一天（UTC）某个提供商和模型的用量与成本
+#[derive(Debug, Clone, FromRow)]
+pub struct UsageCostRow {
+    /// 日期（YYYY-MM-DD）
+    pub date: String,
+    /// 提供商API密钥（BYOK请求为池中模板提供商的密钥，用于补算成本时查询定价）
+    pub provider_api_key: String,
+    /// 模型名称
+    pub model: String,
+    /// 是否为自带上游密钥（BYOK）的请求
+    pub byok: bool,
+    /// 请求次数
+    pub requests: i64,
+    /// 输入token
+    pub prompt_tokens: i64,
+    /// 输出token
+    pub completion_tokens: i64,
+    /// 已记录的成本合计
+    pub recorded_cost: f64,
+    /// 未记录成本的请求数
+    pub unpriced_requests: i64,
+    /// 未记录成本的请求的输入token
+    pub unpriced_prompt_tokens: i64,
+    /// 未记录成本的请求的输出token
+    pub unpriced_completion_tokens: i64,
+}
+
+/// 网关密钥某一天（UTC）的用量
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct KeyUsageDay {
+    /// 日期（YYYY-MM-DD）
+    pub date: String,
+
+    /// 请求次数
+    pub requests: i64,
+
+    /// 失败请求数（状态不是 Success 或 PartialSuccess）
+    pub errors: i64,
+
+    /// 失败率（0-1）
+    pub error_rate: f64,
+
+    /// 输入token
+    pub prompt_tokens: i64,
+
+    /// 输出token
+    pub completion_tokens: i64,
+
+    /// 总token
+    pub total_tokens: i64,
+
+    /// 按定价估算的成本（未配置定价的请求不计入）
+    pub cost: f64,
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::model_pricing::ModelPricing;
+use crate::models::prepaid_key::PrepaidKey;
+
+/// 账本条目类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum LedgerEntryType {
+    /// 借记：请求成本
+    Debit,
+    /// 贷记：手动充值
+    Credit,
+    /// 调整：人工修正（可正可负）
+    Adjustment,
+}
+
+/// 计费账本条目（只追加，不可修改）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct LedgerEntry {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 计费账户（客户端虚拟密钥或组织标识）
+    pub account: String,
+
+    /// 条目类型（Debit/Credit/Adjustment）
+    pub entry_type: String,
+
+    /// 带符号的金额，借记为负
+    pub amount: f64,
+
+    /// 货币单位
+    pub currency: String,
+
+    /// 说明
+    pub description: Option<String>,
+
+    /// 借记关联的API使用记录ID
+    pub usage_id: Option<String>,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 账户余额
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LedgerBalance {
+    /// 计费账户
+    pub account: String,
+    /// 当前余额（所有条目金额之和）
+    pub balance: f64,
+    /// 累计借记金额
+    pub total_debits: f64,
+    /// 累计贷记金额（含调整）
+    pub total_credits: f64,
+    /// 条目数量
+    pub entry_count: i64,
+}
+
+impl LedgerEntry {
+    /// 创建新的账本条目
+    pub fn new(
+        account: &str,
+        entry_type: LedgerEntryType,
+        amount: f64,
+        currency: &str,
+        description: Option<String>,
+        usage_id: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            account: account.to_string(),
+            entry_type: format!("{:?}", entry_type),
+            amount,
+            currency: currency.to_string(),
+            description,
+            usage_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 写入数据库
+    pub async fn insert<'e>(&self, db: impl sqlx::SqliteExecutor<'e>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO billing_ledger (
+                id, account, entry_type, amount, currency,
+                description, usage_id, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&self.id)
+        .bind(&self.account)
+        .bind(&self.entry_type)
+        .bind(self.amount)
+        .bind(&self.currency)
+        .bind(&self.description)
+        .bind(&self.usage_id)
+        .bind(self.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 按模型定价计算请求成本并记录借记；账户是预付费网关密钥时在同一事务中从可用余额扣除
+    pub async fn record_usage_debit(
+        db: &sqlx::SqlitePool,
+        account: &str,
+        pricing: &ModelPricing,
+        model: &str,
+        (prompt_tokens, completion_tokens): (u32, u32),
+        usage_id: &str,
+    ) -> Result<Self, sqlx::Error> {
+        let cost = pricing.calculate_cost(prompt_tokens, completion_tokens);
+        let entry = Self::new(
+            account,
+            LedgerEntryType::Debit,
+            -cost,
+            &pricing.currency,
+            Some(format!("{} 请求: prompt={}, completion={}", model, prompt_tokens, completion_tokens)),
+            Some(usage_id.to_string()),
+        );
+        let mut tx = db.begin().await?;
+        entry.insert(&mut *tx).await?;
+        PrepaidKey::apply(&mut *tx, account, entry.amount).await?;
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    /// 查询账户的账本条目，按时间正序
+    pub async fn list(
+        db: &sqlx::SqlitePool,
+        account: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT * FROM billing_ledger
+            WHERE account = ?
+              AND (? IS NULL OR created_at >= ?)
+              AND (? IS NULL OR created_at < ?)
+            ORDER BY created_at ASC
+            "#
+        )
+        .bind(account)
+        .bind(from)
+        .bind(from)
+        .bind(to)
+        .bind(to)
+        .fetch_all(db)
+        .await
+    }
+
+    /// 查询账户余额
+    pub async fn balance(db: &sqlx::SqlitePool, account: &str) -> Result<LedgerBalance, sqlx::Error> {
+        let (balance, total_debits, total_credits, entry_count) = sqlx::query_as::<_, (f64, f64, f64, i64)>(
+            r#"
+            SELECT
+                COALESCE(SUM(amount), 0.0),
+                COALESCE(SUM(CASE WHEN amount < 0 THEN -amount ELSE 0.0 END), 0.0),
+                COALESCE(SUM(CASE WHEN amount > 0 THEN amount ELSE 0.0 END), 0.0),
+                COUNT(*)
+            FROM billing_ledger
+            WHERE account = ?
+            "#
+        )
+        .bind(account)
+        .fetch_one(db)
+        .await?;
+
+        Ok(LedgerBalance {
+            account: account.to_string(),
+            balance,
+            total_debits,
+            total_credits,
+            entry_count,
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// 提供商数据质量事件（上游返回格式异常的响应）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct DataQualityEvent {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 提供商API密钥
+    pub provider_api_key: String,
+
+    /// 事件类型（MalformedJson / NonConforming）
+    pub event_type: String,
+
+    /// 是否通过宽松解析恢复
+    pub recovered: bool,
+
+    /// 逗号分隔的修复项
+    pub repairs: Option<String>,
+
+    /// 严格解析的错误信息
+    pub detail: Option<String>,
+
+    /// 发生时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 单个提供商的数据质量统计
+#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ProviderDataQuality {
+    /// 提供商API密钥
+    pub provider_api_key: String,
+    /// 提供商名称（提供商已删除时为空）
+    pub name: Option<String>,
+    /// 宽松解析恢复的次数
+    pub recovered_count: i64,
+    /// 无法恢复的次数
+    pub failed_count: i64,
+    /// 最近一次事件时间
+    pub last_event_at: DateTime<Utc>,
+}
+
+impl DataQualityEvent {
+    /// 记录一次上游JSON格式异常
+    pub async fn record_malformed_json(
+        db: &sqlx::SqlitePool,
+        provider_api_key: &str,
+        recovered: bool,
+        repairs: &[&str],
+        detail: &str,
+    ) -> Result<(), sqlx::Error> {
+        Self::record(db, provider_api_key, "MalformedJson", recovered, repairs, detail).await
+    }
+
+    /// 记录一次不符合 OpenAI 格式的响应（严格兼容模式下修复或拒绝）
+    pub async fn record_nonconforming(
+        db: &sqlx::SqlitePool,
+        provider_api_key: &str,
+        recovered: bool,
+        repairs: &[&str],
+        detail: &str,
+    ) -> Result<(), sqlx::Error> {
+        Self::record(db, provider_api_key, "NonConforming", recovered, repairs, detail).await
+    }
+
+    async fn record(
+        db: &sqlx::SqlitePool,
+        provider_api_key: &str,
+        event_type: &str,
+        recovered: bool,
+        repairs: &[&str],
+        detail: &str,
+    ) -> Result<(), sqlx::Error> {
+        let event = Self {
+            id: Uuid::new_v4().to_string(),
+            provider_api_key: provider_api_key.to_string(),
+            event_type: event_type.to_string(),
+            recovered,
+            repairs: (!repairs.is_empty()).then(|| repairs.join(",")),
+            detail: Some(detail.to_string()),
+            created_at: Utc::now(),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO provider_data_quality_events (
+                id, provider_api_key, event_type, recovered, repairs, detail, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&event.id)
+        .bind(&event.provider_api_key)
+        .bind(&event.event_type)
+        .bind(event.recovered)
+        .bind(&event.repairs)
+        .bind(&event.detail)
+        .bind(event.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 按提供商汇总指定时间之后的数据质量事件，问题最多的排在前面
+    pub async fn summary(db: &sqlx::SqlitePool, since: DateTime<Utc>) -> Result<Vec<ProviderDataQuality>, sqlx::Error> {
+        sqlx::query_as::<_, ProviderDataQuality>(
+            r#"
+            SELECT
+                e.provider_api_key,
+                p.name,
+                SUM(CASE WHEN e.recovered THEN 1 ELSE 0 END) AS recovered_count,
+                SUM(CASE WHEN e.recovered THEN 0 ELSE 1 END) AS failed_count,
+                MAX(e.created_at) AS last_event_at
+            FROM provider_data_quality_events e
+            LEFT JOIN api_providers p ON p.api_key = e.provider_api_key
+            WHERE e.created_at >= ?
+            GROUP BY e.provider_api_key
+            ORDER BY COUNT(*) DESC
+            "#
+        )
+        .bind(since)
+        .fetch_all(db)
+        .await
+    }
+}
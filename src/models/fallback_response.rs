@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// 匹配所有模型的默认规则
+pub const DEFAULT_MODEL: &str = "*";
+
+/// 全部提供商失败时返回的兜底回复
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FallbackResponse {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 适用的模型（* 表示默认规则）
+    pub model: String,
+
+    /// 回复模板，支持 {model} 占位符
+    pub template: String,
+
+    /// 是否启用
+    pub enabled: bool,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+const COLUMNS: &str = "id, model, template, enabled, created_at, updated_at";
+
+impl FallbackResponse {
+    /// 创建或替换模型的兜底回复
+    pub async fn upsert(
+        db: &sqlx::SqlitePool,
+        model: &str,
+        template: &str,
+        enabled: bool,
+    ) -> Result<Self, sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query_as::<_, Self>(&format!(
+            r#"
+            INSERT INTO fallback_responses (id, model, template, enabled, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(model) DO UPDATE SET
+                template = excluded.template,
+                enabled = excluded.enabled,
+                updated_at = excluded.updated_at
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(model)
+        .bind(template)
+        .bind(enabled)
+        .bind(now)
+        .bind(now)
+        .fetch_one(db)
+        .await
+    }
+
+    /// 列出所有兜底回复
+    pub async fn list(db: &sqlx::SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM fallback_responses ORDER BY model", COLUMNS))
+            .fetch_all(db)
+            .await
+    }
+
+    /// 查找模型生效的兜底回复：优先使用该模型的规则，其次使用默认规则
+    pub async fn for_model(db: &sqlx::SqlitePool, model: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!(
+            r#"
+            SELECT {} FROM fallback_responses
+            WHERE enabled = 1 AND model IN (?, ?)
+            ORDER BY model = ?
+            LIMIT 1
+            "#,
+            COLUMNS
+        ))
+        .bind(model)
+        .bind(DEFAULT_MODEL)
+        .bind(DEFAULT_MODEL)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// 删除模型的兜底回复，返回是否找到
+    pub async fn delete(db: &sqlx::SqlitePool, model: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM fallback_responses WHERE model = ?")
+            .bind(model)
+            .execute(db)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 渲染回复内容
+    pub fn render(&self, model: &str) -> String {
+        self.template.replace("{model}", model)
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::admin_token::{hash_token, to_hex};
+
+/// 密钥明文前缀，用于和管理令牌等其他凭证区分
+pub const KEY_PREFIX: &str = "gwk_";
+// 记录的明文前缀长度（含 gwk_）
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// 推理接口的网关密钥（不包含密钥明文）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct GatewayKey {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 密钥用途说明
+    pub name: String,
+
+    /// 密钥明文前缀，便于识别
+    pub key_prefix: String,
+
+    /// 是否启用
+    pub enabled: bool,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+
+    /// 吊销时间（非空表示已失效）
+    pub revoked_at: Option<DateTime<Utc>>,
+
+    /// 每分钟请求数上限（为空时使用全局默认值，0表示不限制）
+    pub requests_per_minute: Option<i64>,
+
+    /// 每分钟token数上限（为空时使用全局默认值，0表示不限制）
+    pub tokens_per_minute: Option<i64>,
+
+    /// 月度预算（为空时使用全局默认值，0表示不限制）
+    pub monthly_budget: Option<f64>,
+
+    /// 是否在响应的 usage 中附带成本、上游耗时和当月累计花费
+    pub usage_echo: bool,
+
+    /// 过期时间（为空表示永不过期）
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// 轮换后替代该密钥的新密钥ID
+    pub replaced_by: Option<String>,
+
+    /// 所属组织（设置后只使用绑定到该组织的提供商）
+    pub organization: Option<String>,
+
+    /// 是否开启严格 OpenAI 兼容模式（按 OpenAI 格式修复或拒绝不规范的聊天响应）
+    pub strict_openai: bool,
+
+    /// 默认负载均衡策略（为空时使用 LOAD_BALANCE_STRATEGY）
+    pub load_balance_strategy: Option<String>,
+
+    /// 流式输出节流速率（tokens/秒，为空时使用提供商的设置）
+    pub stream_pacing_tps: Option<f64>,
+}
+
+/// 网关密钥的限流设置
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyRateLimits {
+    /// 每分钟请求数上限
+    pub requests_per_minute: Option<i64>,
+    /// 每分钟token数上限
+    pub tokens_per_minute: Option<i64>,
+}
+
+const COLUMNS: &str =
+    "id, name, key_prefix, enabled, created_at, updated_at, revoked_at, requests_per_minute, tokens_per_minute, monthly_budget, usage_echo, expires_at, replaced_by, organization, strict_openai, load_balance_strategy, stream_pacing_tps";
+
+impl GatewayKey {
+    /// 创建新密钥，返回密钥记录和仅此一次可见的密钥明文
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create(
+        db: &sqlx::SqlitePool,
+        name: &str,
+        limits: KeyRateLimits,
+        monthly_budget: Option<f64>,
+        usage_echo: bool,
+        expires_at: Option<DateTime<Utc>>,
+        organization: Option<&str>,
+        strict_openai: bool,
+        load_balance_strategy: Option<&str>,
+        stream_pacing_tps: Option<f64>,
+    ) -> Result<(Self, String), sqlx::Error> {
+        let (key, plaintext) = Self::generate(name, limits, monthly_budget, usage_echo, expires_at, organization, strict_openai, load_balance_strategy, stream_pacing_tps);
+        key.insert(db, &plaintext).await?;
+        Ok((key, plaintext))
+    }
+
+    // 生成新密钥记录和密钥明文（尚未写入数据库）
+    #[allow(clippy::too_many_arguments)]
+    fn generate(
+        name: &str,
+        limits: KeyRateLimits,
+        monthly_budget: Option<f64>,
+        usage_echo: bool,
+        expires_at: Option<DateTime<Utc>>,
+        organization: Option<&str>,
+        strict_openai: bool,
+        load_balance_strategy: Option<&str>,
+        stream_pacing_tps: Option<f64>,
+    ) -> (Self, String) {
+        let mut bytes = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let plaintext = format!("{}{}", KEY_PREFIX, to_hex(&bytes));
+
+        let now = Utc::now();
+        let key = Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            key_prefix: plaintext[..DISPLAY_PREFIX_LEN].to_string(),
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+            revoked_at: None,
+            requests_per_minute: limits.requests_per_minute,
+            tokens_per_minute: limits.tokens_per_minute,
+            monthly_budget,
+            usage_echo,
+            expires_at,
+            replaced_by: None,
+            organization: organization.map(str::to_string),
+            strict_openai,
+            load_balance_strategy: load_balance_strategy.map(str::to_string),
+            stream_pacing_tps,
+        };
+        (key, plaintext)
+    }
+
+    async fn insert<'e, E>(&self, executor: E, plaintext: &str) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO gateway_keys (
+                id, name, key_hash, key_prefix, enabled, created_at, updated_at,
+                requests_per_minute, tokens_per_minute, monthly_budget, usage_echo, expires_at, organization,
+                strict_openai, load_balance_strategy, stream_pacing_tps
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&self.id)
+        .bind(&self.name)
+        .bind(hash_token(plaintext))
+        .bind(&self.key_prefix)
+        .bind(self.enabled)
+        .bind(self.created_at)
+        .bind(self.updated_at)
+        .bind(self.requests_per_minute)
+        .bind(self.tokens_per_minute)
+        .bind(self.monthly_budget)
+        .bind(self.usage_echo)
+        .bind(self.expires_at)
+        .bind(&self.organization)
+        .bind(self.strict_openai)
+        .bind(&self.load_balance_strategy)
+        .bind(self.stream_pacing_tps)
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 轮换密钥：签发沿用原密钥设置的新密钥，原密钥在 grace_until 之后过期（已有更早的过期时间时保持不变），
+    /// 新密钥承接原密钥当月的花费。返回新密钥记录、新密钥明文和更新后的原密钥；原密钥不存在、已吊销或已过期时返回空
+    pub async fn rotate(
+        db: &sqlx::SqlitePool,
+        id: &str,
+        grace_until: DateTime<Utc>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<(Self, String, Self)>, sqlx::Error> {
+        let mut tx = db.begin().await?;
+        let old = sqlx::query_as::<_, Self>(&format!(
+            "SELECT {} FROM gateway_keys WHERE id = ? AND revoked_at IS NULL",
+            COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let old = match old {
+            Some(old) if !old.is_expired() => old,
+            _ => return Ok(None),
+        };
+
+        let limits = KeyRateLimits {
+            requests_per_minute: old.requests_per_minute,
+            tokens_per_minute: old.tokens_per_minute,
+        };
+        let (mut key, plaintext) = Self::generate(&old.name, limits, old.monthly_budget, old.usage_echo, expires_at, old.organization.as_deref(), old.strict_openai, old.load_balance_strategy.as_deref(), old.stream_pacing_tps);
+        key.enabled = old.enabled;
+        key.insert(&mut *tx, &plaintext).await?;
+
+        let now = Utc::now();
+        sqlx::query(
+            r#"
+            INSERT INTO gateway_key_spend (key_id, period, spent, reset_at, updated_at)
+            SELECT ?, period, spent, reset_at, ? FROM gateway_key_spend WHERE key_id = ? AND period = ?
+            "#
+        )
+        .bind(&key.id)
+        .bind(now)
+        .bind(&old.id)
+        .bind(now.format("%Y-%m").to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        let old_expires_at = old.expires_at.map_or(grace_until, |at| at.min(grace_until));
+        let old = sqlx::query_as::<_, Self>(&format!(
+            "UPDATE gateway_keys SET expires_at = ?, replaced_by = ?, updated_at = ? WHERE id = ? RETURNING {}",
+            COLUMNS
+        ))
+        .bind(old_expires_at)
+        .bind(&key.id)
+        .bind(now)
+        .bind(&old.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some((key, plaintext, old)))
+    }
+
+    /// 密钥是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    /// 根据密钥明文查找启用且未吊销的密钥
+    pub async fn find_active(db: &sqlx::SqlitePool, plaintext: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!(
+            "SELECT {} FROM gateway_keys WHERE key_hash = ? AND enabled = 1 AND revoked_at IS NULL",
+            COLUMNS
+        ))
+        .bind(hash_token(plaintext))
+        .fetch_optional(db)
+        .await
+    }
+
+    /// 根据ID查找密钥
+    pub async fn find(db: &sqlx::SqlitePool, id: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM gateway_keys WHERE id = ?", COLUMNS))
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// 列出所有密钥
+    pub async fn list(db: &sqlx::SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM gateway_keys ORDER BY created_at DESC", COLUMNS))
+            .fetch_all(db)
+            .await
+    }
+
+    /// 更新未吊销密钥的名称、启用状态、限流设置、月度预算、用量回显、过期时间、所属组织、严格兼容模式、默认负载均衡策略和流式输出节流速率（为空的字段保持不变），返回更新后的记录
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update(
+        db: &sqlx::SqlitePool,
+        id: &str,
+        name: Option<&str>,
+        enabled: Option<bool>,
+        limits: KeyRateLimits,
+        monthly_budget: Option<f64>,
+        usage_echo: Option<bool>,
+        expires_at: Option<DateTime<Utc>>,
+        organization: Option<&str>,
+        strict_openai: Option<bool>,
+        load_balance_strategy: Option<&str>,
+        stream_pacing_tps: Option<f64>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!(
+            r#"
+            UPDATE gateway_keys
+            SET name = COALESCE(?, name),
+                enabled = COALESCE(?, enabled),
+                requests_per_minute = COALESCE(?, requests_per_minute),
+                tokens_per_minute = COALESCE(?, tokens_per_minute),
+                monthly_budget = COALESCE(?, monthly_budget),
+                usage_echo = COALESCE(?, usage_echo),
+                expires_at = COALESCE(?, expires_at),
+                organization = COALESCE(?, organization),
+                strict_openai = COALESCE(?, strict_openai),
+                load_balance_strategy = COALESCE(?, load_balance_strategy),
+                stream_pacing_tps = COALESCE(?, stream_pacing_tps),
+                updated_at = ?
+            WHERE id = ? AND revoked_at IS NULL
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(name)
+        .bind(enabled)
+        .bind(limits.requests_per_minute)
+        .bind(limits.tokens_per_minute)
+        .bind(monthly_budget)
+        .bind(usage_echo)
+        .bind(expires_at)
+        .bind(organization)
+        .bind(strict_openai)
+        .bind(load_balance_strategy)
+        .bind(stream_pacing_tps)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// 吊销密钥，返回是否找到未吊销的密钥
+    pub async fn revoke(db: &sqlx::SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query("UPDATE gateway_keys SET revoked_at = ?, updated_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(now)
+            .bind(now)
+            .bind(id)
+            .execute(db)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// 网关密钥某个月的累计花费
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct GatewayKeySpend {
+    /// 密钥ID
+    pub key_id: String,
+
+    /// 计费月份（UTC，YYYY-MM）
+    pub period: String,
+
+    /// 累计花费
+    pub spent: f64,
+
+    /// 最近一次手动重置时间
+    pub reset_at: Option<DateTime<Utc>>,
+
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 密钥生效的月度预算：未单独设置时使用默认值，0表示不限制
+pub fn effective_budget(monthly_budget: Option<f64>, default: f64) -> f64 {
+    monthly_budget.unwrap_or(default).max(0.0)
+}
+
+impl GatewayKeySpend {
+    /// 当前计费月份（UTC）
+    pub fn current_period() -> String {
+        Utc::now().format("%Y-%m").to_string()
+    }
+
+    /// 查询密钥在指定月份的累计花费，没有记录时为0
+    pub async fn spent(db: &sqlx::SqlitePool, key_id: &str, period: &str) -> Result<f64, sqlx::Error> {
+        let spent = sqlx::query_scalar::<_, f64>("SELECT spent FROM gateway_key_spend WHERE key_id = ? AND period = ?")
+            .bind(key_id)
+            .bind(period)
+            .fetch_optional(db)
+            .await?;
+
+        Ok(spent.unwrap_or(0.0))
+    }
+
+    /// 查询密钥在指定月份的花费记录
+    pub async fn find(db: &sqlx::SqlitePool, key_id: &str, period: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            "SELECT key_id, period, spent, reset_at, updated_at FROM gateway_key_spend WHERE key_id = ? AND period = ?"
+        )
+        .bind(key_id)
+        .bind(period)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// 把一次请求的成本计入密钥当月花费
+    pub async fn add(db: &sqlx::SqlitePool, key_id: &str, cost: f64) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO gateway_key_spend (key_id, period, spent, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (key_id, period) DO UPDATE
+            SET spent = spent + excluded.spent, updated_at = excluded.updated_at
+            "#
+        )
+        .bind(key_id)
+        .bind(Self::current_period())
+        .bind(cost)
+        .bind(Utc::now())
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 将密钥当月花费清零
+    pub async fn reset(db: &sqlx::SqlitePool, key_id: &str) -> Result<Self, sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query_as::<_, Self>(
+            r#"
+            INSERT INTO gateway_key_spend (key_id, period, spent, reset_at, updated_at)
+            VALUES (?, ?, 0, ?, ?)
+            ON CONFLICT (key_id, period) DO UPDATE
+            SET spent = 0, reset_at = excluded.reset_at, updated_at = excluded.updated_at
+            RETURNING key_id, period, spent, reset_at, updated_at
+            "#
+        )
+        .bind(key_id)
+        .bind(Self::current_period())
+        .bind(now)
+        .bind(now)
+        .fetch_one(db)
+        .await
+    }
+}
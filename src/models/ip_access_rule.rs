@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// IP访问规则的动作
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum IpAccessAction {
+    /// 允许：配置了允许规则后，只有命中允许规则的客户端可以访问
+    Allow,
+    /// 拒绝：命中拒绝规则的客户端总是被拒绝
+    Deny,
+}
+
+impl IpAccessAction {
+    /// 从存储的动作名解析
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "Allow" => Some(Self::Allow),
+            "Deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "Allow",
+            Self::Deny => "Deny",
+        }
+    }
+}
+
+/// 运行时添加的客户端IP访问规则
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct IpAccessRule {
+    /// 唯一标识符
+    pub id: String,
+
+    /// CIDR 网段（单个IP保存为 /32 或 /128）
+    pub cidr: String,
+
+    /// 动作（Allow 或 Deny）
+    pub action: String,
+
+    /// 规则说明
+    pub description: Option<String>,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+const COLUMNS: &str = "id, cidr, action, description, created_at";
+
+impl IpAccessRule {
+    /// 解析后的动作，未知动作视为拒绝
+    pub fn action(&self) -> IpAccessAction {
+        IpAccessAction::parse(&self.action).unwrap_or(IpAccessAction::Deny)
+    }
+
+    /// 添加规则，相同网段和动作的规则已存在时返回唯一约束错误
+    pub async fn create(
+        db: &sqlx::SqlitePool,
+        cidr: &str,
+        action: IpAccessAction,
+        description: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!(
+            r#"
+            INSERT INTO ip_access_rules (id, cidr, action, description, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(cidr)
+        .bind(action.as_str())
+        .bind(description)
+        .bind(Utc::now())
+        .fetch_one(db)
+        .await
+    }
+
+    /// 列出所有规则
+    pub async fn list(db: &sqlx::SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM ip_access_rules ORDER BY created_at", COLUMNS))
+            .fetch_all(db)
+            .await
+    }
+
+    /// 删除规则，返回是否存在
+    pub async fn delete(db: &sqlx::SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM ip_access_rules WHERE id = ?")
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
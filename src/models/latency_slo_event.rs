@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// 开始降载
+pub const SHED_ENABLED: &str = "ShedEnabled";
+/// 解除降载
+pub const SHED_DISABLED: &str = "ShedDisabled";
+
+/// 延迟SLO事件（开始或解除降载）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct LatencySloEvent {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 事件类型（ShedEnabled / ShedDisabled）
+    pub event_type: String,
+
+    /// 触发时窗口内的p99延迟(毫秒)，请求数不足时为空
+    pub p99_ms: Option<i64>,
+
+    /// p99延迟上限(毫秒)
+    pub threshold_ms: i64,
+
+    /// 窗口内的请求数
+    pub sample_count: i64,
+
+    /// 发生时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl LatencySloEvent {
+    pub fn new(event_type: &str, p99_ms: Option<u64>, threshold_ms: u64, sample_count: usize) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            event_type: event_type.to_string(),
+            p99_ms: p99_ms.map(|p99| p99 as i64),
+            threshold_ms: threshold_ms as i64,
+            sample_count: sample_count as i64,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 保存事件
+    pub async fn record(&self, db: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO latency_slo_events (
+                id, event_type, p99_ms, threshold_ms, sample_count, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&self.id)
+        .bind(&self.event_type)
+        .bind(self.p99_ms)
+        .bind(self.threshold_ms)
+        .bind(self.sample_count)
+        .bind(self.created_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 获取最近的事件，最新的排在前面
+    pub async fn recent(db: &sqlx::SqlitePool, limit: i64) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT id, event_type, p99_ms, threshold_ms, sample_count, created_at
+            FROM latency_slo_events
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#
+        )
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    }
+}
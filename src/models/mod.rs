@@ -4,9 +4,30 @@ pub mod api_provider;
 pub mod ai_model;
 pub mod api_usage;
 pub mod model_pricing;
+pub mod billing_ledger;
+pub mod prepaid_key;
+pub mod admin_token;
+pub mod gateway_key;
+pub mod gateway_key_spend;
+pub mod data_quality_event;
+pub mod latency_slo_event;
+pub mod user;
+pub mod fallback_response;
+pub mod ip_access_rule;
+pub mod usage_rollup;
 
 // 重新导出核心类型
 pub use api_provider::{ApiProvider, ProviderType, ProviderStatus};
 pub use ai_model::{AiModel, ModelType};
 pub use api_usage::{ApiUsage, ApiCallStatus, ApiUsageSummary, ProviderStats, ModelStats};
 pub use model_pricing::{ModelPricing, ModelPricingSummary};
+pub use billing_ledger::{LedgerEntry, LedgerEntryType, LedgerBalance};
+pub use prepaid_key::PrepaidKey;
+pub use admin_token::AdminToken;
+pub use gateway_key::GatewayKey;
+pub use gateway_key_spend::GatewayKeySpend;
+pub use data_quality_event::{DataQualityEvent, ProviderDataQuality};
+pub use latency_slo_event::LatencySloEvent;
+pub use user::{User, UserRole};
+pub use fallback_response::FallbackResponse;
+pub use ip_access_rule::{IpAccessAction, IpAccessRule};
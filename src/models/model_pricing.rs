@@ -85,6 +85,27 @@ impl ModelPricing {
         .await
     }
     
+    /// 根据提供商的API密钥获取其模型的当前价格
+    pub async fn get_price_for_provider_key(
+        db: &sqlx::SqlitePool,
+        provider_api_key: &str,
+        model: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(
+            r#"
+            SELECT mp.* FROM model_pricing mp
+            JOIN api_providers p ON p.name = mp.name
+            WHERE p.api_key = ? AND mp.model = ?
+            ORDER BY mp.effective_date DESC
+            LIMIT 1
+            "#
+        )
+        .bind(provider_api_key)
+        .bind(model)
+        .fetch_optional(db)
+        .await
+    }
+    
     /// 更新价格（创建新记录，保持价格历史）
     pub async fn update_price(
         db: &sqlx::SqlitePool,
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+use crate::models::billing_ledger::LedgerEntry;
+
+/// 预付费网关密钥：按可用余额限制请求，请求开始时原子地预扣额度，结束后按实际成本结算
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PrepaidKey {
+    /// 网关密钥ID
+    pub gateway_key_id: String,
+
+    /// 可用余额（充值减去已结算的成本和在途请求的预扣额度）
+    pub balance: f64,
+
+    /// 首次加载额度的时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl PrepaidKey {
+    /// 为网关密钥加载额度（首次加载时登记为预付费密钥），与账本贷记条目在同一事务中写入
+    pub async fn load(db: &sqlx::SqlitePool, gateway_key_id: &str, credit: &LedgerEntry) -> Result<Self, sqlx::Error> {
+        let mut tx = db.begin().await?;
+        credit.insert(&mut *tx).await?;
+        let key = sqlx::query_as::<_, Self>(
+            r#"
+            INSERT INTO prepaid_keys (gateway_key_id, balance, created_at) VALUES (?, ?, ?)
+            ON CONFLICT (gateway_key_id) DO UPDATE SET balance = balance + excluded.balance
+            RETURNING gateway_key_id, balance, created_at
+            "#
+        )
+        .bind(gateway_key_id)
+        .bind(credit.amount)
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(key)
+    }
+
+    /// 查询网关密钥的预付额度，不是预付费密钥时返回空
+    pub async fn find(db: &sqlx::SqlitePool, gateway_key_id: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>("SELECT gateway_key_id, balance, created_at FROM prepaid_keys WHERE gateway_key_id = ?")
+            .bind(gateway_key_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// 是否登记了任何预付费密钥
+    pub async fn any(db: &sqlx::SqlitePool) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM prepaid_keys)")
+            .fetch_one(db)
+            .await
+    }
+
+    /// 预扣额度：检查余额和扣减在同一条 UPDATE 中完成，并发请求不会透支；
+    /// 余额不足时返回空，成功时返回预扣后的余额
+    pub async fn reserve(db: &sqlx::SqlitePool, gateway_key_id: &str, amount: f64) -> Result<Option<f64>, sqlx::Error> {
+        sqlx::query_scalar::<_, f64>(
+            "UPDATE prepaid_keys SET balance = balance - ? WHERE gateway_key_id = ? AND balance >= ? RETURNING balance"
+        )
+        .bind(amount)
+        .bind(gateway_key_id)
+        .bind(amount)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// 按账本条目的带符号金额调整余额（借记按实际成本结算，余额可以为负，之后的预扣会被拒绝），
+    /// 不是预付费密钥时不影响
+    pub async fn apply<'e>(db: impl sqlx::SqliteExecutor<'e>, gateway_key_id: &str, amount: f64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE prepaid_keys SET balance = balance + ? WHERE gateway_key_id = ?")
+            .bind(amount)
+            .bind(gateway_key_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 归还预扣额度
+    pub async fn release(db: &sqlx::SqlitePool, gateway_key_id: &str, amount: f64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE prepaid_keys SET balance = balance + ? WHERE gateway_key_id = ?")
+            .bind(amount)
+            .bind(gateway_key_id)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::billing_ledger::LedgerEntryType;
+
+    async fn pool() -> sqlx::SqlitePool {
+        // 内存数据库每个连接各自独立，只使用一个连接
+        let db = sqlx::sqlite::SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE prepaid_keys (gateway_key_id TEXT PRIMARY KEY, balance REAL NOT NULL DEFAULT 0, created_at TEXT NOT NULL)")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE billing_ledger (id TEXT PRIMARY KEY, account TEXT NOT NULL, entry_type TEXT NOT NULL, amount REAL NOT NULL, \
+             currency TEXT NOT NULL, description TEXT, usage_id TEXT, created_at TEXT NOT NULL)"
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        db
+    }
+
+    fn credit(amount: f64) -> LedgerEntry {
+        LedgerEntry::new("key-1", LedgerEntryType::Credit, amount, "USD", None, None)
+    }
+
+    #[tokio::test]
+    async fn load_accumulates_balance() {
+        let db = pool().await;
+        assert_eq!(PrepaidKey::load(&db, "key-1", &credit(1.0)).await.unwrap().balance, 1.0);
+        assert_eq!(PrepaidKey::load(&db, "key-1", &credit(0.5)).await.unwrap().balance, 1.5);
+        assert!(PrepaidKey::find(&db, "key-2").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn reserve_never_overdraws() {
+        let db = pool().await;
+        PrepaidKey::load(&db, "key-1", &credit(0.25)).await.unwrap();
+
+        let reservations = futures::future::join_all((0..10).map(|_| PrepaidKey::reserve(&db, "key-1", 0.1))).await;
+        let granted = reservations.into_iter().filter(|r| matches!(r, Ok(Some(_)))).count();
+        assert_eq!(granted, 2);
+        assert!(PrepaidKey::find(&db, "key-1").await.unwrap().unwrap().balance >= 0.0);
+        assert_eq!(PrepaidKey::reserve(&db, "key-2", 0.1).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn settlement_returns_hold_and_charges_cost() {
+        let db = pool().await;
+        PrepaidKey::load(&db, "key-1", &credit(1.0)).await.unwrap();
+
+        PrepaidKey::reserve(&db, "key-1", 0.1).await.unwrap().unwrap();
+        PrepaidKey::apply(&db, "key-1", -0.3).await.unwrap();
+        PrepaidKey::release(&db, "key-1", 0.1).await.unwrap();
+
+        let balance = PrepaidKey::find(&db, "key-1").await.unwrap().unwrap().balance;
+        assert!((balance - 0.7).abs() < 1e-9);
+    }
+}
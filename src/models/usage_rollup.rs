@@ -0,0 +1,280 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use futures_util::{Stream, TryStreamExt};
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+
+use crate::models::api_usage::{attributed_provider_key_sql, ApiUsageSummary, KeyUsageDay, ModelStats, ProviderStats, UsageFilter};
+
+/// 使用记录汇总的时间粒度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollupGranularity {
+    Hour,
+    Day,
+}
+
+impl RollupGranularity {
+    pub const ALL: [RollupGranularity; 2] = [RollupGranularity::Hour, RollupGranularity::Day];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    /// 时间段长度
+    pub fn span(self) -> Duration {
+        match self {
+            Self::Hour => Duration::hours(1),
+            Self::Day => Duration::days(1),
+        }
+    }
+
+    /// 向下取整到时间段起点
+    pub fn floor(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        time.duration_trunc(self.span()).unwrap_or(time)
+    }
+
+    /// 向上取整到时间段起点
+    pub fn ceil(self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let floor = self.floor(time);
+        if floor == time { floor } else { floor + self.span() }
+    }
+
+    // 由 request_time 计算时间段起点的SQL表达式，格式与绑定的 DateTime<Utc> 一致（RFC3339）
+    fn bucket_expr(self) -> &'static str {
+        match self {
+            Self::Hour => "substr(request_time, 1, 10) || 'T' || substr(request_time, 12, 2) || ':00:00+00:00'",
+            Self::Day => "substr(request_time, 1, 10) || 'T00:00:00+00:00'",
+        }
+    }
+}
+
+/// 一个时间段内某个网关密钥、提供商和模型的汇总用量
+#[derive(Debug, Clone, FromRow)]
+pub struct UsageRollup {
+    /// 时间段起点（UTC）
+    pub bucket_start: DateTime<Utc>,
+    /// 网关密钥ID，未使用网关密钥的请求为空字符串
+    pub gateway_key_id: String,
+    /// 提供商API密钥
+    pub provider_api_key: String,
+    /// 模型名称
+    pub model: String,
+    /// 请求次数
+    pub requests: i64,
+    /// 成功请求数
+    pub successful_requests: i64,
+    /// 输入token
+    pub prompt_tokens: i64,
+    /// 输出token
+    pub completion_tokens: i64,
+    /// 总token
+    pub total_tokens: i64,
+    /// 按定价计算的成本
+    pub cost: f64,
+}
+
+/// 已汇总到的时间点（不含），尚未汇总过时为空
+pub async fn rolled_up_to(
+    db: &sqlx::SqlitePool,
+    granularity: RollupGranularity,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar::<_, DateTime<Utc>>("SELECT rolled_up_to FROM usage_rollup_state WHERE granularity = ?")
+        .bind(granularity.as_str())
+        .fetch_optional(db)
+        .await
+}
+
+/// 最早一条使用记录的请求时间
+pub async fn earliest_usage(db: &sqlx::SqlitePool) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT MIN(request_time) FROM api_usage")
+        .fetch_one(db)
+        .await
+}
+
+/// 在一个事务中重新汇总 [from, to) 内的完整时间段（from、to 须对齐到时间段起点），并推进已汇总时间点；
+/// 自带上游密钥（BYOK）的请求汇总到 BYOK_PROVIDER_KEY 下，不计入池中的提供商密钥
+pub async fn roll_up(
+    db: &sqlx::SqlitePool,
+    granularity: RollupGranularity,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let result = sqlx::query(&format!(
+        r#"
+        INSERT OR REPLACE INTO usage_rollups (
+            granularity, bucket_start, gateway_key_id, provider_api_key, model,
+            requests, successful_requests, prompt_tokens, completion_tokens, total_tokens, cost
+        )
+        SELECT ?, {} AS bucket, COALESCE(gateway_key_id, ''), {} AS attributed_key, model,
+               COUNT(*),
+               SUM(CASE WHEN status IN ('Success', 'PartialSuccess') THEN 1 ELSE 0 END),
+               COALESCE(SUM(prompt_tokens), 0),
+               COALESCE(SUM(completion_tokens), 0),
+               COALESCE(SUM(total_tokens), 0),
+               COALESCE(SUM(cost), 0.0)
+        FROM api_usage
+        WHERE request_time >= ? AND request_time < ?
+        GROUP BY bucket, COALESCE(gateway_key_id, ''), attributed_key, model
+        "#,
+        granularity.bucket_expr(),
+        attributed_provider_key_sql()
+    ))
+    .bind(granularity.as_str())
+    .bind(from)
+    .bind(to)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO usage_rollup_state (granularity, rolled_up_to, updated_at) VALUES (?, ?, ?)
+        ON CONFLICT(granularity) DO UPDATE SET rolled_up_to = excluded.rolled_up_to, updated_at = excluded.updated_at
+        "#
+    )
+    .bind(granularity.as_str())
+    .bind(to)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(result.rows_affected())
+}
+
+/// 按小时汇总表统计 [from, to) 内的用量（from、to 须对齐到整点），可选按提供商、按模型分组（按总token倒序）
+pub async fn summarize(
+    db: &sqlx::SqlitePool,
+    from: Option<DateTime<Utc>>,
+    to: DateTime<Utc>,
+    by_provider: bool,
+    by_model: bool,
+) -> Result<ApiUsageSummary, sqlx::Error> {
+    let push_conditions = |query: &mut QueryBuilder<'_, Sqlite>| {
+        query.push(" WHERE granularity = ").push_bind(RollupGranularity::Hour.as_str());
+        if let Some(from) = from {
+            query.push(" AND bucket_start >= ").push_bind(from);
+        }
+        query.push(" AND bucket_start < ").push_bind(to);
+    };
+
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT COALESCE(SUM(requests), 0), \
+            COALESCE(SUM(prompt_tokens), 0), \
+            COALESCE(SUM(completion_tokens), 0), \
+            COALESCE(SUM(total_tokens), 0), \
+            COALESCE(SUM(successful_requests), 0) \
+         FROM usage_rollups"
+    );
+    push_conditions(&mut query);
+    let (total_requests, total_prompt_tokens, total_completion_tokens, total_tokens, successful_requests) = query
+        .build_query_as::<(i64, i64, i64, i64, i64)>()
+        .fetch_one(db)
+        .await?;
+
+    let provider_stats = if by_provider {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT provider_api_key, SUM(requests) AS request_count, SUM(total_tokens) AS total_tokens \
+             FROM usage_rollups"
+        );
+        push_conditions(&mut query);
+        query.push(" GROUP BY provider_api_key ORDER BY total_tokens DESC, provider_api_key");
+        Some(query.build_query_as::<ProviderStats>().fetch_all(db).await?)
+    } else {
+        None
+    };
+
+    let model_stats = if by_model {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT model, SUM(requests) AS request_count, \
+                SUM(prompt_tokens) AS total_prompt_tokens, \
+                SUM(completion_tokens) AS total_completion_tokens, \
+                SUM(total_tokens) AS total_tokens \
+             FROM usage_rollups"
+        );
+        push_conditions(&mut query);
+        query.push(" GROUP BY model ORDER BY total_tokens DESC, model");
+        Some(query.build_query_as::<ModelStats>().fetch_all(db).await?)
+    } else {
+        None
+    };
+
+    Ok(ApiUsageSummary {
+        total_requests,
+        total_prompt_tokens,
+        total_completion_tokens,
+        total_tokens,
+        successful_requests,
+        failed_requests: total_requests - successful_requests,
+        provider_stats,
+        model_stats,
+    })
+}
+
+/// 按天汇总表统计网关密钥在 [from, to) 内的用量（from、to 须对齐到UTC零点），按日期升序
+pub async fn daily_for_gateway_key(
+    db: &sqlx::SqlitePool,
+    key_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<KeyUsageDay>, sqlx::Error> {
+    sqlx::query_as::<_, KeyUsageDay>(
+        r#"
+        SELECT substr(bucket_start, 1, 10) AS date,
+               SUM(requests) AS requests,
+               SUM(requests - successful_requests) AS errors,
+               CAST(SUM(requests - successful_requests) AS REAL) / SUM(requests) AS error_rate,
+               SUM(prompt_tokens) AS prompt_tokens,
+               SUM(completion_tokens) AS completion_tokens,
+               SUM(total_tokens) AS total_tokens,
+               SUM(cost) AS cost
+        FROM usage_rollups
+        WHERE granularity = ? AND gateway_key_id = ? AND bucket_start >= ? AND bucket_start < ?
+        GROUP BY date
+        ORDER BY date
+        "#
+    )
+    .bind(RollupGranularity::Day.as_str())
+    .bind(key_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(db)
+    .await
+}
+
+/// 按时间段起点升序逐条读取汇总记录，只使用 filter 中的 from、to、model、provider_api_key 和 gateway_key_id 条件
+pub fn stream(
+    db: sqlx::SqlitePool,
+    granularity: RollupGranularity,
+    filter: UsageFilter,
+) -> impl Stream<Item = Result<UsageRollup, sqlx::Error>> + Send + 'static {
+    async_stream::try_stream! {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT bucket_start, gateway_key_id, provider_api_key, model, requests, successful_requests, \
+                prompt_tokens, completion_tokens, total_tokens, cost \
+             FROM usage_rollups WHERE granularity = "
+        );
+        query.push_bind(granularity.as_str());
+        if let Some(from) = filter.from {
+            query.push(" AND bucket_start >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            query.push(" AND bucket_start < ").push_bind(to);
+        }
+        for (column, value) in [
+            ("model", &filter.model),
+            ("provider_api_key", &filter.provider_api_key),
+            ("gateway_key_id", &filter.gateway_key_id),
+        ] {
+            if let Some(value) = value {
+                query.push(format!(" AND {} = ", column)).push_bind(value.clone());
+            }
+        }
+        query.push(" ORDER BY bucket_start, gateway_key_id, provider_api_key, model");
+        let mut rows = query.build_query_as::<UsageRollup>().fetch(&db);
+        while let Some(rollup) = rows.try_next().await? {
+            yield rollup;
+        }
+    }
+}
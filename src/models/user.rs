@@ -0,0 +1,254 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::admin_token::{scopes_allow, ADMIN_SCOPES};
+
+/// 用户角色，决定用户可以持有的作用域上限
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum UserRole {
+    /// 管理员：拥有全部管理接口权限
+    Admin,
+    /// 运维用户：可以查看和修改提供商、定价、计费和网关密钥，不能管理令牌和用户
+    User,
+    /// 只读用户：只能查看提供商、定价、用量和系统状态
+    #[default]
+    ReadOnly,
+    /// 接口调用方：没有管理接口权限，只通过网关密钥调用推理接口
+    ApiConsumer,
+}
+
+impl UserRole {
+    /// 从存储的角色名解析
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "Admin" => Some(Self::Admin),
+            "User" => Some(Self::User),
+            "ReadOnly" => Some(Self::ReadOnly),
+            "ApiConsumer" => Some(Self::ApiConsumer),
+            _ => None,
+        }
+    }
+
+    /// 角色的默认作用域，也是该角色可以持有的作用域上限
+    pub fn scopes(self) -> &'static [&'static str] {
+        match self {
+            Self::Admin => &["*"],
+            Self::User => &["providers:*", "pricing:*", "billing:*", "keys:*", "system:read"],
+            Self::ReadOnly => &["providers:read", "pricing:read", "billing:read", "keys:read", "system:read"],
+            Self::ApiConsumer => &[],
+        }
+    }
+
+    /// 角色是否允许持有指定作用域（通配作用域要求覆盖的每个作用域都被允许）
+    pub fn permits(self, scope: &str) -> bool {
+        let granted = self.scopes().iter().copied();
+        match scope.strip_suffix('*') {
+            Some("") => self == Self::Admin,
+            Some(prefix) => ADMIN_SCOPES
+                .iter()
+                .filter(|s| s.starts_with(prefix))
+                .all(|s| scopes_allow(granted.clone(), s)),
+            None => scopes_allow(granted, scope),
+        }
+    }
+}
+
+/// 管理员用户（不包含密码哈希）
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct User {
+    /// 唯一标识符
+    pub id: String,
+
+    /// 登录用户名
+    pub username: String,
+
+    /// 邮箱
+    pub email: Option<String>,
+
+    /// 角色（Admin/User/ReadOnly/ApiConsumer）
+    pub role: String,
+
+    /// 逗号分隔的作用域，登录签发的令牌继承这些作用域
+    pub scopes: String,
+
+    /// 是否启用
+    pub enabled: bool,
+
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+
+    /// 最近登录时间
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+const COLUMNS: &str = "id, username, email, role, scopes, enabled, created_at, updated_at, last_login_at";
+
+impl User {
+    /// 创建用户，password_hash 为 hash_password 的结果
+    pub async fn create(
+        db: &sqlx::SqlitePool,
+        username: &str,
+        email: Option<&str>,
+        password_hash: &str,
+        role: UserRole,
+        scopes: &[String],
+    ) -> Result<Self, sqlx::Error> {
+        let now = Utc::now();
+        let user = Self {
+            id: Uuid::new_v4().to_string(),
+            username: username.to_string(),
+            email: email.map(str::to_string),
+            role: format!("{:?}", role),
+            scopes: scopes.join(","),
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+            last_login_at: None,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, username, email, password_hash, role, scopes, enabled, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#
+        )
+        .bind(&user.id)
+        .bind(&user.username)
+        .bind(&user.email)
+        .bind(password_hash)
+        .bind(&user.role)
+        .bind(&user.scopes)
+        .bind(user.enabled)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .execute(db)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// 根据ID查找用户
+    pub async fn find(db: &sqlx::SqlitePool, id: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM users WHERE id = ?", COLUMNS))
+            .bind(id)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// 根据用户名查找用户及其密码哈希
+    pub async fn find_with_password(
+        db: &sqlx::SqlitePool,
+        username: &str,
+    ) -> Result<Option<(Self, String)>, sqlx::Error> {
+        let user = sqlx::query_as::<_, Self>(&format!("SELECT {} FROM users WHERE username = ?", COLUMNS))
+            .bind(username)
+            .fetch_optional(db)
+            .await?;
+        let user = match user {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+
+        let password_hash: String = sqlx::query_scalar("SELECT password_hash FROM users WHERE id = ?")
+            .bind(&user.id)
+            .fetch_one(db)
+            .await?;
+        Ok(Some((user, password_hash)))
+    }
+
+    /// 列出所有用户
+    pub async fn list(db: &sqlx::SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!("SELECT {} FROM users ORDER BY created_at", COLUMNS))
+            .fetch_all(db)
+            .await
+    }
+
+    /// 更新邮箱、密码、角色、作用域和启用状态（为空的字段保持不变），返回更新后的记录
+    pub async fn update(
+        db: &sqlx::SqlitePool,
+        id: &str,
+        email: Option<&str>,
+        password_hash: Option<&str>,
+        role: Option<UserRole>,
+        scopes: Option<&[String]>,
+        enabled: Option<bool>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Self>(&format!(
+            r#"
+            UPDATE users
+            SET email = COALESCE(?, email),
+                password_hash = COALESCE(?, password_hash),
+                role = COALESCE(?, role),
+                scopes = COALESCE(?, scopes),
+                enabled = COALESCE(?, enabled),
+                updated_at = ?
+            WHERE id = ?
+            RETURNING {}
+            "#,
+            COLUMNS
+        ))
+        .bind(email)
+        .bind(password_hash)
+        .bind(role.map(|role| format!("{:?}", role)))
+        .bind(scopes.map(|scopes| scopes.join(",")))
+        .bind(enabled)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// 删除用户，返回是否找到该用户
+    pub async fn delete(db: &sqlx::SqlitePool, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 记录登录时间
+    pub async fn record_login(db: &sqlx::SqlitePool, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET last_login_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// 用户角色；无法识别的角色按权限最小的 ApiConsumer 处理
+    pub fn role(&self) -> UserRole {
+        UserRole::parse(&self.role).unwrap_or(UserRole::ApiConsumer)
+    }
+
+    /// 作用域列表
+    pub fn scope_list(&self) -> Vec<String> {
+        self.scopes.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+    }
+}
+
+/// 使用 argon2 计算密码哈希（PHC 字符串，包含随机盐）；计算较慢，应在阻塞线程中调用
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("计算密码哈希失败: {}", e))
+}
+
+/// 校验密码是否与哈希匹配；计算较慢，应在阻塞线程中调用
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
@@ -1,21 +1,79 @@
 use axum::{
-    routing::{post, get, put},
-    Router, http::HeaderValue,
+    middleware,
+    routing::{delete, post, get, put},
+    Router,
 };
 use sqlx::SqlitePool;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use crate::handlers::api::{
-    chat_completion::{handle_chat_completion, ChatCompletionRequest, ChatCompletionResponse, ErrorResponse, Message},
-    provider::{add_provider, batch_add_providers, get_all_providers, AddProviderRequest, AddProviderResponse, BatchAddProviderRequest, ProviderInfoDTO, ProviderListResponse},
+    chat_completion::{handle_chat_completion, ChatCompletionRequest, ChatCompletionResponse, ContentPart, ErrorResponse, ImageUrl, Message, MessageContent, Usage},
+    provider::{add_provider, batch_add_providers, get_all_providers, get_import_job, AddProviderRequest, AddProviderResponse, BatchAddProviderRequest, ImportJobAccepted, ProviderAddResult, ProviderInfoDTO, ProviderListResponse},
+    embeddings::{handle_embeddings, EmbeddingRequest},
+    completions::{handle_completion, CompletionRequest},
+    audio::handle_audio_transcription,
+    moderations::{handle_moderation, ModerationRequest},
+    depletion::{get_depletion_calendar, get_depletion_feed, DepletionFeed},
+    data_quality::{get_data_quality, DataQualityReport},
+    expiry::{get_expiring_providers, ExpiringProviderList},
+    provider_stats::{get_provider_stats, get_provider_limits},
+    model_catalog::{get_model_metadata, sync_provider_models},
+    reconcile::{get_reconcile_report, post_invoice_reconcile},
+    system::{get_database_schema, get_db_metrics, get_diagnostics, get_latency_slo, get_liveness, get_prometheus_metrics, get_readiness, get_scheduled_jobs},
+    billing::{add_ledger_entry, get_account_balance, get_account_ledger, load_prepaid_credit, AddLedgerEntryRequest, LedgerEntryList, LoadCreditRequest},
+    users::{create_user, delete_user, get_current_user, get_user, list_users, login, update_user, CreateUserRequest, LoginRequest, LoginResponse, UpdateUserRequest, UserList},
+    admin_tokens::{create_admin_token, list_admin_tokens, revoke_admin_token, AdminTokenList, CreateAdminTokenRequest, CreateAdminTokenResponse},
+    gateway_keys::{create_gateway_key, get_gateway_key, get_gateway_key_quota, get_gateway_key_usage, list_gateway_keys, reset_gateway_key_quota, revoke_gateway_key, rotate_gateway_key, update_gateway_key, CreateGatewayKeyRequest, CreateGatewayKeyResponse, GatewayKeyList, GatewayKeyQuota, GatewayKeyUsage, RotateGatewayKeyRequest, RotateGatewayKeyResponse, UpdateGatewayKeyRequest},
+    usage::{create_usage_report_link, export_usage, get_usage_cost, get_usage_summary, list_usage, CreateReportLinkRequest, ReportLink, UsageList},
+    ip_access::{create_ip_access_rule, delete_ip_access_rule, list_ip_access_rules, CreateIpAccessRuleRequest, IpAccessRuleList},
+    fallbacks::{delete_fallback_response, list_fallback_responses, set_fallback_response, FallbackResponseList, SetFallbackResponseRequest},
     pricing::{add_pricing, get_all_pricing, get_pricing, update_pricing, AddPricingRequest, UpdatePricingRequest, PricingResponse},
 };
-use crate::services::{ProviderPoolState, provider_pool::{initialize_provider_pool}};
+use crate::services::{ConcurrencyController, DbMetrics, ImportJobRegistry, ProviderPoolState, ProviderStatsReplica, ListCache, KeyRateLimiter, LatencySloGuard, UsageRecorder, IpAccessList, KeyUsageMetrics, ConversationAffinity, SlowRequestLog, InFlightLimiter};
+use crate::services::db_metrics::{DbMetricsSnapshot, QueryStats};
+use crate::services::latency_slo::LatencySloStatus;
+use crate::services::diagnostics::{CheckStatus, DiagnosticCheck, DiagnosticsReport};
+use crate::services::scheduler::{self, JobStatus};
+use crate::services::usage_cost::{UsageCostGroup, UsageCostReport};
+use crate::database::schema::{AppliedMigration, ColumnSchema, DatabaseSchema, ForeignKeySchema, IndexSchema, TableSchema};
+use crate::services::import_jobs::{ImportJob, ImportJobStatus, ImportKeyResult};
+use crate::services::burn_rate::DepletionProjection;
+use crate::services::contract_expiry::ExpiringProvider;
+use crate::services::provider_latency::LatencySummary;
+use crate::services::provider_stats::{ProviderLiveStats, ProviderStatsSnapshot};
+use crate::services::model_catalog::{ModelMetadata, ModelSyncResult};
+use crate::services::provider_limits::{AdaptiveConcurrencyLimits, BreakerState, BudgetHeadroom, ConnectionLimits, ProviderLimits, UsageWindow};
+use crate::services::degradation::DegradationAdvisory;
+use crate::services::invoice_reconcile::{InvoiceDiff, InvoiceDiscrepancy, InvoiceLine, InvoiceReconcileReport};
+use crate::services::reconcile::{DuplicateRequestId, LedgerMissingUsage, ReconcileReport, UsageMissingCost};
+use crate::middlewares::{
+    admin_auth::{require_scope, ScopeGuard},
+    degradation::degradation_advisory,
+    gateway_auth::authenticate_gateway_key,
+    in_flight_limit::limit_in_flight,
+    ip_access::enforce_ip_access,
+    key_quota::enforce_gateway_key_quota,
+    key_rate_limit::enforce_gateway_key_rate_limit,
+    latency_slo::latency_slo_guard,
+    prepaid_credit::enforce_prepaid_credit,
+    request_tracing::trace_requests,
+};
 use crate::models::model_pricing::{ModelPricing, ModelPricingSummary};
-use utoipa::{OpenApi, IntoParams};
+use crate::models::billing_ledger::{LedgerBalance, LedgerEntry, LedgerEntryType};
+use crate::models::prepaid_key::PrepaidKey;
+use crate::models::admin_token::AdminToken;
+use crate::models::user::{User, UserRole};
+use crate::models::gateway_key::GatewayKey;
+use crate::models::api_usage::{ApiUsage, ApiUsageSummary, KeyUsageDay, ModelStats, ProviderStats};
+use crate::models::data_quality_event::ProviderDataQuality;
+use crate::models::latency_slo_event::LatencySloEvent;
+use crate::models::fallback_response::FallbackResponse;
+use crate::models::ip_access_rule::{IpAccessAction, IpAccessRule};
+use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 use tower_http::cors::{CorsLayer, Any};
+use tower_http::compression::{predicate::{NotForContentType, Predicate, SizeAbove}, CompressionLayer};
 use axum::http::{Method};
 
 /// API文档
@@ -23,63 +81,271 @@ use axum::http::{Method};
 #[openapi(
     paths(
         crate::handlers::api::chat_completion::handle_chat_completion,
+        crate::handlers::api::embeddings::handle_embeddings,
+        crate::handlers::api::completions::handle_completion,
+        crate::handlers::api::audio::handle_audio_transcription,
+        crate::handlers::api::moderations::handle_moderation,
         crate::handlers::api::provider::add_provider,
         crate::handlers::api::provider::batch_add_providers,
         crate::handlers::api::provider::get_all_providers,
+        crate::handlers::api::provider::get_import_job,
+        crate::handlers::api::depletion::get_depletion_feed,
+        crate::handlers::api::depletion::get_depletion_calendar,
+        crate::handlers::api::data_quality::get_data_quality,
+        crate::handlers::api::expiry::get_expiring_providers,
+        crate::handlers::api::provider_stats::get_provider_stats,
+        crate::handlers::api::provider_stats::get_provider_limits,
+        crate::handlers::api::model_catalog::sync_provider_models,
+        crate::handlers::api::model_catalog::get_model_metadata,
         crate::handlers::api::pricing::add_pricing,
         crate::handlers::api::pricing::get_all_pricing,
         crate::handlers::api::pricing::get_pricing,
-        crate::handlers::api::pricing::update_pricing
+        crate::handlers::api::pricing::update_pricing,
+        crate::handlers::api::billing::add_ledger_entry,
+        crate::handlers::api::billing::get_account_balance,
+        crate::handlers::api::billing::get_account_ledger,
+        crate::handlers::api::billing::load_prepaid_credit,
+        crate::handlers::api::reconcile::get_reconcile_report,
+        crate::handlers::api::reconcile::post_invoice_reconcile,
+        crate::handlers::api::admin_tokens::create_admin_token,
+        crate::handlers::api::admin_tokens::list_admin_tokens,
+        crate::handlers::api::admin_tokens::revoke_admin_token,
+        crate::handlers::api::users::create_user,
+        crate::handlers::api::users::list_users,
+        crate::handlers::api::users::get_user,
+        crate::handlers::api::users::update_user,
+        crate::handlers::api::users::delete_user,
+        crate::handlers::api::users::login,
+        crate::handlers::api::users::get_current_user,
+        crate::handlers::api::fallbacks::list_fallback_responses,
+        crate::handlers::api::fallbacks::set_fallback_response,
+        crate::handlers::api::fallbacks::delete_fallback_response,
+        crate::handlers::api::gateway_keys::create_gateway_key,
+        crate::handlers::api::gateway_keys::list_gateway_keys,
+        crate::handlers::api::gateway_keys::get_gateway_key,
+        crate::handlers::api::gateway_keys::update_gateway_key,
+        crate::handlers::api::gateway_keys::revoke_gateway_key,
+        crate::handlers::api::gateway_keys::rotate_gateway_key,
+        crate::handlers::api::gateway_keys::get_gateway_key_quota,
+        crate::handlers::api::gateway_keys::reset_gateway_key_quota,
+        crate::handlers::api::gateway_keys::get_gateway_key_usage,
+        crate::handlers::api::usage::list_usage,
+        crate::handlers::api::usage::get_usage_summary,
+        crate::handlers::api::usage::get_usage_cost,
+        crate::handlers::api::usage::create_usage_report_link,
+        crate::handlers::api::usage::export_usage,
+        crate::handlers::api::system::get_db_metrics,
+        crate::handlers::api::system::get_prometheus_metrics,
+        crate::handlers::api::system::get_latency_slo,
+        crate::handlers::api::system::get_database_schema,
+        crate::handlers::api::system::get_diagnostics,
+        crate::handlers::api::system::get_scheduled_jobs,
+        crate::handlers::api::system::get_liveness,
+        crate::handlers::api::system::get_readiness,
+        crate::handlers::api::ip_access::list_ip_access_rules,
+        crate::handlers::api::ip_access::create_ip_access_rule,
+        crate::handlers::api::ip_access::delete_ip_access_rule
     ),
     components(
         schemas(
             ChatCompletionRequest,
             ChatCompletionResponse,
+            Usage,
             ErrorResponse,
             Message,
+            MessageContent,
+            ContentPart,
+            ImageUrl,
+            EmbeddingRequest,
+            CompletionRequest,
+            ModerationRequest,
             AddProviderRequest,
             AddProviderResponse,
             BatchAddProviderRequest,
+            ProviderAddResult,
             ProviderInfoDTO,
             ProviderListResponse,
+            ImportJobAccepted,
+            ImportJob,
+            ImportJobStatus,
+            ImportKeyResult,
+            DepletionFeed,
+            DepletionProjection,
+            DataQualityReport,
+            ProviderDataQuality,
+            ExpiringProviderList,
+            ExpiringProvider,
+            ProviderStatsSnapshot,
+            ProviderLiveStats,
+            LatencySummary,
+            ProviderLimits,
+            ConnectionLimits,
+            AdaptiveConcurrencyLimits,
+            UsageWindow,
+            BreakerState,
+            BudgetHeadroom,
+            ModelSyncResult,
+            ModelMetadata,
             AddPricingRequest,
             UpdatePricingRequest,
             PricingResponse,
             ModelPricing,
-            ModelPricingSummary
+            ModelPricingSummary,
+            AddLedgerEntryRequest,
+            LedgerEntryList,
+            LoadCreditRequest,
+            LedgerEntry,
+            LedgerEntryType,
+            LedgerBalance,
+            PrepaidKey,
+            ReconcileReport,
+            UsageMissingCost,
+            LedgerMissingUsage,
+            DuplicateRequestId,
+            InvoiceLine,
+            InvoiceDiff,
+            InvoiceDiscrepancy,
+            InvoiceReconcileReport,
+            CreateAdminTokenRequest,
+            CreateAdminTokenResponse,
+            AdminTokenList,
+            AdminToken,
+            CreateUserRequest,
+            UpdateUserRequest,
+            UserList,
+            User,
+            UserRole,
+            LoginRequest,
+            LoginResponse,
+            FallbackResponse,
+            FallbackResponseList,
+            SetFallbackResponseRequest,
+            CreateGatewayKeyRequest,
+            CreateGatewayKeyResponse,
+            UpdateGatewayKeyRequest,
+            RotateGatewayKeyRequest,
+            RotateGatewayKeyResponse,
+            GatewayKeyList,
+            GatewayKeyQuota,
+            GatewayKeyUsage,
+            KeyUsageDay,
+            UsageList,
+            ApiUsage,
+            ApiUsageSummary,
+            ProviderStats,
+            ModelStats,
+            UsageCostReport,
+            UsageCostGroup,
+            CreateReportLinkRequest,
+            ReportLink,
+            GatewayKey,
+            DbMetricsSnapshot,
+            QueryStats,
+            LatencySloStatus,
+            LatencySloEvent,
+            DatabaseSchema,
+            AppliedMigration,
+            TableSchema,
+            ColumnSchema,
+            IndexSchema,
+            ForeignKeySchema,
+            DiagnosticsReport,
+            DiagnosticCheck,
+            CheckStatus,
+            JobStatus,
+            CreateIpAccessRuleRequest,
+            IpAccessRuleList,
+            IpAccessRule,
+            IpAccessAction,
+            DegradationAdvisory
         )
     ),
     tags(
         (name = "chat", description = "聊天相关的API"),
+        (name = "embeddings", description = "向量嵌入API"),
+        (name = "completions", description = "文本补全API（旧版）"),
+        (name = "audio", description = "音频转写API"),
+        (name = "moderations", description = "内容审核API"),
+        (name = "models", description = "网关模型目录"),
         (name = "providers", description = "API提供商管理"),
-        (name = "pricing", description = "模型定价管理")
+        (name = "pricing", description = "模型定价管理"),
+        (name = "billing", description = "计费账本"),
+        (name = "admin", description = "管理令牌与作用域"),
+        (name = "users", description = "管理员用户与登录"),
+        (name = "fallbacks", description = "全部提供商失败时的兜底回复"),
+        (name = "keys", description = "推理接口的网关密钥"),
+        (name = "system", description = "系统运行状态")
     )
 )]
-struct ApiDoc;
+pub(crate) struct ApiDoc;
 
 // 应用程序状态
 #[derive(Clone)]
 pub struct AppState {
     pub db: SqlitePool,
-    pub provider_pool: Arc<Mutex<ProviderPoolState>>,
+    pub provider_pool: Arc<RwLock<ProviderPoolState>>,
+    pub concurrency: Arc<ConcurrencyController>,
+    pub import_jobs: Arc<ImportJobRegistry>,
+    pub db_metrics: Arc<DbMetrics>,
+    pub provider_stats: Arc<ProviderStatsReplica>,
+    pub list_cache: Arc<ListCache>,
+    pub key_rate_limiter: Arc<KeyRateLimiter>,
+    pub latency_slo: Arc<LatencySloGuard>,
+    pub usage_recorder: Arc<UsageRecorder>,
+    pub key_metrics: Arc<KeyUsageMetrics>,
+    pub ip_access: Arc<IpAccessList>,
+    pub conversation_affinity: Arc<ConversationAffinity>,
+    pub slow_requests: Arc<SlowRequestLog>,
+    pub in_flight: Arc<InFlightLimiter>,
     pub config: crate::config::AppConfig,
 }
 
-// 配置API路由
-pub async fn app_routes(pool: SqlitePool, config: crate::config::AppConfig) -> Router {
-    // 初始化provider pool
-    let provider_pool = Arc::new(Mutex::new(
-        initialize_provider_pool(&pool)
-            .await
-            .expect("Failed to initialize provider pool")
+// 配置API路由，provider_pool 与后台任务共用，任务对池的修改立即作用于请求
+pub async fn app_routes(
+    pool: SqlitePool,
+    config: crate::config::AppConfig,
+    provider_pool: Arc<RwLock<ProviderPoolState>>,
+) -> Router {
+    let db_metrics = Arc::new(DbMetrics::new(&config.database));
+    let key_metrics = Arc::new(KeyUsageMetrics::new(config.metrics.key_top_n));
+    let usage_recorder = Arc::new(UsageRecorder::new(
+        pool.clone(),
+        db_metrics.clone(),
+        key_metrics.clone(),
+        config.usage_recorder.clone(),
     ));
 
     // 创建应用程序状态
     let state = AppState {
         db: pool,
         provider_pool,
+        concurrency: Arc::new(ConcurrencyController::new(config.concurrency.clone())),
+        import_jobs: Arc::new(ImportJobRegistry::new()),
+        db_metrics,
+        provider_stats: Arc::new(ProviderStatsReplica::new()),
+        list_cache: Arc::new(ListCache::new(config.list_cache.clone())),
+        key_rate_limiter: Arc::new(KeyRateLimiter::new()),
+        latency_slo: Arc::new(LatencySloGuard::new(config.latency_slo.clone())),
+        usage_recorder,
+        key_metrics,
+        ip_access: Arc::new(IpAccessList::new(&config.ip_access)),
+        conversation_affinity: Arc::new(ConversationAffinity::new(config.conversation_affinity.clone())),
+        slow_requests: Arc::new(SlowRequestLog::new(&config.slow_requests)),
+        in_flight: Arc::new(InFlightLimiter::new(&config.in_flight)),
         config,
     };
+    let jobs = scheduler::scheduler();
+    state.provider_stats.register(
+        &jobs,
+        &state.config.scheduler,
+        state.provider_pool.clone(),
+        state.concurrency.clone(),
+        state.config.provider_stats.refresh_interval_ms,
+    );
+    state.latency_slo.register(&jobs, &state.config.scheduler, state.db.clone());
+    if let Err(e) = state.ip_access.reload(&state.db).await {
+        tracing::error!("加载IP访问规则失败，仅使用环境变量中的规则: {}", e);
+    }
 
     // 配置CORS - 简单配置
     let cors = CorsLayer::new()
@@ -100,6 +366,12 @@ pub async fn app_routes(pool: SqlitePool, config: crate::config::AppConfig) -> R
             axum::http::header::ACCEPT,
             axum::http::header::ORIGIN,
             axum::http::header::ACCEPT_ENCODING,
+            axum::http::header::IF_NONE_MATCH,
+            axum::http::HeaderName::from_static("x-request-priority"),
+            axum::http::HeaderName::from_static("x-gateway-usage-echo"),
+            axum::http::HeaderName::from_static("x-conversation-id"),
+            axum::http::HeaderName::from_static("x-session-id"),
+            axum::http::HeaderName::from_static("x-lb-strategy"),
             axum::http::header::ACCESS_CONTROL_REQUEST_HEADERS,
             axum::http::header::ACCESS_CONTROL_REQUEST_METHOD,
         ])
@@ -109,26 +381,153 @@ pub async fn app_routes(pool: SqlitePool, config: crate::config::AppConfig) -> R
         .expose_headers([
             axum::http::header::CONTENT_TYPE,
             axum::http::header::CONTENT_LENGTH,
+            axum::http::header::ETAG,
+            axum::http::HeaderName::from_static("x-credit-remaining"),
+            axum::http::HeaderName::from_static("x-key-budget-remaining"),
+            axum::http::HeaderName::from_static("x-gateway-degraded"),
+            axum::http::HeaderName::from_static("x-gateway-fallback"),
+            axum::http::HeaderName::from_static("x-gateway-model"),
+            axum::http::HeaderName::from_static("x-gateway-shed"),
+            axum::http::header::RETRY_AFTER,
+            axum::http::HeaderName::from_static("x-ratelimit-limit-requests"),
+            axum::http::HeaderName::from_static("x-ratelimit-remaining-requests"),
+            axum::http::HeaderName::from_static("x-ratelimit-reset-requests"),
+            axum::http::HeaderName::from_static("x-ratelimit-limit-tokens"),
+            axum::http::HeaderName::from_static("x-ratelimit-remaining-tokens"),
+            axum::http::HeaderName::from_static("x-ratelimit-reset-tokens"),
         ])
         // 缓存CORS预检请求结果1小时
         .max_age(Duration::from_secs(3600));
 
-    Router::new()
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+    // 管理接口按作用域鉴权
+    if state.config.auth.admin_api_token.is_none() {
+        tracing::warn!("未配置 ADMIN_API_TOKEN，未携带凭证的管理请求不做鉴权");
+    }
+    if state.config.report_links.signing_secret.is_none() {
+        tracing::warn!("未配置 REPORT_LINK_SECRET，用量报表分享链接已停用");
+    }
+    let scope = |scope: &'static str| {
+        middleware::from_fn_with_state(ScopeGuard::new(state.clone(), scope), require_scope)
+    };
+
+    // 代理接口（延迟过高时先拒绝低优先级请求；再校验网关密钥，检查密钥月度预算并限流，最后预扣预付费网关密钥的额度）
+    let proxy_routes = Router::new()
         .route("/v1/chat/completions", post(handle_chat_completion))
-        .route("/v1/providers", post(add_provider))
-        .route("/v1/providers", get(get_all_providers))
-        .route("/v1/providers/batch", post(batch_add_providers))
+        .route("/v1/embeddings", post(handle_embeddings))
+        .route("/v1/completions", post(handle_completion))
+        .route("/v1/audio/transcriptions", post(handle_audio_transcription))
+        .route("/v1/moderations", post(handle_moderation))
+        .route_layer(middleware::from_fn_with_state(state.clone(), enforce_prepaid_credit))
+        .route_layer(middleware::from_fn_with_state(state.clone(), degradation_advisory))
+        .route_layer(middleware::from_fn_with_state(state.clone(), enforce_gateway_key_rate_limit))
+        .route_layer(middleware::from_fn_with_state(state.clone(), enforce_gateway_key_quota))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate_gateway_key))
+        .route_layer(middleware::from_fn_with_state(state.clone(), latency_slo_guard));
+
+    // 模型目录查询（只识别网关密钥，不计入密钥的额度和限流）
+    let catalog_routes = Router::new()
+        .route("/v1/models/:name/metadata", get(get_model_metadata))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate_gateway_key));
+
+    // 容器探针不经过IP访问控制和请求追踪，也不需要鉴权
+    let probe_routes = Router::new()
+        .route("/healthz", get(get_liveness))
+        .route("/readyz", get(get_readiness))
+        .with_state(state.clone());
+
+    let routes = Router::new()
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .merge(proxy_routes)
+        .merge(catalog_routes)
+        .route("/v1/providers", post(add_provider).route_layer(scope("providers:write")))
+        .route("/v1/providers", get(get_all_providers).route_layer(scope("providers:read")))
+        .route("/v1/providers/batch", post(batch_add_providers).route_layer(scope("providers:write")))
+        .route("/v1/providers/import/:job_id", get(get_import_job).route_layer(scope("providers:read")))
+        .route("/v1/providers/depletion", get(get_depletion_feed).route_layer(scope("providers:read")))
+        .route("/v1/providers/depletion.ics", get(get_depletion_calendar).route_layer(middleware::from_fn_with_state(
+            ScopeGuard::new(state.clone(), "providers:read").with_query_token(),
+            require_scope,
+        )))
+        .route("/v1/providers/data-quality", get(get_data_quality).route_layer(scope("providers:read")))
+        .route("/v1/providers/expiring", get(get_expiring_providers).route_layer(scope("providers:read")))
+        .route("/v1/providers/stats", get(get_provider_stats).route_layer(scope("providers:read")))
+        .route("/v1/providers/:id/limits", get(get_provider_limits).route_layer(scope("providers:read")))
+        .route("/v1/providers/:id/sync-models", post(sync_provider_models).route_layer(scope("providers:write")))
         // 模型定价相关路由
-        .route("/v1/pricing", post(add_pricing))
-        .route("/v1/pricing", get(get_all_pricing))
-        .route("/v1/pricing/:name/:model", get(get_pricing))
-        .route("/v1/pricing/:name/:model", put(update_pricing))
+        .route("/v1/pricing", post(add_pricing).route_layer(scope("pricing:write")))
+        .route("/v1/pricing", get(get_all_pricing).route_layer(scope("pricing:read")))
+        .route("/v1/pricing/:name/:model", get(get_pricing).route_layer(scope("pricing:read")))
+        .route("/v1/pricing/:name/:model", put(update_pricing).route_layer(scope("pricing:write")))
+        // 计费账本相关路由
+        .route("/v1/billing/ledger", post(add_ledger_entry).route_layer(scope("billing:write")))
+        .route("/v1/billing/accounts/:account/balance", get(get_account_balance).route_layer(scope("billing:read")))
+        .route("/v1/billing/accounts/:account/ledger", get(get_account_ledger).route_layer(scope("billing:read")))
+        .route("/v1/billing/keys/:id/credits", post(load_prepaid_credit).route_layer(scope("billing:write")))
+        .route("/v1/usage", get(list_usage).route_layer(scope("billing:read")))
+        .route("/v1/usage/summary", get(get_usage_summary).route_layer(scope("billing:read")))
+        .route("/v1/usage/cost", get(get_usage_cost).route_layer(scope("billing:read")))
+        .route("/v1/usage/export", get(export_usage).route_layer(scope("billing:read")))
+        .route("/v1/usage/reports/links", post(create_usage_report_link).route_layer(scope("billing:read")))
+        .route("/v1/admin/reconcile", get(get_reconcile_report).route_layer(scope("billing:read")))
+        .route("/v1/admin/reconcile/invoice", post(post_invoice_reconcile).route_layer(scope("billing:read")))
+        // 管理令牌相关路由
+        .route("/v1/admin/tokens", post(create_admin_token).route_layer(scope("tokens:write")))
+        .route("/v1/admin/tokens", get(list_admin_tokens).route_layer(scope("tokens:write")))
+        .route("/v1/admin/tokens/:id", delete(revoke_admin_token).route_layer(scope("tokens:write")))
+        // 用户相关路由（登录不需要管理令牌）
+        .route("/v1/users/login", post(login))
+        .route("/v1/users/me", get(get_current_user))
+        .route("/v1/users", post(create_user).route_layer(scope("users:write")))
+        .route("/v1/users", get(list_users).route_layer(scope("users:read")))
+        .route("/v1/users/:id", get(get_user).route_layer(scope("users:read")))
+        .route("/v1/users/:id", put(update_user).route_layer(scope("users:write")))
+        .route("/v1/users/:id", delete(delete_user).route_layer(scope("users:write")))
+        .route("/v1/fallbacks", get(list_fallback_responses).route_layer(scope("providers:read")))
+        .route("/v1/fallbacks/:model", put(set_fallback_response).route_layer(scope("providers:write")))
+        .route("/v1/fallbacks/:model", delete(delete_fallback_response).route_layer(scope("providers:write")))
+        // 网关密钥相关路由
+        .route("/v1/keys", post(create_gateway_key).route_layer(scope("keys:write")))
+        .route("/v1/keys", get(list_gateway_keys).route_layer(scope("keys:read")))
+        .route("/v1/keys/:id", get(get_gateway_key).route_layer(scope("keys:read")))
+        .route("/v1/keys/:id", put(update_gateway_key).route_layer(scope("keys:write")))
+        .route("/v1/keys/:id", delete(revoke_gateway_key).route_layer(scope("keys:write")))
+        .route("/v1/keys/:id/rotate", post(rotate_gateway_key).route_layer(scope("keys:write")))
+        .route("/v1/keys/:id/quota", get(get_gateway_key_quota).route_layer(scope("keys:read")))
+        .route("/v1/keys/:id/quota/reset", post(reset_gateway_key_quota).route_layer(scope("keys:write")))
+        .route("/v1/keys/:id/usage", get(get_gateway_key_usage).route_layer(scope("keys:read")))
+        // 系统状态
+        .route("/v1/system/db-metrics", get(get_db_metrics).route_layer(scope("system:read")))
+        .route("/metrics", get(get_prometheus_metrics).route_layer(scope("system:read")))
+        .route("/v1/system/slo", get(get_latency_slo).route_layer(scope("system:read")))
+        .route("/v1/system/jobs", get(get_scheduled_jobs).route_layer(scope("system:read")))
+        .route("/v1/admin/schema", get(get_database_schema).route_layer(scope("system:read")))
+        .route("/v1/admin/diagnostics", get(get_diagnostics).route_layer(scope("system:read")))
+        .route("/v1/admin/ip-rules", get(list_ip_access_rules).route_layer(scope("system:read")))
+        .route("/v1/admin/ip-rules", post(create_ip_access_rule).route_layer(scope("system:write")))
+        .route("/v1/admin/ip-rules/:id", delete(delete_ip_access_rule).route_layer(scope("system:write")))
+        // 签名链接授权的Web页面
+        .merge(crate::routes::web::web_routes())
+        // 请求追踪采样（route_layer 保证能获取到匹配的路由模板）
+        .route_layer(middleware::from_fn_with_state(state.clone(), trace_requests));
+
+    // 响应压缩：SSE 流式响应不压缩，避免数据块被压缩器缓冲
+    let compression = &state.config.response_compression;
+    let routes = if compression.enabled {
+        let predicate = SizeAbove::new(compression.min_size_bytes)
+            .and(NotForContentType::SSE)
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::IMAGES);
+        routes.layer(CompressionLayer::new().gzip(true).br(true).compress_when(predicate))
+    } else {
+        routes
+    };
+
+    routes
+        // 全局在途请求上限（在CORS内层，拒绝响应仍带CORS头）
+        .layer(middleware::from_fn_with_state(state.clone(), limit_in_flight))
         .layer(cors)
+        // 客户端IP访问控制（最外层，先于CORS和鉴权执行）
+        .layer(middleware::from_fn_with_state(state.clone(), enforce_ip_access))
         .with_state(state)
-}
-
-// 简单的健康检查API
-async fn health_check() -> &'static str {
-    "OK"
+        .merge(probe_routes)
 }
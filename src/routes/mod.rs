@@ -3,10 +3,13 @@ pub mod web;
 
 use axum::Router;
 use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 use crate::config::AppConfig;
+use crate::services::ProviderPoolState;
 
 // 创建应用路由
-pub async fn create_routes(pool: SqlitePool, config: AppConfig) -> Router {
+pub async fn create_routes(pool: SqlitePool, config: AppConfig, provider_pool: Arc<RwLock<ProviderPoolState>>) -> Router {
     Router::new()
-        .nest("/api", api::app_routes(pool, config).await)
+        .nest("/api", api::app_routes(pool, config, provider_pool).await)
 }
@@ -1 +1,12 @@
-// Web路由模块，暂时留空
+// Web路由模块：无需网关凭证即可访问的页面（由签名链接授权）
+
+use axum::{routing::get, Router};
+
+use crate::handlers::web::usage_report::get_shared_usage_report;
+use crate::routes::api::AppState;
+use crate::services::usage_report::REPORT_PATH;
+
+/// Web页面路由
+pub fn web_routes() -> Router<AppState> {
+    Router::new().route(REPORT_PATH, get(get_shared_usage_report))
+}
@@ -0,0 +1,101 @@
+use axum::Router;
+use std::net::SocketAddr;
+use tokio::task::JoinSet;
+use tracing::info;
+
+use crate::config::AppConfig;
+
+/// 按配置启动监听器（TCP 和/或 UNIX域套接字），任一监听器退出即返回
+pub async fn serve(app: Router, config: &AppConfig) -> anyhow::Result<()> {
+    let mut listeners = JoinSet::new();
+
+    // 先绑定所有监听器，地址被占用等错误在启动时即可暴露
+    if config.server.tcp_enabled {
+        let addr = config.socket_addr();
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        info!("Starting server on {}", addr);
+        let app = app.clone();
+        listeners.spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+            Ok(())
+        });
+    }
+
+    if let Some(path) = &config.server.unix_socket {
+        let listener = unix::bind(path)?;
+        info!("Starting server on unix:{}", path.display());
+        listeners.spawn(unix::serve(listener, app));
+    }
+
+    if listeners.is_empty() {
+        anyhow::bail!("未配置任何监听器: APP_TCP_ENABLED=false 且未设置 APP_UNIX_SOCKET");
+    }
+
+    match listeners.join_next().await {
+        Some(result) => result?,
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use axum::{extract::ConnectInfo, Extension, Router};
+    use hyper::server::conn::http1;
+    use hyper_util::{rt::TokioIo, service::TowerToHyperService};
+    use std::net::SocketAddr;
+    use std::path::Path;
+    use tokio::net::UnixListener;
+    use tokio::time::{sleep, Duration};
+    use tracing::{debug, error};
+
+    pub(super) fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+        // 上次运行遗留的套接字文件会导致绑定失败，先删除
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(UnixListener::bind(path)?)
+    }
+
+    pub(super) async fn serve(listener: UnixListener, app: Router) -> anyhow::Result<()> {
+        // UNIX域连接没有IP地址，为依赖 ConnectInfo<SocketAddr> 的处理器提供本机回环占位地址
+        let app = app.layer(Extension(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 0)))));
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // 与 axum::serve 一致：接受连接失败（如文件描述符耗尽）时稍后重试
+                    error!("UNIX域套接字接受连接失败: {}", e);
+                    sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            let service = TowerToHyperService::new(app.clone());
+            tokio::spawn(async move {
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .with_upgrades()
+                    .await
+                {
+                    debug!("UNIX域套接字连接异常结束: {}", e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod unix {
+    use axum::Router;
+    use std::path::Path;
+
+    pub(super) struct UnixListener;
+
+    pub(super) fn bind(_path: &Path) -> anyhow::Result<UnixListener> {
+        anyhow::bail!("当前平台不支持UNIX域套接字")
+    }
+
+    pub(super) async fn serve(_listener: UnixListener, _app: Router) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
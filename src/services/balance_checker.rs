@@ -1,12 +1,18 @@
 use std::sync::Arc;
+use std::time::Duration;
 use reqwest::Client;
 use serde::Deserialize;
-use tracing::{error, info};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
 use chrono::Utc;
 use sqlx::{SqlitePool, Row};
-use tokio::sync::Mutex;
-use crate::services::provider_pool::{ProviderInfo, ProviderPoolState};
+use tokio::sync::RwLock;
+use crate::services::{openrouter, upstream_client};
+use crate::services::provider_errors::{self, ProviderErrorCategory};
+use crate::services::provider_pool::{ProviderInfo, ProviderPoolState, refresh_providers};
+use crate::utils::redact::redact;
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct UserInfoResponse {
     code: i32,
@@ -15,6 +21,7 @@ struct UserInfoResponse {
     data: UserData,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct UserData {
     id: String,
@@ -25,19 +32,145 @@ struct UserData {
     total_balance: String,
 }
 
+// 账户指纹取哈希的前多少个十六进制字符
+const FINGERPRINT_LEN: usize = 16;
+
+// 余额查询结果
+struct BalanceReport {
+    balance: f64,
+    // 上游账户指纹，余额接口没有返回用户ID时为空
+    account_fingerprint: Option<String>,
+}
+
+// 根据余额接口的主机和返回的用户ID计算上游账户指纹，同一账户下的不同密钥指纹相同
+fn account_fingerprint(balance_url: &str, user: &UserData) -> Option<String> {
+    let account = Some(user.id.trim()).filter(|id| !id.is_empty())?;
+    let host = reqwest::Url::parse(balance_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| balance_url.to_string());
+    let digest = format!("{:x}", Sha256::digest(format!("{}|{}", host, account).as_bytes()));
+    Some(digest[..FINGERPRINT_LEN].to_string())
+}
+
+// 自托管提供商的健康探测接口：OpenAI 兼容的模型列表，以及 Ollama 原生的模型列表
+const HEALTH_PROBE_PATHS: &[&str] = &["/v1/models", "/api/tags"];
+
 pub struct BalanceChecker {
     client: Client,
     db_pool: Arc<SqlitePool>,
-    provider_pool: Arc<Mutex<ProviderPoolState>>,
+    provider_pool: Arc<RwLock<ProviderPoolState>>,
+    quarantine_failure_threshold: u32,
+    probe_timeout: Duration,
 }
 
 impl BalanceChecker {
-    pub fn new(db_pool: Arc<SqlitePool>, provider_pool: Arc<Mutex<ProviderPoolState>>) -> Self {
+    pub fn new(
+        db_pool: Arc<SqlitePool>,
+        provider_pool: Arc<RwLock<ProviderPoolState>>,
+        quarantine_failure_threshold: u32,
+        probe_timeout_ms: u64,
+    ) -> Self {
         Self {
             client: Client::new(),
             db_pool,
             provider_pool,
+            quarantine_failure_threshold,
+            probe_timeout: Duration::from_millis(probe_timeout_ms),
+        }
+    }
+
+    // 获取余额查询URL：优先使用提供商配置的 balance_check_url，否则根据 base_url 推导
+    fn balance_url(provider: &ProviderInfo) -> anyhow::Result<String> {
+        if let Some(url) = provider.balance_check_url.as_deref().filter(|u| !u.is_empty()) {
+            return Ok(url.to_string());
         }
+
+        if provider.is_openrouter() {
+            return Ok(openrouter::key_url(&provider.base_url));
+        }
+
+        let base_url = provider.base_url.split("/v1/").next()
+            .ok_or_else(|| anyhow::anyhow!("无效的 base_url 格式"))?;
+
+        Ok(format!("{}/v1/user/info", base_url))
+    }
+
+    // 解析余额查询响应，OpenRouter 使用专用的额度接口格式（不返回用户ID，没有账户指纹）
+    async fn parse_balance(&self, provider: &ProviderInfo, url: &str, response: reqwest::Response) -> anyhow::Result<BalanceReport> {
+        if provider.is_openrouter() {
+            let balance = openrouter::remaining_credits(&self.client, &provider.base_url, &provider.api_key, response).await?;
+            return Ok(BalanceReport { balance, account_fingerprint: None });
+        }
+
+        let user_info: UserInfoResponse = response.json().await?;
+        Ok(BalanceReport {
+            balance: user_info.data.balance.parse::<f64>()?,
+            account_fingerprint: account_fingerprint(url, &user_info.data),
+        })
+    }
+
+    // 探测自托管提供商是否在线：依次请求模型列表接口，任一返回成功即视为健康
+    pub async fn probe_health(&self, provider: &ProviderInfo) -> anyhow::Result<()> {
+        let origin = provider.base_url.split("/v1/").next().unwrap_or_default().trim_end_matches('/');
+
+        // 配置了私有CA或跳过证书校验的提供商使用按其TLS设置创建的客户端
+        let client = if provider.tls_options().is_custom() {
+            upstream_client::tls_client(provider).map_err(|e| anyhow::anyhow!(e))?
+        } else {
+            self.client.clone()
+        };
+
+        let mut last_error = anyhow::anyhow!("没有可用的健康探测接口");
+        for path in HEALTH_PROBE_PATHS {
+            let url = format!("{}{}", origin, path);
+            let mut request = client.get(&url).timeout(self.probe_timeout);
+            if let Some((auth_name, auth_value)) = provider.auth_header() {
+                request = request.header(auth_name, auth_value);
+            }
+
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    info!("自托管提供商健康探测成功: api_key={}, URL: {}", redact(&provider.api_key), url);
+                    return Ok(());
+                }
+                Ok(response) => last_error = anyhow::anyhow!("健康探测失败: {} 返回 HTTP {}", url, response.status()),
+                Err(e) => last_error = anyhow::anyhow!("健康探测失败: {}: {}", url, e),
+            }
+        }
+        Err(last_error)
+    }
+
+    // 自托管提供商没有计费接口，离线时标记为 Inactive 并移出内存池，恢复在线后重新启用
+    // 返回提供商是否从离线状态恢复
+    async fn check_self_hosted_health(&self, provider: &ProviderInfo, status: &str) -> anyhow::Result<bool> {
+        match self.probe_health(provider).await {
+            Ok(()) if status == "Active" => Ok(false),
+            Ok(()) => {
+                self.set_provider_status(&provider.api_key, "Active").await?;
+                info!("自托管提供商已恢复在线: api_key={}", redact(&provider.api_key));
+                Ok(true)
+            }
+            Err(e) => {
+                provider_errors::record_for_key(&self.db_pool, &provider.api_key, ProviderErrorCategory::HealthCheck, &e.to_string()).await;
+                if status != "Inactive" {
+                    self.set_provider_status(&provider.api_key, "Inactive").await?;
+                    self.provider_pool.write().await.remove_provider(&provider.api_key);
+                    info!("自托管提供商离线，已停用: api_key={}", redact(&provider.api_key));
+                }
+                Err(e)
+            }
+        }
+    }
+
+    async fn set_provider_status(&self, api_key: &str, status: &str) -> anyhow::Result<()> {
+        sqlx::query("UPDATE api_providers SET status = ?, updated_at = ? WHERE api_key = ?")
+            .bind(status)
+            .bind(Utc::now())
+            .bind(api_key)
+            .execute(&*self.db_pool)
+            .await?;
+        Ok(())
     }
 
     // 删除余额为0的提供商
@@ -53,50 +186,58 @@ impl BalanceChecker {
         if rows_affected > 0 {
             info!(
                 "已从数据库删除余额为0的提供商: api_key={}",
-                api_key
+                redact(api_key)
             );
-            self.provider_pool.lock().await.remove_provider(api_key);
+            self.provider_pool.write().await.remove_provider(api_key);
         } else {
-             info!("尝试从数据库删除 {} 失败或记录不存在/余额不为0", api_key);
+             info!("尝试从数据库删除 {} 失败或记录不存在/余额不为0", redact(api_key));
         }
 
         Ok(())
     }
 
-    async fn remove_invalid_provider(&self, api_key: &str) -> anyhow::Result<()> {
-        let rows_affected = sqlx::query("DELETE FROM api_providers WHERE api_key = ?")
-            .bind(api_key)
-            .execute(&*self.db_pool)
-            .await?
-            .rows_affected();
+    // 隔离鉴权失败的提供商：累计连续失败次数并移出内存池，等待后续重新验证
+    async fn quarantine_provider(&self, api_key: &str) -> anyhow::Result<()> {
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE api_providers
+            SET status = 'Quarantined',
+                consecutive_auth_failures = consecutive_auth_failures + 1,
+                quarantined_at = COALESCE(quarantined_at, ?),
+                last_balance_check = ?
+            WHERE api_key = ?
+            "#
+        )
+        .bind(Utc::now())
+        .bind(Utc::now())
+        .bind(api_key)
+        .execute(&*self.db_pool)
+        .await?
+        .rows_affected();
 
         if rows_affected > 0 {
-            info!(
-                "已从数据库删除无效的提供商: api_key={}",
-                api_key
-            );
-            self.provider_pool.lock().await.remove_provider(api_key);
+            info!("提供商鉴权失败，已隔离: api_key={}", redact(api_key));
+            self.provider_pool.write().await.remove_provider(api_key);
         }
         Ok(())
     }
 
-    // 检查单个提供商的余额并更新数据库
+    // 检查单个提供商的余额并更新数据库，失败时记录为提供商的最近错误
     async fn check_balance_and_update_db(&self, provider: &ProviderInfo) -> anyhow::Result<f64> {
+        let result = self.fetch_balance_and_update_db(provider).await;
+        if let Err(e) = &result {
+            provider_errors::record_for_key(&self.db_pool, &provider.api_key, ProviderErrorCategory::BalanceCheck, &e.to_string()).await;
+        }
+        result
+    }
+
+    async fn fetch_balance_and_update_db(&self, provider: &ProviderInfo) -> anyhow::Result<f64> {
         if !provider.support_balance_check {
-            info!("提供商 {} 不支持余额检查", provider.api_key);
+            info!("提供商 {} 不支持余额检查", redact(&provider.api_key));
             return Ok(provider.balance);
         }
 
-        // 修改 URL 构建逻辑
-        let base_url = if provider.base_url.contains("siliconflow") {
-            "https://api.siliconflow.cn".to_string()
-        } else {
-            provider.base_url.split("/v1/").next()
-                .ok_or_else(|| anyhow::anyhow!("无效的 base_url 格式"))?
-                .to_string()
-        };
-        
-        let url = format!("{}/v1/user/info", base_url);
+        let url = Self::balance_url(provider)?;
         
         info!("检查提供商余额, URL: {}", url);
 
@@ -107,9 +248,9 @@ impl BalanceChecker {
             .await?;
 
         if response.status() == reqwest::StatusCode::UNAUTHORIZED {
-            error!("获取余额失败: HTTP 401 Unauthorized. 密钥 {} 无效或已过期。", provider.api_key);
-            // 将余额设置为NULL表示无效
-            self.update_provider_balance_to_null(&provider.api_key).await?;
+            error!("获取余额失败: HTTP 401 Unauthorized. 密钥 {} 无效或已过期。", redact(&provider.api_key));
+            // 隔离而不是立即删除，避免上游鉴权服务短暂故障导致密钥被永久删除
+            self.quarantine_provider(&provider.api_key).await?;
             return Err(anyhow::anyhow!("获取余额失败: HTTP 401 Unauthorized"));
         }
 
@@ -118,17 +259,21 @@ impl BalanceChecker {
             return Err(anyhow::anyhow!("获取余额失败: HTTP {}", response.status()));
         }
 
-        let user_info: UserInfoResponse = response.json().await?;
-        let balance = user_info.data.balance.parse::<f64>()?;
+        let BalanceReport { balance, account_fingerprint } = self.parse_balance(provider, &url, response).await?;
         
         // 更新数据库中的余额
         if let Err(e) = self.update_provider_balance_in_db(&provider.api_key, balance).await {
-            error!("更新提供商 {} 数据库余额失败: {}", provider.api_key, e);
+            error!("更新提供商 {} 数据库余额失败: {}", redact(&provider.api_key), e);
+        }
+        if let Some(fingerprint) = account_fingerprint {
+            if let Err(e) = self.record_account_fingerprint(&provider.api_key, &fingerprint).await {
+                error!("记录提供商 {} 账户指纹失败: {}", redact(&provider.api_key), e);
+            }
         }
 
         info!(
             "提供商 {} 余额获取成功: {}, 最后检查时间: {}",
-            provider.api_key,
+            redact(&provider.api_key),
             balance,
             Utc::now()
         );
@@ -139,20 +284,11 @@ impl BalanceChecker {
     // 验证API密钥有效性（用于新添加的提供商，不更新数据库）
     pub async fn verify_api_key(&self, provider: &ProviderInfo) -> anyhow::Result<f64> {
         if !provider.support_balance_check {
-            info!("提供商 {} 不支持余额检查", provider.api_key);
+            info!("提供商 {} 不支持余额检查", redact(&provider.api_key));
             return Ok(provider.balance);
         }
 
-        // 修改 URL 构建逻辑
-        let base_url = if provider.base_url.contains("siliconflow") {
-            "https://api.siliconflow.cn".to_string()
-        } else {
-            provider.base_url.split("/v1/").next()
-                .ok_or_else(|| anyhow::anyhow!("无效的 base_url 格式"))?
-                .to_string()
-        };
-        
-        let url = format!("{}/v1/user/info", base_url);
+        let url = Self::balance_url(provider)?;
         
         info!("验证API密钥有效性, URL: {}", url);
 
@@ -163,7 +299,7 @@ impl BalanceChecker {
             .await?;
 
         if response.status() == reqwest::StatusCode::UNAUTHORIZED {
-            error!("API密钥无效: HTTP 401 Unauthorized. 密钥 {} 无效或已过期。", provider.api_key);
+            error!("API密钥无效: HTTP 401 Unauthorized. 密钥 {} 无效或已过期。", redact(&provider.api_key));
             return Err(anyhow::anyhow!("API密钥无效: HTTP 401 Unauthorized"));
         }
 
@@ -172,12 +308,11 @@ impl BalanceChecker {
             return Err(anyhow::anyhow!("验证API密钥失败: HTTP {}", response.status()));
         }
 
-        let user_info: UserInfoResponse = response.json().await?;
-        let balance = user_info.data.balance.parse::<f64>()?;
+        let balance = self.parse_balance(provider, &url, response).await?.balance;
         
         info!(
             "API密钥验证成功: api_key={}, balance={}",
-            provider.api_key,
+            redact(&provider.api_key),
             balance
         );
 
@@ -188,60 +323,32 @@ impl BalanceChecker {
     pub async fn check_balance(&self, provider: &mut ProviderInfo) -> anyhow::Result<()> {
         match self.check_balance_and_update_db(provider).await {
             Ok(balance) => {
+                provider.balance = balance;
                 // 如果余额为0，尝试删除（包括数据库和内存）
                 if balance <= 0.0 {
                     if let Err(e) = self.remove_zero_balance_provider(&provider.api_key).await {
-                        error!("处理余额为0的提供商 {} 时出错: {}", provider.api_key, e);
+                        error!("处理余额为0的提供商 {} 时出错: {}", redact(&provider.api_key), e);
                     }
                 }
                 Ok(())
             }
-            Err(e) => {
-                // 如果是401错误，删除无效的提供商
-                if e.to_string().contains("HTTP 401 Unauthorized") {
-                    if let Err(delete_err) = self.remove_invalid_provider(&provider.api_key).await {
-                        error!("处理无效的提供商 {} 时出错: {}", provider.api_key, delete_err);
-                    }
-                }
-                Err(e)
-            }
+            // 401错误已在检查时隔离提供商，这里直接返回错误
+            Err(e) => Err(e),
         }
     }
 
-    // 更新数据库中的提供商余额
-    async fn update_provider_balance(&self, provider: &ProviderInfo) -> anyhow::Result<()> {
-        sqlx::query(
-            r#"
-            UPDATE api_providers 
-            SET balance = ?, 
-                last_balance_check = ?
-            WHERE api_key = ?
-            "#
-        )
-        .bind(provider.balance)
-        .bind(provider.last_balance_check)
-        .bind(&provider.api_key)
-        .execute(&*self.db_pool)
-        .await?;
-
-        info!(
-            "数据库中的提供商余额已更新: api_key={}, balance={}", 
-            provider.api_key, 
-            provider.balance
-        );
-
-        Ok(())
-    }
-
     // 更新数据库中的提供商余额（新方法）
     async fn update_provider_balance_in_db(&self, api_key: &str, balance: f64) -> anyhow::Result<()> {
-        info!("开始更新数据库余额: api_key={}, balance={}", api_key, balance);
+        info!("开始更新数据库余额: api_key={}, balance={}", redact(api_key), balance);
         
         let result = sqlx::query(
             r#"
             UPDATE api_providers 
             SET balance = ?, 
-                last_balance_check = ?
+                last_balance_check = ?,
+                consecutive_auth_failures = 0,
+                status = CASE WHEN status = 'Quarantined' THEN 'Active' ELSE status END,
+                quarantined_at = NULL
             WHERE api_key = ?
             "#
         )
@@ -253,7 +360,7 @@ impl BalanceChecker {
 
         info!(
             "数据库中的提供商余额已更新: api_key={}, balance={}, 影响行数={}", 
-            api_key, 
+            redact(api_key), 
             balance,
             result.rows_affected()
         );
@@ -267,35 +374,43 @@ impl BalanceChecker {
         .fetch_one(&*self.db_pool)
         .await?;
         
-        info!("验证更新结果: api_key={}, 匹配记录数={}", api_key, count);
+        info!("验证更新结果: api_key={}, 匹配记录数={}", redact(api_key), count);
 
         Ok(())
     }
 
-    // 将提供商余额设置为NULL（表示无效）
-    async fn update_provider_balance_to_null(&self, api_key: &str) -> anyhow::Result<()> {
-        sqlx::query(
-            r#"
-            UPDATE api_providers 
-            SET balance = NULL, 
-                last_balance_check = ?
-            WHERE api_key = ?
-            "#
+    // 记录提供商的上游账户指纹；指纹首次记录或发生变化时，检查是否有其他密钥属于同一账户
+    async fn record_account_fingerprint(&self, api_key: &str, fingerprint: &str) -> anyhow::Result<()> {
+        let changed = sqlx::query(
+            "UPDATE api_providers SET account_fingerprint = ? WHERE api_key = ? AND account_fingerprint IS NOT ?"
         )
-        .bind(Utc::now())
+        .bind(fingerprint)
         .bind(api_key)
+        .bind(fingerprint)
         .execute(&*self.db_pool)
-        .await?;
-
-        info!(
-            "数据库中的提供商余额已设置为NULL（无效）: api_key={}", 
-            api_key
-        );
+        .await?
+        .rows_affected();
+        if changed == 0 {
+            return Ok(());
+        }
 
+        let shared = sqlx::query_scalar::<_, String>(
+            "SELECT api_key FROM api_providers WHERE account_fingerprint = ? AND api_key != ? ORDER BY created_at"
+        )
+        .bind(fingerprint)
+        .bind(api_key)
+        .fetch_all(&*self.db_pool)
+        .await?;
+        if !shared.is_empty() {
+            warn!(
+                "多个提供商密钥属于同一上游账户，共享同一额度: 账户指纹={}, api_key={}, 同账户的密钥={:?}",
+                fingerprint, redact(api_key), shared.iter().map(|key| redact(key)).collect::<Vec<_>>()
+            );
+        }
         Ok(())
     }
 
-    // 批量删除余额为0或无效的提供商
+    // 批量删除余额为0或多次鉴权失败的提供商
     async fn batch_delete_providers(&self) -> anyhow::Result<(usize, usize)> {
         info!("开始批量删除提供商...");
         
@@ -306,13 +421,17 @@ impl BalanceChecker {
         .fetch_one(&*self.db_pool)
         .await?;
         
-        let null_balance_count = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM api_providers WHERE balance IS NULL AND support_balance_check = 1"
+        let invalid_count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM api_providers WHERE status = 'Quarantined' AND consecutive_auth_failures >= ?"
         )
+        .bind(self.quarantine_failure_threshold)
         .fetch_one(&*self.db_pool)
         .await?;
         
-        info!("准备删除: 余额为0的提供商 {} 个, 余额为NULL的提供商 {} 个", zero_balance_count, null_balance_count);
+        info!(
+            "准备删除: 余额为0的提供商 {} 个, 连续鉴权失败达到 {} 次的隔离提供商 {} 个",
+            zero_balance_count, self.quarantine_failure_threshold, invalid_count
+        );
         
         // 删除余额为0的提供商
         let zero_balance_result = sqlx::query(
@@ -323,10 +442,11 @@ impl BalanceChecker {
         
         let zero_balance_deleted = zero_balance_result.rows_affected() as usize;
         
-        // 删除余额为NULL的提供商（无效密钥）
+        // 删除连续鉴权失败达到阈值的隔离提供商（无效密钥）
         let invalid_result = sqlx::query(
-            "DELETE FROM api_providers WHERE balance IS NULL AND support_balance_check = 1"
+            "DELETE FROM api_providers WHERE status = 'Quarantined' AND consecutive_auth_failures >= ?"
         )
+        .bind(self.quarantine_failure_threshold)
         .execute(&*self.db_pool)
         .await?;
         
@@ -345,15 +465,17 @@ impl BalanceChecker {
     pub async fn check_all_providers_from_db(&self) -> anyhow::Result<()> {
         info!("开始从数据库加载提供商进行余额检查...");
         
-        // 从数据库加载所有活跃的提供商
+        // 从数据库加载所有活跃的提供商，以及需要重新验证的隔离提供商和离线的自托管提供商
         let rows = sqlx::query(
             r#"
             SELECT 
                 id, name, provider_type, is_official, base_url, api_key,
                 status, rate_limit, balance, last_balance_check, min_balance_threshold,
-                support_balance_check, model_name, model_type, model_version
+                support_balance_check, balance_check_url, model_name, model_type, model_version,
+                tls_ca_path, tls_insecure_skip_verify
             FROM api_providers 
-            WHERE status = 'Active'
+            WHERE status IN ('Active', 'Quarantined')
+               OR (provider_type = 'SelfHosted' AND status = 'Inactive')
             ORDER BY created_at DESC
             "#
         )
@@ -361,7 +483,7 @@ impl BalanceChecker {
         .await?;
         
         let total_count = rows.len();
-        info!("从数据库加载了 {} 个活跃提供商", total_count);
+        info!("从数据库加载了 {} 个活跃、隔离中或离线的提供商", total_count);
         
         if total_count == 0 {
             info!("没有活跃的提供商需要检查");
@@ -371,54 +493,101 @@ impl BalanceChecker {
         let mut success_count = 0;
         let mut failure_count = 0;
         let mut skipped_count = 0;
+        let mut restored = Vec::new();
         
         // 第一阶段：检查所有提供商并更新数据库
         for (index, row) in rows.iter().enumerate() {
             let api_key: String = row.get("api_key");
+            let status: String = row.get("status");
             let support_balance_check: i64 = row.get("support_balance_check");
             let base_url: String = row.get("base_url");
-            let balance: f64 = row.get("balance");
+            let balance: Option<f64> = row.get("balance");
             let min_balance_threshold: f64 = row.get("min_balance_threshold");
+            let balance_check_url: Option<String> = row.get("balance_check_url");
             let model_name: String = row.get("model_name");
             let model_type: String = row.get("model_type");
             let model_version: String = row.get("model_version");
             
-            info!("检查提供商 {}/{}: {}", index + 1, total_count, api_key);
-            
-            if support_balance_check == 0 {
-                info!("提供商 {} 不支持余额检查，跳过", api_key);
-                skipped_count += 1;
-                continue;
-            }
+            info!("检查提供商 {}/{}: {}", index + 1, total_count, redact(&api_key));
             
             // 创建临时的ProviderInfo用于余额检查
             let provider = ProviderInfo {
                 base_url: base_url.clone(),
                 api_key: api_key.clone(),
+                own_api_key: None,
+                provider_type: row.get("provider_type"),
                 max_connections: 10,
                 min_connections: 1,
                 acquire_timeout_ms: 3000,
                 idle_timeout_ms: 600000,
                 load_balance_strategy: "RoundRobin".to_string(),
+                requests_per_minute: 0,
                 retry_attempts: 3,
-                balance,
+                retry_delay_ms: 1000,
+                retry_on_statuses: None,
+                connect_timeout_ms: None,
+                request_timeout_ms: None,
+                priority: 0,
+                pool_max_idle_per_host: None,
+                keep_alive_secs: None,
+                http_version: None,
+                extra_headers: None,
+                tls_ca_path: row.get("tls_ca_path"),
+                tls_insecure_skip_verify: row.get("tls_insecure_skip_verify"),
+                balance: balance.unwrap_or(0.0),
                 last_balance_check: None,
                 min_balance_threshold,
                 support_balance_check: support_balance_check == 1,
+                balance_check_url,
+                stream_pacing_tps: None,
+                supports_gzip_request: false,
+                supports_structured_output: true,
+                deployment: None,
+                api_version: None,
+                aws_region: None,
+                expires_at: None,
+                organization: None,
                 model_name: model_name.clone(),
                 model_type: model_type.clone(),
                 model_version: model_version.clone(),
             };
+
+            // 不支持余额检查的自托管提供商改为探测服务是否在线
+            if provider.is_self_hosted() && !provider.support_balance_check {
+                match self.check_self_hosted_health(&provider, &status).await {
+                    Ok(is_restored) => {
+                        success_count += 1;
+                        if is_restored {
+                            restored.push(api_key.clone());
+                        }
+                    }
+                    Err(e) => {
+                        failure_count += 1;
+                        error!("自托管提供商 {} 健康检查失败: {}", redact(&api_key), e);
+                    }
+                }
+                continue;
+            }
+
+            if support_balance_check == 0 {
+                info!("提供商 {} 不支持余额检查，跳过", redact(&api_key));
+                skipped_count += 1;
+                continue;
+            }
             
             match self.check_balance_and_update_db(&provider).await {
                 Ok(_balance) => {
                     success_count += 1;
+                    if status == "Quarantined" {
+                        info!("隔离中的提供商重新验证成功，已恢复: api_key={}", redact(&api_key));
+                        restored.push(api_key.clone());
+                    }
                 }
                 Err(e) => {
                     failure_count += 1;
                     error!(
                         "提供商 {} 余额检查失败: {}", 
-                        api_key, 
+                        redact(&api_key), 
                         e
                     );
                 }
@@ -426,9 +595,16 @@ impl BalanceChecker {
         }
         
         info!(
-            "余额检查阶段完成: 总计={}, 成功={}, 失败={}, 跳过={}", 
-            total_count, success_count, failure_count, skipped_count
+            "余额检查阶段完成: 总计={}, 成功={}, 失败={}, 跳过={}, 恢复={}", 
+            total_count, success_count, failure_count, skipped_count, restored.len()
         );
+
+        // 有隔离的提供商恢复时，把它们重新加入内存中的提供商池
+        if !restored.is_empty() {
+            if let Err(e) = refresh_providers(&self.db_pool, &self.provider_pool, &restored).await {
+                error!("恢复提供商后更新提供商池失败: {}", e);
+            }
+        }
         
         // 第二阶段：批量删除余额为0和无效的提供商
         match self.batch_delete_providers().await {
@@ -447,7 +623,7 @@ impl BalanceChecker {
         Ok(())
     }
 
-    pub async fn check_all_providers(&self, providers: &mut Vec<ProviderInfo>) {
+    pub async fn check_all_providers(&self, providers: &mut [ProviderInfo]) {
         let total_count = providers.len();
         let mut success_count = 0;
         let mut failure_count = 0;
@@ -457,10 +633,10 @@ impl BalanceChecker {
         
         // 第一阶段：检查所有提供商并更新数据库
         for (index, provider) in providers.iter().enumerate() {
-            info!("检查提供商 {}/{}: {}", index + 1, total_count, provider.api_key);
+            info!("检查提供商 {}/{}: {}", index + 1, total_count, redact(&provider.api_key));
             
             if !provider.support_balance_check {
-                info!("提供商 {} 不支持余额检查，跳过", provider.api_key);
+                info!("提供商 {} 不支持余额检查，跳过", redact(&provider.api_key));
                 skipped_count += 1;
                 continue;
             }
@@ -473,7 +649,7 @@ impl BalanceChecker {
                     failure_count += 1;
                     error!(
                         "提供商 {} 余额检查失败: {}", 
-                        provider.api_key, 
+                        redact(&provider.api_key), 
                         e
                     );
                 }
@@ -0,0 +1,581 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+use serde_json::{json, Value};
+
+use crate::utils::sigv4::uri_encode;
+
+/// Bedrock 默认的 Anthropic 消息接口版本
+const ANTHROPIC_BEDROCK_VERSION: &str = "bedrock-2023-05-31";
+/// Anthropic 消息接口要求 max_tokens，客户端未指定时使用该值
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 1000;
+
+/// 转换为OpenAI格式所需的补全结果
+#[derive(Debug, Clone)]
+pub struct BedrockCompletion {
+    pub content: String,
+    pub finish_reason: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+/// 流式响应中的增量内容
+#[derive(Debug, Default)]
+pub struct BedrockDelta {
+    pub text: Option<String>,
+    pub finish_reason: Option<String>,
+    pub usage: Option<(u32, u32)>,
+}
+
+/// Bedrock InvokeModel 端点：{endpoint}/model/{model_id}/invoke
+pub fn invoke_url(endpoint: &str, model_id: &str) -> String {
+    format!("{}/model/{}/invoke", endpoint.trim_end_matches('/'), uri_encode(model_id))
+}
+
+/// 默认的 Bedrock Runtime 区域端点
+pub fn default_endpoint(region: &str) -> String {
+    format!("https://bedrock-runtime.{}.amazonaws.com", region)
+}
+
+/// InvokeModelWithResponseStream 端点
+pub fn stream_url(invoke_url: &str) -> String {
+    match invoke_url.strip_suffix("/invoke") {
+        Some(prefix) => format!("{}/invoke-with-response-stream", prefix),
+        None => invoke_url.to_string(),
+    }
+}
+
+/// 从 InvokeModel 端点中取出模型ID
+pub fn model_id_from_url(invoke_url: &str) -> String {
+    let encoded = invoke_url
+        .split("/model/")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or_default();
+    percent_decode(encoded)
+}
+
+// Anthropic Claude 使用消息接口，其余模型（Titan）使用文本接口
+fn is_anthropic(model_id: &str) -> bool {
+    model_id.starts_with("anthropic.") || model_id.contains(".anthropic.")
+}
+
+/// 将OpenAI格式的消息转换为对应模型的 InvokeModel 请求体，
+/// 消息内容为字符串或内容片段数组（text / image_url）；客户端未指定的生成参数不发送，使用模型的默认值
+pub fn build_request_body<'a>(
+    model_id: &str,
+    messages: impl IntoIterator<Item = (&'a str, Value)>,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+) -> Result<Value, String> {
+    if is_anthropic(model_id) {
+        let mut system = Vec::new();
+        let mut converted = Vec::new();
+        for (role, content) in messages {
+            if role == "system" {
+                system.push(content_text(&content));
+            } else {
+                converted.push(json!({ "role": role, "content": anthropic_content(&content)? }));
+            }
+        }
+
+        let mut body = json!({
+            "anthropic_version": ANTHROPIC_BEDROCK_VERSION,
+            "max_tokens": max_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_TOKENS),
+            "messages": converted,
+        });
+        if let Some(temperature) = temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !system.is_empty() {
+            body["system"] = json!(system.join("\n"));
+        }
+        return Ok(body);
+    }
+
+    let mut prompt = String::new();
+    for (role, content) in messages {
+        let speaker = match role {
+            "assistant" => "Bot",
+            "system" => "System",
+            _ => "User",
+        };
+        if content_parts(&content).any(|part| part_type(part) == Some("image_url")) {
+            return Err(format!("Bedrock模型 {} 不支持图片输入", model_id));
+        }
+        prompt.push_str(&format!("{}: {}\n", speaker, content_text(&content)));
+    }
+    prompt.push_str("Bot:");
+
+    let mut generation_config = serde_json::Map::new();
+    if let Some(max_tokens) = max_tokens {
+        generation_config.insert("maxTokenCount".to_string(), json!(max_tokens));
+    }
+    if let Some(temperature) = temperature {
+        generation_config.insert("temperature".to_string(), json!(temperature));
+    }
+    Ok(json!({
+        "inputText": prompt,
+        "textGenerationConfig": generation_config,
+    }))
+}
+
+// 消息内容中的片段，字符串内容没有片段
+fn content_parts(content: &Value) -> impl Iterator<Item = &Value> {
+    content.as_array().into_iter().flatten()
+}
+
+fn part_type(part: &Value) -> Option<&str> {
+    part.get("type").and_then(|t| t.as_str())
+}
+
+// 消息内容中的文本：字符串内容原样返回，片段数组拼接其中的文本片段
+fn content_text(content: &Value) -> String {
+    match content.as_str() {
+        Some(text) => text.to_string(),
+        None => content_parts(content)
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+// 转换为 Anthropic 内容块，图片仅支持 data URL（base64）形式
+fn anthropic_content(content: &Value) -> Result<Value, String> {
+    if !content.is_array() {
+        return Ok(json!(content_text(content)));
+    }
+
+    content_parts(content)
+        .map(|part| match part_type(part) {
+            Some("image_url") => {
+                let url = part.pointer("/image_url/url").and_then(|u| u.as_str()).unwrap_or_default();
+                let (media_type, data) = parse_data_url(url)
+                    .ok_or_else(|| "Bedrock 仅支持 data URL（base64）格式的图片".to_string())?;
+                Ok(json!({
+                    "type": "image",
+                    "source": { "type": "base64", "media_type": media_type, "data": data },
+                }))
+            }
+            _ => Ok(json!({
+                "type": "text",
+                "text": part.get("text").and_then(|t| t.as_str()).unwrap_or_default(),
+            })),
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(Value::Array)
+}
+
+// 解析 data:image/png;base64,<数据> 形式的图片地址，返回 (媒体类型, base64数据)
+fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let (meta, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64")?;
+    Some((media_type, data))
+}
+
+/// 解析 InvokeModel 响应
+pub fn parse_response(model_id: &str, body: &Value) -> Result<BedrockCompletion, String> {
+    if is_anthropic(model_id) {
+        let content = body
+            .get("content")
+            .and_then(|c| c.as_array())
+            .ok_or_else(|| "Bedrock响应缺少content字段".to_string())?
+            .iter()
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<String>();
+        let usage = body.get("usage");
+        return Ok(BedrockCompletion {
+            content,
+            finish_reason: finish_reason(body.get("stop_reason").and_then(|r| r.as_str())),
+            prompt_tokens: token_count(usage, "input_tokens"),
+            completion_tokens: token_count(usage, "output_tokens"),
+        });
+    }
+
+    let result = body
+        .get("results")
+        .and_then(|r| r.get(0))
+        .ok_or_else(|| "Bedrock响应缺少results字段".to_string())?;
+    Ok(BedrockCompletion {
+        content: result.get("outputText").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+        finish_reason: finish_reason(result.get("completionReason").and_then(|r| r.as_str())),
+        prompt_tokens: token_count(Some(body), "inputTextTokenCount"),
+        completion_tokens: token_count(Some(result), "tokenCount"),
+    })
+}
+
+/// 解析流式响应中的单个数据块
+pub fn parse_stream_chunk(model_id: &str, chunk: &Value) -> BedrockDelta {
+    // 最后一个数据块附带 Bedrock 统计的调用用量
+    let usage = chunk.get("amazon-bedrock-invocationMetrics").map(|metrics| {
+        (
+            token_count(Some(metrics), "inputTokenCount"),
+            token_count(Some(metrics), "outputTokenCount"),
+        )
+    });
+
+    if is_anthropic(model_id) {
+        let text = match chunk.get("type").and_then(|t| t.as_str()) {
+            Some("content_block_delta") => chunk
+                .pointer("/delta/text")
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_string()),
+            _ => None,
+        };
+        let finish = chunk
+            .pointer("/delta/stop_reason")
+            .and_then(|r| r.as_str())
+            .map(|r| finish_reason(Some(r)));
+        return BedrockDelta { text, finish_reason: finish, usage };
+    }
+
+    BedrockDelta {
+        text: chunk.get("outputText").and_then(|t| t.as_str()).map(|t| t.to_string()),
+        finish_reason: chunk
+            .get("completionReason")
+            .and_then(|r| r.as_str())
+            .map(|r| finish_reason(Some(r))),
+        usage,
+    }
+}
+
+/// 将 InvokeModelWithResponseStream 的事件流转换为OpenAI格式的SSE数据块
+pub fn openai_sse_stream(
+    response: reqwest::Response,
+    model: String,
+    model_id: String,
+) -> impl Stream<Item = Result<Bytes, String>> {
+    async_stream::stream! {
+        let id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+        let created = chrono::Utc::now().timestamp();
+        let mut upstream = response.bytes_stream();
+        let mut decoder = EventStreamDecoder::default();
+        let mut finish = None;
+        let mut usage = None;
+
+        while let Some(chunk) = upstream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(e.to_string());
+                    return;
+                }
+            };
+
+            let events = match decoder.push(&chunk) {
+                Ok(events) => events,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            for event in events {
+                let delta = parse_stream_chunk(&model_id, &event);
+                if delta.finish_reason.is_some() {
+                    finish = delta.finish_reason;
+                }
+                if delta.usage.is_some() {
+                    usage = delta.usage;
+                }
+                if let Some(text) = delta.text.filter(|t| !t.is_empty()) {
+                    let data = json!({
+                        "id": id,
+                        "object": "chat.completion.chunk",
+                        "created": created,
+                        "model": model,
+                        "choices": [{ "index": 0, "delta": { "content": text }, "finish_reason": null }],
+                    });
+                    yield Ok(Bytes::from(format!("data: {}\n\n", data)));
+                }
+            }
+        }
+
+        let mut data = json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": model,
+            "choices": [{ "index": 0, "delta": {}, "finish_reason": finish.unwrap_or_else(|| "stop".to_string()) }],
+        });
+        if let Some((prompt_tokens, completion_tokens)) = usage {
+            data["usage"] = json!({
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens,
+            });
+        }
+        yield Ok(Bytes::from(format!("data: {}\n\n", data)));
+        yield Ok(Bytes::from("data: [DONE]\n\n"));
+    }
+}
+
+/// AWS 事件流（application/vnd.amazon.eventstream）解码器
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    buffer: BytesMut,
+}
+
+impl EventStreamDecoder {
+    /// 追加收到的数据，返回已完整接收的事件中解码出的模型数据块
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<Value>, String> {
+        self.buffer.extend_from_slice(data);
+        let mut events = Vec::new();
+
+        // 消息结构：总长度(4) 头部长度(4) 前导CRC(4) 头部 负载 消息CRC(4)
+        while self.buffer.len() >= 12 {
+            let total_len = u32::from_be_bytes(self.buffer[0..4].try_into().unwrap()) as usize;
+            let headers_len = u32::from_be_bytes(self.buffer[4..8].try_into().unwrap()) as usize;
+            if total_len < 16 + headers_len {
+                return Err(format!("无效的事件流消息长度: {}", total_len));
+            }
+            if self.buffer.len() < total_len {
+                break;
+            }
+
+            let message = self.buffer.split_to(total_len).freeze();
+            let headers = parse_headers(&message[12..12 + headers_len])?;
+            let payload = &message[12 + headers_len..total_len - 4];
+            let header = |name: &str| headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+
+            match header(":message-type") {
+                Some("event") => {
+                    if header(":event-type") != Some("chunk") {
+                        continue;
+                    }
+                    let wrapper: Value = serde_json::from_slice(payload)
+                        .map_err(|e| format!("解析事件流负载失败: {}", e))?;
+                    let encoded = wrapper.get("bytes").and_then(|b| b.as_str()).unwrap_or_default();
+                    let decoded = BASE64
+                        .decode(encoded)
+                        .map_err(|e| format!("解码事件流数据失败: {}", e))?;
+                    events.push(
+                        serde_json::from_slice(&decoded)
+                            .map_err(|e| format!("解析模型数据块失败: {}", e))?,
+                    );
+                }
+                _ => {
+                    let message = serde_json::from_slice::<Value>(payload)
+                        .ok()
+                        .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(|m| m.to_string()))
+                        .unwrap_or_else(|| String::from_utf8_lossy(payload).into_owned());
+                    return Err(format!(
+                        "Bedrock返回异常: {}: {}",
+                        header(":exception-type").unwrap_or("unknown"),
+                        message
+                    ));
+                }
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+// 解析事件流消息头，仅保留字符串类型的值
+fn parse_headers(mut data: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let truncated = || "事件流消息头不完整".to_string();
+    let mut headers = Vec::new();
+
+    while data.has_remaining() {
+        let name_len = data.get_u8() as usize;
+        if data.remaining() < name_len + 1 {
+            return Err(truncated());
+        }
+        let name = String::from_utf8_lossy(&data[..name_len]).into_owned();
+        data.advance(name_len);
+
+        let value_len = match data.get_u8() {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                if data.remaining() < 2 {
+                    return Err(truncated());
+                }
+                data.get_u16() as usize
+            }
+            other => return Err(format!("未知的事件流消息头类型: {}", other)),
+        };
+        if data.remaining() < value_len {
+            return Err(truncated());
+        }
+        headers.push((name, String::from_utf8_lossy(&data[..value_len]).into_owned()));
+        data.advance(value_len);
+    }
+
+    Ok(headers)
+}
+
+fn finish_reason(reason: Option<&str>) -> String {
+    match reason {
+        Some("max_tokens") | Some("LENGTH") => "length".to_string(),
+        Some("CONTENT_FILTERED") => "content_filter".to_string(),
+        _ => "stop".to_string(),
+    }
+}
+
+fn token_count(value: Option<&Value>, field: &str) -> u32 {
+    value
+        .and_then(|v| v.get(field))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE: &str = "anthropic.claude-3-haiku-20240307-v1:0";
+    const TITAN: &str = "amazon.titan-text-express-v1";
+
+    // 按事件流格式编码一条消息（解码器不校验CRC，CRC填0）
+    fn event_message(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut encoded_headers = Vec::new();
+        for (name, value) in headers {
+            encoded_headers.push(name.len() as u8);
+            encoded_headers.extend_from_slice(name.as_bytes());
+            encoded_headers.push(7);
+            encoded_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            encoded_headers.extend_from_slice(value.as_bytes());
+        }
+        let total_len = 16 + encoded_headers.len() + payload.len();
+        let mut message = Vec::new();
+        message.extend_from_slice(&(total_len as u32).to_be_bytes());
+        message.extend_from_slice(&(encoded_headers.len() as u32).to_be_bytes());
+        message.extend_from_slice(&[0; 4]);
+        message.extend_from_slice(&encoded_headers);
+        message.extend_from_slice(payload);
+        message.extend_from_slice(&[0; 4]);
+        message
+    }
+
+    fn chunk_event(chunk: &Value) -> Vec<u8> {
+        let payload = json!({ "bytes": BASE64.encode(chunk.to_string()) });
+        event_message(
+            &[(":message-type", "event"), (":event-type", "chunk")],
+            payload.to_string().as_bytes(),
+        )
+    }
+
+    #[test]
+    fn model_id_round_trips_through_invoke_url() {
+        let url = invoke_url(&default_endpoint("us-east-1"), CLAUDE);
+        assert_eq!(
+            url,
+            "https://bedrock-runtime.us-east-1.amazonaws.com/model/anthropic.claude-3-haiku-20240307-v1%3A0/invoke"
+        );
+        assert_eq!(model_id_from_url(&url), CLAUDE);
+        assert_eq!(model_id_from_url(&stream_url(&url)), CLAUDE);
+        assert!(stream_url(&url).ends_with("/invoke-with-response-stream"));
+    }
+
+    #[test]
+    fn anthropic_request_and_response() {
+        let messages = vec![
+            ("system", json!("Be brief.")),
+            ("user", json!([
+                { "type": "text", "text": "What is this?" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+            ])),
+        ];
+        let body = build_request_body(CLAUDE, messages, None, Some(0.5)).unwrap();
+        assert_eq!(body["anthropic_version"], ANTHROPIC_BEDROCK_VERSION);
+        assert_eq!(body["max_tokens"], ANTHROPIC_DEFAULT_MAX_TOKENS);
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["messages"][0]["content"][1]["source"]["media_type"], "image/png");
+        assert_eq!(body["messages"][0]["content"][1]["source"]["data"], "iVBORw0KGgo=");
+
+        let response = json!({
+            "content": [{ "type": "text", "text": "A " }, { "type": "text", "text": "logo." }],
+            "stop_reason": "max_tokens",
+            "usage": { "input_tokens": 12, "output_tokens": 3 },
+        });
+        let completion = parse_response(CLAUDE, &response).unwrap();
+        assert_eq!(completion.content, "A logo.");
+        assert_eq!(completion.finish_reason, "length");
+        assert_eq!((completion.prompt_tokens, completion.completion_tokens), (12, 3));
+
+        assert!(parse_response(CLAUDE, &json!({})).is_err());
+        let remote_image = vec![("user", json!([{ "type": "image_url", "image_url": { "url": "https://example.com/a.png" } }]))];
+        assert!(build_request_body(CLAUDE, remote_image, None, None).is_err());
+    }
+
+    #[test]
+    fn titan_request_and_response() {
+        let messages = vec![("system", json!("Be brief.")), ("user", json!("Hi")), ("assistant", json!("Hello"))];
+        let body = build_request_body(TITAN, messages, Some(64), None).unwrap();
+        assert_eq!(body["inputText"], "System: Be brief.\nUser: Hi\nBot: Hello\nBot:");
+        assert_eq!(body["textGenerationConfig"], json!({ "maxTokenCount": 64 }));
+
+        let response = json!({
+            "inputTextTokenCount": 9,
+            "results": [{ "outputText": "Hey", "tokenCount": 2, "completionReason": "FINISH" }],
+        });
+        let completion = parse_response(TITAN, &response).unwrap();
+        assert_eq!(completion.content, "Hey");
+        assert_eq!(completion.finish_reason, "stop");
+        assert_eq!((completion.prompt_tokens, completion.completion_tokens), (9, 2));
+
+        let filtered = json!({ "results": [{ "outputText": "", "completionReason": "CONTENT_FILTERED" }] });
+        assert_eq!(parse_response(TITAN, &filtered).unwrap().finish_reason, "content_filter");
+        let image = vec![("user", json!([{ "type": "image_url", "image_url": { "url": "data:image/png;base64,AA==" } }]))];
+        assert!(build_request_body(TITAN, image, None, None).is_err());
+    }
+
+    #[test]
+    fn decodes_split_event_stream() {
+        let mut stream = chunk_event(&json!({ "type": "content_block_delta", "delta": { "type": "text_delta", "text": "Hel" } }));
+        stream.extend(chunk_event(&json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": "end_turn" },
+            "amazon-bedrock-invocationMetrics": { "inputTokenCount": 5, "outputTokenCount": 1 },
+        })));
+
+        // 消息可能被拆分到多个数据块中
+        let mut decoder = EventStreamDecoder::default();
+        let (first, second) = stream.split_at(10);
+        assert!(decoder.push(first).unwrap().is_empty());
+        let events = decoder.push(second).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let text = parse_stream_chunk(CLAUDE, &events[0]);
+        assert_eq!(text.text.as_deref(), Some("Hel"));
+        assert!(text.finish_reason.is_none() && text.usage.is_none());
+        let last = parse_stream_chunk(CLAUDE, &events[1]);
+        assert_eq!(last.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(last.usage, Some((5, 1)));
+    }
+
+    #[test]
+    fn surfaces_event_stream_exceptions() {
+        let message = event_message(
+            &[(":message-type", "exception"), (":exception-type", "throttlingException")],
+            br#"{"message":"Too many requests"}"#,
+        );
+        let error = EventStreamDecoder::default().push(&message).unwrap_err();
+        assert_eq!(error, "Bedrock返回异常: throttlingException: Too many requests");
+    }
+}
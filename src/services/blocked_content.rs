@@ -0,0 +1,39 @@
+use serde_json::Value;
+
+use crate::config::BlockedResponseConfig;
+
+/// 内容被上游策略拦截时，上游调用返回的错误前缀
+pub const CONTENT_BLOCKED_ERROR: &str = "内容被上游策略拦截";
+
+/// 被拦截的补全使用的 finish_reason（与 OpenAI 一致）
+pub const CONTENT_FILTER_FINISH_REASON: &str = "content_filter";
+
+// 各提供商表示内容审核拦截的错误码（OpenAI、Azure、阿里云等）
+const BLOCKED_ERROR_CODES: &[&str] = &[
+    "content_filter",
+    "content_policy_violation",
+    "responsible_ai_policy_violation",
+    "data_inspection_failed",
+];
+
+/// 判断上游错误响应是否为内容审核拦截，返回拦截原因
+pub fn blocked_reason(status: u16, body: &str) -> Option<String> {
+    if !matches!(status, 400 | 403 | 451) {
+        return None;
+    }
+
+    let json: Value = serde_json::from_str(body).ok()?;
+    let error = json.get("error").unwrap_or(&json);
+    let code = ["code", "type"]
+        .iter()
+        .filter_map(|field| error.get(*field).and_then(Value::as_str))
+        .find(|code| BLOCKED_ERROR_CODES.contains(code))?;
+
+    let message = error.get("message").and_then(Value::as_str).unwrap_or(code);
+    Some(message.to_string())
+}
+
+/// 渲染拦截提示模板，支持 {model} 和 {reason} 占位符
+pub fn render(config: &BlockedResponseConfig, model: &str, reason: &str) -> String {
+    config.template.replace("{model}", model).replace("{reason}", reason)
+}
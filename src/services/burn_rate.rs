@@ -0,0 +1,113 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::models::model_pricing::ModelPricing;
+
+/// 提供商余额耗尽预测
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DepletionProjection {
+    /// 提供商ID
+    pub provider_id: String,
+    /// 提供商名称
+    pub name: String,
+    /// 当前余额
+    pub balance: f64,
+    /// 最小余额阈值
+    pub min_balance_threshold: f64,
+    /// 统计窗口内的日均消耗（按模型定价计算）
+    pub daily_burn: f64,
+    /// 预计可用天数（无消耗时为空）
+    pub days_remaining: Option<f64>,
+    /// 预计余额降至最小阈值的时间（无消耗时为空）
+    pub depletion_date: Option<DateTime<Utc>>,
+}
+
+// 单个提供商的统计窗口内用量
+struct ProviderUsage {
+    name: String,
+    api_key: String,
+    balance: f64,
+    min_balance_threshold: f64,
+    tokens_by_model: Vec<(String, i64, i64)>,
+}
+
+/// 根据最近 window_days 天的用量计算各活跃提供商的消耗速度和预计耗尽时间
+pub async fn project_depletion(db: &SqlitePool, window_days: i64) -> Result<Vec<DepletionProjection>, sqlx::Error> {
+    let window_days = window_days.max(1);
+    let now = Utc::now();
+    let since = now - Duration::days(window_days);
+
+    let rows = sqlx::query_as::<_, (String, String, String, f64, f64, Option<String>, i64, i64)>(
+        r#"
+        SELECT
+            p.id, p.name, p.api_key, COALESCE(p.balance, 0.0), COALESCE(p.min_balance_threshold, 0.0),
+            u.model,
+            COALESCE(SUM(u.prompt_tokens), 0),
+            COALESCE(SUM(u.completion_tokens), 0)
+        FROM api_providers p
+        LEFT JOIN api_usage u
+            ON u.provider_api_key = p.api_key
+            AND u.status = 'Success'
+            AND u.own_key_id IS NULL
+            AND u.request_time >= ?
+        WHERE p.status = 'Active'
+        GROUP BY p.id, u.model
+        "#
+    )
+    .bind(since)
+    .fetch_all(db)
+    .await?;
+
+    let mut providers: BTreeMap<String, ProviderUsage> = BTreeMap::new();
+    for (id, name, api_key, balance, min_balance_threshold, model, prompt_tokens, completion_tokens) in rows {
+        let entry = providers.entry(id).or_insert_with(|| ProviderUsage {
+            name,
+            api_key,
+            balance,
+            min_balance_threshold,
+            tokens_by_model: Vec::new(),
+        });
+        if let Some(model) = model {
+            entry.tokens_by_model.push((model, prompt_tokens, completion_tokens));
+        }
+    }
+
+    let mut projections = Vec::with_capacity(providers.len());
+    for (provider_id, usage) in providers {
+        let mut window_cost = 0.0;
+        for (model, prompt_tokens, completion_tokens) in &usage.tokens_by_model {
+            if let Some(pricing) = ModelPricing::get_price_for_provider_key(db, &usage.api_key, model).await? {
+                window_cost += pricing.calculate_cost(*prompt_tokens as u32, *completion_tokens as u32);
+            }
+        }
+
+        let daily_burn = window_cost / window_days as f64;
+        let days_remaining = (daily_burn > 0.0)
+            .then(|| ((usage.balance - usage.min_balance_threshold) / daily_burn).max(0.0));
+        let depletion_date = days_remaining
+            .map(|days| now + Duration::seconds((days * 86400.0) as i64));
+
+        projections.push(DepletionProjection {
+            provider_id,
+            name: usage.name,
+            balance: usage.balance,
+            min_balance_threshold: usage.min_balance_threshold,
+            daily_burn,
+            days_remaining,
+            depletion_date,
+        });
+    }
+
+    // 最早耗尽的排在前面，无消耗的排在最后
+    projections.sort_by(|a, b| match (a.depletion_date, b.depletion_date) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.name.cmp(&b.name),
+    });
+
+    Ok(projections)
+}
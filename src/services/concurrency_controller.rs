@@ -0,0 +1,177 @@
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::info;
+
+use crate::config::ConcurrencyConfig;
+use crate::utils::redact::redact;
+
+// 乘性减小的系数
+const DECREASE_FACTOR: f64 = 0.5;
+// 两次减小之间的最短间隔，避免同一批失败请求把并发连续砍半
+const DECREASE_COOLDOWN: Duration = Duration::from_secs(2);
+
+// 单个提供商的自适应并发状态
+#[derive(Debug)]
+struct AdaptiveLimit {
+    limit: f64,
+    in_flight: u32,
+    last_decrease: Option<Instant>,
+}
+
+/// 提供商并发自适应控制器（AIMD）
+/// 请求成功且延迟正常时加性增大并发上限，遇到429或延迟过高时乘性减小
+#[derive(Debug)]
+pub struct ConcurrencyController {
+    config: ConcurrencyConfig,
+    limits: DashMap<String, AdaptiveLimit>,
+    // 有名额归还时唤醒排队等待的请求
+    released: Notify,
+}
+
+/// 并发许可，释放时归还占用的并发数
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    controller: Arc<ConcurrencyController>,
+    api_key: String,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.controller.release(&self.api_key);
+    }
+}
+
+impl ConcurrencyController {
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        Self {
+            config,
+            limits: DashMap::new(),
+            released: Notify::new(),
+        }
+    }
+
+    /// 是否启用自适应并发
+    pub fn is_enabled(&self) -> bool {
+        self.config.auto_tuning
+    }
+
+    /// 尝试占用一个并发名额，首次使用时以 initial_limit 作为初始上限
+    pub fn try_acquire(self: &Arc<Self>, api_key: &str, initial_limit: i32) -> Option<ConcurrencyPermit> {
+        let mut state = self.limits.entry(api_key.to_string()).or_insert_with(|| AdaptiveLimit {
+            limit: self.clamp(initial_limit.max(1) as f64),
+            in_flight: 0,
+            last_decrease: None,
+        });
+
+        if state.in_flight as f64 >= state.limit.floor() {
+            tracing::info!(
+                "提供商并发已达自适应上限: api_key={}, in_flight={}, limit={:.2}",
+                redact(api_key), state.in_flight, state.limit
+            );
+            return None;
+        }
+
+        state.in_flight += 1;
+        Some(ConcurrencyPermit {
+            controller: self.clone(),
+            api_key: api_key.to_string(),
+        })
+    }
+
+    /// 占用一个并发名额，已达上限时最多等待 wait 直到有名额归还，超时返回空
+    pub async fn acquire(self: &Arc<Self>, api_key: &str, initial_limit: i32, wait: Duration) -> Option<ConcurrencyPermit> {
+        let deadline = Instant::now() + wait;
+        loop {
+            // 先登记等待再检查名额，避免错过检查之后的归还通知
+            let notified = self.released.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if let Some(permit) = self.try_acquire(api_key, initial_limit) {
+                return Some(permit);
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    /// 记录一次成功请求及其延迟
+    pub fn record_success(&self, api_key: &str, latency: Duration) {
+        if latency > Duration::from_millis(self.config.latency_target_ms) {
+            info!(
+                "提供商延迟超过目标({}ms > {}ms)，减小并发上限: api_key={}",
+                latency.as_millis(), self.config.latency_target_ms, redact(api_key)
+            );
+            self.decrease(api_key);
+            return;
+        }
+
+        if let Some(mut state) = self.limits.get_mut(api_key) {
+            // 加性增大：每个成功请求增加 1/limit，约每轮并发增加1
+            state.limit = self.clamp(state.limit + 1.0 / state.limit);
+        }
+    }
+
+    /// 记录一次上游限流（429）
+    pub fn record_rate_limited(&self, api_key: &str) {
+        info!("提供商返回429，减小并发上限: api_key={}", redact(api_key));
+        self.decrease(api_key);
+    }
+
+    /// 获取提供商当前的自适应并发上限
+    pub fn current_limit(&self, api_key: &str) -> Option<u32> {
+        self.limits
+            .get(api_key)
+            .map(|s| s.limit.floor() as u32)
+    }
+
+    /// 提供商是否已占满自适应并发上限（尚未使用过的提供商视为未满）
+    pub fn is_saturated(&self, api_key: &str) -> bool {
+        self.limits
+            .get(api_key)
+            .is_some_and(|s| s.in_flight as f64 >= s.limit.floor())
+    }
+
+    /// 获取提供商当前占用的并发数
+    pub fn in_flight(&self, api_key: &str) -> Option<u32> {
+        self.limits
+            .get(api_key)
+            .map(|s| s.in_flight)
+    }
+
+    /// 获取提供商距离下次允许减小并发上限的剩余冷却时间（不在冷却期时为空）
+    pub fn decrease_cooldown_remaining(&self, api_key: &str) -> Option<Duration> {
+        self.limits
+            .get(api_key)
+            .and_then(|s| s.last_decrease)
+            .and_then(|t| DECREASE_COOLDOWN.checked_sub(t.elapsed()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    fn decrease(&self, api_key: &str) {
+        if let Some(mut state) = self.limits.get_mut(api_key) {
+            let now = Instant::now();
+            if state.last_decrease.is_some_and(|t| now.duration_since(t) < DECREASE_COOLDOWN) {
+                return;
+            }
+            state.limit = self.clamp(state.limit * DECREASE_FACTOR);
+            state.last_decrease = Some(now);
+            info!("提供商并发上限已调整: api_key={}, limit={:.2}", redact(api_key), state.limit);
+        }
+    }
+
+    fn release(&self, api_key: &str) {
+        if let Some(mut state) = self.limits.get_mut(api_key) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+        self.released.notify_waiters();
+    }
+
+    fn clamp(&self, limit: f64) -> f64 {
+        limit.clamp(self.config.min_limit as f64, self.config.max_limit as f64)
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, SqlitePool};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::services::provider_pool::ProviderPoolState;
+
+/// 合同即将到期或已到期的提供商
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ExpiringProvider {
+    /// 提供商ID
+    pub provider_id: String,
+    /// 提供商名称
+    pub name: String,
+    /// 提供商类型
+    pub provider_type: String,
+    /// 当前状态（到期后为 Inactive）
+    pub status: String,
+    /// 供应商账号邮箱
+    pub vendor_account_email: Option<String>,
+    /// 购买的额度
+    pub purchased_quota: Option<f64>,
+    /// 当前余额
+    pub balance: f64,
+    /// 到期时间
+    pub expires_at: DateTime<Utc>,
+    /// 剩余天数（已到期为负数）
+    #[sqlx(skip)]
+    pub days_remaining: f64,
+}
+
+/// 查询在 within_days 天内到期（含已到期）的提供商，按到期时间排序
+pub async fn find_expiring(db: &SqlitePool, within_days: i64) -> Result<Vec<ExpiringProvider>, sqlx::Error> {
+    let now = Utc::now();
+    let mut providers = sqlx::query_as::<_, ExpiringProvider>(
+        r#"
+        SELECT
+            id AS provider_id, name, provider_type, status, vendor_account_email,
+            purchased_quota, COALESCE(balance, 0.0) AS balance, expires_at
+        FROM api_providers
+        WHERE expires_at IS NOT NULL AND expires_at <= ?
+        ORDER BY expires_at
+        "#
+    )
+    .bind(now + Duration::days(within_days))
+    .fetch_all(db)
+    .await?;
+
+    for provider in &mut providers {
+        provider.days_remaining = (provider.expires_at - now).num_seconds() as f64 / 86400.0;
+    }
+    Ok(providers)
+}
+
+/// 停用已到期的提供商并移出提供请求服务的代理池，对即将到期的提供商记录提醒日志；
+/// 数据库中已是 Inactive 的到期提供商也会从池中移除，保证池中不残留到期的提供商
+pub async fn check_expiry(db: &SqlitePool, provider_pool: &Arc<RwLock<ProviderPoolState>>, alert_days: i64) {
+    let providers = match find_expiring(db, alert_days).await {
+        Ok(providers) => providers,
+        Err(e) => {
+            error!("查询即将到期的提供商失败: {}", e);
+            return;
+        }
+    };
+
+    for provider in providers {
+        if provider.days_remaining > 0.0 {
+            warn!(
+                target: "provider_expiry",
+                "提供商合同即将到期: name={}, 到期时间={}, 剩余天数={:.1}, 账号={}",
+                provider.name,
+                provider.expires_at,
+                provider.days_remaining,
+                provider.vendor_account_email.as_deref().unwrap_or("-"),
+            );
+            continue;
+        }
+
+        let newly_expired = provider.status != "Inactive";
+        let api_key = if newly_expired {
+            deactivate(db, &provider.provider_id).await
+        } else {
+            api_key_of(db, &provider.provider_id).await
+        };
+        let api_key = match api_key {
+            Ok(api_key) => api_key,
+            Err(e) => {
+                error!("停用已到期的提供商失败: name={}, 错误={}", provider.name, e);
+                continue;
+            }
+        };
+        // 先用读锁确认池中仍有该提供商，避免每次检查都为早已移除的提供商获取写锁
+        let in_pool = provider_pool.read().await.providers().iter().any(|p| p.api_key == api_key);
+        if in_pool {
+            provider_pool.write().await.remove_provider(&api_key);
+        }
+        if newly_expired {
+            info!("提供商合同已到期，已停用: name={}, 到期时间={}", provider.name, provider.expires_at);
+        }
+    }
+}
+
+// 查询提供商的 api_key
+async fn api_key_of(db: &SqlitePool, provider_id: &str) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT api_key FROM api_providers WHERE id = ?")
+        .bind(provider_id)
+        .fetch_one(db)
+        .await
+}
+
+// 将提供商标记为 Inactive，返回其 api_key
+async fn deactivate(db: &SqlitePool, provider_id: &str) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        "UPDATE api_providers SET status = 'Inactive', updated_at = ? WHERE id = ? RETURNING api_key"
+    )
+    .bind(Utc::now())
+    .bind(provider_id)
+    .fetch_one(db)
+    .await
+}
@@ -0,0 +1,89 @@
+use axum::http::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+use crate::config::ConversationAffinityConfig;
+
+/// 客户端标明会话的请求头，按顺序取第一个非空值
+pub const CONVERSATION_HEADERS: [&str; 2] = ["x-conversation-id", "x-session-id"];
+// 会话标识的最大长度，过长的值不参与亲和
+const MAX_CONVERSATION_ID_LEN: usize = 256;
+
+#[derive(Debug)]
+struct AffinityEntry {
+    api_key: String,
+    last_used: Instant,
+}
+
+/// 会话亲和表：记录每个会话最近一次成功服务的提供商，
+/// 同一会话的后续轮次优先使用该提供商，以提高厂商侧提示缓存的命中率并保持回答风格一致。
+/// 会话按调用方（网关密钥、客户端密钥或IP）和模型区分，不同调用方使用相同的会话标识互不影响
+pub struct ConversationAffinity {
+    config: ConversationAffinityConfig,
+    entries: Mutex<HashMap<String, AffinityEntry>>,
+}
+
+impl ConversationAffinity {
+    pub fn new(config: ConversationAffinityConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从请求头得到会话的亲和键，启用 user_field 时退回到请求体中的 user 字段；
+    /// 未启用或请求未携带会话标识时返回空
+    pub fn key(&self, headers: &HeaderMap, user: Option<&str>, caller: &str, model: &str) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let valid = |value: &str| !value.is_empty() && value.len() <= MAX_CONVERSATION_ID_LEN;
+        let conversation = CONVERSATION_HEADERS
+            .iter()
+            .find_map(|name| {
+                headers
+                    .get(*name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::trim)
+                    .filter(|value| valid(value))
+            })
+            .or_else(|| user.filter(|_| self.config.user_field).map(str::trim).filter(|value| valid(value)))?;
+        Some(format!("{}\n{}\n{}", caller, model, conversation))
+    }
+
+    /// 会话之前使用的提供商（api_key），已过期时返回空
+    pub fn provider(&self, key: &str) -> Option<String> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|entry| entry.last_used.elapsed() < self.ttl())
+            .map(|entry| entry.api_key.clone())
+    }
+
+    /// 记录会话本轮成功使用的提供商
+    pub fn remember(&self, key: &str, api_key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(key) && entries.len() >= self.config.max_entries {
+            let ttl = self.ttl();
+            entries.retain(|_, entry| entry.last_used.elapsed() < ttl);
+            // 仍然已满时淘汰最久未使用的会话
+            if entries.len() >= self.config.max_entries {
+                if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone()) {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(
+            key.to_string(),
+            AffinityEntry {
+                api_key: api_key.to_string(),
+                last_used: Instant::now(),
+            },
+        );
+    }
+
+    fn ttl(&self) -> Duration {
+        Duration::from_secs(self.config.ttl_secs)
+    }
+}
@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::config::DatabaseConfig;
+
+// SQLITE_BUSY 重试的初始退避时间
+const BUSY_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// 单类查询的统计
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct QueryStats {
+    /// 执行次数（含重试）
+    pub calls: u64,
+    /// 累计耗时（毫秒）
+    pub total_ms: u64,
+    /// 最大耗时（毫秒）
+    pub max_ms: u64,
+    /// 超过慢查询阈值的次数
+    pub slow_calls: u64,
+    /// 因 SQLITE_BUSY/SQLITE_LOCKED 重试的次数
+    pub busy_retries: u64,
+    /// 重试后仍然失败的次数
+    pub busy_failures: u64,
+}
+
+/// 数据库查询统计快照
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbMetricsSnapshot {
+    /// 慢查询阈值（毫秒）
+    pub slow_query_threshold_ms: u64,
+    /// 按查询标签汇总的统计
+    pub queries: BTreeMap<String, QueryStats>,
+}
+
+/// 热路径数据库查询的计时与锁竞争统计
+pub struct DbMetrics {
+    slow_query_threshold: Duration,
+    busy_retry_attempts: u32,
+    stats: Mutex<BTreeMap<&'static str, QueryStats>>,
+    // 最近一次因数据库只读导致写入失败的时间
+    last_read_only: Mutex<Option<Instant>>,
+}
+
+impl DbMetrics {
+    pub fn new(config: &DatabaseConfig) -> Self {
+        Self {
+            slow_query_threshold: Duration::from_millis(config.slow_query_threshold_ms),
+            busy_retry_attempts: config.busy_retry_attempts,
+            stats: Mutex::new(BTreeMap::new()),
+            last_read_only: Mutex::new(None),
+        }
+    }
+
+    /// 执行查询并计时，超过阈值时记录慢查询日志；遇到数据库锁竞争时退避重试
+    pub async fn run<T, F, Fut>(&self, label: &'static str, mut query: F) -> Result<T, sqlx::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let start = Instant::now();
+            let result = query().await;
+            let elapsed = start.elapsed();
+            let busy = result.as_ref().err().is_some_and(is_busy);
+            let retry = busy && attempt < self.busy_retry_attempts;
+
+            let slow = elapsed >= self.slow_query_threshold;
+            if slow {
+                warn!(
+                    target: "db_slow_query",
+                    "慢查询: {}, 耗时: {}ms, 阈值: {}ms",
+                    label, elapsed.as_millis(), self.slow_query_threshold.as_millis()
+                );
+            }
+            self.record(label, elapsed, slow, retry, busy && !retry);
+            if result.as_ref().err().is_some_and(is_read_only) {
+                warn!(target: "db_read_only", "数据库只读，写入失败: {}", label);
+                *self.last_read_only.lock().unwrap() = Some(Instant::now());
+            }
+
+            if !retry {
+                return result;
+            }
+
+            attempt += 1;
+            warn!(
+                target: "db_lock_contention",
+                "数据库锁竞争，重试查询: {}, 第 {}/{} 次",
+                label, attempt, self.busy_retry_attempts
+            );
+            tokio::time::sleep(BUSY_RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    /// 获取统计快照
+    pub fn snapshot(&self) -> DbMetricsSnapshot {
+        let stats = self.stats.lock().unwrap();
+        DbMetricsSnapshot {
+            slow_query_threshold_ms: self.slow_query_threshold.as_millis() as u64,
+            queries: stats.iter().map(|(label, stats)| (label.to_string(), stats.clone())).collect(),
+        }
+    }
+
+    /// 最近 window 时长内是否出现过数据库只读错误
+    pub fn read_only_within(&self, window: Duration) -> bool {
+        self.last_read_only
+            .lock()
+            .unwrap()
+            .is_some_and(|at| at.elapsed() < window)
+    }
+
+    fn record(&self, label: &'static str, elapsed: Duration, slow: bool, retried: bool, busy_failure: bool) {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(label).or_default();
+        entry.calls += 1;
+        entry.total_ms += elapsed_ms;
+        entry.max_ms = entry.max_ms.max(elapsed_ms);
+        entry.slow_calls += slow as u64;
+        entry.busy_retries += retried as u64;
+        entry.busy_failures += busy_failure as u64;
+    }
+}
+
+// SQLITE_BUSY(5) 或 SQLITE_LOCKED(6)，包括扩展错误码
+fn is_busy(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| matches!(code & 0xff, 5 | 6)),
+        _ => false,
+    }
+}
+
+// SQLITE_READONLY(8)，包括扩展错误码
+fn is_read_only(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| code & 0xff == 8),
+        _ => false,
+    }
+}
@@ -0,0 +1,55 @@
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::config::DegradationConfig;
+use crate::services::db_metrics::DbMetrics;
+use crate::services::provider_stats::ProviderStatsSnapshot;
+
+/// 可用提供商不足
+pub const PROVIDERS_UNHEALTHY: &str = "providers_unhealthy";
+/// 数据库只读，使用记录和计费可能无法写入
+pub const DATABASE_READ_ONLY: &str = "database_read_only";
+
+/// 网关降级提示
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DegradationAdvisory {
+    /// 降级原因代码（providers_unhealthy / database_read_only）
+    pub reasons: Vec<&'static str>,
+    /// 可展示给用户的提示
+    pub message: String,
+}
+
+/// 根据提供商统计快照和数据库状态判断网关是否处于降级状态
+pub fn assess(
+    config: &DegradationConfig,
+    stats: &ProviderStatsSnapshot,
+    db_metrics: &DbMetrics,
+) -> Option<DegradationAdvisory> {
+    if !config.enabled {
+        return None;
+    }
+
+    let mut reasons = Vec::new();
+    let mut notices = Vec::new();
+
+    let total = stats.providers.len();
+    let available = stats.providers.iter().filter(|p| p.available).count();
+    if total > 0 && (available as f64) < total as f64 * config.min_healthy_ratio {
+        reasons.push(PROVIDERS_UNHEALTHY);
+        notices.push(format!("可用提供商不足（{}/{}），响应可能变慢或失败", available, total));
+    }
+
+    if db_metrics.read_only_within(Duration::from_secs(config.read_only_window_secs)) {
+        reasons.push(DATABASE_READ_ONLY);
+        notices.push("数据库只读，使用记录可能无法保存".to_string());
+    }
+
+    if reasons.is_empty() {
+        return None;
+    }
+    Some(DegradationAdvisory {
+        reasons,
+        message: format!("服务处于降级状态：{}", notices.join("；")),
+    })
+}
@@ -0,0 +1,271 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use utoipa::ToSchema;
+
+use crate::config::{AppConfig, DiagnosticsConfig};
+use crate::database::MIGRATOR;
+use crate::services::provider_stats::ProviderStatsSnapshot;
+use crate::services::upstream_client::create_http_client;
+
+// 时钟参考地址的请求超时(秒)
+const CLOCK_CHECK_TIMEOUT_SECS: u64 = 5;
+// 就绪检查中数据库查询的超时(秒)，应小于探针的超时时间
+const READINESS_DB_TIMEOUT_SECS: u64 = 2;
+
+/// 单项检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// 通过
+    Pass,
+    /// 通过但接近阈值
+    Warn,
+    /// 失败
+    Fail,
+    /// 未配置，跳过
+    Skip,
+}
+
+/// 单项检查
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiagnosticCheck {
+    /// 检查名称（database_writable / migrations_current / providers_healthy / disk_space / clock_skew，
+    /// 就绪检查为 database_connected / active_providers）
+    pub name: &'static str,
+    /// 检查结果
+    pub status: CheckStatus,
+    /// 结果说明
+    pub message: String,
+}
+
+/// 自检诊断报告
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiagnosticsReport {
+    /// 总体结果：任一检查失败时为 fail，否则有警告时为 warn
+    pub status: CheckStatus,
+    /// 检查时间
+    pub checked_at: DateTime<Utc>,
+    /// 各项检查
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+impl DiagnosticsReport {
+    fn new(checks: Vec<DiagnosticCheck>) -> Self {
+        let status = if checks.iter().any(|c| c.status == CheckStatus::Fail) {
+            CheckStatus::Fail
+        } else if checks.iter().any(|c| c.status == CheckStatus::Warn) {
+            CheckStatus::Warn
+        } else {
+            CheckStatus::Pass
+        };
+        Self {
+            status,
+            checked_at: Utc::now(),
+            checks,
+        }
+    }
+
+    /// 是否有检查失败
+    pub fn failed(&self) -> bool {
+        self.status == CheckStatus::Fail
+    }
+}
+
+/// 依次执行全部深度检查
+pub async fn run(db: &SqlitePool, stats: &ProviderStatsSnapshot, config: &AppConfig) -> DiagnosticsReport {
+    let checks = vec![
+        check_database_writable(db).await,
+        check_migrations(db).await,
+        check_providers(db, stats).await,
+        check_disk_space(&config.database.path, &config.diagnostics),
+        check_clock_skew(config).await,
+    ];
+    DiagnosticsReport::new(checks)
+}
+
+/// 存活检查：进程能处理请求即通过，不检查外部依赖，避免数据库短暂不可用时被编排系统重启
+pub fn liveness() -> DiagnosticsReport {
+    DiagnosticsReport::new(Vec::new())
+}
+
+/// 就绪检查：数据库可连接且至少有一个 Active 状态的提供商，适合频繁执行的容器探针
+pub async fn readiness(db: &SqlitePool) -> DiagnosticsReport {
+    DiagnosticsReport::new(vec![check_database_connected(db).await, check_active_providers(db).await])
+}
+
+fn check(name: &'static str, status: CheckStatus, message: impl Into<String>) -> DiagnosticCheck {
+    DiagnosticCheck { name, status, message: message.into() }
+}
+
+// 在超时时间内执行一次简单查询
+async fn check_database_connected(db: &SqlitePool) -> DiagnosticCheck {
+    const NAME: &str = "database_connected";
+    let query = sqlx::query_scalar::<_, i64>("SELECT 1").fetch_one(db);
+    match tokio::time::timeout(std::time::Duration::from_secs(READINESS_DB_TIMEOUT_SECS), query).await {
+        Ok(Ok(_)) => check(NAME, CheckStatus::Pass, "数据库连接正常"),
+        Ok(Err(e)) => check(NAME, CheckStatus::Fail, format!("数据库查询失败: {}", e)),
+        Err(_) => check(NAME, CheckStatus::Fail, format!("数据库查询超过 {} 秒", READINESS_DB_TIMEOUT_SECS)),
+    }
+}
+
+// 至少有一个 Active 状态的提供商
+async fn check_active_providers(db: &SqlitePool) -> DiagnosticCheck {
+    const NAME: &str = "active_providers";
+    match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM api_providers WHERE status = 'Active'")
+        .fetch_one(db)
+        .await
+    {
+        Ok(0) => check(NAME, CheckStatus::Fail, "没有 Active 状态的提供商"),
+        Ok(count) => check(NAME, CheckStatus::Pass, format!("{} 个 Active 状态的提供商", count)),
+        Err(e) => check(NAME, CheckStatus::Fail, format!("读取提供商失败: {}", e)),
+    }
+}
+
+// 在回滚的事务中建表，验证数据库可获取写锁并写入
+async fn check_database_writable(db: &SqlitePool) -> DiagnosticCheck {
+    const NAME: &str = "database_writable";
+    let result = async {
+        let mut tx = db.begin().await?;
+        sqlx::query("CREATE TABLE _diagnostics_probe (id INTEGER)")
+            .execute(&mut *tx)
+            .await?;
+        tx.rollback().await
+    }
+    .await;
+    match result {
+        Ok(()) => check(NAME, CheckStatus::Pass, "数据库可写"),
+        Err(e) => check(NAME, CheckStatus::Fail, format!("数据库写入失败: {}", e)),
+    }
+}
+
+// 已执行的迁移与编译时嵌入的迁移一致
+async fn check_migrations(db: &SqlitePool) -> DiagnosticCheck {
+    const NAME: &str = "migrations_current";
+    let applied = match sqlx::query_as::<_, (i64, bool)>("SELECT version, success FROM _sqlx_migrations")
+        .fetch_all(db)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => return check(NAME, CheckStatus::Fail, format!("读取迁移记录失败: {}", e)),
+    };
+
+    let failed: Vec<i64> = applied.iter().filter(|(_, success)| !success).map(|(version, _)| *version).collect();
+    if !failed.is_empty() {
+        return check(NAME, CheckStatus::Fail, format!("迁移执行失败: {:?}", failed));
+    }
+    let applied: HashSet<i64> = applied.into_iter().map(|(version, _)| version).collect();
+    let pending: Vec<i64> = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration() && !applied.contains(&m.version))
+        .map(|m| m.version)
+        .collect();
+    if pending.is_empty() {
+        check(NAME, CheckStatus::Pass, format!("已执行全部 {} 个迁移", applied.len()))
+    } else {
+        check(NAME, CheckStatus::Fail, format!("有未执行的迁移: {:?}", pending))
+    }
+}
+
+// 数据库中配置的每个模型至少有一个可用的提供商
+async fn check_providers(db: &SqlitePool, stats: &ProviderStatsSnapshot) -> DiagnosticCheck {
+    const NAME: &str = "providers_healthy";
+    let models = match sqlx::query_scalar::<_, String>("SELECT DISTINCT model_name FROM api_providers")
+        .fetch_all(db)
+        .await
+    {
+        Ok(models) => models,
+        Err(e) => return check(NAME, CheckStatus::Fail, format!("读取提供商失败: {}", e)),
+    };
+    if models.is_empty() {
+        return check(NAME, CheckStatus::Warn, "未配置任何提供商");
+    }
+
+    let mut available: BTreeMap<&str, usize> = models.iter().map(|m| (m.as_str(), 0)).collect();
+    for provider in stats.providers.iter().filter(|p| p.available) {
+        if let Some(count) = available.get_mut(provider.model_name.as_str()) {
+            *count += 1;
+        }
+    }
+    let unhealthy: Vec<&str> = available.iter().filter(|(_, count)| **count == 0).map(|(model, _)| *model).collect();
+    if unhealthy.is_empty() {
+        check(NAME, CheckStatus::Pass, format!("{} 个模型均有可用的提供商", models.len()))
+    } else {
+        check(NAME, CheckStatus::Fail, format!("以下模型没有可用的提供商: {}", unhealthy.join(", ")))
+    }
+}
+
+// 数据库所在磁盘的剩余空间，低于阈值两倍时警告
+fn check_disk_space(db_path: &Path, config: &DiagnosticsConfig) -> DiagnosticCheck {
+    const NAME: &str = "disk_space";
+    let dir = db_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let free_mb = match free_disk_bytes(dir) {
+        Ok(bytes) => bytes / (1024 * 1024),
+        Err(e) => return check(NAME, CheckStatus::Fail, format!("读取磁盘空间失败: {}", e)),
+    };
+    let message = format!("剩余 {} MB（最低 {} MB）", free_mb, config.min_free_disk_mb);
+    if free_mb < config.min_free_disk_mb {
+        check(NAME, CheckStatus::Fail, message)
+    } else if free_mb < config.min_free_disk_mb.saturating_mul(2) {
+        check(NAME, CheckStatus::Warn, message)
+    } else {
+        check(NAME, CheckStatus::Pass, message)
+    }
+}
+
+#[cfg(unix)]
+fn free_disk_bytes(dir: &Path) -> std::io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: path 是以NUL结尾的有效字符串，stat 指向可写的 statvfs 结构
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_disk_bytes(_dir: &Path) -> std::io::Result<u64> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "当前平台不支持"))
+}
+
+// 与参考地址响应的 Date 头比对本机时钟（Date 头精度为秒）
+async fn check_clock_skew(config: &AppConfig) -> DiagnosticCheck {
+    const NAME: &str = "clock_skew";
+    let Some(url) = config.diagnostics.clock_reference_url.as_deref() else {
+        return check(NAME, CheckStatus::Skip, "未配置 DIAGNOSTICS_CLOCK_URL");
+    };
+    let client = match create_http_client(config.proxy.enable, &config.proxy.url, CLOCK_CHECK_TIMEOUT_SECS) {
+        Ok(client) => client,
+        Err(e) => return check(NAME, CheckStatus::Fail, format!("创建HTTP客户端失败: {}", e)),
+    };
+    let sent_at = Utc::now();
+    let response = match client.head(url).send().await {
+        Ok(response) => response,
+        Err(e) => return check(NAME, CheckStatus::Fail, format!("请求时钟参考地址失败: {}", e)),
+    };
+    let received_at = Utc::now();
+    let remote = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+    let Some(remote) = remote else {
+        return check(NAME, CheckStatus::Fail, "参考地址的响应没有有效的 Date 头");
+    };
+
+    // 以请求往返的中点作为本机时间
+    let local = sent_at + (received_at - sent_at) / 2;
+    let skew_ms = (local - remote.with_timezone(&Utc)).num_milliseconds();
+    let message = format!("本机时钟偏差 {} 毫秒（允许 {} 秒）", skew_ms, config.diagnostics.max_clock_skew_secs);
+    if skew_ms.unsigned_abs() > config.diagnostics.max_clock_skew_secs * 1000 {
+        check(NAME, CheckStatus::Fail, message)
+    } else {
+        check(NAME, CheckStatus::Pass, message)
+    }
+}
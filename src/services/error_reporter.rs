@@ -0,0 +1,206 @@
+use chrono::Utc;
+use reqwest::{Client, Url};
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::AppConfig;
+use crate::services::provider_pool::ProviderInfo;
+use crate::services::upstream_client;
+use crate::utils::redact::redact;
+
+// 待发送事件队列容量，发送跟不上时丢弃新事件
+const QUEUE_CAPACITY: usize = 1000;
+// 发送事件的超时(秒)
+const SEND_TIMEOUT_SECS: u64 = 10;
+// 事件消息的最大字符数
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+static REPORTER: OnceLock<ErrorReporter> = OnceLock::new();
+
+/// 错误事件的级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Warning,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Fatal => "fatal",
+        }
+    }
+}
+
+/// 上报的错误事件；处理器只需构造事件并调用 capture，未配置 DSN 时什么也不做
+#[derive(Debug, Clone)]
+pub struct ErrorEvent {
+    level: Level,
+    message: String,
+    tags: Map<String, Value>,
+    extra: Map<String, Value>,
+}
+
+impl ErrorEvent {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            message: message.into().chars().take(MAX_MESSAGE_CHARS).collect(),
+            tags: Map::new(),
+            extra: Map::new(),
+        }
+    }
+
+    /// 可用于筛选和聚合的标签
+    pub fn tag(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.tags.insert(key.to_string(), Value::String(value.into()));
+        self
+    }
+
+    /// 附加信息
+    pub fn extra(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.extra.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// 带提供商和模型上下文的事件；标签中的提供商密钥已脱敏
+    pub fn for_provider(level: Level, provider: &ProviderInfo, message: impl Into<String>) -> Self {
+        Self::new(level, message)
+            .tag("provider_type", provider.provider_type.clone())
+            .tag("model", provider.model_name.clone())
+            .tag("provider_key", redact(&provider.api_key))
+            .extra("base_url", provider.base_url.clone())
+    }
+}
+
+// 从 DSN 解析出的上报地址（兼容 Sentry 的 store 接口）
+struct Dsn {
+    store_url: Url,
+    public_key: String,
+}
+
+impl Dsn {
+    // DSN 格式：{协议}://{公钥}@{主机}[:{端口}]/[{路径}/]{项目ID}
+    fn parse(dsn: &str) -> Result<Self, String> {
+        let url = Url::parse(dsn).map_err(|e| format!("DSN 格式错误: {}", e))?;
+        let public_key = url.username().to_string();
+        if public_key.is_empty() {
+            return Err("DSN 缺少公钥".to_string());
+        }
+        let path = url.path().trim_end_matches('/');
+        let (prefix, project_id) = path.rsplit_once('/').ok_or("DSN 缺少项目ID")?;
+        if project_id.is_empty() {
+            return Err("DSN 缺少项目ID".to_string());
+        }
+        let mut store_url = url.clone();
+        let _ = store_url.set_username("");
+        let _ = store_url.set_password(None);
+        store_url.set_path(&format!("{}/api/{}/store/", prefix, project_id));
+        Ok(Self { store_url, public_key })
+    }
+}
+
+/// 错误上报服务：事件进入队列，由后台任务发送到兼容 Sentry 的 DSN，上报失败不影响请求处理
+pub struct ErrorReporter {
+    sender: mpsc::Sender<ErrorEvent>,
+}
+
+/// 按配置启动错误上报并安装 panic 钩子，启动时在运行时内调用一次；未配置 DSN 时不启用
+pub fn configure(config: &AppConfig) {
+    let Some(dsn) = config.error_reporting.dsn.as_deref() else {
+        return;
+    };
+    let dsn = match Dsn::parse(dsn) {
+        Ok(dsn) => dsn,
+        Err(e) => {
+            warn!("错误上报未启用: {}", e);
+            return;
+        }
+    };
+    let client = match upstream_client::create_http_client(config.proxy.enable, &config.proxy.url, SEND_TIMEOUT_SECS) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("错误上报未启用，创建HTTP客户端失败: {}", e);
+            return;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+    if REPORTER.set(ErrorReporter { sender }).is_err() {
+        return;
+    }
+    tokio::spawn(run(client, dsn, config.error_reporting.environment.clone(), receiver));
+    install_panic_hook();
+    info!("已启用错误上报");
+}
+
+/// 上报事件；未启用时忽略，队列已满时丢弃
+pub fn capture(event: ErrorEvent) {
+    let Some(reporter) = REPORTER.get() else {
+        return;
+    };
+    let _ = reporter.sender.try_send(event);
+}
+
+// 保留原有的 panic 输出，同时上报 panic 信息和位置
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic| {
+        let message = panic
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic".to_string());
+        let mut event = ErrorEvent::new(Level::Fatal, format!("panic: {}", message)).tag("kind", "panic");
+        if let Some(location) = panic.location() {
+            event = event.extra("location", format!("{}:{}:{}", location.file(), location.line(), location.column()));
+        }
+        if let Some(name) = std::thread::current().name() {
+            event = event.extra("thread", name);
+        }
+        capture(event);
+        previous(panic);
+    }));
+}
+
+async fn run(client: Client, dsn: Dsn, environment: String, mut receiver: mpsc::Receiver<ErrorEvent>) {
+    let auth = format!(
+        "Sentry sentry_version=7, sentry_client=api-manager/{}, sentry_key={}",
+        env!("CARGO_PKG_VERSION"),
+        dsn.public_key
+    );
+    while let Some(event) = receiver.recv().await {
+        let body = json!({
+            "event_id": uuid::Uuid::new_v4().simple().to_string(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "platform": "other",
+            "logger": "api-manager",
+            "level": event.level.as_str(),
+            "release": format!("api-manager@{}", env!("CARGO_PKG_VERSION")),
+            "environment": environment,
+            "message": { "formatted": event.message },
+            "tags": event.tags,
+            "extra": event.extra,
+        });
+        let result = client
+            .post(dsn.store_url.clone())
+            .header("X-Sentry-Auth", &auth)
+            .json(&body)
+            .send()
+            .await;
+        // 这里的失败只写日志，不再上报
+        match result {
+            Ok(response) if !response.status().is_success() => {
+                warn!("错误上报失败，状态码: {}", response.status());
+            }
+            Err(e) => warn!("错误上报失败: {}", e),
+            Ok(_) => {}
+        }
+    }
+}
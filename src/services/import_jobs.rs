@@ -0,0 +1,112 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+// 已完成的导入任务保留时长，超过后在创建新任务时清理
+const FINISHED_JOB_RETENTION_HOURS: i64 = 24;
+
+/// 导入任务状态
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ToSchema)]
+pub enum ImportJobStatus {
+    /// 正在后台验证
+    Running,
+    /// 全部密钥已验证完成
+    Completed,
+}
+
+/// 单个API密钥的验证结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportKeyResult {
+    /// 提供商ID
+    pub id: String,
+    /// 提供商名称
+    pub name: String,
+    /// API密钥
+    pub api_key: String,
+    /// 验证得到的余额
+    pub balance: Option<f64>,
+    /// 失败原因（如果有）
+    pub error: Option<String>,
+}
+
+/// 异步导入任务进度
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportJob {
+    /// 任务ID
+    pub job_id: String,
+    /// 任务状态
+    pub status: ImportJobStatus,
+    /// 需要验证的密钥总数
+    pub total: usize,
+    /// 验证通过的密钥数
+    pub verified: usize,
+    /// 验证失败的密钥数
+    pub failed: usize,
+    /// 已完成验证的密钥结果
+    pub results: Vec<ImportKeyResult>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 完成时间
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// 异步导入任务登记表（仅保存在内存中）
+#[derive(Debug, Default)]
+pub struct ImportJobRegistry {
+    jobs: Mutex<HashMap<String, ImportJob>>,
+}
+
+impl ImportJobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建新的导入任务，同时清理过期的已完成任务
+    pub async fn create(&self, job_id: String, total: usize) {
+        let now = Utc::now();
+        let mut jobs = self.jobs.lock().await;
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|t| now - t < Duration::hours(FINISHED_JOB_RETENTION_HOURS))
+        });
+        jobs.insert(job_id.clone(), ImportJob {
+            job_id,
+            status: ImportJobStatus::Running,
+            total,
+            verified: 0,
+            failed: 0,
+            results: Vec::new(),
+            created_at: now,
+            finished_at: None,
+        });
+    }
+
+    /// 记录单个密钥的验证结果
+    pub async fn record(&self, job_id: &str, result: ImportKeyResult) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            if result.error.is_some() {
+                job.failed += 1;
+            } else {
+                job.verified += 1;
+            }
+            job.results.push(result);
+        }
+    }
+
+    /// 标记任务完成
+    pub async fn finish(&self, job_id: &str) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            job.status = ImportJobStatus::Completed;
+            job.finished_at = Some(Utc::now());
+        }
+    }
+
+    /// 获取任务进度
+    pub async fn get(&self, job_id: &str) -> Option<ImportJob> {
+        self.jobs.lock().await.get(job_id).cloned()
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::InFlightLimitConfig;
+
+/// 全局在途请求上限：整个网关同时处理的请求数超过上限时直接拒绝，
+/// 避免突发流量耗尽SQLite连接池和内存
+pub struct InFlightLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+    max_in_flight: usize,
+    retry_after_secs: u64,
+    rejected: AtomicU64,
+}
+
+impl InFlightLimiter {
+    pub fn new(config: &InFlightLimitConfig) -> Self {
+        Self {
+            semaphore: (config.max_in_flight > 0).then(|| Arc::new(Semaphore::new(config.max_in_flight))),
+            max_in_flight: config.max_in_flight,
+            retry_after_secs: config.retry_after_secs,
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// 是否启用全局上限
+    pub fn is_enabled(&self) -> bool {
+        self.semaphore.is_some()
+    }
+
+    /// 占用一个在途名额，已满时返回空并计入拒绝数
+    pub fn try_enter(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = self.semaphore.as_ref()?.clone().try_acquire_owned().ok();
+        if permit.is_none() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        permit
+    }
+
+    /// 被拒绝请求的 Retry-After(秒)
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after_secs
+    }
+
+    /// 当前在途请求数
+    pub fn in_flight(&self) -> usize {
+        self.semaphore
+            .as_ref()
+            .map(|semaphore| self.max_in_flight - semaphore.available_permits())
+            .unwrap_or(0)
+    }
+
+    /// 以 Prometheus 文本格式导出在途请求数和拒绝数
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP gateway_in_flight_requests 网关当前正在处理的请求数");
+        let _ = writeln!(out, "# TYPE gateway_in_flight_requests gauge");
+        let _ = writeln!(out, "gateway_in_flight_requests {}", self.in_flight());
+        let _ = writeln!(out, "# HELP gateway_in_flight_rejected_total 超过 MAX_IN_FLIGHT_REQUESTS 被拒绝的请求数");
+        let _ = writeln!(out, "# TYPE gateway_in_flight_rejected_total counter");
+        let _ = writeln!(out, "gateway_in_flight_rejected_total {}", self.rejected.load(Ordering::Relaxed));
+        out
+    }
+}
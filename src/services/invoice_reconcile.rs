@@ -0,0 +1,279 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use utoipa::ToSchema;
+
+/// 供应商账单中的一行用量（按密钥、按天）
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct InvoiceLine {
+    /// 提供商API密钥
+    pub api_key: String,
+    /// 日期（UTC，YYYY-MM-DD）
+    pub date: NaiveDate,
+    /// 输入token数
+    #[serde(default)]
+    pub prompt_tokens: Option<u64>,
+    /// 输出token数
+    #[serde(default)]
+    pub completion_tokens: Option<u64>,
+    /// 总token数（缺省时为输入与输出之和）
+    #[serde(default)]
+    pub total_tokens: Option<u64>,
+    /// 账单金额
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+impl InvoiceLine {
+    fn tokens(&self) -> u64 {
+        self.total_tokens
+            .unwrap_or_else(|| self.prompt_tokens.unwrap_or(0) + self.completion_tokens.unwrap_or(0))
+    }
+}
+
+/// 对账结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceDiscrepancy {
+    /// 一致（在容差范围内）
+    Match,
+    /// 供应商记录的token多于网关：可能有未经网关的流量或供应商多计
+    UntrackedTraffic,
+    /// token一致但供应商金额高于网关按定价计算的成本
+    Overbilled,
+    /// 供应商记录的token少于网关
+    Underreported,
+    /// 网关有使用记录但账单中没有
+    MissingFromInvoice,
+}
+
+/// 单个密钥单日的对账结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct InvoiceDiff {
+    /// 提供商API密钥
+    pub api_key: String,
+    /// 日期（UTC）
+    pub date: NaiveDate,
+    /// 账单中的token数
+    pub vendor_tokens: u64,
+    /// 网关记录的token数
+    pub gateway_tokens: u64,
+    /// token差额（账单减网关）
+    pub token_diff: i64,
+    /// 账单金额
+    pub vendor_cost: Option<f64>,
+    /// 网关按定价计算的成本
+    pub gateway_cost: Option<f64>,
+    /// 对账结果
+    pub status: InvoiceDiscrepancy,
+}
+
+/// 账单对账报告
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InvoiceReconcileReport {
+    /// 报告生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 账单行数
+    pub invoice_lines: usize,
+    /// 相对容差
+    pub tolerance: f64,
+    /// 不一致的密钥日数
+    pub discrepancy_count: usize,
+    /// 按日期、密钥排序的对账结果
+    pub rows: Vec<InvoiceDiff>,
+}
+
+// 网关按密钥、按天汇总的用量
+#[derive(Debug, FromRow)]
+struct GatewayDailyUsage {
+    api_key: String,
+    day: String,
+    total_tokens: i64,
+    cost: Option<f64>,
+}
+
+/// 解析CSV格式的供应商用量导出，首行为表头（列名不区分大小写，支持常见别名）
+pub fn parse_csv(text: &str) -> Result<Vec<InvoiceLine>, String> {
+    let mut rows = text
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty());
+    let header = rows.next().ok_or_else(|| "CSV内容为空".to_string())?;
+    let header: Vec<String> = split_csv_line(header).iter().map(|h| h.trim().to_lowercase()).collect();
+
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let key_col = column(&["api_key", "key"]).ok_or_else(|| "CSV缺少 api_key 列".to_string())?;
+    let date_col = column(&["date", "day"]).ok_or_else(|| "CSV缺少 date 列".to_string())?;
+    let prompt_col = column(&["prompt_tokens", "input_tokens"]);
+    let completion_col = column(&["completion_tokens", "output_tokens"]);
+    let total_col = column(&["total_tokens", "tokens"]);
+    let cost_col = column(&["cost", "amount"]);
+
+    rows.enumerate()
+        .map(|(index, line)| {
+            let fields = split_csv_line(line);
+            let line_no = index + 2;
+            let field = |col: Option<usize>| {
+                col.and_then(|c| fields.get(c)).map(|f| f.trim()).filter(|f| !f.is_empty())
+            };
+            let number = |col: Option<usize>| -> Result<Option<u64>, String> {
+                field(col)
+                    .map(|f| f.parse::<u64>().map_err(|e| format!("第 {} 行数值无效 '{}': {}", line_no, f, e)))
+                    .transpose()
+            };
+
+            let api_key = field(Some(key_col))
+                .ok_or_else(|| format!("第 {} 行缺少 api_key", line_no))?
+                .to_string();
+            let date = field(Some(date_col))
+                .ok_or_else(|| format!("第 {} 行缺少 date", line_no))?;
+            // 兼容带时间的日期（如 2024-01-01T00:00:00Z）
+            let date = NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d")
+                .map_err(|e| format!("第 {} 行日期无效 '{}': {}", line_no, date, e))?;
+            let cost = field(cost_col)
+                .map(|f| f.parse::<f64>().map_err(|e| format!("第 {} 行金额无效 '{}': {}", line_no, f, e)))
+                .transpose()?;
+
+            Ok(InvoiceLine {
+                api_key,
+                date,
+                prompt_tokens: number(prompt_col)?,
+                completion_tokens: number(completion_col)?,
+                total_tokens: number(total_col)?,
+                cost,
+            })
+        })
+        .collect()
+}
+
+// 拆分一行CSV，支持双引号包裹的字段和 "" 转义
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// 将账单用量与网关记录按密钥、按天比对（只比对账单中出现的密钥和日期范围，不含自带密钥的请求）
+pub async fn reconcile_invoice(
+    db: &SqlitePool,
+    lines: &[InvoiceLine],
+    tolerance: f64,
+) -> Result<InvoiceReconcileReport, sqlx::Error> {
+    // 账单中同一密钥同一天可能有多行（如按模型拆分），先合并
+    let mut vendor: BTreeMap<(NaiveDate, String), (u64, Option<f64>)> = BTreeMap::new();
+    for line in lines {
+        let entry = vendor.entry((line.date, line.api_key.clone())).or_insert((0, None));
+        entry.0 += line.tokens();
+        if let Some(cost) = line.cost {
+            entry.1 = Some(entry.1.unwrap_or(0.0) + cost);
+        }
+    }
+
+    let (first, last) = match (vendor.keys().next(), vendor.keys().next_back()) {
+        (Some((first, _)), Some((last, _))) => (*first, *last),
+        _ => {
+            return Ok(InvoiceReconcileReport {
+                generated_at: Utc::now(),
+                invoice_lines: 0,
+                tolerance,
+                discrepancy_count: 0,
+                rows: Vec::new(),
+            })
+        }
+    };
+    let keys: HashSet<&str> = lines.iter().map(|line| line.api_key.as_str()).collect();
+
+    let gateway = sqlx::query_as::<_, GatewayDailyUsage>(
+        r#"
+        SELECT
+            provider_api_key AS api_key,
+            date(request_time) AS day,
+            SUM(total_tokens) AS total_tokens,
+            SUM(cost) AS cost
+        FROM api_usage
+        WHERE own_key_id IS NULL
+          AND date(request_time) BETWEEN ? AND ?
+        GROUP BY provider_api_key, date(request_time)
+        "#
+    )
+    .bind(first.to_string())
+    .bind(last.to_string())
+    .fetch_all(db)
+    .await?;
+
+    let mut gateway_usage: BTreeMap<(NaiveDate, String), (u64, Option<f64>)> = BTreeMap::new();
+    for usage in gateway {
+        if !keys.contains(usage.api_key.as_str()) {
+            continue;
+        }
+        if let Ok(day) = NaiveDate::parse_from_str(&usage.day, "%Y-%m-%d") {
+            gateway_usage.insert((day, usage.api_key), (usage.total_tokens.max(0) as u64, usage.cost));
+        }
+    }
+
+    let all_keys: BTreeSet<_> = vendor.keys().chain(gateway_usage.keys()).cloned().collect();
+    let rows: Vec<InvoiceDiff> = all_keys
+        .into_iter()
+        .map(|key| {
+            let vendor_entry = vendor.get(&key);
+            let (gateway_tokens, gateway_cost) = gateway_usage.get(&key).copied().unwrap_or((0, None));
+            let (vendor_tokens, vendor_cost) = vendor_entry.copied().unwrap_or((0, None));
+
+            let status = if vendor_entry.is_none() {
+                InvoiceDiscrepancy::MissingFromInvoice
+            } else if !within(vendor_tokens as f64, gateway_tokens as f64, tolerance) {
+                if vendor_tokens > gateway_tokens {
+                    InvoiceDiscrepancy::UntrackedTraffic
+                } else {
+                    InvoiceDiscrepancy::Underreported
+                }
+            } else {
+                match (vendor_cost, gateway_cost) {
+                    (Some(v), Some(g)) if v > g && !within(v, g, tolerance) => InvoiceDiscrepancy::Overbilled,
+                    _ => InvoiceDiscrepancy::Match,
+                }
+            };
+
+            let (date, api_key) = key;
+            InvoiceDiff {
+                api_key,
+                date,
+                vendor_tokens,
+                gateway_tokens,
+                token_diff: vendor_tokens as i64 - gateway_tokens as i64,
+                vendor_cost,
+                gateway_cost,
+                status,
+            }
+        })
+        .collect();
+
+    Ok(InvoiceReconcileReport {
+        generated_at: Utc::now(),
+        invoice_lines: lines.len(),
+        tolerance,
+        discrepancy_count: rows.iter().filter(|row| row.status != InvoiceDiscrepancy::Match).count(),
+        rows,
+    })
+}
+
+// 两个值的差额是否在相对容差范围内
+fn within(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= a.max(b) * tolerance
+}
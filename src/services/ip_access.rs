@@ -0,0 +1,207 @@
+use arc_swap::ArcSwap;
+use std::fmt;
+use std::net::IpAddr;
+use tracing::{info, warn};
+
+use crate::config::IpAccessConfig;
+use crate::models::ip_access_rule::{IpAccessAction, IpAccessRule};
+
+/// CIDR 网段（不带前缀长度的单个IP视为 /32 或 /128）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    // 已按前缀长度清零主机位
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| format!("无效的IP地址: {}", value))?
+            .to_canonical();
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("无效的前缀长度: {}", value))?,
+            None => max_prefix,
+        };
+        Ok(Self { addr: mask(addr, prefix), prefix })
+    }
+
+    /// 网段是否包含该IP（IPv4映射的IPv6地址按IPv4匹配）
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        ip.is_ipv4() == self.addr.is_ipv4() && mask(ip, self.prefix) == self.addr
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+fn mask(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => {
+            let bits = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & bits).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & bits).into())
+        }
+    }
+}
+
+/// 生效的访问规则：命中拒绝规则的IP总是被拒绝；存在允许规则时只放行命中允许规则的IP
+#[derive(Debug, Default)]
+pub struct IpAccessRules {
+    allow: Vec<IpNetwork>,
+    deny: Vec<IpNetwork>,
+}
+
+impl IpAccessRules {
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        if self.deny.iter().any(|net| net.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(ip))
+    }
+}
+
+/// 客户端IP访问控制：环境变量中的规则在启动时确定，数据库中的规则可在运行时增删，
+/// 修改后调用 reload 即时生效，请求路径上只读取快照
+pub struct IpAccessList {
+    config_allow: Vec<IpNetwork>,
+    config_deny: Vec<IpNetwork>,
+    rules: ArcSwap<IpAccessRules>,
+}
+
+impl IpAccessList {
+    pub fn new(config: &IpAccessConfig) -> Self {
+        let config_allow = parse_config("IP_ALLOWLIST", &config.allowlist);
+        let config_deny = parse_config("IP_DENYLIST", &config.denylist);
+        let rules = IpAccessRules { allow: config_allow.clone(), deny: config_deny.clone() };
+        Self { config_allow, config_deny, rules: ArcSwap::from_pointee(rules) }
+    }
+
+    /// 客户端IP是否允许访问
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        self.rules.load().permits(ip)
+    }
+
+    /// 合并环境变量和给定的数据库规则，数据库中无法解析的规则被忽略
+    pub fn compose(&self, rules: &[IpAccessRule]) -> IpAccessRules {
+        let mut composed = IpAccessRules { allow: self.config_allow.clone(), deny: self.config_deny.clone() };
+        for rule in rules {
+            let net = match IpNetwork::parse(&rule.cidr) {
+                Ok(net) => net,
+                Err(e) => {
+                    warn!("忽略无效的IP访问规则: id={}, {}", rule.id, e);
+                    continue;
+                }
+            };
+            match rule.action() {
+                IpAccessAction::Allow => composed.allow.push(net),
+                IpAccessAction::Deny => composed.deny.push(net),
+            }
+        }
+        composed
+    }
+
+    /// 从数据库重新加载运行时规则
+    pub async fn reload(&self, db: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+        let rules = IpAccessRule::list(db).await?;
+        let composed = self.compose(&rules);
+        info!("IP访问规则已加载: 允许={}, 拒绝={}", composed.allow.len(), composed.deny.len());
+        self.rules.store(composed.into());
+        Ok(())
+    }
+
+    /// 环境变量中配置的允许网段
+    pub fn config_allow(&self) -> Vec<String> {
+        self.config_allow.iter().map(ToString::to_string).collect()
+    }
+
+    /// 环境变量中配置的拒绝网段
+    pub fn config_deny(&self) -> Vec<String> {
+        self.config_deny.iter().map(ToString::to_string).collect()
+    }
+}
+
+fn parse_config(name: &str, entries: &[String]) -> Vec<IpNetwork> {
+    entries
+        .iter()
+        .filter_map(|entry| match IpNetwork::parse(entry) {
+            Ok(net) => Some(net),
+            Err(e) => {
+                warn!("忽略 {} 中的无效网段: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn net(value: &str) -> IpNetwork {
+        IpNetwork::parse(value).unwrap()
+    }
+
+    #[test]
+    fn parse_clears_host_bits_and_defaults_prefix() {
+        assert_eq!(net("10.1.2.3/8").to_string(), "10.0.0.0/8");
+        assert_eq!(net(" 192.168.1.10 ").to_string(), "192.168.1.10/32");
+        assert_eq!(net("2001:db8::1/32").to_string(), "2001:db8::/32");
+        assert_eq!(net("::1").to_string(), "::1/128");
+    }
+
+    #[test]
+    fn parse_rejects_invalid_input() {
+        assert!(IpNetwork::parse("10.0.0.0/33").is_err());
+        assert!(IpNetwork::parse("2001:db8::/129").is_err());
+        assert!(IpNetwork::parse("10.0.0.0/x").is_err());
+        assert!(IpNetwork::parse("not-an-ip").is_err());
+    }
+
+    #[test]
+    fn contains_matches_prefix_boundaries() {
+        let private = net("172.16.0.0/12");
+        assert!(private.contains(ip("172.16.0.1")));
+        assert!(private.contains(ip("172.31.255.255")));
+        assert!(!private.contains(ip("172.32.0.0")));
+        assert!(net("0.0.0.0/0").contains(ip("8.8.8.8")));
+        assert!(!net("0.0.0.0/0").contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_networks() {
+        assert!(net("10.0.0.0/8").contains(ip("::ffff:10.1.2.3")));
+        assert!(net("::ffff:10.0.0.0/8").contains(ip("10.1.2.3")));
+    }
+
+    #[test]
+    fn deny_rules_take_precedence_over_allow_rules() {
+        let rules = IpAccessRules { allow: vec![net("10.0.0.0/8")], deny: vec![net("10.0.0.5")] };
+        assert!(rules.permits(ip("10.0.0.4")));
+        assert!(!rules.permits(ip("10.0.0.5")));
+        assert!(!rules.permits(ip("192.168.0.1")));
+        assert!(IpAccessRules::default().permits(ip("192.168.0.1")));
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::models::ApiUsage;
+
+// 未使用网关密钥的请求和超出前N名的密钥使用的标签值
+const NO_KEY_LABEL: &str = "none";
+const OTHER_LABEL: &str = "other";
+
+/// 单个网关密钥的累计用量
+#[derive(Debug, Clone, Copy, Default)]
+struct KeyCounters {
+    requests: u64,
+    errors: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    cost: f64,
+}
+
+impl KeyCounters {
+    fn add(&mut self, other: &KeyCounters) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+    }
+}
+
+// 导出的指标：名称、说明、取值
+type MetricDef = (&'static str, &'static str, fn(&KeyCounters) -> String);
+
+const METRICS: [MetricDef; 5] = [
+    ("gateway_key_requests_total", "网关密钥的请求次数", |c| c.requests.to_string()),
+    ("gateway_key_errors_total", "网关密钥的失败请求次数", |c| c.errors.to_string()),
+    ("gateway_key_prompt_tokens_total", "网关密钥的输入token数", |c| c.prompt_tokens.to_string()),
+    ("gateway_key_completion_tokens_total", "网关密钥的输出token数", |c| c.completion_tokens.to_string()),
+    ("gateway_key_cost_total", "网关密钥按定价计算的累计成本", |c| c.cost.to_string()),
+];
+
+#[derive(Default)]
+struct State {
+    counters: HashMap<String, KeyCounters>,
+    // 已单独导出的密钥：一旦导出就保持单独导出，保证各序列（包括 other）单调递增
+    exported: BTreeSet<String>,
+}
+
+/// 按网关密钥统计的用量计数器，以 Prometheus 文本格式导出。
+/// 为控制标签基数，只有用量最大的前 top_n 个密钥单独导出，其余密钥合并为 key_id="other"；
+/// 名额分配后不再回收，进程重启后重新分配
+pub struct KeyUsageMetrics {
+    top_n: usize,
+    state: Mutex<State>,
+}
+
+impl KeyUsageMetrics {
+    pub fn new(top_n: usize) -> Self {
+        Self {
+            top_n,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// 计入一条已写入的使用记录
+    pub fn observe(&self, usage: &ApiUsage) {
+        let key = usage.gateway_key_id.as_deref().unwrap_or(NO_KEY_LABEL);
+        let mut state = self.state.lock().unwrap();
+        let counters = state.counters.entry(key.to_string()).or_default();
+        counters.requests += 1;
+        if !matches!(usage.status.as_str(), "Success" | "PartialSuccess") {
+            counters.errors += 1;
+        }
+        counters.prompt_tokens += usage.prompt_tokens.max(0) as u64;
+        counters.completion_tokens += usage.completion_tokens.max(0) as u64;
+        counters.cost += usage.cost.unwrap_or(0.0);
+    }
+
+    /// 计入使用记录写入后才算出的成本
+    pub fn observe_cost(&self, gateway_key_id: Option<&str>, cost: f64) {
+        let key = gateway_key_id.unwrap_or(NO_KEY_LABEL);
+        let mut state = self.state.lock().unwrap();
+        state.counters.entry(key.to_string()).or_default().cost += cost;
+    }
+
+    /// 以 Prometheus 文本格式导出，names 为密钥ID到名称的映射（用于 key_name 标签）
+    pub fn render(&self, names: &HashMap<String, String>) -> String {
+        let series = self.series();
+        let mut out = String::new();
+        for (name, help, value) in METRICS {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            for (key, counters) in &series {
+                let key_name = names.get(key).map(String::as_str).unwrap_or("");
+                let _ = writeln!(
+                    out,
+                    "{}{{key_id=\"{}\",key_name=\"{}\"}} {}",
+                    name,
+                    escape_label(key),
+                    escape_label(key_name),
+                    value(counters)
+                );
+            }
+        }
+        out
+    }
+
+    // 得到导出的序列：前N名密钥各自一条，其余合并为 other
+    fn series(&self) -> Vec<(String, KeyCounters)> {
+        let mut state = self.state.lock().unwrap();
+        let State { counters, exported } = &mut *state;
+
+        // 仍有名额时，按请求次数从高到低为尚未导出的密钥分配名额
+        if exported.len() < self.top_n {
+            let mut candidates: Vec<(&String, &KeyCounters)> = counters
+                .iter()
+                .filter(|(key, _)| !exported.contains(*key))
+                .collect();
+            candidates.sort_by(|a, b| b.1.requests.cmp(&a.1.requests).then(a.0.cmp(b.0)));
+            let free = self.top_n - exported.len();
+            for (key, _) in candidates.into_iter().take(free) {
+                exported.insert(key.clone());
+            }
+        }
+
+        let mut series = Vec::new();
+        let mut other = KeyCounters::default();
+        let mut has_other = false;
+        for (key, key_counters) in counters.iter() {
+            if exported.contains(key) {
+                series.push((key.clone(), *key_counters));
+            } else {
+                other.add(key_counters);
+                has_other = true;
+            }
+        }
+        series.sort_by(|a, b| a.0.cmp(&b.0));
+        if has_other {
+            series.push((OTHER_LABEL.to_string(), other));
+        }
+        series
+    }
+}
+
+// 转义标签值中的反斜杠、双引号和换行
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
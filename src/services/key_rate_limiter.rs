@@ -0,0 +1,201 @@
+use dashmap::DashMap;
+use tokio::time::Instant;
+
+// 令牌桶：容量为每分钟上限，按 上限/60 每秒匀速恢复
+#[derive(Debug)]
+struct TokenBucket {
+    limit: u64,
+    available: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            available: limit as f64,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.available = (self.available + elapsed * self.rate()).min(self.limit as f64);
+        self.updated_at = now;
+    }
+
+    fn rate(&self) -> f64 {
+        self.limit as f64 / 60.0
+    }
+
+    // 恢复到 amount 所需的秒数
+    fn secs_until(&self, amount: f64) -> u64 {
+        if self.available >= amount {
+            0
+        } else {
+            ((amount - self.available) / self.rate()).ceil() as u64
+        }
+    }
+
+    fn state(&self) -> BucketState {
+        BucketState {
+            limit: self.limit,
+            remaining: self.available.max(0.0).floor() as u64,
+            reset_secs: self.secs_until(self.limit as f64),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyBuckets {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+}
+
+/// 单个令牌桶的当前状态，用于生成限流响应头
+#[derive(Debug, Clone, Copy)]
+pub struct BucketState {
+    /// 每分钟上限
+    pub limit: u64,
+    /// 剩余额度
+    pub remaining: u64,
+    /// 恢复满额所需秒数
+    pub reset_secs: u64,
+}
+
+/// 限流检查结果
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    /// 是否放行
+    pub allowed: bool,
+    /// 被拒绝时建议的重试等待秒数
+    pub retry_after_secs: u64,
+    /// 请求数令牌桶状态（未限制时为空）
+    pub requests: Option<BucketState>,
+    /// token数令牌桶状态（未限制时为空）
+    pub tokens: Option<BucketState>,
+}
+
+/// 按网关密钥的令牌桶限流器（每分钟请求数和每分钟token数）
+/// 请求数在请求开始时扣减；token数在响应完成后按实际用量扣减，允许透支，
+/// 余额为负时拒绝新请求直到恢复；各密钥的令牌桶分片存放，不同密钥的请求互不阻塞
+#[derive(Debug, Default)]
+pub struct KeyRateLimiter {
+    buckets: DashMap<String, KeyBuckets>,
+}
+
+// 限额为0时不限制；限额变化时重建令牌桶
+fn sync_bucket(bucket: &mut Option<TokenBucket>, limit: u64) {
+    match bucket {
+        _ if limit == 0 => *bucket = None,
+        Some(existing) if existing.limit == limit => existing.refill(),
+        _ => *bucket = Some(TokenBucket::new(limit)),
+    }
+}
+
+impl KeyRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 检查并扣减一次请求；任一令牌桶不足时拒绝且不扣减
+    pub fn check(&self, key_id: &str, requests_per_minute: u64, tokens_per_minute: u64) -> RateLimitDecision {
+        let mut key = self.buckets.entry(key_id.to_string()).or_default();
+        sync_bucket(&mut key.requests, requests_per_minute);
+        sync_bucket(&mut key.tokens, tokens_per_minute);
+
+        let request_wait = key.requests.as_ref().map_or(0, |bucket| bucket.secs_until(1.0));
+        // token桶只要求余额为正，实际用量在响应后扣减
+        let token_wait = key.tokens.as_ref().map_or(0, |bucket| {
+            if bucket.available > 0.0 {
+                0
+            } else {
+                bucket.secs_until(1.0)
+            }
+        });
+
+        let allowed = request_wait == 0 && token_wait == 0;
+        if allowed {
+            if let Some(bucket) = key.requests.as_mut() {
+                bucket.available -= 1.0;
+            }
+        }
+
+        RateLimitDecision {
+            allowed,
+            retry_after_secs: request_wait.max(token_wait),
+            requests: key.requests.as_ref().map(TokenBucket::state),
+            tokens: key.tokens.as_ref().map(TokenBucket::state),
+        }
+    }
+
+    /// 按响应中的实际用量扣减token额度
+    pub fn consume_tokens(&self, key_id: &str, tokens: u64) {
+        if let Some(mut key) = self.buckets.get_mut(key_id) {
+            if let Some(bucket) = key.tokens.as_mut() {
+                bucket.refill();
+                bucket.available -= tokens as f64;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_once_request_bucket_is_empty() {
+        let limiter = KeyRateLimiter::new();
+        assert!(limiter.check("key", 2, 0).allowed);
+        assert!(limiter.check("key", 2, 0).allowed);
+
+        let decision = limiter.check("key", 2, 0);
+        assert!(!decision.allowed);
+        assert!(decision.retry_after_secs > 0);
+        assert_eq!(decision.requests.unwrap().remaining, 0);
+        assert!(decision.tokens.is_none());
+    }
+
+    #[test]
+    fn zero_limits_are_unlimited() {
+        let limiter = KeyRateLimiter::new();
+        for _ in 0..100 {
+            let decision = limiter.check("key", 0, 0);
+            assert!(decision.allowed);
+            assert!(decision.requests.is_none());
+        }
+    }
+
+    #[test]
+    fn token_overdraft_blocks_until_refilled() {
+        let limiter = KeyRateLimiter::new();
+        assert!(limiter.check("key", 0, 60).allowed);
+        limiter.consume_tokens("key", 120);
+
+        let decision = limiter.check("key", 0, 60);
+        assert!(!decision.allowed);
+        assert!(decision.retry_after_secs >= 60);
+        assert_eq!(decision.tokens.unwrap().remaining, 0);
+    }
+
+    #[test]
+    fn limit_change_rebuilds_bucket() {
+        let limiter = KeyRateLimiter::new();
+        assert!(limiter.check("key", 1, 0).allowed);
+        assert!(!limiter.check("key", 1, 0).allowed);
+
+        let decision = limiter.check("key", 5, 0);
+        assert!(decision.allowed);
+        assert_eq!(decision.requests.unwrap().remaining, 4);
+    }
+
+    #[test]
+    fn keys_have_independent_buckets() {
+        let limiter = KeyRateLimiter::new();
+        assert!(limiter.check("a", 1, 0).allowed);
+        assert!(!limiter.check("a", 1, 0).allowed);
+        assert!(limiter.check("b", 1, 0).allowed);
+    }
+}
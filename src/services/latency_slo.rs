@@ -0,0 +1,255 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::config::{LatencySloConfig, SchedulerConfig};
+use crate::models::latency_slo_event::{LatencySloEvent, SHED_DISABLED, SHED_ENABLED};
+use crate::services::scheduler::{self, Scheduler};
+
+// 评估p99的间隔
+const EVALUATE_INTERVAL: Duration = Duration::from_secs(10);
+// 窗口内请求数不足时不判断延迟（视为未超出）
+const MIN_SAMPLES: usize = 20;
+// 窗口内最多保留的样本数，避免高流量时占用过多内存
+const MAX_SAMPLES: usize = 100_000;
+
+/// 请求优先级（由 X-Request-Priority 请求头指定，缺省为 normal）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RequestPriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl RequestPriority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "normal" => Some(Self::Normal),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+}
+
+/// 延迟SLO状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LatencySloStatus {
+    /// 是否启用延迟SLO保护
+    pub enabled: bool,
+    /// 当前是否正在降载
+    pub shedding: bool,
+    /// 开始降载的时间
+    pub shed_since: Option<DateTime<Utc>>,
+    /// 降载时拒绝的最高优先级
+    pub shed_priority: &'static str,
+    /// 本次启动以来因降载拒绝的请求数
+    pub shed_requests: u64,
+    /// 最近一次评估的p99延迟(毫秒)，请求数不足时为空
+    pub p99_ms: Option<u64>,
+    /// p99延迟上限(毫秒)
+    pub threshold_ms: u64,
+    /// 最近一次评估时窗口内的请求数
+    pub sample_count: usize,
+    /// 统计窗口(秒)
+    pub window_secs: u64,
+    /// p99已持续超出上限的秒数
+    pub breaching_secs: Option<u64>,
+    /// 降载期间p99已持续恢复的秒数
+    pub recovering_secs: Option<u64>,
+    /// 最近的降载事件，最新的排在前面
+    pub recent_events: Vec<LatencySloEvent>,
+}
+
+#[derive(Debug, Default)]
+struct SloState {
+    breach_since: Option<Instant>,
+    recovered_since: Option<Instant>,
+    shed_since: Option<DateTime<Utc>>,
+    p99_ms: Option<u64>,
+    sample_count: usize,
+}
+
+/// 网关整体延迟SLO保护
+/// 记录每个推理请求的延迟，定期计算滑动窗口内的p99；持续超出上限时进入降载模式，
+/// 拒绝低优先级请求，p99持续恢复后解除降载，每次切换都记录事件
+pub struct LatencySloGuard {
+    config: LatencySloConfig,
+    shed_priority: RequestPriority,
+    shedding: AtomicBool,
+    shed_requests: AtomicU64,
+    samples: Mutex<VecDeque<(Instant, u64)>>,
+    state: Mutex<SloState>,
+}
+
+impl LatencySloGuard {
+    pub fn new(config: LatencySloConfig) -> Self {
+        Self {
+            shed_priority: RequestPriority::parse(&config.shed_priority).unwrap_or(RequestPriority::Low),
+            config,
+            shedding: AtomicBool::new(false),
+            shed_requests: AtomicU64::new(0),
+            samples: Mutex::new(VecDeque::new()),
+            state: Mutex::new(SloState::default()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 建议被拒绝的客户端等待的秒数
+    pub fn retry_after_secs(&self) -> u64 {
+        self.config.window_secs.max(EVALUATE_INTERVAL.as_secs())
+    }
+
+    /// 记录一次请求的延迟
+    pub fn record(&self, elapsed: Duration) {
+        if !self.config.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let mut samples = self.samples.lock().unwrap();
+        samples.push_back((now, elapsed.as_millis() as u64));
+        self.prune(&mut samples, now);
+    }
+
+    /// 降载期间是否拒绝该优先级的请求（拒绝时计数）
+    pub fn should_shed(&self, priority: RequestPriority) -> bool {
+        if !self.shedding.load(Ordering::Relaxed) || priority > self.shed_priority {
+            return false;
+        }
+        self.shed_requests.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    fn prune(&self, samples: &mut VecDeque<(Instant, u64)>, now: Instant) {
+        let window = Duration::from_secs(self.config.window_secs);
+        while samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > window)
+        {
+            samples.pop_front();
+        }
+        while samples.len() > MAX_SAMPLES {
+            samples.pop_front();
+        }
+    }
+
+    /// 计算窗口内的p99并更新降载状态，发生切换时返回对应的事件
+    pub fn evaluate(&self) -> Option<LatencySloEvent> {
+        let now = Instant::now();
+        let (p99_ms, sample_count) = {
+            let mut samples = self.samples.lock().unwrap();
+            self.prune(&mut samples, now);
+            let mut latencies: Vec<u64> = samples.iter().map(|(_, ms)| *ms).collect();
+            let count = latencies.len();
+            if count < MIN_SAMPLES {
+                (None, count)
+            } else {
+                latencies.sort_unstable();
+                let index = ((count as f64 * 0.99).ceil() as usize).clamp(1, count) - 1;
+                (Some(latencies[index]), count)
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.p99_ms = p99_ms;
+        state.sample_count = sample_count;
+        let shedding = self.shedding.load(Ordering::Relaxed);
+        let threshold = self.config.p99_ms;
+
+        if p99_ms.is_some_and(|p99| p99 > threshold) {
+            state.recovered_since = None;
+            let breach_since = *state.breach_since.get_or_insert(now);
+            let breach_for = Duration::from_secs(self.config.breach_minutes * 60);
+            if !shedding && now.duration_since(breach_since) >= breach_for {
+                self.shedding.store(true, Ordering::Relaxed);
+                state.shed_since = Some(Utc::now());
+                return Some(LatencySloEvent::new(SHED_ENABLED, p99_ms, threshold, sample_count));
+            }
+        } else {
+            state.breach_since = None;
+            if shedding {
+                let recovered_since = *state.recovered_since.get_or_insert(now);
+                let recover_for = Duration::from_secs(self.config.recovery_minutes * 60);
+                if now.duration_since(recovered_since) >= recover_for {
+                    self.shedding.store(false, Ordering::Relaxed);
+                    state.recovered_since = None;
+                    state.shed_since = None;
+                    return Some(LatencySloEvent::new(SHED_DISABLED, p99_ms, threshold, sample_count));
+                }
+            }
+        }
+        None
+    }
+
+    /// 当前状态（不含事件列表）
+    pub fn status(&self) -> LatencySloStatus {
+        let state = self.state.lock().unwrap();
+        LatencySloStatus {
+            enabled: self.config.enabled,
+            shedding: self.shedding.load(Ordering::Relaxed),
+            shed_since: state.shed_since,
+            shed_priority: self.shed_priority.as_str(),
+            shed_requests: self.shed_requests.load(Ordering::Relaxed),
+            p99_ms: state.p99_ms,
+            threshold_ms: self.config.p99_ms,
+            sample_count: state.sample_count,
+            window_secs: self.config.window_secs,
+            breaching_secs: state.breach_since.map(|since| since.elapsed().as_secs()),
+            recovering_secs: state.recovered_since.map(|since| since.elapsed().as_secs()),
+            recent_events: Vec::new(),
+        }
+    }
+
+    /// 注册定时评估任务 latency_slo_evaluate，降载状态切换时输出日志并保存事件
+    pub fn register(self: &Arc<Self>, scheduler: &Scheduler, schedules: &SchedulerConfig, db: SqlitePool) {
+        let default = if self.config.enabled {
+            format!("@every {}s", EVALUATE_INTERVAL.as_secs())
+        } else {
+            "off".to_string()
+        };
+        let guard = self.clone();
+        scheduler.register(
+            "latency_slo_evaluate",
+            "评估网关p99延迟并切换降载状态",
+            scheduler::resolve(schedules, "latency_slo_evaluate", &default),
+            move || {
+                let (guard, db) = (guard.clone(), db.clone());
+                async move {
+                    let Some(event) = guard.evaluate() else {
+                        return Ok(());
+                    };
+                    let p99 = event.p99_ms.map_or_else(|| "-".to_string(), |p99| format!("{}ms", p99));
+                    if event.event_type == SHED_ENABLED {
+                        warn!(
+                            "网关p99延迟持续超出上限，开始拒绝低优先级请求: p99={}, 上限={}ms, 请求数={}",
+                            p99, event.threshold_ms, event.sample_count
+                        );
+                    } else {
+                        info!(
+                            "网关p99延迟已恢复，解除降载: p99={}, 上限={}ms, 请求数={}",
+                            p99, event.threshold_ms, event.sample_count
+                        );
+                    }
+                    event.record(&db).await.map_err(|e| format!("保存延迟SLO事件失败: {}", e))
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::config::ListCacheConfig;
+
+/// 提供商列表缓存键
+pub const PROVIDERS_KEY: &str = "providers";
+/// 定价列表缓存键
+pub const PRICING_KEY: &str = "pricing";
+
+/// 缓存的列表响应（已序列化的JSON）
+#[derive(Debug)]
+pub struct CachedList {
+    /// 响应体
+    pub body: Bytes,
+    /// 根据响应体计算的强校验ETag（含引号）
+    pub etag: String,
+    fetched_at: Instant,
+}
+
+impl CachedList {
+    fn new(body: Bytes) -> Self {
+        let digest = format!("{:x}", Sha256::digest(&body));
+        Self {
+            etag: format!("\"{}\"", &digest[..32]),
+            body,
+            fetched_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    lists: HashMap<&'static str, Arc<CachedList>>,
+    // 正在后台刷新的键，避免重复刷新
+    refreshing: HashMap<&'static str, u64>,
+    // 每个键的失效代数：写操作使缓存失效后，之前发起的刷新结果不再写回
+    generations: HashMap<&'static str, u64>,
+}
+
+/// 管理列表接口的响应缓存（stale-while-revalidate）
+/// 新鲜期内直接返回缓存；过期但仍在容忍期内时返回旧数据并在后台刷新；
+/// 超过容忍期或没有缓存时同步查询数据库
+pub struct ListCache {
+    config: ListCacheConfig,
+    entries: Mutex<CacheEntries>,
+}
+
+impl ListCache {
+    pub fn new(config: ListCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(CacheEntries::default()),
+        }
+    }
+
+    /// 读取缓存的列表，必要时通过 load 重新加载
+    pub async fn get<F, Fut>(self: &Arc<Self>, key: &'static str, load: F) -> Result<Arc<CachedList>, String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Bytes, String>> + Send + 'static,
+    {
+        if !self.config.enabled {
+            return load().await.map(|body| Arc::new(CachedList::new(body)));
+        }
+
+        let ttl = Duration::from_millis(self.config.ttl_ms);
+        let stale = ttl + Duration::from_millis(self.config.stale_ms);
+        let generation = {
+            let mut entries = self.entries.lock().unwrap();
+            let generation = entries.generations.get(key).copied().unwrap_or(0);
+            if let Some(list) = entries.lists.get(key).cloned() {
+                let age = list.fetched_at.elapsed();
+                if age < ttl {
+                    return Ok(list);
+                }
+                if age < stale {
+                    if !entries.refreshing.contains_key(key) {
+                        entries.refreshing.insert(key, generation);
+                        self.spawn_refresh(key, generation, load());
+                    }
+                    return Ok(list);
+                }
+            }
+            generation
+        };
+
+        let list = Arc::new(CachedList::new(load().await?));
+        self.store(key, generation, list.clone());
+        Ok(list)
+    }
+
+    /// 数据变更后使缓存失效
+    pub fn invalidate(&self, key: &'static str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.lists.remove(key);
+        entries.refreshing.remove(key);
+        *entries.generations.entry(key).or_insert(0) += 1;
+    }
+
+    fn spawn_refresh<Fut>(self: &Arc<Self>, key: &'static str, generation: u64, load: Fut)
+    where
+        Fut: Future<Output = Result<Bytes, String>> + Send + 'static,
+    {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            match load.await {
+                Ok(body) => cache.store(key, generation, Arc::new(CachedList::new(body))),
+                Err(e) => {
+                    warn!("后台刷新列表缓存失败: key={}, 错误={}", key, e);
+                    let mut entries = cache.entries.lock().unwrap();
+                    if entries.refreshing.get(key) == Some(&generation) {
+                        entries.refreshing.remove(key);
+                    }
+                }
+            }
+        });
+    }
+
+    // 加载期间缓存已失效时丢弃结果
+    fn store(&self, key: &'static str, generation: u64, list: Arc<CachedList>) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generations.get(key).copied().unwrap_or(0) != generation {
+            return;
+        }
+        entries.lists.insert(key, list);
+        if entries.refreshing.get(key) == Some(&generation) {
+            entries.refreshing.remove(key);
+        }
+    }
+}
@@ -1,5 +1,60 @@
 pub mod provider_pool;
 pub mod balance_checker;
+pub mod stream_pacer;
+pub mod sse_decoder;
+pub mod stream_heartbeat;
+pub mod stream_flush;
+pub mod concurrency_controller;
+pub mod import_jobs;
+pub mod burn_rate;
+pub mod bedrock;
+pub mod openrouter;
+pub mod contract_expiry;
+pub mod blocked_content;
+pub mod db_metrics;
+pub mod provider_stats;
+pub mod reconcile;
+pub mod invoice_reconcile;
+pub mod list_cache;
+pub mod degradation;
+pub mod upstream_client;
+pub mod key_rate_limiter;
+pub mod provider_limits;
+pub mod latency_slo;
+pub mod usage_recorder;
+pub mod ip_access;
+pub mod provider_errors;
+pub mod key_metrics;
+pub mod usage_rollup;
+pub mod diagnostics;
+pub mod usage_cost;
+pub mod usage_report;
+pub mod usage_export;
+pub mod scheduler;
+pub mod usage_retention;
+pub mod conversation_affinity;
+pub mod provider_latency;
+pub mod error_reporter;
+pub mod slow_requests;
+pub mod provider_cooldown;
+pub mod in_flight;
+pub mod model_fallback;
+pub mod model_catalog;
 
 pub use provider_pool::{ProviderPoolState, ProviderInfo, TokenManager};
 pub use balance_checker::BalanceChecker;
+pub use stream_pacer::StreamPacer;
+pub use sse_decoder::{error_event as sse_error_event, SseDecoder, SseEvent};
+pub use concurrency_controller::{ConcurrencyController, ConcurrencyPermit};
+pub use import_jobs::ImportJobRegistry;
+pub use db_metrics::DbMetrics;
+pub use provider_stats::ProviderStatsReplica;
+pub use list_cache::ListCache;
+pub use key_rate_limiter::KeyRateLimiter;
+pub use latency_slo::LatencySloGuard;
+pub use usage_recorder::UsageRecorder;
+pub use ip_access::IpAccessList;
+pub use key_metrics::KeyUsageMetrics;
+pub use conversation_affinity::ConversationAffinity;
+pub use slow_requests::SlowRequestLog;
+pub use in_flight::InFlightLimiter;
@@ -0,0 +1,316 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::{OpenRouterConfig, SchedulerConfig};
+use crate::models::{AiModel, ModelType};
+use crate::services::provider_pool::load_provider;
+use crate::services::scheduler::{self, Scheduler};
+use crate::services::upstream_client::{record_request, tls_client};
+use crate::services::ProviderInfo;
+use crate::utils::redact::redact;
+
+// 请求提供商模型列表接口的超时时间（秒）
+const SYNC_TIMEOUT_SECS: u64 = 30;
+// 模型列表中可能表示上下文窗口的字段（OpenAI兼容服务、vLLM、OpenRouter 等各不相同）
+const CONTEXT_WINDOW_FIELDS: [&str; 4] = ["context_length", "context_window", "max_model_len", "max_context_length"];
+
+/// 一次模型目录同步的结果
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ModelSyncResult {
+    /// 提供商ID
+    pub provider_id: String,
+    /// 提供商模型列表中的模型数
+    pub listed: usize,
+    /// 新增的模型数
+    pub added: usize,
+    /// 更新的模型数
+    pub updated: usize,
+    /// 已不在模型列表中而被停用的模型数
+    pub disabled: usize,
+}
+
+/// 网关模型目录中一个模型的能力信息，汇总自所有可用提供商的同名模型：
+/// 取各提供商中最保守的值，保证请求无论路由到哪个提供商都能满足
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelMetadata {
+    /// 模型名称
+    pub model: String,
+    /// 模型类型（ChatCompletion、Embedding 等）
+    pub model_type: String,
+    /// 提供该模型的提供商数量
+    pub provider_count: usize,
+    /// 上下文窗口（token数），取各提供商中的最小值
+    pub context_window: Option<u32>,
+    /// 单次最多输出的token数，取各提供商中的最小值
+    pub max_output_tokens: Option<u32>,
+    /// 是否支持图片输入，只有全部提供该信息的提供商都支持时为 true，没有提供商提供该信息时为空
+    pub supports_vision: Option<bool>,
+    /// 是否支持工具调用，规则同 supports_vision
+    pub supports_tools: Option<bool>,
+    /// 是否支持结构化输出，规则同 supports_vision
+    pub supports_structured_output: Option<bool>,
+    /// 输入价格（美元/百万tokens），取各提供商中的最高值
+    pub input_price_per_million_tokens: Option<f64>,
+    /// 输出价格（美元/百万tokens），取各提供商中的最高值
+    pub output_price_per_million_tokens: Option<f64>,
+    /// 最近一次同步的时间
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 查询模型的能力信息，模型目录中没有可用提供商的同名模型时返回空
+pub async fn metadata(db: &SqlitePool, model: &str, organization: Option<&str>) -> Result<Option<ModelMetadata>, sqlx::Error> {
+    let entries = AiModel::list_enabled_by_name(db, model, organization).await?;
+    let latest = match entries.first() {
+        Some(latest) => latest,
+        None => return Ok(None),
+    };
+
+    let min = |values: Vec<u32>| values.into_iter().min();
+    let max = |values: Vec<f64>| values.into_iter().reduce(f64::max);
+    let capability = |name: &str| {
+        let flags: Vec<bool> = entries.iter().filter_map(|entry| entry.capabilities.get(name)).map(|flag| flag == "true").collect();
+        (!flags.is_empty()).then(|| flags.iter().all(|flag| *flag))
+    };
+
+    Ok(Some(ModelMetadata {
+        model: model.to_string(),
+        model_type: latest.model_type.as_str().to_string(),
+        provider_count: entries.len(),
+        context_window: min(entries.iter().filter_map(|entry| entry.context_window).collect()),
+        max_output_tokens: min(entries
+            .iter()
+            .filter_map(|entry| entry.capabilities.get("max_output_tokens").and_then(|tokens| tokens.parse().ok()))
+            .collect()),
+        supports_vision: capability("vision"),
+        supports_tools: capability("tools"),
+        supports_structured_output: capability("structured_output"),
+        input_price_per_million_tokens: max(entries.iter().filter_map(|entry| entry.input_price_per_million_tokens).collect()),
+        output_price_per_million_tokens: max(entries.iter().filter_map(|entry| entry.output_price_per_million_tokens).collect()),
+        updated_at: latest.updated_at,
+    }))
+}
+
+/// 提供商是否提供 OpenAI 兼容的模型列表接口（Bedrock 使用 SigV4 签名，Azure 部署没有 /v1/models）
+pub fn supports_sync(provider: &ProviderInfo) -> bool {
+    !provider.is_bedrock() && provider.deployment.is_none()
+}
+
+/// 查询提供商的模型列表接口并写入模型目录：列表中的模型新增或更新，
+/// 之前同步过、已不在列表中的模型被停用
+pub async fn sync_provider(
+    db: &SqlitePool,
+    provider_id: &str,
+    provider: &ProviderInfo,
+    openrouter_config: &OpenRouterConfig,
+) -> Result<ModelSyncResult, String> {
+    let listed = fetch_models(provider, openrouter_config).await?;
+    let existing: HashMap<String, AiModel> = AiModel::list_for_provider(db, provider_id)
+        .await
+        .map_err(|e| format!("读取模型目录失败: {}", e))?
+        .into_iter()
+        .map(|model| (model.name.clone(), model))
+        .collect();
+
+    let mut result = ModelSyncResult { provider_id: provider_id.to_string(), listed: listed.len(), ..Default::default() };
+    let mut seen = HashSet::new();
+    for entry in listed {
+        let model_type = if entry.name == provider.model_name {
+            ModelType::parse(&provider.model_type)
+        } else {
+            infer_model_type(&entry.name)
+        };
+        let mut model = AiModel::new(
+            Uuid::new_v4().to_string(),
+            entry.name.clone(),
+            provider_id.to_string(),
+            model_type,
+            provider.model_version.clone(),
+            entry.context_window,
+            entry.input_price,
+            entry.output_price,
+            entry.capabilities,
+        );
+        if let Some(current) = existing.get(&entry.name) {
+            model.id = current.id.clone();
+            model.created_at = current.created_at;
+            result.updated += 1;
+        } else {
+            result.added += 1;
+        }
+        model.upsert(db).await.map_err(|e| format!("写入模型目录失败: {}", e))?;
+        seen.insert(entry.name);
+    }
+
+    for model in existing.values().filter(|model| model.is_enabled && !seen.contains(&model.name)) {
+        AiModel::set_enabled(db, &model.id, false)
+            .await
+            .map_err(|e| format!("停用模型失败: {}", e))?;
+        result.disabled += 1;
+    }
+
+    info!(
+        "提供商 {} 模型目录同步完成: 列表 {} 个, 新增 {} 个, 更新 {} 个, 停用 {} 个",
+        provider_id, result.listed, result.added, result.updated, result.disabled
+    );
+    Ok(result)
+}
+
+/// 同步所有 Active 提供商的模型目录，单个提供商失败不影响其余提供商
+pub async fn sync_all(db: &SqlitePool, openrouter_config: &OpenRouterConfig) -> Result<(), String> {
+    let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM api_providers WHERE status = 'Active'")
+        .fetch_all(db)
+        .await
+        .map_err(|e| format!("读取提供商列表失败: {}", e))?;
+
+    let mut failed = 0;
+    for id in &ids {
+        let provider = match load_provider(db, id).await {
+            Ok(Some(provider)) if supports_sync(&provider) => provider,
+            Ok(_) => continue,
+            Err(e) => return Err(format!("读取提供商失败: {}", e)),
+        };
+        if let Err(e) = sync_provider(db, id, &provider, openrouter_config).await {
+            warn!("提供商 {} (api_key={}) 模型目录同步失败: {}", id, redact(&provider.api_key), e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} 个提供商的模型目录同步失败", failed));
+    }
+    Ok(())
+}
+
+/// 注册定期同步模型目录的任务
+pub fn register(scheduler: &Scheduler, schedules: &SchedulerConfig, db: SqlitePool, openrouter_config: OpenRouterConfig) {
+    scheduler.register(
+        "model_catalog_sync",
+        "从提供商的模型列表接口同步模型目录",
+        scheduler::resolve(schedules, "model_catalog_sync", "@every 6h"),
+        move || {
+            let (db, openrouter_config) = (db.clone(), openrouter_config.clone());
+            async move { sync_all(&db, &openrouter_config).await }
+        },
+    );
+}
+
+// 模型列表中的一项
+struct ListedModel {
+    name: String,
+    context_window: Option<u32>,
+    input_price: Option<f64>,
+    output_price: Option<f64>,
+    capabilities: HashMap<String, String>,
+}
+
+// 请求提供商的 /v1/models 接口
+async fn fetch_models(provider: &ProviderInfo, openrouter_config: &OpenRouterConfig) -> Result<Vec<ListedModel>, String> {
+    let origin = provider.base_url.split("/v1/").next().unwrap_or_default().trim_end_matches('/');
+    let url = format!("{}/v1/models", origin);
+
+    let client = tls_client(provider)?;
+    let mut request = client.get(&url).timeout(Duration::from_secs(SYNC_TIMEOUT_SECS));
+    if let Some((auth_name, auth_value)) = provider.auth_header() {
+        request = request.header(auth_name, auth_value);
+    }
+    for (name, value) in provider.static_headers(openrouter_config) {
+        request = request.header(name, value);
+    }
+    record_request(&url);
+
+    let response = request.send().await.map_err(|e| format!("请求 {} 失败: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("{} 返回 HTTP {}", url, response.status()));
+    }
+    let body: Value = response.json().await.map_err(|e| format!("解析 {} 的响应失败: {}", url, e))?;
+
+    // OpenAI 兼容服务返回 {"data": [...]}，部分服务直接返回数组
+    let entries = body.get("data").and_then(Value::as_array).or_else(|| body.as_array());
+    match entries {
+        Some(entries) => Ok(entries.iter().filter_map(parse_model).collect()),
+        None => Err(format!("{} 的响应中没有模型列表", url)),
+    }
+}
+
+// 读取模型列表项中能识别的字段，缺少 id 的项被忽略
+fn parse_model(entry: &Value) -> Option<ListedModel> {
+    let name = entry.get("id").and_then(Value::as_str)?.to_string();
+    let top_provider = entry.get("top_provider");
+
+    let context_window = CONTEXT_WINDOW_FIELDS
+        .iter()
+        .find_map(|field| entry.get(*field).and_then(Value::as_u64))
+        .or_else(|| top_provider.and_then(|p| p.get("context_length")).and_then(Value::as_u64))
+        .and_then(|window| u32::try_from(window).ok());
+
+    // OpenRouter 的价格为每 token 的美元金额（字符串）
+    let pricing = entry.get("pricing");
+    let price = |field: &str| {
+        pricing
+            .and_then(|p| p.get(field))
+            .and_then(|v| v.as_str().and_then(|s| s.parse::<f64>().ok()).or_else(|| v.as_f64()))
+            .filter(|price| *price >= 0.0)
+            .map(|price| price * 1_000_000.0)
+    };
+
+    let mut capabilities = HashMap::new();
+    if let Some(owned_by) = entry.get("owned_by").and_then(Value::as_str) {
+        capabilities.insert("owned_by".to_string(), owned_by.to_string());
+    }
+    if let Some(max_output) = entry
+        .get("max_completion_tokens")
+        .or_else(|| top_provider.and_then(|p| p.get("max_completion_tokens")))
+        .and_then(Value::as_u64)
+    {
+        capabilities.insert("max_output_tokens".to_string(), max_output.to_string());
+    }
+    // OpenRouter：architecture.input_modalities 和 supported_parameters
+    if let Some(modalities) = entry.pointer("/architecture/input_modalities").and_then(Value::as_array) {
+        let vision = modalities.iter().any(|m| m.as_str() == Some("image"));
+        capabilities.insert("vision".to_string(), vision.to_string());
+    }
+    if let Some(parameters) = entry.get("supported_parameters").and_then(Value::as_array) {
+        let supports = |name: &str| parameters.iter().any(|p| p.as_str() == Some(name)).to_string();
+        capabilities.insert("tools".to_string(), supports("tools"));
+        capabilities.insert("structured_output".to_string(), supports("structured_outputs"));
+    }
+    // Mistral 等服务：capabilities 对象中的布尔值，function_calling 记为 tools
+    if let Some(flags) = entry.get("capabilities").and_then(Value::as_object) {
+        for (name, value) in flags {
+            if let Some(flag) = value.as_bool() {
+                let name = if name == "function_calling" { "tools" } else { name.as_str() };
+                capabilities.insert(name.to_string(), flag.to_string());
+            }
+        }
+    }
+
+    Some(ListedModel {
+        name,
+        context_window,
+        input_price: price("prompt"),
+        output_price: price("completion"),
+        capabilities,
+    })
+}
+
+// 按模型名称推断模型类型，无法识别时按对话模型处理
+fn infer_model_type(name: &str) -> ModelType {
+    let name = name.to_ascii_lowercase();
+    if name.contains("embed") {
+        ModelType::Embedding
+    } else if name.contains("whisper") || name.contains("transcribe") {
+        ModelType::AudioTranscription
+    } else if name.contains("moderation") {
+        ModelType::Moderation
+    } else if name.contains("dall-e") || name.contains("gpt-image") || name.contains("stable-diffusion") {
+        ModelType::ImageGeneration
+    } else {
+        ModelType::ChatCompletion
+    }
+}
@@ -0,0 +1,91 @@
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::config::ModelFallbackConfig;
+use crate::services::ProviderPoolState;
+
+/// 实际使用的模型响应头（请求的模型没有可用提供商、改用回退模型时设置）
+pub const MODEL_USED_HEADER: &str = "X-Gateway-Model";
+
+/// 请求的模型没有可用的提供商时，返回回退链上第一个有可用提供商的模型；
+/// 请求的模型可用、未配置回退链或回退模型也都不可用时返回空
+pub async fn resolve(
+    config: &ModelFallbackConfig,
+    pool: &RwLock<ProviderPoolState>,
+    model_name: &str,
+    model_type: Option<&str>,
+    organization: Option<&str>,
+) -> Option<String> {
+    let chain = config.chains.get(model_name)?;
+    let pool = pool.read().await;
+    if pool.has_available_provider(model_name, model_type, organization) {
+        return None;
+    }
+    let fallback = chain
+        .iter()
+        .find(|model| pool.has_available_provider(model, model_type, organization))?;
+    info!("模型 {} 没有可用的提供商，改用回退模型 {}", model_name, fallback);
+    Some(fallback.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::services::provider_pool::tests::provider;
+    use crate::services::ProviderInfo;
+
+    type Chains<'a> = &'a [(&'a str, &'a [&'a str])];
+
+    #[test]
+    fn parses_fallback_chains() {
+        let cases: &[(&str, Chains)] = &[
+            ("", &[]),
+            ("gpt-4o=gpt-4o-mini", &[("gpt-4o", &["gpt-4o-mini"])]),
+            (
+                "DeepSeek-V3=Qwen2.5-72B,Llama-3.3-70B;gpt-4o=gpt-4o-mini",
+                &[("DeepSeek-V3", &["Qwen2.5-72B", "Llama-3.3-70B"]), ("gpt-4o", &["gpt-4o-mini"])],
+            ),
+            (" gpt-4o = gpt-4o-mini , gpt-3.5-turbo ;", &[("gpt-4o", &["gpt-4o-mini", "gpt-3.5-turbo"])]),
+            ("gpt-4o=gpt-4o-mini,,;;", &[("gpt-4o", &["gpt-4o-mini"])]),
+            ("gpt-4o=a;gpt-4o=b", &[("gpt-4o", &["b"])]),
+            ("gpt-4o", &[]),
+            ("gpt-4o=", &[]),
+            ("gpt-4o= , ", &[]),
+            ("=gpt-4o-mini", &[]),
+        ];
+
+        for (value, expected) in cases {
+            let chains = ModelFallbackConfig::parse(value).chains;
+            let expected: HashMap<String, Vec<String>> = expected
+                .iter()
+                .map(|(model, chain)| (model.to_string(), chain.iter().map(|m| m.to_string()).collect()))
+                .collect();
+            assert_eq!(chains, expected, "{:?}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_first_available_fallback() {
+        let model = |name: &str| ProviderInfo { model_name: name.to_string(), ..provider(name, 0) };
+        let pool = RwLock::new(ProviderPoolState::new(vec![model("gpt-4o-mini"), model("gpt-3.5-turbo")]));
+        let config = ModelFallbackConfig::parse("gpt-4o=o1,gpt-4o-mini,gpt-3.5-turbo;gpt-4o-mini=gpt-3.5-turbo;o1=o1-mini");
+
+        let cases = [
+            ("gpt-4o", Some("gpt-4o-mini")),
+            // 请求的模型可用时不回退
+            ("gpt-4o-mini", None),
+            // 未配置回退链
+            ("claude-3", None),
+            // 回退模型也都不可用
+            ("o1", None),
+        ];
+        for (requested, expected) in cases {
+            let resolved = resolve(&config, &pool, requested, None, None).await;
+            assert_eq!(resolved.as_deref(), expected, "{}", requested);
+        }
+
+        // 组织的请求只看该组织的提供商
+        assert_eq!(resolve(&config, &pool, "gpt-4o", None, Some("org-1")).await, None);
+    }
+}
@@ -0,0 +1,88 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::config::OpenRouterConfig;
+
+/// 客户端可使用 openrouter/<厂商>/<模型> 显式指定由 OpenRouter 提供商处理
+pub const MODEL_PREFIX: &str = "openrouter/";
+
+#[derive(Debug, Deserialize)]
+struct KeyResponse {
+    data: KeyData,
+}
+
+// /auth/key 返回的密钥额度信息，未设置额度上限时 limit 为空
+#[derive(Debug, Deserialize)]
+struct KeyData {
+    usage: f64,
+    limit: Option<f64>,
+    limit_remaining: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreditsResponse {
+    data: CreditsData,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreditsData {
+    total_credits: f64,
+    total_usage: f64,
+}
+
+/// OpenRouter 接口根地址，如 https://openrouter.ai/api/v1
+pub fn api_root(base_url: &str) -> String {
+    match base_url.find("/api/v1") {
+        Some(index) => base_url[..index + "/api/v1".len()].to_string(),
+        None => format!("{}/api/v1", base_url.trim_end_matches('/')),
+    }
+}
+
+/// 密钥信息接口，用于查询剩余额度
+pub fn key_url(base_url: &str) -> String {
+    format!("{}/auth/key", api_root(base_url))
+}
+
+/// 从 /auth/key 响应中解析剩余额度；密钥未设置额度上限时改为查询账户剩余积分
+pub async fn remaining_credits(
+    client: &Client,
+    base_url: &str,
+    api_key: &str,
+    response: reqwest::Response,
+) -> anyhow::Result<f64> {
+    let key: KeyResponse = response.json().await?;
+    if let Some(remaining) = key.data.limit_remaining {
+        return Ok(remaining);
+    }
+    if let Some(limit) = key.data.limit {
+        return Ok(limit - key.data.usage);
+    }
+
+    let credits: CreditsResponse = client
+        .get(format!("{}/credits", api_root(base_url)))
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(credits.data.total_credits - credits.data.total_usage)
+}
+
+/// OpenRouter 要求的应用归属请求头
+pub fn attribution_headers(config: &OpenRouterConfig) -> [(&'static str, String); 2] {
+    [
+        ("HTTP-Referer", config.referer.clone()),
+        ("X-Title", config.title.clone()),
+    ]
+}
+
+/// 请求的模型是否由该 OpenRouter 模型提供：支持完整名称（openai/gpt-4o）、
+/// 省略厂商前缀的名称（gpt-4o）以及带 openrouter/ 前缀的名称
+pub fn matches_model(provider_model: &str, requested: &str) -> bool {
+    let requested = requested.strip_prefix(MODEL_PREFIX).unwrap_or(requested);
+    requested == provider_model
+        || provider_model
+            .split_once('/')
+            .is_some_and(|(_, name)| name == requested)
+}
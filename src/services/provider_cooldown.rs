@@ -0,0 +1,325 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use dashmap::DashMap;
+use std::sync::OnceLock;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::ProviderCooldownConfig;
+use crate::services::error_reporter::{self, ErrorEvent, Level};
+use crate::services::provider_pool::ProviderInfo;
+use crate::utils::redact::redact;
+
+static COOLDOWN: OnceLock<ProviderCooldown> = OnceLock::new();
+
+/// 启用提供商冷却，启动时调用一次
+pub fn configure(config: &ProviderCooldownConfig) {
+    let _ = COOLDOWN.set(ProviderCooldown::new(config.clone()));
+}
+
+/// 全局的提供商冷却状态；连续失败冷却和 Retry-After 冷却都未启用时返回空
+pub fn cooldowns() -> Option<&'static ProviderCooldown> {
+    COOLDOWN
+        .get()
+        .filter(|cooldown| cooldown.config.failure_threshold > 0 || cooldown.config.retry_after_max_secs > 0)
+}
+
+/// 上游返回429时按响应头中建议的等待时长暂停使用该提供商；自带密钥不影响池中提供商
+pub fn respect_retry_after(provider: &ProviderInfo, headers: &HeaderMap) {
+    if provider.own_api_key.is_some() {
+        return;
+    }
+    let Some(cooldowns) = cooldowns() else {
+        return;
+    };
+    if let Some(wait) = retry_after(headers) {
+        cooldowns.cool_for(provider, wait);
+    }
+}
+
+/// 解析限流响应头中建议的等待时长：优先 Retry-After（秒数或HTTP日期），
+/// 其次 x-ratelimit-reset-requests / x-ratelimit-reset-tokens（如 1s、6m0s、20ms，取较长者）
+/// 和 x-ratelimit-reset（秒数或Unix时间戳）
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return seconds(secs);
+        }
+        if let Ok(at) = DateTime::parse_from_rfc2822(value) {
+            return (at.with_timezone(&Utc) - Utc::now()).to_std().ok();
+        }
+    }
+
+    let reset = ["x-ratelimit-reset-requests", "x-ratelimit-reset-tokens"]
+        .into_iter()
+        .filter_map(|name| header(name).and_then(parse_reset))
+        .max();
+    if reset.is_some() {
+        return reset;
+    }
+
+    let value = header("x-ratelimit-reset")?.parse::<f64>().ok()?;
+    // 大于一年的数值视为Unix时间戳
+    if value > 365.0 * 24.0 * 3600.0 {
+        return seconds(value - Utc::now().timestamp() as f64);
+    }
+    seconds(value)
+}
+
+fn seconds(secs: f64) -> Option<Duration> {
+    (secs.is_finite() && secs > 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+// 解析 OpenAI 风格的时长（1s、6m0s、1h2m3.5s、20ms），纯数字按秒处理
+fn parse_reset(value: &str) -> Option<Duration> {
+    if let Ok(secs) = value.parse::<f64>() {
+        return seconds(secs);
+    }
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let amount = rest[..end].parse::<f64>().ok()?;
+        rest = &rest[end..];
+        let (unit, factor) = [("ms", 0.001), ("h", 3600.0), ("m", 60.0), ("s", 1.0)]
+            .into_iter()
+            .find(|(unit, _)| rest.starts_with(unit))?;
+        total += amount * factor;
+        rest = &rest[unit.len()..];
+    }
+    seconds(total)
+}
+
+#[derive(Debug, Default)]
+struct CooldownState {
+    // 连续失败次数
+    failures: u32,
+    // 冷却结束时间
+    until: Option<Instant>,
+    until_at: Option<DateTime<Utc>>,
+    // 冷却结束后尚未成功过，再次失败立即重新冷却
+    probation: bool,
+}
+
+/// 提供商冷却：连续返回限流或服务端错误的提供商在冷却期内不参与选择（不删除、不改数据库），
+/// 避免一个有问题的密钥耗尽所有重试机会；成功一次即清除失败计数。
+/// 上游限流响应带有 Retry-After 等响应头时，按建议的等待时长立即冷却
+pub struct ProviderCooldown {
+    config: ProviderCooldownConfig,
+    states: DashMap<String, CooldownState>,
+}
+
+impl ProviderCooldown {
+    fn new(config: ProviderCooldownConfig) -> Self {
+        Self {
+            config,
+            states: DashMap::new(),
+        }
+    }
+
+    /// 提供商是否在冷却中
+    pub fn is_cooling(&self, api_key: &str) -> bool {
+        self.states
+            .get(api_key)
+            .and_then(|state| state.until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// 冷却结束时间，不在冷却中时返回空
+    pub fn cooling_until(&self, api_key: &str) -> Option<DateTime<Utc>> {
+        let state = self.states.get(api_key)?;
+        match state.until {
+            Some(until) if Instant::now() < until => state.until_at,
+            _ => None,
+        }
+    }
+
+    /// 请求成功，清除失败计数
+    pub fn record_success(&self, api_key: &str) {
+        if self.states.remove(api_key).is_some_and(|(_, state)| state.probation) {
+            info!("提供商冷却后已恢复: api_key={}", redact(api_key));
+        }
+    }
+
+    /// 按上游建议的等待时长（不超过 PROVIDER_RETRY_AFTER_MAX_SECS）暂停使用提供商，已在更长的冷却中时不缩短
+    pub fn cool_for(&self, provider: &ProviderInfo, wait: Duration) {
+        if self.config.retry_after_max_secs == 0 {
+            return;
+        }
+        let wait = wait.min(Duration::from_secs(self.config.retry_after_max_secs));
+        {
+            let mut state = self.states.entry(provider.api_key.clone()).or_default();
+            let until = Instant::now() + wait;
+            if state.until.is_some_and(|existing| existing >= until) {
+                return;
+            }
+            state.failures = 0;
+            state.until = Some(until);
+            state.until_at = chrono::Duration::from_std(wait).ok().map(|wait| Utc::now() + wait);
+        }
+        warn!(
+            "提供商限流，按 Retry-After 暂停使用 {}ms: api_key={}, URL: {}",
+            wait.as_millis(), redact(&provider.api_key), provider.base_url
+        );
+    }
+
+    /// 根据上游状态码记录失败，只统计限流(429)和服务端错误(5xx)
+    pub fn record_status(&self, provider: &ProviderInfo, status: StatusCode) {
+        if self.config.failure_threshold == 0 {
+            return;
+        }
+        if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+            return;
+        }
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+        let started = {
+            let mut state = self.states.entry(provider.api_key.clone()).or_default();
+            let now = Instant::now();
+            // 冷却中仍在处理的请求返回的失败不延长冷却
+            if state.until.is_some_and(|until| now < until) {
+                return;
+            }
+            if state.until.take().is_some() {
+                state.probation = true;
+            }
+            state.failures += 1;
+            if !state.probation && state.failures < self.config.failure_threshold {
+                return;
+            }
+            state.failures = 0;
+            state.until = Some(now + cooldown);
+            state.until_at = Some(Utc::now() + chrono::Duration::seconds(self.config.cooldown_secs as i64));
+            state.until_at
+        };
+        warn!(
+            "提供商连续返回 {}，暂停使用 {} 秒: api_key={}, URL: {}",
+            status, self.config.cooldown_secs, redact(&provider.api_key), provider.base_url
+        );
+        error_reporter::capture(
+            ErrorEvent::for_provider(Level::Warning, provider, format!("提供商进入冷却: 状态码 {}", status))
+                .tag("kind", "provider_cooldown")
+                .extra("cooldown_secs", self.config.cooldown_secs)
+                .extra("until", started.map(|until| until.to_rfc3339()).unwrap_or_default()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::provider_pool::tests::provider;
+    use reqwest::header::HeaderValue;
+
+    type Headers<'a> = &'a [(&'static str, &'a str)];
+
+    fn headers(pairs: Headers) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn cooldown(failure_threshold: u32, retry_after_max_secs: u64) -> ProviderCooldown {
+        ProviderCooldown::new(ProviderCooldownConfig { failure_threshold, cooldown_secs: 30, retry_after_max_secs })
+    }
+
+    #[test]
+    fn parses_rate_limit_headers() {
+        let cases: &[(Headers, Option<Duration>)] = &[
+            (&[("retry-after", "120")], Some(Duration::from_secs(120))),
+            (&[("retry-after", " 1.5 ")], Some(Duration::from_millis(1500))),
+            (&[("retry-after", "0")], None),
+            (&[("retry-after", "-5")], None),
+            (&[("retry-after", "soon")], None),
+            (&[("x-ratelimit-reset-requests", "1s"), ("x-ratelimit-reset-tokens", "6m0s")], Some(Duration::from_secs(360))),
+            (&[("x-ratelimit-reset-tokens", "1h2m3.5s")], Some(Duration::from_secs_f64(3723.5))),
+            (&[("x-ratelimit-reset-requests", "20ms")], Some(Duration::from_millis(20))),
+            (&[("x-ratelimit-reset-requests", "5x")], None),
+            (&[("x-ratelimit-reset", "7")], Some(Duration::from_secs(7))),
+            // Retry-After 优先于其他响应头
+            (&[("retry-after", "3"), ("x-ratelimit-reset-requests", "1m")], Some(Duration::from_secs(3))),
+            (&[], None),
+        ];
+
+        for (pairs, expected) in cases {
+            assert_eq!(retry_after(&headers(pairs)), *expected, "{:?}", pairs);
+        }
+    }
+
+    #[test]
+    fn parses_retry_after_http_date() {
+        let within = |value: String, low: u64, high: u64| {
+            let wait = retry_after(&headers(&[("retry-after", &value)])).unwrap();
+            assert!(wait > Duration::from_secs(low) && wait <= Duration::from_secs(high), "{}: {:?}", value, wait);
+        };
+
+        let at = Utc::now() + chrono::Duration::seconds(120);
+        within(at.format("%a, %d %b %Y %H:%M:%S GMT").to_string(), 110, 120);
+        within(at.to_rfc2822(), 110, 120);
+
+        // 已过去的时间不冷却
+        let past = (Utc::now() - chrono::Duration::seconds(60)).format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        assert_eq!(retry_after(&headers(&[("retry-after", &past)])), None);
+
+        // Unix 时间戳形式的 x-ratelimit-reset
+        let reset = (Utc::now().timestamp() + 60).to_string();
+        let wait = retry_after(&headers(&[("x-ratelimit-reset", &reset)])).unwrap();
+        assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60), "{:?}", wait);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_after_cooldown_is_capped_and_expires() {
+        let cooldowns = cooldown(0, 60);
+        let provider = provider("key-1", 0);
+
+        cooldowns.cool_for(&provider, Duration::from_secs(3600));
+        assert!(cooldowns.is_cooling("key-1"));
+        assert!(cooldowns.cooling_until("key-1").is_some());
+
+        // 已在更长的冷却中时不缩短
+        cooldowns.cool_for(&provider, Duration::from_secs(5));
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert!(cooldowns.is_cooling("key-1"));
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert!(!cooldowns.is_cooling("key-1"));
+        assert!(cooldowns.cooling_until("key-1").is_none());
+
+        // 未启用 Retry-After 冷却时忽略
+        let disabled = cooldown(3, 0);
+        disabled.cool_for(&provider, Duration::from_secs(10));
+        assert!(!disabled.is_cooling("key-1"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn consecutive_failures_cool_down_until_expiry() {
+        let cooldowns = cooldown(2, 0);
+        let provider = provider("key-1", 0);
+
+        // 只统计429和5xx，成功一次清除失败计数
+        cooldowns.record_status(&provider, StatusCode::TOO_MANY_REQUESTS);
+        cooldowns.record_status(&provider, StatusCode::BAD_REQUEST);
+        cooldowns.record_success("key-1");
+        cooldowns.record_status(&provider, StatusCode::BAD_GATEWAY);
+        assert!(!cooldowns.is_cooling("key-1"));
+
+        cooldowns.record_status(&provider, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(cooldowns.is_cooling("key-1"));
+
+        tokio::time::advance(Duration::from_secs(31)).await;
+        assert!(!cooldowns.is_cooling("key-1"));
+
+        // 冷却结束后尚未成功，再次失败立即重新冷却
+        cooldowns.record_status(&provider, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(cooldowns.is_cooling("key-1"));
+
+        tokio::time::advance(Duration::from_secs(31)).await;
+        cooldowns.record_success("key-1");
+        cooldowns.record_status(&provider, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!cooldowns.is_cooling("key-1"));
+    }
+}
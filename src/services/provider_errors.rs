@@ -0,0 +1,117 @@
+use chrono::Utc;
+use reqwest::StatusCode;
+use sqlx::SqlitePool;
+use tracing::error;
+
+use crate::services::error_reporter::{self, ErrorEvent, Level};
+use crate::services::provider_pool::ProviderInfo;
+use crate::utils::redact::redact;
+
+// 保存的错误信息最大字符数
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// 提供商失败的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderErrorCategory {
+    /// 鉴权失败（401/403）
+    Auth,
+    /// 上游限流（429）
+    RateLimited,
+    /// 上游返回其他非成功状态码
+    UpstreamStatus,
+    /// 请求超时
+    Timeout,
+    /// 连接或发送请求失败
+    Network,
+    /// 响应无法解析
+    InvalidResponse,
+    /// 流式响应中断或为空
+    Stream,
+    /// 余额检查失败
+    BalanceCheck,
+    /// 自托管服务健康探测失败
+    HealthCheck,
+}
+
+impl ProviderErrorCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::RateLimited => "rate_limited",
+            Self::UpstreamStatus => "upstream_status",
+            Self::Timeout => "timeout",
+            Self::Network => "network",
+            Self::InvalidResponse => "invalid_response",
+            Self::Stream => "stream",
+            Self::BalanceCheck => "balance_check",
+            Self::HealthCheck => "health_check",
+        }
+    }
+
+    /// 根据上游的非成功状态码分类
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Auth,
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+            _ => Self::UpstreamStatus,
+        }
+    }
+
+    /// 根据发送请求的错误分类
+    pub fn from_request_error(e: &reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Network
+        }
+    }
+
+    /// 根据错误信息分类（用于只返回错误文本的调用路径）
+    pub fn classify(message: &str) -> Self {
+        if message.contains("401") || message.contains("403") {
+            Self::Auth
+        } else if message.contains("429") {
+            Self::RateLimited
+        } else if message.contains("状态码") {
+            Self::UpstreamStatus
+        } else if message.contains("解析响应失败") {
+            Self::InvalidResponse
+        } else if message.contains("timed out") || message.contains("超时") {
+            Self::Timeout
+        } else {
+            Self::Network
+        }
+    }
+}
+
+/// 记录提供商最近一次失败并上报错误事件（限流属于预期情况，不上报）；
+/// 客户端自带密钥（BYOK）的失败与池中密钥无关，不记录
+pub async fn record(db: &SqlitePool, provider: &ProviderInfo, category: ProviderErrorCategory, message: &str) {
+    if provider.own_api_key.is_some() {
+        return;
+    }
+    if category != ProviderErrorCategory::RateLimited {
+        error_reporter::capture(
+            ErrorEvent::for_provider(Level::Warning, provider, format!("上游失败: {}", message))
+                .tag("category", category.as_str()),
+        );
+    }
+    record_for_key(db, &provider.api_key, category, message).await;
+}
+
+/// 按提供商API密钥记录最近一次失败，错误信息超长时截断
+pub async fn record_for_key(db: &SqlitePool, api_key: &str, category: ProviderErrorCategory, message: &str) {
+    let message: String = message.chars().take(MAX_MESSAGE_CHARS).collect();
+    let result = sqlx::query(
+        "UPDATE api_providers SET last_error_at = ?, last_error_category = ?, last_error_message = ? WHERE api_key = ?"
+    )
+    .bind(Utc::now())
+    .bind(category.as_str())
+    .bind(&message)
+    .bind(api_key)
+    .execute(db)
+    .await;
+    if let Err(e) = result {
+        error!("记录提供商最近错误失败: api_key={}, 错误={}", redact(api_key), e);
+    }
+}
@@ -0,0 +1,327 @@
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::config::SchedulerConfig;
+use crate::services::scheduler::{self, Scheduler};
+
+/// 直方图各桶的上界(毫秒)，超过最后一个上界的计入 +Inf
+pub const BUCKET_BOUNDS_MS: [f64; 10] = [100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 30000.0, 60000.0, 120000.0];
+
+// 每个提供商保留的最近请求耗时样本数，用于按延迟选择提供商
+const RECENT_SAMPLES: usize = 100;
+
+/// 统计的耗时类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LatencyMetric {
+    /// 上游请求耗时（非流式为完整响应，流式为收到响应头）
+    Request,
+    /// 流式请求从发送到收到第一个内容数据块的耗时（首字延迟）
+    FirstToken,
+}
+
+impl LatencyMetric {
+    const ALL: [LatencyMetric; 2] = [LatencyMetric::Request, LatencyMetric::FirstToken];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            LatencyMetric::Request => "request",
+            LatencyMetric::FirstToken => "first_token",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str() == value)
+    }
+
+    // Prometheus 指标名称和说明
+    fn metric_name(&self) -> (&'static str, &'static str) {
+        match self {
+            LatencyMetric::Request => (
+                "gateway_provider_request_duration_seconds",
+                "提供商上游请求耗时（流式请求为收到响应头的耗时）",
+            ),
+            LatencyMetric::FirstToken => (
+                "gateway_provider_time_to_first_token_seconds",
+                "流式请求从发送到收到第一个内容数据块的耗时",
+            ),
+        }
+    }
+}
+
+static TRACKER: OnceLock<Arc<ProviderLatency>> = OnceLock::new();
+
+/// 全局的提供商耗时统计
+pub fn tracker() -> Arc<ProviderLatency> {
+    TRACKER.get_or_init(|| Arc::new(ProviderLatency::default())).clone()
+}
+
+/// 固定分桶的耗时直方图
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    // 各桶的非累计计数，最后一个为 +Inf
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    count: u64,
+    sum_ms: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, ms: f64) {
+        let index = BUCKET_BOUNDS_MS.iter().position(|bound| ms <= *bound).unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+    }
+
+    // 按桶内线性插值估算分位数，落在 +Inf 桶时取最后一个上界
+    fn quantile(&self, q: f64) -> f64 {
+        let rank = q * self.count as f64;
+        let mut seen = 0u64;
+        for (index, count) in self.buckets.iter().enumerate() {
+            if *count == 0 || ((seen + count) as f64) < rank {
+                seen += count;
+                continue;
+            }
+            let Some(upper) = BUCKET_BOUNDS_MS.get(index) else {
+                return BUCKET_BOUNDS_MS[BUCKET_BOUNDS_MS.len() - 1];
+            };
+            let lower = if index == 0 { 0.0 } else { BUCKET_BOUNDS_MS[index - 1] };
+            return lower + (upper - lower) * ((rank - seen as f64) / *count as f64).clamp(0.0, 1.0);
+        }
+        0.0
+    }
+
+    fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            count: self.count,
+            mean_ms: if self.count == 0 { 0.0 } else { self.sum_ms / self.count as f64 },
+            p50_ms: self.quantile(0.5),
+            p95_ms: self.quantile(0.95),
+            p99_ms: self.quantile(0.99),
+        }
+    }
+}
+
+/// 提供商耗时的分位数摘要（由直方图估算）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LatencySummary {
+    /// 样本数
+    pub count: u64,
+    /// 平均耗时(毫秒)
+    pub mean_ms: f64,
+    /// p50耗时(毫秒)
+    pub p50_ms: f64,
+    /// p95耗时(毫秒)
+    pub p95_ms: f64,
+    /// p99耗时(毫秒)
+    pub p99_ms: f64,
+}
+
+/// 提供商用于指标标签的信息
+#[derive(Debug, Clone)]
+pub struct ProviderLabel {
+    pub id: String,
+    pub name: String,
+    pub model: String,
+}
+
+/// 提供商最近请求耗时的分位数
+#[derive(Debug, Clone, Copy)]
+pub struct RecentLatency {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    /// 最近一次样本的时间
+    pub last_observed: Instant,
+}
+
+/// 按提供商统计的上游耗时直方图：请求成功时在内存中累计，
+/// 定时任务定期写入数据库，启动时加载，重启后统计不清零。
+/// 用于在提供商统计接口和 Prometheus 指标中找出慢的提供商。
+/// 每个提供商只对应一个模型，按提供商统计即按提供商和模型统计。
+/// 另在内存中保留每个提供商最近的请求耗时样本，供 LowestLatency 策略使用
+#[derive(Default)]
+pub struct ProviderLatency {
+    histograms: DashMap<(LatencyMetric, String), Histogram>,
+    recent: DashMap<String, (VecDeque<f64>, Instant)>,
+}
+
+impl ProviderLatency {
+    /// 记录一次耗时
+    pub fn observe(&self, metric: LatencyMetric, api_key: &str, latency: Duration) {
+        self.histograms
+            .entry((metric, api_key.to_string()))
+            .or_default()
+            .observe(latency.as_secs_f64() * 1000.0);
+
+        if metric == LatencyMetric::Request {
+            let mut entry = self.recent
+                .entry(api_key.to_string())
+                .or_insert_with(|| (VecDeque::with_capacity(RECENT_SAMPLES), Instant::now()));
+            let (samples, last_observed) = &mut *entry;
+            if samples.len() == RECENT_SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back(latency.as_secs_f64() * 1000.0);
+            *last_observed = Instant::now();
+        }
+    }
+
+    /// 提供商最近请求耗时的 p50/p95，没有样本时返回空
+    pub fn recent(&self, api_key: &str) -> Option<RecentLatency> {
+        let (mut samples, last_observed) = {
+            let entry = self.recent.get(api_key).filter(|entry| !entry.0.is_empty())?;
+            (entry.0.iter().copied().collect::<Vec<f64>>(), entry.1)
+        };
+        samples.sort_by(f64::total_cmp);
+        let quantile = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Some(RecentLatency {
+            p50_ms: quantile(0.5),
+            p95_ms: quantile(0.95),
+            last_observed,
+        })
+    }
+
+    /// 提供商的耗时摘要，没有样本时返回空
+    pub fn summary(&self, metric: LatencyMetric, api_key: &str) -> Option<LatencySummary> {
+        self.histograms
+            .get(&(metric, api_key.to_string()))
+            .filter(|h| h.count > 0)
+            .map(|h| h.summary())
+    }
+
+    /// 从数据库加载之前保存的直方图
+    pub async fn load(&self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, String, String, i64, f64)>(
+            "SELECT api_key, metric, bucket_counts, count, sum_ms FROM provider_latency"
+        )
+        .fetch_all(db)
+        .await?;
+
+        for (api_key, metric, bucket_counts, count, sum_ms) in rows {
+            let Some(metric) = LatencyMetric::parse(&metric) else {
+                continue;
+            };
+            let Ok(counts) = serde_json::from_str::<Vec<u64>>(&bucket_counts) else {
+                continue;
+            };
+            // 分桶调整过时丢弃旧数据
+            if counts.len() != BUCKET_BOUNDS_MS.len() {
+                continue;
+            }
+            let mut histogram = Histogram { count: count.max(0) as u64, sum_ms, ..Default::default() };
+            histogram.buckets[..counts.len()].copy_from_slice(&counts);
+            // 数据库中不保存 +Inf 桶，由总数减去其余各桶得到
+            histogram.buckets[counts.len()] = histogram.count.saturating_sub(counts.iter().sum());
+            self.histograms.insert((metric, api_key), histogram);
+        }
+        info!("已加载 {} 项提供商耗时统计", self.histograms.len());
+        Ok(())
+    }
+
+    /// 把当前直方图写入数据库
+    pub async fn persist(&self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let histograms: Vec<((LatencyMetric, String), Histogram)> = self.histograms
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        if histograms.is_empty() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let mut tx = db.begin().await?;
+        for ((metric, api_key), histogram) in histograms {
+            sqlx::query(
+                r#"
+                INSERT INTO provider_latency (api_key, metric, bucket_counts, count, sum_ms, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON CONFLICT (api_key, metric) DO UPDATE SET
+                    bucket_counts = excluded.bucket_counts,
+                    count = excluded.count,
+                    sum_ms = excluded.sum_ms,
+                    updated_at = excluded.updated_at
+                "#
+            )
+            .bind(&api_key)
+            .bind(metric.as_str())
+            .bind(serde_json::to_string(&histogram.buckets[..BUCKET_BOUNDS_MS.len()]).unwrap_or_default())
+            .bind(histogram.count as i64)
+            .bind(histogram.sum_ms)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    /// 以 Prometheus 直方图格式导出，providers 为 api_key 到提供商标签信息的映射；
+    /// 标签不包含密钥，已删除的提供商不导出
+    pub fn render(&self, providers: &HashMap<String, ProviderLabel>) -> String {
+        let mut out = String::new();
+        for metric in LatencyMetric::ALL {
+            let (name, help) = metric.metric_name();
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} histogram", name);
+            let mut series: Vec<(&ProviderLabel, Histogram)> = self.histograms
+                .iter()
+                .filter(|entry| entry.key().0 == metric)
+                .filter_map(|entry| providers.get(&entry.key().1).map(|provider| (provider, entry.value().clone())))
+                .collect();
+            series.sort_by(|a, b| a.0.id.cmp(&b.0.id));
+            for (provider, histogram) in series {
+                render_histogram(&mut out, name, provider, &histogram);
+            }
+        }
+        out
+    }
+}
+
+fn render_histogram(out: &mut String, name: &str, provider: &ProviderLabel, histogram: &Histogram) {
+    let labels = format!(
+        "provider_id=\"{}\",provider_name=\"{}\",model=\"{}\"",
+        escape_label(&provider.id), escape_label(&provider.name), escape_label(&provider.model)
+    );
+    let mut cumulative = 0;
+    for (bound, count) in BUCKET_BOUNDS_MS.iter().zip(histogram.buckets.iter()) {
+        cumulative += count;
+        let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound / 1000.0, cumulative);
+    }
+    let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, histogram.count);
+    let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, histogram.sum_ms / 1000.0);
+    let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, histogram.count);
+}
+
+/// 注册耗时统计的定期写入任务
+pub fn register(scheduler: &Scheduler, schedules: &SchedulerConfig, db: SqlitePool) {
+    scheduler.register(
+        "provider_latency_flush",
+        "把提供商耗时直方图写入数据库",
+        scheduler::resolve(schedules, "provider_latency_flush", "@every 1m"),
+        move || {
+            let db = db.clone();
+            async move { tracker().persist(&db).await.map_err(|e| format!("写入提供商耗时统计失败: {}", e)) }
+        },
+    );
+}
+
+/// 提供商 api_key 到标签信息的映射，用于指标标签
+pub async fn provider_labels(db: &SqlitePool) -> Result<HashMap<String, ProviderLabel>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String, String, String)>("SELECT api_key, id, name, model_name FROM api_providers")
+        .fetch_all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(api_key, id, name, model)| (api_key, ProviderLabel { id, name, model }))
+        .collect())
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
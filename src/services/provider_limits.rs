@@ -0,0 +1,268 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, SqlitePool};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::services::concurrency_controller::ConcurrencyController;
+use crate::services::provider_pool::ProviderPoolState;
+
+/// 连接信号量状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConnectionLimits {
+    /// 最大并发连接数
+    pub max_connections: u32,
+    /// 当前可用的连接许可（未加载到提供商池时为空）
+    pub available_permits: Option<u32>,
+    /// 当前占用的连接数
+    pub in_use: u32,
+}
+
+/// 自适应并发状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AdaptiveConcurrencyLimits {
+    /// 是否启用自适应并发
+    pub auto_tuning: bool,
+    /// 当前自适应并发上限（尚未处理过请求时为空）
+    pub current_limit: Option<u32>,
+    /// 当前占用的并发数
+    pub in_flight: Option<u32>,
+    /// 距离下次允许减小并发上限的剩余冷却时间(毫秒)
+    pub decrease_cooldown_ms: Option<u64>,
+}
+
+/// 最近用量窗口
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UsageWindow {
+    /// 最近一分钟的请求数
+    pub requests_last_minute: i64,
+    /// 最近一分钟消耗的token数
+    pub tokens_last_minute: i64,
+    /// 提供商池加载以来的请求数
+    pub requests_since_reload: u32,
+    /// 提供商池加载以来消耗的token数
+    pub tokens_since_reload: u32,
+    /// 最近一次使用时间
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// 隔离（熔断）状态
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerState {
+    /// 是否已被隔离
+    pub quarantined: bool,
+    /// 连续鉴权失败次数
+    pub consecutive_auth_failures: i64,
+    /// 达到后删除提供商的连续失败次数
+    pub failure_threshold: u32,
+    /// 隔离时间
+    pub quarantined_at: Option<DateTime<Utc>>,
+}
+
+/// 余额与额度余量
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BudgetHeadroom {
+    /// 是否检查余额
+    pub support_balance_check: bool,
+    /// 当前余额
+    pub balance: f64,
+    /// 最小余额阈值
+    pub min_balance_threshold: f64,
+    /// 余额高出阈值的部分（负数表示已低于阈值）
+    pub balance_headroom: f64,
+    /// 采购额度
+    pub purchased_quota: Option<f64>,
+    /// 按定价计算的累计花费
+    pub spent: f64,
+    /// 剩余采购额度（未设置采购额度时为空）
+    pub quota_remaining: Option<f64>,
+    /// 合同到期时间
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 单个提供商当前受到的所有限制
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProviderLimits {
+    /// 生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 提供商ID
+    pub provider_id: String,
+    /// 提供商名称
+    pub name: String,
+    /// API密钥
+    pub api_key: String,
+    /// 模型名称
+    pub model_name: String,
+    /// 数据库中的状态
+    pub status: String,
+    /// 是否已加载到提供商池
+    pub in_pool: bool,
+    /// 当前是否可被选中处理新请求
+    pub selectable: bool,
+    /// 阻止选中的原因代码（inactive、not_in_pool、expired、balance_below_threshold、
+    /// connections_exhausted、concurrency_limit_reached）
+    pub blocking: Vec<&'static str>,
+    /// 连接信号量
+    pub connections: ConnectionLimits,
+    /// 自适应并发
+    pub concurrency: AdaptiveConcurrencyLimits,
+    /// 最近用量
+    pub usage: UsageWindow,
+    /// 隔离状态
+    pub breaker: BreakerState,
+    /// 余额与额度
+    pub budget: BudgetHeadroom,
+}
+
+#[derive(Debug, FromRow)]
+struct ProviderRow {
+    id: String,
+    name: String,
+    api_key: String,
+    model_name: String,
+    status: String,
+    max_connections: i64,
+    balance: f64,
+    min_balance_threshold: f64,
+    support_balance_check: bool,
+    purchased_quota: Option<f64>,
+    expires_at: Option<DateTime<Utc>>,
+    consecutive_auth_failures: i64,
+    quarantined_at: Option<DateTime<Utc>>,
+}
+
+/// 汇总提供商当前受到的限制，提供商不存在时返回 None
+pub async fn inspect(
+    db: &SqlitePool,
+    pool: &RwLock<ProviderPoolState>,
+    concurrency: &ConcurrencyController,
+    failure_threshold: u32,
+    provider_id: &str,
+) -> Result<Option<ProviderLimits>, sqlx::Error> {
+    let row = sqlx::query_as::<_, ProviderRow>(
+        r#"
+        SELECT
+            id, name, api_key, model_name, status, max_connections,
+            COALESCE(balance, 0.0) AS balance,
+            COALESCE(min_balance_threshold, 0.0) AS min_balance_threshold,
+            support_balance_check, purchased_quota, expires_at,
+            consecutive_auth_failures, quarantined_at
+        FROM api_providers
+        WHERE id = ?
+        "#
+    )
+    .bind(provider_id)
+    .fetch_optional(db)
+    .await?;
+    let row = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+
+    let (requests_last_minute, tokens_last_minute): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*), COALESCE(SUM(total_tokens), 0)
+        FROM api_usage
+        WHERE provider_api_key = ? AND own_key_id IS NULL AND request_time >= ?
+        "#
+    )
+    .bind(&row.api_key)
+    .bind(Utc::now() - Duration::minutes(1))
+    .fetch_one(db)
+    .await?;
+
+    let spent: f64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(cost), 0.0) FROM api_usage WHERE provider_api_key = ? AND own_key_id IS NULL"
+    )
+    .bind(&row.api_key)
+    .fetch_one(db)
+    .await?;
+
+    // 只短暂持有提供商池的锁读取内存状态
+    let (in_pool, available, max_connections, available_permits, usage) = {
+        let pool = pool.read().await;
+        match pool.providers().iter().find(|p| p.api_key == row.api_key) {
+            Some(provider) => (
+                true,
+                pool.is_provider_available(provider),
+                provider.max_connections.max(0) as u32,
+                pool.get_semaphore(&provider.api_key).map(|s| s.available_permits() as u32),
+                pool.token_usage(&provider.api_key),
+            ),
+            None => (false, false, row.max_connections.max(0) as u32, None, None),
+        }
+    };
+
+    let concurrency_limits = AdaptiveConcurrencyLimits {
+        auto_tuning: concurrency.is_enabled(),
+        current_limit: concurrency.current_limit(&row.api_key),
+        in_flight: concurrency.in_flight(&row.api_key),
+        decrease_cooldown_ms: concurrency
+            .decrease_cooldown_remaining(&row.api_key)
+            .map(|remaining| remaining.as_millis() as u64),
+    };
+
+    let mut blocking = Vec::new();
+    if row.status != "Active" {
+        blocking.push("inactive");
+    } else if !in_pool {
+        blocking.push("not_in_pool");
+    }
+    if row.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        blocking.push("expired");
+    }
+    if row.support_balance_check && row.balance < row.min_balance_threshold {
+        blocking.push("balance_below_threshold");
+    }
+    if available_permits == Some(0) {
+        blocking.push("connections_exhausted");
+    }
+    if concurrency_limits.auto_tuning {
+        if let (Some(limit), Some(in_flight)) = (concurrency_limits.current_limit, concurrency_limits.in_flight) {
+            if in_flight >= limit {
+                blocking.push("concurrency_limit_reached");
+            }
+        }
+    }
+
+    Ok(Some(ProviderLimits {
+        generated_at: Utc::now(),
+        provider_id: row.id,
+        name: row.name,
+        api_key: row.api_key,
+        model_name: row.model_name,
+        selectable: in_pool && available && blocking.is_empty(),
+        in_pool,
+        blocking,
+        connections: ConnectionLimits {
+            max_connections,
+            available_permits,
+            in_use: available_permits.map(|p| max_connections.saturating_sub(p)).unwrap_or(0),
+        },
+        concurrency: concurrency_limits,
+        usage: UsageWindow {
+            requests_last_minute,
+            tokens_last_minute,
+            requests_since_reload: usage.as_ref().map(|u| u.request_count).unwrap_or(0),
+            tokens_since_reload: usage.as_ref().map(|u| u.total_tokens).unwrap_or(0),
+            last_used: usage.map(|u| u.last_used),
+        },
+        breaker: BreakerState {
+            quarantined: row.status == "Quarantined",
+            consecutive_auth_failures: row.consecutive_auth_failures,
+            failure_threshold,
+            quarantined_at: row.quarantined_at,
+        },
+        budget: BudgetHeadroom {
+            support_balance_check: row.support_balance_check,
+            balance: row.balance,
+            min_balance_threshold: row.min_balance_threshold,
+            balance_headroom: row.balance - row.min_balance_threshold,
+            purchased_quota: row.purchased_quota,
+            spent,
+            quota_remaining: row.purchased_quota.map(|quota| quota - spent),
+            expires_at: row.expires_at,
+        },
+        status: row.status,
+    }))
+}
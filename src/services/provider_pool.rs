@@ -1,54 +1,257 @@
+use dashmap::DashMap;
 use std::collections::HashMap;
+use rand::seq::SliceRandom;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 // use std::time::Duration; // 未使用，已注释
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{RwLock, Semaphore};
 use chrono::{DateTime, Utc};
-use sqlx::{SqlitePool, Row};
+use sqlx::{sqlite::SqliteRow, SqlitePool, Row};
+use sha2::{Digest, Sha256};
 use tracing::info;
 
 use anyhow::Result;
 
+use crate::config::{OpenRouterConfig, UpstreamTimeoutConfig};
+use crate::services::concurrency_controller::{ConcurrencyController, ConcurrencyPermit};
+use crate::services::key_rate_limiter::KeyRateLimiter;
+use crate::services::provider_latency::{self, LatencyMetric, ProviderLatency};
+use crate::services::{openrouter, provider_cooldown, upstream_client};
+use crate::services::upstream_client::{ConnectionTuning, HttpVersion, TlsOptions};
+use crate::utils::client_key::UpstreamKey;
+use crate::utils::redact::redact;
+
                                 // 最大重试次数
 
+/// 未配置密钥的自托管提供商使用的标识前缀（api_key 同时作为提供商的唯一标识）
+pub const LOCAL_API_KEY_PREFIX: &str = "local:";
+
+/// 自带密钥（BYOK）标识的前缀
+pub const OWN_KEY_ID_PREFIX: &str = "byok:";
+
+// LowestLatency 策略随机选择提供商的比例
+const LATENCY_EXPLORE_RATE: f64 = 0.05;
+// LowestLatency 策略中超过该时长没有新样本的耗时视为过期，提供商按未测量处理（按中位数计分）
+const LATENCY_STALE_AFTER: Duration = Duration::from_secs(300);
+
 // 令牌使用记录
 #[derive(Debug, Clone)]
 pub struct TokenUsage {
     pub last_used: DateTime<Utc>,
     pub total_tokens: u32,
     pub request_count: u32,
+    /// 累计成本（按该提供商的模型定价计算，未配置定价时不增长）
+    pub total_cost: f64,
 }
 
 // 代理池状态
+// 提供商列表只在重新加载或移除提供商时修改（持有写锁）；选择提供商和记录用量只需读锁，
+// 按密钥的状态使用原子计数和 DashMap，并发请求之间互不阻塞
 #[derive(Debug)]
 pub struct ProviderPoolState {
     providers: Vec<ProviderInfo>,
-    current_index: usize,
-    token_usage: HashMap<String, TokenUsage>,
-    connection_semaphores: HashMap<String, Arc<Semaphore>>, // 每个提供商的并发控制
+    current_index: AtomicUsize,
+    token_usage: DashMap<String, TokenUsage>,
+    connection_semaphores: DashMap<String, Arc<Semaphore>>, // 每个提供商的并发控制
+    request_buckets: KeyRateLimiter, // 每个提供商的每分钟请求数令牌桶
+    in_flight: DashMap<String, Arc<AtomicUsize>>, // 每个提供商正在处理的请求数（LeastConnections 策略）
+}
+
+// 占用提供商的一个在途请求计数，释放时归还
+#[derive(Debug)]
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl InFlightGuard {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ProviderInfo {
     pub base_url: String,
     pub api_key: String,
+    /// 客户端自带的上游密钥（BYOK），设置后代替 api_key 用于上游鉴权
+    pub own_api_key: Option<String>,
+    pub provider_type: String,
     pub max_connections: i32,
     pub min_connections: i32,
     pub acquire_timeout_ms: i32,
     pub idle_timeout_ms: i32,
     pub load_balance_strategy: String,
+    /// 每分钟请求数上限（rate_limit），0表示不限制
+    pub requests_per_minute: i64,
+    /// 同一提供商的最大尝试次数（含首次）
     pub retry_attempts: i32,
+    /// 重试间隔(毫秒)
+    pub retry_delay_ms: i64,
+    /// 需要重试的上游状态码（逗号分隔），为空表示所有失败状态码都重试
+    pub retry_on_statuses: Option<String>,
+    /// 连接超时(毫秒)，为空时使用 UPSTREAM_CONNECT_TIMEOUT_MS
+    pub connect_timeout_ms: Option<i64>,
+    /// 请求超时(毫秒)，为空时使用 UPSTREAM_REQUEST_TIMEOUT_MS
+    pub request_timeout_ms: Option<i64>,
+    /// 优先级，数值越大越优先；同一模型只在较高一层饱和或全部失败时才使用较低一层
+    pub priority: i32,
+    /// 每个上游域名最多保留的空闲连接数，为空时不限制
+    pub pool_max_idle_per_host: Option<i64>,
+    /// TCP keepalive 和 HTTP/2 PING 的间隔(秒)，为空时不发送
+    pub keep_alive_secs: Option<i64>,
+    /// HTTP 协议版本：auto、http1 或 http2，为空时按 auto 处理
+    pub http_version: Option<String>,
+    /// 附加请求头（JSON对象），随每个上游请求发送
+    pub extra_headers: Option<String>,
+    /// 额外信任的CA证书文件路径（PEM）
+    pub tls_ca_path: Option<String>,
+    /// 跳过TLS证书校验
+    pub tls_insecure_skip_verify: bool,
     pub balance: f64,
     pub last_balance_check: Option<DateTime<Utc>>,
     pub min_balance_threshold: f64,
     pub support_balance_check: bool,
+    pub balance_check_url: Option<String>,
+    pub stream_pacing_tps: Option<f64>,
+    pub supports_gzip_request: bool,
+    pub supports_structured_output: bool,
+    pub deployment: Option<String>,
+    pub api_version: Option<String>,
+    pub aws_region: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 绑定的组织：设置后只服务该组织的请求，为空表示属于共享池
+    pub organization: Option<String>,
     pub model_name: String,
     pub model_type: String,
     pub model_version: String,
 }
 
+impl ProviderInfo {
+    /// 上游鉴权请求头（小写名称）：Azure OpenAI 部署使用 api-key，其余提供商使用 Bearer 令牌
+    /// Bedrock 提供商使用 SigV4 签名，不使用该请求头；未配置密钥的自托管提供商不发送鉴权请求头
+    pub fn auth_header(&self) -> Option<(&'static str, String)> {
+        let credential = self.credential();
+        if self.is_self_hosted() && credential.starts_with(LOCAL_API_KEY_PREFIX) {
+            None
+        } else if self.deployment.is_some() && !self.is_bedrock() {
+            Some(("api-key", credential.to_string()))
+        } else {
+            Some(("authorization", format!("Bearer {}", credential)))
+        }
+    }
+
+    /// 请求上游使用的密钥：客户端自带密钥优先，否则使用池中密钥
+    pub fn credential(&self) -> &str {
+        self.own_api_key.as_deref().unwrap_or(&self.api_key)
+    }
+
+    /// 自带密钥的标识（密钥摘要），用于使用记录，避免保存密钥明文
+    pub fn own_key_id(&self) -> Option<String> {
+        self.own_api_key.as_ref().map(|own_api_key| {
+            let digest = format!("{:x}", Sha256::digest(own_api_key.as_bytes()));
+            format!("{}{}", OWN_KEY_ID_PREFIX, &digest[..16])
+        })
+    }
+
+    /// 并发控制使用的标识：自带密钥请求单独限制，不占用池中密钥的并发
+    pub fn limit_key(&self) -> String {
+        self.own_key_id().unwrap_or_else(|| self.api_key.clone())
+    }
+
+    /// 是否为自托管的 OpenAI 兼容服务（Ollama、LM Studio 等）
+    pub fn is_self_hosted(&self) -> bool {
+        self.provider_type == "SelfHosted"
+    }
+
+    /// 是否为 OpenRouter 提供商
+    pub fn is_openrouter(&self) -> bool {
+        self.provider_type == "OpenRouter"
+    }
+
+    /// 提供商是否支持请求的模型，OpenRouter 提供商允许省略厂商前缀或带 openrouter/ 前缀
+    pub fn serves_model(&self, model_name: &str) -> bool {
+        self.model_name == model_name
+            || (self.is_openrouter() && openrouter::matches_model(&self.model_name, model_name))
+    }
+
+    /// 上游返回该失败状态码时是否在同一提供商上重试
+    pub fn retries_status(&self, status: u16) -> bool {
+        match self.retry_on_statuses.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(statuses) => statuses.split(',').any(|code| code.trim().parse::<u16>() == Ok(status)),
+            None => true,
+        }
+    }
+
+    /// 连接上游的超时，未单独配置时使用默认值
+    pub fn connect_timeout(&self, defaults: &UpstreamTimeoutConfig) -> Duration {
+        let millis = self.connect_timeout_ms.filter(|ms| *ms > 0).map(|ms| ms as u64);
+        Duration::from_millis(millis.unwrap_or(defaults.connect_timeout_ms))
+    }
+
+    /// 请求上游的超时（流式请求包含接收完整个响应的时间），未单独配置时使用默认值
+    pub fn request_timeout(&self, defaults: &UpstreamTimeoutConfig) -> Duration {
+        let millis = self.request_timeout_ms.filter(|ms| *ms > 0).map(|ms| ms as u64);
+        Duration::from_millis(millis.unwrap_or(defaults.request_timeout_ms))
+    }
+
+    /// 随每个上游请求发送的固定请求头：OpenRouter 的应用归属请求头，以及提供商配置的附加请求头（同名时后者优先）
+    pub fn static_headers(&self, openrouter_config: &OpenRouterConfig) -> Vec<(String, String)> {
+        let extra: Vec<(String, String)> = self.extra_headers
+            .as_deref()
+            .and_then(|json| serde_json::from_str::<HashMap<String, String>>(json).ok())
+            .map(|headers| headers.into_iter().collect())
+            .unwrap_or_default();
+        let mut headers: Vec<(String, String)> = Vec::new();
+        if self.is_openrouter() {
+            headers.extend(
+                openrouter::attribution_headers(openrouter_config)
+                    .into_iter()
+                    .filter(|(name, _)| !extra.iter().any(|(extra_name, _)| extra_name.eq_ignore_ascii_case(name)))
+                    .map(|(name, value)| (name.to_string(), value)),
+            );
+        }
+        headers.extend(extra);
+        headers
+    }
+
+    /// 上游HTTP客户端的连接复用设置
+    pub fn connection_tuning(&self) -> ConnectionTuning {
+        ConnectionTuning {
+            pool_max_idle_per_host: self.pool_max_idle_per_host.filter(|max| *max >= 0).map(|max| max as usize),
+            pool_idle_timeout: Some(Duration::from_millis(self.idle_timeout_ms.max(0) as u64)),
+            keep_alive: self.keep_alive_secs.filter(|secs| *secs > 0).map(|secs| Duration::from_secs(secs as u64)),
+            http_version: HttpVersion::parse(self.http_version.as_deref()),
+        }
+    }
+
+    /// 上游HTTP客户端的TLS设置
+    pub fn tls_options(&self) -> TlsOptions {
+        TlsOptions {
+            ca_path: self.tls_ca_path.clone().filter(|path| !path.trim().is_empty()),
+            insecure_skip_verify: self.tls_insecure_skip_verify,
+        }
+    }
+
+    /// 是否接受 response_format：需标记支持结构化输出，Bedrock 的请求格式转换不携带该参数
+    pub fn accepts_response_format(&self) -> bool {
+        self.supports_structured_output && !self.is_bedrock()
+    }
+
+    /// 是否为 AWS Bedrock 提供商（配置了区域）
+    pub fn is_bedrock(&self) -> bool {
+        self.aws_region.is_some()
+    }
+}
+
 impl ProviderPoolState {
     pub fn new(providers: Vec<ProviderInfo>) -> Self {
-        let mut connection_semaphores = HashMap::new();
+        let connection_semaphores = DashMap::new();
         
         // 为每个提供商创建信号量
         for provider in &providers {
@@ -60,58 +263,143 @@ impl ProviderPoolState {
         
         Self {
             providers,
-            current_index: 0,
-            token_usage: HashMap::new(),
+            current_index: AtomicUsize::new(0),
+            token_usage: DashMap::new(),
             connection_semaphores,
+            request_buckets: KeyRateLimiter::new(),
+            in_flight: DashMap::new(),
+        }
+    }
+
+    // 获取提供商的在途请求计数器，首次使用时创建
+    fn in_flight_counter(&self, api_key: &str) -> Arc<AtomicUsize> {
+        self.in_flight.entry(api_key.to_string()).or_default().clone()
+    }
+
+    // 提供商当前正在处理的请求数
+    pub fn in_flight(&self, api_key: &str) -> usize {
+        self.in_flight
+            .get(api_key)
+            .map(|counter| counter.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    // 扣减提供商的每分钟请求额度，额度不足时返回 false 且不扣减
+    pub fn take_request_quota(&self, provider: &ProviderInfo) -> bool {
+        let requests_per_minute = provider.requests_per_minute.max(0) as u64;
+        let decision = self.request_buckets.check(&provider.api_key, requests_per_minute, 0);
+        if !decision.allowed {
+            tracing::info!(
+                "提供商已达每分钟请求数上限({}): api_key={}, {}秒后恢复",
+                requests_per_minute, redact(&provider.api_key), decision.retry_after_secs
+            );
         }
+        decision.allowed
     }
 
     // 获取提供商的并发控制信号量
     pub fn get_semaphore(&self, api_key: &str) -> Option<Arc<Semaphore>> {
-        self.connection_semaphores.get(api_key).cloned()
+        self.connection_semaphores.get(api_key).map(|semaphore| semaphore.clone())
+    }
+
+    // 选择自带密钥请求使用的提供商模板（仅取其地址和协议配置），不要求池中密钥余额充足
+    pub fn select_own_key_template(&self, model_name: &str, model_type: Option<&str>, provider_type: Option<&str>) -> Option<&ProviderInfo> {
+        self.providers.iter()
+            .filter(|p| p.serves_model(model_name))
+            .filter(|p| model_type.is_none_or(|t| p.model_type == t))
+            .find(|p| provider_type.is_none_or(|t| p.provider_type.eq_ignore_ascii_case(t)))
+    }
+
+    // 获取自带密钥的并发控制信号量，首次使用时按提供商的连接上限创建
+    pub fn own_key_semaphore(&self, limit_key: &str, max_connections: i32) -> Arc<Semaphore> {
+        self.connection_semaphores
+            .entry(limit_key.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_connections as usize)))
+            .clone()
+    }
+
+    // 根据负载均衡策略从共享池选择下一个可用的提供商
+    pub fn select_provider(&self, model_name: &str, strategy: &str, concurrency: &ConcurrencyController) -> Option<&ProviderInfo> {
+        self.select_provider_of_type(model_name, None, strategy, concurrency)
     }
 
-    // 根据负载均衡策略选择下一个可用的提供商
-    pub fn select_provider(&self, model_name: &str, strategy: &str) -> Option<&ProviderInfo> {
+    // 根据负载均衡策略从共享池选择下一个可用的提供商，可限定模型类型（如 Embedding）
+    pub fn select_provider_of_type(
+        &self,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        concurrency: &ConcurrencyController,
+    ) -> Option<&ProviderInfo> {
+        self.select_provider_excluding(model_name, model_type, strategy, None, &[], concurrency)
+    }
+
+    // 根据负载均衡策略选择提供商，跳过 exclude 中的密钥（本次请求已失败的提供商）；
+    // 组织的请求只使用绑定到该组织的提供商，未指定组织时只使用共享池，两者互不占用
+    pub fn select_provider_excluding(
+        &self,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        organization: Option<&str>,
+        exclude: &[String],
+        concurrency: &ConcurrencyController,
+    ) -> Option<&ProviderInfo> {
         if self.providers.is_empty() {
             tracing::info!("没有可用的提供商");
             return None;
         }
 
-        tracing::info!("正在查找模型: {}", model_name);
-        for provider in &self.providers {
-            tracing::info!(
-                "检查提供商: base_url={}, model_name={}, balance={}, available={}", 
-                provider.base_url,
-                provider.model_name,
-                provider.balance,
-                self.is_provider_available(provider)
-            );
-        }
+        tracing::debug!("正在查找模型: {}", model_name);
 
         // 先过滤出余额充足且支持指定模型的提供商
-        let available_providers: Vec<&ProviderInfo> = self.providers.iter()
-            .filter(|p| self.is_provider_available(p) && p.model_name == model_name)
+        let candidates: Vec<&ProviderInfo> = self.providers.iter()
+            .filter(|p| self.is_provider_available(p) && p.serves_model(model_name))
+            .filter(|p| model_type.is_none_or(|t| p.model_type == t))
+            .filter(|p| p.organization.as_deref() == organization)
+            .filter(|p| !exclude.contains(&p.api_key))
             .collect();
 
-        if available_providers.is_empty() {
-            tracing::info!("没有找到支持模型 {} 的可用提供商", model_name);
+        if candidates.is_empty() {
+            tracing::info!("没有找到支持模型 {} 的可用提供商（组织: {}）", model_name, organization.unwrap_or("共享池"));
             return None;
         }
 
+        // 只在优先级最高的一层中选择：连接许可已满的提供商不参与定层，整层饱和时才使用较低一层，
+        // 所有层都饱和时仍使用最高一层（由调用方排队等待连接许可）；
+        // 启用并发自适应时连接许可由控制器发放，按控制器的剩余名额判断
+        let saturated = |p: &ProviderInfo| {
+            if concurrency.is_enabled() {
+                return concurrency.is_saturated(&p.limit_key());
+            }
+            self.connection_semaphores
+                .get(&p.api_key)
+                .is_some_and(|semaphore| semaphore.available_permits() == 0)
+        };
+        let tier = candidates.iter()
+            .filter(|p| !saturated(p))
+            .map(|p| p.priority)
+            .max()
+            .or_else(|| candidates.iter().map(|p| p.priority).max())?;
+        let available_providers: Vec<&ProviderInfo> = candidates.into_iter()
+            .filter(|p| p.priority == tier)
+            .collect();
+
         // 从可用的提供商中选择一个
         match strategy {
             "RoundRobin" => {
-                let provider_index = self.current_index % available_providers.len();
+                let provider_index = self.current_index.load(Ordering::Relaxed) % available_providers.len();
                 available_providers.get(provider_index).copied()
             }
+            // 按正在处理的请求数选择，相同时按累计请求数
             "LeastConnections" => {
                 available_providers.iter()
                     .min_by_key(|p| {
-                        self.token_usage
+                        let requests = self.token_usage
                             .get(&p.api_key)
                             .map(|u| u.request_count)
-                            .unwrap_or(0)
+                            .unwrap_or(0);
+                        (self.in_flight(&p.api_key), requests)
                     })
                     .copied()
             }
@@ -125,23 +413,98 @@ impl ProviderPoolState {
                     })
                     .copied()
             }
+            // 按累计成本均衡，使单价不同的密钥以相近的速度消耗金额；成本相同（如都未配置定价）时按token数
+            "LeastCost" => {
+                let usage = |p: &ProviderInfo| {
+                    self.token_usage
+                        .get(&p.api_key)
+                        .map(|u| (u.total_cost, u.total_tokens))
+                        .unwrap_or((0.0, 0))
+                };
+                available_providers.iter()
+                    .min_by(|a, b| {
+                        let (a_cost, a_tokens) = usage(a);
+                        let (b_cost, b_tokens) = usage(b);
+                        a_cost.total_cmp(&b_cost).then(a_tokens.cmp(&b_tokens))
+                    })
+                    .copied()
+            }
+            // 按最近的请求耗时选择最快的提供商；一部分请求随机选择，使未测量和过期的测量能够更新
+            "LowestLatency" => {
+                if rand::random::<f64>() < LATENCY_EXPLORE_RATE {
+                    return available_providers.choose(&mut rand::thread_rng()).copied();
+                }
+                self.select_lowest_latency(&available_providers, &provider_latency::tracker(), LATENCY_STALE_AFTER)
+            }
             _ => {
                 available_providers.first().copied()
             }
         }
     }
 
+    // 按最近请求耗时（p50 与 p95 的均值）选择最快的提供商。没有样本或样本过期的提供商
+    // 按已测量提供商的中位数计分，同分时已测量的优先：失败（没有成功样本）的提供商不会因此独占流量，
+    // 也不会永远不被选中；都没有测量时按轮询选择
+    fn select_lowest_latency<'a>(
+        &self,
+        providers: &[&'a ProviderInfo],
+        latency: &ProviderLatency,
+        stale_after: Duration,
+    ) -> Option<&'a ProviderInfo> {
+        let scores: Vec<Option<f64>> = providers.iter()
+            .map(|p| {
+                latency.recent(&p.api_key)
+                    .filter(|recent| recent.last_observed.elapsed() < stale_after)
+                    .map(|recent| (recent.p50_ms + recent.p95_ms) / 2.0)
+            })
+            .collect();
+        let mut measured: Vec<f64> = scores.iter().flatten().copied().collect();
+        if measured.is_empty() {
+            return providers.get(self.current_index.load(Ordering::Relaxed) % providers.len().max(1)).copied();
+        }
+        measured.sort_by(f64::total_cmp);
+        let neutral = measured[measured.len() / 2];
+
+        providers.iter()
+            .zip(scores)
+            .map(|(p, score)| (*p, score.unwrap_or(neutral), score.is_none()))
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.2.cmp(&b.2)))
+            .map(|(p, _, _)| p)
+    }
+
+    // 是否有可用于该模型的提供商（组织专属池或共享池）
+    pub fn has_available_provider(&self, model_name: &str, model_type: Option<&str>, organization: Option<&str>) -> bool {
+        self.providers.iter().any(|p| {
+            p.serves_model(model_name)
+                && model_type.is_none_or(|t| p.model_type == t)
+                && p.organization.as_deref() == organization
+                && self.is_provider_available(p)
+        })
+    }
+
+    // 按密钥查找仍可用于该模型和组织的提供商（会话亲和）
+    pub fn find_available(&self, api_key: &str, model_name: &str, organization: Option<&str>) -> Option<&ProviderInfo> {
+        self.providers.iter().find(|p| {
+            p.api_key == api_key
+                && p.serves_model(model_name)
+                && p.organization.as_deref() == organization
+                && self.is_provider_available(p)
+        })
+    }
+
     // 更新轮询索引
-    pub fn update_index(&mut self) {
-        self.current_index = (self.current_index + 1) % self.providers.len();
+    pub fn update_index(&self) {
+        let len = self.providers.len().max(1);
+        let _ = self.current_index.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |index| Some((index + 1) % len));
     }
 
     // 更新令牌使用情况
-    pub fn update_usage(&mut self, api_key: &str, tokens: u32) {
-        let usage = self.token_usage.entry(api_key.to_string()).or_insert(TokenUsage {
+    pub fn update_usage(&self, api_key: &str, tokens: u32) {
+        let mut usage = self.token_usage.entry(api_key.to_string()).or_insert(TokenUsage {
             last_used: Utc::now(),
             total_tokens: 0,
             request_count: 0,
+            total_cost: 0.0,
         });
         
         usage.last_used = Utc::now();
@@ -149,8 +512,24 @@ impl ProviderPoolState {
         usage.request_count += 1;
     }
 
+    // 累加按定价计算的请求成本（LeastCost 策略）
+    pub fn record_cost(&self, api_key: &str, cost: f64) {
+        if let Some(mut usage) = self.token_usage.get_mut(api_key) {
+            usage.total_cost += cost;
+        }
+    }
+
     // 检查提供商是否可用
     pub fn is_provider_available(&self, provider: &ProviderInfo) -> bool {
+        // 合同已到期的提供商不再使用（定期任务停用前也不会被选中）
+        if provider.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return false;
+        }
+        // 连续限流或服务端错误而冷却中的提供商暂不使用
+        if provider_cooldown::cooldowns().is_some_and(|cooldowns| cooldowns.is_cooling(&provider.api_key)) {
+            return false;
+        }
+
         // 检查token余额是否充足
         if provider.support_balance_check {
             // 如果支持余额检查，需要检查余额是否充足
@@ -160,33 +539,168 @@ impl ProviderPoolState {
         }
     }
 
+    // 获取提供商列表（只读）
+    pub fn providers(&self) -> &[ProviderInfo] {
+        &self.providers
+    }
+
+    // 获取提供商的令牌使用记录（副本）
+    pub fn token_usage(&self, api_key: &str) -> Option<TokenUsage> {
+        self.token_usage.get(api_key).map(|usage| usage.clone())
+    }
+
     // 获取所有提供商
     pub fn get_providers(&mut self) -> &mut Vec<ProviderInfo> {
         &mut self.providers
     }
 
+    // 新增提供商，已存在同一密钥的提供商时就地更新
+    pub fn add_provider(&mut self, provider: ProviderInfo) {
+        if self.providers.iter().any(|p| p.api_key == provider.api_key) {
+            self.update_provider(provider);
+            return;
+        }
+        info!("向提供商池新增提供商: {}", redact(&provider.api_key));
+        self.connection_semaphores.insert(
+            provider.api_key.clone(),
+            Arc::new(Semaphore::new(provider.max_connections as usize))
+        );
+        self.providers.push(provider);
+    }
+
+    // 就地更新提供商的配置，保留其用量统计和在途计数；
+    // 连接上限变化时调整现有信号量的许可数，已借出的许可仍然有效
+    pub fn update_provider(&mut self, provider: ProviderInfo) {
+        let Some(existing) = self.providers.iter_mut().find(|p| p.api_key == provider.api_key) else {
+            return self.add_provider(provider);
+        };
+        let old_limit = existing.max_connections.max(0) as usize;
+        let new_limit = provider.max_connections.max(0) as usize;
+        if new_limit != old_limit {
+            if let Some(semaphore) = self.connection_semaphores.get(&provider.api_key) {
+                if new_limit > old_limit {
+                    semaphore.add_permits(new_limit - old_limit);
+                } else {
+                    let forgotten = semaphore.forget_permits(old_limit - new_limit);
+                    if forgotten < old_limit - new_limit {
+                        tracing::warn!(
+                            "提供商连接上限下调时部分许可仍被占用，实际上限暂时高于配置: api_key={}, 新上限={}",
+                            redact(&provider.api_key), new_limit
+                        );
+                    }
+                }
+            }
+        }
+        info!("已更新提供商池中的提供商: {}", redact(&provider.api_key));
+        *existing = provider;
+    }
+
     // 新增方法：从内存中移除提供商
     pub fn remove_provider(&mut self, api_key: &str) {
         let initial_len = self.providers.len();
         self.providers.retain(|p| p.api_key != api_key);
         if self.providers.len() < initial_len {
-             info!("已从 ProviderPoolState 内存中移除提供商及其相关状态: {}", api_key);
+             info!("已从 ProviderPoolState 内存中移除提供商及其相关状态: {}", redact(api_key));
              // 移除信号量和使用记录
              self.connection_semaphores.remove(api_key);
              self.token_usage.remove(api_key);
+             self.in_flight.remove(api_key);
 
-             // 如果移除后 current_index 超出范围，重置为 0
-             if self.current_index >= self.providers.len() && !self.providers.is_empty() {
-                 self.current_index = 0;
-             }
-             // 如果 providers 为空， current_index 保持为 0 或其他合适的值
-             else if self.providers.is_empty() {
-                 self.current_index = 0;
+             // 如果移除后 current_index 超出范围（或 providers 为空），重置为 0
+             if *self.current_index.get_mut() >= self.providers.len() {
+                 *self.current_index.get_mut() = 0;
              }
         }
     }
 }
 
+// 提供商池读取的列
+const PROVIDER_SELECT: &str = r#"
+    SELECT 
+        base_url,
+        api_key,
+        provider_type,
+        max_connections,
+        1 as min_connections,
+        acquire_timeout_ms,
+        60000 as idle_timeout_ms,
+        'RoundRobin' as load_balance_strategy,
+        rate_limit as requests_per_minute,
+        retry_attempts,
+        retry_delay_ms,
+        retry_on_statuses,
+        connect_timeout_ms,
+        request_timeout_ms,
+        priority,
+        pool_max_idle_per_host,
+        keep_alive_secs,
+        http_version,
+        extra_headers,
+        tls_ca_path,
+        tls_insecure_skip_verify,
+        balance,
+        last_balance_check,
+        min_balance_threshold,
+        support_balance_check,
+        balance_check_url,
+        stream_pacing_tps,
+        supports_gzip_request,
+        supports_structured_output,
+        deployment,
+        api_version,
+        aws_region,
+        expires_at,
+        organization,
+        model_name,
+        model_type,
+        '1.0' as model_version
+    FROM api_providers
+"#;
+
+// 从查询结果构建 ProviderInfo
+fn provider_from_row(row: &SqliteRow) -> ProviderInfo {
+    ProviderInfo {
+        base_url: row.get("base_url"),
+        api_key: row.get("api_key"),
+        own_api_key: None,
+        provider_type: row.get("provider_type"),
+        max_connections: row.get("max_connections"),
+        min_connections: row.get("min_connections"),
+        acquire_timeout_ms: row.get("acquire_timeout_ms"),
+        idle_timeout_ms: row.get("idle_timeout_ms"),
+        load_balance_strategy: row.get("load_balance_strategy"),
+        requests_per_minute: row.get("requests_per_minute"),
+        retry_attempts: row.get("retry_attempts"),
+        retry_delay_ms: row.get("retry_delay_ms"),
+        retry_on_statuses: row.get("retry_on_statuses"),
+        connect_timeout_ms: row.get("connect_timeout_ms"),
+        request_timeout_ms: row.get("request_timeout_ms"),
+        priority: row.get("priority"),
+        pool_max_idle_per_host: row.get("pool_max_idle_per_host"),
+        keep_alive_secs: row.get("keep_alive_secs"),
+        http_version: row.get("http_version"),
+        extra_headers: row.get("extra_headers"),
+        tls_ca_path: row.get("tls_ca_path"),
+        tls_insecure_skip_verify: row.get("tls_insecure_skip_verify"),
+        balance: row.get("balance"),
+        last_balance_check: row.get("last_balance_check"),
+        min_balance_threshold: row.get("min_balance_threshold"),
+        support_balance_check: row.get("support_balance_check"),
+        balance_check_url: row.get("balance_check_url"),
+        stream_pacing_tps: row.get("stream_pacing_tps"),
+        supports_gzip_request: row.get("supports_gzip_request"),
+        supports_structured_output: row.get("supports_structured_output"),
+        deployment: row.get("deployment"),
+        api_version: row.get("api_version"),
+        aws_region: row.get("aws_region"),
+        expires_at: row.get("expires_at"),
+        organization: row.get("organization"),
+        model_name: row.get("model_name"),
+        model_type: row.get("model_type"),
+        model_version: row.get("model_version"),
+    }
+}
+
 // 从数据库初始化代理池
 pub async fn initialize_provider_pool(pool: &SqlitePool) -> Result<ProviderPoolState> {
     info!("开始从数据库初始化提供商池...");
@@ -200,123 +714,506 @@ pub async fn initialize_provider_pool(pool: &SqlitePool) -> Result<ProviderPoolS
     
     info!("数据库中活跃的提供商总数: {}", total_count);
     
-    let providers = sqlx::query(
-        r#"
-        SELECT 
-            base_url,
-            api_key,
-            rate_limit as max_connections,
-            1 as min_connections,
-            3000 as acquire_timeout_ms,
-            60000 as idle_timeout_ms,
-            'RoundRobin' as load_balance_strategy,
-            3 as retry_attempts,
-            balance,
-            last_balance_check,
-            min_balance_threshold,
-            support_balance_check,
-            model_name,
-            'text' as model_type,
-            '1.0' as model_version
-        FROM api_providers
-        WHERE status = 'Active'
-        "#
-    )
-    .fetch_all(pool)
-    .await?;
+    let providers = sqlx::query(&format!("{} WHERE status = 'Active'", PROVIDER_SELECT))
+        .fetch_all(pool)
+        .await?;
 
-    let mut provider_info_vec = Vec::new();
-    for row in providers {
-        let provider_info = ProviderInfo {
-            base_url: row.get("base_url"),
-            api_key: row.get("api_key"),
-            max_connections: row.get("max_connections"),
-            min_connections: row.get("min_connections"),
-            acquire_timeout_ms: row.get("acquire_timeout_ms"),
-            idle_timeout_ms: row.get("idle_timeout_ms"),
-            load_balance_strategy: row.get("load_balance_strategy"),
-            retry_attempts: row.get("retry_attempts"),
-            balance: row.get("balance"),
-            last_balance_check: row.get("last_balance_check"),
-            min_balance_threshold: row.get("min_balance_threshold"),
-            support_balance_check: row.get("support_balance_check"),
-            model_name: row.get("model_name"),
-            model_type: row.get("model_type"),
-            model_version: row.get("model_version"),
-        };
-        provider_info_vec.push(provider_info);
-    }
+    let provider_info_vec: Vec<ProviderInfo> = providers.iter().map(provider_from_row).collect();
 
     info!("初始化提供商池，加载了 {} 个API提供商", provider_info_vec.len());
+    upstream_client::prewarm(&provider_info_vec);
     
     Ok(ProviderPoolState::new(provider_info_vec))
 }
 
+/// 按ID从数据库读取提供商（不限状态）
+pub async fn load_provider(db: &SqlitePool, provider_id: &str) -> Result<Option<ProviderInfo>> {
+    let row = sqlx::query(&format!("{} WHERE id = ?", PROVIDER_SELECT))
+        .bind(provider_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(row.as_ref().map(provider_from_row))
+}
+
+// 按密钥从数据库重新读取提供商并就地更新代理池：新增或修改的提供商写入池中，
+// 已不是 Active 状态的从池中移除；其余提供商的用量统计、在途计数和连接许可不受影响
+pub async fn refresh_providers(db: &SqlitePool, provider_pool: &RwLock<ProviderPoolState>, api_keys: &[String]) -> Result<()> {
+    let mut providers = Vec::with_capacity(api_keys.len());
+    for api_key in api_keys {
+        let row = sqlx::query(&format!("{} WHERE status = 'Active' AND api_key = ?", PROVIDER_SELECT))
+            .bind(api_key)
+            .fetch_optional(db)
+            .await?;
+        providers.push((api_key, row.as_ref().map(provider_from_row)));
+    }
+
+    let added: Vec<ProviderInfo> = providers.iter().filter_map(|(_, provider)| provider.clone()).collect();
+    {
+        let mut pool = provider_pool.write().await;
+        for (api_key, provider) in providers {
+            match provider {
+                Some(provider) => pool.add_provider(provider),
+                None => pool.remove_provider(api_key),
+            }
+        }
+        info!("提供商池已更新 {} 个提供商，当前有 {} 个提供商", api_keys.len(), pool.providers().len());
+    }
+    upstream_client::prewarm(&added);
+    Ok(())
+}
+
 // Token管理器
 pub struct TokenManager {
-    pool: Arc<Mutex<ProviderPoolState>>,
+    pool: Arc<RwLock<ProviderPoolState>>,
     pub provider: ProviderInfo,
+    concurrency: Arc<ConcurrencyController>,
     _connection_permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    _concurrency_permit: Option<ConcurrencyPermit>,
+    _in_flight: Option<InFlightGuard>,
 }
 
 impl TokenManager {
-    pub async fn new(pool: Arc<Mutex<ProviderPoolState>>, model_name: &str, strategy: &str) -> Option<Self> {
-        let (provider, semaphore) = {
-            let mut state = pool.lock().await;
-            
-            // 选择提供商
-            let selected = match state.select_provider(model_name, strategy) {
-                Some(p) => {
-                    tracing::info!("找到可用提供商: base_url={}, api_key={}", p.base_url, p.api_key);
-                    let provider = p.clone();
-                    // 更新索引（仅用于RoundRobin策略）
-                    if strategy == "RoundRobin" {
-                        state.update_index();
-                    }
-                    provider
+    pub async fn new(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        model_name: &str,
+        strategy: &str,
+    ) -> Option<Self> {
+        Self::new_of_type(pool, concurrency, model_name, None, strategy, None).await
+    }
+
+    // 选择指定模型类型的提供商（如 Embedding），organization 为请求方所属组织
+    pub async fn new_of_type(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        organization: Option<&str>,
+    ) -> Option<Self> {
+        Self::new_excluding(pool, concurrency, model_name, model_type, strategy, organization, &[]).await
+    }
+
+    // 按策略从池中选择提供商，跳过 exclude 中的密钥；选中的提供商没有空闲的连接许可时排队等待，
+    // 等待超时则跳过它重新选择。整个选择过程共用一个期限（首个选中提供商的 acquire_timeout_ms），
+    // 期限过后只尝试立即可用的许可，不会按提供商数量累计等待。
+    // 取得许可后才扣减每分钟请求额度，额度不足时归还许可并跳过该提供商
+    async fn new_excluding(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        organization: Option<&str>,
+        exclude: &[String],
+    ) -> Option<Self> {
+        let mut exclude = exclude.to_vec();
+        let mut deadline = None;
+        loop {
+            let (provider, semaphore) = Self::select(&pool, &concurrency, model_name, model_type, strategy, organization, &exclude).await?;
+            let deadline = *deadline.get_or_insert_with(|| {
+                Instant::now() + Duration::from_millis(provider.acquire_timeout_ms.max(0) as u64)
+            });
+            let wait = deadline.saturating_duration_since(Instant::now());
+            let api_key = provider.api_key.clone();
+            match Self::with_permits(pool.clone(), concurrency.clone(), provider, semaphore, wait).await {
+                Some(manager) if pool.read().await.take_request_quota(&manager.provider) => return Some(manager),
+                Some(_) => {}
+                None => tracing::info!("提供商连接许可已满，尝试下一个提供商: api_key={}", redact(&api_key)),
+            }
+            exclude.push(api_key);
+        }
+    }
+
+    // 按策略选择提供商并取得其信号量
+    async fn select(
+        pool: &Arc<RwLock<ProviderPoolState>>,
+        concurrency: &ConcurrencyController,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        organization: Option<&str>,
+        exclude: &[String],
+    ) -> Option<(ProviderInfo, Arc<Semaphore>)> {
+        let state = pool.read().await;
+
+        // 选择提供商
+        let selected = match state.select_provider_excluding(model_name, model_type, strategy, organization, exclude, concurrency) {
+            Some(p) => {
+                tracing::info!("找到可用提供商: base_url={}, api_key={}", p.base_url, redact(&p.api_key));
+                let provider = p.clone();
+                // 更新索引（仅用于RoundRobin策略）
+                if strategy == "RoundRobin" {
+                    state.update_index();
                 }
+                provider
+            }
+            None => {
+                tracing::info!("没有找到可用提供商");
+                return None;
+            }
+        };
+
+        // 获取信号量
+        let semaphore = match state.get_semaphore(&selected.api_key) {
+            Some(s) => {
+                tracing::info!("获取到提供商的信号量");
+                s
+            },
+            None => {
+                tracing::error!("无法获取提供商的信号量: api_key={}", redact(&selected.api_key));
+                return None;
+            }
+        };
+
+        Some((selected, semaphore))
+    }
+
+    // 使用客户端自带的上游密钥：按模型（及可选的提供商类型）选择提供商作为模板并替换密钥，
+    // 不占用池中密钥的信号量，并发按自带密钥单独限制；用量归属请求方所属组织
+    pub async fn with_upstream_key(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        model_name: &str,
+        model_type: Option<&str>,
+        organization: Option<&str>,
+        upstream_key: &UpstreamKey,
+    ) -> Option<Self> {
+        let (provider, semaphore) = {
+            let state = pool.read().await;
+
+            let mut provider = match state.select_own_key_template(model_name, model_type, upstream_key.provider_type.as_deref()) {
+                Some(p) => p.clone(),
                 None => {
-                    tracing::info!("没有找到可用提供商");
+                    tracing::info!(
+                        "没有找到支持模型 {} 的提供商（自带密钥，提供商类型: {}）",
+                        model_name,
+                        upstream_key.provider_type.as_deref().unwrap_or("任意")
+                    );
                     return None;
                 }
             };
-            
-            // 获取信号量
-            let semaphore = match state.get_semaphore(&selected.api_key) {
-                Some(s) => {
-                    tracing::info!("获取到提供商的信号量");
-                    s
-                },
+            provider.own_api_key = Some(upstream_key.api_key.clone());
+            provider.organization = organization.map(str::to_string);
+
+            let limit_key = provider.limit_key();
+            tracing::info!("使用自带密钥: base_url={}, 标识={}", provider.base_url, limit_key);
+            let semaphore = state.own_key_semaphore(&limit_key, provider.max_connections);
+            (provider, semaphore)
+        };
+
+        let wait = Duration::from_millis(provider.acquire_timeout_ms.max(0) as u64);
+        Self::with_permits(pool, concurrency, provider, semaphore, wait).await
+    }
+
+    // 选择提供商：携带自带密钥时使用该密钥，否则从池中（组织专属池或共享池）按策略选择
+    pub async fn acquire(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        organization: Option<&str>,
+        upstream_key: Option<&UpstreamKey>,
+    ) -> Option<Self> {
+        match upstream_key {
+            Some(upstream_key) => Self::with_upstream_key(pool, concurrency, model_name, model_type, organization, upstream_key).await,
+            None => Self::new_of_type(pool, concurrency, model_name, model_type, strategy, organization).await,
+        }
+    }
+
+    // 请求失败后重新选择提供商，跳过 tried 中已尝试过的（按 limit_key）；
+    // 自带密钥只有一个上游密钥，已尝试过时不再重试
+    #[allow(clippy::too_many_arguments)]
+    pub async fn acquire_excluding(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        model_name: &str,
+        model_type: Option<&str>,
+        strategy: &str,
+        organization: Option<&str>,
+        upstream_key: Option<&UpstreamKey>,
+        tried: &[String],
+    ) -> Option<Self> {
+        match upstream_key {
+            Some(_) if !tried.is_empty() => None,
+            Some(upstream_key) => Self::with_upstream_key(pool, concurrency, model_name, model_type, organization, upstream_key).await,
+            None => Self::new_excluding(pool, concurrency, model_name, model_type, strategy, organization, tried).await,
+        }
+    }
+
+    // 使用指定的提供商（会话亲和）：提供商已不可用或没有空闲的连接许可时返回空，由调用方按策略重新选择
+    pub async fn acquire_preferred(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        api_key: &str,
+        model_name: &str,
+        organization: Option<&str>,
+    ) -> Option<Self> {
+        let (provider, semaphore) = {
+            let state = pool.read().await;
+            let provider = state.find_available(api_key, model_name, organization)?.clone();
+            let semaphore = state.get_semaphore(&provider.api_key)?;
+            (provider, semaphore)
+        };
+        tracing::info!("会话亲和：使用之前的提供商 base_url={}", provider.base_url);
+        // 不等待连接许可，之前的提供商繁忙或已达每分钟请求数上限时直接按策略重新选择
+        let manager = Self::with_permits(pool.clone(), concurrency, provider, semaphore, Duration::ZERO).await?;
+        if !pool.read().await.take_request_quota(&manager.provider) {
+            return None;
+        }
+        Some(manager)
+    }
+
+    // 获取连接许可：启用并发自适应时由控制器限制并发，否则使用静态信号量；
+    // 许可已满时最多排队等待 wait，超时返回空
+    async fn with_permits(
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        provider: ProviderInfo,
+        semaphore: Arc<Semaphore>,
+        wait: Duration,
+    ) -> Option<Self> {
+        let (permit, concurrency_permit) = if concurrency.is_enabled() {
+            match concurrency.acquire(&provider.limit_key(), provider.max_connections, wait).await {
+                Some(permit) => (None, Some(permit)),
                 None => {
-                    tracing::error!("无法获取提供商的信号量: api_key={}", selected.api_key);
+                    tracing::error!("无法获取连接许可: 已达自适应并发上限，等待 {}ms 后仍无空闲名额", wait.as_millis());
                     return None;
                 }
+            }
+        } else {
+            let acquired = match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Ok(permit),
+                Err(_) => match tokio::time::timeout(wait, semaphore.acquire_owned()).await {
+                    Ok(result) => result.map_err(|e| e.to_string()),
+                    Err(_) => Err(format!("等待 {}ms 后仍无空闲连接", wait.as_millis())),
+                },
             };
-            
-            (selected, semaphore)
+            match acquired {
+                Ok(permit) => {
+                    tracing::info!("成功获取连接许可");
+                    (Some(permit), None)
+                },
+                Err(e) => {
+                    tracing::error!("无法获取连接许可: {}", e);
+                    return None;
+                }
+            }
         };
 
-        // 尝试获取连接许可
-        let permit = match semaphore.try_acquire_owned() {
-            Ok(permit) => {
-                tracing::info!("成功获取连接许可");
-                Some(permit)
-            },
-            Err(e) => {
-                tracing::error!("无法获取连接许可: {}", e);
-                return None;
-            }
+        // 自带密钥请求不占用池中提供商的在途计数
+        let in_flight = match provider.own_api_key {
+            Some(_) => None,
+            None => Some(InFlightGuard::new(pool.read().await.in_flight_counter(&provider.api_key))),
         };
-        
+
         Some(Self {
-            pool: pool.clone(),
+            pool,
             provider,
+            concurrency,
             _connection_permit: permit,
+            _concurrency_permit: concurrency_permit,
+            _in_flight: in_flight,
         })
     }
 
+    // 记录请求成功及延迟，用于并发自适应和提供商耗时统计
+    pub fn record_success(&self, latency: std::time::Duration) {
+        // 自带密钥请求不计入池中提供商的耗时统计
+        if self.provider.own_api_key.is_none() {
+            provider_latency::tracker().observe(LatencyMetric::Request, &self.provider.api_key, latency);
+            if let Some(cooldowns) = provider_cooldown::cooldowns() {
+                cooldowns.record_success(&self.provider.api_key);
+            }
+        }
+        if self.concurrency.is_enabled() {
+            self.concurrency.record_success(&self.provider.limit_key(), latency);
+        }
+    }
+
+    // 记录流式请求的首字延迟
+    pub fn record_first_token(&self, latency: std::time::Duration) {
+        if self.provider.own_api_key.is_none() {
+            provider_latency::tracker().observe(LatencyMetric::FirstToken, &self.provider.api_key, latency);
+        }
+    }
+
+    /// 记录上游响应：限流(429)时按 Retry-After 等响应头暂停使用该提供商，并统计失败状态码
+    pub fn record_upstream_response(&self, response: &reqwest::Response) {
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            provider_cooldown::respect_retry_after(&self.provider, response.headers());
+        }
+        self.record_upstream_status(response.status());
+    }
+
+    // 记录上游返回的状态码：限流用于并发自适应，连续限流或服务端错误时提供商进入冷却
+    pub fn record_upstream_status(&self, status: reqwest::StatusCode) {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            self.record_rate_limited();
+        }
+        if self.provider.own_api_key.is_some() {
+            return;
+        }
+        if let Some(cooldowns) = provider_cooldown::cooldowns() {
+            cooldowns.record_status(&self.provider, status);
+        }
+    }
+
+    // 记录上游限流，用于并发自适应
+    pub fn record_rate_limited(&self) {
+        if self.concurrency.is_enabled() {
+            self.concurrency.record_rate_limited(&self.provider.limit_key());
+        }
+    }
+
     pub async fn update_usage(&self, tokens: u32) {
-        let mut state = self.pool.lock().await;
-        state.update_usage(&self.provider.api_key, tokens);
+        // 自带密钥请求不计入池中提供商的用量（LeastTokens 策略）
+        if self.provider.own_api_key.is_some() {
+            return;
+        }
+        self.pool.read().await.update_usage(&self.provider.api_key, tokens);
     }
-} 
\ No newline at end of file
+
+    pub async fn record_cost(&self, cost: Option<f64>) {
+        // 自带密钥请求不计入池中提供商的成本（LeastCost 策略）
+        let cost = match cost {
+            Some(cost) if self.provider.own_api_key.is_none() => cost,
+            _ => return,
+        };
+        self.pool.read().await.record_cost(&self.provider.api_key, cost);
+    }
+} 
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::ConcurrencyConfig;
+
+    /// 测试用的共享池提供商（模型 gpt-4o，最多1个并发）
+    pub(crate) fn provider(api_key: &str, priority: i32) -> ProviderInfo {
+        ProviderInfo {
+            base_url: "https://api.example.com/v1/chat/completions".to_string(),
+            api_key: api_key.to_string(),
+            own_api_key: None,
+            provider_type: "OpenAI".to_string(),
+            max_connections: 1,
+            min_connections: 1,
+            acquire_timeout_ms: 0,
+            idle_timeout_ms: 60000,
+            load_balance_strategy: "RoundRobin".to_string(),
+            requests_per_minute: 0,
+            retry_attempts: 1,
+            retry_delay_ms: 0,
+            retry_on_statuses: None,
+            connect_timeout_ms: None,
+            request_timeout_ms: None,
+            priority,
+            pool_max_idle_per_host: None,
+            keep_alive_secs: None,
+            http_version: None,
+            extra_headers: None,
+            tls_ca_path: None,
+            tls_insecure_skip_verify: false,
+            balance: 0.0,
+            last_balance_check: None,
+            min_balance_threshold: 0.0,
+            support_balance_check: false,
+            balance_check_url: None,
+            stream_pacing_tps: None,
+            supports_gzip_request: false,
+            supports_structured_output: true,
+            deployment: None,
+            api_version: None,
+            aws_region: None,
+            expires_at: None,
+            organization: None,
+            model_name: "gpt-4o".to_string(),
+            model_type: "Chat".to_string(),
+            model_version: "1.0".to_string(),
+        }
+    }
+
+    fn controller(auto_tuning: bool) -> Arc<ConcurrencyController> {
+        Arc::new(ConcurrencyController::new(ConcurrencyConfig {
+            auto_tuning,
+            min_limit: 1,
+            max_limit: 10,
+            latency_target_ms: 10000,
+        }))
+    }
+
+    fn select<'a>(pool: &'a ProviderPoolState, exclude: &[String], concurrency: &ConcurrencyController) -> Option<&'a str> {
+        pool.select_provider_excluding("gpt-4o", None, "RoundRobin", None, exclude, concurrency)
+            .map(|p| p.api_key.as_str())
+    }
+
+    #[test]
+    fn prefers_highest_priority_tier() {
+        let pool = ProviderPoolState::new(vec![provider("low", 0), provider("high", 10), provider("mid", 5)]);
+        let concurrency = controller(false);
+
+        assert_eq!(select(&pool, &[], &concurrency), Some("high"));
+        assert_eq!(select(&pool, &["high".to_string()], &concurrency), Some("mid"));
+        assert_eq!(select(&pool, &["high".to_string(), "mid".to_string()], &concurrency), Some("low"));
+        assert!(pool.select_provider("gpt-3.5", "RoundRobin", &concurrency).is_none());
+    }
+
+    #[test]
+    fn spills_to_lower_tier_when_connection_permits_are_exhausted() {
+        let pool = ProviderPoolState::new(vec![provider("high", 10), provider("low", 0)]);
+        let concurrency = controller(false);
+
+        let high = pool.get_semaphore("high").unwrap().try_acquire_owned().unwrap();
+        assert_eq!(select(&pool, &[], &concurrency), Some("low"));
+
+        // 所有层都饱和时仍使用最高一层，由调用方排队等待
+        let low = pool.get_semaphore("low").unwrap().try_acquire_owned().unwrap();
+        assert_eq!(select(&pool, &[], &concurrency), Some("high"));
+
+        drop((high, low));
+        assert_eq!(select(&pool, &[], &concurrency), Some("high"));
+    }
+
+    #[test]
+    fn spills_by_adaptive_controller_when_enabled() {
+        let pool = ProviderPoolState::new(vec![provider("high", 10), provider("low", 0)]);
+        let concurrency = controller(true);
+
+        // 启用自适应时按控制器的名额判断，不看信号量
+        let _semaphore = pool.get_semaphore("high").unwrap().try_acquire_owned().unwrap();
+        assert_eq!(select(&pool, &[], &concurrency), Some("high"));
+
+        let permit = concurrency.try_acquire("high", 1).unwrap();
+        assert!(concurrency.is_saturated("high"));
+        assert_eq!(select(&pool, &[], &concurrency), Some("low"));
+
+        drop(permit);
+        assert_eq!(select(&pool, &[], &concurrency), Some("high"));
+    }
+
+    #[test]
+    fn lowest_latency_scores_unmeasured_providers_at_the_median() {
+        let pool = ProviderPoolState::new(Vec::new());
+        let latency = ProviderLatency::default();
+        let observe = |api_key: &str, ms: u64| latency.observe(LatencyMetric::Request, api_key, Duration::from_millis(ms));
+        observe("fast", 100);
+        observe("medium", 300);
+        observe("slow", 500);
+
+        let (new, fast, medium, slow) = (provider("new", 0), provider("fast", 0), provider("medium", 0), provider("slow", 0));
+        let pick = |providers: &[&ProviderInfo], stale_after: Duration| {
+            pool.select_lowest_latency(providers, &latency, stale_after).map(|p| p.api_key.clone())
+        };
+        let fresh = Duration::from_secs(300);
+
+        // 未测量的提供商不再以 0 分抢占测量过的快提供商
+        assert_eq!(pick(&[&new, &fast], fresh).as_deref(), Some("fast"));
+        assert_eq!(pick(&[&new, &slow, &fast, &medium], fresh).as_deref(), Some("fast"));
+        // 未测量的按中位数计分：比中位数快的提供商优先，同分时已测量的优先
+        assert_eq!(pick(&[&new, &slow], fresh).as_deref(), Some("slow"));
+        assert_eq!(pick(&[&slow, &medium, &new], fresh).as_deref(), Some("medium"));
+        // 样本全部过期时按轮询选择
+        assert_eq!(pick(&[&new, &fast], Duration::ZERO).as_deref(), Some("new"));
+        assert_eq!(pick(&[], fresh), None);
+    }
+}
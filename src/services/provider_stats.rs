@@ -0,0 +1,165 @@
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::config::SchedulerConfig;
+use crate::services::concurrency_controller::ConcurrencyController;
+use crate::services::provider_cooldown;
+use crate::services::provider_latency::{self, LatencyMetric, LatencySummary};
+use crate::services::provider_pool::ProviderPoolState;
+use crate::services::scheduler::{self, Scheduler};
+
+/// 单个提供商的实时统计
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProviderLiveStats {
+    /// API密钥
+    pub api_key: String,
+    /// 提供商类型
+    pub provider_type: String,
+    /// 基础URL
+    pub base_url: String,
+    /// 模型名称
+    pub model_name: String,
+    /// 模型类型
+    pub model_type: String,
+    /// 当前余额
+    pub balance: f64,
+    /// 是否可被选中（余额充足且未到期）
+    pub available: bool,
+    /// 当前占用的并发数
+    pub in_flight: u32,
+    /// 并发上限（启用自适应并发时为当前自适应上限）
+    pub max_connections: u32,
+    /// 启动以来处理的请求数
+    pub request_count: u32,
+    /// 启动以来消耗的token数
+    pub total_tokens: u32,
+    /// 启动以来按定价计算的累计成本
+    pub total_cost: f64,
+    /// 最近一次使用时间
+    pub last_used: Option<DateTime<Utc>>,
+    /// 上游请求耗时摘要（持久化，重启后保留），没有样本时为空
+    pub latency: Option<LatencySummary>,
+    /// 流式请求首字延迟摘要，没有样本时为空
+    pub first_token_latency: Option<LatencySummary>,
+    /// 连续限流或服务端错误后的冷却结束时间，不在冷却中时为空
+    pub cooldown_until: Option<DateTime<Utc>>,
+}
+
+/// 提供商统计快照
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProviderStatsSnapshot {
+    /// 快照生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 各提供商的统计
+    pub providers: Vec<ProviderLiveStats>,
+}
+
+/// 提供商统计的只读副本：后台任务定期从提供商池生成快照，
+/// 管理接口读取快照时无需获取提供商池的锁（最终一致）
+pub struct ProviderStatsReplica {
+    snapshot: ArcSwap<ProviderStatsSnapshot>,
+}
+
+impl Default for ProviderStatsReplica {
+    fn default() -> Self {
+        Self {
+            snapshot: ArcSwap::from_pointee(ProviderStatsSnapshot {
+                generated_at: Utc::now(),
+                providers: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl ProviderStatsReplica {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取最近一次生成的快照
+    pub fn load(&self) -> Arc<ProviderStatsSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// 根据提供商池的当前状态生成新快照
+    pub fn refresh(&self, pool: &ProviderPoolState, concurrency: &ConcurrencyController) {
+        let latency = provider_latency::tracker();
+        let providers = pool
+            .providers()
+            .iter()
+            .map(|provider| {
+                let usage = pool.token_usage(&provider.api_key);
+                let (in_flight, max_connections) = if concurrency.is_enabled() {
+                    (
+                        concurrency.in_flight(&provider.api_key).unwrap_or(0),
+                        concurrency
+                            .current_limit(&provider.api_key)
+                            .unwrap_or(provider.max_connections.max(0) as u32),
+                    )
+                } else {
+                    let max_connections = provider.max_connections.max(0) as u32;
+                    let available = pool
+                        .get_semaphore(&provider.api_key)
+                        .map(|s| s.available_permits() as u32)
+                        .unwrap_or(max_connections);
+                    (max_connections.saturating_sub(available), max_connections)
+                };
+
+                ProviderLiveStats {
+                    api_key: provider.api_key.clone(),
+                    provider_type: provider.provider_type.clone(),
+                    base_url: provider.base_url.clone(),
+                    model_name: provider.model_name.clone(),
+                    model_type: provider.model_type.clone(),
+                    balance: provider.balance,
+                    available: pool.is_provider_available(provider),
+                    in_flight,
+                    max_connections,
+                    request_count: usage.as_ref().map(|u| u.request_count).unwrap_or(0),
+                    total_tokens: usage.as_ref().map(|u| u.total_tokens).unwrap_or(0),
+                    total_cost: usage.as_ref().map(|u| u.total_cost).unwrap_or(0.0),
+                    last_used: usage.as_ref().map(|u| u.last_used),
+                    latency: latency.summary(LatencyMetric::Request, &provider.api_key),
+                    first_token_latency: latency.summary(LatencyMetric::FirstToken, &provider.api_key),
+                    cooldown_until: provider_cooldown::cooldowns().and_then(|cooldowns| cooldowns.cooling_until(&provider.api_key)),
+                }
+            })
+            .collect();
+
+        self.snapshot.store(Arc::new(ProviderStatsSnapshot {
+            generated_at: Utc::now(),
+            providers,
+        }));
+    }
+
+    /// 注册定时刷新任务 provider_stats_refresh，每次执行只短暂持有一次提供商池的锁；
+    /// 定时任务按秒调度，刷新间隔向上取整到秒
+    pub fn register(
+        self: &Arc<Self>,
+        scheduler: &Scheduler,
+        schedules: &SchedulerConfig,
+        pool: Arc<RwLock<ProviderPoolState>>,
+        concurrency: Arc<ConcurrencyController>,
+        refresh_interval_ms: u64,
+    ) {
+        let replica = self.clone();
+        let default = format!("@every {}s", refresh_interval_ms.div_ceil(1000).max(1));
+        scheduler.register(
+            "provider_stats_refresh",
+            "刷新提供商实时统计快照",
+            scheduler::resolve(schedules, "provider_stats_refresh", &default),
+            move || {
+                let (replica, pool, concurrency) = (replica.clone(), pool.clone(), concurrency.clone());
+                async move {
+                    let pool = pool.read().await;
+                    replica.refresh(&pool, &concurrency);
+                    Ok(())
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, SqlitePool};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+// 报告中每类问题最多列出的样例数量
+const SAMPLE_LIMIT: i64 = 100;
+
+// 需要计算成本的使用记录：成功且消耗了token
+const BILLABLE_USAGE: &str = "status = 'Success' AND total_tokens > 0";
+
+/// 缺少成本的使用记录
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct UsageMissingCost {
+    /// 使用记录ID
+    pub id: String,
+    /// 提供商API密钥
+    pub provider_api_key: String,
+    /// 模型名称
+    pub model: String,
+    /// 请求时间
+    pub request_time: DateTime<Utc>,
+}
+
+/// 找不到对应使用记录的借记条目
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct LedgerMissingUsage {
+    /// 账本条目ID
+    pub id: String,
+    /// 计费账户
+    pub account: String,
+    /// 金额
+    pub amount: f64,
+    /// 关联的使用记录ID（可能为空）
+    pub usage_id: Option<String>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 重复的请求ID
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct DuplicateRequestId {
+    /// 请求ID
+    pub request_id: String,
+    /// 使用记录条数
+    pub count: i64,
+}
+
+/// 使用记录与账本/成本的一致性检查报告
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReconcileReport {
+    /// 报告生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 本次按历史定价回填成本的使用记录数（未请求回填时为空）
+    pub backfilled_costs: Option<u64>,
+    /// 缺少成本的使用记录总数
+    pub usage_missing_cost_count: i64,
+    /// 缺少成本的使用记录样例
+    pub usage_missing_cost: Vec<UsageMissingCost>,
+    /// 找不到对应使用记录的借记条目总数
+    pub ledger_missing_usage_count: i64,
+    /// 找不到对应使用记录的借记条目样例
+    pub ledger_missing_usage: Vec<LedgerMissingUsage>,
+    /// 重复的请求ID
+    pub duplicate_request_ids: Vec<DuplicateRequestId>,
+}
+
+impl ReconcileReport {
+    /// 是否发现不一致
+    pub fn has_issues(&self) -> bool {
+        self.usage_missing_cost_count > 0
+            || self.ledger_missing_usage_count > 0
+            || !self.duplicate_request_ids.is_empty()
+    }
+}
+
+/// 检查使用记录与账本、成本的一致性；backfill 为 true 时先按请求时生效的历史定价回填缺少的成本
+pub async fn reconcile(db: &SqlitePool, backfill: bool) -> Result<ReconcileReport, sqlx::Error> {
+    let backfilled_costs = if backfill {
+        Some(backfill_costs(db).await?)
+    } else {
+        None
+    };
+
+    let usage_missing_cost_count: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM api_usage WHERE {} AND cost IS NULL",
+        BILLABLE_USAGE
+    ))
+    .fetch_one(db)
+    .await?;
+    let usage_missing_cost = sqlx::query_as::<_, UsageMissingCost>(&format!(
+        r#"
+        SELECT id, provider_api_key, model, request_time
+        FROM api_usage
+        WHERE {} AND cost IS NULL
+        ORDER BY request_time DESC
+        LIMIT ?
+        "#,
+        BILLABLE_USAGE
+    ))
+    .bind(SAMPLE_LIMIT)
+    .fetch_all(db)
+    .await?;
+
+    const LEDGER_MISSING_USAGE: &str = r#"
+        FROM billing_ledger l
+        LEFT JOIN api_usage u ON u.id = l.usage_id
+        WHERE l.entry_type = 'Debit' AND u.id IS NULL
+    "#;
+    let ledger_missing_usage_count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {}", LEDGER_MISSING_USAGE))
+        .fetch_one(db)
+        .await?;
+    let ledger_missing_usage = sqlx::query_as::<_, LedgerMissingUsage>(&format!(
+        "SELECT l.id, l.account, l.amount, l.usage_id, l.created_at {} ORDER BY l.created_at DESC LIMIT ?",
+        LEDGER_MISSING_USAGE
+    ))
+    .bind(SAMPLE_LIMIT)
+    .fetch_all(db)
+    .await?;
+
+    let duplicate_request_ids = sqlx::query_as::<_, DuplicateRequestId>(
+        r#"
+        SELECT request_id, COUNT(*) AS count
+        FROM api_usage
+        WHERE request_id IS NOT NULL
+        GROUP BY request_id
+        HAVING COUNT(*) > 1
+        ORDER BY count DESC
+        LIMIT ?
+        "#
+    )
+    .bind(SAMPLE_LIMIT)
+    .fetch_all(db)
+    .await?;
+
+    Ok(ReconcileReport {
+        generated_at: Utc::now(),
+        backfilled_costs,
+        usage_missing_cost_count,
+        usage_missing_cost,
+        ledger_missing_usage_count,
+        ledger_missing_usage,
+        duplicate_request_ids,
+    })
+}
+
+// 按请求时已生效的最新定价回填缺少的成本（单价为每千token），返回回填的记录数
+async fn backfill_costs(db: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(&format!(
+        r#"
+        UPDATE api_usage
+        SET cost = (
+            SELECT (api_usage.prompt_tokens * mp.prompt_token_price
+                    + api_usage.completion_tokens * mp.completion_token_price) / 1000.0
+            FROM model_pricing mp
+            JOIN api_providers p ON p.name = mp.name
+            WHERE p.api_key = api_usage.provider_api_key
+              AND mp.model = api_usage.model
+              AND mp.effective_date <= api_usage.request_time
+            ORDER BY mp.effective_date DESC
+            LIMIT 1
+        )
+        WHERE {} AND cost IS NULL
+        "#,
+        BILLABLE_USAGE
+    ))
+    .execute(db)
+    .await?;
+
+    // 请求时尚无生效定价的记录仍为空，不计入回填数
+    let still_missing: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM api_usage WHERE {} AND cost IS NULL",
+        BILLABLE_USAGE
+    ))
+    .fetch_one(db)
+    .await?;
+    Ok(result.rows_affected().saturating_sub(still_missing as u64))
+}
+
+/// 启动时执行一次一致性检查并记录结果（不回填）
+pub async fn check_on_startup(db: &SqlitePool) {
+    match reconcile(db, false).await {
+        Ok(report) if report.has_issues() => warn!(
+            target: "reconcile",
+            "使用记录与账本不一致: 缺少成本={}, 借记缺少使用记录={}, 重复请求ID={}",
+            report.usage_missing_cost_count,
+            report.ledger_missing_usage_count,
+            report.duplicate_request_ids.len(),
+        ),
+        Ok(_) => info!("使用记录与账本一致性检查通过"),
+        Err(e) => warn!(target: "reconcile", "使用记录与账本一致性检查失败: {}", e),
+    }
+}
@@ -0,0 +1,427 @@
+use chrono::{DateTime, Datelike, Duration, DurationRound, Months, NaiveDate, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::config::SchedulerConfig;
+
+// 查找下一次执行时间时最多检查的步数，避免永远不会匹配的表达式（如2月30日）陷入死循环
+const MAX_CRON_STEPS: usize = 100_000;
+
+static SCHEDULER: OnceLock<Arc<Scheduler>> = OnceLock::new();
+
+/// 全局的定时任务调度器
+pub fn scheduler() -> Arc<Scheduler> {
+    SCHEDULER.get_or_init(|| Arc::new(Scheduler::new())).clone()
+}
+
+/// 任务的执行计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// 固定间隔：注册后立即执行一次，之后每隔一段时间执行（@every 30s / 5m / 1h / 1d）
+    Every(Duration),
+    /// 五段式 cron 表达式（分 时 日 月 周，UTC）
+    Cron(CronSchedule),
+    /// 停用（off）
+    Disabled,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "off" | "disabled" => return Ok(Self::Disabled),
+            "@hourly" => return "0 * * * *".parse(),
+            "@daily" => return "0 0 * * *".parse(),
+            "@weekly" => return "0 0 * * 0".parse(),
+            "@monthly" => return "0 0 1 * *".parse(),
+            _ => {}
+        }
+        if let Some(every) = s.strip_prefix("@every") {
+            return parse_duration(every.trim()).map(Self::Every);
+        }
+        CronSchedule::parse(s).map(Self::Cron)
+    }
+}
+
+impl Schedule {
+    /// after 之后（不含）的下一次执行时间
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Every(every) => Some(after + *every),
+            Self::Cron(cron) => cron.next_after(after),
+            Self::Disabled => None,
+        }
+    }
+}
+
+// 解析 30s / 5m / 1h / 1d 形式的时长
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("无效的时长: {}（例如 30s、5m、1h、1d）", s);
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let value = s[..split].parse::<i64>().map_err(|_| invalid())?;
+    let duration = match &s[split..] {
+        "s" => Duration::seconds(value),
+        "m" => Duration::minutes(value),
+        "h" => Duration::hours(value),
+        "d" => Duration::days(value),
+        _ => return Err(invalid()),
+    };
+    if value <= 0 {
+        return Err(invalid());
+    }
+    Ok(duration)
+}
+
+/// 五段式 cron 表达式，每段支持 *、数字、范围 a-b、步长 */n 或 a-b/n 以及逗号分隔的列表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日和周都有限制时任一匹配即可（与标准 cron 一致）
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    fn parse(s: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron 表达式应为5段（分 时 日 月 周）: {}", s));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // 周日可以写作0或7
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            expr: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        for _ in 0..MAX_CRON_STEPS {
+            if self.months & (1 << t.month()) == 0 {
+                let month_start = Utc.with_ymd_and_hms(t.year(), t.month(), 1, 0, 0, 0).single()?;
+                t = month_start.checked_add_months(Months::new(1))?;
+            } else if !self.matches_day(t.date_naive()) {
+                t = t.duration_trunc(Duration::days(1)).ok()? + Duration::days(1);
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.duration_trunc(Duration::hours(1)).ok()? + Duration::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+// 解析一段 cron 字段为位集合
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let invalid = || format!("无效的 cron 字段: {}（取值 {}-{}）", field, min, max);
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse::<u32>().map_err(|_| invalid())?, end.parse::<u32>().map_err(|_| invalid())?)
+        } else {
+            let value = range.parse::<u32>().map_err(|_| invalid())?;
+            // a/n 表示从 a 开始到最大值
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// 按配置得到任务的执行计划：JOB_SCHEDULES 中配置了该任务时优先使用，表达式无效时使用默认计划
+pub fn resolve(config: &SchedulerConfig, name: &str, default: &str) -> Schedule {
+    if let Some(expr) = config.schedules.get(name) {
+        match expr.parse() {
+            Ok(schedule) => return schedule,
+            Err(e) => warn!("任务 {} 的执行计划无效，使用默认计划 {}: {}", name, default, e),
+        }
+    }
+    default.parse().unwrap_or(Schedule::Disabled)
+}
+
+/// 定时任务的运行状态
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct JobStatus {
+    /// 任务名称
+    pub name: String,
+    /// 任务说明
+    pub description: String,
+    /// 执行计划（@every 间隔、cron 表达式或 off）
+    pub schedule: String,
+    /// 是否正在执行
+    pub running: bool,
+    /// 启动以来执行的次数
+    pub run_count: u64,
+    /// 因上一次执行尚未结束而跳过的次数
+    pub skipped_runs: u64,
+    /// 最近一次开始执行的时间
+    pub last_started_at: Option<DateTime<Utc>>,
+    /// 最近一次执行结束的时间
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// 最近一次执行耗时(毫秒)
+    pub last_duration_ms: Option<u64>,
+    /// 最近一次执行的错误，成功时为空
+    pub last_error: Option<String>,
+    /// 下一次执行的时间，停用时为空
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+struct Job {
+    schedule: Schedule,
+    run: Box<dyn Fn() -> JobFuture + Send + Sync>,
+    running: AtomicBool,
+    status: Mutex<JobStatus>,
+}
+
+/// 定时任务调度器：各周期任务在此注册，由调度器按计划执行并记录运行状态；
+/// 上一次执行尚未结束时跳过本次执行，避免同一任务并发运行
+pub struct Scheduler {
+    jobs: Mutex<Vec<Arc<Job>>>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self { jobs: Mutex::new(Vec::new()) }
+    }
+
+    /// 注册任务并按计划开始调度
+    pub fn register<F, Fut>(&self, name: &str, description: &str, schedule: Schedule, run: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let status = JobStatus {
+            name: name.to_string(),
+            description: description.to_string(),
+            schedule: schedule.to_string(),
+            ..Default::default()
+        };
+        let job = Arc::new(Job {
+            schedule,
+            run: Box::new(move || Box::pin(run())),
+            running: AtomicBool::new(false),
+            status: Mutex::new(status),
+        });
+        self.jobs.lock().unwrap().push(job.clone());
+
+        if job.schedule == Schedule::Disabled {
+            info!("定时任务 {} 已停用", name);
+            return;
+        }
+        info!("注册定时任务 {}: {}", name, job.schedule);
+        tokio::spawn(schedule_loop(job));
+    }
+
+    /// 全部任务的运行状态，按名称排序
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        let mut statuses: Vec<JobStatus> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|job| {
+                let mut status = job.status.lock().unwrap().clone();
+                status.running = job.running.load(Ordering::SeqCst);
+                status
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Every(every) => write!(f, "@every {}s", every.num_seconds()),
+            Self::Cron(cron) => write!(f, "{}", cron.expr),
+            Self::Disabled => write!(f, "off"),
+        }
+    }
+}
+
+async fn schedule_loop(job: Arc<Job>) {
+    let name = job.status.lock().unwrap().name.clone();
+    // 固定间隔的任务注册后立即执行一次
+    let mut next = match job.schedule {
+        Schedule::Every(_) => Some(Utc::now()),
+        _ => job.schedule.next_after(Utc::now()),
+    };
+    while let Some(at) = next {
+        job.status.lock().unwrap().next_run_at = Some(at);
+        tokio::time::sleep((at - Utc::now()).to_std().unwrap_or_default()).await;
+
+        if job.running.swap(true, Ordering::SeqCst) {
+            warn!("定时任务 {} 上一次执行尚未结束，跳过本次执行", name);
+            job.status.lock().unwrap().skipped_runs += 1;
+        } else {
+            tokio::spawn(run_job(job.clone(), name.clone()));
+        }
+        // 错过的执行时间不补执行
+        next = job.schedule.next_after(at.max(Utc::now() - Duration::seconds(1)));
+    }
+    warn!("定时任务 {} 没有下一次执行时间，停止调度", name);
+    job.status.lock().unwrap().next_run_at = None;
+}
+
+async fn run_job(job: Arc<Job>, name: String) {
+    let started_at = Utc::now();
+    {
+        let mut status = job.status.lock().unwrap();
+        status.last_started_at = Some(started_at);
+        status.run_count += 1;
+    }
+    // 在单独的任务中执行，任务 panic 时也能记录结果并清除运行标记
+    let run = job.clone();
+    let result = match tokio::spawn(async move { (run.run)().await }).await {
+        Ok(result) => result,
+        Err(e) => Err(format!("任务异常退出: {}", e)),
+    };
+    let finished_at = Utc::now();
+    if let Err(e) = &result {
+        error!("定时任务 {} 执行失败: {}", name, e);
+    }
+    {
+        let mut status = job.status.lock().unwrap();
+        status.last_finished_at = Some(finished_at);
+        status.last_duration_ms = Some((finished_at - started_at).num_milliseconds().max(0) as u64);
+        status.last_error = result.err();
+    }
+    job.running.store(false, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn next(expr: &str, after: &str) -> Option<DateTime<Utc>> {
+        expr.parse::<Schedule>().unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn parses_shorthands_and_intervals() {
+        assert_eq!("@every 5m".parse::<Schedule>(), Ok(Schedule::Every(Duration::minutes(5))));
+        assert_eq!("off".parse::<Schedule>(), Ok(Schedule::Disabled));
+        assert_eq!("@daily".parse::<Schedule>(), "0 0 * * *".parse::<Schedule>());
+        assert_eq!("0 0 * * 7".parse::<Schedule>().unwrap().next_after(at("2026-10-17T12:00:00Z")), Some(at("2026-10-18T00:00:00Z")));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expr in ["@every 0s", "@every 5w", "* * * *", "60 * * * *", "* 24 * * *", "0 0 0 * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(expr.parse::<Schedule>().is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn next_run_is_strictly_after() {
+        assert_eq!(next("0,30 * * * *", "2026-10-17T10:00:00Z"), Some(at("2026-10-17T10:30:00Z")));
+        assert_eq!(next("0,30 * * * *", "2026-10-17T10:29:59Z"), Some(at("2026-10-17T10:30:00Z")));
+        assert_eq!(next("0,30 * * * *", "2026-10-17T10:30:00Z"), Some(at("2026-10-17T11:00:00Z")));
+    }
+
+    #[test]
+    fn ranges_and_steps() {
+        assert_eq!(next("0 9-17 * * *", "2026-10-17T17:30:00Z"), Some(at("2026-10-18T09:00:00Z")));
+        assert_eq!(next("*/15 * * * *", "2026-10-17T10:07:00Z"), Some(at("2026-10-17T10:15:00Z")));
+        assert_eq!(next("10-30/10 * * * *", "2026-10-17T10:31:00Z"), Some(at("2026-10-17T11:10:00Z")));
+        assert_eq!(next("45/5 * * * *", "2026-10-17T10:56:00Z"), Some(at("2026-10-17T11:45:00Z")));
+    }
+
+    #[test]
+    fn day_of_month_and_weekday_match_either() {
+        // 2026-10-17 是周六；日和周都有限制时任一匹配即可
+        assert_eq!(next("0 0 20 * 5", "2026-10-17T12:00:00Z"), Some(at("2026-10-20T00:00:00Z")));
+        assert_eq!(next("0 0 20 * 5", "2026-10-20T00:00:00Z"), Some(at("2026-10-23T00:00:00Z")));
+        assert_eq!(next("0 0 * * 1", "2026-10-17T12:00:00Z"), Some(at("2026-10-19T00:00:00Z")));
+        assert_eq!(next("0 0 13 * *", "2026-10-17T12:00:00Z"), Some(at("2026-11-13T00:00:00Z")));
+    }
+
+    #[test]
+    fn rolls_over_months_and_years() {
+        assert_eq!(next("0 0 1 * *", "2026-12-15T00:00:00Z"), Some(at("2027-01-01T00:00:00Z")));
+        assert_eq!(next("0 0 31 * *", "2026-04-01T00:00:00Z"), Some(at("2026-05-31T00:00:00Z")));
+        assert_eq!(next("0 12 * 2 *", "2026-10-17T00:00:00Z"), Some(at("2027-02-01T12:00:00Z")));
+        assert_eq!(next("0 0 29 2 *", "2026-10-17T00:00:00Z"), Some(at("2028-02-29T00:00:00Z")));
+        assert_eq!(next("0 0 30 2 *", "2026-10-17T00:00:00Z"), None);
+    }
+
+    #[tokio::test]
+    async fn panicking_job_clears_running_flag() {
+        let job = Arc::new(Job {
+            schedule: Schedule::Disabled,
+            run: Box::new(|| Box::pin(async { panic!("boom") })),
+            running: AtomicBool::new(true),
+            status: Mutex::new(JobStatus::default()),
+        });
+        run_job(job.clone(), "panicking".to_string()).await;
+
+        assert!(!job.running.load(Ordering::SeqCst));
+        let status = job.status.lock().unwrap();
+        assert_eq!(status.run_count, 1);
+        assert!(status.last_error.as_deref().is_some_and(|e| e.contains("panic")));
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::SlowRequestConfig;
+use crate::services::provider_pool::ProviderInfo;
+use crate::utils::redact::redact;
+
+/// 一次已完成请求的耗时和用量
+pub struct CompletedRequest<'a> {
+    pub provider: &'a ProviderInfo,
+    pub model: &'a str,
+    pub stream: bool,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// 从收到请求到响应结束的耗时
+    pub elapsed: Duration,
+}
+
+/// 慢请求日志：端到端耗时超过阈值的请求记录 target 为 slow_request 的结构化警告日志，
+/// 并按模型和是否流式计数，便于排查长尾延迟
+pub struct SlowRequestLog {
+    threshold: Option<Duration>,
+    counts: Mutex<BTreeMap<(String, bool), u64>>,
+}
+
+impl SlowRequestLog {
+    pub fn new(config: &SlowRequestConfig) -> Self {
+        Self {
+            threshold: (config.threshold_ms > 0).then(|| Duration::from_millis(config.threshold_ms)),
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 检查请求耗时，超过阈值时记录日志和计数
+    pub fn observe(&self, request: CompletedRequest<'_>) {
+        let Some(threshold) = self.threshold else {
+            return;
+        };
+        if request.elapsed < threshold {
+            return;
+        }
+        warn!(
+            target: "slow_request",
+            elapsed_ms = request.elapsed.as_millis() as u64,
+            threshold_ms = threshold.as_millis() as u64,
+            provider_type = %request.provider.provider_type,
+            provider_key = %redact(&request.provider.api_key),
+            base_url = %request.provider.base_url,
+            model = request.model,
+            stream = request.stream,
+            prompt_tokens = request.prompt_tokens,
+            completion_tokens = request.completion_tokens,
+            "慢请求"
+        );
+        let mut counts = self.counts.lock().unwrap();
+        *counts.entry((request.model.to_string(), request.stream)).or_default() += 1;
+    }
+
+    /// 以 Prometheus 文本格式导出慢请求计数
+    pub fn render(&self) -> String {
+        const NAME: &str = "gateway_slow_requests_total";
+        let counts = self.counts.lock().unwrap();
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP {} 端到端耗时超过 SLOW_REQUEST_THRESHOLD_MS 的请求数", NAME);
+        let _ = writeln!(out, "# TYPE {} counter", NAME);
+        for ((model, stream), count) in counts.iter() {
+            let model = model.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(out, "{}{{model=\"{}\",stream=\"{}\"}} {}", NAME, model, stream, count);
+        }
+        out
+    }
+}
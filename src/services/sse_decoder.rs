@@ -0,0 +1,136 @@
+use bytes::Bytes;
+
+/// 一个完整的SSE事件
+#[derive(Debug, Clone)]
+pub struct SseEvent {
+    /// 事件的原始字节（含结尾空行），原样转发给客户端
+    pub raw: Bytes,
+    /// 所有 data 字段按换行拼接后的内容，没有 data 字段（如注释心跳）时为空
+    pub data: Option<String>,
+}
+
+/// 发送给客户端的SSE错误事件，错误信息按JSON转义
+pub fn error_event(message: &str) -> Bytes {
+    Bytes::from(format!("data: {}\n\n", serde_json::json!({ "error": message })))
+}
+
+/// 增量SSE解码器
+/// 上游的网络数据块可能在任意位置截断事件，解码器缓存未完成的行，
+/// 只在遇到空行（事件结束）时产出完整事件，支持 \n 和 \r\n 换行
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    // 缓冲区中已扫描到的位置（当前事件中已处理完的行）
+    scanned: usize,
+    data: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一个数据块，返回其中已完整的事件
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer[self.scanned..].iter().position(|&b| b == b'\n') {
+            let line_end = self.scanned + pos;
+            let line = &self.buffer[self.scanned..line_end];
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+            if line.is_empty() {
+                let raw: Vec<u8> = self.buffer.drain(..=line_end).collect();
+                self.scanned = 0;
+                events.push(SseEvent {
+                    raw: Bytes::from(raw),
+                    data: self.data.take(),
+                });
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix(b"data:") {
+                let value = value.strip_prefix(b" ").unwrap_or(value);
+                let value = String::from_utf8_lossy(value);
+                match self.data.as_mut() {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(&value);
+                    }
+                    None => self.data = Some(value.into_owned()),
+                }
+            }
+            self.scanned = line_end + 1;
+        }
+
+        events
+    }
+
+    /// 上游结束时取出缓冲区中剩余的不完整事件（缺少结尾空行）
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        // 最后一行可能没有换行符，补齐结尾空行后按完整事件解析
+        let event = self.push(b"\n\n").into_iter().next();
+        self.buffer.clear();
+        self.scanned = 0;
+        self.data = None;
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(events: &[SseEvent]) -> Vec<Option<&str>> {
+        events.iter().map(|event| event.data.as_deref()).collect()
+    }
+
+    #[test]
+    fn decodes_events_split_across_chunks() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: {\"a\"").is_empty());
+        assert!(decoder.push(b":1}\n").is_empty());
+        let events = decoder.push(b"\ndata: [DONE]\n\n");
+        assert_eq!(data(&events), vec![Some("{\"a\":1}"), Some("[DONE]")]);
+        assert_eq!(&events[0].raw[..], b"data: {\"a\":1}\n\n");
+    }
+
+    #[test]
+    fn handles_crlf_and_multiline_data() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"event: delta\r\ndata: first\r\ndata:second\r\n\r\n");
+        assert_eq!(data(&events), vec![Some("first\nsecond")]);
+    }
+
+    #[test]
+    fn comment_only_event_has_no_data() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b": keep-alive\n\n");
+        assert_eq!(data(&events), vec![None]);
+        assert_eq!(&events[0].raw[..], b": keep-alive\n\n");
+    }
+
+    #[test]
+    fn finish_flushes_incomplete_event() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: tail").is_empty());
+        let event = decoder.finish().expect("应取出剩余事件");
+        assert_eq!(event.data.as_deref(), Some("tail"));
+        assert!(decoder.finish().is_none());
+    }
+
+    #[test]
+    fn error_event_escapes_message() {
+        let event = error_event("bad \"quote\"\nline");
+        let text = std::str::from_utf8(&event).unwrap();
+        let json = text.strip_prefix("data: ").unwrap().trim_end();
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(value["error"], "bad \"quote\"\nline");
+        assert!(text.ends_with("\n\n"));
+    }
+}
@@ -0,0 +1,117 @@
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::StreamFlushConfig;
+use crate::services::SseDecoder;
+
+// 视为句子结束的字符
+const SENTENCE_ENDINGS: &[char] = &['.', '!', '?', '。', '！', '？', '\n'];
+
+/// 流式响应的合并发送策略
+#[derive(Debug, Clone, Copy)]
+pub struct FlushPolicy {
+    /// 第一块数据进入缓冲后最多等待多久发送
+    pub max_delay: Duration,
+    /// 缓冲达到多少字节后立即发送
+    pub max_bytes: usize,
+    /// 内容在句子结尾处时立即发送
+    pub flush_on_sentence: bool,
+}
+
+impl FlushPolicy {
+    /// 根据配置得到合并策略；immediate 模式或等待时间为0时返回 None，表示每块数据立即发送
+    pub fn from_config(config: &StreamFlushConfig) -> Option<Self> {
+        if config.mode != "batched" || config.batch_ms == 0 {
+            return None;
+        }
+        Some(Self {
+            max_delay: Duration::from_millis(config.batch_ms),
+            max_bytes: config.batch_bytes.max(1),
+            flush_on_sentence: config.flush_on_sentence,
+        })
+    }
+}
+
+/// 按策略合并SSE数据块后再发送，减少高并发时大量的小TCP包；policy 为 None 时原样返回。
+/// 上游出错或结束时先发送已缓冲的数据
+pub fn with_flush_policy<S, E>(
+    stream: S,
+    policy: Option<FlushPolicy>,
+) -> impl Stream<Item = Result<Bytes, E>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + Unpin,
+    E: Send,
+{
+    async_stream::stream! {
+        let mut stream = stream;
+        let policy = match policy {
+            Some(policy) => policy,
+            None => {
+                while let Some(item) = stream.next().await {
+                    yield item;
+                }
+                return;
+            }
+        };
+
+        let mut buffer = BytesMut::new();
+        let mut decoder = SseDecoder::new();
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let next = match deadline {
+                Some(at) => match tokio::time::timeout_at(at, stream.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        yield Ok(buffer.split().freeze());
+                        deadline = None;
+                        continue;
+                    }
+                },
+                None => stream.next().await,
+            };
+            match next {
+                Some(Ok(chunk)) => {
+                    let sentence_end = policy.flush_on_sentence && ends_sentence(&mut decoder, &chunk);
+                    buffer.extend_from_slice(&chunk);
+                    if sentence_end || buffer.len() >= policy.max_bytes {
+                        yield Ok(buffer.split().freeze());
+                        deadline = None;
+                    } else if deadline.is_none() {
+                        deadline = Some(Instant::now() + policy.max_delay);
+                    }
+                }
+                Some(Err(e)) => {
+                    if !buffer.is_empty() {
+                        yield Ok(buffer.split().freeze());
+                    }
+                    deadline = None;
+                    yield Err(e);
+                }
+                None => break,
+            }
+        }
+        if !buffer.is_empty() {
+            yield Ok(buffer.freeze());
+        }
+    }
+}
+
+// 数据块中最后一个内容增量是否以句子结尾字符结束
+fn ends_sentence(decoder: &mut SseDecoder, chunk: &[u8]) -> bool {
+    let mut ends = false;
+    for event in decoder.push(chunk) {
+        let Some(data) = event.data else { continue };
+        let Ok(value) = serde_json::from_str::<Value>(&data) else { continue };
+        let content = value
+            .pointer("/choices/0/delta/content")
+            .and_then(Value::as_str)
+            .filter(|content| !content.is_empty());
+        if let Some(content) = content {
+            ends = content.trim_end_matches(' ').ends_with(SENTENCE_ENDINGS);
+        }
+    }
+    ends
+}
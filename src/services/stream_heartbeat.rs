@@ -0,0 +1,42 @@
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use std::time::Duration;
+
+// SSE注释帧，客户端解析时会忽略
+const HEARTBEAT_FRAME: &[u8] = b": ping\n\n";
+
+/// 为SSE流加上心跳：超过 interval 没有数据时发送一个注释帧；interval 为 None 时原样返回
+pub fn with_heartbeat<S, E>(
+    stream: S,
+    interval: Option<Duration>,
+) -> impl Stream<Item = Result<Bytes, E>> + Send
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + Unpin,
+    E: Send,
+{
+    async_stream::stream! {
+        let mut stream = stream;
+        let interval = match interval {
+            Some(interval) => interval,
+            None => {
+                while let Some(item) = stream.next().await {
+                    yield item;
+                }
+                return;
+            }
+        };
+
+        loop {
+            match tokio::time::timeout(interval, stream.next()).await {
+                Ok(Some(item)) => yield item,
+                Ok(None) => break,
+                Err(_) => yield Ok(Bytes::from_static(HEARTBEAT_FRAME)),
+            }
+        }
+    }
+}
+
+/// 根据配置的毫秒数得到心跳间隔，0表示关闭
+pub fn heartbeat_interval(interval_ms: u64) -> Option<Duration> {
+    (interval_ms > 0).then(|| Duration::from_millis(interval_ms))
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 流式输出节流器
+/// 按目标 tokens/秒 控制SSE增量事件的下发速度，使输出更平滑
+#[derive(Debug)]
+pub struct StreamPacer {
+    interval: Duration,
+    next_emit: Instant,
+}
+
+impl StreamPacer {
+    /// 根据目标速率创建节流器，速率无效（<=0）时返回None表示不节流
+    pub fn new(tokens_per_second: f64) -> Option<Self> {
+        if !tokens_per_second.is_finite() || tokens_per_second <= 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            interval: Duration::from_secs_f64(1.0 / tokens_per_second),
+            next_emit: Instant::now(),
+        })
+    }
+
+    /// 等待直到允许下发下一个token
+    pub async fn pace(&mut self) {
+        let now = Instant::now();
+        if self.next_emit > now {
+            tokio::time::sleep_until(self.next_emit).await;
+        }
+        // 上游空闲后不补发积压的配额，避免突发输出
+        self.next_emit = self.next_emit.max(now) + self.interval;
+    }
+}
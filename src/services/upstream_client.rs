@@ -0,0 +1,468 @@
+use dashmap::DashMap;
+use futures_util::future::join_all;
+use hyper_014::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::{Client, Url};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, OnceLock};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::config::{AppConfig, ProxyConfig, UpstreamTimeoutConfig, UpstreamWarmupConfig};
+use crate::services::ProviderInfo;
+
+// 预热连接请求的超时
+const WARM_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct WarmupSettings {
+    warmup: UpstreamWarmupConfig,
+    proxy: ProxyConfig,
+    timeouts: UpstreamTimeoutConfig,
+}
+
+static SETTINGS: OnceLock<WarmupSettings> = OnceLock::new();
+static DNS_CACHE: OnceLock<Arc<DnsCache>> = OnceLock::new();
+
+// 共享HTTP客户端的配置，配置相同的请求复用同一个客户端的连接池（也保留预热的连接）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    enable_proxy: bool,
+    proxy_url: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    tuning: ConnectionTuning,
+    tls: TlsOptions,
+}
+
+// 共享客户端和连接统计都在请求路径上访问，使用分片的并发映射避免所有请求争用同一把锁
+static CLIENTS: LazyLock<DashMap<ClientKey, Client>> = LazyLock::new(DashMap::new);
+
+// 按上游域名统计的请求数和新建连接数
+#[derive(Debug, Default)]
+struct HostStats {
+    requests: AtomicU64,
+    connections: AtomicU64,
+}
+
+static CONNECTION_STATS: LazyLock<DashMap<String, HostStats>> = LazyLock::new(DashMap::new);
+
+/// 提供商的连接复用设置，未设置的项使用 reqwest 的默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ConnectionTuning {
+    /// 每个上游域名最多保留的空闲连接数
+    pub pool_max_idle_per_host: Option<usize>,
+    /// 空闲连接在池中保留的时长
+    pub pool_idle_timeout: Option<Duration>,
+    /// TCP keepalive 和 HTTP/2 PING 的间隔
+    pub keep_alive: Option<Duration>,
+    /// HTTP 协议版本
+    pub http_version: HttpVersion,
+}
+
+/// 提供商的TLS设置，默认使用系统内置的根证书并校验证书
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TlsOptions {
+    /// 额外信任的CA证书文件路径（PEM，可包含多个证书）
+    pub ca_path: Option<String>,
+    /// 跳过证书校验（需显式开启）
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsOptions {
+    /// 是否与默认设置不同
+    pub fn is_custom(&self) -> bool {
+        self.ca_path.is_some() || self.insecure_skip_verify
+    }
+}
+
+/// 与上游通信使用的 HTTP 协议版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HttpVersion {
+    /// 通过 TLS ALPN 协商（HTTPS 上游支持时使用 HTTP/2）
+    #[default]
+    Auto,
+    /// 只使用 HTTP/1.1
+    Http1,
+    /// 直接使用 HTTP/2（prior knowledge，适用于明文 h2c 上游）
+    Http2,
+}
+
+/// 提供商可配置的 http_version 取值
+pub const HTTP_VERSIONS: [&str; 3] = ["auto", "http1", "http2"];
+
+impl HttpVersion {
+    /// 解析 http_version 设置，为空或无法识别时按 auto 处理
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("http1") => Self::Http1,
+            Some("http2") => Self::Http2,
+            _ => Self::Auto,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CachedAddrs {
+    addrs: Vec<SocketAddr>,
+    resolved_at: Instant,
+}
+
+/// 上游域名解析缓存：解析结果在有效期内直接复用，重新解析失败时退回到过期的结果
+pub struct DnsCache {
+    ttl: Duration,
+    entries: Arc<DashMap<String, CachedAddrs>>,
+}
+
+impl DnsCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// 解析域名（优先使用缓存）
+    pub async fn lookup(&self, host: &str) -> std::io::Result<Vec<SocketAddr>> {
+        lookup(self.entries.clone(), self.ttl, host.to_string()).await
+    }
+}
+
+impl Resolve for DnsCache {
+    fn resolve(&self, name: Name) -> Resolving {
+        // 连接器每建立一条新连接解析一次域名，用于统计连接复用情况
+        record_connection(name.as_str());
+        let entries = self.entries.clone();
+        let ttl = self.ttl;
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = lookup(entries, ttl, host).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+async fn lookup(
+    entries: Arc<DashMap<String, CachedAddrs>>,
+    ttl: Duration,
+    host: String,
+) -> std::io::Result<Vec<SocketAddr>> {
+    let cached = entries.get(&host).map(|entry| entry.clone());
+    if let Some(cached) = &cached {
+        if cached.resolved_at.elapsed() < ttl {
+            return Ok(cached.addrs.clone());
+        }
+    }
+
+    // 端口由连接器根据URL填充，这里只解析地址
+    let resolved = tokio::net::lookup_host((host.as_str(), 0))
+        .await
+        .map(|addrs| addrs.collect::<Vec<SocketAddr>>());
+    match resolved {
+        Ok(addrs) => {
+            if !addrs.is_empty() {
+                entries.insert(
+                    host,
+                    CachedAddrs {
+                        addrs: addrs.clone(),
+                        resolved_at: Instant::now(),
+                    },
+                );
+            }
+            Ok(addrs)
+        }
+        Err(e) => match cached {
+            Some(cached) => {
+                warn!("域名解析失败，使用过期的解析结果: host={}, 错误={}", host, e);
+                Ok(cached.addrs)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// 记录预热配置，启动时调用一次
+pub fn configure(config: &AppConfig) {
+    let _ = SETTINGS.set(WarmupSettings {
+        warmup: config.upstream_warmup.clone(),
+        proxy: config.proxy.clone(),
+        timeouts: config.upstream_timeouts.clone(),
+    });
+}
+
+/// 全局的上游域名解析缓存
+pub fn dns_cache() -> Arc<DnsCache> {
+    DNS_CACHE
+        .get_or_init(|| {
+            let ttl_secs = SETTINGS.get().map(|s| s.warmup.dns_ttl_secs).unwrap_or(300);
+            Arc::new(DnsCache::new(Duration::from_secs(ttl_secs)))
+        })
+        .clone()
+}
+
+// 创建 HTTP 客户端（支持代理），相同配置的客户端只创建一次
+pub fn create_http_client(enable_proxy: bool, proxy_url: &str, timeout_secs: u64) -> Result<Client, String> {
+    shared_client(ClientKey {
+        enable_proxy,
+        proxy_url: proxy_url.to_string(),
+        timeout: Duration::from_secs(timeout_secs),
+        connect_timeout: None,
+        tuning: ConnectionTuning::default(),
+        tls: TlsOptions::default(),
+    })
+}
+
+/// 请求提供商使用的HTTP客户端：按提供商的连接超时和连接复用设置共享，请求超时由每个请求单独设置
+pub fn provider_client(config: &AppConfig, provider: &ProviderInfo) -> Result<Client, String> {
+    let timeouts = &config.upstream_timeouts;
+    shared_client(ClientKey {
+        enable_proxy: config.proxy.enable,
+        proxy_url: config.proxy.url.clone(),
+        timeout: Duration::from_millis(timeouts.request_timeout_ms),
+        connect_timeout: Some(provider.connect_timeout(timeouts)),
+        tuning: provider.connection_tuning(),
+        tls: provider.tls_options(),
+    })
+}
+
+/// 按提供商的TLS设置创建的HTTP客户端（用于健康探测等不持有应用配置的场景），代理和超时使用启动时的配置
+pub fn tls_client(provider: &ProviderInfo) -> Result<Client, String> {
+    let (enable_proxy, proxy_url, timeouts) = match SETTINGS.get() {
+        Some(settings) => (settings.proxy.enable, settings.proxy.url.clone(), settings.timeouts.clone()),
+        None => (false, String::new(), UpstreamTimeoutConfig { connect_timeout_ms: 10000, request_timeout_ms: 300000 }),
+    };
+    shared_client(ClientKey {
+        enable_proxy,
+        proxy_url,
+        timeout: Duration::from_millis(timeouts.request_timeout_ms),
+        connect_timeout: Some(provider.connect_timeout(&timeouts)),
+        tuning: ConnectionTuning::default(),
+        tls: provider.tls_options(),
+    })
+}
+
+/// 记录一次发往上游的请求（按域名统计连接复用情况）
+pub fn record_request(base_url: &str) {
+    if let Some(host) = Url::parse(base_url).ok().and_then(|url| url.host_str().map(str::to_string)) {
+        host_stats(&host, |stats| stats.requests.fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+fn record_connection(host: &str) {
+    host_stats(host, |stats| stats.connections.fetch_add(1, Ordering::Relaxed));
+}
+
+// 更新域名的统计计数：已有的域名只需读取分片，首次出现时才插入
+fn host_stats(host: &str, update: impl Fn(&HostStats) -> u64) {
+    if let Some(stats) = CONNECTION_STATS.get(host) {
+        update(&stats);
+        return;
+    }
+    update(&CONNECTION_STATS.entry(host.to_string()).or_default());
+}
+
+/// 以 Prometheus 格式导出上游请求数和新建连接数（按域名）；
+/// 新建连接数在未启用代理时统计，两者之比反映连接复用的效果
+pub fn render() -> String {
+    let mut hosts: Vec<(String, (u64, u64))> = CONNECTION_STATS
+        .iter()
+        .map(|entry| {
+            let stats = entry.value();
+            (entry.key().clone(), (stats.requests.load(Ordering::Relaxed), stats.connections.load(Ordering::Relaxed)))
+        })
+        .collect();
+    hosts.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# HELP gateway_upstream_requests_total 发往上游的请求数（按域名）");
+    let _ = writeln!(out, "# TYPE gateway_upstream_requests_total counter");
+    for (host, (requests, _)) in &hosts {
+        let _ = writeln!(out, "gateway_upstream_requests_total{{host=\"{}\"}} {}", escape_label(host), requests);
+    }
+    let _ = writeln!(out, "# HELP gateway_upstream_connections_total 新建的上游连接数（按域名，未启用代理时统计）");
+    let _ = writeln!(out, "# TYPE gateway_upstream_connections_total counter");
+    for (host, (_, connections)) in &hosts {
+        let _ = writeln!(out, "gateway_upstream_connections_total{{host=\"{}\"}} {}", escape_label(host), connections);
+    }
+    out
+}
+
+// 转义标签值中的反斜杠、双引号和换行
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn shared_client(key: ClientKey) -> Result<Client, String> {
+    if let Some(client) = CLIENTS.get(&key) {
+        return Ok(client.clone());
+    }
+
+    let (enable_proxy, proxy_url, timeout, connect_timeout) = (key.enable_proxy, key.proxy_url.as_str(), key.timeout, key.connect_timeout);
+    info!(
+        "创建HTTP客户端：enable_proxy={}, proxy_url={}, timeout={:?}, connect_timeout={:?}, tuning={:?}",
+        enable_proxy, proxy_url, timeout, connect_timeout, key.tuning
+    );
+
+    let mut client_builder = Client::builder()
+        .timeout(timeout)
+        .dns_resolver(dns_cache());
+    if let Some(connect_timeout) = connect_timeout {
+        client_builder = client_builder.connect_timeout(connect_timeout);
+    }
+    if let Some(max_idle) = key.tuning.pool_max_idle_per_host {
+        client_builder = client_builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout) = key.tuning.pool_idle_timeout {
+        client_builder = client_builder.pool_idle_timeout(idle_timeout);
+    }
+    if let Some(keep_alive) = key.tuning.keep_alive {
+        client_builder = client_builder
+            .tcp_keepalive(keep_alive)
+            .http2_keep_alive_interval(keep_alive)
+            .http2_keep_alive_while_idle(true);
+    }
+    client_builder = match key.tuning.http_version {
+        HttpVersion::Auto => client_builder,
+        HttpVersion::Http1 => client_builder.http1_only(),
+        HttpVersion::Http2 => client_builder.http2_prior_knowledge(),
+    };
+    if let Some(ca_path) = &key.tls.ca_path {
+        let pem = std::fs::read(ca_path).map_err(|e| format!("读取CA证书失败: {} - {}", ca_path, e))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("解析CA证书失败: {} - {}", ca_path, e))?;
+        for certificate in certificates {
+            client_builder = client_builder.add_root_certificate(certificate);
+        }
+    }
+    if key.tls.insecure_skip_verify {
+        warn!("HTTP客户端已跳过TLS证书校验，仅应在测试环境使用");
+        client_builder = client_builder.danger_accept_invalid_certs(true);
+    }
+
+    // 如果启用代理，添加代理配置
+    if enable_proxy {
+        info!("正在配置代理: {}", proxy_url);
+
+        // 检查是否是 SOCKS 代理，如果是，确保使用远程 DNS 解析
+        let proxy_url_fixed = if proxy_url.starts_with("socks5://") {
+            let fixed_url = proxy_url.replace("socks5://", "socks5h://");
+            info!("将 SOCKS 代理URL修改为远程DNS解析: {} -> {}", proxy_url, fixed_url);
+            fixed_url
+        } else {
+            proxy_url.to_string()
+        };
+
+        match reqwest::Proxy::all(&proxy_url_fixed) {
+            Ok(proxy) => {
+                client_builder = client_builder.proxy(proxy);
+                info!("✅ 代理配置成功: {}", proxy_url_fixed);
+            }
+            Err(e) => {
+                error!("❌ 代理配置失败: {} - 错误: {}", proxy_url_fixed, e);
+                return Err(format!("无效的代理URL: {} - {}", proxy_url_fixed, e));
+            }
+        }
+    } else {
+        info!("未启用代理，使用直连");
+    }
+
+    match client_builder.build() {
+        Ok(client) => {
+            info!("✅ HTTP客户端创建成功");
+            // 并发创建同一配置的客户端时保留先写入的一个
+            Ok(CLIENTS.entry(key).or_insert(client).clone())
+        }
+        Err(e) => {
+            error!("❌ HTTP客户端创建失败: {}", e);
+            Err(format!("创建HTTP客户端失败: {}", e))
+        }
+    }
+}
+
+// 提供商基础URL的源地址（scheme://host:port/）
+fn origin(base_url: &str) -> Option<Url> {
+    let url = Url::parse(base_url).ok()?;
+    url.host_str()?;
+    Url::parse(&format!("{}/", url.origin().ascii_serialization())).ok()
+}
+
+/// 提供商池重新加载后在后台预热上游：预解析域名，按配置为每个源地址建立一条连接
+pub fn prewarm(providers: &[ProviderInfo]) {
+    let Some(settings) = SETTINGS.get() else {
+        return;
+    };
+    if !settings.warmup.enabled {
+        return;
+    }
+
+    let mut origins: Vec<Url> = providers.iter().filter_map(|p| origin(&p.base_url)).collect();
+    origins.sort();
+    origins.dedup();
+    if origins.is_empty() {
+        return;
+    }
+
+    let warm_connections = settings.warmup.warm_connections;
+    let proxy = settings.proxy.clone();
+    let timeouts = settings.timeouts.clone();
+    tokio::spawn(async move {
+        let started = Instant::now();
+
+        // 通过代理访问时目标域名由代理解析，只需预热连接
+        let mut resolved = 0;
+        if !proxy.enable {
+            let dns = dns_cache();
+            for origin in &origins {
+                let host = origin.host_str().unwrap_or_default();
+                match dns.lookup(host).await {
+                    Ok(_) => resolved += 1,
+                    Err(e) => warn!("预解析提供商域名失败: host={}, 错误={}", host, e),
+                }
+            }
+        }
+
+        let mut warmed = 0;
+        if warm_connections {
+            // 使用默认超时的提供商客户端总是预热，其余已创建的共享客户端一并预热
+            let key = ClientKey {
+                enable_proxy: proxy.enable,
+                proxy_url: proxy.url.clone(),
+                timeout: Duration::from_millis(timeouts.request_timeout_ms),
+                connect_timeout: Some(Duration::from_millis(timeouts.connect_timeout_ms)),
+                tuning: ConnectionTuning::default(),
+                tls: TlsOptions::default(),
+            };
+            if let Err(e) = shared_client(key) {
+                warn!("创建预热客户端失败: {}", e);
+            }
+            let clients: Vec<Client> = CLIENTS
+                .iter()
+                .filter(|entry| entry.key().enable_proxy == proxy.enable && entry.key().proxy_url == proxy.url)
+                .map(|entry| entry.value().clone())
+                .collect();
+
+            // 只为建立连接，响应状态码无关紧要
+            let requests = clients.iter().flat_map(|client| {
+                origins.iter().map(move |origin| async move {
+                    match client.head(origin.clone()).timeout(WARM_CONNECTION_TIMEOUT).send().await {
+                        Ok(_) => true,
+                        Err(e) => {
+                            debug!("预热上游连接失败: origin={}, 错误={}", origin, e);
+                            false
+                        }
+                    }
+                })
+            });
+            warmed = join_all(requests).await.into_iter().filter(|ok| *ok).count();
+        }
+
+        info!(
+            "上游预热完成: 源地址={}, 预解析={}, 预热连接={}, 耗时={:?}",
+            origins.len(),
+            resolved,
+            warmed,
+            started.elapsed()
+        );
+    });
+}
@@ -0,0 +1,119 @@
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
+
+use crate::models::api_usage::{ApiUsage, UsageFilter, BYOK_PROVIDER_KEY};
+use crate::models::model_pricing::ModelPricing;
+
+// 分组键：日期、提供商、模型（未参与分组的维度为空）
+type GroupKey = (Option<String>, Option<String>, Option<String>);
+
+/// 一组用量的成本
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct UsageCostGroup {
+    /// 提供商API密钥（按提供商分组时，自带上游密钥的请求为 byok）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_api_key: Option<String>,
+    /// 模型名称（按模型分组时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 日期 YYYY-MM-DD（按天分组时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// 请求次数
+    pub requests: i64,
+    /// 输入token
+    pub prompt_tokens: i64,
+    /// 输出token
+    pub completion_tokens: i64,
+    /// 成本：已记录的成本加上按当前定价补算的成本
+    pub cost: f64,
+    /// 其中按当前定价补算的成本
+    pub estimated_cost: f64,
+    /// 未配置定价、未计入成本的请求数
+    pub unpriced_requests: i64,
+}
+
+impl UsageCostGroup {
+    fn add(&mut self, other: &UsageCostGroup) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+        self.estimated_cost += other.estimated_cost;
+        self.unpriced_requests += other.unpriced_requests;
+    }
+}
+
+/// 成本归属报表
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UsageCostReport {
+    /// 全部用量的合计
+    pub total: UsageCostGroup,
+    /// 按请求的维度分组的成本，按日期升序、成本倒序；未指定分组时为空
+    pub groups: Vec<UsageCostGroup>,
+}
+
+/// 汇总符合条件的用量成本：使用记录中已有的成本直接累加，
+/// 没有成本的记录（请求时未配置定价）按提供商和模型的当前定价补算，可按提供商、模型和天分组
+pub async fn attribute(
+    db: &SqlitePool,
+    filter: &UsageFilter,
+    by_provider: bool,
+    by_model: bool,
+    by_day: bool,
+) -> Result<UsageCostReport, sqlx::Error> {
+    let rows = ApiUsage::cost_breakdown(db, filter).await?;
+
+    // 同一提供商和模型的定价只查询一次
+    let mut prices: HashMap<(String, String), Option<ModelPricing>> = HashMap::new();
+    let mut total = UsageCostGroup::default();
+    let mut groups: BTreeMap<GroupKey, UsageCostGroup> = BTreeMap::new();
+    for row in rows {
+        let price_key = (row.provider_api_key.clone(), row.model.clone());
+        let pricing = match prices.get(&price_key) {
+            Some(pricing) => pricing.clone(),
+            None if row.unpriced_requests > 0 => {
+                let pricing = ModelPricing::get_price_for_provider_key(db, &row.provider_api_key, &row.model).await?;
+                prices.insert(price_key, pricing.clone());
+                pricing
+            }
+            None => None,
+        };
+
+        let mut group = UsageCostGroup {
+            // BYOK请求按池中模板提供商的定价估算成本，但单独归到 BYOK_PROVIDER_KEY 下
+            provider_api_key: by_provider.then(|| {
+                if row.byok { BYOK_PROVIDER_KEY.to_string() } else { row.provider_api_key.clone() }
+            }),
+            model: by_model.then(|| row.model.clone()),
+            date: by_day.then(|| row.date.clone()),
+            requests: row.requests,
+            prompt_tokens: row.prompt_tokens,
+            completion_tokens: row.completion_tokens,
+            cost: row.recorded_cost,
+            ..Default::default()
+        };
+        match pricing.filter(|_| row.unpriced_requests > 0) {
+            Some(pricing) => {
+                group.estimated_cost = pricing.calculate_cost(
+                    u32::try_from(row.unpriced_prompt_tokens).unwrap_or(u32::MAX),
+                    u32::try_from(row.unpriced_completion_tokens).unwrap_or(u32::MAX),
+                );
+                group.cost += group.estimated_cost;
+            }
+            None => group.unpriced_requests = row.unpriced_requests,
+        }
+
+        total.add(&group);
+        if by_provider || by_model || by_day {
+            let key = (group.date.clone(), group.provider_api_key.clone(), group.model.clone());
+            groups.entry(key).and_modify(|existing| existing.add(&group)).or_insert(group);
+        }
+    }
+
+    let mut groups: Vec<UsageCostGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| a.date.cmp(&b.date).then(b.cost.total_cmp(&a.cost)));
+    Ok(UsageCostReport { total, groups })
+}
@@ -0,0 +1,174 @@
+use bytes::{Bytes, BytesMut};
+use chrono::SecondsFormat;
+use futures_util::{Stream, StreamExt};
+use sqlx::SqlitePool;
+
+use crate::models::api_usage::{ApiUsage, UsageFilter, BYOK_PROVIDER_KEY};
+use crate::models::usage_rollup::{self, RollupGranularity, UsageRollup};
+use crate::utils::redact::redact;
+
+// 缓冲达到该大小后发送一块数据
+const CHUNK_BYTES: usize = 64 * 1024;
+
+const USAGE_HEADER: &str = "id,request_time,provider_api_key,model,prompt_tokens,completion_tokens,total_tokens,\
+status,client_ip,request_id,num_sources,audio_seconds,own_key_id,cost,gateway_key_id,organization\n";
+const ROLLUP_HEADER: &str = "bucket_start,gateway_key_id,provider_api_key,model,requests,successful_requests,\
+prompt_tokens,completion_tokens,total_tokens,cost\n";
+
+/// 以CSV格式流式导出符合条件的使用记录（按请求时间升序），提供商密钥只导出脱敏后的末四位
+pub fn usage_csv(db: SqlitePool, filter: UsageFilter) -> impl Stream<Item = Result<Bytes, sqlx::Error>> + Send + 'static {
+    to_csv(USAGE_HEADER, ApiUsage::stream(db, filter), write_usage)
+}
+
+/// 以CSV格式流式导出汇总记录（按时间段起点升序）
+pub fn rollup_csv(
+    db: SqlitePool,
+    granularity: RollupGranularity,
+    filter: UsageFilter,
+) -> impl Stream<Item = Result<Bytes, sqlx::Error>> + Send + 'static {
+    to_csv(ROLLUP_HEADER, usage_rollup::stream(db, granularity, filter), write_rollup)
+}
+
+// 逐行写入CSV，按块发送；读取出错时先发送已缓冲的数据再结束
+fn to_csv<T, S>(
+    header: &'static str,
+    rows: S,
+    write_row: fn(&mut BytesMut, &T),
+) -> impl Stream<Item = Result<Bytes, sqlx::Error>> + Send + 'static
+where
+    S: Stream<Item = Result<T, sqlx::Error>> + Send + 'static,
+    T: Send + 'static,
+{
+    async_stream::stream! {
+        let mut rows = Box::pin(rows);
+        let mut buffer = BytesMut::from(header);
+        while let Some(row) = rows.next().await {
+            match row {
+                Ok(row) => {
+                    write_row(&mut buffer, &row);
+                    if buffer.len() >= CHUNK_BYTES {
+                        yield Ok(buffer.split().freeze());
+                    }
+                }
+                Err(e) => {
+                    if !buffer.is_empty() {
+                        yield Ok(buffer.split().freeze());
+                    }
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        if !buffer.is_empty() {
+            yield Ok(buffer.freeze());
+        }
+    }
+}
+
+fn write_usage(buffer: &mut BytesMut, usage: &ApiUsage) {
+    let fields = [
+        escape(&usage.id),
+        usage.request_time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        escape(&redact(&usage.provider_api_key)),
+        escape(&usage.model),
+        usage.prompt_tokens.to_string(),
+        usage.completion_tokens.to_string(),
+        usage.total_tokens.to_string(),
+        escape(&usage.status),
+        escape(usage.client_ip.as_deref().unwrap_or("")),
+        escape(usage.request_id.as_deref().unwrap_or("")),
+        usage.num_sources.to_string(),
+        usage.audio_seconds.to_string(),
+        escape(usage.own_key_id.as_deref().unwrap_or("")),
+        usage.cost.map(|cost| cost.to_string()).unwrap_or_default(),
+        escape(usage.gateway_key_id.as_deref().unwrap_or("")),
+        escape(usage.organization.as_deref().unwrap_or("")),
+    ];
+    push_record(buffer, &fields);
+}
+
+fn write_rollup(buffer: &mut BytesMut, rollup: &UsageRollup) {
+    let fields = [
+        rollup.bucket_start.to_rfc3339_opts(SecondsFormat::Secs, true),
+        escape(&rollup.gateway_key_id),
+        match rollup.provider_api_key.as_str() {
+            BYOK_PROVIDER_KEY => BYOK_PROVIDER_KEY.to_string(),
+            key => escape(&redact(key)),
+        },
+        escape(&rollup.model),
+        rollup.requests.to_string(),
+        rollup.successful_requests.to_string(),
+        rollup.prompt_tokens.to_string(),
+        rollup.completion_tokens.to_string(),
+        rollup.total_tokens.to_string(),
+        rollup.cost.to_string(),
+    ];
+    push_record(buffer, &fields);
+}
+
+fn push_record(buffer: &mut BytesMut, fields: &[String]) {
+    buffer.extend_from_slice(fields.join(",").as_bytes());
+    buffer.extend_from_slice(b"\n");
+}
+
+// 按 RFC 4180 转义：包含逗号、引号或换行的字段用双引号包裹，引号加倍；
+// 以 = + - @ 开头的字段前加单引号，避免在表格软件中被当作公式执行
+fn escape(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn plain_fields_are_unchanged() {
+        assert_eq!(escape("gpt-4o"), "gpt-4o");
+        assert_eq!(escape(""), "");
+    }
+
+    #[test]
+    fn separators_and_quotes_are_quoted() {
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("line1\nline2"), "\"line1\nline2\"");
+        assert_eq!(escape("line1\r\nline2"), "\"line1\r\nline2\"");
+    }
+
+    #[test]
+    fn formula_prefixes_are_neutralized() {
+        for value in ["=SUM(A1)", "+1", "-1", "@cmd"] {
+            assert_eq!(escape(value), format!("'{}", value));
+        }
+        assert_eq!(escape("=HYPERLINK(\"x\",\"y\")"), "\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\"");
+    }
+
+    #[test]
+    fn rollup_rows_mask_provider_key() {
+        let rollup = UsageRollup {
+            bucket_start: Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap(),
+            gateway_key_id: "gk_1".to_string(),
+            provider_api_key: "sk-abcdefghijklmnop".to_string(),
+            model: "gpt-4o".to_string(),
+            requests: 3,
+            successful_requests: 2,
+            prompt_tokens: 10,
+            completion_tokens: 20,
+            total_tokens: 30,
+            cost: 0.5,
+        };
+        let mut buffer = BytesMut::new();
+        write_rollup(&mut buffer, &rollup);
+        assert_eq!(&buffer[..], b"2026-10-01T00:00:00Z,gk_1,****mnop,gpt-4o,3,2,10,20,30,0.5\n");
+    }
+}
@@ -0,0 +1,231 @@
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::{debug, error, warn};
+
+use crate::config::UsageRecorderConfig;
+use crate::models::ApiUsage;
+use crate::services::db_metrics::DbMetrics;
+use crate::services::key_metrics::KeyUsageMetrics;
+
+enum UsageWrite {
+    Insert(Box<ApiUsage>),
+    SetCost { id: String, cost: f64 },
+    Flush(oneshot::Sender<()>),
+}
+
+/// 使用记录批量写入器
+/// 请求处理完成后把使用记录放入队列即可返回；后台任务在攒够一批或等待超过刷新间隔后，
+/// 把队列中的记录合并为多行INSERT在一个事务中写入，避免每个请求单独提交一次事务。
+/// 成本在写入前到达时直接合并到待写入的记录中，否则在该批记录写入后单独更新。
+/// 写入成功的记录同时计入按网关密钥统计的导出指标。
+/// 队列有容量上限，写满时按配置等待入队、丢弃记录或绕过队列直接写入
+pub struct UsageRecorder {
+    sender: mpsc::Sender<UsageWrite>,
+    overflow: OverflowPolicy,
+    db: SqlitePool,
+    db_metrics: Arc<DbMetrics>,
+    key_metrics: Arc<KeyUsageMetrics>,
+    // 队列已满时丢弃的使用记录数
+    dropped: AtomicU64,
+    // 队列已满时直接写入的使用记录数
+    inlined: AtomicU64,
+}
+
+/// 队列已满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OverflowPolicy {
+    /// 等待入队，请求处理被阻塞直到写入任务腾出空间
+    Block,
+    /// 丢弃使用记录并计数
+    Drop,
+    /// 绕过队列在当前请求中直接写入
+    Inline,
+}
+
+impl OverflowPolicy {
+    fn from_config(value: &str) -> Self {
+        match value {
+            "drop" => Self::Drop,
+            "inline" => Self::Inline,
+            _ => Self::Block,
+        }
+    }
+}
+
+impl UsageRecorder {
+    /// 创建写入器并启动后台写入任务
+    pub fn new(
+        db: SqlitePool,
+        db_metrics: Arc<DbMetrics>,
+        key_metrics: Arc<KeyUsageMetrics>,
+        config: UsageRecorderConfig,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity);
+        let overflow = OverflowPolicy::from_config(&config.overflow);
+        tokio::spawn(run(db.clone(), db_metrics.clone(), key_metrics.clone(), config, receiver));
+        Self {
+            sender,
+            overflow,
+            db,
+            db_metrics,
+            key_metrics,
+            dropped: AtomicU64::new(0),
+            inlined: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一次请求的使用情况
+    pub async fn record(&self, usage: ApiUsage) {
+        match self.sender.try_reserve() {
+            Ok(permit) => return permit.send(UsageWrite::Insert(Box::new(usage))),
+            Err(mpsc::error::TrySendError::Closed(())) => {
+                error!("使用记录写入任务已退出，丢弃使用记录");
+                return;
+            }
+            Err(mpsc::error::TrySendError::Full(())) => {}
+        }
+        match self.overflow {
+            OverflowPolicy::Block => {
+                if self.sender.send(UsageWrite::Insert(Box::new(usage))).await.is_err() {
+                    error!("使用记录写入任务已退出，丢弃使用记录");
+                }
+            }
+            OverflowPolicy::Drop => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                // 避免在持续过载时刷屏，每丢弃1000条提示一次
+                if dropped == 1 || dropped.is_multiple_of(1000) {
+                    warn!("使用记录队列已满，丢弃使用记录: usage_id={}, 累计丢弃={}", usage.id, dropped);
+                }
+            }
+            OverflowPolicy::Inline => {
+                self.inlined.fetch_add(1, Ordering::Relaxed);
+                let usages = [usage];
+                let insert = self.db_metrics.run("api_usage.insert_batch", || ApiUsage::insert_batch(&self.db, &usages));
+                match insert.await {
+                    Ok(()) => self.key_metrics.observe(&usages[0]),
+                    Err(e) => error!("直接写入使用记录失败: usage_id={}, 错误={}", usages[0].id, e),
+                }
+            }
+        }
+    }
+
+    /// 写入请求成本（对应的使用记录须已通过 record 提交）；成本总是等待入队，保证在记录写入之后更新
+    pub async fn set_cost(&self, id: &str, cost: f64) {
+        let write = UsageWrite::SetCost { id: id.to_string(), cost };
+        if self.sender.send(write).await.is_err() {
+            error!("使用记录写入任务已退出，丢弃请求成本: usage_id={}", id);
+        }
+    }
+
+    /// 以 Prometheus 文本格式导出队列长度和溢出计数
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let depth = self.sender.max_capacity() - self.sender.capacity();
+        for (name, kind, help, value) in [
+            ("gateway_usage_queue_depth", "gauge", "等待写入的使用记录队列长度", depth as u64),
+            ("gateway_usage_records_dropped_total", "counter", "队列已满时丢弃的使用记录数", self.dropped.load(Ordering::Relaxed)),
+            ("gateway_usage_records_inline_total", "counter", "队列已满时直接写入的使用记录数", self.inlined.load(Ordering::Relaxed)),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+
+    /// 立即写入队列中已有的记录，写入完成后返回
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.sender.send(UsageWrite::Flush(done)).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+#[derive(Default)]
+struct PendingBatch {
+    usages: Vec<ApiUsage>,
+    // 待写入记录的ID到下标，用于把成本合并进尚未写入的记录
+    index: HashMap<String, usize>,
+    costs: Vec<(String, f64)>,
+    flushed: Vec<oneshot::Sender<()>>,
+}
+
+impl PendingBatch {
+    fn push(&mut self, write: UsageWrite) {
+        match write {
+            UsageWrite::Insert(usage) => {
+                self.index.insert(usage.id.clone(), self.usages.len());
+                self.usages.push(*usage);
+            }
+            UsageWrite::SetCost { id, cost } => match self.index.get(&id) {
+                Some(&i) => self.usages[i].cost = Some(cost),
+                None => self.costs.push((id, cost)),
+            },
+            UsageWrite::Flush(done) => self.flushed.push(done),
+        }
+    }
+
+    fn is_full(&self, batch_size: usize) -> bool {
+        self.usages.len() >= batch_size || !self.flushed.is_empty()
+    }
+}
+
+async fn run(
+    db: SqlitePool,
+    db_metrics: Arc<DbMetrics>,
+    key_metrics: Arc<KeyUsageMetrics>,
+    config: UsageRecorderConfig,
+    mut receiver: mpsc::Receiver<UsageWrite>,
+) {
+    let flush_interval = Duration::from_millis(config.flush_interval_ms);
+    while let Some(first) = receiver.recv().await {
+        let mut batch = PendingBatch::default();
+        batch.push(first);
+
+        // 攒批：直到达到批量大小、收到立即写入请求或超过刷新间隔
+        let deadline = Instant::now() + flush_interval;
+        while !batch.is_full(config.batch_size) {
+            match timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(write)) => batch.push(write),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        write_batch(&db, &db_metrics, &key_metrics, batch).await;
+    }
+}
+
+async fn write_batch(db: &SqlitePool, db_metrics: &DbMetrics, key_metrics: &KeyUsageMetrics, batch: PendingBatch) {
+    if !batch.usages.is_empty() {
+        let count = batch.usages.len();
+        let insert = db_metrics.run("api_usage.insert_batch", || ApiUsage::insert_batch(db, &batch.usages));
+        match insert.await {
+            Ok(()) => {
+                debug!("批量写入使用记录: {} 条", count);
+                for usage in &batch.usages {
+                    key_metrics.observe(usage);
+                }
+            }
+            Err(e) => error!("批量写入使用记录失败: {} 条, 错误={}", count, e),
+        }
+    }
+
+    for (id, cost) in &batch.costs {
+        let update = db_metrics.run("api_usage.set_cost", || ApiUsage::set_cost(db, id, *cost));
+        match update.await {
+            Ok(Some(gateway_key_id)) => key_metrics.observe_cost(gateway_key_id.as_deref(), *cost),
+            Ok(None) => {}
+            Err(e) => error!("记录请求成本失败: usage_id={}, 错误={}", id, e),
+        }
+    }
+
+    for done in batch.flushed {
+        let _ = done.send(());
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use utoipa::ToSchema;
+
+use crate::models::api_usage::UsageFilter;
+use crate::services::usage_cost::{self, UsageCostReport};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 分享链接的访问路径
+pub const REPORT_PATH: &str = "/reports/usage";
+
+/// 报表的统计对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportSubject {
+    /// 单个网关密钥
+    GatewayKey(String),
+    /// 组织
+    Organization(String),
+}
+
+impl ReportSubject {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::GatewayKey(_) => "key",
+            Self::Organization(_) => "organization",
+        }
+    }
+
+    fn id(&self) -> &str {
+        match self {
+            Self::GatewayKey(id) | Self::Organization(id) => id,
+        }
+    }
+}
+
+/// 分享链接携带的参数（查询字符串）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReportParams {
+    /// 网关密钥ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// 组织
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// 起始日期（UTC，包含）
+    pub from: NaiveDate,
+    /// 结束日期（UTC，包含）
+    pub to: NaiveDate,
+    /// 过期时间（Unix时间戳，秒）
+    pub expires: i64,
+    /// 签名（十六进制）
+    pub sig: String,
+}
+
+impl SignedReportParams {
+    /// 统计对象：网关密钥和组织必须且只能设置一个
+    pub fn subject(&self) -> Option<ReportSubject> {
+        match (&self.key, &self.organization) {
+            (Some(key), None) => Some(ReportSubject::GatewayKey(key.clone())),
+            (None, Some(organization)) => Some(ReportSubject::Organization(organization.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// 分享出去的用量报表
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UsageReport {
+    /// 统计对象类型（key / organization）
+    pub subject_type: &'static str,
+    /// 网关密钥ID或组织
+    pub subject: String,
+    /// 起始日期（UTC，包含）
+    pub from: NaiveDate,
+    /// 结束日期（UTC，包含）
+    pub to: NaiveDate,
+    /// 链接过期时间
+    pub expires_at: DateTime<Utc>,
+    /// 报表生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 合计与按天的用量和成本
+    pub usage: UsageCostReport,
+}
+
+/// 生成签名的分享链接参数
+pub fn sign(secret: &str, subject: &ReportSubject, from: NaiveDate, to: NaiveDate, expires_at: DateTime<Utc>) -> SignedReportParams {
+    let expires = expires_at.timestamp();
+    let sig = to_hex(&mac(secret, subject, from, to, expires).finalize().into_bytes());
+    let (key, organization) = match subject {
+        ReportSubject::GatewayKey(id) => (Some(id.clone()), None),
+        ReportSubject::Organization(id) => (None, Some(id.clone())),
+    };
+    SignedReportParams { key, organization, from, to, expires, sig }
+}
+
+/// 拼接分享链接：配置了外部访问地址时返回完整URL，否则返回相对路径
+pub fn link(public_base_url: Option<&str>, params: &SignedReportParams, format: &str) -> String {
+    let query = serde_urlencoded::to_string(params).unwrap_or_default();
+    format!("{}{}?{}&format={}", public_base_url.unwrap_or(""), REPORT_PATH, query, format)
+}
+
+/// 校验链接的签名和有效期，通过时返回统计对象
+pub fn verify(secret: &str, params: &SignedReportParams, now: DateTime<Utc>) -> Result<ReportSubject, &'static str> {
+    let subject = params.subject().ok_or("链接参数无效")?;
+    let sig = from_hex(&params.sig).ok_or("链接签名无效")?;
+    mac(secret, &subject, params.from, params.to, params.expires)
+        .verify_slice(&sig)
+        .map_err(|_| "链接签名无效")?;
+    if params.expires <= now.timestamp() {
+        return Err("链接已过期");
+    }
+    Ok(subject)
+}
+
+/// 生成报表：按天统计 [from, to] 内的用量和成本
+pub async fn build(
+    db: &SqlitePool,
+    subject: &ReportSubject,
+    from: NaiveDate,
+    to: NaiveDate,
+    expires_at: DateTime<Utc>,
+) -> Result<UsageReport, sqlx::Error> {
+    let mut filter = UsageFilter {
+        from: Some(from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()),
+        to: Some((to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()),
+        ..Default::default()
+    };
+    match subject {
+        ReportSubject::GatewayKey(id) => filter.gateway_key_id = Some(id.clone()),
+        ReportSubject::Organization(id) => filter.organization = Some(id.clone()),
+    }
+    let usage = usage_cost::attribute(db, &filter, false, false, true).await?;
+    Ok(UsageReport {
+        subject_type: subject.kind(),
+        subject: subject.id().to_string(),
+        from,
+        to,
+        expires_at,
+        generated_at: Utc::now(),
+        usage,
+    })
+}
+
+// 签名内容：版本、对象类型、对象、日期范围和过期时间，以换行分隔
+fn mac(secret: &str, subject: &ReportSubject, from: NaiveDate, to: NaiveDate, expires: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC支持任意长度的密钥");
+    mac.update(format!("v1\n{}\n{}\n{}\n{}\n{}", subject.kind(), subject.id(), from, to, expires).as_bytes());
+    mac
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "report-secret";
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn signed(subject: &ReportSubject, expires_at: DateTime<Utc>) -> SignedReportParams {
+        sign(SECRET, subject, date("2026-10-01"), date("2026-10-07"), expires_at)
+    }
+
+    #[test]
+    fn signed_link_verifies_before_expiry() {
+        let now = Utc::now();
+        let subject = ReportSubject::Organization("acme".to_string());
+        let params = signed(&subject, now + Duration::hours(1));
+        assert_eq!(verify(SECRET, &params, now), Ok(subject));
+    }
+
+    #[test]
+    fn expired_link_is_rejected() {
+        let now = Utc::now();
+        let params = signed(&ReportSubject::GatewayKey("key-1".to_string()), now);
+        assert_eq!(verify(SECRET, &params, now), Err("链接已过期"));
+        assert!(verify(SECRET, &params, now - Duration::seconds(1)).is_ok());
+    }
+
+    #[test]
+    fn tampered_parameters_are_rejected() {
+        let now = Utc::now();
+        let params = signed(&ReportSubject::GatewayKey("key-1".to_string()), now + Duration::hours(1));
+
+        let mut other_key = params.clone();
+        other_key.key = Some("key-2".to_string());
+        assert_eq!(verify(SECRET, &other_key, now), Err("链接签名无效"));
+
+        let mut extended = params.clone();
+        extended.expires += 3600;
+        assert_eq!(verify(SECRET, &extended, now), Err("链接签名无效"));
+
+        let mut wider = params.clone();
+        wider.to = date("2026-10-31");
+        assert_eq!(verify(SECRET, &wider, now), Err("链接签名无效"));
+
+        assert_eq!(verify("other-secret", &params, now), Err("链接签名无效"));
+    }
+
+    #[test]
+    fn subject_kind_is_part_of_the_signature() {
+        let now = Utc::now();
+        let mut params = signed(&ReportSubject::GatewayKey("acme".to_string()), now + Duration::hours(1));
+        params.organization = params.key.take();
+        assert_eq!(verify(SECRET, &params, now), Err("链接签名无效"));
+
+        params.key = Some("acme".to_string());
+        assert_eq!(verify(SECRET, &params, now), Err("链接参数无效"));
+    }
+
+    #[test]
+    fn link_round_trips_through_query_string() {
+        let params = signed(&ReportSubject::Organization("a&b".to_string()), Utc::now() + Duration::hours(1));
+        let url = link(Some("https://gateway.example.com"), &params, "json");
+        let query = url.strip_prefix("https://gateway.example.com/reports/usage?").unwrap();
+        let parsed: SignedReportParams = serde_urlencoded::from_str(query.trim_end_matches("&format=json")).unwrap();
+        assert_eq!(parsed.organization.as_deref(), Some("a&b"));
+        assert_eq!(parsed.sig, params.sig);
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Duration, Utc};
+use futures_util::StreamExt;
+use sqlx::SqlitePool;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::config::{SchedulerConfig, UsageRetentionConfig};
+use crate::models::api_usage::{ApiUsage, UsageFilter};
+use crate::models::usage_rollup::{self, RollupGranularity};
+use crate::services::scheduler::{self, Scheduler};
+use crate::services::usage_export;
+
+/// 注册使用记录清理定时任务：删除（可选先归档）超过保留期的原始使用记录
+pub fn register(scheduler: &Scheduler, schedules: &SchedulerConfig, db: SqlitePool, config: UsageRetentionConfig) {
+    let default = if config.retention_days > 0 { "@daily" } else { "off" };
+    scheduler.register(
+        "usage_retention",
+        "清理超过保留期的使用记录",
+        scheduler::resolve(schedules, "usage_retention", default),
+        move || {
+            let (db, config) = (db.clone(), config.clone());
+            async move { run_once(&db, &config).await }
+        },
+    );
+}
+
+/// 清理请求时间早于保留期的使用记录；只清理已汇总进各粒度汇总表的部分，保证汇总统计不受影响
+pub async fn run_once(db: &SqlitePool, config: &UsageRetentionConfig) -> Result<(), String> {
+    if config.retention_days == 0 {
+        return Ok(());
+    }
+    let mut cutoff = Utc::now() - Duration::days(config.retention_days as i64);
+    for granularity in RollupGranularity::ALL {
+        match usage_rollup::rolled_up_to(db, granularity).await.map_err(|e| format!("查询汇总进度失败: {}", e))? {
+            Some(rolled_up_to) => cutoff = cutoff.min(rolled_up_to),
+            None => {
+                info!("{} 粒度尚未汇总，跳过使用记录清理", granularity.as_str());
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(dir) = &config.archive_dir {
+        archive(db, Path::new(dir), cutoff).await?;
+    }
+
+    let mut deleted = 0;
+    loop {
+        let rows = ApiUsage::delete_before(db, cutoff, config.batch_size as i64)
+            .await
+            .map_err(|e| format!("删除使用记录失败: {}", e))?;
+        deleted += rows;
+        if rows < config.batch_size {
+            break;
+        }
+        // 批次之间让出写锁，避免阻塞请求记录的写入
+        tokio::task::yield_now().await;
+    }
+    if deleted > 0 {
+        info!("已清理 {} 之前的使用记录 {} 条", cutoff, deleted);
+    }
+    Ok(())
+}
+
+// 把 cutoff 之前的使用记录写入归档目录下的CSV文件，写入完成后才会删除
+async fn archive(db: &SqlitePool, dir: &Path, cutoff: DateTime<Utc>) -> Result<(), String> {
+    let filter = UsageFilter { to: Some(cutoff), ..Default::default() };
+    if ApiUsage::search(db, &filter, 1, 0).await.map_err(|e| format!("查询待归档记录失败: {}", e))?.1 == 0 {
+        return Ok(());
+    }
+
+    tokio::fs::create_dir_all(dir).await.map_err(|e| format!("创建归档目录失败: {}", e))?;
+    let path = dir.join(format!("api_usage_until_{}.csv", cutoff.format("%Y%m%dT%H%M%SZ")));
+    let mut file = tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| format!("创建归档文件 {} 失败: {}", path.display(), e))?;
+    let mut chunks = Box::pin(usage_export::usage_csv(db.clone(), filter));
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| format!("读取待归档记录失败: {}", e))?;
+        file.write_all(&chunk).await.map_err(|e| format!("写入归档文件失败: {}", e))?;
+    }
+    file.sync_all().await.map_err(|e| format!("写入归档文件失败: {}", e))?;
+    info!("已归档 {} 之前的使用记录到 {}", cutoff, path.display());
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use tracing::info;
+
+use crate::config::{SchedulerConfig, UsageRollupConfig};
+use crate::models::api_usage::{ApiUsage, ApiUsageSummary, KeyUsageDay, UsageFilter};
+use crate::models::usage_rollup::{self, RollupGranularity};
+use crate::services::scheduler::{self, Scheduler};
+
+// 单次汇总的最大时间跨度，避免首次启动时一次性扫描全部历史记录
+const MAX_STEP_DAYS: i64 = 7;
+
+/// 注册汇总定时任务：把已结束（超过 settle_secs）的时间段汇总进 usage_rollups
+pub fn register(scheduler: &Scheduler, schedules: &SchedulerConfig, db: SqlitePool, config: UsageRollupConfig) {
+    let default = if config.enabled {
+        format!("@every {}s", config.interval_secs)
+    } else {
+        "off".to_string()
+    };
+    let settle = Duration::seconds(config.settle_secs as i64);
+    scheduler.register(
+        "usage_rollup",
+        "把使用记录汇总为按小时、按天的统计",
+        scheduler::resolve(schedules, "usage_rollup", &default),
+        move || {
+            let db = db.clone();
+            async move { run_once(&db, settle).await.map_err(|e| format!("汇总使用记录失败: {}", e)) }
+        },
+    );
+}
+
+/// 把各粒度的汇总推进到 now - settle 之前的最后一个完整时间段
+pub async fn run_once(db: &SqlitePool, settle: Duration) -> Result<(), sqlx::Error> {
+    let now = Utc::now() - settle;
+    for granularity in RollupGranularity::ALL {
+        let target = granularity.floor(now);
+        let mut start = match usage_rollup::rolled_up_to(db, granularity).await? {
+            Some(rolled_up_to) => rolled_up_to,
+            None => {
+                // 首次汇总从最早的使用记录开始；还没有记录时直接从当前时间段开始
+                let start = usage_rollup::earliest_usage(db)
+                    .await?
+                    .map(|earliest| granularity.floor(earliest).min(target))
+                    .unwrap_or(target);
+                usage_rollup::roll_up(db, granularity, start, start).await?;
+                start
+            }
+        };
+        while start < target {
+            let end = (start + Duration::days(MAX_STEP_DAYS)).min(target);
+            let rows = usage_rollup::roll_up(db, granularity, start, end).await?;
+            info!("已汇总 {} 粒度使用记录 [{}, {})，写入 {} 行", granularity.as_str(), start, end, rows);
+            start = end;
+        }
+    }
+    Ok(())
+}
+
+/// 统计 [from, to) 内的用量：已汇总的整点时间段读取小时汇总表，其余部分读取原始记录
+pub async fn summarize(
+    db: &SqlitePool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    by_provider: bool,
+    by_model: bool,
+) -> Result<ApiUsageSummary, sqlx::Error> {
+    let hour = RollupGranularity::Hour;
+    let raw = |from, to| {
+        let filter = UsageFilter { from, to, ..Default::default() };
+        async move { ApiUsageSummary::summarize(db, &filter, by_provider, by_model).await }
+    };
+
+    let Some(rolled_up_to) = usage_rollup::rolled_up_to(db, hour).await? else {
+        return raw(from, to).await;
+    };
+    // 汇总表覆盖的区间 [rollup_from, rollup_to)
+    let rollup_from = from.map(|from| hour.ceil(from));
+    let rollup_to = to.map(|to| hour.floor(to)).map_or(rolled_up_to, |to| to.min(rolled_up_to));
+    if rollup_from.is_some_and(|rollup_from| rollup_from >= rollup_to) {
+        return raw(from, to).await;
+    }
+
+    let mut summary = usage_rollup::summarize(db, rollup_from, rollup_to, by_provider, by_model).await?;
+    if let (Some(from), Some(rollup_from)) = (from, rollup_from) {
+        if from < rollup_from {
+            summary = summary.merge(raw(Some(from), Some(rollup_from)).await?);
+        }
+    }
+    if to.is_none_or(|to| rollup_to < to) {
+        summary = summary.merge(raw(Some(rollup_to), to).await?);
+    }
+    Ok(summary)
+}
+
+/// 按天统计网关密钥在 [from, to) 内的用量（from、to 须对齐到UTC零点）：已汇总的天读取天汇总表，其余读取原始记录
+pub async fn daily_for_gateway_key(
+    db: &SqlitePool,
+    key_id: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<KeyUsageDay>, sqlx::Error> {
+    let rolled_up_to = usage_rollup::rolled_up_to(db, RollupGranularity::Day)
+        .await?
+        .map_or(from, |rolled_up_to| rolled_up_to.clamp(from, to));
+
+    let mut days = if from < rolled_up_to {
+        usage_rollup::daily_for_gateway_key(db, key_id, from, rolled_up_to).await?
+    } else {
+        Vec::new()
+    };
+    if rolled_up_to < to {
+        days.extend(ApiUsage::daily_for_gateway_key(db, key_id, rolled_up_to, to).await?);
+    }
+    Ok(days)
+}
@@ -0,0 +1,33 @@
+// 跨模块的集成检查，单个模块的单元测试放在模块自身的 tests 子模块中
+
+use utoipa::OpenApi;
+
+use crate::routes::api::ApiDoc;
+
+// 接口文档能完整生成，且注册的路径和结构体都能互相解析
+#[test]
+fn openapi_document_resolves_all_schemas() {
+    let doc = ApiDoc::openapi();
+    let json = serde_json::to_value(&doc).expect("接口文档应能序列化");
+
+    let schemas = json["components"]["schemas"].as_object().expect("接口文档缺少 schemas");
+    let text = json.to_string();
+    let mut missing: Vec<&str> = text
+        .split("\"$ref\":\"#/components/schemas/")
+        .skip(1)
+        .map(|reference| &reference[..reference.find('"').unwrap()])
+        .filter(|name| !schemas.contains_key(*name))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+    assert!(missing.is_empty(), "接口文档引用了未注册的结构体: {:?}", missing);
+}
+
+// 管理接口、推理接口和模型目录接口都出现在接口文档中
+#[test]
+fn openapi_document_lists_core_paths() {
+    let doc = ApiDoc::openapi();
+    for path in ["/v1/chat/completions", "/v1/providers", "/v1/keys", "/v1/users/login", "/v1/models/{name}/metadata"] {
+        assert!(doc.paths.paths.contains_key(path), "接口文档缺少路径: {}", path);
+    }
+}
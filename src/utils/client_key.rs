@@ -0,0 +1,49 @@
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+
+/// 从 Authorization 头读取客户端密钥（Bearer token）
+pub fn client_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_start_matches("Bearer ").trim())
+        .filter(|key| !key.is_empty())
+        .map(|key| key.to_string())
+}
+
+/// 客户端自带上游密钥（BYOK）的请求头
+pub const UPSTREAM_KEY_HEADER: &str = "x-upstream-api-key";
+/// 自带密钥时可选的提供商类型请求头（如 OpenAI、OpenRouter）
+pub const UPSTREAM_PROVIDER_HEADER: &str = "x-upstream-provider";
+
+/// 客户端自带的上游密钥
+#[derive(Debug, Clone)]
+pub struct UpstreamKey {
+    /// 上游密钥明文，仅用于请求上游，不写入日志和数据库
+    pub api_key: String,
+    /// 限定的提供商类型，未指定时使用任一支持该模型的提供商
+    pub provider_type: Option<String>,
+}
+
+/// 读取客户端自带的上游密钥；未启用 BYOK 模式却携带了密钥时返回错误
+pub fn upstream_key(headers: &HeaderMap, enabled: bool) -> Result<Option<UpstreamKey>, String> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+
+    let api_key = match header(UPSTREAM_KEY_HEADER) {
+        Some(api_key) => api_key,
+        None => return Ok(None),
+    };
+    if !enabled {
+        return Err("未启用自带上游密钥模式".to_string());
+    }
+    Ok(Some(UpstreamKey {
+        api_key,
+        provider_type: header(UPSTREAM_PROVIDER_HEADER),
+    }))
+}
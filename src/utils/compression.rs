@@ -0,0 +1,34 @@
+use std::io::Write;
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+
+/// 序列化后的请求体
+pub struct EncodedBody {
+    /// 请求体字节
+    pub bytes: Vec<u8>,
+    /// Content-Encoding（未压缩时为None）
+    pub content_encoding: Option<&'static str>,
+}
+
+/// 将请求序列化为JSON，允许压缩且超过阈值时使用gzip压缩
+pub fn encode_json_body<T: Serialize>(
+    value: &T,
+    allow_gzip: bool,
+    gzip_threshold_bytes: usize,
+) -> Result<EncodedBody, String> {
+    let json = serde_json::to_vec(value).map_err(|e| format!("序列化请求失败: {}", e))?;
+
+    if !allow_gzip || json.len() < gzip_threshold_bytes {
+        return Ok(EncodedBody { bytes: json, content_encoding: None });
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder
+        .write_all(&json)
+        .map_err(|e| format!("压缩请求失败: {}", e))?;
+    let compressed = encoder.finish().map_err(|e| format!("压缩请求失败: {}", e))?;
+
+    tracing::debug!("请求体已gzip压缩: {} -> {} 字节", json.len(), compressed.len());
+
+    Ok(EncodedBody { bytes: compressed, content_encoding: Some("gzip") })
+}
@@ -0,0 +1,38 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+};
+
+use crate::services::list_cache::CachedList;
+
+/// 请求的 If-None-Match 是否与 ETag 匹配（支持多个值、弱校验前缀和 *）
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// 返回缓存的JSON列表；客户端已持有相同版本时返回304
+pub fn cached_json_response(list: &CachedList, headers: &HeaderMap) -> Response {
+    let builder = Response::builder()
+        .header(header::ETAG, &list.etag)
+        .header(header::CACHE_CONTROL, "no-cache");
+
+    if if_none_match(headers, &list.etag) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    builder
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(list.body.clone()))
+        .unwrap()
+}
@@ -0,0 +1,28 @@
+use axum::http::HeaderMap;
+
+use crate::config::LOAD_BALANCE_STRATEGIES;
+
+/// 客户端为本次请求指定负载均衡策略的请求头
+pub const LB_STRATEGY_HEADER: &str = "x-lb-strategy";
+
+/// 本次请求使用的负载均衡策略：请求头优先，其次网关密钥的默认策略，最后使用 LOAD_BALANCE_STRATEGY；
+/// 请求头中的策略不受支持时返回错误
+pub fn request_strategy(headers: &HeaderMap, key_default: Option<&str>, default: &str) -> Result<String, String> {
+    let requested = headers
+        .get(LB_STRATEGY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    match requested {
+        Some(strategy) => LOAD_BALANCE_STRATEGIES
+            .iter()
+            .find(|supported| supported.eq_ignore_ascii_case(strategy))
+            .map(|supported| supported.to_string())
+            .ok_or_else(|| format!(
+                "不支持的负载均衡策略: {}，可选: {}",
+                strategy,
+                LOAD_BALANCE_STRATEGIES.join("、")
+            )),
+        None => Ok(key_default.unwrap_or(default).to_string()),
+    }
+}
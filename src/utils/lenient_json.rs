@@ -0,0 +1,136 @@
+use serde::de::DeserializeOwned;
+
+/// 宽松解析的结果
+pub struct LenientParse<T> {
+    /// 解析出的值
+    pub value: T,
+    /// 解析前做过的修复（bom、leading_garbage、trailing_commas、trailing_garbage）
+    pub repairs: Vec<&'static str>,
+}
+
+/// 宽松解析上游返回的JSON：去除BOM、开头和结尾的无关内容以及多余的尾随逗号
+pub fn parse_lenient<T: DeserializeOwned>(text: &str) -> Result<LenientParse<T>, serde_json::Error> {
+    let mut repairs = Vec::new();
+
+    let mut text = text;
+    if let Some(stripped) = text.strip_prefix('\u{feff}') {
+        text = stripped;
+        repairs.push("bom");
+    }
+
+    if let Some(start) = text.find(['{', '[']) {
+        if !text[..start].trim().is_empty() {
+            repairs.push("leading_garbage");
+        }
+        text = &text[start..];
+    }
+
+    let cleaned = remove_trailing_commas(text);
+    if cleaned.len() != text.len() {
+        repairs.push("trailing_commas");
+    }
+
+    // 只解析第一个完整的值，忽略其后的无关内容
+    let mut values = serde_json::Deserializer::from_str(&cleaned).into_iter::<T>();
+    let value = match values.next() {
+        Some(value) => value?,
+        None => return serde_json::from_str(&cleaned).map(|value| LenientParse { value, repairs }),
+    };
+    if !cleaned[values.byte_offset()..].trim().is_empty() {
+        repairs.push("trailing_garbage");
+    }
+
+    Ok(LenientParse { value, repairs })
+}
+
+// 删除值之后、紧跟在 } 或 ] 之前的逗号（忽略字符串内部）；{,} 这类没有前置值的逗号保留，由解析报错
+fn remove_trailing_commas(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let previous = result.chars().rev().find(|c| !c.is_whitespace());
+            let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) && !matches!(previous, Some('{') | Some('[') | Some(',') | None) {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn repairs_malformed_responses() {
+        let cases: &[(&str, Value, &[&str])] = &[
+            (r#"{"a": 1}"#, json!({ "a": 1 }), &[]),
+            ("\u{feff}{\"a\": 1}", json!({ "a": 1 }), &["bom"]),
+            (r#"{"a": [1, 2,], "b": {"c": 3,},}"#, json!({ "a": [1, 2], "b": { "c": 3 } }), &["trailing_commas"]),
+            (r#"{"a": [1,
+                ]}"#, json!({ "a": [1] }), &["trailing_commas"]),
+            (r#"{"a": "x,}"}"#, json!({ "a": "x,}" }), &[]),
+            (r#"{"a": "say \",]\""}"#, json!({ "a": "say \",]\"" }), &[]),
+            ("```json\n{\"a\": 1}\n```", json!({ "a": 1 }), &["leading_garbage", "trailing_garbage"]),
+            ("```\n[1, 2,]\n```\n", json!([1, 2]), &["leading_garbage", "trailing_commas", "trailing_garbage"]),
+            ("data: {\"a\": 1}\n\ndata: [DONE]", json!({ "a": 1 }), &["leading_garbage", "trailing_garbage"]),
+            ("  {\"a\": 1}  ", json!({ "a": 1 }), &[]),
+        ];
+
+        for (input, expected, repairs) in cases {
+            let parsed = parse_lenient::<Value>(input).unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            assert_eq!(&parsed.value, expected, "{:?}", input);
+            assert_eq!(parsed.repairs, *repairs, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_unrecoverable_input() {
+        let cases = [
+            "",
+            "   ",
+            "not json at all",
+            r#"{"a": 1"#,
+            r#"{"a": [1, 2"#,
+            r#"{"a": "unterminated}"#,
+            "```json\n{\"a\":\n```",
+            r#"{"a" 1}"#,
+            r#"{,}"#,
+            r#"[1,,]"#,
+        ];
+
+        for input in cases {
+            assert!(parse_lenient::<Value>(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn reports_type_mismatch() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Completion {
+            id: String,
+        }
+
+        assert!(parse_lenient::<Completion>(r#"{"id": 1}"#).is_err());
+        assert_eq!(parse_lenient::<Completion>(r#"{"id": "x",}"#).unwrap().value.id, "x");
+    }
+}
@@ -0,0 +1,9 @@
+pub mod compression;
+pub mod client_key;
+pub mod response_limit;
+pub mod sigv4;
+pub mod lenient_json;
+pub mod etag;
+pub mod redact;
+pub mod payload_log;
+pub mod lb_strategy;
@@ -0,0 +1,26 @@
+use crate::config::PayloadLoggingConfig;
+
+/// 按 LOG_PAYLOADS 配置得到要写入日志的请求/响应内容；不记录时返回空，且不会生成内容
+pub fn payload<F: FnOnce() -> String>(config: &PayloadLoggingConfig, render: F) -> Option<String> {
+    match config.mode.as_str() {
+        "full" => Some(render()),
+        "truncated" => {
+            let text = render();
+            let total = text.chars().count();
+            if total <= config.max_chars {
+                return Some(text);
+            }
+            let truncated: String = text.chars().take(config.max_chars).collect();
+            Some(format!("{}...（已截断，共 {} 个字符）", truncated, total))
+        }
+        _ => None,
+    }
+}
+
+/// 流式响应数据块的日志内容：需开启 LOG_STREAM_CHUNKS 且日志级别包含 trace，否则返回空且不会生成内容
+pub fn stream_chunk<F: FnOnce() -> String>(config: &PayloadLoggingConfig, render: F) -> Option<String> {
+    if !config.stream_chunks || !tracing::enabled!(tracing::Level::TRACE) {
+        return None;
+    }
+    payload(config, render)
+}
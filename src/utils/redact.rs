@@ -0,0 +1,10 @@
+/// 日志中脱敏显示密钥：只保留最后4个字符，其余用 * 代替；
+/// 不超过8个字符的短密钥整个隐藏，避免泄露大部分内容
+pub fn redact(secret: &str) -> String {
+    let len = secret.chars().count();
+    if len <= 8 {
+        return "****".to_string();
+    }
+    let tail: String = secret.chars().skip(len - 4).collect();
+    format!("****{}", tail)
+}
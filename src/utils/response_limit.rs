@@ -0,0 +1,52 @@
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+
+use crate::services::sse_error_event;
+
+/// 读取上游响应体，超过 max_bytes 时立即停止读取并返回截断错误
+pub async fn read_limited_body(response: reqwest::Response, max_bytes: usize) -> Result<Bytes, String> {
+    if let Some(length) = response.content_length() {
+        if length > max_bytes as u64 {
+            return Err(too_large_message(max_bytes));
+        }
+    }
+
+    let mut body = BytesMut::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("读取响应失败: {}", e))?;
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large_message(max_bytes));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// 流式响应累计大小计数器
+pub struct StreamSizeLimit {
+    received: usize,
+    max_bytes: usize,
+}
+
+impl StreamSizeLimit {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { received: 0, max_bytes }
+    }
+
+    /// 累计收到的数据块大小，超过上限时返回false
+    pub fn accept(&mut self, chunk_len: usize) -> bool {
+        self.received += chunk_len;
+        self.received <= self.max_bytes
+    }
+
+    /// 超过上限时发送给客户端的SSE错误事件
+    pub fn truncation_event(&self) -> Bytes {
+        sse_error_event(&format!("上游流式响应超过大小限制({} 字节)，已截断", self.max_bytes))
+    }
+}
+
+fn too_large_message(max_bytes: usize) -> String {
+    format!("上游响应超过大小限制({} 字节)，已截断", max_bytes)
+}
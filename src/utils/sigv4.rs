@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// AWS访问凭证
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// 从提供商的api_key解析凭证，格式为 `ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]`
+    pub fn parse(api_key: &str) -> Result<Self, String> {
+        let mut parts = api_key.splitn(3, ':');
+        match (parts.next(), parts.next()) {
+            (Some(id), Some(secret)) if !id.is_empty() && !secret.is_empty() => Ok(Self {
+                access_key_id: id.to_string(),
+                secret_access_key: secret.to_string(),
+                session_token: parts.next().filter(|t| !t.is_empty()).map(|t| t.to_string()),
+            }),
+            _ => Err("AWS凭证格式错误，应为 ACCESS_KEY_ID:SECRET_ACCESS_KEY[:SESSION_TOKEN]".to_string()),
+        }
+    }
+}
+
+/// 按SigV4规范对请求签名，返回需要附加到请求上的请求头
+pub fn sign_request(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    url: &reqwest::Url,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    // 规范请求头需按名称排序
+    let mut headers = vec![("host", host), ("x-amz-date", amz_date.clone())];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = canonical_request(method, url, &headers, body);
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = string_to_sign(&amz_date, &scope, &canonical_request);
+
+    let signing_key = signing_key(&credentials.secret_access_key, &date, region, service);
+    let signature = to_hex(&hmac(&signing_key, string_to_sign.as_bytes()));
+
+    let mut result = vec![
+        ("x-amz-date", amz_date),
+        (
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                credentials.access_key_id, scope, signed_headers, signature
+            ),
+        ),
+    ];
+    if let Some(token) = &credentials.session_token {
+        result.push(("x-amz-security-token", token.clone()));
+    }
+    result
+}
+
+// 规范请求，headers 须已按名称排序
+fn canonical_request(method: &str, url: &reqwest::Url, headers: &[(&str, String)], body: &[u8]) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+
+    // 非S3服务的规范URI需要对路径的每一段再编码一次
+    let canonical_uri = url
+        .path()
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut query = url
+        .query_pairs()
+        .map(|(k, v)| format!("{}={}", uri_encode(&k), uri_encode(&v)))
+        .collect::<Vec<_>>();
+    query.sort();
+
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri,
+        query.join("&"),
+        canonical_headers,
+        signed_headers,
+        to_hex(&Sha256::digest(body)),
+    )
+}
+
+fn string_to_sign(amz_date: &str, scope: &str, canonical_request: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        to_hex(&Sha256::digest(canonical_request.as_bytes())),
+    )
+}
+
+/// 按SigV4规则进行URI编码（仅保留非保留字符）
+pub fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let k_region = hmac(&k_date, region.as_bytes());
+    let k_service = hmac(&k_region, service.as_bytes());
+    hmac(&k_service, b"aws4_request")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC支持任意长度的密钥");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // AWS SigV4 测试套件（aws-sig-v4-test-suite）使用的凭证和时间
+    fn credentials() -> AwsCredentials {
+        AwsCredentials::parse("AKIDEXAMPLE:wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY").unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap()
+    }
+
+    fn headers() -> Vec<(&'static str, String)> {
+        vec![("host", "example.amazonaws.com".to_string()), ("x-amz-date", "20150830T123600Z".to_string())]
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let url = reqwest::Url::parse("https://example.amazonaws.com/").unwrap();
+
+        let canonical = canonical_request("GET", &url, &headers(), b"");
+        assert_eq!(
+            canonical,
+            "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n\
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            string_to_sign("20150830T123600Z", "20150830/us-east-1/service/aws4_request", &canonical),
+            "AWS4-HMAC-SHA256\n20150830T123600Z\n20150830/us-east-1/service/aws4_request\n\
+             bb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63"
+        );
+
+        let signed = sign_request(&credentials(), "us-east-1", "service", "GET", &url, b"", now());
+        assert_eq!(signed[0], ("x-amz-date", "20150830T123600Z".to_string()));
+        assert_eq!(
+            signed[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key_case() {
+        let url = reqwest::Url::parse("https://example.amazonaws.com/?Param2=value2&Param1=value1").unwrap();
+
+        let canonical = canonical_request("GET", &url, &headers(), b"");
+        assert_eq!(canonical.lines().nth(2), Some("Param1=value1&Param2=value2"));
+
+        let signed = sign_request(&credentials(), "us-east-1", "service", "GET", &url, b"", now());
+        assert!(signed[1].1.ends_with("Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"));
+    }
+
+    #[test]
+    fn derives_documented_signing_key() {
+        // AWS 文档中派生签名密钥的示例
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(to_hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn session_token_is_signed() {
+        let credentials = AwsCredentials::parse("AKIDEXAMPLE:secret:token").unwrap();
+        let url = reqwest::Url::parse("https://example.amazonaws.com/").unwrap();
+        let signed = sign_request(&credentials, "us-east-1", "service", "GET", &url, b"", now());
+        assert!(signed[1].1.contains("SignedHeaders=host;x-amz-date;x-amz-security-token,"));
+        assert_eq!(signed[2], ("x-amz-security-token", "token".to_string()));
+    }
+
+    #[test]
+    fn encodes_path_segments_and_rejects_malformed_credentials() {
+        assert_eq!(uri_encode("model:0"), "model%3A0");
+        assert_eq!(uri_encode("a b~"), "a%20b~");
+        assert!(AwsCredentials::parse("missing-secret").is_err());
+        assert!(AwsCredentials::parse(":secret").is_err());
+    }
+}